@@ -0,0 +1,123 @@
+//! Benchmarks for looking up addresses in a parsed symbol file.
+//!
+//! By default this uses a generated symbol file with a couple hundred thousand
+//! functions. Set `BREAKPAD_SYMBOLS_BENCH_FILE` to the path of a real symbol
+//! file (the bigger the better, `xul.sym` is a good one) to use that instead.
+
+use breakpad_symbols::{FrameWalker, SimpleFrame, SimpleModule, SymbolFile};
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use std::fmt::Write;
+
+const FUNCTIONS: u64 = 200_000;
+const LOOKUPS: u64 = 1000;
+
+fn generated_symbols() -> SymbolFile {
+    let mut text = String::from(
+        "MODULE Linux x86_64 D3096ED481217FD4C16B29CD9BC208BA0 bench\n\
+         FILE 0 bench.c\n\
+         INLINE_ORIGIN 0 inlined\n",
+    );
+    for i in 0..FUNCTIONS {
+        let address = i * 0x100;
+        writeln!(text, "FUNC {:x} 80 0 func{}", address, i).unwrap();
+        writeln!(text, "INLINE 0 10 0 0 {:x} 20", address + 0x20).unwrap();
+        for line in 0..4 {
+            writeln!(text, "{:x} 20 {} 0", address + line * 0x20, line + 1).unwrap();
+        }
+        writeln!(text, "PUBLIC {:x} 0 public{}", address + 0x80, i).unwrap();
+        writeln!(
+            text,
+            "STACK CFI INIT {:x} 80 .cfa: $rsp 8 + .ra: .cfa -8 + ^",
+            address
+        )
+        .unwrap();
+        writeln!(text, "STACK CFI {:x} .cfa: $rsp 16 +", address + 4).unwrap();
+    }
+    SymbolFile::from_bytes(text.as_bytes()).unwrap()
+}
+
+fn symbols() -> SymbolFile {
+    match std::env::var_os("BREAKPAD_SYMBOLS_BENCH_FILE") {
+        Some(path) => SymbolFile::from_file(path.as_ref()).unwrap(),
+        None => generated_symbols(),
+    }
+}
+
+/// Addresses spread over the whole module, which land in functions, in
+/// PUBLICs between them, and in the gaps.
+fn addresses(symbols: &SymbolFile) -> Vec<u64> {
+    let end = symbols
+        .functions
+        .ranges_values()
+        .last()
+        .map_or(0x1000, |(range, _)| range.end);
+    (0..LOOKUPS)
+        .map(|i| i * 7919 % LOOKUPS * end / LOOKUPS + i % 0x100)
+        .collect()
+}
+
+/// A walker that has a value for every register and memory address.
+struct Walker {
+    instruction: u64,
+}
+
+impl FrameWalker for Walker {
+    fn get_instruction(&self) -> u64 {
+        self.instruction
+    }
+    fn get_grand_callee_parameter_size(&self) -> u32 {
+        0
+    }
+    fn get_register_at_address(&self, _address: u64) -> Option<u64> {
+        Some(0x1000)
+    }
+    fn get_callee_register(&self, _name: &str) -> Option<u64> {
+        Some(0x2000)
+    }
+    fn set_caller_register(&mut self, _name: &str, _val: u64) -> Option<()> {
+        Some(())
+    }
+    fn clear_caller_register(&mut self, _name: &str) {}
+    fn set_cfa(&mut self, _val: u64) -> Option<()> {
+        Some(())
+    }
+    fn set_ra(&mut self, _val: u64) -> Option<()> {
+        Some(())
+    }
+}
+
+fn lookups(c: &mut Criterion) {
+    let symbols = symbols();
+    let addresses = addresses(&symbols);
+    let module = SimpleModule::default();
+
+    c.bench_function("fill_symbol", |b| {
+        b.iter(|| {
+            for &address in &addresses {
+                let mut frame = SimpleFrame::with_instruction(address);
+                symbols.fill_symbol(&module, &mut frame);
+                black_box(frame);
+            }
+        })
+    });
+    c.bench_function("walk_frame", |b| {
+        b.iter(|| {
+            for &address in &addresses {
+                let mut walker = Walker {
+                    instruction: address,
+                };
+                black_box(symbols.walk_frame(&module, &mut walker));
+            }
+        })
+    });
+    c.bench_function("find_nearest_public", |b| {
+        b.iter(|| {
+            for &address in &addresses {
+                black_box(symbols.find_nearest_public(address));
+            }
+        })
+    });
+}
+
+criterion_group!(benches, lookups);
+criterion_main!(benches);
@@ -0,0 +1,272 @@
+//! Getting symbols out of a zip or tar archive.
+//!
+//! Symbols are often shipped as a single archive per build, holding symbol
+//! files in the usual layout (see [`relative_symbol_path`]), possibly under
+//! some top-level directory. Rather than extracting it, the archive is
+//! indexed once when it's opened, and only the symbol files that are needed
+//! are read out of it.
+//!
+//! Zip and tar archives can be read out of order, but gzipped tar archives
+//! can't, so those are decompressed to a temporary file first.
+
+use async_trait::async_trait;
+use flate2::read::GzDecoder;
+use reqwest::{Client, Url};
+use tempfile::NamedTempFile;
+use tracing::debug;
+use zip::ZipArchive;
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::convert::download;
+use crate::retry::Retrier;
+use crate::{relative_symbol_path, Module, SymbolError, SymbolFile, SymbolSupplier};
+
+/// An archive, and where each symbol file is in it, by the last three
+/// components of its path (see [`index_key`]).
+enum Archive {
+    /// Files in a zip archive are found by their full name.
+    Zip(Mutex<ZipArchive<File>>, HashMap<String, String>),
+    /// Files in a tar archive are found by their offset and size.
+    Tar(Mutex<File>, HashMap<String, (u64, u64)>),
+}
+
+/// An implementation of `SymbolSupplier` that reads symbols from a zip, tar,
+/// or gzipped tar archive.
+pub struct ArchiveSymbolSupplier {
+    archive: Archive,
+    /// Where the archive is on disk, if it was opened from there. This is
+    /// the `path` of all the symbols found in it.
+    path: Option<PathBuf>,
+    /// Where the archive was downloaded from, if it was. This is the `url` of
+    /// all the symbols found in it.
+    url: Option<String>,
+    /// A downloaded archive, which goes away with the supplier.
+    _temp: Option<NamedTempFile>,
+}
+
+impl ArchiveSymbolSupplier {
+    /// Open the archive at `path`.
+    pub fn open(path: &Path) -> Result<ArchiveSymbolSupplier, SymbolError> {
+        Ok(ArchiveSymbolSupplier {
+            archive: open_archive(File::open(path)?)?,
+            path: Some(path.to_path_buf()),
+            url: None,
+            _temp: None,
+        })
+    }
+
+    /// Download the archive at `url` to a temporary file in `tmp`, and open
+    /// it.
+    ///
+    /// The archive is only downloaded once, so this is best for archives
+    /// that are used to process many minidumps.
+    pub async fn fetch(
+        url: &str,
+        tmp: &Path,
+        timeout: Duration,
+    ) -> Result<ArchiveSymbolSupplier, SymbolError> {
+        let url = Url::parse(url).map_err(|_| SymbolError::NotFound)?;
+        let client = Client::builder().timeout(timeout).build().unwrap();
+        let temp = download(&client, &Retrier::default(), &url, tmp).await?;
+        Ok(ArchiveSymbolSupplier {
+            archive: open_archive(temp.reopen()?)?,
+            path: None,
+            url: Some(url.to_string()),
+            _temp: Some(temp),
+        })
+    }
+
+    /// Read and parse the symbol file that has `key` in our index.
+    fn load(&self, key: &str) -> Result<SymbolFile, SymbolError> {
+        let start = Instant::now();
+        let (file_size, mut symbols) = match &self.archive {
+            Archive::Zip(zip, index) => {
+                let name = index.get(key).ok_or(SymbolError::NotFound)?;
+                let mut zip = zip.lock().unwrap();
+                let file = zip.by_name(name).map_err(io::Error::from)?;
+                (file.size(), SymbolFile::parse(file, |_| ())?)
+            }
+            Archive::Tar(tar, index) => {
+                let (offset, size) = *index.get(key).ok_or(SymbolError::NotFound)?;
+                let mut tar = tar.lock().unwrap();
+                tar.seek(SeekFrom::Start(offset))?;
+                (size, SymbolFile::parse((&mut *tar).take(size), |_| ())?)
+            }
+        };
+        debug!("Loaded {} from archive", key);
+        symbols.path = self.path.clone();
+        symbols.url = self.url.clone();
+        symbols.file_size = Some(file_size);
+        symbols.parse_duration = Some(start.elapsed());
+        Ok(symbols)
+    }
+}
+
+/// Index the zip, tar or gzipped tar archive in `file`.
+fn open_archive(mut file: File) -> Result<Archive, SymbolError> {
+    let mut magic = [0; 2];
+    let len = file.read(&mut magic)?;
+    file.seek(SeekFrom::Start(0))?;
+    match &magic[..len] {
+        b"PK" => {
+            let zip = ZipArchive::new(file).map_err(io::Error::from)?;
+            let index = zip
+                .file_names()
+                .filter(|name| !name.ends_with('/'))
+                .filter_map(|name| Some((index_key(name)?, name.to_string())))
+                .collect();
+            Ok(Archive::Zip(Mutex::new(zip), index))
+        }
+        [0x1f, 0x8b] => {
+            let mut tar = tempfile::tempfile()?;
+            io::copy(&mut GzDecoder::new(file), &mut tar)?;
+            tar.seek(SeekFrom::Start(0))?;
+            open_tar(tar)
+        }
+        _ => open_tar(file),
+    }
+}
+
+/// Index the tar archive in `file`.
+fn open_tar(file: File) -> Result<Archive, SymbolError> {
+    let mut tar = tar::Archive::new(file);
+    let mut index = HashMap::new();
+    for entry in tar.entries_with_seek()? {
+        let entry = entry?;
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+        if let Some(key) = index_key(&entry.path()?.to_string_lossy()) {
+            index.insert(key, (entry.raw_file_position(), entry.size()));
+        }
+    }
+    Ok(Archive::Tar(Mutex::new(tar.into_inner()), index))
+}
+
+/// The last three components of `path`, which are how symbol files are found
+/// in an archive, whatever directory they're in.
+fn index_key(path: &str) -> Option<String> {
+    let components: Vec<_> = path
+        .split(|c| c == '/' || c == '\\')
+        .filter(|c| !c.is_empty() && *c != ".")
+        .collect();
+    let first = components.len().checked_sub(3)?;
+    Some(components[first..].join("/"))
+}
+
+#[async_trait]
+impl SymbolSupplier for ArchiveSymbolSupplier {
+    async fn locate_symbols(
+        &self,
+        module: &(dyn Module + Sync),
+    ) -> Result<SymbolFile, SymbolError> {
+        let rel_path = relative_symbol_path(module, "sym").ok_or(SymbolError::NotFound)?;
+        // Symbol files may also be stored compressed.
+        match self.load(&rel_path) {
+            Err(SymbolError::NotFound) => self.load(&format!("{}.gz", rel_path)),
+            result => result,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::SimpleModule;
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use std::io::Write;
+
+    const SYMBOLS: &[u8] = b"MODULE Linux x86 abcd1234 foo
+FILE 1 foo.c
+FUNC 1000 30 10 some func
+1000 30 100 1
+";
+
+    fn gzipped(data: &[u8]) -> Vec<u8> {
+        let mut encoder = GzEncoder::new(vec![], Compression::default());
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    fn tar(files: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut builder = tar::Builder::new(vec![]);
+        for (name, data) in files {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(data.len() as u64);
+            header.set_cksum();
+            builder.append_data(&mut header, name, *data).unwrap();
+        }
+        builder.into_inner().unwrap()
+    }
+
+    fn zip(files: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut writer = zip::ZipWriter::new(io::Cursor::new(vec![]));
+        for (name, data) in files {
+            writer
+                .start_file(*name, zip::write::FileOptions::default())
+                .unwrap();
+            writer.write_all(data).unwrap();
+        }
+        writer.finish().unwrap().into_inner()
+    }
+
+    #[test]
+    fn test_index_key() {
+        assert_eq!(
+            index_key("foo.pdb/abcd1234/foo.sym").as_deref(),
+            Some("foo.pdb/abcd1234/foo.sym")
+        );
+        assert_eq!(
+            index_key("./build/symbols/foo.pdb/abcd1234/foo.sym").as_deref(),
+            Some("foo.pdb/abcd1234/foo.sym")
+        );
+        assert_eq!(
+            index_key("symbols\\foo.pdb\\abcd1234\\foo.sym").as_deref(),
+            Some("foo.pdb/abcd1234/foo.sym")
+        );
+        assert_eq!(index_key("foo.pdb/foo.sym"), None);
+    }
+
+    #[tokio::test]
+    async fn test_archive_symbol_supplier() {
+        let t = tempfile::tempdir().unwrap();
+        let compressed = gzipped(SYMBOLS);
+        let files: &[(&str, &[u8])] = &[
+            ("symbols/README", b"not symbols"),
+            ("symbols/foo.pdb/abcd1234/foo.sym", SYMBOLS),
+            ("symbols/bar.pdb/abcd1234/bar.sym.gz", &compressed),
+        ];
+        let archives = [
+            ("symbols.zip", zip(files)),
+            ("symbols.tar", tar(files)),
+            ("symbols.tar.gz", gzipped(&tar(files))),
+        ];
+        for (name, contents) in &archives {
+            let path = t.path().join(name);
+            std::fs::write(&path, contents).unwrap();
+            let supplier = ArchiveSymbolSupplier::open(&path).unwrap();
+
+            let foo = SimpleModule::new("foo.pdb", "abcd1234");
+            let symbols = supplier.locate_symbols(&foo).await.unwrap();
+            assert_eq!(symbols.functions.ranges_values().count(), 1, "{}", name);
+            assert_eq!(symbols.path.unwrap(), path);
+
+            let bar = SimpleModule::new("bar.pdb", "abcd1234");
+            let symbols = supplier.locate_symbols(&bar).await.unwrap();
+            assert_eq!(symbols.functions.ranges_values().count(), 1, "{}", name);
+
+            let baz = SimpleModule::new("baz.pdb", "abcd1234");
+            assert!(matches!(
+                supplier.locate_symbols(&baz).await,
+                Err(SymbolError::NotFound)
+            ));
+        }
+    }
+}
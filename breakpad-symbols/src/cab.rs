@@ -0,0 +1,301 @@
+//! Extracting files from the CAB files symbol servers store compressed files
+//! in.
+//!
+//! `symstore /compress` (and so many symbol servers) stores each file as a CAB
+//! file with a single file in it, named like the original with the last letter
+//! of the extension replaced by an underscore (`foo.pd_`, `foo.ex_`). Those are
+//! almost always MSZIP-compressed, which is just deflate, so that's all that's
+//! supported here (along with uncompressed CAB files), and only with the
+//! `compressed-symbols` feature. LZX and Quantum compressed files are an
+//! error.
+//!
+//! See [the format documentation][cab] for the details.
+//!
+//! [cab]: https://docs.microsoft.com/en-us/previous-versions/bb417343(v=msdn.10)
+
+#[cfg(feature = "compressed-symbols")]
+use flate2::{Decompress, FlushDecompress, Status};
+
+use std::convert::TryFrom;
+use std::io;
+
+const SIGNATURE: &[u8] = b"MSCF";
+const HEADER_SIZE: usize = 36;
+
+const FLAG_PREV_CABINET: u16 = 0x1;
+const FLAG_NEXT_CABINET: u16 = 0x2;
+const FLAG_RESERVE_PRESENT: u16 = 0x4;
+
+const COMPRESS_NONE: u16 = 0;
+const COMPRESS_MSZIP: u16 = 1;
+
+/// MSZIP blocks never decompress to more than this.
+const MSZIP_BLOCK_SIZE: usize = 0x8000;
+
+/// Whether `data` looks like a CAB file.
+pub(crate) fn is_cab(data: &[u8]) -> bool {
+    data.starts_with(SIGNATURE)
+}
+
+/// The path a symbol server stores the compressed version of the file at
+/// `path` under (`foo.pdb` -> `foo.pd_`).
+pub(crate) fn compressed_path(path: &str) -> String {
+    let mut path = path.to_owned();
+    path.pop();
+    path.push('_');
+    path
+}
+
+fn invalid(msg: &'static str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+/// Reads little-endian values out of a CAB file.
+struct Reader<'a> {
+    data: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn at(data: &'a [u8], offset: usize) -> Reader<'a> {
+        Reader { data, offset }
+    }
+
+    fn bytes(&mut self, len: usize) -> io::Result<&'a [u8]> {
+        let end = self
+            .offset
+            .checked_add(len)
+            .filter(|&end| end <= self.data.len())
+            .ok_or_else(|| invalid("truncated CAB file"))?;
+        let bytes = &self.data[self.offset..end];
+        self.offset = end;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> io::Result<u8> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn u16(&mut self) -> io::Result<u16> {
+        let bytes = self.bytes(2)?;
+        Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn u32(&mut self) -> io::Result<u32> {
+        let bytes = self.bytes(4)?;
+        Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+}
+
+/// Extract the (first) file in the CAB file `data`.
+pub(crate) fn extract(data: &[u8]) -> io::Result<Vec<u8>> {
+    if !is_cab(data) {
+        return Err(invalid("not a CAB file"));
+    }
+    let mut header = Reader::at(data, 16);
+    let files_offset = header.u32()?;
+    header.bytes(6)?;
+    let folder_count = header.u16()?;
+    let file_count = header.u16()?;
+    let flags = header.u16()?;
+    header.bytes(4)?;
+    debug_assert_eq!(header.offset, HEADER_SIZE);
+
+    let (mut folder_reserve, mut data_reserve) = (0, 0);
+    if flags & FLAG_RESERVE_PRESENT != 0 {
+        let header_reserve = header.u16()?;
+        folder_reserve = usize::from(header.u8()?);
+        data_reserve = usize::from(header.u8()?);
+        header.bytes(usize::from(header_reserve))?;
+    }
+    if flags & (FLAG_PREV_CABINET | FLAG_NEXT_CABINET) != 0 {
+        return Err(invalid(
+            "CAB files spanning several cabinets aren't supported",
+        ));
+    }
+    if file_count == 0 {
+        return Err(invalid("empty CAB file"));
+    }
+
+    let usize_from = |v: u32| usize::try_from(v).map_err(|_| invalid("CAB file too big"));
+    let mut file = Reader::at(data, usize_from(files_offset)?);
+    let size = usize_from(file.u32()?)?;
+    let offset = usize_from(file.u32()?)?;
+    let folder = file.u16()?;
+    if folder >= folder_count {
+        return Err(invalid("CAB file continued from another cabinet"));
+    }
+    let end = offset
+        .checked_add(size)
+        .ok_or_else(|| invalid("CAB file too big"))?;
+
+    let mut folder_header = Reader::at(
+        data,
+        header.offset + usize::from(folder) * (8 + folder_reserve),
+    );
+    let data_offset = usize_from(folder_header.u32()?)?;
+    let block_count = folder_header.u16()?;
+    let compression = folder_header.u16()? & 0xf;
+    if compression != COMPRESS_NONE && compression != COMPRESS_MSZIP {
+        return Err(invalid(
+            "only uncompressed and MSZIP-compressed CAB files are supported",
+        ));
+    }
+
+    let mut out = vec![];
+    let mut blocks = Reader::at(data, data_offset);
+    for _ in 0..block_count {
+        if out.len() >= end {
+            break;
+        }
+        // checksum, then the sizes.
+        blocks.u32()?;
+        let compressed_size = usize::from(blocks.u16()?);
+        let uncompressed_size = usize::from(blocks.u16()?);
+        blocks.bytes(data_reserve)?;
+        let block = blocks.bytes(compressed_size)?;
+        if compression == COMPRESS_NONE {
+            out.extend_from_slice(block);
+        } else {
+            inflate_mszip_block(block, uncompressed_size, &mut out)?;
+        }
+    }
+    if out.len() < end {
+        return Err(invalid("truncated CAB file"));
+    }
+    out.truncate(end);
+    out.drain(..offset);
+    Ok(out)
+}
+
+/// Decompress an MSZIP block onto the end of `out`.
+///
+/// Every block is a complete deflate stream, but one that can refer back to
+/// the previous block's output. flate2 can't be given that as a dictionary,
+/// so it goes in front of the block as an uncompressed deflate block instead.
+#[cfg(feature = "compressed-symbols")]
+fn inflate_mszip_block(
+    block: &[u8],
+    uncompressed_size: usize,
+    out: &mut Vec<u8>,
+) -> io::Result<()> {
+    let block = block
+        .strip_prefix(b"CK")
+        .ok_or_else(|| invalid("bad MSZIP block"))?;
+    if uncompressed_size > MSZIP_BLOCK_SIZE {
+        return Err(invalid("bad MSZIP block"));
+    }
+
+    let history = &out[out.len().saturating_sub(MSZIP_BLOCK_SIZE)..];
+    let history_len = history.len() as u16;
+    let mut input = Vec::with_capacity(5 + history.len() + block.len());
+    input.push(0);
+    input.extend_from_slice(&history_len.to_le_bytes());
+    input.extend_from_slice(&(!history_len).to_le_bytes());
+    input.extend_from_slice(history);
+    input.extend_from_slice(block);
+
+    let mut inflated = Vec::with_capacity(history.len() + uncompressed_size);
+    let status = Decompress::new(false)
+        .decompress_vec(&input, &mut inflated, FlushDecompress::Finish)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    if status != Status::StreamEnd || inflated.len() != history.len() + uncompressed_size {
+        return Err(invalid("bad MSZIP block"));
+    }
+    out.extend_from_slice(&inflated[history.len()..]);
+    Ok(())
+}
+
+#[cfg(not(feature = "compressed-symbols"))]
+fn inflate_mszip_block(
+    _block: &[u8],
+    _uncompressed_size: usize,
+    _out: &mut Vec<u8>,
+) -> io::Result<()> {
+    Err(invalid(
+        "MSZIP-compressed CAB files need the `compressed-symbols` feature",
+    ))
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    use super::*;
+
+    /// `foo.txt`, a 32KiB MSZIP block of `hello world, hello world!\n` over and
+    /// over, and then another block with the line once more (which refers
+    /// back to the first block), laid out like `symstore /compress` does it.
+    const MSZIP_CAB: &[u8] = b"\x4d\x53\x43\x46\x00\x00\x00\x00\xd1\x00\x00\x00\x00\x00\x00\x00\
+        \x2c\x00\x00\x00\x00\x00\x00\x00\x03\x01\x01\x00\x01\x00\x00\x00\
+        \x00\x00\x00\x00\x44\x00\x00\x00\x02\x00\x01\x00\x1a\x80\x00\x00\
+        \x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x20\x00\x66\x6f\x6f\x2e\
+        \x74\x78\x74\x00\x00\x00\x00\x00\x77\x00\x00\x80\x43\x4b\xed\xc9\
+        \xa1\x09\x00\x20\x10\x00\xc0\xee\x14\xda\x1d\x4b\xe1\xc3\x83\x60\
+        \x71\x7d\xa3\x43\x78\xf1\xb8\x98\x99\xab\x9e\xb5\x73\xf4\x1a\x0f\
+        \xad\x84\x31\xc6\x18\x63\x8c\x31\xc6\x18\x63\x8c\x31\xc6\x18\x63\
+        \x8c\x31\xc6\x18\x63\x8c\x31\xc6\x18\x63\x8c\x31\xc6\x18\x63\x8c\
+        \x31\xc6\x18\x63\x8c\x31\xc6\x18\x63\x8c\x31\xc6\x18\x63\x8c\x31\
+        \xc6\x18\x63\x8c\x31\xc6\x18\x63\x8c\x31\xc6\x18\x63\x8c\x31\xc6\
+        \x18\x63\x8c\x31\xc6\x18\x63\x8c\x31\xc6\x18\x63\x8c\x31\xc6\x18\
+        \xf3\xcd\x5c\x00\x00\x00\x00\x06\x00\x1a\x00\x43\x4b\xc3\xad\x02\
+        \x00";
+
+    fn mszip_contents() -> Vec<u8> {
+        let line = b"hello world, hello world!\n";
+        let mut contents = line.repeat(2000);
+        contents.truncate(0x8000);
+        contents.extend_from_slice(line);
+        contents
+    }
+
+    #[test]
+    fn test_compressed_path() {
+        assert_eq!(
+            compressed_path("foo.pdb/ABCD1234/foo.pdb"),
+            "foo.pdb/ABCD1234/foo.pd_"
+        );
+    }
+
+    #[test]
+    fn test_extract_mszip() {
+        if cfg!(feature = "compressed-symbols") {
+            assert_eq!(extract(MSZIP_CAB).unwrap(), mszip_contents());
+        } else {
+            assert!(extract(MSZIP_CAB).is_err());
+        }
+        assert!(extract(&MSZIP_CAB[..MSZIP_CAB.len() - 4]).is_err());
+        assert!(extract(b"MSCF").is_err());
+        assert!(extract(b"\x7fELF and so on").is_err());
+    }
+
+    /// Make an uncompressed CAB file with `contents` in it.
+    pub(crate) fn uncompressed_cab(contents: &[u8]) -> Vec<u8> {
+        let mut cab = vec![];
+        // The header: signature, size, files offset, version, one folder and
+        // one file, no flags.
+        cab.extend_from_slice(b"MSCF\0\0\0\0");
+        cab.extend_from_slice(&[0; 8]);
+        cab.extend_from_slice(&(36u32 + 8).to_le_bytes());
+        cab.extend_from_slice(&[0, 0, 0, 0, 3, 1, 1, 0, 1, 0, 0, 0, 0, 0, 0, 0]);
+        // The folder: data offset, one block, no compression.
+        cab.extend_from_slice(&(36u32 + 8 + 16 + 8).to_le_bytes());
+        cab.extend_from_slice(&[1, 0, 0, 0]);
+        // The file: size, offset in the folder, folder, date/time/attributes,
+        // name.
+        cab.extend_from_slice(&(contents.len() as u32).to_le_bytes());
+        cab.extend_from_slice(&[0; 4]);
+        cab.extend_from_slice(&[0; 8]);
+        cab.extend_from_slice(b"foo.sym\0");
+        // The block.
+        cab.extend_from_slice(&[0; 4]);
+        cab.extend_from_slice(&(contents.len() as u16).to_le_bytes());
+        cab.extend_from_slice(&(contents.len() as u16).to_le_bytes());
+        cab.extend_from_slice(contents);
+        cab
+    }
+
+    #[test]
+    fn test_extract_uncompressed() {
+        let contents = b"some symbols";
+        assert_eq!(extract(&uncompressed_cab(contents)).unwrap(), contents);
+    }
+}
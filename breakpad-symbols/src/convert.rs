@@ -0,0 +1,326 @@
+//! Turning other kinds of debug information into Breakpad symbols.
+//!
+//! Some symbol sources (like Microsoft symbol servers and debuginfod) don't
+//! serve Breakpad symbol files, so their suppliers download whatever they do
+//! serve and hand it to a [`SymbolConverter`].
+
+use async_trait::async_trait;
+use reqwest::{Client, Url};
+use tempfile::NamedTempFile;
+use tracing::{debug, warn};
+
+#[cfg(any(feature = "pdb-converter", feature = "dwarf-converter"))]
+use std::collections::{HashMap, HashSet};
+use std::ffi::OsString;
+use std::io::{self, Write};
+use std::path::Path;
+
+use crate::retry::Retrier;
+use crate::{commit_cache_file, create_cache_file, Module, SymbolError};
+
+/// Something that can turn a debug file (a PDB, an ELF file with DWARF...)
+/// into a Breakpad text-format symbol file.
+#[async_trait]
+pub trait SymbolConverter {
+    /// Convert the debug file at `debug_file`, returning the contents of the
+    /// symbol file.
+    async fn convert(&self, debug_file: &Path) -> Result<Vec<u8>, SymbolError>;
+
+    /// Convert the debug file at `debug_file`, which was found for `module`.
+    ///
+    /// Converters that can do better knowing the module (e.g. by picking its
+    /// architecture out of a fat Mach-O file) override this; by default it's
+    /// just [`SymbolConverter::convert`].
+    async fn convert_module(
+        &self,
+        debug_file: &Path,
+        _module: &(dyn Module + Sync),
+    ) -> Result<Vec<u8>, SymbolError> {
+        self.convert(debug_file).await
+    }
+}
+
+/// A [`SymbolConverter`] that runs a [dump_syms] executable.
+///
+/// [dump_syms]: https://github.com/mozilla/dump_syms
+#[derive(Debug, Clone)]
+pub struct DumpSymsConverter {
+    program: OsString,
+}
+
+impl DumpSymsConverter {
+    /// Run the dump_syms at `program` (which may just be a name to be looked
+    /// up on the `PATH`).
+    pub fn new(program: impl Into<OsString>) -> DumpSymsConverter {
+        DumpSymsConverter {
+            program: program.into(),
+        }
+    }
+}
+
+impl Default for DumpSymsConverter {
+    fn default() -> Self {
+        Self::new("dump_syms")
+    }
+}
+
+#[async_trait]
+impl SymbolConverter for DumpSymsConverter {
+    async fn convert(&self, debug_file: &Path) -> Result<Vec<u8>, SymbolError> {
+        let output = tokio::process::Command::new(&self.program)
+            .arg(debug_file)
+            .output()
+            .await?;
+        if !output.status.success() {
+            return Err(SymbolError::LoadError(io::Error::other(format!(
+                "dump_syms failed ({}): {}",
+                output.status,
+                String::from_utf8_lossy(&output.stderr).trim()
+            ))));
+        }
+        Ok(output.stdout)
+    }
+}
+
+/// Download the file at `url` into a temporary file in `tmp`.
+pub(crate) async fn download(
+    client: &Client,
+    retrier: &Retrier,
+    url: &Url,
+    tmp: &Path,
+) -> Result<NamedTempFile, SymbolError> {
+    debug!("Trying {}", url);
+    let mut res = retrier
+        .send(url, || client.get(url.clone()))
+        .await
+        .map_err(|_| SymbolError::NotFound)?;
+    let mut file = NamedTempFile::new_in(tmp)?;
+    while let Some(chunk) = res.chunk().await.map_err(|_| SymbolError::NotFound)? {
+        file.write_all(&chunk)?;
+    }
+    Ok(file)
+}
+
+/// Save converted symbols under `cache` + `rel_path`.
+pub(crate) fn cache_symbols(symbols: &[u8], cache: &Path, tmp: &Path, rel_path: &str, url: &Url) {
+    let final_cache_path = cache.join(rel_path);
+    let result = create_cache_file(tmp, &final_cache_path).and_then(|mut temp| {
+        temp.write_all(symbols)?;
+        commit_cache_file(temp, &final_cache_path, url)
+    });
+    if let Err(e) = result {
+        warn!("Failed to save symbol file in local disk cache: {}", e);
+    }
+}
+
+/// Get the debug identifier in the MODULE record of `symbols`.
+pub(crate) fn module_id(symbols: &[u8]) -> Option<&str> {
+    let line = symbols.split(|&b| b == b'\n').next()?;
+    let line = std::str::from_utf8(line).ok()?;
+    let mut fields = line.split_whitespace();
+    if fields.next() != Some("MODULE") {
+        return None;
+    }
+    fields.nth(2)
+}
+
+/// A `FUNC` record and its line records, for a [`SymbolWriter`].
+#[cfg(any(feature = "pdb-converter", feature = "dwarf-converter"))]
+pub(crate) struct FunctionRecord {
+    pub address: u64,
+    pub size: u64,
+    pub parameter_size: u32,
+    pub name: String,
+    pub lines: Vec<LineRecord>,
+}
+
+/// A line record, for a [`SymbolWriter`].
+#[cfg(any(feature = "pdb-converter", feature = "dwarf-converter"))]
+pub(crate) struct LineRecord {
+    pub address: u64,
+    pub size: u64,
+    pub line: u32,
+    pub file: u32,
+}
+
+/// Collects the records of a symbol file converted from some other kind of
+/// debug information, and writes them out in the order Breakpad expects.
+#[cfg(any(feature = "pdb-converter", feature = "dwarf-converter"))]
+#[derive(Default)]
+pub(crate) struct SymbolWriter {
+    files: HashMap<String, u32>,
+    functions: Vec<FunctionRecord>,
+    publics: Vec<(u64, u32, String)>,
+    stack: Vec<String>,
+}
+
+#[cfg(any(feature = "pdb-converter", feature = "dwarf-converter"))]
+impl SymbolWriter {
+    /// The id of the `FILE` record for `path`, adding one if there isn't one
+    /// yet.
+    pub fn file(&mut self, path: &str) -> u32 {
+        let next = self.files.len() as u32;
+        *self.files.entry(path.to_owned()).or_insert(next)
+    }
+
+    pub fn add_function(&mut self, function: FunctionRecord) {
+        self.functions.push(function);
+    }
+
+    pub fn add_public(&mut self, address: u64, parameter_size: u32, name: String) {
+        self.publics.push((address, parameter_size, name));
+    }
+
+    /// Add a `STACK` record (`record` is everything after `STACK `).
+    pub fn add_stack(&mut self, record: String) {
+        self.stack.push(record);
+    }
+
+    /// Write out the symbol file of the module `name` with the debug
+    /// identifier `id`.
+    pub fn finish(self, os: &str, arch: &str, id: &str, name: &str) -> Vec<u8> {
+        let mut out = vec![];
+        self.write(&mut out, os, arch, id, name)
+            .expect("writing to a Vec can't fail");
+        out
+    }
+
+    fn write(
+        mut self,
+        out: &mut impl Write,
+        os: &str,
+        arch: &str,
+        id: &str,
+        name: &str,
+    ) -> io::Result<()> {
+        writeln!(out, "MODULE {} {} {} {}", os, arch, id, name)?;
+
+        let mut files = self.files.into_iter().collect::<Vec<_>>();
+        files.sort_by_key(|&(_, id)| id);
+        for (path, id) in files {
+            writeln!(out, "FILE {} {}", id, path)?;
+        }
+
+        // Identical functions are often folded together, so several can
+        // start at the same address. Only the first is kept, marked with `m`.
+        self.functions.sort_by_key(|function| function.address);
+        let starts = self
+            .functions
+            .iter()
+            .map(|function| function.address)
+            .collect::<HashSet<_>>();
+        let mut functions = self.functions.into_iter().peekable();
+        while let Some(mut function) = functions.next() {
+            let mut multiple = "";
+            while matches!(functions.peek(), Some(next) if next.address == function.address) {
+                functions.next();
+                multiple = "m ";
+            }
+            writeln!(
+                out,
+                "FUNC {}{:x} {:x} {:x} {}",
+                multiple, function.address, function.size, function.parameter_size, function.name
+            )?;
+            function.lines.sort_by_key(|line| line.address);
+            for line in function.lines {
+                writeln!(
+                    out,
+                    "{:x} {:x} {} {}",
+                    line.address, line.size, line.line, line.file
+                )?;
+            }
+        }
+
+        // Public symbols for the start of a function just repeat it.
+        self.publics.sort_by_key(|&(address, ..)| address);
+        self.publics.dedup_by_key(|&mut (address, ..)| address);
+        for (address, parameter_size, name) in self.publics {
+            if !starts.contains(&address) {
+                writeln!(out, "PUBLIC {:x} {:x} {}", address, parameter_size, name)?;
+            }
+        }
+
+        for record in self.stack {
+            writeln!(out, "STACK {}", record)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_module_id() {
+        assert_eq!(
+            module_id(b"MODULE Linux x86_64 ABCD12340 libfoo.so\nFILE 1 foo.c\n"),
+            Some("ABCD12340")
+        );
+        assert_eq!(module_id(b"MODULE Linux"), None);
+        assert_eq!(module_id(b"FUNC 1000 30 10 some func\n"), None);
+    }
+
+    #[cfg(any(feature = "pdb-converter", feature = "dwarf-converter"))]
+    #[test]
+    fn test_symbol_writer() {
+        let mut symbols = SymbolWriter::default();
+        let file = symbols.file("foo.c");
+        assert_eq!(symbols.file("bar.c"), 1);
+        assert_eq!(symbols.file("foo.c"), file);
+        let line = |address, line| LineRecord {
+            address,
+            size: 0x10,
+            line,
+            file,
+        };
+        symbols.add_function(FunctionRecord {
+            address: 0x2000,
+            size: 0x10,
+            parameter_size: 0,
+            name: String::from("other func"),
+            lines: vec![],
+        });
+        symbols.add_function(FunctionRecord {
+            address: 0x1000,
+            size: 0x20,
+            parameter_size: 8,
+            name: String::from("some func"),
+            lines: vec![line(0x1010, 11), line(0x1000, 10)],
+        });
+        symbols.add_function(FunctionRecord {
+            address: 0x2000,
+            size: 0x10,
+            parameter_size: 0,
+            name: String::from("folded func"),
+            lines: vec![],
+        });
+        symbols.add_public(0x3000, 0, String::from("public"));
+        symbols.add_public(0x1000, 0, String::from("some_func"));
+        symbols.add_stack(String::from(
+            "CFI INIT 1000 20 .cfa: $rsp 8 + .ra: .cfa -8 + ^",
+        ));
+
+        let symbols = symbols.finish("Linux", "x86_64", "ABCD12340", "libfoo.so");
+        assert_eq!(
+            std::str::from_utf8(&symbols).unwrap(),
+            "MODULE Linux x86_64 ABCD12340 libfoo.so
+FILE 0 foo.c
+FILE 1 bar.c
+FUNC 1000 20 8 some func
+1000 10 10 0
+1010 10 11 0
+FUNC m 2000 10 0 other func
+PUBLIC 3000 0 public
+STACK CFI INIT 1000 20 .cfa: $rsp 8 + .ra: .cfa -8 + ^
+"
+        );
+        assert_eq!(module_id(&symbols), Some("ABCD12340"));
+        let symbols = crate::SymbolFile::from_bytes(&symbols).unwrap();
+        assert!(symbols.functions.get(0x2008).is_some());
+        assert!(symbols
+            .publics
+            .iter()
+            .any(|public| public.address == 0x3000));
+    }
+}
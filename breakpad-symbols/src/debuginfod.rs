@@ -0,0 +1,282 @@
+//! Getting symbols from debuginfod servers.
+//!
+//! [debuginfod] servers (which most Linux distributions run for their
+//! packages) serve the separate debug information of ELF files, looked up by
+//! build id: `<server>/buildid/<build id>/debuginfo`. Minidumps record the
+//! build ids of ELF modules as their code identifiers.
+//!
+//! What comes back is an ELF file with DWARF, which is turned into a Breakpad
+//! symbol file by a [`SymbolConverter`] (dump_syms, or `DwarfConverter` with the
+//! `dwarf-converter` feature).
+//!
+//! [debuginfod]: https://sourceware.org/elfutils/Debuginfod.html
+
+use async_trait::async_trait;
+use reqwest::{Client, Url};
+
+use std::borrow::Cow;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::convert::{cache_symbols, download};
+use crate::retry::Retrier;
+use crate::{
+    leafname, relative_symbol_path, Module, RetryPolicy, SimpleSymbolSupplier, SymbolConverter,
+    SymbolError, SymbolFile, SymbolSupplier,
+};
+
+/// An implementation of `SymbolSupplier` that downloads debug information
+/// from debuginfod servers and converts it to Breakpad symbols.
+///
+/// Converted symbols are saved in the cache in the usual Breakpad layout
+/// (see [`relative_symbol_path`]), so the cache can also be used by a
+/// [`SimpleSymbolSupplier`] or [`HttpSymbolSupplier`][crate::HttpSymbolSupplier].
+///
+/// Only modules with a build id are looked up.
+pub struct DebuginfodSymbolSupplier {
+    /// HTTP Client to use for fetching debug information.
+    client: Client,
+    /// debuginfod server URLs.
+    urls: Vec<Url>,
+    /// A `SimpleSymbolSupplier` for the symbols we've already converted.
+    local: SimpleSymbolSupplier,
+    /// A path at which to cache converted symbols.
+    cache: PathBuf,
+    /// A path to a temporary location where downloaded debug information and
+    /// converted symbols can be written.
+    tmp: PathBuf,
+    /// The thing that turns debug information into symbol files.
+    converter: Box<dyn SymbolConverter + Send + Sync>,
+    /// Retries failed requests.
+    retrier: Retrier,
+}
+
+impl DebuginfodSymbolSupplier {
+    /// Create a new `DebuginfodSymbolSupplier`.
+    ///
+    /// Symbols will be searched for in `cache` first, then debug information
+    /// will be searched for on each of the debuginfod servers at `urls` (see
+    /// [`DebuginfodSymbolSupplier::urls_from_env`]), and converted with
+    /// `converter`.
+    pub fn new<C: SymbolConverter + Send + Sync + 'static>(
+        urls: Vec<String>,
+        cache: PathBuf,
+        tmp: PathBuf,
+        converter: C,
+        timeout: Duration,
+    ) -> DebuginfodSymbolSupplier {
+        let client = Client::builder().timeout(timeout).build().unwrap();
+        let urls = urls
+            .into_iter()
+            .filter_map(|mut u| {
+                if !u.ends_with('/') {
+                    u.push('/');
+                }
+                Url::parse(&u).ok()
+            })
+            .collect();
+        let local = SimpleSymbolSupplier::new(vec![]).with_cache(cache.clone());
+        DebuginfodSymbolSupplier {
+            client,
+            urls,
+            local,
+            cache,
+            tmp,
+            converter: Box::new(converter),
+            retrier: Retrier::default(),
+        }
+    }
+
+    /// Retry failed downloads, and stop asking servers that keep failing,
+    /// according to `policy`.
+    pub fn with_retry_policy(mut self, policy: RetryPolicy) -> DebuginfodSymbolSupplier {
+        self.retrier = Retrier::new(policy);
+        self
+    }
+
+    /// The debuginfod servers configured for the system, from the
+    /// `DEBUGINFOD_URLS` environment variable (like every other debuginfod
+    /// client).
+    pub fn urls_from_env() -> Vec<String> {
+        std::env::var("DEBUGINFOD_URLS")
+            .map(|urls| parse_urls(&urls))
+            .unwrap_or_default()
+    }
+}
+
+/// Split up a `DEBUGINFOD_URLS`-style list of URLs.
+fn parse_urls(urls: &str) -> Vec<String> {
+    urls.split_ascii_whitespace().map(String::from).collect()
+}
+
+/// Get `module`'s build id, if it has one.
+pub(crate) fn build_id<'a>(module: &'a (dyn Module + Sync)) -> Option<Cow<'a, str>> {
+    // Windows modules have PDBs, not build ids.
+    let debug_file = module.debug_file()?;
+    if leafname(&debug_file).to_lowercase().ends_with(".pdb") {
+        return None;
+    }
+    // Build ids are lowercase hex bytes. Code identifiers of other kinds of
+    // modules have uppercase in them, or are empty.
+    let build_id = module.code_identifier();
+    if build_id.is_empty()
+        || build_id.len() % 2 == 1
+        || !build_id
+            .chars()
+            .all(|c| c.is_ascii_digit() || ('a'..='f').contains(&c))
+        || build_id.chars().all(|c| c == '0')
+    {
+        return None;
+    }
+    Some(build_id)
+}
+
+/// Get the path of `module`'s debug information on a debuginfod server, if
+/// it has any.
+fn debuginfo_path(module: &(dyn Module + Sync)) -> Option<String> {
+    build_id(module).map(|build_id| format!("buildid/{}/debuginfo", build_id))
+}
+
+#[async_trait]
+impl SymbolSupplier for DebuginfodSymbolSupplier {
+    async fn locate_symbols(
+        &self,
+        module: &(dyn Module + Sync),
+    ) -> Result<SymbolFile, SymbolError> {
+        let debuginfo_path = debuginfo_path(module).ok_or(SymbolError::NotFound)?;
+        let sym_path = relative_symbol_path(module, "sym").ok_or(SymbolError::NotFound)?;
+
+        // Check for symbols we've already converted first.
+        let local_result = self.local.locate_symbols(module).await;
+        if !matches!(local_result, Err(SymbolError::NotFound)) {
+            return local_result;
+        }
+
+        for base_url in &self.urls {
+            let url = match base_url.join(&debuginfo_path) {
+                Ok(url) => url,
+                Err(_) => continue,
+            };
+            let debuginfo = match download(&self.client, &self.retrier, &url, &self.tmp).await {
+                Ok(debuginfo) => debuginfo,
+                Err(SymbolError::NotFound) => continue,
+                Err(e) => return Err(e),
+            };
+            let symbols = self
+                .converter
+                .convert_module(debuginfo.path(), module)
+                .await?;
+            let mut symbol_file = SymbolFile::from_bytes(&symbols)?;
+            symbol_file.url = Some(url.to_string());
+            cache_symbols(&symbols, &self.cache, &self.tmp, &sym_path, &url);
+            return Ok(symbol_file);
+        }
+        Err(SymbolError::NotFound)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test::{serve_file, ElfModule};
+    use crate::DumpSymsConverter;
+
+    #[test]
+    fn test_parse_urls() {
+        assert_eq!(
+            parse_urls(" https://debuginfod.example.com/  https://other.example.com\n"),
+            vec![
+                "https://debuginfod.example.com/",
+                "https://other.example.com"
+            ]
+        );
+        assert!(parse_urls("").is_empty());
+    }
+
+    #[test]
+    fn test_debuginfo_path() {
+        let module = ElfModule {
+            name: "/usr/lib/libfoo.so",
+            build_id: "0123456789abcdef",
+        };
+        assert_eq!(
+            debuginfo_path(&module).as_deref(),
+            Some("buildid/0123456789abcdef/debuginfo")
+        );
+
+        // Windows code identifiers aren't build ids.
+        let module = ElfModule {
+            name: "foo.dll",
+            build_id: "5A9832E51000",
+        };
+        assert_eq!(debuginfo_path(&module), None);
+        let module = ElfModule {
+            name: "foo.pdb",
+            build_id: "12345678",
+        };
+        assert_eq!(debuginfo_path(&module), None);
+
+        // Neither are nothing, or zeroes.
+        for build_id in &["", "000000", "abc"] {
+            let module = ElfModule {
+                name: "libfoo.so",
+                build_id,
+            };
+            assert_eq!(debuginfo_path(&module), None);
+        }
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_debuginfod_symbol_supplier() {
+        // `cat` makes a fine dump_syms when the debuginfo is already a
+        // symbol file.
+        let (base, requests) = serve_file(
+            "/debuginfod/buildid/0123456789abcdef/debuginfo",
+            b"MODULE Linux x86_64 ABCD12340 libfoo.so
+FILE 1 foo.c
+FUNC 1000 30 10 some func
+1000 30 100 1
+",
+        )
+        .await;
+        let cache = tempfile::tempdir().unwrap();
+        let tmp = tempfile::tempdir().unwrap();
+        let supplier = DebuginfodSymbolSupplier::new(
+            vec![format!("{}/missing", base), format!("{}/debuginfod", base)],
+            cache.path().to_path_buf(),
+            tmp.path().to_path_buf(),
+            DumpSymsConverter::new("cat"),
+            Duration::from_secs(10),
+        );
+
+        let module = ElfModule {
+            name: "/usr/lib/libfoo.so",
+            build_id: "0123456789abcdef",
+        };
+        let symbols = supplier.locate_symbols(&module).await.unwrap();
+        assert!(symbols.functions.get(0x1010).is_some());
+        assert_eq!(
+            symbols.url.as_deref(),
+            Some(&*format!(
+                "{}/debuginfod/buildid/0123456789abcdef/debuginfo",
+                base
+            ))
+        );
+        assert_eq!(
+            *requests.lock().unwrap(),
+            vec![
+                "/missing/buildid/0123456789abcdef/debuginfo",
+                "/debuginfod/buildid/0123456789abcdef/debuginfo"
+            ]
+        );
+
+        // Now it's cached.
+        assert!(cache
+            .path()
+            .join("libfoo.so/ABCD12340/libfoo.so.sym")
+            .is_file());
+        supplier.locate_symbols(&module).await.unwrap();
+        assert_eq!(requests.lock().unwrap().len(), 2);
+    }
+}
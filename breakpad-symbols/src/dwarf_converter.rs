@@ -0,0 +1,765 @@
+//! Converting ELF and Mach-O files with DWARF to Breakpad symbols in-process.
+//!
+//! [`DwarfConverter`] reads ELF files, Mach-O files and dSYMs with the
+//! [`object`] and [`gimli`] crates rather than running dump_syms, so it can be
+//! used with [`ElfSymbolSupplier`][crate::ElfSymbolSupplier],
+//! [`DebuginfodSymbolSupplier`][crate::DebuginfodSymbolSupplier] and
+//! [`MachoSymbolSupplier`][crate::MachoSymbolSupplier] on machines that don't
+//! have dump_syms.
+//!
+//! This needs the `dwarf-converter` feature.
+
+use async_trait::async_trait;
+use gimli::{
+    BaseAddresses, CfaRule, CieOrFde, DebugFrame, EhFrame, EndianSlice, Register, RegisterRule,
+    RunTimeEndian, UnwindContext, UnwindSection,
+};
+use object::read::macho::{FatArch, FatHeader};
+use object::{
+    Architecture, BinaryFormat, FileKind, Object, ObjectSection, ObjectSegment, ObjectSymbol,
+    SymbolKind,
+};
+
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::convert::TryFrom;
+use std::error::Error;
+use std::fmt::Write;
+use std::io;
+use std::path::Path;
+
+use crate::convert::{FunctionRecord, LineRecord, SymbolWriter};
+use crate::{leafname, Module, SymbolConverter, SymbolError};
+
+type Slice<'a> = EndianSlice<'a, RunTimeEndian>;
+type Dwarf<'a> = gimli::Dwarf<Slice<'a>>;
+type Unit<'a> = gimli::Unit<Slice<'a>>;
+type Result<T> = std::result::Result<T, Box<dyn Error + Send + Sync>>;
+
+/// How many `DW_AT_abstract_origin`s and `DW_AT_specification`s to follow
+/// looking for a function's name.
+const MAX_NAME_REFERENCES: usize = 8;
+
+/// A [`SymbolConverter`] that reads ELF and Mach-O files in-process, with the
+/// [`object`] and [`gimli`] crates.
+///
+/// Functions and their lines come from the DWARF debug information, and
+/// anything else in the symbol table becomes a `PUBLIC` record. Unwind info
+/// comes from `.debug_frame` and `.eh_frame` (as `STACK CFI` records). Inlined
+/// functions aren't written out, and neither is Mach-O compact unwind info
+/// (`__unwind_info`).
+///
+/// Of the architectures in a fat Mach-O file, [`convert_module`] picks the
+/// module's (by UUID), and [`convert`] the first.
+///
+/// [`convert`]: SymbolConverter::convert
+/// [`convert_module`]: SymbolConverter::convert_module
+#[derive(Debug, Clone, Default)]
+pub struct DwarfConverter {}
+
+impl DwarfConverter {
+    /// Create a new `DwarfConverter`.
+    pub fn new() -> DwarfConverter {
+        DwarfConverter {}
+    }
+}
+
+#[async_trait]
+impl SymbolConverter for DwarfConverter {
+    async fn convert(&self, debug_file: &Path) -> std::result::Result<Vec<u8>, SymbolError> {
+        let name = debug_file
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        convert_in_background(debug_file, name, None).await
+    }
+
+    async fn convert_module(
+        &self,
+        debug_file: &Path,
+        module: &(dyn Module + Sync),
+    ) -> std::result::Result<Vec<u8>, SymbolError> {
+        // The file may be separate debug information or have been downloaded
+        // to a temporary file, so the name the module knows it by is better.
+        let name = match module.debug_file() {
+            Some(name) => leafname(&name).to_owned(),
+            None => return self.convert(debug_file).await,
+        };
+        let debug_id = module.debug_identifier().map(|id| id.into_owned());
+        convert_in_background(debug_file, name, debug_id).await
+    }
+}
+
+/// Convert the object file at `path` (which is called `name`) on a blocking
+/// thread, picking the architecture with `debug_id` out of fat files.
+async fn convert_in_background(
+    path: &Path,
+    name: String,
+    debug_id: Option<String>,
+) -> std::result::Result<Vec<u8>, SymbolError> {
+    let path = path.to_path_buf();
+    tokio::task::spawn_blocking(move || {
+        let data = std::fs::read(&path)?;
+        convert_file(&data, &name, debug_id.as_deref())
+            .map_err(|e| SymbolError::LoadError(io::Error::new(io::ErrorKind::InvalidData, e)))
+    })
+    .await
+    .map_err(io::Error::other)?
+}
+
+/// Turn the file in `data` (which is called `name`) into a Breakpad symbol
+/// file. If it's a fat Mach-O file, the architecture with `debug_id` (or
+/// the first one) is converted.
+fn convert_file(data: &[u8], name: &str, debug_id: Option<&str>) -> Result<Vec<u8>> {
+    let arches = match FileKind::parse(data)? {
+        FileKind::MachOFat32 => FatHeader::parse_arch32(data)?
+            .iter()
+            .map(|arch| arch.data(data))
+            .collect::<object::Result<Vec<_>>>()?,
+        FileKind::MachOFat64 => FatHeader::parse_arch64(data)?
+            .iter()
+            .map(|arch| arch.data(data))
+            .collect::<object::Result<Vec<_>>>()?,
+        _ => return convert_object(&object::File::parse(data)?, name),
+    };
+    for arch in arches {
+        let object = object::File::parse(arch)?;
+        let wanted = match debug_id {
+            Some(debug_id) => {
+                matches!(object_debug_id(&object), Ok(id) if id.eq_ignore_ascii_case(debug_id))
+            }
+            None => true,
+        };
+        if wanted {
+            return convert_object(&object, name);
+        }
+    }
+    Err("no matching architecture in fat file".into())
+}
+
+/// Turn `object` (which is called `name`) into a Breakpad symbol file.
+fn convert_object(object: &object::File, name: &str) -> Result<Vec<u8>> {
+    let id = object_debug_id(object)?;
+    let (os, base) = match object.format() {
+        // Breakpad addresses are relative to where the module is loaded.
+        BinaryFormat::Elf => {
+            let base = object
+                .segments()
+                .map(|segment| segment.address())
+                .min()
+                .unwrap_or(0);
+            ("Linux", base)
+        }
+        // dSYMs have an empty `__TEXT` segment, at the same address.
+        BinaryFormat::MachO => {
+            let base = object
+                .segments()
+                .find(|segment| matches!(segment.name(), Ok(Some("__TEXT"))))
+                .map(|segment| segment.address())
+                .unwrap_or(0);
+            ("mac", base)
+        }
+        _ => return Err("not an ELF or Mach-O file".into()),
+    };
+    let arch = Arch::new(object.architecture());
+
+    let mut symbols = SymbolWriter::default();
+    add_functions(&mut symbols, object, base)?;
+    add_publics(&mut symbols, object, base);
+    add_cfi(&mut symbols, object, base, &arch);
+    Ok(symbols.finish(os, arch.name, &id, name))
+}
+
+/// Get the Breakpad debug identifier of `object`.
+fn object_debug_id(object: &object::File) -> Result<String> {
+    match object.format() {
+        BinaryFormat::Elf => Ok(elf_debug_id(object.build_id()?.ok_or("no build id")?)),
+        // The UUID and an age of 0.
+        BinaryFormat::MachO => {
+            let uuid = object.mach_uuid()?.ok_or("no UUID")?;
+            let mut id = uuid
+                .iter()
+                .map(|b| format!("{:02X}", b))
+                .collect::<String>();
+            id.push('0');
+            Ok(id)
+        }
+        _ => Err("not an ELF or Mach-O file".into()),
+    }
+}
+
+/// Get the Breakpad debug identifier of an ELF file with `build_id`.
+fn elf_debug_id(build_id: &[u8]) -> String {
+    // The first 16 bytes of the build id, as a little-endian GUID, and an
+    // age of 0.
+    let mut guid = [0; 16];
+    let len = build_id.len().min(16);
+    guid[..len].copy_from_slice(&build_id[..len]);
+    guid[0..4].reverse();
+    guid[4..6].reverse();
+    guid[6..8].reverse();
+    let mut id = guid
+        .iter()
+        .map(|b| format!("{:02X}", b))
+        .collect::<String>();
+    id.push('0');
+    id
+}
+
+/// The architecture of a module, as it matters to its symbol file.
+struct Arch {
+    /// The name in the MODULE record.
+    name: &'static str,
+    /// The names of the DWARF registers in `STACK CFI` records, by number.
+    registers: &'static [&'static str],
+}
+
+impl Arch {
+    fn new(architecture: Architecture) -> Arch {
+        let (name, registers): (_, &[_]) = match architecture {
+            Architecture::I386 => (
+                "x86",
+                &[
+                    "$eax", "$ecx", "$edx", "$ebx", "$esp", "$ebp", "$esi", "$edi", "$eip",
+                ],
+            ),
+            Architecture::X86_64 => (
+                "x86_64",
+                &[
+                    "$rax", "$rdx", "$rcx", "$rbx", "$rsi", "$rdi", "$rbp", "$rsp", "$r8", "$r9",
+                    "$r10", "$r11", "$r12", "$r13", "$r14", "$r15", "$rip",
+                ],
+            ),
+            Architecture::Arm => (
+                "arm",
+                &[
+                    "r0", "r1", "r2", "r3", "r4", "r5", "r6", "r7", "r8", "r9", "r10", "r11",
+                    "r12", "sp", "lr", "pc",
+                ],
+            ),
+            Architecture::Aarch64 => (
+                "arm64",
+                &[
+                    "x0", "x1", "x2", "x3", "x4", "x5", "x6", "x7", "x8", "x9", "x10", "x11",
+                    "x12", "x13", "x14", "x15", "x16", "x17", "x18", "x19", "x20", "x21", "x22",
+                    "x23", "x24", "x25", "x26", "x27", "x28", "x29", "x30", "sp",
+                ],
+            ),
+            Architecture::Mips => ("mips", &[]),
+            Architecture::Mips64 => ("mips64", &[]),
+            Architecture::PowerPc => ("ppc", &[]),
+            Architecture::PowerPc64 => ("ppc64", &[]),
+            _ => ("unknown", &[]),
+        };
+        Arch { name, registers }
+    }
+
+    fn register(&self, register: Register) -> Option<&'static str> {
+        self.registers.get(usize::from(register.0)).copied()
+    }
+}
+
+/// Demangle a C++ or Rust symbol name, or leave it as it is.
+fn demangle(name: &str) -> String {
+    if let Ok(demangled) = rustc_demangle::try_demangle(name) {
+        return format!("{:#}", demangled);
+    }
+    cpp_demangle::Symbol::new(name)
+        .ok()
+        .and_then(|symbol| symbol.demangle(&Default::default()).ok())
+        .unwrap_or_else(|| name.to_owned())
+}
+
+/// Load the DWARF sections of `object`.
+fn load_dwarf<'data>(object: &object::File<'data>) -> Result<gimli::Dwarf<Cow<'data, [u8]>>> {
+    Ok(gimli::Dwarf::load(|id| {
+        match object.section_by_name(id.name()) {
+            Some(section) => section.uncompressed_data(),
+            None => Ok(Cow::Borrowed(&[][..])),
+        }
+    })?)
+}
+
+/// A line record, before it's been assigned to a function.
+struct Line {
+    address: u64,
+    size: u64,
+    line: u32,
+    file: u32,
+}
+
+/// Add `FUNC` records for the functions in `object`'s DWARF.
+fn add_functions(symbols: &mut SymbolWriter, object: &object::File, base: u64) -> Result<()> {
+    let endian = if object.is_little_endian() {
+        RunTimeEndian::Little
+    } else {
+        RunTimeEndian::Big
+    };
+    let sections = load_dwarf(object)?;
+    let dwarf = sections.borrow(|section| EndianSlice::new(section, endian));
+
+    let mut units = dwarf.units();
+    while let Some(header) = units.next()? {
+        let unit = match dwarf.unit(header) {
+            Ok(unit) => unit,
+            Err(_) => continue,
+        };
+        let lines = unit_lines(symbols, &dwarf, &unit).unwrap_or_default();
+
+        // The names of the namespaces and types we're in, with their depth.
+        let mut scopes: Vec<(isize, String)> = vec![];
+        let mut depth = 0;
+        let mut entries = unit.entries();
+        while let Some((delta, entry)) = entries.next_dfs()? {
+            depth += delta;
+            while matches!(scopes.last(), Some(&(scope_depth, _)) if scope_depth >= depth) {
+                scopes.pop();
+            }
+            match entry.tag() {
+                gimli::DW_TAG_namespace
+                | gimli::DW_TAG_class_type
+                | gimli::DW_TAG_structure_type
+                | gimli::DW_TAG_union_type => {
+                    if let Some(name) = entry_name(&dwarf, &unit, entry) {
+                        scopes.push((depth, name));
+                    }
+                }
+                gimli::DW_TAG_subprogram => {
+                    let name = match function_name(&dwarf, &unit, entry) {
+                        Some(name) => name,
+                        None => match entry_name(&dwarf, &unit, entry) {
+                            Some(name) => scopes
+                                .iter()
+                                .map(|(_, scope)| &scope[..])
+                                .chain(Some(&name[..]))
+                                .collect::<Vec<_>>()
+                                .join("::"),
+                            None => String::from("<name omitted>"),
+                        },
+                    };
+                    let mut ranges = match dwarf.die_ranges(&unit, entry) {
+                        Ok(ranges) => ranges,
+                        Err(_) => continue,
+                    };
+                    while let Some(range) = ranges.next()? {
+                        if range.begin < base || range.end <= range.begin {
+                            continue;
+                        }
+                        symbols.add_function(FunctionRecord {
+                            address: range.begin - base,
+                            size: range.end - range.begin,
+                            parameter_size: 0,
+                            name: name.clone(),
+                            lines: lines_in(&lines, range.begin, range.end, base),
+                        });
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+    Ok(())
+}
+
+/// The lines of `unit`, sorted by address.
+fn unit_lines(symbols: &mut SymbolWriter, dwarf: &Dwarf, unit: &Unit) -> Result<Vec<Line>> {
+    let program = match unit.line_program {
+        Some(ref program) => program.clone(),
+        None => return Ok(vec![]),
+    };
+    let mut files = HashMap::new();
+    let mut lines = vec![];
+    // The row before this one in the sequence, which runs until this one.
+    let mut previous: Option<(u64, u32, u64)> = None;
+    let mut rows = program.rows();
+    while let Some((header, row)) = rows.next_row()? {
+        if let Some((address, line, file_index)) = previous.take() {
+            if row.address() > address {
+                let file = match files.get(&file_index) {
+                    Some(&file) => file,
+                    None => {
+                        let path = header
+                            .file(file_index)
+                            .and_then(|file| file_path(dwarf, unit, header, file))
+                            .unwrap_or_default();
+                        let file = symbols.file(&path);
+                        files.insert(file_index, file);
+                        file
+                    }
+                };
+                lines.push(Line {
+                    address,
+                    size: row.address() - address,
+                    line,
+                    file,
+                });
+            }
+        }
+        if !row.end_sequence() {
+            if let Some(line) = row.line() {
+                let line = u32::try_from(line.get()).unwrap_or(u32::MAX);
+                previous = Some((row.address(), line, row.file_index()));
+            }
+        }
+    }
+    lines.sort_by_key(|line| line.address);
+    Ok(lines)
+}
+
+/// The full path of a file in a line program.
+fn file_path(
+    dwarf: &Dwarf,
+    unit: &Unit,
+    header: &gimli::LineProgramHeader<Slice>,
+    file: &gimli::FileEntry<Slice>,
+) -> Option<String> {
+    let name = dwarf.attr_string(unit, file.path_name()).ok()?;
+    let name = name.to_string_lossy();
+    if name.starts_with('/') {
+        return Some(name.into_owned());
+    }
+    let mut path = String::new();
+    if let Some(dir) = file.directory(header) {
+        let dir = dwarf.attr_string(unit, dir).ok()?;
+        let dir = dir.to_string_lossy();
+        if !dir.starts_with('/') {
+            if let Some(comp_dir) = unit.comp_dir {
+                path.push_str(&comp_dir.to_string_lossy());
+                path.push('/');
+            }
+        }
+        if !dir.is_empty() {
+            path.push_str(&dir);
+            path.push('/');
+        }
+    }
+    path.push_str(&name);
+    Some(path)
+}
+
+/// The line records for the function at `begin..end`, out of all the
+/// (sorted) `lines` of its unit.
+fn lines_in(lines: &[Line], begin: u64, end: u64, base: u64) -> Vec<LineRecord> {
+    let first = lines.partition_point(|line| line.address + line.size <= begin);
+    lines[first..]
+        .iter()
+        .take_while(|line| line.address < end)
+        .map(|line| {
+            let address = line.address.max(begin);
+            LineRecord {
+                address: address - base,
+                size: (line.address + line.size).min(end) - address,
+                line: line.line,
+                file: line.file,
+            }
+        })
+        .collect()
+}
+
+/// The name of a function from its linkage name, demangled, looking through
+/// the declarations it refers to.
+fn function_name(
+    dwarf: &Dwarf,
+    unit: &Unit,
+    entry: &gimli::DebuggingInformationEntry<Slice>,
+) -> Option<String> {
+    let mut offset = None;
+    for _ in 0..MAX_NAME_REFERENCES {
+        let entry = match offset {
+            Some(offset) => unit.entry(offset).ok()?,
+            None => entry.clone(),
+        };
+        for name in &[gimli::DW_AT_linkage_name, gimli::DW_AT_MIPS_linkage_name] {
+            if let Some(attr) = entry.attr_value(*name).ok()? {
+                let name = dwarf.attr_string(unit, attr).ok()?;
+                return Some(demangle(&name.to_string_lossy()));
+            }
+        }
+        let reference = entry
+            .attr_value(gimli::DW_AT_abstract_origin)
+            .ok()?
+            .or(entry.attr_value(gimli::DW_AT_specification).ok()?);
+        match reference {
+            Some(gimli::AttributeValue::UnitRef(reference)) => offset = Some(reference),
+            _ => return None,
+        }
+    }
+    None
+}
+
+/// The plain `DW_AT_name` of an entry, looking through the declarations it
+/// refers to.
+fn entry_name(
+    dwarf: &Dwarf,
+    unit: &Unit,
+    entry: &gimli::DebuggingInformationEntry<Slice>,
+) -> Option<String> {
+    let mut offset = None;
+    for _ in 0..MAX_NAME_REFERENCES {
+        let entry = match offset {
+            Some(offset) => unit.entry(offset).ok()?,
+            None => entry.clone(),
+        };
+        if let Some(attr) = entry.attr_value(gimli::DW_AT_name).ok()? {
+            let name = dwarf.attr_string(unit, attr).ok()?;
+            return Some(name.to_string_lossy().into_owned());
+        }
+        let reference = entry
+            .attr_value(gimli::DW_AT_abstract_origin)
+            .ok()?
+            .or(entry.attr_value(gimli::DW_AT_specification).ok()?);
+        match reference {
+            Some(gimli::AttributeValue::UnitRef(reference)) => offset = Some(reference),
+            _ => return None,
+        }
+    }
+    None
+}
+
+/// Add `PUBLIC` records for the functions in `object`'s symbol tables.
+fn add_publics(symbols: &mut SymbolWriter, object: &object::File, base: u64) {
+    for symbol in object.symbols().chain(object.dynamic_symbols()) {
+        if symbol.kind() != SymbolKind::Text || symbol.is_undefined() || symbol.address() < base {
+            continue;
+        }
+        if let Ok(mut name) = symbol.name() {
+            // Mach-O symbol names have an extra leading underscore.
+            if object.format() == BinaryFormat::MachO {
+                name = name.strip_prefix('_').unwrap_or(name);
+            }
+            if !name.is_empty() {
+                symbols.add_public(symbol.address() - base, 0, demangle(name));
+            }
+        }
+    }
+}
+
+/// Add `STACK CFI` records for the unwind info in `object`'s `.debug_frame`
+/// and `.eh_frame`.
+fn add_cfi(symbols: &mut SymbolWriter, object: &object::File, base: u64, arch: &Arch) {
+    if arch.registers.is_empty() {
+        return;
+    }
+    let endian = if object.is_little_endian() {
+        RunTimeEndian::Little
+    } else {
+        RunTimeEndian::Big
+    };
+    let section_data = |name| {
+        let section = object.section_by_name(name)?;
+        let data = section.uncompressed_data().ok()?;
+        Some((section.address(), data))
+    };
+    let section_address = |name| {
+        object
+            .section_by_name(name)
+            .map(|section| section.address())
+    };
+    let mut bases = BaseAddresses::default();
+    if let Some(address) = section_address(".text") {
+        bases = bases.set_text(address);
+    }
+    if let Some(address) = section_address(".got") {
+        bases = bases.set_got(address);
+    }
+
+    // Functions with both only get the `.debug_frame` version.
+    let mut seen = HashSet::new();
+    if let Some((_, data)) = section_data(".debug_frame") {
+        let mut debug_frame = DebugFrame::new(&data, endian);
+        debug_frame.set_address_size(object_address_size(object));
+        add_section_cfi(symbols, &debug_frame, &bases, base, arch, &mut seen);
+    }
+    if let Some((address, data)) = section_data(".eh_frame") {
+        let mut eh_frame = EhFrame::new(&data, endian);
+        eh_frame.set_address_size(object_address_size(object));
+        let bases = bases.set_eh_frame(address);
+        add_section_cfi(symbols, &eh_frame, &bases, base, arch, &mut seen);
+    }
+}
+
+fn object_address_size(object: &object::File) -> u8 {
+    if object.is_64() {
+        8
+    } else {
+        4
+    }
+}
+
+fn add_section_cfi<'a, S: UnwindSection<Slice<'a>>>(
+    symbols: &mut SymbolWriter,
+    section: &S,
+    bases: &BaseAddresses,
+    base: u64,
+    arch: &Arch,
+    seen: &mut HashSet<u64>,
+) {
+    let mut context = UnwindContext::new();
+    let mut entries = section.entries(bases);
+    // A bad entry means we can't find where the next one starts.
+    while let Ok(Some(entry)) = entries.next() {
+        let partial = match entry {
+            CieOrFde::Fde(partial) => partial,
+            CieOrFde::Cie(_) => continue,
+        };
+        let fde =
+            match partial.parse(|section, bases, offset| section.cie_from_offset(bases, offset)) {
+                Ok(fde) => fde,
+                Err(_) => continue,
+            };
+        let start = fde.initial_address();
+        if fde.len() == 0 || start < base || !seen.insert(start) {
+            continue;
+        }
+        let return_address = fde.cie().return_address_register();
+        let mut records = vec![];
+        let mut table = match fde.rows(section, bases, &mut context) {
+            Ok(table) => table,
+            Err(_) => continue,
+        };
+        while let Ok(Some(row)) = table.next_row() {
+            let rules = match row_rules(row, return_address, arch) {
+                Some(rules) => rules,
+                None => {
+                    // Partial CFI would be worse than none.
+                    records.clear();
+                    break;
+                }
+            };
+            if records.is_empty() {
+                records.push(format!(
+                    "CFI INIT {:x} {:x} {}",
+                    start - base,
+                    fde.len(),
+                    rules
+                ));
+            } else {
+                records.push(format!("CFI {:x} {}", row.start_address() - base, rules));
+            }
+        }
+        for record in records {
+            symbols.add_stack(record);
+        }
+    }
+}
+
+/// The rules of a row of an unwind table, or `None` if its CFA can't be
+/// expressed in a `STACK CFI` record.
+fn row_rules(
+    row: &gimli::UnwindTableRow<Slice>,
+    return_address: Register,
+    arch: &Arch,
+) -> Option<String> {
+    let mut rules = match *row.cfa() {
+        CfaRule::RegisterAndOffset { register, offset } => {
+            format!(".cfa: {} {} +", arch.register(register)?, offset)
+        }
+        CfaRule::Expression(_) => return None,
+    };
+    let mut has_return_address = false;
+    for &(register, ref rule) in row.registers() {
+        let name = if register == return_address {
+            ".ra"
+        } else {
+            match arch.register(register) {
+                Some(name) => name,
+                None => continue,
+            }
+        };
+        let rule = match *rule {
+            // There's no caller (e.g. in `_start`).
+            RegisterRule::Undefined if name == ".ra" => {
+                has_return_address = true;
+                continue;
+            }
+            RegisterRule::SameValue => match arch.register(register) {
+                Some(name) => name.to_owned(),
+                None => continue,
+            },
+            RegisterRule::Offset(offset) => format!(".cfa {} + ^", offset),
+            RegisterRule::ValOffset(offset) => format!(".cfa {} +", offset),
+            RegisterRule::Register(other) => match arch.register(other) {
+                Some(name) => name.to_owned(),
+                None => continue,
+            },
+            _ => continue,
+        };
+        has_return_address |= name == ".ra";
+        let _ = write!(rules, " {}: {}", name, rule);
+    }
+    // Until it's saved somewhere, the return address is still in its
+    // register (the link register, on ARM).
+    if !has_return_address {
+        let _ = write!(rules, " .ra: {}", arch.register(return_address)?);
+    }
+    Some(rules)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_elf_debug_id() {
+        assert_eq!(
+            elf_debug_id(&[
+                0x01, 0x23, 0x45, 0x67, 0x89, 0xab, 0xcd, 0xef, 0x01, 0x23, 0x45, 0x67, 0x89, 0xab,
+                0xcd, 0xef, 0x01, 0x23, 0x45, 0x67
+            ]),
+            "67452301AB89EFCD0123456789ABCDEF0"
+        );
+        // Short build ids are padded.
+        assert_eq!(
+            elf_debug_id(&[0x01, 0x23, 0x45, 0x67]),
+            "674523010000000000000000000000000"
+        );
+    }
+    /// Make a thin x86-64 Mach-O file with just a UUID.
+    fn thin_macho(uuid: u8) -> Vec<u8> {
+        let mut file = vec![];
+        // magic, cputype, cpusubtype, filetype, ncmds, sizeofcmds, flags,
+        // reserved, then LC_UUID.
+        for v in &[0xfeedfacf, 0x01000007, 3, 0xa, 1, 24, 0, 0, 0x1b, 24] {
+            file.extend_from_slice(&u32::to_le_bytes(*v));
+        }
+        file.extend_from_slice(&[uuid; 16]);
+        file
+    }
+
+    #[test]
+    fn test_fat_macho() {
+        let thins = [thin_macho(0x11), thin_macho(0x22)];
+        let mut fat = vec![];
+        fat.extend_from_slice(&u32::to_be_bytes(0xcafebabe));
+        fat.extend_from_slice(&u32::to_be_bytes(2));
+        for (i, thin) in thins.iter().enumerate() {
+            let offset = 0x1000 * (i as u32 + 1);
+            for v in &[0x01000007, 3, offset, thin.len() as u32, 12] {
+                fat.extend_from_slice(&u32::to_be_bytes(*v));
+            }
+        }
+        for (i, thin) in thins.iter().enumerate() {
+            fat.resize(0x1000 * (i + 1), 0);
+            fat.extend_from_slice(thin);
+        }
+
+        let module_line = |debug_id| {
+            let symbols = convert_file(&fat, "Foo", debug_id).unwrap();
+            String::from_utf8(symbols)
+                .unwrap()
+                .lines()
+                .next()
+                .unwrap()
+                .to_owned()
+        };
+        assert_eq!(
+            module_line(Some("222222222222222222222222222222220")),
+            "MODULE mac x86_64 222222222222222222222222222222220 Foo"
+        );
+        assert_eq!(
+            module_line(None),
+            "MODULE mac x86_64 111111111111111111111111111111110 Foo"
+        );
+        assert!(convert_file(&fat, "Foo", Some("333333333333333333333333333333330")).is_err());
+    }
+}
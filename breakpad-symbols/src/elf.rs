@@ -0,0 +1,255 @@
+//! Getting symbols from ELF files on local disk.
+//!
+//! On Linux, the binaries that were running are usually still around, and
+//! either have their DWARF debug information in them or have it installed
+//! separately (in `/usr/lib/debug/.build-id/`, by build id). Rather than
+//! requiring Breakpad symbol files to be generated for all of them ahead of
+//! time, this finds the ELF file for a module when it's needed and converts it
+//! with a [`SymbolConverter`].
+//!
+//! The conversion is done by the converter, which is [dump_syms] for
+//! [`DumpSymsConverter`][crate::DumpSymsConverter], or gimli in-process for
+//! `DwarfConverter` (with the `dwarf-converter` feature). Both turn
+//! `.debug_frame`/`.eh_frame` into `STACK CFI` records, so the stackwalker gets
+//! CFI for these modules too.
+//!
+//! Since binaries on disk may have been replaced since the minidump was
+//! written, converted symbols are only used if they're for the same build as
+//! the module.
+//!
+//! [dump_syms]: https://github.com/mozilla/dump_syms
+
+use async_trait::async_trait;
+use reqwest::Url;
+use tracing::debug;
+
+use std::path::PathBuf;
+
+use crate::convert::{cache_symbols, module_id};
+use crate::debuginfod::build_id;
+use crate::{
+    leafname, relative_symbol_path, Module, SimpleSymbolSupplier, SymbolConverter, SymbolError,
+    SymbolFile, SymbolSupplier,
+};
+
+/// Where distributions install separate debug information.
+const DEBUG_ROOT: &str = "/usr/lib/debug";
+
+/// An implementation of `SymbolSupplier` that converts ELF files with DWARF
+/// found on local disk to Breakpad symbols.
+///
+/// For each of its paths, the ELF file is searched for at
+/// `<debug file>/<debug identifier>/<debug file>`. After that, separate debug
+/// information is searched for by build id under each of its debug roots
+/// (`/usr/lib/debug` by default), and finally the module's own file is tried.
+///
+/// Converted symbols are saved in the cache in the usual Breakpad layout
+/// (see [`relative_symbol_path`]), so they're only converted once.
+pub struct ElfSymbolSupplier {
+    /// Local disk paths in which to search for ELF files.
+    paths: Vec<PathBuf>,
+    /// Directories with a `.build-id` directory of separate debug information.
+    debug_roots: Vec<PathBuf>,
+    /// A `SimpleSymbolSupplier` for the symbols we've already converted.
+    local: SimpleSymbolSupplier,
+    /// A path at which to cache converted symbols.
+    cache: PathBuf,
+    /// A path to a temporary location where converted symbols can be written.
+    tmp: PathBuf,
+    /// The thing that turns ELF files into symbol files.
+    converter: Box<dyn SymbolConverter + Send + Sync>,
+}
+
+impl ElfSymbolSupplier {
+    /// Create a new `ElfSymbolSupplier`.
+    ///
+    /// Symbols will be searched for in `cache` first, then ELF files will be
+    /// searched for in `paths` and the usual places, and converted with
+    /// `converter`.
+    pub fn new<C: SymbolConverter + Send + Sync + 'static>(
+        paths: Vec<PathBuf>,
+        cache: PathBuf,
+        tmp: PathBuf,
+        converter: C,
+    ) -> ElfSymbolSupplier {
+        let local = SimpleSymbolSupplier::new(vec![]).with_cache(cache.clone());
+        ElfSymbolSupplier {
+            paths,
+            debug_roots: vec![PathBuf::from(DEBUG_ROOT)],
+            local,
+            cache,
+            tmp,
+            converter: Box::new(converter),
+        }
+    }
+
+    /// Search for separate debug information under `debug_roots` rather than
+    /// `/usr/lib/debug`.
+    pub fn with_debug_roots(mut self, debug_roots: Vec<PathBuf>) -> ElfSymbolSupplier {
+        self.debug_roots = debug_roots;
+        self
+    }
+
+    /// The places `module`'s ELF file might be, best first.
+    fn candidates(&self, module: &(dyn Module + Sync)) -> Vec<PathBuf> {
+        let build_id = match build_id(module) {
+            Some(build_id) => build_id,
+            None => return vec![],
+        };
+        let mut candidates = vec![];
+        if let (Some(debug_file), Some(debug_id)) = (module.debug_file(), module.debug_identifier())
+        {
+            let leaf = leafname(&debug_file);
+            candidates.extend(
+                self.paths
+                    .iter()
+                    .map(|path| path.join(leaf).join(&*debug_id).join(leaf)),
+            );
+        }
+        let (dir, file) = build_id.split_at(2);
+        candidates.extend(self.debug_roots.iter().map(|root| {
+            root.join(".build-id")
+                .join(dir)
+                .join(format!("{}.debug", file))
+        }));
+        // The module's own file is only any use if it's a full path on this
+        // machine.
+        candidates
+            .extend(Some(PathBuf::from(&*module.code_file())).filter(|path| path.is_absolute()));
+        candidates.retain(|path| path.is_file());
+        candidates
+    }
+}
+
+#[async_trait]
+impl SymbolSupplier for ElfSymbolSupplier {
+    async fn locate_symbols(
+        &self,
+        module: &(dyn Module + Sync),
+    ) -> Result<SymbolFile, SymbolError> {
+        let sym_path = relative_symbol_path(module, "sym").ok_or(SymbolError::NotFound)?;
+        let debug_id = module.debug_identifier().ok_or(SymbolError::NotFound)?;
+
+        // Check for symbols we've already converted first.
+        let local_result = self.local.locate_symbols(module).await;
+        if !matches!(local_result, Err(SymbolError::NotFound)) {
+            return local_result;
+        }
+
+        for elf in self.candidates(module) {
+            let symbols = self.converter.convert_module(&elf, module).await?;
+            if !matches!(module_id(&symbols), Some(id) if id.eq_ignore_ascii_case(&debug_id)) {
+                debug!("{} is not the right build of {}", elf.display(), sym_path);
+                continue;
+            }
+            let mut symbol_file = SymbolFile::from_bytes(&symbols)?;
+            let url = Url::from_file_path(&elf).ok();
+            symbol_file.url = url.as_ref().map(Url::to_string);
+            if let Some(url) = url {
+                cache_symbols(&symbols, &self.cache, &self.tmp, &sym_path, &url);
+            }
+            return Ok(symbol_file);
+        }
+        Err(SymbolError::NotFound)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test::ElfModule;
+    use std::path::Path;
+    use std::sync::{Arc, Mutex};
+
+    const SYMBOLS: &[u8] = b"MODULE Linux x86_64 ABCD12340 libfoo.so
+FILE 1 foo.c
+FUNC 1000 30 10 some func
+1000 30 100 1
+";
+
+    const OTHER_SYMBOLS: &[u8] = b"MODULE Linux x86_64 FFFF00000 libfoo.so
+FUNC 1000 30 10 other func
+";
+
+    /// Pretends ELF files are already symbol files, and remembers what it saw.
+    #[derive(Default)]
+    struct TestConverter {
+        seen: Mutex<Vec<PathBuf>>,
+    }
+
+    #[async_trait]
+    impl SymbolConverter for Arc<TestConverter> {
+        async fn convert(&self, elf: &Path) -> Result<Vec<u8>, SymbolError> {
+            self.seen.lock().unwrap().push(elf.to_path_buf());
+            Ok(std::fs::read(elf)?)
+        }
+    }
+
+    fn write(path: &Path, contents: &[u8]) {
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, contents).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_elf_symbol_supplier() {
+        let debug_root = tempfile::tempdir().unwrap();
+        let cache = tempfile::tempdir().unwrap();
+        let tmp = tempfile::tempdir().unwrap();
+        // The separate debug information is out of date, the binary isn't.
+        let debuginfo = debug_root.path().join(".build-id/01/23456789abcdef.debug");
+        write(&debuginfo, OTHER_SYMBOLS);
+        let binary = debug_root.path().join("bin/libfoo.so");
+        write(&binary, SYMBOLS);
+        let code_file: &'static str = Box::leak(binary.to_str().unwrap().into());
+
+        let converter = Arc::new(TestConverter::default());
+        let supplier = ElfSymbolSupplier::new(
+            vec![],
+            cache.path().to_path_buf(),
+            tmp.path().to_path_buf(),
+            converter.clone(),
+        )
+        .with_debug_roots(vec![debug_root.path().to_path_buf()]);
+
+        let module = ElfModule {
+            name: code_file,
+            build_id: "0123456789abcdef",
+        };
+        let symbols = supplier.locate_symbols(&module).await.unwrap();
+        assert!(symbols.functions.get(0x1010).is_some());
+        assert_eq!(
+            symbols.url,
+            Some(Url::from_file_path(&binary).unwrap().to_string())
+        );
+        assert_eq!(
+            *converter.seen.lock().unwrap(),
+            vec![debuginfo, binary.clone()]
+        );
+
+        // Converted once, then cached.
+        assert!(cache
+            .path()
+            .join("libfoo.so/ABCD12340/libfoo.so.sym")
+            .is_file());
+        supplier.locate_symbols(&module).await.unwrap();
+        assert_eq!(converter.seen.lock().unwrap().len(), 2);
+
+        // Modules without build ids aren't looked for.
+        let module = ElfModule {
+            name: code_file,
+            build_id: "",
+        };
+        let cache = tempfile::tempdir().unwrap();
+        let supplier = ElfSymbolSupplier::new(
+            vec![],
+            cache.path().to_path_buf(),
+            tmp.path().to_path_buf(),
+            converter.clone(),
+        );
+        assert_eq!(
+            supplier.locate_symbols(&module).await,
+            Err(SymbolError::NotFound)
+        );
+        assert_eq!(converter.seen.lock().unwrap().len(), 2);
+    }
+}
@@ -0,0 +1,138 @@
+// Copyright 2015 Ted Mielczarek. See the COPYRIGHT
+// file at the top-level directory of this distribution.
+
+//! Getting symbols from symbol servers without a filesystem.
+//!
+//! [`HttpSymbolSupplier`][crate::HttpSymbolSupplier] saves everything it
+//! downloads in a cache on disk, and that's not something a web page has.
+//! [`FetchSymbolSupplier`] just downloads symbol files (with the browser's
+//! `fetch` on wasm32) and keeps nothing; the [`Symbolizer`][crate::Symbolizer]
+//! keeps the symbols it's using in memory anyway.
+
+use async_trait::async_trait;
+use reqwest::{Client, Url};
+use tracing::debug;
+
+use std::future::Future;
+
+use crate::{relative_symbol_path, Module, SymbolError, SymbolFile, SymbolSupplier};
+
+/// A [`SymbolSupplier`] that downloads Breakpad text-format symbols from HTTP
+/// URLs, without caching them anywhere.
+///
+/// Symbols are looked for at each of the URLs in turn, with the same layout
+/// as [`HttpSymbolSupplier`][crate::HttpSymbolSupplier]. This is the one to
+/// use on wasm32, where every other supplier that downloads symbols is
+/// missing.
+pub struct FetchSymbolSupplier {
+    client: Client,
+    urls: Vec<Url>,
+}
+
+impl FetchSymbolSupplier {
+    /// Create a new `FetchSymbolSupplier` looking for symbols at `urls`.
+    pub fn new(urls: Vec<String>) -> FetchSymbolSupplier {
+        let urls = urls
+            .into_iter()
+            .filter_map(|mut u| {
+                if !u.ends_with('/') {
+                    u.push('/');
+                }
+                Url::parse(&u).ok()
+            })
+            .collect();
+        FetchSymbolSupplier {
+            client: Client::new(),
+            urls,
+        }
+    }
+
+    async fn fetch(&self, rel_path: &str) -> Result<SymbolFile, SymbolError> {
+        for base_url in &self.urls {
+            let url = match base_url.join(rel_path) {
+                Ok(url) => url,
+                Err(_) => continue,
+            };
+            debug!("Trying {}", url);
+            let res = match self.client.get(url.clone()).send().await {
+                Ok(res) if res.status().is_success() => res,
+                _ => continue,
+            };
+            let bytes = match res.bytes().await {
+                Ok(bytes) => bytes,
+                Err(e) => {
+                    debug!("Failed to download {}: {}", url, e);
+                    continue;
+                }
+            };
+            let mut symbol_file = SymbolFile::from_bytes(&bytes)?;
+            symbol_file.url = Some(url.to_string());
+            symbol_file.file_size = Some(bytes.len() as u64);
+            return Ok(symbol_file);
+        }
+        Err(SymbolError::NotFound)
+    }
+}
+
+#[async_trait]
+impl SymbolSupplier for FetchSymbolSupplier {
+    async fn locate_symbols(
+        &self,
+        module: &(dyn Module + Sync),
+    ) -> Result<SymbolFile, SymbolError> {
+        let rel_path = relative_symbol_path(module, "sym").ok_or(SymbolError::NotFound)?;
+        sendable(self.fetch(&rel_path)).await
+    }
+}
+
+/// The browser's futures can't be sent between threads, but there's only one
+/// thread on wasm32 anyway.
+#[cfg(target_arch = "wasm32")]
+fn sendable<F: Future>(future: F) -> send_wrapper::SendWrapper<F> {
+    send_wrapper::SendWrapper::new(future)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn sendable<F: Future>(future: F) -> F {
+    future
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test::serve_file;
+    use crate::SimpleModule;
+
+    #[tokio::test]
+    async fn test_fetch_symbol_supplier() {
+        const SYMBOLS: &[u8] = b"MODULE Linux x86 abcd1234 foo
+FILE 1 foo.c
+FUNC 1000 30 10 some func
+1000 30 100 1
+";
+        let (base, requests) = serve_file("/symbols/foo.pdb/abcd1234/foo.sym", SYMBOLS).await;
+        let supplier = FetchSymbolSupplier::new(vec![
+            format!("{}/missing", base),
+            format!("{}/symbols", base),
+        ]);
+
+        let m = SimpleModule::new("foo.pdb", "abcd1234");
+        let symbols = supplier.locate_symbols(&m).await.unwrap();
+        assert_eq!(
+            *requests.lock().unwrap(),
+            vec![
+                "/missing/foo.pdb/abcd1234/foo.sym",
+                "/symbols/foo.pdb/abcd1234/foo.sym"
+            ]
+        );
+        let url = format!("{}/symbols/foo.pdb/abcd1234/foo.sym", base);
+        assert_eq!(symbols.url.as_deref(), Some(&*url));
+        assert_eq!(symbols.file_size, Some(SYMBOLS.len() as u64));
+
+        let missing = SimpleModule::new("bar.pdb", "abcd1234");
+        assert!(matches!(
+            supplier.locate_symbols(&missing).await,
+            Err(SymbolError::NotFound)
+        ));
+    }
+}
@@ -37,25 +37,89 @@
 //! ```
 
 use async_trait::async_trait;
-use log::{debug, trace, warn};
+use futures_util::stream::{self, StreamExt};
+#[cfg(not(target_arch = "wasm32"))]
 use reqwest::{Client, Url};
+#[cfg(not(target_arch = "wasm32"))]
 use tempfile::NamedTempFile;
+use tracing::{debug, debug_span, field, trace, Instrument};
 
 use std::borrow::Cow;
 use std::boxed::Box;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fs;
-use std::io::{self, Write};
 use std::path::{Path, PathBuf};
-use std::sync::Mutex;
+use std::sync::{Arc, Mutex, MutexGuard};
 use std::time::Duration;
 
+// Everything that downloads symbols into an on-disk cache, or runs a
+// converter, needs a filesystem and processes, which wasm32 doesn't have.
+// `FetchSymbolSupplier` is what's left for getting symbols there.
+#[cfg(not(target_arch = "wasm32"))]
+use crate::retry::Retrier;
+#[cfg(not(target_arch = "wasm32"))]
+use std::io::{self, Write};
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::Instant;
+#[cfg(not(target_arch = "wasm32"))]
+use tracing::warn;
+
 pub use minidump_common::traits::Module;
 pub use sym_file::walker;
 
+#[cfg(all(feature = "archive", not(target_arch = "wasm32")))]
+pub use crate::archive::ArchiveSymbolSupplier;
+#[cfg(not(target_arch = "wasm32"))]
+pub use crate::convert::{DumpSymsConverter, SymbolConverter};
+#[cfg(not(target_arch = "wasm32"))]
+pub use crate::debuginfod::DebuginfodSymbolSupplier;
+#[cfg(all(feature = "dwarf-converter", not(target_arch = "wasm32")))]
+pub use crate::dwarf_converter::DwarfConverter;
+#[cfg(not(target_arch = "wasm32"))]
+pub use crate::elf::ElfSymbolSupplier;
+#[cfg(feature = "fetch")]
+pub use crate::fetch::FetchSymbolSupplier;
+#[cfg(not(target_arch = "wasm32"))]
+pub use crate::macho::MachoSymbolSupplier;
+#[cfg(all(feature = "object-store", not(target_arch = "wasm32")))]
+pub use crate::object_store::{AwsCredentials, ObjectStore, ObjectStoreSymbolSupplier};
+#[cfg(not(target_arch = "wasm32"))]
+pub use crate::pdb::PdbSymbolSupplier;
+#[cfg(all(feature = "pdb-converter", not(target_arch = "wasm32")))]
+pub use crate::pdb_converter::PdbConverter;
+#[cfg(not(target_arch = "wasm32"))]
+pub use crate::retry::RetryPolicy;
 pub use crate::sym_file::{CfiRules, SymbolFile};
-
+#[cfg(not(target_arch = "wasm32"))]
+pub use crate::symsrv::MicrosoftSymbolSupplier;
+
+#[cfg(all(feature = "archive", not(target_arch = "wasm32")))]
+mod archive;
+#[cfg(not(target_arch = "wasm32"))]
+mod cab;
+#[cfg(not(target_arch = "wasm32"))]
+mod convert;
+#[cfg(not(target_arch = "wasm32"))]
+mod debuginfod;
+#[cfg(all(feature = "dwarf-converter", not(target_arch = "wasm32")))]
+mod dwarf_converter;
+#[cfg(not(target_arch = "wasm32"))]
+mod elf;
+#[cfg(feature = "fetch")]
+mod fetch;
+#[cfg(not(target_arch = "wasm32"))]
+mod macho;
+#[cfg(all(feature = "object-store", not(target_arch = "wasm32")))]
+mod object_store;
+#[cfg(not(target_arch = "wasm32"))]
+mod pdb;
+#[cfg(all(feature = "pdb-converter", not(target_arch = "wasm32")))]
+mod pdb_converter;
+#[cfg(not(target_arch = "wasm32"))]
+mod retry;
 mod sym_file;
+#[cfg(not(target_arch = "wasm32"))]
+mod symsrv;
 
 // Re-exports for the purposes of the cfi_eval fuzzer. Not public API.
 #[doc(hidden)]
@@ -66,16 +130,44 @@ pub mod fuzzing_private_exports {
 }
 
 /// Statistics on the symbols of a module.
-#[derive(Default, Debug)]
+#[derive(Default, Debug, Clone)]
 pub struct SymbolStats {
     /// If the module's symbols were downloaded, this is the url used.
     pub symbol_url: Option<String>,
+    /// If the module's symbols were loaded from local disk, this is the path
+    /// used.
+    pub symbol_path: Option<PathBuf>,
+    /// The size of the symbol file, in bytes.
+    pub symbol_file_size: Option<u64>,
+    /// How long it took to download the symbols, if they were downloaded.
+    pub fetch_duration: Option<Duration>,
+    /// How long it took to parse the symbols.
+    pub parse_duration: Option<Duration>,
+    /// If the symbols were found in a cache of downloaded (or converted)
+    /// symbols.
+    pub cache_hit: bool,
     /// If the symbols were found and loaded into memory.
     pub loaded_symbols: bool,
     /// If we tried to parse the symbols, but failed.
     pub corrupt_symbols: bool,
 }
 
+impl SymbolStats {
+    /// The statistics for successfully loaded `symbols`.
+    fn loaded(symbols: &SymbolFile) -> SymbolStats {
+        SymbolStats {
+            symbol_url: symbols.url.clone(),
+            symbol_path: symbols.path.clone(),
+            symbol_file_size: symbols.file_size,
+            fetch_duration: symbols.fetch_duration,
+            parse_duration: symbols.parse_duration,
+            cache_hit: symbols.cache_hit,
+            loaded_symbols: true,
+            corrupt_symbols: false,
+        }
+    }
+}
+
 /// A `Module` implementation that holds arbitrary data.
 ///
 /// This can be useful for getting symbols for a module when you
@@ -171,18 +263,41 @@ fn replace_or_add_extension(filename: &str, match_extension: &str, new_extension
 /// You can use [this script][packagesymbols] to run dump_syms and put the
 /// resulting symbol files in the proper directory structure.
 ///
+/// Modules without debug information, such as unloaded modules, are looked
+/// up by their code file and code identifier instead:
+/// `<code filename>/<code identifier>/<code filename stem>.sym`. This is the
+/// layout symbol servers like Tecken use for code-file lookups.
+///
 /// [module_line]: https://chromium.googlesource.com/breakpad/breakpad/+/master/docs/symbol_files.md#MODULE-records
 /// [packagesymbols]: https://gist.github.com/luser/2ad32d290f224782fcfc#file-packagesymbols-py
 pub fn relative_symbol_path(module: &(dyn Module + Sync), extension: &str) -> Option<String> {
-    module.debug_file().and_then(|debug_file| {
-        module.debug_identifier().map(|debug_id| {
-            // Can't use PathBuf::file_name here, it doesn't handle
-            // Windows file paths on non-Windows.
-            let leaf = leafname(&debug_file);
-            let filename = replace_or_add_extension(leaf, "pdb", extension);
-            [leaf, &debug_id[..], &filename[..]].join("/")
+    module
+        .debug_file()
+        .and_then(|debug_file| {
+            module.debug_identifier().map(|debug_id| {
+                // Can't use PathBuf::file_name here, it doesn't handle
+                // Windows file paths on non-Windows.
+                let leaf = leafname(&debug_file);
+                let filename = replace_or_add_extension(leaf, "pdb", extension);
+                [leaf, &debug_id[..], &filename[..]].join("/")
+            })
         })
-    })
+        .or_else(|| code_info_symbol_path(module, extension))
+}
+
+/// The fallback for [`relative_symbol_path`] when `module` has no debug information.
+fn code_info_symbol_path(module: &(dyn Module + Sync), extension: &str) -> Option<String> {
+    if module.debug_file().is_some() || module.debug_identifier().is_some() {
+        return None;
+    }
+    let code_file = module.code_file();
+    let code_id = module.code_identifier();
+    let leaf = leafname(&code_file);
+    if leaf.is_empty() || code_id.is_empty() {
+        return None;
+    }
+    let stem = leaf.rsplit_once('.').map_or(leaf, |(stem, _)| stem);
+    Some([leaf, &code_id[..], &format!("{}.{}", stem, extension)].join("/"))
 }
 
 /// Possible results of locating symbols for a module.
@@ -230,17 +345,17 @@ pub enum SymbolError {
 #[derive(Debug)]
 pub struct FillSymbolError {
     // We don't want to yield a full SymbolError for fill_symbol
-// as this would involve cloning bulky Error strings every time
-// someone requested symbols for a missing module.
-//
-// As it turns out there's currently no reason to care about *why*
-// fill_symbol, so for now this is just a dummy type until we have
-// something to put here.
-//
-// The only reason fill_symbol *can* produce an Err is so that
-// the caller can distinguish between "we had symbols, but this address
-// didn't map to a function name" and "we had no symbols for that module"
-// (this is used as a heuristic for stack scanning).
+    // as this would involve cloning bulky Error strings every time
+    // someone requested symbols for a missing module.
+    //
+    // As it turns out there's currently no reason to care about *why*
+    // fill_symbol, so for now this is just a dummy type until we have
+    // something to put here.
+    //
+    // The only reason fill_symbol *can* produce an Err is so that
+    // the caller can distinguish between "we had symbols, but this address
+    // didn't map to a function name" and "we had no symbols for that module"
+    // (this is used as a heuristic for stack scanning).
 }
 
 impl PartialEq for SymbolError {
@@ -265,21 +380,87 @@ pub trait SymbolSupplier {
         -> Result<SymbolFile, SymbolError>;
 }
 
+/// Boxed suppliers are suppliers too, so a supplier picked from runtime
+/// configuration can be passed around as a `Box<dyn SymbolSupplier + Send + Sync>`
+/// and handed to [`Symbolizer::new`].
+#[async_trait]
+impl<T: SymbolSupplier + Sync + ?Sized> SymbolSupplier for Box<T> {
+    async fn locate_symbols(
+        &self,
+        module: &(dyn Module + Sync),
+    ) -> Result<SymbolFile, SymbolError> {
+        (**self).locate_symbols(module).await
+    }
+}
+
+#[async_trait]
+impl<T: SymbolSupplier + Send + Sync + ?Sized> SymbolSupplier for Arc<T> {
+    async fn locate_symbols(
+        &self,
+        module: &(dyn Module + Sync),
+    ) -> Result<SymbolFile, SymbolError> {
+        (**self).locate_symbols(module).await
+    }
+}
+
 /// An implementation of `SymbolSupplier` that loads Breakpad text-format symbols from local disk
 /// paths.
 ///
 /// See [`relative_symbol_path`] for details on how paths are searched.
+/// With the `compressed-symbols` feature, symbol files may be gzip- or
+/// zstd-compressed, optionally with an extra `.gz` or `.zst` extension. If a
+/// symbol file has an up to date binary cache next to it (see
+/// [`SymbolFile::write_binary_cache`]), that's loaded instead.
 ///
 /// [`relative_symbol_path`]: fn.relative_symbol_path.html
 pub struct SimpleSymbolSupplier {
     /// Local disk paths in which to search for symbols.
     paths: Vec<PathBuf>,
+    /// Symbol files at least this big are parsed lazily.
+    lazy_min_size: Option<u64>,
+    /// The one of `paths` that's a cache of downloaded or converted symbols.
+    cache: Option<PathBuf>,
 }
 
 impl SimpleSymbolSupplier {
     /// Instantiate a new `SimpleSymbolSupplier` that will search in `paths`.
     pub fn new(paths: Vec<PathBuf>) -> SimpleSymbolSupplier {
-        SimpleSymbolSupplier { paths }
+        SimpleSymbolSupplier {
+            paths,
+            lazy_min_size: None,
+            cache: None,
+        }
+    }
+
+    /// Also search `cache`, a cache of downloaded or converted symbols.
+    /// Symbols found there are reported as cache hits.
+    pub(crate) fn with_cache(mut self, cache: PathBuf) -> SimpleSymbolSupplier {
+        self.paths.push(cache.clone());
+        self.cache = Some(cache);
+        self
+    }
+
+    /// Parse symbol files of at least `min_file_size` bytes lazily (see
+    /// [`SymbolFile::from_file_lazy`]).
+    pub fn with_lazy_parsing(mut self, min_file_size: u64) -> SimpleSymbolSupplier {
+        self.lazy_min_size = Some(min_file_size);
+        self
+    }
+
+    /// Load the symbol file at `path`, which is `len` bytes.
+    fn load(&self, path: &Path, len: u64) -> Result<SymbolFile, SymbolError> {
+        if matches!(self.lazy_min_size, Some(min) if len >= min) {
+            return SymbolFile::from_file_lazy(path);
+        }
+        let cache_path = SymbolFile::binary_cache_path(path);
+        match SymbolFile::from_binary_cache(&cache_path, len) {
+            Ok(symbols) => return Ok(symbols),
+            Err(SymbolError::ParseError(e, _)) => {
+                debug!("Ignoring binary symbol cache {:?}: {}", cache_path, e);
+            }
+            Err(_) => {}
+        }
+        SymbolFile::from_file(path)
     }
 }
 
@@ -291,9 +472,20 @@ impl SymbolSupplier for SimpleSymbolSupplier {
     ) -> Result<SymbolFile, SymbolError> {
         if let Some(rel_path) = relative_symbol_path(module, "sym") {
             for path in self.paths.iter() {
-                let test_path = path.join(&rel_path);
-                if fs::metadata(&test_path).ok().map_or(false, |m| m.is_file()) {
-                    return SymbolFile::from_file(&test_path);
+                // Symbol files may also be stored compressed.
+                let test_paths = [
+                    path.join(&rel_path),
+                    path.join(format!("{}.gz", rel_path)),
+                    path.join(format!("{}.zst", rel_path)),
+                ];
+                for test_path in &test_paths {
+                    let metadata = match fs::metadata(test_path) {
+                        Ok(metadata) if metadata.is_file() => metadata,
+                        _ => continue,
+                    };
+                    let mut symbols = self.load(test_path, metadata.len())?;
+                    symbols.cache_hit = self.cache.as_ref() == Some(path);
+                    return Ok(symbols);
                 }
             }
         }
@@ -329,10 +521,14 @@ impl SymbolSupplier for StringSymbolSupplier {
     }
 }
 
+#[cfg(not(target_arch = "wasm32"))]
 /// An implementation of `SymbolSupplier` that loads Breakpad text-format symbols from HTTP
 /// URLs.
 ///
-/// See [`relative_symbol_path`] for details on how paths are searched.
+/// See [`relative_symbol_path`] for details on how paths are searched. This is the
+/// layout used by Tecken and other symbol servers.
+///
+/// Concurrent requests for the same symbol file only download it once.
 ///
 /// [`relative_symbol_path`]: fn.relative_symbol_path.html
 pub struct HttpSymbolSupplier {
@@ -355,8 +551,49 @@ pub struct HttpSymbolSupplier {
     /// We recommend using `std::env::temp_dir()`, as this will be your OS's
     /// intended location for temporary files.
     tmp: PathBuf,
+    /// Symbol files currently being downloaded, by relative path.
+    ///
+    /// Anyone else looking for the same file waits on the lock, and then
+    /// finds it in the cache instead of downloading it again.
+    in_flight: Mutex<HashMap<String, Arc<tokio::sync::Mutex<()>>>>,
+    /// Called with the progress of every download.
+    on_fetch: Arc<dyn Fn(&FetchEvent) + Send + Sync>,
+    /// Retries failed requests.
+    retrier: Retrier,
+}
+
+/// The progress of a symbol file download, as reported to the callback given
+/// to [`HttpSymbolSupplier::with_fetch_callback`].
+///
+/// Every download that's `Started` ends with either `Finished` or `Failed`,
+/// with any number of `Progress` events in between. Each URL that's tried is
+/// a separate download, so looking for a file that's only on the last of
+/// several servers fails several times before it starts for real.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum FetchEvent {
+    /// We're asking for the symbol file at `url`.
+    Started { url: String },
+    /// More of the symbol file has arrived: `downloaded` bytes so far, out of
+    /// `total`, if the server said how big it is.
+    Progress {
+        url: String,
+        downloaded: u64,
+        total: Option<u64>,
+    },
+    /// The whole symbol file arrived, and was parsed.
+    Finished { url: String, downloaded: u64 },
+    /// The server didn't have the symbol file, or the download failed, or the
+    /// symbol file couldn't be parsed. `missing` is set only if the server
+    /// answered that it doesn't have the file, so a server that's down (or
+    /// serving garbage) can be told apart from an ordinary miss.
+    Failed {
+        url: String,
+        error: String,
+        missing: bool,
+    },
 }
 
+#[cfg(not(target_arch = "wasm32"))]
 impl HttpSymbolSupplier {
     /// Create a new `HttpSymbolSupplier`.
     ///
@@ -366,7 +603,7 @@ impl HttpSymbolSupplier {
         urls: Vec<String>,
         cache: PathBuf,
         tmp: PathBuf,
-        mut local_paths: Vec<PathBuf>,
+        local_paths: Vec<PathBuf>,
         timeout: Duration,
     ) -> HttpSymbolSupplier {
         let client = Client::builder().timeout(timeout).build().unwrap();
@@ -379,18 +616,91 @@ impl HttpSymbolSupplier {
                 Url::parse(&u).ok()
             })
             .collect();
-        local_paths.push(cache.clone());
-        let local = SimpleSymbolSupplier::new(local_paths);
+        let local = SimpleSymbolSupplier::new(local_paths).with_cache(cache.clone());
         HttpSymbolSupplier {
             client,
             urls,
             local,
             cache,
             tmp,
+            in_flight: Mutex::new(HashMap::new()),
+            on_fetch: Arc::new(|_: &FetchEvent| ()),
+            retrier: Retrier::default(),
         }
     }
+
+    /// Retry failed downloads, and stop asking servers that keep failing,
+    /// according to `policy`.
+    pub fn with_retry_policy(mut self, policy: RetryPolicy) -> HttpSymbolSupplier {
+        self.retrier = Retrier::new(policy);
+        self
+    }
+
+    /// Call `on_fetch` with the progress of each symbol file download, e.g.
+    /// to drive a progress bar or collect telemetry.
+    ///
+    /// This may be called from several downloads at once.
+    pub fn with_fetch_callback(
+        mut self,
+        on_fetch: impl Fn(&FetchEvent) + Send + Sync + 'static,
+    ) -> HttpSymbolSupplier {
+        self.on_fetch = Arc::new(on_fetch);
+        self
+    }
+
+    /// Fetch `rel_path` from the first of our urls that has it, unless
+    /// someone else is already doing that, in which case wait for them to
+    /// finish and use their copy from the cache.
+    async fn fetch_deduplicated(
+        &self,
+        module: &(dyn Module + Sync),
+        rel_path: &str,
+    ) -> Result<SymbolFile, SymbolError> {
+        let lock = self
+            .in_flight
+            .lock()
+            .unwrap()
+            .entry(rel_path.to_string())
+            .or_default()
+            .clone();
+        let result = {
+            let _guard = lock.lock().await;
+            // If we had to wait, whoever we waited on has probably cached it.
+            match self.local.locate_symbols(module).await {
+                Err(SymbolError::NotFound) => self.fetch_from_urls(rel_path).await,
+                local_result => local_result,
+            }
+        };
+
+        // Clean up after ourselves if no one else is waiting.
+        let mut in_flight = self.in_flight.lock().unwrap();
+        if Arc::strong_count(&lock) == 2 {
+            in_flight.remove(rel_path);
+        }
+        result
+    }
+
+    async fn fetch_from_urls(&self, rel_path: &str) -> Result<SymbolFile, SymbolError> {
+        for url in &self.urls {
+            let file = fetch_symbol_file(
+                &self.client,
+                &self.retrier,
+                url,
+                rel_path,
+                &self.cache,
+                &self.tmp,
+                &*self.on_fetch,
+            )
+            .await;
+            if let Ok(file) = file {
+                return Ok(file);
+            }
+        }
+        Err(SymbolError::NotFound)
+    }
 }
 
+#[cfg(not(target_arch = "wasm32"))]
 fn create_cache_file(tmp_path: &Path, final_path: &Path) -> io::Result<NamedTempFile> {
     // Use tempfile to save things to our cache to ensure proper
     // atomicity of writes. We may want multiple instances of rust-minidump
@@ -413,6 +723,7 @@ fn create_cache_file(tmp_path: &Path, final_path: &Path) -> io::Result<NamedTemp
     NamedTempFile::new_in(tmp_path)
 }
 
+#[cfg(not(target_arch = "wasm32"))]
 fn commit_cache_file(mut temp: NamedTempFile, final_path: &Path, url: &Url) -> io::Result<()> {
     // Append any extra metadata we also want to be cached as "INFO" lines,
     // because this is an established format that parsers will ignore the
@@ -430,14 +741,35 @@ fn commit_cache_file(mut temp: NamedTempFile, final_path: &Path, url: &Url) -> i
     Ok(())
 }
 
+#[cfg(not(target_arch = "wasm32"))]
+/// Save `symbol_file` in a binary cache alongside the text symbol file we just
+/// cached at `final_path`, so that loading it from the cache is quicker.
+fn commit_binary_cache(
+    symbol_file: &SymbolFile,
+    tmp_path: &Path,
+    final_path: &Path,
+) -> io::Result<()> {
+    let source_len = fs::metadata(final_path)?.len();
+    let cache_path = SymbolFile::binary_cache_path(final_path);
+    let mut temp = create_cache_file(tmp_path, &cache_path)?;
+    symbol_file.write_binary_cache(source_len, &mut temp)?;
+    temp.persist_noclobber(&cache_path)?;
+    Ok(())
+}
+
+#[cfg(not(target_arch = "wasm32"))]
 /// Fetch a symbol file from the URL made by combining `base_url` and `rel_path` using `client`,
-/// save the file contents under `cache` + `rel_path` and also return them.
+/// save the file contents under `cache` + `rel_path` (along with a binary cache of the parsed
+/// symbols) and also return them. The download's progress is reported to `on_fetch`.
+#[tracing::instrument(level = "debug", skip_all, fields(server = %base_url, rel_path = rel_path))]
 async fn fetch_symbol_file(
     client: &Client,
+    retrier: &Retrier,
     base_url: &Url,
     rel_path: &str,
     cache: &Path,
     tmp: &Path,
+    on_fetch: &(dyn Fn(&FetchEvent) + Send + Sync),
 ) -> Result<SymbolFile, SymbolError> {
     // This function is a bit of a complicated mess because we want to write
     // the input to our symbol cache, but we're a streaming parser. So we
@@ -451,12 +783,26 @@ async fn fetch_symbol_file(
     // First try to GET the file from a server
     let url = base_url.join(rel_path).map_err(|_| SymbolError::NotFound)?;
     debug!("Trying {}", url);
-    let res = client
-        .get(url.clone())
-        .send()
-        .await
-        .and_then(|res| res.error_for_status())
-        .map_err(|_| SymbolError::NotFound)?;
+    let failed = |error: &dyn std::fmt::Display, missing: bool| {
+        on_fetch(&FetchEvent::Failed {
+            url: url.to_string(),
+            error: error.to_string(),
+            missing,
+        })
+    };
+    on_fetch(&FetchEvent::Started {
+        url: url.to_string(),
+    });
+    let start = Instant::now();
+    let res = match retrier.send(&url, || client.get(url.clone())).await {
+        Ok(res) => res,
+        Err(e) => {
+            failed(&e, e.is_not_found());
+            return Err(SymbolError::NotFound);
+        }
+    };
+    let request_duration = start.elapsed();
+    let total = res.content_length();
 
     // Now try to create the temp cache file (not yet in the cache)
     let final_cache_path = cache.join(rel_path);
@@ -467,30 +813,60 @@ async fn fetch_symbol_file(
         .ok();
 
     // Now stream parse the file as it downloads.
-    let mut symbol_file = SymbolFile::parse_async(res, |data| {
-        // While we're downloading+parsing, save this data to the the disk cache too
-        if let Some(file) = temp.as_mut() {
-            if let Err(e) = file.write_all(data) {
-                // Give up on caching this.
-                warn!("Failed to save symbol file in local disk cache: {}", e);
-                temp = None;
+    let mut file_size = 0;
+    let mut downloaded = 0;
+    let result = SymbolFile::parse_async_with_progress(
+        res,
+        |data| {
+            file_size += data.len() as u64;
+            // While we're downloading+parsing, save this data to the the disk cache too
+            if let Some(file) = temp.as_mut() {
+                if let Err(e) = file.write_all(data) {
+                    // Give up on caching this.
+                    warn!("Failed to save symbol file in local disk cache: {}", e);
+                    temp = None;
+                }
             }
+        },
+        |bytes| {
+            downloaded = bytes;
+            on_fetch(&FetchEvent::Progress {
+                url: url.to_string(),
+                downloaded,
+                total,
+            });
+        },
+    )
+    .await;
+    let mut symbol_file = match result {
+        Ok(symbol_file) => symbol_file,
+        Err(e) => {
+            failed(&e, false);
+            return Err(e);
         }
-    })
-    .await?;
+    };
     // Make note of what URL this symbol file was downloaded from.
     symbol_file.url = Some(url.to_string());
+    symbol_file.file_size = Some(file_size);
+    symbol_file.fetch_duration = symbol_file.fetch_duration.map(|d| d + request_duration);
 
     // Try to finish the cache file and atomically swap it into the cache.
     if let Some(temp) = temp {
-        let _ = commit_cache_file(temp, &final_cache_path, &url).map_err(|e| {
-            warn!("Failed to save symbol file in local disk cache: {}", e);
-        });
+        let _ = commit_cache_file(temp, &final_cache_path, &url)
+            .and_then(|()| commit_binary_cache(&symbol_file, tmp, &final_cache_path))
+            .map_err(|e| {
+                warn!("Failed to save symbol file in local disk cache: {}", e);
+            });
     }
 
+    on_fetch(&FetchEvent::Finished {
+        url: url.to_string(),
+        downloaded,
+    });
     Ok(symbol_file)
 }
 
+#[cfg(not(target_arch = "wasm32"))]
 #[async_trait]
 impl SymbolSupplier for HttpSymbolSupplier {
     async fn locate_symbols(
@@ -505,13 +881,7 @@ impl SymbolSupplier for HttpSymbolSupplier {
         }
         // Now try urls
         if let Some(rel_path) = relative_symbol_path(module, "sym") {
-            for url in &self.urls {
-                if let Ok(file) =
-                    fetch_symbol_file(&self.client, url, &rel_path, &self.cache, &self.tmp).await
-                {
-                    return Ok(file);
-                }
-            }
+            return self.fetch_deduplicated(module, &rel_path).await;
         }
         // If we get this far, we have failed to find anything
         Err(SymbolError::NotFound)
@@ -527,6 +897,24 @@ pub trait FrameSymbolizer {
     fn set_function(&mut self, name: &str, base: u64, parameter_size: u32);
     /// Set the source file and (1-based) line number this frame represents.
     fn set_source_file(&mut self, file: &str, line: u32, base: u64);
+    /// Add a function which was inlined at this frame's instruction.
+    ///
+    /// This is called after `set_function` once for each inlined call, from
+    /// the outermost to the innermost, with the source position in the inlined
+    /// function. Frames which don't care about inlining can ignore this.
+    fn add_inline_frame(&mut self, _name: &str, _file: Option<&str>, _line: Option<u32>) {}
+    /// Set the URL the source file can be found at.
+    ///
+    /// This is called right after `set_source_file` or `add_inline_frame`, for
+    /// the source file that was just given, if the symbols know its URL.
+    fn set_source_url(&mut self, _url: &str) {}
+    /// Add another source line this frame's instruction is also from.
+    ///
+    /// Optimized code often merges the instructions of several lines. This is
+    /// called for each line past the first, for the function that was last
+    /// given to `set_source_file` or `add_inline_frame`. Frames which only want
+    /// one line can ignore this.
+    fn add_source_line(&mut self, _file: &str, _line: u32) {}
 }
 
 pub trait FrameWalker {
@@ -548,6 +936,13 @@ pub trait FrameWalker {
     fn set_cfa(&mut self, val: u64) -> Option<()>;
     /// Set whatever registers in the caller should be set based on the return address (e.g. rip).
     fn set_ra(&mut self, val: u64) -> Option<()>;
+    /// Whether `address` could plausibly be a return address (e.g. it's in a loaded module).
+    ///
+    /// This is used to search the stack when STACK WIN info points at the wrong spot.
+    /// By default every address is plausible, which disables those searches.
+    fn instruction_seems_valid(&self, _address: u64) -> bool {
+        true
+    }
 }
 
 /// A simple implementation of `FrameSymbolizer` that just holds data.
@@ -568,6 +963,15 @@ pub struct SimpleFrame {
     pub source_line: Option<u32>,
     /// The offset of the start of `source_line` from the function base.
     pub source_line_base: Option<u64>,
+    /// The URL of `source_file`, if the symbols know it.
+    pub source_url: Option<String>,
+    /// Other source lines the current instruction is also from, as the file
+    /// and line.
+    pub other_source_lines: Vec<(String, u32)>,
+    /// Functions which were inlined at `instruction`, from the outermost
+    /// to the innermost. Only `function`, `source_file`, `source_line`,
+    /// `source_url` and `other_source_lines` are filled in for these.
+    pub inlines: Vec<SimpleFrame>,
 }
 
 impl SimpleFrame {
@@ -594,6 +998,29 @@ impl FrameSymbolizer for SimpleFrame {
         self.source_line = Some(line);
         self.source_line_base = Some(base);
     }
+    fn add_inline_frame(&mut self, name: &str, file: Option<&str>, line: Option<u32>) {
+        self.inlines.push(SimpleFrame {
+            instruction: self.instruction,
+            function: Some(String::from(name)),
+            source_file: file.map(String::from),
+            source_line: line,
+            ..SimpleFrame::default()
+        });
+    }
+    fn set_source_url(&mut self, url: &str) {
+        let url = Some(String::from(url));
+        match self.inlines.last_mut() {
+            Some(inline) => inline.source_url = url,
+            None => self.source_url = url,
+        }
+    }
+    fn add_source_line(&mut self, file: &str, line: u32) {
+        let line = (String::from(file), line);
+        match self.inlines.last_mut() {
+            Some(inline) => inline.other_source_lines.push(line),
+            None => self.other_source_lines.push(line),
+        }
+    }
 }
 
 // Can't make Module derive Hash, since then it can't be used as a trait
@@ -633,11 +1060,61 @@ pub struct Symbolizer {
     /// Symbol supplier for locating symbols.
     supplier: Box<dyn SymbolSupplier + Send + Sync + 'static>,
     /// Cache of symbol locating results.
-    // TODO?: use lru-cache: https://crates.io/crates/lru-cache/
-    // note that using an lru-cache would mess up the fact that we currently
-    // use this for statistics collection. Splitting out statistics would be
-    // way messier but not impossible.
-    symbols: Mutex<HashMap<ModuleKey, Result<SymbolFile, SymbolError>>>,
+    symbols: Mutex<SymbolCache>,
+    /// How much memory (roughly) the symbols kept in `symbols` may use.
+    memory_budget: Option<usize>,
+}
+
+/// What a `Symbolizer` knows about the symbols of the modules it's seen.
+#[derive(Default)]
+struct SymbolCache {
+    modules: HashMap<ModuleKey, CachedSymbols>,
+    /// The total `memory_usage` of the loaded symbols.
+    resident_bytes: usize,
+    /// Ticks every time symbols are used, to find the least recently used.
+    clock: u64,
+}
+
+enum CachedSymbols {
+    /// Symbols that are in memory.
+    Loaded {
+        symbols: Box<SymbolFile>,
+        size: usize,
+        last_used: u64,
+    },
+    /// Symbols that were dropped to stay within the memory budget, and will
+    /// be located again if they're needed.
+    Evicted(SymbolStats),
+    /// Symbols that couldn't be located. These stay that way.
+    Failed(SymbolError),
+}
+
+impl SymbolCache {
+    /// Drop the least recently used symbols (other than `keep`'s) until the
+    /// loaded symbols fit in `budget`.
+    fn evict(&mut self, budget: usize, keep: &ModuleKey) {
+        while self.resident_bytes > budget {
+            let lru = self
+                .modules
+                .iter()
+                .filter(|(k, _)| *k != keep)
+                .filter_map(|(k, cached)| match cached {
+                    CachedSymbols::Loaded { last_used, .. } => Some((*last_used, k)),
+                    _ => None,
+                })
+                .min();
+            let k = match lru {
+                Some((_, k)) => k.clone(),
+                None => break,
+            };
+            if let Some(CachedSymbols::Loaded { symbols, size, .. }) = self.modules.remove(&k) {
+                debug!("Evicting symbols for {} ({} bytes)", k.0, size);
+                self.resident_bytes -= size;
+                self.modules
+                    .insert(k, CachedSymbols::Evicted(SymbolStats::loaded(&symbols)));
+            }
+        }
+    }
 }
 
 impl Symbolizer {
@@ -645,10 +1122,24 @@ impl Symbolizer {
     pub fn new<T: SymbolSupplier + Send + Sync + 'static>(supplier: T) -> Symbolizer {
         Symbolizer {
             supplier: Box::new(supplier),
-            symbols: Mutex::new(HashMap::new()),
+            symbols: Mutex::new(SymbolCache::default()),
+            memory_budget: None,
         }
     }
 
+    /// Keep the memory used by loaded symbols to roughly `max_resident_bytes`
+    /// (see [`SymbolFile::memory_usage`]).
+    ///
+    /// By default, symbols are kept in memory for as long as the `Symbolizer`
+    /// lives. With a budget, the least recently used symbols are dropped to
+    /// make room for new ones, and located and parsed again if they're needed
+    /// later. The symbols being used are always kept, even if they're bigger
+    /// than the whole budget.
+    pub fn with_memory_budget(mut self, max_resident_bytes: usize) -> Symbolizer {
+        self.memory_budget = Some(max_resident_bytes);
+        self
+    }
+
     /// Helper method for non-minidump-using callers.
     ///
     /// Pass `debug_file` and `debug_id` describing a specific module,
@@ -669,7 +1160,6 @@ impl Symbolizer {
         self.fill_symbol(&k, &mut frame).await.ok()?;
         frame.function
     }
-
     /// Fill symbol information in `frame` using the instruction address
     /// from `frame`, and the module information from `module`. If you're not
     /// using a minidump module, you can use [`SimpleModule`][simplemodule] and
@@ -707,16 +1197,43 @@ impl Symbolizer {
         module: &(dyn Module + Sync),
         frame: &mut (dyn FrameSymbolizer + Send),
     ) -> Result<(), FillSymbolError> {
-        let k = key(module);
-        self.ensure_module(module, &k).await;
-
-        // Symbols will always contain an entry after ensure_module (though it may be an Err).
-        self.symbols.lock().unwrap()[&k]
-            .as_ref()
-            .map(|sym| {
+        self.with_symbols(module, |symbols| match symbols {
+            Some(sym) => {
                 sym.fill_symbol(module, frame);
+                Ok(())
+            }
+            None => Err(FillSymbolError {}),
+        })
+        .await
+    }
+
+    /// Locate the symbols for all of `modules` ahead of time, with up to
+    /// `concurrency` of them being located at once.
+    ///
+    /// Symbols are otherwise located the first time a module is needed, so a
+    /// stackwalk that touches many modules waits on every download in turn.
+    /// Prefetching downloads them side by side instead, at the cost of also
+    /// getting the symbols of modules the stackwalk won't need.
+    ///
+    /// Modules whose symbols were already located (or couldn't be) are
+    /// skipped. With a memory budget (see [`Symbolizer::with_memory_budget`])
+    /// prefetched symbols may be evicted before they're used, so the budget
+    /// should have room for them.
+    pub async fn prefetch<'m>(
+        &self,
+        modules: impl IntoIterator<Item = &'m (dyn Module + Sync)>,
+        concurrency: usize,
+    ) {
+        let mut seen = HashSet::new();
+        let modules = modules
+            .into_iter()
+            .map(|module| (key(module), module))
+            .filter(|(k, _)| seen.insert(k.clone()));
+        stream::iter(modules)
+            .for_each_concurrent(concurrency.max(1), |(k, module)| async move {
+                drop(self.ensure_module(module, &k).await);
             })
-            .map_err(|_| FillSymbolError {})
+            .await;
     }
 
     /// Collect various statistics on the symbols.
@@ -726,26 +1243,20 @@ impl Symbolizer {
         self.symbols
             .lock()
             .unwrap()
+            .modules
             .iter()
-            .map(|(k, res)| {
-                let mut stats = SymbolStats::default();
-                match res {
-                    Ok(sym) => {
-                        stats.symbol_url = sym.url.clone();
-                        stats.loaded_symbols = true;
-                        stats.corrupt_symbols = false;
-                    }
-                    Err(SymbolError::NotFound) => {
-                        stats.loaded_symbols = false;
-                    }
-                    Err(SymbolError::LoadError(_)) => {
-                        stats.loaded_symbols = false;
-                    }
-                    Err(SymbolError::ParseError(..)) => {
-                        stats.loaded_symbols = true;
-                        stats.corrupt_symbols = true;
-                    }
-                }
+            .map(|(k, cached)| {
+                let stats = match cached {
+                    CachedSymbols::Loaded { symbols, .. } => SymbolStats::loaded(symbols),
+                    CachedSymbols::Evicted(stats) => stats.clone(),
+                    CachedSymbols::Failed(SymbolError::NotFound)
+                    | CachedSymbols::Failed(SymbolError::LoadError(_)) => SymbolStats::default(),
+                    CachedSymbols::Failed(SymbolError::ParseError(..)) => SymbolStats {
+                        loaded_symbols: true,
+                        corrupt_symbols: true,
+                        ..SymbolStats::default()
+                    },
+                };
                 (leafname(&k.0).to_string(), stats)
             })
             .collect()
@@ -759,25 +1270,98 @@ impl Symbolizer {
         module: &(dyn Module + Sync),
         walker: &mut (dyn FrameWalker + Send),
     ) -> Option<()> {
+        self.with_symbols(module, |symbols| match symbols {
+            Some(sym) => {
+                trace!("unwind: found symbols for address, searching for cfi entries");
+                sym.walk_frame(module, walker)
+            }
+            None => {
+                trace!("unwind: couldn't find symbols for address, cannot use cfi");
+                None
+            }
+        })
+        .await
+    }
+
+    /// Call `f` with `module`'s symbols (or `None` if there aren't any),
+    /// locating them first if need be.
+    async fn with_symbols<T>(
+        &self,
+        module: &(dyn Module + Sync),
+        f: impl FnOnce(Option<&SymbolFile>) -> T,
+    ) -> T {
         let k = key(module);
-        self.ensure_module(module, &k).await;
-        if let Some(Ok(ref sym)) = self.symbols.lock().unwrap().get(&k) {
-            trace!("unwind: found symbols for address, searching for cfi entries");
-            sym.walk_frame(module, walker)
-        } else {
-            trace!("unwind: couldn't find symbols for address, cannot use cfi");
-            None
+        // The entry can't be evicted while we hold the lock ensure_module
+        // checked or filled it in under.
+        let mut cache = self.ensure_module(module, &k).await;
+        cache.clock += 1;
+        let now = cache.clock;
+        match cache.modules.get_mut(&k) {
+            Some(CachedSymbols::Loaded {
+                symbols, last_used, ..
+            }) => {
+                *last_used = now;
+                f(Some(symbols))
+            }
+            Some(CachedSymbols::Evicted(_)) => unreachable!("ensure_module loads evicted symbols"),
+            Some(CachedSymbols::Failed(_)) | None => f(None),
         }
     }
 
     /// Ensures there is an entry in the `symbols` map for the given key
     /// (although it may be an Error). Will not change the entry if it already
-    /// exists (so if they first time we look is an Error, it always will be).
-    async fn ensure_module(&self, module: &(dyn Module + Sync), k: &ModuleKey) {
-        if !self.symbols.lock().unwrap().contains_key(k) {
-            let res = self.supplier.locate_symbols(module).await;
-            self.symbols.lock().unwrap().insert(k.clone(), res);
+    /// exists (so if they first time we look is an Error, it always will be),
+    /// unless the symbols were evicted, in which case they're located again.
+    ///
+    /// Returns the lock on the cache, so the entry stays as it is for as long
+    /// as the caller holds it.
+    async fn ensure_module(
+        &self,
+        module: &(dyn Module + Sync),
+        k: &ModuleKey,
+    ) -> MutexGuard<'_, SymbolCache> {
+        {
+            let cache = self.symbols.lock().unwrap();
+            if !matches!(cache.modules.get(k), None | Some(CachedSymbols::Evicted(_))) {
+                return cache;
+            }
+        }
+        let code_file = module.code_file();
+        let debug_id = module.debug_identifier();
+        let span = debug_span!(
+            "fetch_symbols",
+            module = leafname(&code_file),
+            debug_id = debug_id.as_deref().unwrap_or(""),
+            found = field::Empty,
+        );
+        let res = self
+            .supplier
+            .locate_symbols(module)
+            .instrument(span.clone())
+            .await;
+        span.record("found", &res.is_ok());
+        let mut cache = self.symbols.lock().unwrap();
+        cache.clock += 1;
+        let cached = match res {
+            Ok(symbols) => {
+                let size = symbols.memory_usage();
+                cache.resident_bytes += size;
+                CachedSymbols::Loaded {
+                    symbols: Box::new(symbols),
+                    size,
+                    last_used: cache.clock,
+                }
+            }
+            Err(e) => CachedSymbols::Failed(e),
+        };
+        // Someone else may have beaten us to it.
+        if let Some(CachedSymbols::Loaded { size, .. }) = cache.modules.insert(k.clone(), cached) {
+            cache.resident_bytes -= size;
+        }
+        if let Some(budget) = self.memory_budget {
+            cache.evict(budget, k);
         }
+        cache
     }
 }
 
@@ -858,6 +1442,34 @@ mod test {
             ..SimpleModule::default()
         };
         assert!(relative_symbol_path(&bad3, "sym").is_none());
+
+        // Without debug information, fall back to the code file and id.
+        let code = SimpleModule {
+            code_file: Some("C:\\Windows\\System32\\foo.dll".to_string()),
+            code_identifier: Some("5CF4C1A6171000".to_string()),
+            ..SimpleModule::default()
+        };
+        assert_eq!(
+            &relative_symbol_path(&code, "sym").unwrap(),
+            "foo.dll/5CF4C1A6171000/foo.sym"
+        );
+
+        let code2 = SimpleModule {
+            code_file: Some("foo".to_string()),
+            code_identifier: Some("abcd1234".to_string()),
+            ..SimpleModule::default()
+        };
+        assert_eq!(
+            &relative_symbol_path(&code2, "sym").unwrap(),
+            "foo/abcd1234/foo.sym"
+        );
+
+        // Partial debug information doesn't fall back.
+        let bad4 = SimpleModule {
+            debug_file: Some("foo.pdb".to_string()),
+            ..code
+        };
+        assert!(relative_symbol_path(&bad4, "sym").is_none());
     }
 
     #[tokio::test]
@@ -965,6 +1577,328 @@ mod test {
         );
     }
 
+    #[cfg(feature = "compressed-symbols")]
+    fn gzip(contents: &[u8]) -> Vec<u8> {
+        let mut encoder = flate2::write::GzEncoder::new(vec![], flate2::Compression::default());
+        encoder.write_all(contents).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_lazy_symbol_files() {
+        // Lazily parsed symbols give the same answers as fully parsed ones.
+        let paths = vec![PathBuf::from("../testdata/symbols/")];
+        let full = Symbolizer::new(SimpleSymbolSupplier::new(paths.clone()));
+        let lazy = Symbolizer::new(SimpleSymbolSupplier::new(paths).with_lazy_parsing(0));
+        let m = SimpleModule::new("test_app.pdb", "5A9832E5287241C1838ED98914E9B7FF1");
+        let mut found = 0;
+        for address in (0..0x30000).step_by(0x11) {
+            let mut full_frame = SimpleFrame::with_instruction(address);
+            let mut lazy_frame = SimpleFrame::with_instruction(address);
+            let full_result = full.fill_symbol(&m, &mut full_frame).await;
+            let lazy_result = lazy.fill_symbol(&m, &mut lazy_frame).await;
+            assert_eq!(full_result.is_ok(), lazy_result.is_ok());
+            assert_eq!(full_frame.function, lazy_frame.function, "{:#x}", address);
+            assert_eq!(full_frame.function_base, lazy_frame.function_base);
+            assert_eq!(full_frame.parameter_size, lazy_frame.parameter_size);
+            assert_eq!(full_frame.source_file, lazy_frame.source_file);
+            assert_eq!(full_frame.source_line, lazy_frame.source_line);
+            assert_eq!(full_frame.source_line_base, lazy_frame.source_line_base);
+            found += full_frame.function.is_some() as usize;
+        }
+        assert!(found > 100);
+
+        // And they're much smaller.
+        let stats = |symbolizer: &Symbolizer| symbolizer.symbols.lock().unwrap().resident_bytes;
+        assert!(stats(&lazy) * 4 < stats(&full));
+    }
+
+    #[cfg(feature = "compressed-symbols")]
+    #[tokio::test]
+    async fn test_compressed_symbol_files() {
+        let t = tempfile::tempdir().unwrap();
+        let supplier = SimpleSymbolSupplier::new(vec![t.path().to_path_buf()]);
+        let contents = b"MODULE Linux x86 abcd1234 foo
+FILE 1 foo.c
+FUNC 1000 30 10 some func
+1000 30 100 1
+";
+
+        // With an extra extension, or without.
+        let m = SimpleModule::new("foo.pdb", "abcd1234");
+        write_symbol_file(
+            &t.path().join("foo.pdb/abcd1234/foo.sym.gz"),
+            &gzip(contents),
+        );
+        let symbols = supplier.locate_symbols(&m).await.unwrap();
+        assert!(symbols.functions.get(0x1010).is_some());
+        let m = SimpleModule::new("bar.pdb", "abcd1234");
+        write_symbol_file(&t.path().join("bar.pdb/abcd1234/bar.sym"), &gzip(contents));
+        let symbols = supplier.locate_symbols(&m).await.unwrap();
+        assert!(symbols.functions.get(0x1010).is_some());
+
+        // zstd works the same way.
+        let m = SimpleModule::new("baz.pdb", "abcd1234");
+        write_symbol_file(
+            &t.path().join("baz.pdb/abcd1234/baz.sym.zst"),
+            &zstd::encode_all(&contents[..], 0).unwrap(),
+        );
+        let symbols = supplier.locate_symbols(&m).await.unwrap();
+        assert!(symbols.functions.get(0x1010).is_some());
+        let m = SimpleModule::new("qux.pdb", "abcd1234");
+        write_symbol_file(
+            &t.path().join("qux.pdb/abcd1234/qux.sym"),
+            &zstd::encode_all(&contents[..], 0).unwrap(),
+        );
+        let symbols = supplier.locate_symbols(&m).await.unwrap();
+        assert!(symbols.functions.get(0x1010).is_some());
+
+        // A broken zstd file is an error, not a missing one.
+        let m = SimpleModule::new("quux.pdb", "abcd1234");
+        write_symbol_file(
+            &t.path().join("quux.pdb/abcd1234/quux.sym"),
+            b"\x28\xb5\x2f\xfd and so on",
+        );
+        assert!(supplier.locate_symbols(&m).await.is_err());
+    }
+
+    #[cfg(feature = "compressed-symbols")]
+    #[tokio::test]
+    async fn test_http_compressed_symbol_file() {
+        let contents = b"MODULE Linux x86 abcd1234 foo
+FILE 1 foo.c
+FUNC 1000 30 10 some func
+1000 30 100 1
+";
+        let zstd = zstd::encode_all(&contents[..], 0).unwrap();
+        for body in [gzip(contents), zstd] {
+            let body = Box::leak(body.into_boxed_slice());
+            let (base, _requests) = serve_file("/symbols/foo.pdb/abcd1234/foo.sym", body).await;
+            let cache = tempfile::tempdir().unwrap();
+            let tmp = tempfile::tempdir().unwrap();
+            let supplier = HttpSymbolSupplier::new(
+                vec![format!("{}/symbols", base)],
+                cache.path().to_path_buf(),
+                tmp.path().to_path_buf(),
+                vec![],
+                Duration::from_secs(10),
+            );
+
+            let m = SimpleModule::new("foo.pdb", "abcd1234");
+            let symbols = supplier.locate_symbols(&m).await.unwrap();
+            assert!(symbols.functions.get(0x1010).is_some());
+            // The cache has the decompressed symbol file.
+            let cached = fs::read(cache.path().join("foo.pdb/abcd1234/foo.sym")).unwrap();
+            assert!(cached.starts_with(contents));
+        }
+    }
+
+    /// An ELF module, which (unlike SimpleModule) has a build id.
+    pub(crate) struct ElfModule {
+        pub(crate) name: &'static str,
+        pub(crate) build_id: &'static str,
+    }
+
+    impl Module for ElfModule {
+        fn base_address(&self) -> u64 {
+            0
+        }
+        fn size(&self) -> u64 {
+            0
+        }
+        fn code_file(&self) -> Cow<'_, str> {
+            Cow::Borrowed(self.name)
+        }
+        fn code_identifier(&self) -> Cow<'_, str> {
+            Cow::Borrowed(self.build_id)
+        }
+        fn debug_file(&self) -> Option<Cow<'_, str>> {
+            Some(Cow::Borrowed(self.name))
+        }
+        fn debug_identifier(&self) -> Option<Cow<'_, str>> {
+            Some(Cow::Borrowed("ABCD12340"))
+        }
+        fn version(&self) -> Option<Cow<'_, str>> {
+            None
+        }
+    }
+
+    /// Serve `body` at `served_path` (and 404 everything else), slowly,
+    /// counting requests.
+    pub(crate) async fn serve_file(
+        served_path: &'static str,
+        body: &'static [u8],
+    ) -> (String, Arc<Mutex<Vec<String>>>) {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        let requests = Arc::new(Mutex::new(vec![]));
+        let server_requests = requests.clone();
+        tokio::spawn(async move {
+            loop {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let requests = server_requests.clone();
+                tokio::spawn(async move {
+                    let mut request = vec![];
+                    let mut buf = [0; 1024];
+                    while !request.ends_with(b"\r\n\r\n") {
+                        let len = socket.read(&mut buf).await.unwrap();
+                        if len == 0 {
+                            return;
+                        }
+                        request.extend_from_slice(&buf[..len]);
+                    }
+                    let request = String::from_utf8(request).unwrap();
+                    let path = request.split(' ').nth(1).unwrap().to_string();
+                    requests.lock().unwrap().push(path.clone());
+                    tokio::time::sleep(Duration::from_millis(100)).await;
+
+                    let (status, body) = if path == served_path {
+                        ("200 OK", body)
+                    } else {
+                        ("404 Not Found", &b""[..])
+                    };
+                    let head = format!(
+                        "HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                        status,
+                        body.len()
+                    );
+                    socket.write_all(head.as_bytes()).await.unwrap();
+                    socket.write_all(body).await.unwrap();
+                });
+            }
+        });
+        (base, requests)
+    }
+
+    #[tokio::test]
+    async fn test_http_symbol_supplier() {
+        const SYMBOLS: &[u8] = b"MODULE Linux x86 abcd1234 foo
+FILE 1 foo.c
+FUNC 1000 30 10 some func
+1000 30 100 1
+";
+        let (base, requests) = serve_file("/symbols/foo.pdb/abcd1234/foo.sym", SYMBOLS).await;
+        let cache = tempfile::tempdir().unwrap();
+        let tmp = tempfile::tempdir().unwrap();
+        let supplier = HttpSymbolSupplier::new(
+            vec![format!("{}/missing", base), format!("{}/symbols", base)],
+            cache.path().to_path_buf(),
+            tmp.path().to_path_buf(),
+            vec![],
+            Duration::from_secs(10),
+        );
+
+        // Ask for the same module twice at once: only one of them should
+        // actually go looking for it.
+        let m = SimpleModule::new("foo.pdb", "abcd1234");
+        let (first, second) =
+            tokio::join!(supplier.locate_symbols(&m), supplier.locate_symbols(&m));
+        let first = first.unwrap();
+        let second = second.unwrap();
+        assert_eq!(
+            *requests.lock().unwrap(),
+            vec![
+                "/missing/foo.pdb/abcd1234/foo.sym",
+                "/symbols/foo.pdb/abcd1234/foo.sym"
+            ]
+        );
+        let url = format!("{}/symbols/foo.pdb/abcd1234/foo.sym", base);
+        assert_eq!(first.url.as_deref(), Some(&*url));
+        assert_eq!(second.url.as_deref(), Some(&*url));
+        assert!(cache.path().join("foo.pdb/abcd1234/foo.sym").is_file());
+        assert!(cache
+            .path()
+            .join("foo.pdb/abcd1234/foo.sym.cache")
+            .is_file());
+        assert!(supplier.in_flight.lock().unwrap().is_empty());
+
+        assert!(first.fetch_duration.is_some());
+        assert!(first.parse_duration.is_some());
+        assert_eq!(first.file_size, Some(SYMBOLS.len() as u64));
+        assert_eq!(first.path, None);
+
+        // Now it's cached.
+        let cached = supplier.locate_symbols(&m).await.unwrap();
+        assert_eq!(cached.functions, first.functions);
+        assert_eq!(cached.url, first.url);
+        assert!(cached.cache_hit);
+        assert_eq!(cached.fetch_duration, None);
+        let sym_path = cache.path().join("foo.pdb/abcd1234/foo.sym");
+        assert_eq!(cached.path, Some(SymbolFile::binary_cache_path(&sym_path)));
+        assert_eq!(requests.lock().unwrap().len(), 2);
+
+        // The text symbol file is still used if the binary cache is bad.
+        fs::write(SymbolFile::binary_cache_path(&sym_path), b"junk").unwrap();
+        let cached = supplier.locate_symbols(&m).await.unwrap();
+        assert_eq!(cached.functions, first.functions);
+        assert!(cached.cache_hit);
+        assert_eq!(cached.path, Some(sym_path));
+        assert_eq!(requests.lock().unwrap().len(), 2);
+
+        // Things that aren't there are looked for everywhere.
+        let missing = SimpleModule::new("bar.pdb", "ffff0000");
+        assert_eq!(
+            supplier.locate_symbols(&missing).await,
+            Err(SymbolError::NotFound)
+        );
+        assert_eq!(requests.lock().unwrap().len(), 4);
+    }
+
+    #[tokio::test]
+    async fn test_http_fetch_events() {
+        const SYMBOLS: &[u8] = b"MODULE Linux x86 abcd1234 foo
+FUNC 1000 30 10 some func
+";
+        let (base, _requests) = serve_file("/symbols/foo.pdb/abcd1234/foo.sym", SYMBOLS).await;
+        let cache = tempfile::tempdir().unwrap();
+        let tmp = tempfile::tempdir().unwrap();
+        let events = Arc::new(Mutex::new(vec![]));
+        let supplier_events = events.clone();
+        let supplier = HttpSymbolSupplier::new(
+            vec![format!("{}/missing", base), format!("{}/symbols", base)],
+            cache.path().to_path_buf(),
+            tmp.path().to_path_buf(),
+            vec![],
+            Duration::from_secs(10),
+        )
+        .with_fetch_callback(move |event| supplier_events.lock().unwrap().push(event.clone()));
+
+        let m = SimpleModule::new("foo.pdb", "abcd1234");
+        supplier.locate_symbols(&m).await.unwrap();
+
+        let missing = format!("{}/missing/foo.pdb/abcd1234/foo.sym", base);
+        let found = format!("{}/symbols/foo.pdb/abcd1234/foo.sym", base);
+        let events = events.lock().unwrap();
+        assert_eq!(
+            events[0],
+            FetchEvent::Started {
+                url: missing.clone()
+            }
+        );
+        assert!(matches!(
+            &events[1],
+            FetchEvent::Failed { url, missing: true, .. } if *url == missing
+        ));
+        assert_eq!(events[2], FetchEvent::Started { url: found.clone() });
+        assert_eq!(
+            events[events.len() - 2],
+            FetchEvent::Progress {
+                url: found.clone(),
+                downloaded: SYMBOLS.len() as u64,
+                total: Some(SYMBOLS.len() as u64),
+            }
+        );
+        assert_eq!(
+            events[events.len() - 1],
+            FetchEvent::Finished {
+                url: found,
+                downloaded: SYMBOLS.len() as u64,
+            }
+        );
+    }
+
     #[tokio::test]
     async fn test_symbolizer() {
         let t = tempfile::tempdir().unwrap();
@@ -989,6 +1923,7 @@ FUNC 1000 30 10 some func
         assert_eq!(f1.source_file.unwrap(), "foo.c");
         assert_eq!(f1.source_line.unwrap(), 100);
         assert_eq!(f1.source_line_base.unwrap(), 0x1000);
+        assert!(f1.inlines.is_empty());
 
         assert_eq!(
             symbolizer
@@ -1026,4 +1961,172 @@ FUNC 1000 30 10 another func
             .await
             .is_none());
     }
+
+    /// A `StringSymbolSupplier` that counts how many times it's asked for
+    /// each module.
+    struct CountingSupplier {
+        inner: StringSymbolSupplier,
+        located: Arc<Mutex<HashMap<String, usize>>>,
+    }
+
+    #[async_trait]
+    impl SymbolSupplier for CountingSupplier {
+        async fn locate_symbols(
+            &self,
+            module: &(dyn Module + Sync),
+        ) -> Result<SymbolFile, SymbolError> {
+            *self
+                .located
+                .lock()
+                .unwrap()
+                .entry(module.code_file().into_owned())
+                .or_default() += 1;
+            self.inner.locate_symbols(module).await
+        }
+    }
+
+    #[tokio::test]
+    async fn test_boxed_symbol_supplier() {
+        let mut modules = HashMap::new();
+        modules.insert(
+            String::from("foo.pdb"),
+            String::from("MODULE Linux x86 abcd1234 foo\nFUNC 1000 30 10 some func\n"),
+        );
+        let supplier: Box<dyn SymbolSupplier + Send + Sync> =
+            Box::new(StringSymbolSupplier::new(modules));
+        let symbolizer = Symbolizer::new(supplier);
+        let module = SimpleModule {
+            code_file: Some(String::from("foo.pdb")),
+            ..SimpleModule::new("foo.pdb", "abcd1234")
+        };
+        let mut frame = SimpleFrame::with_instruction(0x1010);
+        symbolizer.fill_symbol(&module, &mut frame).await.unwrap();
+        assert_eq!(frame.function.unwrap(), "some func");
+    }
+
+    #[tokio::test]
+    async fn test_symbolizer_memory_budget() {
+        let mut modules = HashMap::new();
+        for name in &["a", "b", "c"] {
+            modules.insert(
+                name.to_string(),
+                format!(
+                    "MODULE Linux x86 abcd1234 {0}\nFUNC 1000 30 10 {0} func\n",
+                    name
+                ),
+            );
+        }
+        let one_module = SymbolFile::from_bytes(modules["a"].as_bytes())
+            .unwrap()
+            .memory_usage();
+        let located = Arc::new(Mutex::new(HashMap::new()));
+        let supplier = CountingSupplier {
+            inner: StringSymbolSupplier::new(modules),
+            located: located.clone(),
+        };
+        // Room for two modules.
+        let symbolizer = Symbolizer::new(supplier).with_memory_budget(one_module * 2);
+
+        let lookup = |name: &'static str| {
+            let symbolizer = &symbolizer;
+            async move {
+                let module = SimpleModule {
+                    code_file: Some(name.to_string()),
+                    ..SimpleModule::new(name, "abcd1234")
+                };
+                let mut frame = SimpleFrame::with_instruction(0x1010);
+                symbolizer.fill_symbol(&module, &mut frame).await.unwrap();
+                assert_eq!(frame.function.unwrap(), format!("{} func", name));
+            }
+        };
+        let located_count = |name: &str| located.lock().unwrap()[name];
+
+        lookup("a").await;
+        lookup("b").await;
+        lookup("a").await;
+        // b is the least recently used, so it makes way for c.
+        lookup("c").await;
+        lookup("a").await;
+        assert_eq!(located_count("a"), 1);
+        lookup("b").await;
+        assert_eq!(located_count("b"), 2);
+        // Which made way for c.
+        lookup("c").await;
+        assert_eq!(located_count("c"), 2);
+        assert_eq!(located_count("a"), 1);
+
+        // Evicted symbols still count as loaded.
+        let stats = symbolizer.stats();
+        assert_eq!(stats.len(), 3);
+        assert!(stats.values().all(|s| s.loaded_symbols));
+        assert!(symbolizer.symbols.lock().unwrap().resident_bytes <= one_module * 2);
+    }
+
+    #[tokio::test]
+    async fn test_symbolizer_prefetch() {
+        let mut modules = HashMap::new();
+        for name in &["a", "b"] {
+            modules.insert(
+                name.to_string(),
+                format!(
+                    "MODULE Linux x86 abcd1234 {0}\nFUNC 1000 30 10 {0} func\n",
+                    name
+                ),
+            );
+        }
+        let located = Arc::new(Mutex::new(HashMap::new()));
+        let supplier = CountingSupplier {
+            inner: StringSymbolSupplier::new(modules),
+            located: located.clone(),
+        };
+        let symbolizer = Symbolizer::new(supplier);
+        let module = |name: &str| SimpleModule {
+            code_file: Some(name.to_string()),
+            ..SimpleModule::new(name, "abcd1234")
+        };
+        let (a, b, c) = (module("a"), module("b"), module("c"));
+
+        // Duplicates are only located once.
+        let prefetched: [&(dyn Module + Sync); 4] = [&a, &b, &a, &c];
+        symbolizer.prefetch(prefetched.iter().copied(), 2).await;
+        assert_eq!(located.lock().unwrap().len(), 3);
+        assert!(located.lock().unwrap().values().all(|&count| count == 1));
+
+        // Lookups use what was prefetched, including the failures.
+        let mut frame = SimpleFrame::with_instruction(0x1010);
+        symbolizer.fill_symbol(&b, &mut frame).await.unwrap();
+        assert_eq!(frame.function.unwrap(), "b func");
+        let mut frame = SimpleFrame::with_instruction(0x1010);
+        assert!(symbolizer.fill_symbol(&c, &mut frame).await.is_err());
+        symbolizer.prefetch(prefetched.iter().copied(), 2).await;
+        assert!(located.lock().unwrap().values().all(|&count| count == 1));
+    }
+
+    #[tokio::test]
+    async fn test_symbolizer_stats() {
+        let t = tempfile::tempdir().unwrap();
+        let sym_path = t.path().join("foo.pdb/abcd1234/foo.sym");
+        write_good_symbol_file(&sym_path);
+        let supplier = SimpleSymbolSupplier::new(vec![t.path().to_path_buf()]);
+        let symbolizer = Symbolizer::new(supplier);
+        let m = SimpleModule {
+            code_file: Some("foo.dll".to_string()),
+            ..SimpleModule::new("foo.pdb", "abcd1234")
+        };
+        let mut frame = SimpleFrame::with_instruction(0x1010);
+        symbolizer.fill_symbol(&m, &mut frame).await.unwrap();
+
+        let stats = symbolizer.stats();
+        let stats = &stats["foo.dll"];
+        assert!(stats.loaded_symbols);
+        assert_eq!(stats.symbol_url, None);
+        assert_eq!(
+            stats.symbol_file_size,
+            Some(fs::metadata(&sym_path).unwrap().len())
+        );
+        assert_eq!(stats.symbol_path, Some(sym_path));
+        assert_eq!(stats.fetch_duration, None);
+        assert!(stats.parse_duration.is_some());
+        assert!(!stats.cache_hit);
+    }
 }
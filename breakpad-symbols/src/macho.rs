@@ -0,0 +1,467 @@
+//! Getting symbols from dSYM bundles and Mach-O files on local disk.
+//!
+//! Xcode leaves the debug information of everything it builds in dSYM bundles
+//! (`Foo.app.dSYM/Contents/Resources/DWARF/Foo`), which are Mach-O files with
+//! just the DWARF in them. Modules in macOS and iOS minidumps are identified by
+//! the UUID of their Mach-O file, which the dSYM shares, so dSYMs can be
+//! matched up with modules without trusting their names.
+//!
+//! Like the other local suppliers, the matching file is turned into a Breakpad
+//! symbol file by a [`SymbolConverter`]. Both [dump_syms] and `DwarfConverter`
+//! (with the `dwarf-converter` feature) understand dSYMs and plain Mach-O files
+//! with symbol tables.
+//!
+//! [dump_syms]: https://github.com/mozilla/dump_syms
+
+use async_trait::async_trait;
+use reqwest::Url;
+use tracing::{debug, warn};
+
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::convert::{cache_symbols, module_id};
+use crate::{
+    leafname, relative_symbol_path, Module, SimpleSymbolSupplier, SymbolConverter, SymbolError,
+    SymbolFile, SymbolSupplier,
+};
+
+const MH_MAGIC: u32 = 0xfeedface;
+const MH_MAGIC_64: u32 = 0xfeedfacf;
+const FAT_MAGIC: u32 = 0xcafebabe;
+const FAT_MAGIC_64: u32 = 0xcafebabf;
+const LC_UUID: u32 = 0x1b;
+
+/// Don't believe Mach-O headers with more load commands than this.
+const MAX_LOAD_COMMANDS_SIZE: u32 = 1 << 20;
+
+/// An implementation of `SymbolSupplier` that converts dSYMs and Mach-O files
+/// found on local disk to Breakpad symbols.
+///
+/// Its paths are searched (recursively) for dSYM bundles, which are matched
+/// up with modules by UUID. After that, Mach-O files are searched for at
+/// `<debug file>/<debug identifier>/<debug file>` in each of its paths, and
+/// finally the module's own file is tried.
+///
+/// Converted symbols are saved in the cache in the usual Breakpad layout
+/// (see [`relative_symbol_path`]), so they're only converted once.
+pub struct MachoSymbolSupplier {
+    /// Local disk paths in which to search for dSYMs and Mach-O files.
+    paths: Vec<PathBuf>,
+    /// The DWARF files of the dSYMs in `paths`, by UUID. Built on first use.
+    dsyms: Mutex<Option<HashMap<String, PathBuf>>>,
+    /// A `SimpleSymbolSupplier` for the symbols we've already converted.
+    local: SimpleSymbolSupplier,
+    /// A path at which to cache converted symbols.
+    cache: PathBuf,
+    /// A path to a temporary location where converted symbols can be written.
+    tmp: PathBuf,
+    /// The thing that turns Mach-O files into symbol files.
+    converter: Box<dyn SymbolConverter + Send + Sync>,
+}
+
+impl MachoSymbolSupplier {
+    /// Create a new `MachoSymbolSupplier`.
+    ///
+    /// Symbols will be searched for in `cache` first, then dSYMs and Mach-O
+    /// files will be searched for in `paths` (e.g. Xcode's `Archives` or
+    /// `DerivedData` directories), and converted with `converter`.
+    pub fn new<C: SymbolConverter + Send + Sync + 'static>(
+        paths: Vec<PathBuf>,
+        cache: PathBuf,
+        tmp: PathBuf,
+        converter: C,
+    ) -> MachoSymbolSupplier {
+        let local = SimpleSymbolSupplier::new(vec![]).with_cache(cache.clone());
+        MachoSymbolSupplier {
+            paths,
+            dsyms: Mutex::new(None),
+            local,
+            cache,
+            tmp,
+            converter: Box::new(converter),
+        }
+    }
+
+    /// The places `module`'s Mach-O file might be, best first.
+    fn candidates(&self, module: &(dyn Module + Sync), uuid: &str) -> Vec<PathBuf> {
+        let mut candidates = vec![];
+        {
+            let mut dsyms = self.dsyms.lock().unwrap();
+            let dsyms = dsyms.get_or_insert_with(|| index_dsyms(&self.paths));
+            candidates.extend(dsyms.get(uuid).cloned());
+        }
+        if let (Some(debug_file), Some(debug_id)) = (module.debug_file(), module.debug_identifier())
+        {
+            let leaf = leafname(&debug_file);
+            candidates.extend(
+                self.paths
+                    .iter()
+                    .map(|path| path.join(leaf).join(&*debug_id).join(leaf))
+                    .filter(|path| path.is_file()),
+            );
+        }
+        // The module's own file is only any use if it's a full path on this
+        // machine, and the same build.
+        let code_file = PathBuf::from(&*module.code_file());
+        if code_file.is_absolute()
+            && code_file.is_file()
+            && macho_uuids(&code_file).iter().any(|u| u == uuid)
+        {
+            candidates.push(code_file);
+        }
+        candidates
+    }
+}
+
+/// Get the UUID (in the form used in debug identifiers) of a Mac module.
+fn module_uuid(module: &(dyn Module + Sync)) -> Option<String> {
+    let debug_file = module.debug_file()?;
+    if leafname(&debug_file).to_lowercase().ends_with(".pdb") {
+        return None;
+    }
+    // Mac debug identifiers are the UUID followed by an age of 0.
+    let debug_id = module.debug_identifier()?;
+    if debug_id.len() != 33
+        || !debug_id.ends_with('0')
+        || !debug_id.chars().all(|c| c.is_ascii_hexdigit())
+    {
+        return None;
+    }
+    Some(debug_id[..32].to_uppercase())
+}
+
+/// Find the DWARF files of all the dSYMs under `paths`, by UUID.
+fn index_dsyms(paths: &[PathBuf]) -> HashMap<String, PathBuf> {
+    let mut dsyms = HashMap::new();
+    let mut dirs = paths.to_vec();
+    while let Some(dir) = dirs.pop() {
+        let entries = match fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(_) => continue,
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if !matches!(entry.file_type(), Ok(t) if t.is_dir()) {
+                continue;
+            }
+            if path.extension() == Some("dSYM".as_ref()) {
+                let dwarf_dir = path.join("Contents/Resources/DWARF");
+                for dwarf in fs::read_dir(&dwarf_dir).into_iter().flatten().flatten() {
+                    let dwarf = dwarf.path();
+                    for uuid in macho_uuids(&dwarf) {
+                        dsyms.entry(uuid).or_insert_with(|| dwarf.clone());
+                    }
+                }
+            } else {
+                dirs.push(path);
+            }
+        }
+    }
+    debug!("Found {} dSYMs", dsyms.len());
+    dsyms
+}
+
+/// Get the UUIDs of every architecture in the Mach-O file at `path`.
+fn macho_uuids(path: &Path) -> Vec<String> {
+    let result = File::open(path).and_then(|mut file| read_uuids(&mut file));
+    match result {
+        Ok(uuids) => uuids,
+        Err(e) => {
+            if e.kind() != io::ErrorKind::InvalidData {
+                warn!("Couldn't read {}: {}", path.display(), e);
+            }
+            vec![]
+        }
+    }
+}
+
+fn read_u32(file: &mut (impl Read + Seek), big_endian: bool) -> io::Result<u32> {
+    let mut buf = [0; 4];
+    file.read_exact(&mut buf)?;
+    Ok(if big_endian {
+        u32::from_be_bytes(buf)
+    } else {
+        u32::from_le_bytes(buf)
+    })
+}
+
+fn not_macho() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, "not a Mach-O file")
+}
+
+/// Read the UUIDs out of a (possibly fat) Mach-O file.
+fn read_uuids(file: &mut (impl Read + Seek)) -> io::Result<Vec<String>> {
+    // Fat headers are always big-endian.
+    let magic = read_u32(file, true)?;
+    if magic != FAT_MAGIC && magic != FAT_MAGIC_64 {
+        return Ok(read_uuid(file, 0)?.into_iter().collect());
+    }
+    let count = read_u32(file, true)?;
+    let mut offsets = vec![];
+    for _ in 0..count.min(64) {
+        // cputype, cpusubtype, then the offset.
+        file.seek(SeekFrom::Current(8))?;
+        if magic == FAT_MAGIC {
+            offsets.push(u64::from(read_u32(file, true)?));
+            // size, align
+            file.seek(SeekFrom::Current(8))?;
+        } else {
+            let high = u64::from(read_u32(file, true)?);
+            let low = u64::from(read_u32(file, true)?);
+            offsets.push(high << 32 | low);
+            // size, align, reserved
+            file.seek(SeekFrom::Current(16))?;
+        }
+    }
+    let mut uuids = vec![];
+    for offset in offsets {
+        uuids.extend(read_uuid(file, offset)?);
+    }
+    Ok(uuids)
+}
+
+/// Read the UUID out of the (thin) Mach-O file at `offset`.
+fn read_uuid(file: &mut (impl Read + Seek), offset: u64) -> io::Result<Option<String>> {
+    file.seek(SeekFrom::Start(offset))?;
+    let magic = read_u32(file, false)?;
+    let (big_endian, is_64) = match magic {
+        MH_MAGIC => (false, false),
+        MH_MAGIC_64 => (false, true),
+        _ if magic.swap_bytes() == MH_MAGIC => (true, false),
+        _ if magic.swap_bytes() == MH_MAGIC_64 => (true, true),
+        _ => return Err(not_macho()),
+    };
+    // cputype, cpusubtype, filetype
+    file.seek(SeekFrom::Current(12))?;
+    let ncmds = read_u32(file, big_endian)?;
+    let sizeofcmds = read_u32(file, big_endian)?;
+    if sizeofcmds > MAX_LOAD_COMMANDS_SIZE {
+        return Err(not_macho());
+    }
+    // flags (and reserved)
+    let header_size = if is_64 { 32 } else { 28 };
+    file.seek(SeekFrom::Start(offset + header_size))?;
+
+    let mut cmds = vec![0; sizeofcmds as usize];
+    file.read_exact(&mut cmds)?;
+    let read = |bytes: &[u8]| {
+        let bytes = [bytes[0], bytes[1], bytes[2], bytes[3]];
+        if big_endian {
+            u32::from_be_bytes(bytes)
+        } else {
+            u32::from_le_bytes(bytes)
+        }
+    };
+    let mut pos = 0;
+    for _ in 0..ncmds {
+        if pos + 8 > cmds.len() {
+            break;
+        }
+        let cmd = read(&cmds[pos..]);
+        let cmdsize = read(&cmds[pos + 4..]) as usize;
+        if cmd == LC_UUID && cmdsize >= 24 && pos + 24 <= cmds.len() {
+            let uuid = cmds[pos + 8..pos + 24]
+                .iter()
+                .map(|b| format!("{:02X}", b))
+                .collect();
+            return Ok(Some(uuid));
+        }
+        if cmdsize < 8 {
+            break;
+        }
+        pos += cmdsize;
+    }
+    Ok(None)
+}
+
+#[async_trait]
+impl SymbolSupplier for MachoSymbolSupplier {
+    async fn locate_symbols(
+        &self,
+        module: &(dyn Module + Sync),
+    ) -> Result<SymbolFile, SymbolError> {
+        let uuid = module_uuid(module).ok_or(SymbolError::NotFound)?;
+        let sym_path = relative_symbol_path(module, "sym").ok_or(SymbolError::NotFound)?;
+
+        // Check for symbols we've already converted first.
+        let local_result = self.local.locate_symbols(module).await;
+        if !matches!(local_result, Err(SymbolError::NotFound)) {
+            return local_result;
+        }
+
+        let debug_id = format!("{}0", uuid);
+        for macho in self.candidates(module, &uuid) {
+            let symbols = self.converter.convert_module(&macho, module).await?;
+            // Fat files have several architectures in them, and the converter
+            // may not have picked the one we wanted.
+            if !matches!(module_id(&symbols), Some(id) if id.eq_ignore_ascii_case(&debug_id)) {
+                debug!("{} is not the right build of {}", macho.display(), sym_path);
+                continue;
+            }
+            let mut symbol_file = SymbolFile::from_bytes(&symbols)?;
+            let url = Url::from_file_path(&macho).ok();
+            symbol_file.url = url.as_ref().map(Url::to_string);
+            if let Some(url) = url {
+                cache_symbols(&symbols, &self.cache, &self.tmp, &sym_path, &url);
+            }
+            return Ok(symbol_file);
+        }
+        Err(SymbolError::NotFound)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::SimpleModule;
+    use std::io::Cursor;
+    use std::sync::Arc;
+
+    const UUID: &str = "0123456789ABCDEF0123456789ABCDEF";
+    const OTHER_UUID: &str = "FEDCBA9876543210FEDCBA9876543210";
+
+    /// Make a thin 64-bit Mach-O file with the given UUID.
+    fn thin(uuid: &str, big_endian: bool) -> Vec<u8> {
+        let u32_bytes = |v: u32| {
+            if big_endian {
+                v.to_be_bytes()
+            } else {
+                v.to_le_bytes()
+            }
+        };
+        let mut file = vec![];
+        // magic, cputype, cpusubtype, filetype, ncmds, sizeofcmds, flags,
+        // reserved
+        for v in &[MH_MAGIC_64, 0x01000007, 3, 0xa, 2, 40, 0, 0] {
+            file.extend_from_slice(&u32_bytes(*v));
+        }
+        // Something that isn't the UUID.
+        file.extend_from_slice(&u32_bytes(0x2));
+        file.extend_from_slice(&u32_bytes(16));
+        file.extend_from_slice(&[0; 8]);
+        file.extend_from_slice(&u32_bytes(LC_UUID));
+        file.extend_from_slice(&u32_bytes(24));
+        for i in 0..16 {
+            file.push(u8::from_str_radix(&uuid[i * 2..i * 2 + 2], 16).unwrap());
+        }
+        file
+    }
+
+    /// Make a fat Mach-O file out of thin ones, each in its own page.
+    fn fat(thins: &[Vec<u8>]) -> Vec<u8> {
+        let mut file = vec![];
+        file.extend_from_slice(&FAT_MAGIC.to_be_bytes());
+        file.extend_from_slice(&(thins.len() as u32).to_be_bytes());
+        for (i, thin) in thins.iter().enumerate() {
+            let offset = 0x1000 * (i as u32 + 1);
+            for v in &[0x01000007, 3, offset, thin.len() as u32, 12] {
+                file.extend_from_slice(&u32::to_be_bytes(*v));
+            }
+        }
+        for (i, thin) in thins.iter().enumerate() {
+            file.resize(0x1000 * (i + 1), 0);
+            file.extend_from_slice(thin);
+        }
+        file
+    }
+
+    #[test]
+    fn test_read_uuids() {
+        let uuids = |file: Vec<u8>| read_uuids(&mut Cursor::new(file)).unwrap();
+        assert_eq!(uuids(thin(UUID, false)), vec![UUID]);
+        assert_eq!(uuids(thin(UUID, true)), vec![UUID]);
+        assert_eq!(
+            uuids(fat(&[thin(UUID, false), thin(OTHER_UUID, false)])),
+            vec![UUID, OTHER_UUID]
+        );
+        assert!(read_uuids(&mut Cursor::new(b"\x7fELF and so on".to_vec())).is_err());
+        assert!(read_uuids(&mut Cursor::new(thin(UUID, false)[..40].to_vec())).is_err());
+    }
+
+    #[test]
+    fn test_module_uuid() {
+        let module = SimpleModule::new("Foo", &format!("{}0", UUID));
+        assert_eq!(module_uuid(&module).as_deref(), Some(UUID));
+        let module = SimpleModule::new("foo.pdb", &format!("{}1", UUID));
+        assert_eq!(module_uuid(&module), None);
+        let module = SimpleModule::new("libfoo.so", "ABCD1234");
+        assert_eq!(module_uuid(&module), None);
+    }
+
+    /// Pretends every Mach-O file is a build of `Foo` with `UUID`, and
+    /// remembers what it saw.
+    #[derive(Default)]
+    struct TestConverter {
+        seen: Mutex<Vec<PathBuf>>,
+    }
+
+    #[async_trait]
+    impl SymbolConverter for Arc<TestConverter> {
+        async fn convert(&self, macho: &Path) -> Result<Vec<u8>, SymbolError> {
+            self.seen.lock().unwrap().push(macho.to_path_buf());
+            Ok(format!(
+                "MODULE mac x86_64 {}0 Foo\nFUNC 1000 30 10 some func\n",
+                UUID
+            )
+            .into_bytes())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_macho_symbol_supplier() {
+        let archives = tempfile::tempdir().unwrap();
+        let cache = tempfile::tempdir().unwrap();
+        let tmp = tempfile::tempdir().unwrap();
+        let dwarf_dir = archives
+            .path()
+            .join("Foo 2022-02-08/Foo.xcarchive/dSYMs/Foo.app.dSYM/Contents/Resources/DWARF");
+        fs::create_dir_all(&dwarf_dir).unwrap();
+        // The dSYM's name doesn't matter, its UUID does.
+        let dsym = dwarf_dir.join("Bar");
+        fs::write(&dsym, fat(&[thin(OTHER_UUID, false), thin(UUID, false)])).unwrap();
+
+        let converter = Arc::new(TestConverter::default());
+        let supplier = MachoSymbolSupplier::new(
+            vec![archives.path().to_path_buf()],
+            cache.path().to_path_buf(),
+            tmp.path().to_path_buf(),
+            converter.clone(),
+        );
+
+        let module = SimpleModule::new("Foo", &format!("{}0", UUID));
+        let symbols = supplier.locate_symbols(&module).await.unwrap();
+        assert!(symbols.functions.get(0x1010).is_some());
+        assert_eq!(
+            symbols.url,
+            Some(Url::from_file_path(&dsym).unwrap().to_string())
+        );
+
+        // Converted once, then cached.
+        assert!(cache
+            .path()
+            .join(format!("Foo/{}0/Foo.sym", UUID))
+            .is_file());
+        supplier.locate_symbols(&module).await.unwrap();
+        assert_eq!(*converter.seen.lock().unwrap(), vec![dsym.clone()]);
+
+        // The converter didn't produce the architecture we wanted.
+        let module = SimpleModule::new("Foo", &format!("{}0", OTHER_UUID));
+        assert_eq!(
+            supplier.locate_symbols(&module).await,
+            Err(SymbolError::NotFound)
+        );
+        assert_eq!(converter.seen.lock().unwrap().len(), 2);
+
+        // No dSYM for it.
+        let module = SimpleModule::new("Foo", &format!("{}0", "0".repeat(31) + "1"));
+        assert_eq!(
+            supplier.locate_symbols(&module).await,
+            Err(SymbolError::NotFound)
+        );
+        assert_eq!(converter.seen.lock().unwrap().len(), 2);
+    }
+}
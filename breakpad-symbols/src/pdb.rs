@@ -0,0 +1,192 @@
+//! Getting symbols from PDBs on local disk.
+//!
+//! Windows builds leave PDBs lying around: next to the binaries, in a local
+//! symbol store (in the "symsrv" layout, `<name>.pdb/<debug identifier>/<name>.pdb`),
+//! or at the path recorded in the module itself. Rather than requiring
+//! Breakpad symbol files to be generated for all of them ahead of time, this
+//! finds the PDB for a module when it's needed and converts it with a
+//! [`SymbolConverter`].
+//!
+//! The conversion (functions, lines, and FPO/frame data for unwinding) is done
+//! by the converter: [`PdbConverter`][crate::PdbConverter] (with the
+//! `pdb-converter` feature) reads the PDB in-process, and
+//! [`DumpSymsConverter`][crate::DumpSymsConverter] runs [dump_syms].
+//!
+//! [dump_syms]: https://github.com/mozilla/dump_syms
+
+use async_trait::async_trait;
+use reqwest::Url;
+
+use std::path::PathBuf;
+
+use crate::convert::cache_symbols;
+use crate::{
+    leafname, relative_symbol_path, Module, SimpleSymbolSupplier, SymbolConverter, SymbolError,
+    SymbolFile, SymbolSupplier,
+};
+
+/// An implementation of `SymbolSupplier` that converts PDBs found on local
+/// disk to Breakpad symbols.
+///
+/// For each of its paths, the PDB is searched for in the symsrv layout. After
+/// that, the path of the PDB recorded in the module is tried, for symbolicating
+/// on the machine that built it.
+///
+/// Converted symbols are saved in the cache in the usual Breakpad layout
+/// (see [`relative_symbol_path`]), so they're only converted once.
+pub struct PdbSymbolSupplier {
+    /// Local disk paths in which to search for PDBs.
+    paths: Vec<PathBuf>,
+    /// A `SimpleSymbolSupplier` for the symbols we've already converted.
+    local: SimpleSymbolSupplier,
+    /// A path at which to cache converted symbols.
+    cache: PathBuf,
+    /// A path to a temporary location where converted symbols can be written.
+    tmp: PathBuf,
+    /// The thing that turns PDBs into symbol files.
+    converter: Box<dyn SymbolConverter + Send + Sync>,
+}
+
+impl PdbSymbolSupplier {
+    /// Create a new `PdbSymbolSupplier`.
+    ///
+    /// Symbols will be searched for in `cache` first, then PDBs will be
+    /// searched for in `paths`, and converted with `converter`.
+    pub fn new<C: SymbolConverter + Send + Sync + 'static>(
+        paths: Vec<PathBuf>,
+        cache: PathBuf,
+        tmp: PathBuf,
+        converter: C,
+    ) -> PdbSymbolSupplier {
+        let local = SimpleSymbolSupplier::new(vec![]).with_cache(cache.clone());
+        PdbSymbolSupplier {
+            paths,
+            local,
+            cache,
+            tmp,
+            converter: Box::new(converter),
+        }
+    }
+
+    /// Find `module`'s PDB on disk.
+    fn find_pdb(&self, module: &(dyn Module + Sync)) -> Option<PathBuf> {
+        let debug_file = module.debug_file()?;
+        let debug_id = module.debug_identifier()?;
+        let leaf = leafname(&debug_file);
+        if !leaf.to_lowercase().ends_with(".pdb") {
+            return None;
+        }
+        // The recorded path is only any use if it's a full path on this
+        // machine.
+        let recorded = Some(PathBuf::from(&*debug_file)).filter(|path| path.is_absolute());
+        self.paths
+            .iter()
+            .map(|path| path.join(leaf).join(&*debug_id).join(leaf))
+            .chain(recorded)
+            .find(|path| path.is_file())
+    }
+}
+
+#[async_trait]
+impl SymbolSupplier for PdbSymbolSupplier {
+    async fn locate_symbols(
+        &self,
+        module: &(dyn Module + Sync),
+    ) -> Result<SymbolFile, SymbolError> {
+        let sym_path = relative_symbol_path(module, "sym").ok_or(SymbolError::NotFound)?;
+
+        // Check for symbols we've already converted first.
+        let local_result = self.local.locate_symbols(module).await;
+        if !matches!(local_result, Err(SymbolError::NotFound)) {
+            return local_result;
+        }
+
+        let pdb = self.find_pdb(module).ok_or(SymbolError::NotFound)?;
+        let symbols = self.converter.convert_module(&pdb, module).await?;
+        let mut symbol_file = SymbolFile::from_bytes(&symbols)?;
+        let url = Url::from_file_path(&pdb).ok();
+        symbol_file.url = url.as_ref().map(Url::to_string);
+        if let Some(url) = url {
+            cache_symbols(&symbols, &self.cache, &self.tmp, &sym_path, &url);
+        }
+        Ok(symbol_file)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::SimpleModule;
+    use std::path::Path;
+    use std::sync::{Arc, Mutex};
+
+    const SYMBOLS: &[u8] = b"MODULE windows x86 ABCD1234 foo.pdb
+FILE 1 foo.c
+FUNC 1000 30 10 some func
+1000 30 100 1
+";
+
+    /// Pretends the PDB is already a symbol file, and remembers what it saw.
+    #[derive(Default)]
+    struct TestConverter {
+        seen: Mutex<Vec<PathBuf>>,
+    }
+
+    #[async_trait]
+    impl SymbolConverter for Arc<TestConverter> {
+        async fn convert(&self, pdb: &Path) -> Result<Vec<u8>, SymbolError> {
+            self.seen.lock().unwrap().push(pdb.to_path_buf());
+            Ok(std::fs::read(pdb)?)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_pdb_symbol_supplier() {
+        let store = tempfile::tempdir().unwrap();
+        let build = tempfile::tempdir().unwrap();
+        let cache = tempfile::tempdir().unwrap();
+        let tmp = tempfile::tempdir().unwrap();
+        let stored_pdb = store.path().join("foo.pdb/ABCD1234/foo.pdb");
+        std::fs::create_dir_all(stored_pdb.parent().unwrap()).unwrap();
+        std::fs::write(&stored_pdb, SYMBOLS).unwrap();
+        let built_pdb = build.path().join("bar.pdb");
+        std::fs::write(&built_pdb, SYMBOLS).unwrap();
+
+        let converter = Arc::new(TestConverter::default());
+        let supplier = PdbSymbolSupplier::new(
+            vec![store.path().to_path_buf()],
+            cache.path().to_path_buf(),
+            tmp.path().to_path_buf(),
+            converter.clone(),
+        );
+
+        // From the symbol store.
+        let m = SimpleModule::new("c:\\build\\foo.pdb", "ABCD1234");
+        let symbols = supplier.locate_symbols(&m).await.unwrap();
+        assert!(symbols.functions.get(0x1010).is_some());
+        assert_eq!(
+            symbols.url,
+            Some(Url::from_file_path(&stored_pdb).unwrap().to_string())
+        );
+
+        // Converted once, then cached.
+        assert!(cache.path().join("foo.pdb/ABCD1234/foo.sym").is_file());
+        supplier.locate_symbols(&m).await.unwrap();
+        assert_eq!(*converter.seen.lock().unwrap(), vec![stored_pdb]);
+
+        // From where the module says its PDB is.
+        let m = SimpleModule::new(built_pdb.to_str().unwrap(), "ABCD1234");
+        supplier.locate_symbols(&m).await.unwrap();
+        assert_eq!(converter.seen.lock().unwrap()[1], built_pdb);
+
+        // Not found, or not a PDB.
+        for m in &[
+            SimpleModule::new("baz.pdb", "ABCD1234"),
+            SimpleModule::new("foo.pdb", "FFFF0000"),
+            SimpleModule::new("libfoo.so", "ABCD1234"),
+        ] {
+            assert_eq!(supplier.locate_symbols(m).await, Err(SymbolError::NotFound));
+        }
+        assert_eq!(converter.seen.lock().unwrap().len(), 2);
+    }
+}
@@ -0,0 +1,211 @@
+//! Converting PDBs to Breakpad symbols in-process.
+//!
+//! [`PdbConverter`] reads PDBs with the [`pdb`] crate rather than running
+//! dump_syms, so it can be used with [`PdbSymbolSupplier`][crate::PdbSymbolSupplier]
+//! and [`MicrosoftSymbolSupplier`][crate::MicrosoftSymbolSupplier] on machines
+//! that don't have dump_syms.
+//!
+//! This needs the `pdb-converter` feature.
+
+use async_trait::async_trait;
+use pdb::{FallibleIterator, FrameType, MachineType, PdbInternalSectionOffset, SymbolData, PDB};
+
+use std::fs::File;
+use std::io;
+use std::path::Path;
+
+use crate::convert::{FunctionRecord, LineRecord, SymbolWriter};
+use crate::{leafname, Module, SymbolConverter, SymbolError};
+
+/// Line numbers the compiler uses for code that has no line of its own.
+const HIDDEN_LINES: &[u32] = &[0xf00f00, 0xfeefee];
+
+/// A [`SymbolConverter`] that reads PDBs in-process, with the [`pdb`] crate.
+///
+/// It writes out functions and their lines, public symbols, and the FPO and
+/// frame data of 32-bit x86 modules (as `STACK WIN` records). Unlike dump_syms
+/// it doesn't look for the module's PE file, so there's no unwind info for
+/// x86-64 modules (which keep theirs in the PE file), and the names of public
+/// symbols are left mangled.
+#[derive(Debug, Clone, Default)]
+pub struct PdbConverter {}
+
+impl PdbConverter {
+    /// Create a new `PdbConverter`.
+    pub fn new() -> PdbConverter {
+        PdbConverter {}
+    }
+}
+
+#[async_trait]
+impl SymbolConverter for PdbConverter {
+    async fn convert(&self, debug_file: &Path) -> Result<Vec<u8>, SymbolError> {
+        let name = debug_file
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        convert_in_background(debug_file, name).await
+    }
+
+    async fn convert_module(
+        &self,
+        debug_file: &Path,
+        module: &(dyn Module + Sync),
+    ) -> Result<Vec<u8>, SymbolError> {
+        // The PDB may have been downloaded to a temporary file, so the name
+        // the module knows it by is better.
+        match module.debug_file() {
+            Some(name) => convert_in_background(debug_file, leafname(&name).to_owned()).await,
+            None => self.convert(debug_file).await,
+        }
+    }
+}
+
+/// Convert the PDB at `path` (which is called `name`) on a blocking thread.
+async fn convert_in_background(path: &Path, name: String) -> Result<Vec<u8>, SymbolError> {
+    let path = path.to_path_buf();
+    tokio::task::spawn_blocking(move || convert_pdb(&path, &name))
+        .await
+        .map_err(io::Error::other)?
+        .map_err(|e| SymbolError::LoadError(io::Error::new(io::ErrorKind::InvalidData, e)))
+}
+
+/// Turn the PDB at `path` into a Breakpad symbol file.
+fn convert_pdb(path: &Path, name: &str) -> pdb::Result<Vec<u8>> {
+    let mut pdb = PDB::open(File::open(path)?)?;
+    let info = pdb.pdb_information()?;
+    let dbi = pdb.debug_information()?;
+    let address_map = pdb.address_map()?;
+    // Only needed for file names and frame data programs, which aren't
+    // worth failing over.
+    let strings = pdb.string_table().ok();
+    let rva = |offset: PdbInternalSectionOffset| offset.to_rva(&address_map).map(|rva| rva.0);
+
+    let arch = match dbi.machine_type()? {
+        MachineType::X86 => "x86",
+        MachineType::Amd64 => "x86_64",
+        MachineType::Arm | MachineType::ArmNT | MachineType::Thumb => "arm",
+        MachineType::Arm64 => "arm64",
+        _ => "unknown",
+    };
+    // Breakpad uses the age in the DBI stream, which is the one the PE file
+    // refers to.
+    let age = dbi.age().unwrap_or(info.age);
+    let id = format!("{:X}{:X}", info.guid.simple(), age);
+
+    let mut symbols = SymbolWriter::default();
+    let mut modules = dbi.modules()?;
+    while let Some(module) = modules.next()? {
+        let module_info = match pdb.module_info(&module)? {
+            Some(module_info) => module_info,
+            None => continue,
+        };
+        let line_program = module_info.line_program().ok();
+        let mut module_symbols = module_info.symbols()?;
+        while let Some(symbol) = module_symbols.next()? {
+            let procedure = match symbol.parse() {
+                Ok(SymbolData::Procedure(procedure)) => procedure,
+                _ => continue,
+            };
+            let address = match rva(procedure.offset) {
+                Some(address) => address,
+                None => continue,
+            };
+            let end = address + procedure.len;
+
+            let mut lines = vec![];
+            if let Some(ref line_program) = line_program {
+                let mut line_infos = line_program.lines_for_symbol(procedure.offset);
+                while let Some(line_info) = line_infos.next()? {
+                    if HIDDEN_LINES.contains(&line_info.line_start) {
+                        continue;
+                    }
+                    let line_address = match rva(line_info.offset) {
+                        Some(line_address) if line_address < end => line_address,
+                        _ => continue,
+                    };
+                    let file = line_program
+                        .get_file_info(line_info.file_index)
+                        .ok()
+                        .and_then(|file| file.name.to_string_lossy(strings.as_ref()?).ok())
+                        .map(|file| symbols.file(&file))
+                        .unwrap_or(0);
+                    lines.push((line_address, line_info.length, line_info.line_start, file));
+                }
+            }
+            // Lines without a length run until the next one.
+            lines.sort_by_key(|&(line_address, ..)| line_address);
+            let next_addresses = lines
+                .iter()
+                .skip(1)
+                .map(|&(line_address, ..)| line_address)
+                .chain(Some(end))
+                .collect::<Vec<_>>();
+            let lines = lines
+                .into_iter()
+                .zip(next_addresses)
+                .map(|((line_address, length, line, file), next)| LineRecord {
+                    address: u64::from(line_address),
+                    size: u64::from(length.unwrap_or(next - line_address)),
+                    line,
+                    file,
+                })
+                .collect();
+
+            symbols.add_function(FunctionRecord {
+                address: u64::from(address),
+                size: u64::from(procedure.len),
+                parameter_size: 0,
+                name: procedure.name.to_string().into_owned(),
+                lines,
+            });
+        }
+    }
+
+    let globals = pdb.global_symbols()?;
+    let mut globals = globals.iter();
+    while let Some(symbol) = globals.next()? {
+        if let Ok(SymbolData::Public(public)) = symbol.parse() {
+            if public.function || public.code {
+                if let Some(address) = rva(public.offset) {
+                    symbols.add_public(u64::from(address), 0, public.name.to_string().into_owned());
+                }
+            }
+        }
+    }
+
+    // Only 32-bit x86 PDBs have frame data.
+    if let Ok(frame_table) = pdb.frame_table() {
+        let mut frames = frame_table.iter();
+        while let Some(frame) = frames.next()? {
+            if !matches!(frame.ty, FrameType::FPO | FrameType::FrameData) {
+                continue;
+            }
+            let address = match frame.code_start.to_rva(&address_map) {
+                Some(address) => address.0,
+                None => continue,
+            };
+            let program = frame
+                .program
+                .and_then(|program| program.to_string_lossy(strings.as_ref()?).ok());
+            let last = match program {
+                Some(program) => format!("1 {}", program),
+                None => format!("0 {}", u8::from(frame.uses_base_pointer)),
+            };
+            symbols.add_stack(format!(
+                "WIN {:x} {:x} {:x} {:x} 0 {:x} {:x} {:x} {:x} {}",
+                frame.ty as u8,
+                address,
+                frame.code_size,
+                frame.prolog_size,
+                frame.params_size,
+                frame.saved_regs_size,
+                frame.locals_size,
+                frame.max_stack_size.unwrap_or(0),
+                last
+            ));
+        }
+    }
+
+    Ok(symbols.finish("windows", arch, &id, name))
+}
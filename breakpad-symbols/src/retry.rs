@@ -0,0 +1,314 @@
+//! Retrying failed downloads, and giving up on servers that keep failing.
+//!
+//! Every remote symbol supplier sends its requests through a [`Retrier`],
+//! configured with a [`RetryPolicy`]. A request that fails in a way that
+//! might go away (the server couldn't be reached, took too long, or answered
+//! with a server error) is tried again after a backoff. A server that fails
+//! too many times in a row is left alone for a while (its "circuit breaker"
+//! opens), so one broken host doesn't make every lookup wait out its timeout.
+
+use reqwest::{RequestBuilder, Response, StatusCode, Url};
+use tracing::{debug, warn};
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// How a remote symbol supplier deals with failed requests.
+///
+/// A request is retried if the server couldn't be reached, the request timed
+/// out (see the `timeout` each supplier is created with, which applies to
+/// every attempt separately), or the server answered with a server error
+/// (5xx) or 429 Too Many Requests. Any other answer, like a 404, means the
+/// server doesn't have the file, and isn't retried.
+///
+/// The default is to never retry and never give up on a server, which is
+/// what suppliers did before this was configurable.
+#[derive(Clone, Debug)]
+pub struct RetryPolicy {
+    /// How many more times to try a request after it fails.
+    pub retries: u32,
+    /// How long to wait before the first retry. Each retry after that waits
+    /// twice as long as the one before it, up to `max_backoff`.
+    pub backoff: Duration,
+    /// The longest to wait before a retry.
+    pub max_backoff: Duration,
+    /// After this many failed requests in a row (retries included), stop
+    /// sending requests to a server for `cooldown`. `None` never gives up.
+    pub max_consecutive_failures: Option<u32>,
+    /// How long to leave a server alone once it's failed too often. After
+    /// that, the next request is sent, and the server is left alone again
+    /// right away if it fails too.
+    pub cooldown: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> RetryPolicy {
+        RetryPolicy {
+            retries: 0,
+            backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(30),
+            max_consecutive_failures: None,
+            cooldown: Duration::from_secs(60),
+        }
+    }
+}
+
+/// Why a request didn't produce the file.
+#[derive(Debug)]
+pub(crate) enum FetchError {
+    /// The server answered with this (non-success) status.
+    Status(StatusCode),
+    /// The request itself failed.
+    Request(reqwest::Error),
+    /// The server has failed too often recently, so it wasn't asked.
+    CircuitOpen,
+}
+
+impl fmt::Display for FetchError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FetchError::Status(status) => write!(f, "HTTP status {}", status),
+            FetchError::Request(e) => e.fmt(f),
+            FetchError::CircuitOpen => write!(f, "server has been failing, skipped"),
+        }
+    }
+}
+
+impl FetchError {
+    /// Whether this failure might go away if the request is sent again.
+    fn is_transient(&self) -> bool {
+        match self {
+            FetchError::Status(status) => {
+                status.is_server_error() || *status == StatusCode::TOO_MANY_REQUESTS
+            }
+            FetchError::Request(_) => true,
+            FetchError::CircuitOpen => false,
+        }
+    }
+
+    /// Whether the server answered that it doesn't have the file.
+    pub(crate) fn is_not_found(&self) -> bool {
+        matches!(self, FetchError::Status(status) if *status == StatusCode::NOT_FOUND)
+    }
+}
+
+/// How a server has been doing lately.
+#[derive(Default)]
+struct ServerHealth {
+    /// Failed requests in a row.
+    failures: u32,
+    /// When to start sending the server requests again, if we've given up on
+    /// it for now.
+    open_until: Option<Instant>,
+}
+
+/// Sends requests according to a [`RetryPolicy`], keeping track of the
+/// health of every server it talks to.
+#[derive(Default)]
+pub(crate) struct Retrier {
+    policy: RetryPolicy,
+    /// Keyed by origin (scheme, host and port).
+    servers: Mutex<HashMap<String, ServerHealth>>,
+}
+
+impl Retrier {
+    pub(crate) fn new(policy: RetryPolicy) -> Retrier {
+        Retrier {
+            policy,
+            servers: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Send the request made by `request` for `url` until it succeeds, the
+    /// server says it doesn't have the file, or we run out of retries.
+    ///
+    /// `request` is called for every attempt.
+    pub(crate) async fn send(
+        &self,
+        url: &Url,
+        request: impl Fn() -> RequestBuilder,
+    ) -> Result<Response, FetchError> {
+        let server = url.origin().ascii_serialization();
+        let mut backoff = self.policy.backoff;
+        let mut attempt = 0;
+        loop {
+            self.check_circuit(&server)?;
+            let result = match request().send().await {
+                Ok(res) if res.status().is_success() => Ok(res),
+                Ok(res) => Err(FetchError::Status(res.status())),
+                Err(e) => Err(FetchError::Request(e)),
+            };
+            let error = match result {
+                Err(e) if e.is_transient() => e,
+                result => {
+                    self.record(&server, true);
+                    return result;
+                }
+            };
+            let gave_up = self.record(&server, false);
+            if gave_up || attempt == self.policy.retries {
+                return Err(error);
+            }
+            attempt += 1;
+            debug!("Retrying {} in {:?} after: {}", url, backoff, error);
+            tokio::time::sleep(backoff).await;
+            backoff = (backoff * 2).min(self.policy.max_backoff);
+        }
+    }
+
+    /// Fail if we've given up on `server` for now.
+    fn check_circuit(&self, server: &str) -> Result<(), FetchError> {
+        let servers = self.servers.lock().unwrap();
+        match servers.get(server).and_then(|health| health.open_until) {
+            Some(open_until) if Instant::now() < open_until => Err(FetchError::CircuitOpen),
+            _ => Ok(()),
+        }
+    }
+
+    /// Note how a request to `server` went, and return whether we've given
+    /// up on it for now.
+    fn record(&self, server: &str, succeeded: bool) -> bool {
+        let mut servers = self.servers.lock().unwrap();
+        let health = servers.entry(server.to_string()).or_default();
+        if succeeded {
+            *health = ServerHealth::default();
+            return false;
+        }
+        health.failures += 1;
+        if let Some(max_failures) = self.policy.max_consecutive_failures {
+            if health.failures >= max_failures {
+                warn!(
+                    "{} failed {} times in a row, leaving it alone for {:?}",
+                    server, health.failures, self.policy.cooldown
+                );
+                health.open_until = Some(Instant::now() + self.policy.cooldown);
+                return true;
+            }
+        }
+        false
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use reqwest::Client;
+    use std::sync::Arc;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    /// Answer requests with each of `statuses` in turn (and then the last one
+    /// forever), counting requests.
+    async fn serve_statuses(statuses: &'static [&'static str]) -> (Url, Arc<Mutex<usize>>) {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = Url::parse(&format!(
+            "http://{}/foo.sym",
+            listener.local_addr().unwrap()
+        ))
+        .unwrap();
+        let requests = Arc::new(Mutex::new(0));
+        let server_requests = requests.clone();
+        tokio::spawn(async move {
+            loop {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let mut request = vec![];
+                let mut buf = [0; 1024];
+                while !request.ends_with(b"\r\n\r\n") {
+                    let len = socket.read(&mut buf).await.unwrap();
+                    if len == 0 {
+                        break;
+                    }
+                    request.extend_from_slice(&buf[..len]);
+                }
+                let status = {
+                    let mut requests = server_requests.lock().unwrap();
+                    *requests += 1;
+                    statuses[(*requests - 1).min(statuses.len() - 1)]
+                };
+                let head = format!(
+                    "HTTP/1.1 {}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                    status
+                );
+                socket.write_all(head.as_bytes()).await.unwrap();
+            }
+        });
+        (url, requests)
+    }
+
+    fn policy(retries: u32, max_consecutive_failures: Option<u32>) -> RetryPolicy {
+        RetryPolicy {
+            retries,
+            backoff: Duration::from_millis(10),
+            max_consecutive_failures,
+            ..RetryPolicy::default()
+        }
+    }
+
+    async fn get(retrier: &Retrier, url: &Url) -> Result<Response, FetchError> {
+        let client = Client::new();
+        retrier.send(url, || client.get(url.clone())).await
+    }
+
+    #[tokio::test]
+    async fn test_retries() {
+        let (url, requests) =
+            serve_statuses(&["503 Service Unavailable", "429 Too Many Requests", "200 OK"]).await;
+        let retrier = Retrier::new(policy(2, None));
+        assert!(get(&retrier, &url).await.is_ok());
+        assert_eq!(*requests.lock().unwrap(), 3);
+
+        // Running out of retries.
+        let (url, requests) = serve_statuses(&["500 Internal Server Error", "200 OK"]).await;
+        let retrier = Retrier::new(policy(0, None));
+        assert!(matches!(
+            get(&retrier, &url).await,
+            Err(FetchError::Status(StatusCode::INTERNAL_SERVER_ERROR))
+        ));
+        assert_eq!(*requests.lock().unwrap(), 1);
+
+        // Not found isn't worth retrying.
+        let (url, requests) = serve_statuses(&["404 Not Found", "200 OK"]).await;
+        let retrier = Retrier::new(policy(2, None));
+        assert!(matches!(
+            get(&retrier, &url).await,
+            Err(FetchError::Status(StatusCode::NOT_FOUND))
+        ));
+        assert_eq!(*requests.lock().unwrap(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_circuit_breaker() {
+        let (url, requests) = serve_statuses(&["503 Service Unavailable"]).await;
+        let retrier = Retrier::new(RetryPolicy {
+            cooldown: Duration::from_millis(200),
+            ..policy(5, Some(3))
+        });
+
+        // The retries stop once the server has failed too often...
+        assert!(matches!(
+            get(&retrier, &url).await,
+            Err(FetchError::Status(StatusCode::SERVICE_UNAVAILABLE))
+        ));
+        assert_eq!(*requests.lock().unwrap(), 3);
+        // ...and so do other requests to it.
+        assert!(matches!(
+            get(&retrier, &url).await,
+            Err(FetchError::CircuitOpen)
+        ));
+        assert_eq!(*requests.lock().unwrap(), 3);
+
+        // After the cooldown it gets one more chance.
+        tokio::time::sleep(Duration::from_millis(250)).await;
+        assert!(matches!(
+            get(&retrier, &url).await,
+            Err(FetchError::Status(StatusCode::SERVICE_UNAVAILABLE))
+        ));
+        assert_eq!(*requests.lock().unwrap(), 4);
+        assert!(matches!(
+            get(&retrier, &url).await,
+            Err(FetchError::CircuitOpen)
+        ));
+        assert_eq!(*requests.lock().unwrap(), 4);
+    }
+}
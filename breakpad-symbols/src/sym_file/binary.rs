@@ -0,0 +1,503 @@
+//! A binary cache format for parsed symbol files.
+//!
+//! Parsing a big text symbol file takes a while, and processing lots of dumps
+//! against the same symbols pays for that every time. Instead, a parsed
+//! [`SymbolFile`] can be written out in this format next to the text file it
+//! came from, and loaded back (from an mmap of the cache) with little more
+//! work than copying out the strings.
+//!
+//! The format is just the contents of the `SymbolFile`'s tables, in order,
+//! with little-endian integers and length-prefixed strings and lists. The
+//! header records the size of the text file the cache was made from, so that
+//! a cache left behind by an old version of the symbols isn't used by
+//! mistake.
+//!
+//! The format has no compatibility guarantees between versions of this
+//! crate: if `VERSION` doesn't match, the cache is rejected and the text
+//! file should be parsed again.
+
+use memmap2::Mmap;
+use range_map::{Range, RangeMap};
+
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use super::types::*;
+use crate::SymbolError;
+
+const MAGIC: &[u8; 8] = b"BPSYMBIN";
+const VERSION: u32 = 3;
+
+/// Where the binary cache for the symbol file at `path` goes.
+pub(super) fn cache_path(path: &Path) -> PathBuf {
+    let mut path = path.as_os_str().to_owned();
+    path.push(".cache");
+    PathBuf::from(path)
+}
+
+fn corrupt() -> SymbolError {
+    SymbolError::ParseError("corrupt binary symbol cache", 0)
+}
+
+struct Encoder<W> {
+    out: W,
+}
+
+impl<W: Write> Encoder<W> {
+    fn u8(&mut self, val: u8) -> io::Result<()> {
+        self.out.write_all(&[val])
+    }
+
+    fn u32(&mut self, val: u32) -> io::Result<()> {
+        self.out.write_all(&val.to_le_bytes())
+    }
+
+    fn u64(&mut self, val: u64) -> io::Result<()> {
+        self.out.write_all(&val.to_le_bytes())
+    }
+
+    fn len(&mut self, len: usize) -> io::Result<()> {
+        let len = u32::try_from(len)
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "table too big to cache"))?;
+        self.u32(len)
+    }
+
+    fn str(&mut self, val: &str) -> io::Result<()> {
+        self.len(val.len())?;
+        self.out.write_all(val.as_bytes())
+    }
+
+    fn names(&mut self, names: &HashMap<u32, String>) -> io::Result<()> {
+        self.len(names.len())?;
+        for (id, name) in names {
+            self.u32(*id)?;
+            self.str(name)?;
+        }
+        Ok(())
+    }
+
+    fn range_map<V>(
+        &mut self,
+        map: &RangeMap<u64, V>,
+        mut value: impl FnMut(&mut Self, &V) -> io::Result<()>,
+    ) -> io::Result<()> {
+        let entries = map.ranges_values().as_slice();
+        self.len(entries.len())?;
+        for (range, val) in entries {
+            self.u64(range.start)?;
+            self.u64(range.end)?;
+            value(self, val)?;
+        }
+        Ok(())
+    }
+
+    fn cfi_rules(&mut self, rules: &CfiRules) -> io::Result<()> {
+        self.u64(rules.address)?;
+        self.str(&rules.rules)
+    }
+
+    fn stack_info_win(&mut self, info: &StackInfoWin) -> io::Result<()> {
+        self.u64(info.address)?;
+        self.u32(info.size)?;
+        self.u32(info.prologue_size)?;
+        self.u32(info.epilogue_size)?;
+        self.u32(info.parameter_size)?;
+        self.u32(info.saved_register_size)?;
+        self.u32(info.local_size)?;
+        self.u32(info.max_stack_size)?;
+        match &info.program_string_or_base_pointer {
+            WinStackThing::ProgramString(program) => {
+                self.u8(0)?;
+                self.str(program)
+            }
+            WinStackThing::AllocatesBasePointer(allocates) => {
+                self.u8(1)?;
+                self.u8(*allocates as u8)
+            }
+        }
+    }
+}
+
+struct Decoder<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Decoder<'a> {
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8], SymbolError> {
+        if self.data.len() < len {
+            return Err(corrupt());
+        }
+        let (bytes, rest) = self.data.split_at(len);
+        self.data = rest;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8, SymbolError> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn u32(&mut self) -> Result<u32, SymbolError> {
+        let mut buf = [0; 4];
+        buf.copy_from_slice(self.bytes(4)?);
+        Ok(u32::from_le_bytes(buf))
+    }
+
+    fn u64(&mut self) -> Result<u64, SymbolError> {
+        let mut buf = [0; 8];
+        buf.copy_from_slice(self.bytes(8)?);
+        Ok(u64::from_le_bytes(buf))
+    }
+
+    fn str(&mut self) -> Result<String, SymbolError> {
+        let len = self.u32()? as usize;
+        let bytes = self.bytes(len)?;
+        String::from_utf8(bytes.to_vec()).map_err(|_| corrupt())
+    }
+
+    /// Decode a list written by `Encoder::len` and a loop.
+    fn list<T>(
+        &mut self,
+        mut item: impl FnMut(&mut Self) -> Result<T, SymbolError>,
+    ) -> Result<Vec<T>, SymbolError> {
+        let len = self.u32()? as usize;
+        // Don't trust the length of a corrupt file with a huge allocation.
+        let mut items = Vec::with_capacity(len.min(self.data.len()));
+        for _ in 0..len {
+            items.push(item(self)?);
+        }
+        Ok(items)
+    }
+
+    fn names(&mut self) -> Result<HashMap<u32, String>, SymbolError> {
+        Ok(self
+            .list(|d| Ok((d.u32()?, d.str()?)))?
+            .into_iter()
+            .collect())
+    }
+
+    fn range_map<V>(
+        &mut self,
+        mut value: impl FnMut(&mut Self) -> Result<V, SymbolError>,
+    ) -> Result<RangeMap<u64, V>, SymbolError> {
+        let entries = self.list(|d| {
+            let (start, end) = (d.u64()?, d.u64()?);
+            if start > end {
+                return Err(corrupt());
+            }
+            Ok((Range::new(start, end), value(d)?))
+        })?;
+        // RangeMap requires sorted, disjoint ranges, which is what we wrote.
+        let sorted = entries
+            .windows(2)
+            .all(|pair| pair[0].0.end < pair[1].0.start);
+        if !sorted {
+            return Err(corrupt());
+        }
+        Ok(RangeMap::from_sorted_vec(entries))
+    }
+
+    fn cfi_rules(&mut self) -> Result<CfiRules, SymbolError> {
+        Ok(CfiRules {
+            address: self.u64()?,
+            rules: self.str()?,
+        })
+    }
+
+    fn stack_info_win(&mut self) -> Result<StackInfoWin, SymbolError> {
+        Ok(StackInfoWin {
+            address: self.u64()?,
+            size: self.u32()?,
+            prologue_size: self.u32()?,
+            epilogue_size: self.u32()?,
+            parameter_size: self.u32()?,
+            saved_register_size: self.u32()?,
+            local_size: self.u32()?,
+            max_stack_size: self.u32()?,
+            program_string_or_base_pointer: match self.u8()? {
+                0 => WinStackThing::ProgramString(self.str()?),
+                1 => WinStackThing::AllocatesBasePointer(self.u8()? != 0),
+                _ => return Err(corrupt()),
+            },
+        })
+    }
+}
+
+/// Write `symbols` to `out`, as the cache for a text symbol file of
+/// `source_len` bytes.
+pub(super) fn write(symbols: &SymbolFile, source_len: u64, out: impl Write) -> io::Result<()> {
+    let mut e = Encoder {
+        out: BufWriter::new(out),
+    };
+    e.out.write_all(MAGIC)?;
+    e.u32(VERSION)?;
+    e.u64(source_len)?;
+
+    match &symbols.url {
+        Some(url) => {
+            e.u8(1)?;
+            e.str(url)?;
+        }
+        None => e.u8(0)?,
+    }
+    e.u64(symbols.ambiguities_repaired)?;
+    e.u64(symbols.ambiguities_discarded)?;
+    e.u64(symbols.corruptions_discarded)?;
+
+    e.names(&symbols.files)?;
+    e.names(&symbols.inline_origins)?;
+    e.len(symbols.source_links.len())?;
+    for link in &symbols.source_links {
+        e.str(&link.pattern)?;
+        e.str(&link.url)?;
+    }
+    e.len(symbols.publics.len())?;
+    for public in &symbols.publics {
+        e.u64(public.address)?;
+        e.u32(public.parameter_size)?;
+        e.str(&public.name)?;
+    }
+    e.range_map(&symbols.functions, |e, func| {
+        e.u64(func.address)?;
+        e.u32(func.size)?;
+        e.u32(func.parameter_size)?;
+        e.str(&func.name)?;
+        e.range_map(&func.lines, |e, line| {
+            e.u64(line.address)?;
+            e.u32(line.size)?;
+            e.u32(line.file)?;
+            e.u32(line.line)
+        })?;
+        e.len(func.overlapping_lines.len())?;
+        for line in &func.overlapping_lines {
+            e.u64(line.address)?;
+            e.u32(line.size)?;
+            e.u32(line.file)?;
+            e.u32(line.line)?;
+        }
+        e.len(func.inlinees.len())?;
+        for inlinee in &func.inlinees {
+            e.u32(inlinee.depth)?;
+            e.u64(inlinee.address)?;
+            e.u32(inlinee.size)?;
+            e.u32(inlinee.call_file)?;
+            e.u32(inlinee.call_line)?;
+            e.u32(inlinee.origin_id)?;
+        }
+        Ok(())
+    })?;
+    e.range_map(&symbols.cfi_stack_info, |e, info| {
+        e.cfi_rules(&info.init)?;
+        e.u32(info.size)?;
+        e.len(info.add_rules.len())?;
+        for rules in &info.add_rules {
+            e.cfi_rules(rules)?;
+        }
+        Ok(())
+    })?;
+    e.range_map(&symbols.win_stack_framedata_info, Encoder::stack_info_win)?;
+    e.range_map(&symbols.win_stack_fpo_info, Encoder::stack_info_win)?;
+    e.out.flush()
+}
+
+/// Load symbols from a cache, as long as it was made from a text symbol file
+/// of `source_len` bytes.
+pub(super) fn read(data: &[u8], source_len: u64) -> Result<SymbolFile, SymbolError> {
+    let mut d = Decoder { data };
+    if d.bytes(MAGIC.len())? != MAGIC || d.u32()? != VERSION {
+        return Err(SymbolError::ParseError(
+            "not a binary symbol cache for this version",
+            0,
+        ));
+    }
+    if d.u64()? != source_len {
+        return Err(SymbolError::ParseError(
+            "binary symbol cache is out of date",
+            0,
+        ));
+    }
+
+    let url = match d.u8()? {
+        0 => None,
+        1 => Some(d.str()?),
+        _ => return Err(corrupt()),
+    };
+    let ambiguities_repaired = d.u64()?;
+    let ambiguities_discarded = d.u64()?;
+    let corruptions_discarded = d.u64()?;
+
+    let files = d.names()?;
+    let inline_origins = d.names()?;
+    let source_links = d.list(|d| {
+        Ok(SourceLink {
+            pattern: d.str()?,
+            url: d.str()?,
+        })
+    })?;
+    let publics = d.list(|d| {
+        Ok(PublicSymbol {
+            address: d.u64()?,
+            parameter_size: d.u32()?,
+            name: d.str()?,
+        })
+    })?;
+    let functions = d.range_map(|d| {
+        Ok(Function {
+            address: d.u64()?,
+            size: d.u32()?,
+            parameter_size: d.u32()?,
+            name: d.str()?,
+            lines: d.range_map(|d| {
+                Ok(SourceLine {
+                    address: d.u64()?,
+                    size: d.u32()?,
+                    file: d.u32()?,
+                    line: d.u32()?,
+                })
+            })?,
+            overlapping_lines: d.list(|d| {
+                Ok(SourceLine {
+                    address: d.u64()?,
+                    size: d.u32()?,
+                    file: d.u32()?,
+                    line: d.u32()?,
+                })
+            })?,
+            inlinees: d.list(|d| {
+                Ok(Inlinee {
+                    depth: d.u32()?,
+                    address: d.u64()?,
+                    size: d.u32()?,
+                    call_file: d.u32()?,
+                    call_line: d.u32()?,
+                    origin_id: d.u32()?,
+                })
+            })?,
+        })
+    })?;
+    let cfi_stack_info = d.range_map(|d| {
+        Ok(StackInfoCfi {
+            init: d.cfi_rules()?,
+            size: d.u32()?,
+            add_rules: d.list(Decoder::cfi_rules)?,
+        })
+    })?;
+    let win_stack_framedata_info = d.range_map(Decoder::stack_info_win)?;
+    let win_stack_fpo_info = d.range_map(Decoder::stack_info_win)?;
+    if !d.data.is_empty() {
+        return Err(corrupt());
+    }
+
+    Ok(SymbolFile {
+        files,
+        publics,
+        functions,
+        inline_origins,
+        cfi_stack_info,
+        win_stack_framedata_info,
+        win_stack_fpo_info,
+        source_links,
+        url,
+        path: None,
+        file_size: None,
+        fetch_duration: None,
+        parse_duration: None,
+        cache_hit: false,
+        ambiguities_repaired,
+        ambiguities_discarded,
+        corruptions_discarded,
+        cfi_eval_corruptions: 0,
+        lazy: None,
+    })
+}
+
+/// Load the cache at `path`, as long as it was made from a text symbol file
+/// of `source_len` bytes. Also returns the size of the cache.
+pub(super) fn read_file(path: &Path, source_len: u64) -> Result<(SymbolFile, u64), SymbolError> {
+    let file = File::open(path)?;
+    // Safety: like the symbol files themselves, caches are only ever
+    // replaced atomically, not modified in place.
+    let data = unsafe { Mmap::map(&file)? };
+    Ok((read(&data, source_len)?, data.len() as u64))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const SYMBOLS: &[u8] = b"MODULE Linux x86 ffff0000 bar
+INFO URL https://example.com/bar.sym
+INFO SOURCE_LINK /builds/* https://example.com/src/*
+FILE 53 bar.c
+FILE 54 other.c
+INLINE_ORIGIN 1 inlined func
+PUBLIC 2160 0 Public2
+PUBLIC 2000 8 Public1
+FUNC 1000 30 10 some func
+1000 30 7 53
+INLINE 0 42 54 1 1010 10
+FUNC m 2100 50 0 another func
+2100 50 8 54
+STACK CFI INIT 1000 30 .cfa: $esp 4 + .ra: .cfa 4 - ^
+STACK CFI 1010 .cfa: $esp 8 +
+STACK WIN 4 2100 50 1 0 0 0 0 0 1 $eip 4 + ^ = $esp $esp 8 + =
+STACK WIN 0 1000 30 3 0 8 0 0 0 0 0
+";
+
+    fn round_trip(symbols: &SymbolFile) -> Vec<u8> {
+        let mut data = vec![];
+        write(symbols, 1234, &mut data).unwrap();
+        assert_eq!(&read(&data, 1234).unwrap(), symbols);
+        data
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let symbols = SymbolFile::from_bytes(SYMBOLS).unwrap();
+        assert_eq!(symbols.url.as_deref(), Some("https://example.com/bar.sym"));
+        assert_eq!(symbols.cfi_stack_info.ranges_values().count(), 1);
+        assert_eq!(symbols.source_links.len(), 1);
+        round_trip(&symbols);
+        round_trip(&SymbolFile::from_bytes(b"MODULE Linux x86 ffff0000 bar\n").unwrap());
+    }
+
+    #[test]
+    fn test_round_trip_file() {
+        let path = Path::new(
+            "testdata/symbols/test_app.pdb/5A9832E5287241C1838ED98914E9B7FF1/test_app.sym",
+        );
+        let symbols = SymbolFile::from_bytes(&std::fs::read(path).unwrap()).unwrap();
+        let data = round_trip(&symbols);
+
+        let t = tempfile::tempdir().unwrap();
+        let cache = cache_path(&t.path().join("test_app.sym"));
+        assert_eq!(cache, t.path().join("test_app.sym.cache"));
+        std::fs::write(&cache, data).unwrap();
+        assert_eq!(read_file(&cache, 1234).unwrap().0, symbols);
+    }
+
+    #[test]
+    fn test_bad_caches() {
+        let symbols = SymbolFile::from_bytes(SYMBOLS).unwrap();
+        let data = round_trip(&symbols);
+
+        // Made from a different file.
+        assert!(matches!(
+            read(&data, 1235),
+            Err(SymbolError::ParseError(..))
+        ));
+        // From some other version.
+        let mut other_version = data.clone();
+        other_version[MAGIC.len()] += 1;
+        assert!(read(&other_version, 1234).is_err());
+        // Truncated, or with junk at the end.
+        for len in 0..data.len() {
+            assert!(read(&data[..len], 1234).is_err());
+        }
+        let mut junk = data;
+        junk.push(0);
+        assert!(read(&junk, 1234).is_err());
+    }
+}
@@ -0,0 +1,428 @@
+//! Lazily parsed symbol files.
+//!
+//! Symbol files for big programs can be over a gigabyte, and parsing all of
+//! one up front takes a long time and a lot of memory, when a crash usually
+//! only needs a handful of functions out of it.
+//!
+//! Instead, a lazy symbol file is mmapped and scanned once to build an index
+//! of where each record is (which is much smaller than the parsed records).
+//! When an address is looked up, the records covering it are pulled out of the
+//! file and parsed into a tiny [`SymbolFile`] that answers the query exactly
+//! like the full one would.
+//!
+//! Each kind of record is looked up by taking the one with the greatest
+//! address at or below the address being looked up. That's the same thing the
+//! full parser ends up with for the overlapping records it knows how to repair,
+//! but lazy lookups may differ from full parsing for records it discards.
+//! Malformed records are skipped rather than failing the whole file.
+
+use memmap2::Mmap;
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::fs::File;
+use std::mem::size_of;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+use crate::{SymbolError, SymbolFile};
+
+/// A record in the file: the addresses it covers, and where it is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+struct Entry {
+    address: u64,
+    size: u64,
+    start: usize,
+    end: usize,
+}
+
+impl Entry {
+    fn contains(&self, address: u64) -> bool {
+        address >= self.address && address - self.address < self.size
+    }
+}
+
+/// An index of the records in a symbol file.
+#[derive(Default)]
+struct Index {
+    /// FUNC records, with their line and INLINE records.
+    functions: Vec<Entry>,
+    publics: Vec<Entry>,
+    /// STACK CFI INIT records, with their STACK CFI records.
+    cfi: Vec<Entry>,
+    /// STACK WIN records of type 4.
+    win_framedata: Vec<Entry>,
+    /// STACK WIN records of type 0.
+    win_fpo: Vec<Entry>,
+    files: HashMap<u32, Range<usize>>,
+    inline_origins: HashMap<u32, Range<usize>>,
+    /// INFO SOURCE_LINK records, which every FUNC may need.
+    source_links: Vec<Range<usize>>,
+    url: Option<String>,
+}
+
+/// The kinds of records that have more records after them.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Block {
+    None,
+    Function,
+    Cfi,
+}
+
+fn hex(field: Option<&[u8]>) -> Option<u64> {
+    u64::from_str_radix(std::str::from_utf8(field?).ok()?, 16).ok()
+}
+
+fn dec(field: Option<&[u8]>) -> Option<u32> {
+    std::str::from_utf8(field?).ok()?.parse().ok()
+}
+
+impl Index {
+    fn build(data: &[u8]) -> Index {
+        let mut index = Index::default();
+        let mut block = Block::None;
+        let mut pos = 0;
+        while pos < data.len() {
+            let end = data[pos..]
+                .iter()
+                .position(|&b| b == b'\n')
+                .map_or(data.len(), |i| pos + i + 1);
+            let line = &data[pos..end];
+            let line = line.strip_suffix(b"\n").unwrap_or(line);
+            let line = line.strip_suffix(b"\r").unwrap_or(line);
+
+            let mut fields = line.split(|&b| b == b' ').filter(|f| !f.is_empty());
+            let record = fields.next().unwrap_or_default();
+            let continues = match (block, record) {
+                (Block::Function, _) if !record.is_empty() => {
+                    record == b"INLINE" || record.iter().all(u8::is_ascii_hexdigit)
+                }
+                (Block::Cfi, b"STACK") => {
+                    line.starts_with(b"STACK CFI ") && !line.starts_with(b"STACK CFI INIT ")
+                }
+                _ => false,
+            };
+            if continues {
+                index.extend_last(block, end);
+            } else {
+                block = index.add_record(record, fields, pos, end);
+            }
+            pos = end;
+        }
+        index.functions.sort_unstable();
+        index.publics.sort_unstable();
+        index.cfi.sort_unstable();
+        index.win_framedata.sort_unstable();
+        index.win_fpo.sort_unstable();
+        index
+    }
+
+    fn extend_last(&mut self, block: Block, end: usize) {
+        let entries = match block {
+            Block::Function => &mut self.functions,
+            Block::Cfi => &mut self.cfi,
+            Block::None => return,
+        };
+        if let Some(entry) = entries.last_mut() {
+            entry.end = end;
+        }
+    }
+
+    /// Index a record that starts a line, returning whether it has more
+    /// records after it.
+    fn add_record<'a>(
+        &mut self,
+        record: &[u8],
+        mut fields: impl Iterator<Item = &'a [u8]>,
+        start: usize,
+        end: usize,
+    ) -> Block {
+        let entry = |address, size| Entry {
+            address,
+            size,
+            start,
+            end,
+        };
+        match record {
+            b"FUNC" | b"PUBLIC" => {
+                let mut address = fields.next();
+                if address == Some(b"m") {
+                    address = fields.next();
+                }
+                let address = match hex(address) {
+                    Some(address) => address,
+                    None => return Block::None,
+                };
+                if record == b"PUBLIC" {
+                    self.publics.push(entry(address, 0));
+                    return Block::None;
+                }
+                match hex(fields.next()) {
+                    Some(size) => {
+                        self.functions.push(entry(address, size));
+                        Block::Function
+                    }
+                    None => Block::None,
+                }
+            }
+            b"STACK" => match fields.next() {
+                Some(b"CFI") => {
+                    if fields.next() != Some(b"INIT") {
+                        return Block::None;
+                    }
+                    match (hex(fields.next()), hex(fields.next())) {
+                        (Some(address), Some(size)) => {
+                            self.cfi.push(entry(address, size));
+                            Block::Cfi
+                        }
+                        _ => Block::None,
+                    }
+                }
+                Some(b"WIN") => {
+                    let ty = fields.next();
+                    if let (Some(address), Some(size)) = (hex(fields.next()), hex(fields.next())) {
+                        match ty {
+                            Some(b"4") => self.win_framedata.push(entry(address, size)),
+                            Some(b"0") => self.win_fpo.push(entry(address, size)),
+                            _ => {}
+                        }
+                    }
+                    Block::None
+                }
+                _ => Block::None,
+            },
+            b"FILE" | b"INLINE_ORIGIN" => {
+                if let Some(id) = dec(fields.next()) {
+                    if record == b"FILE" {
+                        self.files.insert(id, start..end);
+                    } else {
+                        self.inline_origins.insert(id, start..end);
+                    }
+                }
+                Block::None
+            }
+            b"INFO" => {
+                match fields.next() {
+                    Some(b"URL") => {
+                        if let Some(url) = fields.next().and_then(|u| std::str::from_utf8(u).ok()) {
+                            self.url = Some(url.to_string());
+                        }
+                    }
+                    Some(b"SOURCE_LINK") => self.source_links.push(start..end),
+                    _ => {}
+                }
+                Block::None
+            }
+            _ => Block::None,
+        }
+    }
+
+    fn memory_usage(&self) -> usize {
+        let entries = self.functions.capacity()
+            + self.publics.capacity()
+            + self.cfi.capacity()
+            + self.win_framedata.capacity()
+            + self.win_fpo.capacity();
+        let ids = self.files.len() + self.inline_origins.len();
+        entries * size_of::<Entry>()
+            + ids * size_of::<(u32, Range<usize>)>()
+            + self.source_links.capacity() * size_of::<Range<usize>>()
+    }
+}
+
+/// The entry with the greatest address at or below `address`.
+fn nearest(entries: &[Entry], address: u64) -> Option<&Entry> {
+    let idx = entries.partition_point(|e| e.address <= address);
+    idx.checked_sub(1).map(|idx| &entries[idx])
+}
+
+/// The entry with the greatest address at or below `address`, if it covers
+/// `address`.
+fn covering(entries: &[Entry], address: u64) -> Option<&Entry> {
+    nearest(entries, address).filter(|e| e.contains(address))
+}
+
+/// A mmapped symbol file and an index of its records.
+pub(crate) struct LazySymbols {
+    path: PathBuf,
+    data: Mmap,
+    index: Index,
+}
+
+impl LazySymbols {
+    pub(crate) fn open(path: &Path) -> Result<LazySymbols, SymbolError> {
+        let file = File::open(path)?;
+        // Safety: symbol files aren't expected to change while they're being
+        // used, just like the minidumps themselves.
+        let data = unsafe { Mmap::map(&file)? };
+        let index = Index::build(&data);
+        Ok(LazySymbols {
+            path: path.to_path_buf(),
+            data,
+            index,
+        })
+    }
+
+    pub(crate) fn url(&self) -> Option<String> {
+        self.index.url.clone()
+    }
+
+    pub(crate) fn memory_usage(&self) -> usize {
+        size_of::<LazySymbols>() + self.path.capacity() + self.index.memory_usage()
+    }
+
+    /// Parse the records needed to look up `address`.
+    pub(crate) fn symbols_for(&self, address: u64) -> Result<SymbolFile, SymbolError> {
+        let mut text = vec![];
+        let append = |text: &mut Vec<u8>, range: Range<usize>| {
+            text.extend_from_slice(&self.data[range]);
+            if !text.ends_with(b"\n") {
+                text.push(b'\n');
+            }
+        };
+
+        // The FUNC covering the address, if any, is the nearest one. PUBLICs
+        // need the FUNC before them too, which is also the nearest one.
+        if let Some(func) = nearest(&self.index.functions, address) {
+            append(&mut text, func.start..func.end);
+        }
+
+        // FUNCs need the FILEs and INLINE_ORIGINs they refer to.
+        let funcs = SymbolFile::from_bytes(&text)?;
+        let mut files = HashSet::new();
+        let mut origins = HashSet::new();
+        for (_, func) in funcs.functions.ranges_values() {
+            files.extend(func.lines.ranges_values().map(|(_, line)| line.file));
+            files.extend(func.overlapping_lines.iter().map(|line| line.file));
+            files.extend(func.inlinees.iter().map(|i| i.call_file));
+            origins.extend(func.inlinees.iter().map(|i| i.origin_id));
+        }
+        let mut names = vec![];
+        for id in files {
+            if let Some(range) = self.index.files.get(&id) {
+                append(&mut names, range.clone());
+            }
+        }
+        for id in origins {
+            if let Some(range) = self.index.inline_origins.get(&id) {
+                append(&mut names, range.clone());
+            }
+        }
+        for range in &self.index.source_links {
+            append(&mut names, range.clone());
+        }
+        names.append(&mut text);
+        let mut text = names;
+
+        if let Some(public) = nearest(&self.index.publics, address) {
+            append(&mut text, public.start..public.end);
+        }
+        for entries in &[
+            &self.index.cfi,
+            &self.index.win_framedata,
+            &self.index.win_fpo,
+        ] {
+            if let Some(entry) = covering(entries, address) {
+                append(&mut text, entry.start..entry.end);
+            }
+        }
+
+        SymbolFile::from_bytes(&text)
+    }
+}
+
+impl fmt::Debug for LazySymbols {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LazySymbols")
+            .field("path", &self.path)
+            .field("functions", &self.index.functions.len())
+            .finish()
+    }
+}
+
+impl PartialEq for LazySymbols {
+    fn eq(&self, other: &LazySymbols) -> bool {
+        self.path == other.path
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const SYMBOLS: &[u8] = b"MODULE Linux x86 ffff0000 bar
+INFO URL https://example.com/bar.sym
+INFO SOURCE_LINK /builds/* https://example.com/src/*
+FILE 53 bar.c
+FILE 54 other.c
+INLINE_ORIGIN 1 inlined func
+PUBLIC 2160 0 Public2
+PUBLIC 2000 0 Public1
+FUNC 1000 30 10 some func
+1000 30 7 53
+INLINE 0 42 54 1 1010 10
+FUNC m 2100 50 0 another func
+2100 50 8 54
+STACK CFI INIT 1000 30 .cfa: $esp 4 + .ra: .cfa 4 - ^
+STACK CFI 1010 .cfa: $esp 8 +
+STACK WIN 4 2100 50 1 0 0 0 0 0 1 $eip 4 + ^ = $esp $esp 8 + =
+STACK WIN 0 1000 30 3 0 8 0 0 0 0 0
+";
+
+    #[test]
+    fn test_index() {
+        let index = Index::build(SYMBOLS);
+        let addresses = |entries: &[Entry]| entries.iter().map(|e| e.address).collect::<Vec<_>>();
+        assert_eq!(addresses(&index.functions), vec![0x1000, 0x2100]);
+        assert_eq!(addresses(&index.publics), vec![0x2000, 0x2160]);
+        assert_eq!(addresses(&index.cfi), vec![0x1000]);
+        assert_eq!(addresses(&index.win_framedata), vec![0x2100]);
+        assert_eq!(addresses(&index.win_fpo), vec![0x1000]);
+        assert_eq!(index.files.len(), 2);
+        assert_eq!(index.inline_origins.len(), 1);
+        assert_eq!(index.url.as_deref(), Some("https://example.com/bar.sym"));
+        assert_eq!(index.source_links.len(), 1);
+
+        // Blocks include the records that go with them.
+        let func = &index.functions[0];
+        assert_eq!(
+            &SYMBOLS[func.start..func.end],
+            b"FUNC 1000 30 10 some func\n1000 30 7 53\nINLINE 0 42 54 1 1010 10\n"
+        );
+        let cfi = &index.cfi[0];
+        assert_eq!(
+            &SYMBOLS[cfi.start..cfi.end],
+            &b"STACK CFI INIT 1000 30 .cfa: $esp 4 + .ra: .cfa 4 - ^\nSTACK CFI 1010 .cfa: $esp 8 +\n"[..]
+        );
+    }
+
+    #[test]
+    fn test_symbols_for() {
+        let t = tempfile::tempdir().unwrap();
+        let path = t.path().join("bar.sym");
+        std::fs::write(&path, SYMBOLS).unwrap();
+        let lazy = LazySymbols::open(&path).unwrap();
+
+        let symbols = lazy.symbols_for(0x1010).unwrap();
+        assert_eq!(symbols.functions.get(0x1010).unwrap().name, "some func");
+        assert_eq!(symbols.files.len(), 2);
+        assert_eq!(symbols.inline_origins.len(), 1);
+        assert_eq!(
+            symbols.cfi_stack_info.get(0x1010).unwrap().add_rules.len(),
+            1
+        );
+        assert!(symbols.win_stack_fpo_info.get(0x1010).is_some());
+        assert_eq!(symbols.source_links.len(), 1);
+
+        // Publics come with the function before them.
+        let symbols = lazy.symbols_for(0x2050).unwrap();
+        assert_eq!(symbols.find_nearest_public(0x2050).unwrap().name, "Public1");
+        assert!(symbols.functions.get(0x2050).is_none());
+        assert!(symbols.functions.get(0x1010).is_some());
+
+        // Nothing here.
+        let symbols = lazy.symbols_for(0x10).unwrap();
+        assert!(symbols.functions.get(0x10).is_none());
+        assert!(symbols.publics.is_empty());
+    }
+}
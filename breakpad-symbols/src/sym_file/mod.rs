@@ -3,15 +3,177 @@
 use crate::{FrameSymbolizer, FrameWalker, Module, SymbolError};
 
 pub use crate::sym_file::types::*;
+#[cfg(feature = "compressed-symbols")]
+use flate2::read::GzDecoder;
+#[cfg(feature = "compressed-symbols")]
+use flate2::write::GzDecoder as GzWriteDecoder;
 pub use parser::SymbolParser;
+use range_map::{Range, RangeMap};
 use std::fs::File;
-use std::io::Read;
-use std::path::Path;
-
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::mem::size_of;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+#[cfg(feature = "compressed-symbols")]
+use zstd::stream::raw::Decoder as ZstdRawDecoder;
+#[cfg(feature = "compressed-symbols")]
+use zstd::stream::read::Decoder as ZstdDecoder;
+#[cfg(feature = "compressed-symbols")]
+use zstd::stream::zio::Writer as ZstdWriteDecoder;
+
+mod binary;
+mod lazy;
 mod parser;
 mod types;
 pub mod walker;
 
+const GZIP_MAGIC: &[u8] = &[0x1f, 0x8b];
+const ZSTD_MAGIC: &[u8] = &[0x28, 0xb5, 0x2f, 0xfd];
+
+/// How a symbol file is compressed, going by its first few bytes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Compression {
+    None,
+    Gzip,
+    Zstd,
+}
+
+impl Compression {
+    fn detect(start: &[u8]) -> Compression {
+        if start.starts_with(GZIP_MAGIC) {
+            Compression::Gzip
+        } else if start.starts_with(ZSTD_MAGIC) {
+            Compression::Zstd
+        } else {
+            Compression::None
+        }
+    }
+}
+
+/// The error for a compressed symbol file without the `compressed-symbols`
+/// feature.
+#[cfg(not(feature = "compressed-symbols"))]
+fn unsupported_compression() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        "compressed symbol files need the `compressed-symbols` feature",
+    )
+}
+
+/// Wrap `input` in whatever it takes to decompress it.
+fn decompressed<'a, R: Read + 'a>(input: R) -> Result<Box<dyn Read + 'a>, SymbolError> {
+    let mut input = BufReader::new(input);
+    match Compression::detect(input.fill_buf()?) {
+        Compression::None => Ok(Box::new(input)),
+        #[cfg(feature = "compressed-symbols")]
+        Compression::Gzip => Ok(Box::new(GzDecoder::new(input))),
+        #[cfg(feature = "compressed-symbols")]
+        Compression::Zstd => Ok(Box::new(ZstdDecoder::with_buffer(input)?)),
+        #[cfg(not(feature = "compressed-symbols"))]
+        Compression::Gzip | Compression::Zstd => Err(unsupported_compression().into()),
+    }
+}
+
+/// Decompresses a response body as it arrives.
+#[cfg(feature = "compressed-symbols")]
+enum BodyDecoder {
+    Gzip(GzWriteDecoder<Vec<u8>>),
+    Zstd(ZstdWriteDecoder<Vec<u8>, ZstdRawDecoder<'static>>),
+}
+
+#[cfg(feature = "compressed-symbols")]
+impl BodyDecoder {
+    /// Get a decoder for a body compressed with `compression`, if it's
+    /// compressed.
+    fn new(compression: Compression) -> io::Result<Option<BodyDecoder>> {
+        Ok(match compression {
+            Compression::None => None,
+            Compression::Gzip => Some(BodyDecoder::Gzip(GzWriteDecoder::new(vec![]))),
+            Compression::Zstd => Some(BodyDecoder::Zstd(ZstdWriteDecoder::new(
+                vec![],
+                ZstdRawDecoder::new()?,
+            ))),
+        })
+    }
+
+    /// Decompress `chunk` (or finish decompressing, if it's empty), and take
+    /// everything that's been decompressed so far.
+    fn decode(&mut self, chunk: &[u8]) -> io::Result<Vec<u8>> {
+        match self {
+            BodyDecoder::Gzip(decoder) => {
+                if chunk.is_empty() {
+                    decoder.try_finish()?;
+                } else {
+                    decoder.write_all(chunk)?;
+                }
+                Ok(std::mem::take(decoder.get_mut()))
+            }
+            BodyDecoder::Zstd(decoder) => {
+                if chunk.is_empty() {
+                    decoder.finish()?;
+                } else {
+                    decoder.write_all(chunk)?;
+                }
+                Ok(std::mem::take(decoder.writer_mut()))
+            }
+        }
+    }
+}
+
+/// Without the `compressed-symbols` feature, there's nothing to decompress
+/// with, and compressed bodies are an error.
+#[cfg(not(feature = "compressed-symbols"))]
+enum BodyDecoder {}
+
+#[cfg(not(feature = "compressed-symbols"))]
+impl BodyDecoder {
+    fn new(compression: Compression) -> io::Result<Option<BodyDecoder>> {
+        match compression {
+            Compression::None => Ok(None),
+            Compression::Gzip | Compression::Zstd => Err(unsupported_compression()),
+        }
+    }
+
+    fn decode(&mut self, _chunk: &[u8]) -> io::Result<Vec<u8>> {
+        match *self {}
+    }
+}
+
+/// Get the next non-empty piece of a response body, decompressing it if
+/// need be. Returns an empty piece at the end of the body.
+///
+/// `decoder` is `None` until the start of the body has been seen, and then
+/// holds the decoder for the body, if it's compressed. `downloaded` counts
+/// the bytes of the body (before decompression).
+async fn next_chunk(
+    response: &mut reqwest::Response,
+    decoder: &mut Option<Option<BodyDecoder>>,
+    downloaded: &mut u64,
+) -> Result<Vec<u8>, SymbolError> {
+    loop {
+        let chunk = response
+            .chunk()
+            .await
+            .map_err(io::Error::other)?
+            .unwrap_or_default();
+        *downloaded += chunk.len() as u64;
+        // The first bytes of the body decide whether it's compressed.
+        let decoder = match decoder {
+            Some(decoder) => decoder,
+            None if chunk.is_empty() => return Ok(vec![]),
+            None => decoder.insert(BodyDecoder::new(Compression::detect(&chunk))?),
+        };
+        let decoder = match decoder {
+            Some(decoder) => decoder,
+            None => return Ok(chunk.to_vec()),
+        };
+        let decoded = decoder.decode(&chunk)?;
+        if !decoded.is_empty() || chunk.is_empty() {
+            return Ok(decoded);
+        }
+    }
+}
+
 impl SymbolFile {
     /// Parse a SymbolFile from the given Reader.
     ///
@@ -22,10 +184,15 @@ impl SymbolFile {
     ///
     /// The reader is wrapped in a buffer reader so you shouldn't
     /// buffer the input yourself.
+    ///
+    /// gzip- and zstd-compressed input is decompressed (and `callback` gets
+    /// the decompressed data).
     pub fn parse<R: Read>(
-        mut input_reader: R,
+        input_reader: R,
         mut callback: impl FnMut(&[u8]),
     ) -> Result<SymbolFile, SymbolError> {
+        let mut input_reader = decompressed(input_reader)?;
+
         // This parse streams the input to avoid the need to materialize all of
         // it into memory at once (symbol files can be a gigabyte!). As a result,
         // we need to iteratively parse.
@@ -91,16 +258,38 @@ impl SymbolFile {
     }
 
     /// `parse` but async
+    ///
+    /// Bodies with a gzip `Content-Encoding` are decompressed by `reqwest`,
+    /// and bodies that are gzip- or zstd-compressed symbol files are
+    /// decompressed here.
+    ///
+    /// The time spent waiting on `response` is recorded as the
+    /// `fetch_duration`, and the rest as the `parse_duration`.
     pub async fn parse_async(
+        response: reqwest::Response,
+        callback: impl FnMut(&[u8]),
+    ) -> Result<SymbolFile, SymbolError> {
+        Self::parse_async_with_progress(response, callback, |_| ()).await
+    }
+
+    /// `parse_async`, also calling `progress` with the number of bytes of the
+    /// response body downloaded so far (before any decompression) whenever
+    /// more of it arrives.
+    pub(crate) async fn parse_async_with_progress(
         mut response: reqwest::Response,
         mut callback: impl FnMut(&[u8]),
+        mut progress: impl FnMut(u64),
     ) -> Result<SymbolFile, SymbolError> {
         let mut chunk;
         let mut slice = &[][..];
         let mut input_reader = &mut slice;
+        let mut decoder = None;
         let mut buf = circular::Buffer::with_capacity(100_000);
         let mut parser = SymbolParser::new();
         let mut fully_consumed = false;
+        let start = Instant::now();
+        let mut fetch_duration = Duration::ZERO;
+        let mut downloaded = 0;
         loop {
             // Little rube-goldberg machine to stream the contents:
             // * get a chunk (Bytes) from the Response
@@ -109,11 +298,10 @@ impl SymbolFile {
             // * then Read that mutable reference in our circular buffer
             // * when the slice runs out, get the next chunk and repeat
             if input_reader.is_empty() {
-                chunk = response
-                    .chunk()
-                    .await
-                    .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))?
-                    .unwrap_or_default();
+                let fetch_start = Instant::now();
+                chunk = next_chunk(&mut response, &mut decoder, &mut downloaded).await?;
+                fetch_duration += fetch_start.elapsed();
+                progress(downloaded);
                 slice = &chunk[..];
                 input_reader = &mut slice;
             }
@@ -126,7 +314,10 @@ impl SymbolFile {
             // consumed all of those bytes, then the file perfectly parsed!
             if size == 0 {
                 if fully_consumed {
-                    return Ok(parser.finish());
+                    let mut symbol_file = parser.finish();
+                    symbol_file.fetch_duration = Some(fetch_duration);
+                    symbol_file.parse_duration = Some(start.elapsed() - fetch_duration);
+                    return Ok(symbol_file);
                 } else {
                     return Err(SymbolError::ParseError(
                         "unexpected EOF during parsing of SymbolFile (or a line was too long?) at line",
@@ -155,8 +346,156 @@ impl SymbolFile {
 
     // Parse a SymbolFile from a file.
     pub fn from_file(path: &Path) -> Result<SymbolFile, SymbolError> {
+        let start = Instant::now();
         let file = File::open(path)?;
-        Self::parse(file, |_| ())
+        let file_size = file.metadata()?.len();
+        let mut symbol_file = Self::parse(file, |_| ())?;
+        symbol_file.record_load(path, file_size, start);
+        Ok(symbol_file)
+    }
+
+    /// Open a symbol file without parsing it all up front.
+    ///
+    /// The file is mmapped and indexed, and only the records needed for each
+    /// [`fill_symbol`][Self::fill_symbol] or [`walk_frame`][Self::walk_frame]
+    /// are parsed, when they're needed. This is much faster and smaller for
+    /// huge symbol files, but the tables of the returned `SymbolFile`
+    /// (`functions`, `publics` and so on) are all empty.
+    ///
+    /// Compressed symbol files can't be read lazily, and are parsed normally.
+    pub fn from_file_lazy(path: &Path) -> Result<SymbolFile, SymbolError> {
+        let start = Instant::now();
+        let mut file = File::open(path)?;
+        let mut magic = [0; 4];
+        let len = file.read(&mut magic)?;
+        if Compression::detect(&magic[..len]) != Compression::None {
+            return Self::from_file(path);
+        }
+        let lazy = lazy::LazySymbols::open(path)?;
+        let mut symbol_file = SymbolParser::new().finish();
+        symbol_file.url = lazy.url();
+        symbol_file.lazy = Some(lazy);
+        symbol_file.record_load(path, file.metadata()?.len(), start);
+        Ok(symbol_file)
+    }
+
+    /// Write this symbol file to `out` in a binary format that's much quicker
+    /// to load than the text format (see [`from_binary_cache`][Self::from_binary_cache]).
+    ///
+    /// `source_len` is the size of the text symbol file this was parsed from,
+    /// which the cache is only valid for. Lazily parsed symbol files can't be
+    /// written out.
+    pub fn write_binary_cache(&self, source_len: u64, out: impl Write) -> io::Result<()> {
+        if self.lazy.is_some() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "lazily parsed symbol files can't be cached",
+            ));
+        }
+        binary::write(self, source_len, out)
+    }
+
+    /// Load a binary cache written by [`write_binary_cache`][Self::write_binary_cache].
+    ///
+    /// The cache is mmapped and decoded straight out of the mapping. It's
+    /// rejected with a `ParseError` if it isn't a valid cache from this version
+    /// of the crate, or if it was made from a text symbol file that wasn't
+    /// `source_len` bytes.
+    pub fn from_binary_cache(path: &Path, source_len: u64) -> Result<SymbolFile, SymbolError> {
+        let start = Instant::now();
+        let (mut symbol_file, file_size) = binary::read_file(path, source_len)?;
+        symbol_file.record_load(path, file_size, start);
+        Ok(symbol_file)
+    }
+
+    /// Record that this symbol file was loaded from `path`, which is
+    /// `file_size` bytes, starting at `start`.
+    fn record_load(&mut self, path: &Path, file_size: u64, start: Instant) {
+        self.path = Some(path.to_path_buf());
+        self.file_size = Some(file_size);
+        self.parse_duration = Some(start.elapsed());
+    }
+
+    /// Where the binary cache for the text symbol file at `path` is kept:
+    /// alongside it, with `.cache` appended to its name.
+    pub fn binary_cache_path(path: &Path) -> PathBuf {
+        binary::cache_path(path)
+    }
+
+    /// Roughly how much memory this `SymbolFile` is using, in bytes.
+    ///
+    /// This counts the symbols themselves, but not allocator or hash table
+    /// overhead.
+    pub fn memory_usage(&self) -> usize {
+        fn table<T>(len: usize) -> usize {
+            len * size_of::<T>()
+        }
+        let files = table::<(u32, String)>(self.files.len())
+            + self.files.values().map(String::capacity).sum::<usize>();
+        let inline_origins = table::<(u32, String)>(self.inline_origins.len())
+            + self
+                .inline_origins
+                .values()
+                .map(String::capacity)
+                .sum::<usize>();
+        let publics = table::<PublicSymbol>(self.publics.capacity())
+            + self
+                .publics
+                .iter()
+                .map(|p| p.name.capacity())
+                .sum::<usize>();
+        let functions = table::<(Range<u64>, Function)>(self.functions.num_ranges())
+            + self
+                .functions
+                .ranges_values()
+                .map(|(_, f)| {
+                    f.name.capacity()
+                        + table::<(Range<u64>, SourceLine)>(f.lines.num_ranges())
+                        + table::<Inlinee>(f.inlinees.capacity())
+                })
+                .sum::<usize>();
+        let cfi = table::<(Range<u64>, StackInfoCfi)>(self.cfi_stack_info.num_ranges())
+            + self
+                .cfi_stack_info
+                .ranges_values()
+                .map(|(_, cfi)| {
+                    cfi.init.rules.capacity()
+                        + table::<CfiRules>(cfi.add_rules.capacity())
+                        + cfi
+                            .add_rules
+                            .iter()
+                            .map(|r| r.rules.capacity())
+                            .sum::<usize>()
+                })
+                .sum::<usize>();
+        let win = |info: &RangeMap<u64, StackInfoWin>| {
+            table::<(Range<u64>, StackInfoWin)>(info.num_ranges())
+                + info
+                    .ranges_values()
+                    .map(|(_, win)| match &win.program_string_or_base_pointer {
+                        WinStackThing::ProgramString(s) => s.capacity(),
+                        WinStackThing::AllocatesBasePointer(_) => 0,
+                    })
+                    .sum::<usize>()
+        };
+        size_of::<SymbolFile>()
+            + self
+                .lazy
+                .as_ref()
+                .map_or(0, lazy::LazySymbols::memory_usage)
+            + files
+            + inline_origins
+            + publics
+            + functions
+            + cfi
+            + win(&self.win_stack_framedata_info)
+            + win(&self.win_stack_fpo_info)
+            + table::<SourceLink>(self.source_links.capacity())
+            + self
+                .source_links
+                .iter()
+                .map(|l| l.pattern.capacity() + l.url.capacity())
+                .sum::<usize>()
     }
 
     /// Fill in as much source information for `frame` as possible.
@@ -166,6 +505,12 @@ impl SymbolFile {
             return;
         }
         let addr = frame.get_instruction() - module.base_address();
+        if let Some(lazy) = &self.lazy {
+            if let Ok(symbols) = lazy.symbols_for(addr) {
+                symbols.fill_symbol(module, frame);
+            }
+            return;
+        }
         if let Some(func) = self.functions.get(addr) {
             // TODO: although FUNC records have a parameter size, it appears that
             // they aren't to be trusted? The STACK WIN records are more reliable
@@ -188,12 +533,70 @@ impl SymbolFile {
                 func.address + module.base_address(),
                 parameter_size,
             );
-            // See if there's source line info as well.
-            func.lines.get(addr).map(|line| {
-                self.files.get(&line.file).map(|file| {
-                    frame.set_source_file(file, line.line, line.address + module.base_address());
+            // Find the chain of inlined calls covering this address, from the
+            // outermost to the innermost. A gap in the depths (or an unknown
+            // origin) ends the chain.
+            let mut inlinees = Vec::new();
+            while let Some(inlinee) = func.inlinee_at(inlinees.len() as u32, addr) {
+                match self.inline_origins.get(&inlinee.origin_id) {
+                    Some(name) => inlinees.push((inlinee, name)),
+                    None => break,
+                }
+            }
+
+            // See if there's source line info as well. If there are inlined
+            // calls, this is the position in the innermost inlined function,
+            // and each outer function is positioned at the next call site.
+            let mut line_records = func
+                .lines
+                .get(addr)
+                .into_iter()
+                .chain(func.overlapping_lines_at(addr));
+            let line = line_records.next();
+            let line_base = line.map(|l| l.address).unwrap_or(func.address) + module.base_address();
+            let mut positions = inlinees
+                .iter()
+                .map(|(inlinee, _)| {
+                    self.files
+                        .get(&inlinee.call_file)
+                        .map(|file| (file.as_str(), inlinee.call_line))
                 })
-            });
+                .collect::<Vec<_>>();
+            positions.push(
+                line.and_then(|l| self.files.get(&l.file).map(|file| (file.as_str(), l.line))),
+            );
+
+            if let Some((file, line)) = positions[0] {
+                frame.set_source_file(file, line, line_base);
+                if let Some(url) = self.source_url(file) {
+                    frame.set_source_url(&url);
+                }
+            }
+            for ((_, name), position) in inlinees.iter().zip(&positions[1..]) {
+                frame.add_inline_frame(
+                    name,
+                    position.map(|(file, _)| file),
+                    position.map(|(_, line)| line),
+                );
+                if let Some(url) = position.and_then(|(file, _)| self.source_url(file)) {
+                    frame.set_source_url(&url);
+                }
+            }
+
+            // Any other lines the instruction is from are in the innermost
+            // function too.
+            if let Some(Some(position)) = positions.last() {
+                let mut seen = vec![*position];
+                for record in line_records {
+                    if let Some(file) = self.files.get(&record.file) {
+                        let other = (file.as_str(), record.line);
+                        if !seen.contains(&other) {
+                            seen.push(other);
+                            frame.add_source_line(other.0, other.1);
+                        }
+                    }
+                }
+            }
         } else if let Some(public) = self.find_nearest_public(addr) {
             // We couldn't find a valid FUNC record, but we could find a PUBLIC record.
             // Unfortauntely, PUBLIC records don't have end-points, so this could be
@@ -256,6 +659,9 @@ impl SymbolFile {
             return None;
         }
         let addr = walker.get_instruction() - module.base_address();
+        if let Some(lazy) = &self.lazy {
+            return lazy.symbols_for(addr).ok()?.walk_frame(module, walker);
+        }
 
         // Preferentially use framedata over fpo, because if both are present,
         // the former tends to be more precise (breakpad heuristic).
@@ -271,11 +677,9 @@ impl SymbolFile {
         win_stack_result.or_else(|| {
             if let Some(info) = self.cfi_stack_info.get(addr) {
                 // Don't use add_rules that come after this address
-                let mut count = 0;
-                let len = info.add_rules.len();
-                while count < len && info.add_rules[count].address <= addr {
-                    count += 1;
-                }
+                let count = info
+                    .add_rules
+                    .partition_point(|rules| rules.address <= addr);
 
                 walker::walk_with_stack_cfi(&info.init, &info.add_rules[0..count], walker)
             } else {
@@ -284,15 +688,44 @@ impl SymbolFile {
         })
     }
 
+    /// The URL the source file at `path` can be found at, if known.
+    ///
+    /// This uses the [`source_links`][Self::source_links] of the symbol file,
+    /// the most specific one first. Failing that, source-indexed paths of the
+    /// form `hg:<repo>:<path>:<revision>` and `git:<repo>:<path>:<revision>`,
+    /// as written by Mozilla's symbol upload scripts, are turned into links to
+    /// the file at that revision.
+    pub fn source_url(&self, path: &str) -> Option<String> {
+        let mut links = self.source_links.iter().collect::<Vec<_>>();
+        links.sort_by_key(|link| std::cmp::Reverse(link.pattern.len()));
+        links
+            .into_iter()
+            .find_map(|link| link.url_for(path))
+            .or_else(|| vcs_source_url(path))
+    }
+
     /// Find the nearest `PublicSymbol` whose address is less than or equal to `addr`.
     pub fn find_nearest_public(&self, addr: u64) -> Option<&PublicSymbol> {
-        for p in self.publics.iter().rev() {
-            if p.address <= addr {
-                return Some(p);
-            }
-        }
+        let idx = self.publics.partition_point(|p| p.address <= addr);
+        idx.checked_sub(1).map(|idx| &self.publics[idx])
+    }
+}
 
-        None
+/// Turn a source-indexed path like `hg:hg.mozilla.org/mozilla-central:dom/base/Foo.cpp:<rev>`
+/// into a link to that file at that revision.
+fn vcs_source_url(path: &str) -> Option<String> {
+    let (kind, rest) = path.split_once(':')?;
+    let (repo, rest) = rest.split_once(':')?;
+    let (file, rev) = rest.rsplit_once(':')?;
+    if repo.is_empty() || file.is_empty() || rev.is_empty() {
+        return None;
+    }
+    match kind {
+        "hg" => Some(format!("https://{}/file/{}/{}", repo, rev, file)),
+        "git" if repo.starts_with("github.com/") => {
+            Some(format!("https://{}/blob/{}/{}", repo, rev, file))
+        }
+        _ => None,
     }
 }
 
@@ -371,6 +804,93 @@ mod test {
         assert_eq!(sym.functions.get(0x1001).unwrap().name, "another func");
     }
 
+    #[test]
+    fn test_source_url() {
+        let symbols = SymbolFile::from_bytes(
+            b"MODULE Linux x86 ffff0000 bar
+INFO SOURCE_LINK /builds/* https://example.com/src/*
+INFO SOURCE_LINK /builds/generated/* https://example.com/generated/*
+INFO SOURCE_LINK C:\\src\\main.c https://example.com/main.c
+FILE 1 /builds/lib/foo.c
+FUNC 1000 30 0 foo
+1000 30 7 1
+",
+        )
+        .unwrap();
+        let url = |path| symbols.source_url(path);
+        assert_eq!(
+            url("/builds/lib/foo.c").as_deref(),
+            Some("https://example.com/src/lib/foo.c")
+        );
+        // The most specific mapping wins.
+        assert_eq!(
+            url("/builds/generated/bar.h").as_deref(),
+            Some("https://example.com/generated/bar.h")
+        );
+        assert_eq!(
+            url("c:\\SRC\\main.c").as_deref(),
+            Some("https://example.com/main.c")
+        );
+        assert_eq!(url("/elsewhere/foo.c"), None);
+        assert_eq!(url("c:\\src\\other.c"), None);
+
+        // Source-indexed paths.
+        assert_eq!(
+            url("hg:hg.mozilla.org/mozilla-central:dom/base/Foo.cpp:abc123").as_deref(),
+            Some("https://hg.mozilla.org/mozilla-central/file/abc123/dom/base/Foo.cpp")
+        );
+        assert_eq!(
+            url("git:github.com/rust-lang/rust:library/core/src/panic.rs:def456").as_deref(),
+            Some("https://github.com/rust-lang/rust/blob/def456/library/core/src/panic.rs")
+        );
+        assert_eq!(url("git:example.com/repo:foo.c:def456"), None);
+
+        // Frames get the URL along with the source file.
+        let mut frame = crate::SimpleFrame::with_instruction(0x1010);
+        symbols.fill_symbol(&crate::SimpleModule::default(), &mut frame);
+        assert_eq!(
+            frame.source_url.as_deref(),
+            Some("https://example.com/src/lib/foo.c")
+        );
+    }
+
+    #[test]
+    fn test_overlapping_lines() {
+        let symbols = SymbolFile::from_bytes(
+            b"MODULE Linux x86 ffff0000 bar
+FILE 1 foo.c
+FILE 2 foo.h
+FUNC 1000 30 0 foo
+1000 10 120 1
+1000 20 98 1
+1008 8 131 1
+1008 4 120 1
+1008 8 7 2
+",
+        )
+        .unwrap();
+        let mut frame = crate::SimpleFrame::with_instruction(0x1009);
+        symbols.fill_symbol(&crate::SimpleModule::default(), &mut frame);
+        assert_eq!(frame.source_file.as_deref(), Some("foo.c"));
+        assert_eq!(frame.source_line, Some(120));
+        // Records for the same line as another aren't repeated.
+        assert_eq!(
+            frame.other_source_lines,
+            vec![
+                (String::from("foo.c"), 98),
+                (String::from("foo.c"), 131),
+                (String::from("foo.h"), 7),
+            ]
+        );
+
+        // Past the first record, the next one that covers the address is
+        // the frame's line.
+        let mut frame = crate::SimpleFrame::with_instruction(0x1018);
+        symbols.fill_symbol(&crate::SimpleModule::default(), &mut frame);
+        assert_eq!(frame.source_line, Some(98));
+        assert!(frame.other_source_lines.is_empty());
+    }
+
     #[test]
     fn test_symbolfile_from_bytes_with_lf() {
         test_symbolfile_from_bytes(
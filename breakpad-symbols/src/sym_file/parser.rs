@@ -1,18 +1,16 @@
 // Copyright 2015 Ted Mielczarek. See the COPYRIGHT
 // file at the top-level directory of this distribution.
 
-use log::warn;
 use nom::IResult::*;
 use nom::*;
 use range_map::{Range, RangeMap};
+use tracing::warn;
 
 use std::collections::HashMap;
 use std::fmt::Debug;
 use std::str;
 use std::str::FromStr;
 
-use minidump_common::traits::IntoRangeMapSafe;
-
 use crate::sym_file::types::*;
 use crate::SymbolError;
 
@@ -21,6 +19,7 @@ enum Line {
     Module,
     Info(Info),
     File(u32, String),
+    InlineOrigin(u32, String),
     Public(PublicSymbol),
     Function(Function, Vec<SourceLine>),
     StackWin(WinFrameType),
@@ -72,6 +71,28 @@ named!(
     )
 );
 
+// Matches an INFO SOURCE_LINK record.
+named!(
+    info_source_link<&[u8], Info>,
+    chain!(
+        tag!("INFO SOURCE_LINK") ~
+        space ~
+        link: map_res!(map_res!(not_line_ending, str::from_utf8), source_link) ~
+        my_eol,
+          ||{ Info::SourceLink(link) }
+    )
+);
+
+/// Split the `<pattern> <url>` of an INFO SOURCE_LINK record. Paths can have
+/// spaces in them but URLs can't, so the URL is everything after the last one.
+fn source_link(record: &str) -> Result<SourceLink, ()> {
+    let (pattern, url) = record.trim_end().rsplit_once(' ').ok_or(())?;
+    Ok(SourceLink {
+        pattern: pattern.trim_end().to_string(),
+        url: url.to_string(),
+    })
+}
+
 // Matches other INFO records.
 named!(
     info_line,
@@ -96,6 +117,18 @@ named!(file_line<&[u8], (u32, String)>,
       ||{ (id, filename.to_string()) }
 ));
 
+// Matches an INLINE_ORIGIN record.
+named!(inline_origin_line<&[u8], (u32, String)>,
+  chain!(
+    tag!("INLINE_ORIGIN") ~
+    space ~
+    id: decimal_u32 ~
+    space ~
+    name: map_res!(not_line_ending, str::from_utf8) ~
+    my_eol ,
+      ||{ (id, name.to_string()) }
+));
+
 // Matches a PUBLIC record.
 named!(public_line<&[u8], PublicSymbol>,
   chain!(
@@ -138,6 +171,46 @@ named!(func_line_data<&[u8], SourceLine>,
       }
 ));
 
+// Matches an address range in an INLINE record.
+named!(inline_address_range<&[u8], (u64, u32)>,
+  chain!(
+    space ~
+    address: hex_str_u64 ~
+    space ~
+    size: hex_u32 ,
+      || { (address, size) }
+));
+
+// Matches INLINE data after a FUNC record. An inline call may cover
+// several address ranges, so this produces an Inlinee for each of them.
+named!(inline_line<&[u8], Vec<Inlinee> >,
+  chain!(
+    tag!("INLINE") ~
+    space ~
+    depth: decimal_u32 ~
+    space ~
+    call_line: decimal_u32 ~
+    space ~
+    call_file: decimal_u32 ~
+    space ~
+    origin_id: decimal_u32 ~
+    ranges: many1!(inline_address_range) ~
+    my_eol ,
+      || {
+          ranges
+              .into_iter()
+              .map(|(address, size)| Inlinee {
+                  depth,
+                  address,
+                  size,
+                  call_file,
+                  call_line,
+                  origin_id,
+              })
+              .collect()
+      }
+));
+
 // Matches a FUNC record.
 named!(func_line<&[u8], Function>,
 chain!(
@@ -159,6 +232,8 @@ chain!(
             parameter_size,
             name: name.to_string(),
             lines: RangeMap::new(),
+            overlapping_lines: Vec::new(),
+            inlinees: Vec::new(),
         }
     }
     ));
@@ -278,8 +353,10 @@ named!(stack_cfi_init<&[u8], StackInfoCfi>,
 named!(line<&[u8], Line>,
   alt!(
     info_url => { Line::Info } |
+    info_source_link => { Line::Info } |
     info_line => { |_| Line::Info(Info::Unknown) } |
     file_line => { |(i,f)| Line::File(i, f) } |
+    inline_origin_line => { |(i,n)| Line::InlineOrigin(i, n) } |
     public_line => { Line::Public } |
     func_line => { |f| Line::Function(f, Vec::new()) } |
     stack_win_line => { Line::StackWin } |
@@ -297,6 +374,7 @@ named!(line<&[u8], Line>,
 #[derive(Debug, Default)]
 pub struct SymbolParser {
     files: HashMap<u32, String>,
+    inline_origins: HashMap<u32, String>,
     publics: Vec<PublicSymbol>,
 
     // When building a RangeMap when need to sort an array of this
@@ -306,6 +384,7 @@ pub struct SymbolParser {
     cfi_stack_info: Vec<(Range<u64>, StackInfoCfi)>,
     win_stack_framedata_info: Vec<(Range<u64>, StackInfoWin)>,
     win_stack_fpo_info: Vec<(Range<u64>, StackInfoWin)>,
+    source_links: Vec<SourceLink>,
     url: Option<String>,
     pub lines: u64,
     cur_item: Option<Line>,
@@ -357,7 +436,7 @@ impl SymbolParser {
             // We `take` and then reconstitute the item for borrowing/move
             // reasons.
             match self.cur_item.take() {
-                Some(Line::Function(cur, mut lines)) => match func_line_data(input) {
+                Some(Line::Function(mut cur, mut lines)) => match func_line_data(input) {
                     Done(new_input, line) => {
                         lines.push(line);
                         input = new_input;
@@ -365,10 +444,19 @@ impl SymbolParser {
                         self.lines += 1;
                         continue;
                     }
-                    Error(_) | Incomplete(_) => {
-                        self.finish_item(Line::Function(cur, lines));
-                        continue;
-                    }
+                    Error(_) | Incomplete(_) => match inline_line(input) {
+                        Done(new_input, inlinees) => {
+                            cur.inlinees.extend(inlinees);
+                            input = new_input;
+                            self.cur_item = Some(Line::Function(cur, lines));
+                            self.lines += 1;
+                            continue;
+                        }
+                        Error(_) | Incomplete(_) => {
+                            self.finish_item(Line::Function(cur, lines));
+                            continue;
+                        }
+                    },
                 },
                 Some(Line::StackCfi(mut cur)) => match stack_cfi(input) {
                     Done(new_input, line) => {
@@ -424,12 +512,18 @@ impl SymbolParser {
                 Line::Info(Info::Url(cached_url)) => {
                     self.url = Some(cached_url);
                 }
+                Line::Info(Info::SourceLink(link)) => {
+                    self.source_links.push(link);
+                }
                 Line::Info(Info::Unknown) => {
                     // Don't care
                 }
                 Line::File(id, filename) => {
                     self.files.insert(id, filename.to_string());
                 }
+                Line::InlineOrigin(id, name) => {
+                    self.inline_origins.insert(id, name);
+                }
                 Line::Public(p) => {
                     self.publics.push(p);
                 }
@@ -508,20 +602,10 @@ impl SymbolParser {
     fn finish_item(&mut self, item: Line) {
         match item {
             Line::Function(mut cur, lines) => {
-                cur.lines = lines
-                    .into_iter()
-                    .map(|l| {
-                        // Line data from PDB files often has a zero-size line entry, so just
-                        // filter those out.
-                        if l.size > 0 {
-                            if let Some(end) = l.address.checked_add(l.size as u64 - 1) {
-                                return (Some(Range::new(l.address, end)), l);
-                            }
-                        }
-
-                        (None, l)
-                    })
-                    .into_rangemap_safe();
+                let (lines, overlapping_lines) = into_line_map(lines);
+                cur.lines = lines;
+                cur.overlapping_lines = overlapping_lines;
+                cur.inlinees.sort_by_key(|i| (i.depth, i.address));
 
                 if let Some(range) = cur.memory_range() {
                     self.functions.push((range, cur));
@@ -555,15 +639,23 @@ impl SymbolParser {
             files: self.files,
             publics: self.publics,
             functions: into_rangemap_safe(self.functions),
+            inline_origins: self.inline_origins,
             cfi_stack_info: into_rangemap_safe(self.cfi_stack_info),
             win_stack_framedata_info: into_rangemap_safe(self.win_stack_framedata_info),
             win_stack_fpo_info: into_rangemap_safe(self.win_stack_fpo_info),
+            source_links: self.source_links,
             // Will get filled in by the caller
             url: self.url,
+            path: None,
+            file_size: None,
+            fetch_duration: None,
+            parse_duration: None,
+            cache_hit: false,
             ambiguities_repaired: 0,
             ambiguities_discarded: 0,
             corruptions_discarded: 0,
             cfi_eval_corruptions: 0,
+            lazy: None,
         }
     }
 }
@@ -588,6 +680,29 @@ fn into_rangemap_safe<V: Clone + Eq + Debug>(mut input: Vec<(Range<u64>, V)>) ->
     RangeMap::from_sorted_vec(vec)
 }
 
+/// Split a function's line records into a map of the ones that don't
+/// overlap, and the ones that overlap them.
+///
+/// Where records overlap, the one that starts first (or is shorter) goes in
+/// the map, as `into_rangemap_safe` would pick.
+fn into_line_map(mut lines: Vec<SourceLine>) -> (RangeMap<u64, SourceLine>, Vec<SourceLine>) {
+    // Line data from PDB files often has a zero-size line entry, so just
+    // filter those out.
+    lines.retain(|l| l.size > 0 && l.address.checked_add(l.size as u64 - 1).is_some());
+    lines.sort_by_key(|l| (l.address, l.size));
+    lines.dedup();
+    let mut map: Vec<(Range<u64>, SourceLine)> = Vec::with_capacity(lines.len());
+    let mut overlapping = Vec::new();
+    for line in lines {
+        let range = Range::new(line.address, line.address + (line.size as u64 - 1));
+        match map.last() {
+            Some((last_range, _)) if range.start <= last_range.end => overlapping.push(line),
+            _ => map.push((range, line)),
+        }
+    }
+    (RangeMap::from_sorted_vec(map), overlapping)
+}
+
 #[cfg(test)]
 fn parse_symbol_bytes(data: &[u8]) -> Result<SymbolFile, SymbolError> {
     SymbolFile::parse(data, |_| ())
@@ -640,6 +755,25 @@ fn test_info_url() {
     assert_eq!(info_url(line), Done(rest, Info::Url(url)));
 }
 
+#[test]
+fn test_info_source_link() {
+    let line = b"INFO SOURCE_LINK C:\\Program Files\\src\\* https://example.com/abc123/*\n";
+    let link = SourceLink {
+        pattern: "C:\\Program Files\\src\\*".to_string(),
+        url: "https://example.com/abc123/*".to_string(),
+    };
+    let rest = &b""[..];
+    assert_eq!(info_source_link(line), Done(rest, Info::SourceLink(link)));
+
+    // A record without a URL is just an unknown INFO.
+    let line = b"INFO SOURCE_LINK https://example.com/\n";
+    assert!(info_source_link(line).is_err());
+    assert!(matches!(
+        self::line(line),
+        Done(_, Line::Info(Info::Unknown))
+    ));
+}
+
 #[test]
 fn test_file_line() {
     let line = b"FILE 1 foo.c\n";
@@ -707,6 +841,8 @@ fn test_func_lines_no_lines() {
                 name: "nsQueryInterfaceWithError::operator()(nsID const&, void**) const"
                     .to_string(),
                 lines: RangeMap::new(),
+                overlapping_lines: Vec::new(),
+                inlinees: Vec::new(),
             }
         )
     );
@@ -768,6 +904,54 @@ fn test_func_lines_and_lines() {
     );
 }
 
+#[test]
+fn test_func_overlapping_lines() {
+    let data = b"FUNC 1000 30 10 some func
+1000 10 42 7
+1004 8 98 7
+1010 20 52 8
+1000 10 42 7
+1010 4 131 8
+1008 0 12 7
+";
+    let file = SymbolFile::from_bytes(data).expect("failed to parse!");
+    let (_, f) = file.functions.ranges_values().next().unwrap();
+    // The first (and then shortest) of overlapping records is in `lines`.
+    assert_eq!(f.lines.ranges_values().count(), 2);
+    assert_eq!(f.lines.get(0x1006).unwrap().line, 42);
+    assert_eq!(f.lines.get(0x1012).unwrap().line, 131);
+    assert_eq!(f.lines.get(0x1014), None);
+    // Duplicates and empty records are dropped, and the rest kept aside.
+    assert_eq!(
+        f.overlapping_lines,
+        vec![
+            SourceLine {
+                address: 0x1004,
+                size: 0x8,
+                file: 7,
+                line: 98,
+            },
+            SourceLine {
+                address: 0x1010,
+                size: 0x20,
+                file: 8,
+                line: 52,
+            },
+        ]
+    );
+    let lines_at = |addr| {
+        f.overlapping_lines_at(addr)
+            .map(|l| l.line)
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(lines_at(0x1000), Vec::<u32>::new());
+    assert_eq!(lines_at(0x100b), vec![98]);
+    assert_eq!(lines_at(0x100c), Vec::<u32>::new());
+    assert_eq!(lines_at(0x1013), vec![52]);
+    assert_eq!(lines_at(0x102f), vec![52]);
+    assert_eq!(lines_at(0x1030), Vec::<u32>::new());
+}
+
 #[test]
 fn test_func_with_m() {
     let data = b"FUNC m 1000 30 10 some func
@@ -779,6 +963,73 @@ fn test_func_with_m() {
     let (_, _f) = file.functions.ranges_values().next().unwrap();
 }
 
+#[test]
+fn test_inline_origin_line() {
+    let line = b"INLINE_ORIGIN 3 nsTArray<int>::Length() const\n";
+    let rest = &b""[..];
+    assert_eq!(
+        inline_origin_line(line),
+        Done(rest, (3, "nsTArray<int>::Length() const".to_string()))
+    );
+}
+
+#[test]
+fn test_func_inlinees() {
+    let data = b"INLINE_ORIGIN 0 inner func
+INLINE_ORIGIN 1 outer inline
+FUNC 1000 30 10 some func
+INLINE 0 42 7 1 1004 10 1020 8
+INLINE 1 12 8 0 1008 4
+1000 10 42 7
+1010 10 52 8
+1020 10 62 15
+";
+    let file = SymbolFile::from_bytes(data).expect("failed to parse!");
+    assert_eq!(file.inline_origins.get(&0).unwrap(), "inner func");
+    assert_eq!(file.inline_origins.get(&1).unwrap(), "outer inline");
+    let (_, f) = file.functions.ranges_values().next().unwrap();
+    assert_eq!(f.lines.ranges_values().count(), 3);
+    assert_eq!(
+        f.inlinees,
+        vec![
+            Inlinee {
+                depth: 0,
+                address: 0x1004,
+                size: 0x10,
+                call_file: 7,
+                call_line: 42,
+                origin_id: 1,
+            },
+            Inlinee {
+                depth: 0,
+                address: 0x1020,
+                size: 0x8,
+                call_file: 7,
+                call_line: 42,
+                origin_id: 1,
+            },
+            Inlinee {
+                depth: 1,
+                address: 0x1008,
+                size: 0x4,
+                call_file: 8,
+                call_line: 12,
+                origin_id: 0,
+            },
+        ]
+    );
+
+    let inlinee_at = |depth, addr| f.inlinee_at(depth, addr).map(|i| i.address);
+    assert_eq!(inlinee_at(0, 0x1000), None);
+    assert_eq!(inlinee_at(0, 0x1004), Some(0x1004));
+    assert_eq!(inlinee_at(0, 0x1013), Some(0x1004));
+    assert_eq!(inlinee_at(0, 0x1014), None);
+    assert_eq!(inlinee_at(0, 0x1027), Some(0x1020));
+    assert_eq!(inlinee_at(1, 0x1008), Some(0x1008));
+    assert_eq!(inlinee_at(1, 0x100c), None);
+    assert_eq!(inlinee_at(2, 0x1008), None);
+}
+
 #[test]
 fn test_stack_win_line_program_string() {
     let line =
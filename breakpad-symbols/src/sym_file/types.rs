@@ -1,9 +1,12 @@
 // Copyright 2015 Ted Mielczarek. See the COPYRIGHT
 // file at the top-level directory of this distribution.
 
+use super::lazy::LazySymbols;
 use range_map::{Range, RangeMap};
 use std::cmp::Ordering;
 use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::Duration;
 
 /// A publicly visible linker symbol.
 #[derive(Debug, Eq, PartialEq)]
@@ -55,6 +58,34 @@ pub struct SourceLine {
     pub line: u32,
 }
 
+/// A call to a function that was inlined into a `Function`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Inlinee {
+    /// The nesting depth of this inline call, 0 for calls made directly
+    /// by the outer function.
+    pub depth: u32,
+    /// The start address relative to the module's load address.
+    pub address: u64,
+    /// The size of this range of instructions in bytes.
+    pub size: u32,
+    /// The source file containing the call to the inlined function.
+    ///
+    /// This is an index into `SymbolFile::files`.
+    pub call_file: u32,
+    /// The line number in `call_file` of the call to the inlined function.
+    pub call_line: u32,
+    /// The function that was inlined.
+    ///
+    /// This is an index into `SymbolFile::inline_origins`.
+    pub origin_id: u32,
+}
+
+impl Inlinee {
+    pub fn contains(&self, addr: u64) -> bool {
+        addr >= self.address && addr - self.address < self.size as u64
+    }
+}
+
 /// A source-language function.
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct Function {
@@ -68,9 +99,39 @@ pub struct Function {
     pub name: String,
     /// Source line information for this function.
     pub lines: RangeMap<u64, SourceLine>,
+    /// Line records that overlap the ones in `lines`, sorted by address.
+    ///
+    /// Optimized code often merges the instructions of several lines, so
+    /// some addresses come from more than one line.
+    pub overlapping_lines: Vec<SourceLine>,
+    /// Calls to other functions which were inlined into this one, sorted by
+    /// depth and then address.
+    pub inlinees: Vec<Inlinee>,
 }
 
 impl Function {
+    /// The inlined call at `depth` that covers `addr`, if any.
+    ///
+    /// If calls at the same depth overlap, the one starting closest before
+    /// `addr` is used.
+    pub fn inlinee_at(&self, depth: u32, addr: u64) -> Option<&Inlinee> {
+        let start = self.inlinees.partition_point(|i| i.depth < depth);
+        let end = self.inlinees.partition_point(|i| i.depth <= depth);
+        let same_depth = &self.inlinees[start..end];
+        let idx = same_depth.partition_point(|i| i.address <= addr);
+        same_depth[..idx].last().filter(|i| i.contains(addr))
+    }
+
+    /// The line records in `overlapping_lines` that cover `addr`.
+    pub fn overlapping_lines_at(&self, addr: u64) -> impl Iterator<Item = &SourceLine> {
+        let end = self
+            .overlapping_lines
+            .partition_point(|l| l.address <= addr);
+        self.overlapping_lines[..end]
+            .iter()
+            .filter(move |l| addr - l.address < l.size as u64)
+    }
+
     pub fn memory_range(&self) -> Option<Range<u64>> {
         if self.size == 0 {
             return None;
@@ -89,10 +150,49 @@ pub enum Info {
     /// by HttpSymbolSupplier when it stores them in its cache, so that we
     /// can populate that info even on a cache hit.
     Url(String),
+    /// A SourceLink mapping from build paths to source URLs.
+    SourceLink(SourceLink),
     /// An info line we either don't know about or don't care about.
     Unknown,
 }
 
+/// A [SourceLink] mapping from the paths source files were built from to the
+/// URLs they can be fetched from, from an `INFO SOURCE_LINK <pattern> <url>`
+/// record.
+///
+/// If `pattern` ends with a `*`, it matches any path starting with the rest of
+/// it, and whatever the `*` matched is substituted for the `*` in `url`.
+/// Otherwise it only matches that exact path.
+///
+/// [SourceLink]: https://github.com/dotnet/designs/blob/main/accepted/2020/diagnostics/source-link.md
+#[derive(Clone, Debug, Eq, Ord, PartialEq, PartialOrd)]
+pub struct SourceLink {
+    /// The path, or path prefix followed by `*`, this applies to.
+    pub pattern: String,
+    /// The URL, which may contain a `*` to substitute the rest of the path into.
+    pub url: String,
+}
+
+impl SourceLink {
+    /// The URL of the source file at `path`, if this mapping applies to it.
+    ///
+    /// Paths are matched case-insensitively, and backslashes in the part
+    /// substituted into the URL become forward slashes.
+    pub fn url_for(&self, path: &str) -> Option<String> {
+        match self.pattern.strip_suffix('*') {
+            Some(prefix) => {
+                let rest = path.get(prefix.len()..)?;
+                if !path[..prefix.len()].eq_ignore_ascii_case(prefix) {
+                    return None;
+                }
+                Some(self.url.replacen('*', &rest.replace('\\', "/"), 1))
+            }
+            None if path.eq_ignore_ascii_case(&self.pattern) => Some(self.url.clone()),
+            None => None,
+        }
+    }
+}
+
 /// DWARF CFI rules for recovering registers at a specific address.
 #[derive(Clone, Debug, Eq, Ord, PartialEq, PartialOrd)]
 pub struct CfiRules {
@@ -183,22 +283,38 @@ impl StackInfoWin {
 pub struct SymbolFile {
     /// The set of source files involved in compilation.
     pub files: HashMap<u32, String>,
-    /// Publicly visible symbols.
+    /// Publicly visible symbols, sorted by address.
     pub publics: Vec<PublicSymbol>,
     /// Functions.
     pub functions: RangeMap<u64, Function>,
+    /// The names of functions which were inlined into other functions.
+    pub inline_origins: HashMap<u32, String>,
     /// DWARF CFI unwind information.
     pub cfi_stack_info: RangeMap<u64, StackInfoCfi>,
     /// Windows unwind information (frame data).
     pub win_stack_framedata_info: RangeMap<u64, StackInfoWin>,
     /// Windows unwind information (FPO data).
     pub win_stack_fpo_info: RangeMap<u64, StackInfoWin>,
+    /// Where to find the source files, see [`SymbolFile::source_url`].
+    pub source_links: Vec<SourceLink>,
 
     // Statistics which are strictly best-effort. Generally this
     // means we might undercount in situations where we forgot to
     // log an event.
     /// If the symbol file was loaded from a URL, this is the url
     pub url: Option<String>,
+    /// If the symbol file was loaded from local disk, this is the path.
+    pub path: Option<PathBuf>,
+    /// The size of the symbol file that was loaded (or downloaded), in bytes.
+    pub file_size: Option<u64>,
+    /// How long was spent waiting for the symbol file to download, if it
+    /// was downloaded.
+    pub fetch_duration: Option<Duration>,
+    /// How long was spent parsing the symbol file.
+    pub parse_duration: Option<Duration>,
+    /// If the symbol file was found in a cache of downloaded (or converted)
+    /// symbol files.
+    pub cache_hit: bool,
     /// The number of times the parser found that the symbol file was
     /// strictly ambiguous but simple heuristics repaired it. (e.g.
     /// two STACK WIN entries overlapped, but the second was a suffix of
@@ -243,4 +359,8 @@ pub struct SymbolFile {
     /// was not mapped memory). In these situations the cfi entry *may*
     /// still be correct.
     pub cfi_eval_corruptions: u64,
+
+    /// For lazily parsed symbol files, where the records really are (all the
+    /// tables above are empty).
+    pub(crate) lazy: Option<LazySymbols>,
 }
@@ -363,6 +363,13 @@
 //!   $esp := $esp + frame_size + 4
 //! ```
 //!
+//! Like framedata, the position of the return address is refined by looking a
+//! few words further up the stack for something that
+//! [`FrameWalker::instruction_seems_valid`]. Functions with allocates_base_pointer
+//! may also have grown their frame with alloca (which is why they need $ebp),
+//! so for those we search up to 40 words, and look for the saved $ebp as far
+//! above its usual position as the return address was.
+//!
 //! I don't have an interesting explanation for why that position is specifically
 //! where $ebp is saved, it just is. The algorithm tries to forward $ebx when $ebp
 //! wasn't messed with as a bit of a hacky way to encourage certain Windows system
@@ -420,13 +427,23 @@
 //!   * `.raSearchStart = .raSearch` (synonym that sometimes shows up?)
 //!
 //! Note that `.raSearch(Start)` roughly corresponds to STACK CFI's `.cfa`, in that
-//! it generally points to where the return address is. However breakpad seems to
-//! believe there are many circumstances where this value can be slightly wrong
-//! (due to the frame pointer having mysterious extra alignment?). As such,
-//! breakpad has several messy heuristics to "refine" `.raSearchStart`, such as
-//! scanning the stack. This implementation does not (yet?) implement those
-//! heuristics. As of this writing I have not encountered an instance of this
-//! problem in the wild (but I haven't done much testing!).
+//! it generally points to where the return address is. However this value can be
+//! slightly wrong (when the frame was aligned, or the sizes in the STACK WIN line
+//! are off), so like breakpad we "refine" it by scanning the stack:
+//!
+//! * If the program doesn't use `@`, `.raSearchStart` is moved up to the first
+//!   of the next 3 words that looks like a return address (according to
+//!   [`FrameWalker::instruction_seems_valid`]). If that word points at the
+//!   current instruction, it's the leftover return address of a system call stub
+//!   and is skipped.
+//!
+//! * If the program produces an `$eip` that doesn't look like a return address,
+//!   the 40 words after `.raSearchStart` are scanned for one, and `$esp` is set
+//!   to just past it.
+//!
+//! * If the program doesn't assign `$ebp` and the callee's `$ebp` doesn't point
+//!   into the stack (or the previous scan skipped over whole frames), the saved
+//!   register area is searched for something that does.
 //!
 //!
 //! After evaluating a STACK WIN expression:
@@ -486,9 +503,10 @@
 
 use super::{CfiRules, StackInfoWin, WinStackThing};
 use crate::FrameWalker;
-use log::{debug, trace};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::convert::TryFrom;
 use std::str::FromStr;
+use tracing::{debug, trace};
 
 pub fn walk_with_stack_cfi(
     init: &CfiRules,
@@ -746,13 +764,33 @@ pub fn eval_win_expr_for_fuzzer(
     eval_win_expr(expr, info, walker)
 }
 
-fn eval_win_expr(expr: &str, info: &StackInfoWin, walker: &mut dyn FrameWalker) -> Option<()> {
-    // TODO?: do a bunch of heuristics to make this more robust.
-    // So far I haven't encountered an in-the-wild example that needs the
-    // extra heuristics that breakpad uses, so leaving them out until they
-    // become a problem.
+/// How many words past the computed `.raSearchStart` might hold the return
+/// address, because the frame was aligned.
+const RA_ALIGN_SEARCH_WORDS: u32 = 3;
+
+/// How many words to search for a return address when the program string
+/// didn't come up with a valid one.
+const RA_SEARCH_WORDS: u32 = 40;
+
+/// Looks through `words` words of the stack (after the one at `start`) for
+/// something the walker thinks is a return address, and returns where it was
+/// found along with the address.
+fn scan_for_return_address(walker: &dyn FrameWalker, start: u32, words: u32) -> Option<(u32, u32)> {
+    for i in 0..=words {
+        let location = start.checked_add(i * 4)?;
+        let ra = walker.get_register_at_address(location as u64)? as u32;
+        if walker.instruction_seems_valid(ra as u64) {
+            return Some((location, ra));
+        }
+    }
+    None
+}
 
+fn eval_win_expr(expr: &str, info: &StackInfoWin, walker: &mut dyn FrameWalker) -> Option<()> {
     let mut vars = HashMap::new();
+    // The variables the program assigned to, so we know which of the
+    // callee's registers it restored.
+    let mut assigned = HashSet::new();
 
     let callee_esp = walker.get_callee_register("esp")? as u32;
     let callee_ebp = walker.get_callee_register("ebp")? as u32;
@@ -774,8 +812,30 @@ fn eval_win_expr(expr: &str, info: &StackInfoWin, walker: &mut dyn FrameWalker)
         trace!("unwind: program used @ operator, using $ebp instead of $esp for return addr");
         callee_ebp.checked_add(4)?
     } else {
-        // $esp should be reasonable, get the return address from that
-        callee_esp.checked_add(frame_size)?
+        // $esp should be reasonable, get the return address from that. The
+        // frame may still have been aligned by a few words, so look a little
+        // further up if there isn't a return address right there.
+        let mut search_start = callee_esp.checked_add(frame_size)?;
+        if let Some((location, ra)) =
+            scan_for_return_address(walker, search_start, RA_ALIGN_SEARCH_WORDS)
+        {
+            if location == search_start && ra as u64 == walker.get_instruction() {
+                // The top of the stack points at the current instruction, which
+                // happens in FPO system call stubs that have been returned to
+                // but haven't popped their return address yet. The real return
+                // address is further up.
+                trace!("unwind: skipping the stale return address of a system call");
+                search_start = search_start.checked_add(4)?;
+                if let Some((location, _)) =
+                    scan_for_return_address(walker, search_start, RA_ALIGN_SEARCH_WORDS)
+                {
+                    search_start = location;
+                }
+            } else {
+                search_start = location;
+            }
+        }
+        search_start
     };
 
     trace!(
@@ -885,6 +945,7 @@ fn eval_win_expr(expr: &str, info: &StackInfoWin, walker: &mut dyn FrameWalker)
                 } else {
                     vars.insert(lhs, rhs.into_int(&vars)?);
                 }
+                assigned.insert(lhs);
             }
             "^" => {
                 // Deref the value
@@ -921,6 +982,60 @@ fn eval_win_expr(expr: &str, info: &StackInfoWin, walker: &mut dyn FrameWalker)
         }
     }
 
+    // If the return address the program found doesn't look like one, the
+    // frame size was probably off, so look for a better one further up.
+    let mut offset = 0;
+    let mut scanned = false;
+    if let Some(&eip) = vars.get("$eip") {
+        if !walker.instruction_seems_valid(eip as u64) {
+            let search_start = vars.get(".raSearchStart").copied().unwrap_or(search_start);
+            let location_start = search_start.checked_add(4)?;
+            if let Some((location, eip)) =
+                scan_for_return_address(walker, location_start, RA_SEARCH_WORDS)
+            {
+                trace!(
+                    "unwind: STACK WIN $eip was bogus, found 0x{:08x} by scanning",
+                    eip
+                );
+                vars.insert("$eip", eip);
+                vars.insert("$esp", location.checked_add(4)?);
+                offset = location - location_start;
+                scanned = true;
+            }
+        }
+    }
+
+    // If the program found a return address but didn't restore $ebp, it's
+    // still the callee's. That's usually right, but if it doesn't point at the stack (or we skipped
+    // over frames looking for the return address, so it belongs to one of
+    // them), look for the saved value in the frame's saved registers, from
+    // the top down, since the prologue probably saved $ebp first.
+    if vars.contains_key("$eip") && !assigned.contains("$ebp") {
+        let ebp = vars.get("$ebp").copied().unwrap_or(callee_ebp);
+        let has_skipped_frames = scanned && ebp <= search_start.saturating_add(offset);
+        if has_skipped_frames || walker.get_register_at_address(ebp as u64).is_none() {
+            let location_end = callee_esp.checked_add(grand_callee_param_size)?;
+            let mut location = location_end
+                .checked_add(info.saved_register_size)?
+                .checked_add(offset)?;
+            while location >= location_end {
+                let candidate = match walker.get_register_at_address(location as u64) {
+                    Some(candidate) => candidate as u32,
+                    None => break,
+                };
+                if walker.get_register_at_address(candidate as u64).is_some() {
+                    trace!("unwind: recovered STACK WIN $ebp 0x{:08x}", candidate);
+                    vars.insert("$ebp", candidate);
+                    break;
+                }
+                location = match location.checked_sub(4) {
+                    Some(location) => location,
+                    None => break,
+                };
+            }
+        }
+    }
+
     let output_regs = ["$eip", "$esp", "$ebp", "$ebx", "$esi", "$edi"];
     for reg in &output_regs {
         if let Some(&val) = vars.get(reg) {
@@ -977,8 +1092,6 @@ pub fn walk_with_stack_win_fpo(info: &StackInfoWin, walker: &mut dyn FrameWalker
     if let WinStackThing::AllocatesBasePointer(allocates_base_pointer) =
         info.program_string_or_base_pointer
     {
-        // FIXME: do a bunch of heuristics to make this more robust.
-        // Haven't needed the heuristics breakpad uses yet.
         trace!("unwind: trying STACK WIN fpo");
         clear_stack_win_caller_registers(walker);
 
@@ -987,14 +1100,35 @@ pub fn walk_with_stack_win_fpo(info: &StackInfoWin, walker: &mut dyn FrameWalker
 
         let callee_esp = walker.get_callee_register("esp")?;
         let eip_address = callee_esp + frame_size;
-        let caller_eip = walker.get_register_at_address(eip_address)?;
-        let caller_esp = callee_esp + frame_size + 4;
+
+        // A function that sets up a base pointer may have grown its frame with
+        // alloca, in which case $esp is further down than frame_size accounts
+        // for and the return address is somewhere above where we'd expect it.
+        // Otherwise the frame can still be off by a few words of alignment.
+        let search_words = if allocates_base_pointer {
+            RA_SEARCH_WORDS
+        } else {
+            RA_ALIGN_SEARCH_WORDS
+        };
+        let (eip_address, caller_eip) = u32::try_from(eip_address)
+            .ok()
+            .and_then(|start| scan_for_return_address(walker, start, search_words))
+            .map(|(location, eip)| (location as u64, eip as u64))
+            .or_else(|| Some((eip_address, walker.get_register_at_address(eip_address)?)))?;
+        let caller_esp = eip_address + 4;
+        // How much bigger the frame was than the STACK WIN line said.
+        let offset = eip_address - (callee_esp + frame_size);
 
         trace!("unwind: found caller $eip and $esp");
 
         let caller_ebp = if allocates_base_pointer {
-            let ebp_address =
-                callee_esp + grand_callee_param_size as u64 + info.saved_register_size as u64 - 8;
+            // The dynamic part of the frame is below everything else, so the
+            // saved $ebp moved up along with the return address.
+            let ebp_address = callee_esp
+                + grand_callee_param_size as u64
+                + info.saved_register_size as u64
+                + offset
+                - 8;
             walker.get_register_at_address(ebp_address)?
         } else {
             // Per Breakpad: We also propagate %ebx through, as it is commonly unmodifed after
@@ -1031,7 +1165,7 @@ fn clear_stack_win_caller_registers(walker: &mut dyn FrameWalker) {
 #[cfg(test)]
 mod test {
     use super::super::types::{CfiRules, StackInfoWin, WinStackThing};
-    use super::{eval_win_expr, walk_with_stack_cfi};
+    use super::{eval_win_expr, walk_with_stack_cfi, walk_with_stack_win_fpo};
     use crate::FrameWalker;
     use std::collections::HashMap;
 
@@ -1047,6 +1181,8 @@ mod test {
         callee_regs: HashMap<&'static str, Reg>,
         caller_regs: HashMap<&'static str, Reg>,
         stack: Vec<u8>,
+        /// If set, only instructions in this range look valid.
+        code: Option<std::ops::Range<u64>>,
     }
 
     trait Int {
@@ -1122,6 +1258,12 @@ mod test {
             self.caller_regs.insert("ra", Reg::from_u64(val));
             Some(())
         }
+        fn instruction_seems_valid(&self, address: u64) -> bool {
+            self.code
+                .as_ref()
+                .map(|code| code.contains(&address))
+                .unwrap_or(true)
+        }
     }
 
     impl<Reg: Int + Copy> TestFrameWalker<Reg> {
@@ -1134,6 +1276,7 @@ mod test {
                 // Arbitrary values
                 instruction: Reg::from_u64(0xF1CEFA32),
                 grand_callee_param_size: 4,
+                code: None,
             }
         }
     }
@@ -1291,6 +1434,145 @@ mod test {
         assert!(eval_win_expr("$esp 1 3 @ = $ebp 1 =", &info, &mut walker).is_none());
     }
 
+    fn write_u32(stack: &mut [u8], address: usize, val: u32) {
+        stack[address..address + 4].copy_from_slice(&val.to_le_bytes());
+    }
+
+    /// A walker with the callee at $esp=0x100, which puts `.raSearchStart`
+    /// at 0x128 for [`whatever_win_info`], and code at 0x1000..0x2000.
+    fn win_search_walker(stack: Vec<u8>, callee_ebp: u32) -> TestFrameWalker<u32> {
+        let input = vec![("esp", 0x100u32), ("ebp", callee_ebp)]
+            .into_iter()
+            .collect();
+        let mut walker = TestFrameWalker::new(stack, input);
+        walker.code = Some(0x1000..0x2000);
+        walker
+    }
+
+    const WIN_RA_SEARCH_EXPR: &str = "$eip .raSearch ^ = $esp .raSearch 4 + =";
+
+    #[test]
+    fn test_stack_win_aligned_ra_search() {
+        // The return address is a couple words past where the frame sizes
+        // say it should be.
+        let mut stack = vec![0; 0x400];
+        write_u32(&mut stack, 0x130, 0x1234);
+
+        let mut walker = win_search_walker(stack, 0x200);
+        let info = whatever_win_info();
+        eval_win_expr(WIN_RA_SEARCH_EXPR, &info, &mut walker).unwrap();
+
+        assert_eq!(walker.caller_regs["eip"], 0x1234);
+        assert_eq!(walker.caller_regs["esp"], 0x134);
+        assert_eq!(walker.caller_regs["ebp"], 0x200);
+    }
+
+    #[test]
+    fn test_stack_win_syscall_stub() {
+        // The top of the frame still holds the return address into the
+        // current instruction, the real one is right after it.
+        let mut stack = vec![0; 0x400];
+        write_u32(&mut stack, 0x128, 0x1100);
+        write_u32(&mut stack, 0x12c, 0x1200);
+
+        let mut walker = win_search_walker(stack, 0x200);
+        walker.instruction = 0x1100;
+        let info = whatever_win_info();
+        eval_win_expr(WIN_RA_SEARCH_EXPR, &info, &mut walker).unwrap();
+
+        assert_eq!(walker.caller_regs["eip"], 0x1200);
+        assert_eq!(walker.caller_regs["esp"], 0x130);
+    }
+
+    #[test]
+    fn test_stack_win_eip_scan() {
+        // The return address is too far up for the alignment search, so the
+        // program comes up with garbage and we have to scan for it.
+        let mut stack = vec![0; 0x400];
+        write_u32(&mut stack, 0x140, 0x1500);
+
+        let mut walker = win_search_walker(stack.clone(), 0x200);
+        let info = whatever_win_info();
+        eval_win_expr(WIN_RA_SEARCH_EXPR, &info, &mut walker).unwrap();
+
+        assert_eq!(walker.caller_regs["eip"], 0x1500);
+        assert_eq!(walker.caller_regs["esp"], 0x144);
+        // $ebp points above everything we skipped, so it's kept.
+        assert_eq!(walker.caller_regs["ebp"], 0x200);
+
+        // If $ebp points into the words we skipped, it belongs to a frame we
+        // skipped, so the saved one is dug out of the saved registers
+        // (which moved up by as much as the return address did).
+        write_u32(&mut stack, 0x124, 0x300);
+        let mut walker = win_search_walker(stack, 0x120);
+        eval_win_expr(WIN_RA_SEARCH_EXPR, &info, &mut walker).unwrap();
+
+        assert_eq!(walker.caller_regs["eip"], 0x1500);
+        assert_eq!(walker.caller_regs["esp"], 0x144);
+        assert_eq!(walker.caller_regs["ebp"], 0x300);
+    }
+
+    #[test]
+    fn test_stack_win_ebp_recovery() {
+        // The callee's $ebp is junk, so look through the saved registers
+        // (0x104..=0x110) from the top for something pointing at the stack.
+        let mut stack = vec![0; 0x400];
+        write_u32(&mut stack, 0x128, 0x1234);
+        write_u32(&mut stack, 0x110, 0x9999);
+        write_u32(&mut stack, 0x10c, 0x280);
+
+        let mut walker = win_search_walker(stack.clone(), 0x5000);
+        let info = whatever_win_info();
+        eval_win_expr(WIN_RA_SEARCH_EXPR, &info, &mut walker).unwrap();
+
+        assert_eq!(walker.caller_regs["eip"], 0x1234);
+        assert_eq!(walker.caller_regs["esp"], 0x12c);
+        assert_eq!(walker.caller_regs["ebp"], 0x280);
+
+        // But if the program restores $ebp, it's trusted.
+        let mut walker = win_search_walker(stack, 0x5000);
+        let expr = format!("{} $ebp $ebp =", WIN_RA_SEARCH_EXPR);
+        eval_win_expr(&expr, &info, &mut walker).unwrap();
+
+        assert_eq!(walker.caller_regs["ebp"], 0x5000);
+    }
+
+    #[test]
+    fn test_stack_win_fpo_alloca() {
+        // The function alloca'd 0x20 bytes, so everything is that much further
+        // up the stack than the frame size says.
+        let mut stack = vec![0; 0x400];
+        write_u32(&mut stack, 0x148, 0x1234);
+        write_u32(&mut stack, 0x128, 0x3a0);
+
+        let mut walker = win_search_walker(stack, 0x200);
+        let info = StackInfoWin {
+            program_string_or_base_pointer: WinStackThing::AllocatesBasePointer(true),
+            ..whatever_win_info()
+        };
+        walk_with_stack_win_fpo(&info, &mut walker).unwrap();
+
+        assert_eq!(walker.caller_regs["eip"], 0x1234);
+        assert_eq!(walker.caller_regs["esp"], 0x14c);
+        assert_eq!(walker.caller_regs["ebp"], 0x3a0);
+    }
+
+    #[test]
+    fn test_stack_win_fpo_no_alloca() {
+        // Frames without a base pointer only get the short alignment search,
+        // and keep the callee's $ebp.
+        let mut stack = vec![0; 0x400];
+        write_u32(&mut stack, 0x12c, 0x1234);
+
+        let mut walker = win_search_walker(stack, 0x200);
+        let info = whatever_win_info();
+        walk_with_stack_win_fpo(&info, &mut walker).unwrap();
+
+        assert_eq!(walker.caller_regs["eip"], 0x1234);
+        assert_eq!(walker.caller_regs["esp"], 0x130);
+        assert_eq!(walker.caller_regs["ebp"], 0x200);
+    }
+
     #[test]
     fn test_stack_win_corners() {
         // Making sure all the operators do what they should.
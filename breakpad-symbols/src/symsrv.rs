@@ -0,0 +1,307 @@
+//! Getting symbols from a Microsoft symbol server.
+//!
+//! Microsoft's symbol servers (and the many servers that imitate them) store
+//! PDBs rather than Breakpad symbol files, in the "symsrv" layout:
+//! `<name>.pdb/<debug identifier>/<name>.pdb`. The debug identifier there is
+//! the same one Breakpad uses, so everything needed to find a module's PDB is
+//! already in the minidump.
+//!
+//! The hard part is turning a PDB into a Breakpad symbol file, which is left
+//! to a [`SymbolConverter`]. [`PdbConverter`][crate::PdbConverter] (with the
+//! `pdb-converter` feature) does it in-process.
+//! [`DumpSymsConverter`][crate::DumpSymsConverter] runs [dump_syms], which
+//! understands PDBs (and finds the unwinding information of the matching PE
+//! file by itself).
+//!
+//! Symbol servers may also store PDBs compressed as CAB files (with the last
+//! letter of the extension replaced by an underscore, e.g. `name.pd_`). Those
+//! are tried when there's no uncompressed PDB, and extracted before they're
+//! converted (MSZIP compression only, which is what `symstore /compress` uses).
+//!
+//! [dump_syms]: https://github.com/mozilla/dump_syms
+
+use async_trait::async_trait;
+use reqwest::{Client, Url};
+use tempfile::NamedTempFile;
+
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::cab::{self, compressed_path};
+use crate::convert::{cache_symbols, download};
+use crate::retry::Retrier;
+use crate::{
+    leafname, relative_symbol_path, Module, RetryPolicy, SimpleSymbolSupplier, SymbolConverter,
+    SymbolError, SymbolFile, SymbolSupplier,
+};
+
+/// An implementation of `SymbolSupplier` that downloads PDBs from Microsoft
+/// symbol servers and converts them to Breakpad symbols.
+///
+/// Converted symbols are saved in the cache in the usual Breakpad layout
+/// (see [`relative_symbol_path`]), so the cache can also be used by a
+/// [`SimpleSymbolSupplier`] or [`HttpSymbolSupplier`][crate::HttpSymbolSupplier].
+///
+/// Only modules whose debug file is a PDB are looked up.
+pub struct MicrosoftSymbolSupplier {
+    /// HTTP Client to use for fetching PDBs.
+    client: Client,
+    /// Symbol server URLs to search for PDBs.
+    urls: Vec<Url>,
+    /// A `SimpleSymbolSupplier` for the symbols we've already converted.
+    local: SimpleSymbolSupplier,
+    /// A path at which to cache converted symbols.
+    cache: PathBuf,
+    /// A path to a temporary location where downloaded PDBs and converted
+    /// symbols can be written.
+    tmp: PathBuf,
+    /// The thing that turns PDBs into symbol files.
+    converter: Box<dyn SymbolConverter + Send + Sync>,
+    /// Retries failed requests.
+    retrier: Retrier,
+}
+
+impl MicrosoftSymbolSupplier {
+    /// Create a new `MicrosoftSymbolSupplier`.
+    ///
+    /// Symbols will be searched for in `cache` first, then PDBs will be
+    /// searched for at each of `urls` (e.g.
+    /// `https://msdl.microsoft.com/download/symbols/`), and converted with
+    /// `converter`.
+    pub fn new<C: SymbolConverter + Send + Sync + 'static>(
+        urls: Vec<String>,
+        cache: PathBuf,
+        tmp: PathBuf,
+        converter: C,
+        timeout: Duration,
+    ) -> MicrosoftSymbolSupplier {
+        let client = Client::builder().timeout(timeout).build().unwrap();
+        let urls = urls
+            .into_iter()
+            .filter_map(|mut u| {
+                if !u.ends_with('/') {
+                    u.push('/');
+                }
+                Url::parse(&u).ok()
+            })
+            .collect();
+        let local = SimpleSymbolSupplier::new(vec![]).with_cache(cache.clone());
+        MicrosoftSymbolSupplier {
+            client,
+            urls,
+            local,
+            cache,
+            tmp,
+            converter: Box::new(converter),
+            retrier: Retrier::default(),
+        }
+    }
+
+    /// Retry failed downloads, and stop asking servers that keep failing,
+    /// according to `policy`.
+    pub fn with_retry_policy(mut self, policy: RetryPolicy) -> MicrosoftSymbolSupplier {
+        self.retrier = Retrier::new(policy);
+        self
+    }
+}
+
+/// Get the path of `module`'s PDB on a symbol server, if it has one.
+fn pdb_path(module: &(dyn Module + Sync)) -> Option<String> {
+    let debug_file = module.debug_file()?;
+    let debug_id = module.debug_identifier()?;
+    let leaf = leafname(&debug_file);
+    if !leaf.to_lowercase().ends_with(".pdb") {
+        return None;
+    }
+    Some([leaf, &debug_id[..], leaf].join("/"))
+}
+
+/// Extract the PDB in the downloaded CAB file `cab` to a temporary file.
+fn extract_cab(cab: &NamedTempFile, tmp: &Path) -> Result<NamedTempFile, SymbolError> {
+    let pdb_data = cab::extract(&fs::read(cab.path())?)?;
+    let mut pdb = NamedTempFile::new_in(tmp)?;
+    pdb.write_all(&pdb_data)?;
+    Ok(pdb)
+}
+
+#[async_trait]
+impl SymbolSupplier for MicrosoftSymbolSupplier {
+    async fn locate_symbols(
+        &self,
+        module: &(dyn Module + Sync),
+    ) -> Result<SymbolFile, SymbolError> {
+        let pdb_path = pdb_path(module).ok_or(SymbolError::NotFound)?;
+        let sym_path = relative_symbol_path(module, "sym").ok_or(SymbolError::NotFound)?;
+
+        // Check for symbols we've already converted first.
+        let local_result = self.local.locate_symbols(module).await;
+        if !matches!(local_result, Err(SymbolError::NotFound)) {
+            return local_result;
+        }
+
+        let paths = [pdb_path.clone(), compressed_path(&pdb_path)];
+        for base_url in &self.urls {
+            for path in &paths {
+                let url = match base_url.join(path) {
+                    Ok(url) => url,
+                    Err(_) => continue,
+                };
+                let pdb = match download(&self.client, &self.retrier, &url, &self.tmp).await {
+                    Ok(pdb) => pdb,
+                    Err(SymbolError::NotFound) => continue,
+                    Err(e) => return Err(e),
+                };
+                let pdb = if path.ends_with('_') {
+                    extract_cab(&pdb, &self.tmp)?
+                } else {
+                    pdb
+                };
+                let symbols = self.converter.convert_module(pdb.path(), module).await?;
+                let mut symbol_file = SymbolFile::from_bytes(&symbols)?;
+                symbol_file.url = Some(url.to_string());
+                cache_symbols(&symbols, &self.cache, &self.tmp, &sym_path, &url);
+                return Ok(symbol_file);
+            }
+        }
+        Err(SymbolError::NotFound)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::cab::test::uncompressed_cab;
+    use crate::test::serve_file;
+    use crate::{DumpSymsConverter, SimpleModule};
+    use std::path::Path;
+    use std::sync::{Arc, Mutex};
+
+    const SYMBOLS: &[u8] = b"MODULE windows x86 ABCD1234 foo.pdb
+FILE 1 foo.c
+FUNC 1000 30 10 some func
+1000 30 100 1
+";
+
+    /// Pretends the PDB is already a symbol file, and remembers what it saw.
+    #[derive(Default)]
+    struct TestConverter {
+        seen: Mutex<Vec<Vec<u8>>>,
+    }
+
+    #[async_trait]
+    impl SymbolConverter for Arc<TestConverter> {
+        async fn convert(&self, pdb: &Path) -> Result<Vec<u8>, SymbolError> {
+            let contents = std::fs::read(pdb)?;
+            self.seen.lock().unwrap().push(contents.clone());
+            Ok(contents)
+        }
+    }
+
+    fn make_supplier<C: SymbolConverter + Send + Sync + 'static>(
+        base: &str,
+        cache: &Path,
+        tmp: &Path,
+        converter: C,
+    ) -> MicrosoftSymbolSupplier {
+        MicrosoftSymbolSupplier::new(
+            vec![format!("{}/missing", base), format!("{}/symbols", base)],
+            cache.to_path_buf(),
+            tmp.to_path_buf(),
+            converter,
+            Duration::from_secs(10),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_microsoft_symbol_supplier() {
+        let (base, requests) = serve_file("/symbols/foo.pdb/ABCD1234/foo.pdb", SYMBOLS).await;
+        let cache = tempfile::tempdir().unwrap();
+        let tmp = tempfile::tempdir().unwrap();
+        let converter = Arc::new(TestConverter::default());
+        let supplier = make_supplier(&base, cache.path(), tmp.path(), converter.clone());
+
+        let m = SimpleModule::new("c:\\build\\foo.pdb", "ABCD1234");
+        let symbols = supplier.locate_symbols(&m).await.unwrap();
+        assert_eq!(
+            symbols.url.as_deref(),
+            Some(&*format!("{}/symbols/foo.pdb/ABCD1234/foo.pdb", base))
+        );
+        assert_eq!(
+            *requests.lock().unwrap(),
+            vec![
+                "/missing/foo.pdb/ABCD1234/foo.pdb",
+                "/missing/foo.pdb/ABCD1234/foo.pd_",
+                "/symbols/foo.pdb/ABCD1234/foo.pdb"
+            ]
+        );
+        assert_eq!(*converter.seen.lock().unwrap(), vec![SYMBOLS.to_vec()]);
+
+        // The converted symbols were cached where everyone else looks for them.
+        assert!(cache.path().join("foo.pdb/ABCD1234/foo.sym").is_file());
+        supplier.locate_symbols(&m).await.unwrap();
+        assert_eq!(requests.lock().unwrap().len(), 3);
+        assert_eq!(converter.seen.lock().unwrap().len(), 1);
+
+        // Modules without PDBs aren't looked for.
+        let elf = SimpleModule::new("libfoo.so", "ABCD1234");
+        assert_eq!(
+            supplier.locate_symbols(&elf).await,
+            Err(SymbolError::NotFound)
+        );
+        assert_eq!(requests.lock().unwrap().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_compressed_pdb() {
+        let cab = Box::leak(uncompressed_cab(SYMBOLS).into_boxed_slice());
+        let (base, requests) = serve_file("/symbols/foo.pdb/ABCD1234/foo.pd_", cab).await;
+        let cache = tempfile::tempdir().unwrap();
+        let tmp = tempfile::tempdir().unwrap();
+        let converter = Arc::new(TestConverter::default());
+        let supplier = make_supplier(&base, cache.path(), tmp.path(), converter.clone());
+
+        let m = SimpleModule::new("foo.pdb", "ABCD1234");
+        let symbols = supplier.locate_symbols(&m).await.unwrap();
+        assert_eq!(
+            symbols.url.as_deref(),
+            Some(&*format!("{}/symbols/foo.pdb/ABCD1234/foo.pd_", base))
+        );
+        assert_eq!(requests.lock().unwrap().len(), 4);
+        // The converter got the PDB out of the CAB file.
+        assert_eq!(*converter.seen.lock().unwrap(), vec![SYMBOLS.to_vec()]);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_dump_syms_converter() {
+        // `cat` makes a fine dump_syms when the PDB is already a symbol file.
+        let (base, _requests) = serve_file("/symbols/foo.pdb/ABCD1234/foo.pdb", SYMBOLS).await;
+        let cache = tempfile::tempdir().unwrap();
+        let tmp = tempfile::tempdir().unwrap();
+        let supplier = make_supplier(
+            &base,
+            cache.path(),
+            tmp.path(),
+            DumpSymsConverter::new("cat"),
+        );
+
+        let m = SimpleModule::new("foo.pdb", "ABCD1234");
+        let symbols = supplier.locate_symbols(&m).await.unwrap();
+        assert!(symbols.functions.get(0x1010).is_some());
+
+        // A converter that fails to run is an error, not a missing file.
+        let cache = tempfile::tempdir().unwrap();
+        let supplier = make_supplier(
+            &base,
+            cache.path(),
+            tmp.path(),
+            DumpSymsConverter::new("false"),
+        );
+        assert!(matches!(
+            supplier.locate_symbols(&m).await,
+            Err(SymbolError::LoadError(_))
+        ));
+    }
+}
@@ -55,9 +55,62 @@ pub struct MINIDUMP_HEADER {
     pub stream_directory_rva: RVA,
     pub checksum: u32,
     pub time_date_stamp: u32,
+    /// The kind of minidump this is, see [`MinidumpType`].
     pub flags: u64,
 }
 
+impl MINIDUMP_HEADER {
+    /// The kind of minidump this is, as described by the `flags` field.
+    ///
+    /// Unknown bits are ignored.
+    pub fn dump_type(&self) -> MinidumpType {
+        MinidumpType::from_bits_truncate(self.flags)
+    }
+
+    /// The high word of `version`, which is specific to the software that wrote the minidump.
+    ///
+    /// Only the low word is required to be [`MINIDUMP_VERSION`].
+    pub fn implementation_version(&self) -> u16 {
+        (self.version >> 16) as u16
+    }
+}
+
+bitflags! {
+    /// Potential values for [`MINIDUMP_HEADER::flags`]
+    ///
+    /// This matches the [Microsoft enum][msdn] `MINIDUMP_TYPE`. A minidump with
+    /// none of these set is a `MiniDumpNormal` minidump.
+    ///
+    /// [msdn]: https://docs.microsoft.com/en-us/windows/win32/api/minidumpapiset/ne-minidumpapiset-minidump_type
+    pub struct MinidumpType: u64 {
+        const MiniDumpWithDataSegs                   = 0x00000001;
+        const MiniDumpWithFullMemory                 = 0x00000002;
+        const MiniDumpWithHandleData                 = 0x00000004;
+        const MiniDumpFilterMemory                   = 0x00000008;
+        const MiniDumpScanMemory                     = 0x00000010;
+        const MiniDumpWithUnloadedModules            = 0x00000020;
+        const MiniDumpWithIndirectlyReferencedMemory = 0x00000040;
+        const MiniDumpFilterModulePaths              = 0x00000080;
+        const MiniDumpWithProcessThreadData          = 0x00000100;
+        const MiniDumpWithPrivateReadWriteMemory     = 0x00000200;
+        const MiniDumpWithoutOptionalData            = 0x00000400;
+        const MiniDumpWithFullMemoryInfo             = 0x00000800;
+        const MiniDumpWithThreadInfo                 = 0x00001000;
+        const MiniDumpWithCodeSegs                   = 0x00002000;
+        const MiniDumpWithoutAuxiliaryState          = 0x00004000;
+        const MiniDumpWithFullAuxiliaryState         = 0x00008000;
+        const MiniDumpWithPrivateWriteCopyMemory     = 0x00010000;
+        const MiniDumpIgnoreInaccessibleMemory       = 0x00020000;
+        const MiniDumpWithTokenInformation           = 0x00040000;
+        const MiniDumpWithModuleHeaders              = 0x00080000;
+        const MiniDumpFilterTriage                   = 0x00100000;
+        const MiniDumpWithAvxXStateContext           = 0x00200000;
+        const MiniDumpWithIptTrace                   = 0x00400000;
+        const MiniDumpScanInaccessiblePartialPages   = 0x00800000;
+        const MiniDumpFilterWriteCombinedMemory      = 0x01000000;
+    }
+}
+
 /// A location within a minidump file comprised of an offset and a size.
 ///
 /// This struct matches the [Microsoft struct][msdn] of the same name.
@@ -91,6 +144,39 @@ pub struct MINIDUMP_MEMORY_DESCRIPTOR {
     pub memory: MINIDUMP_LOCATION_DESCRIPTOR,
 }
 
+/// A range of memory contained within a full-memory minidump.
+///
+/// Unlike [`MINIDUMP_MEMORY_DESCRIPTOR`], this does not contain the location of the memory
+/// in the dump. All the ranges in a [`MINIDUMP_MEMORY64_LIST`] are stored contiguously,
+/// starting at [`MINIDUMP_MEMORY64_LIST::base_rva`], in the order they are listed.
+///
+/// This struct matches the [Microsoft struct][msdn] of the same name.
+///
+/// [msdn]: https://docs.microsoft.com/en-us/windows/win32/api/minidumpapiset/ns-minidumpapiset-minidump_memory_descriptor64
+#[derive(Debug, Copy, Clone, Default, Pread, SizeWith)]
+pub struct MINIDUMP_MEMORY_DESCRIPTOR64 {
+    /// The base address of this memory range from the process.
+    pub start_of_memory_range: u64,
+    /// The size of this memory range.
+    pub data_size: u64,
+}
+
+/// The header for a list of memory ranges in a full-memory minidump.
+///
+/// This is the format of the [`MINIDUMP_STREAM_TYPE::Memory64ListStream`]. The individual
+/// [`MINIDUMP_MEMORY_DESCRIPTOR64`] entries follow this header in the stream.
+///
+/// This struct matches the [Microsoft struct][msdn] of the same name.
+///
+/// [msdn]: https://docs.microsoft.com/en-us/windows/win32/api/minidumpapiset/ns-minidumpapiset-minidump_memory64_list
+#[derive(Debug, Copy, Clone, Default, Pread, SizeWith)]
+pub struct MINIDUMP_MEMORY64_LIST {
+    /// The number of memory ranges in the list.
+    pub number_of_memory_ranges: u64,
+    /// The offset of the first range's data in the minidump.
+    pub base_rva: u64,
+}
+
 /// Information about a data stream contained in a minidump file.
 ///
 /// The minidump header contains a pointer to a list of these structs which allows locating
@@ -164,6 +250,9 @@ pub enum MINIDUMP_STREAM_TYPE {
     Memory64ListStream = 9,
     CommentStreamA = 10,
     CommentStreamW = 11,
+    /// The handles the process had open
+    ///
+    /// See ['MINIDUMP_HANDLE_DATA_STREAM'].
     HandleDataStream = 12,
     FunctionTable = 13,
     /// The list of executable modules from the process that were unloaded by the time of the crash
@@ -186,6 +275,9 @@ pub enum MINIDUMP_STREAM_TYPE {
     ///
     /// See ['MINIDUMP_MEMORY_INFO_LIST'].
     MemoryInfoListStream = 16,
+    /// Extra information about the threads of the process
+    ///
+    /// See ['MINIDUMP_THREAD_INFO_LIST'].
     ThreadInfoListStream = 17,
     HandleOperationListStream = 18,
     TokenStream = 19,
@@ -356,6 +448,22 @@ pub const VS_FFI_SIGNATURE: u32 = 0xfeef04bd;
 /// The expected value of `VS_FIXEDFILEINFO.struct_version`
 pub const VS_FFI_STRUCVERSION: u32 = 0x00010000;
 
+bitflags! {
+    /// Potential values for [`VS_FIXEDFILEINFO::file_flags`]
+    ///
+    /// See [Microsoft's documentation][msdn] for details.
+    ///
+    /// [msdn]: https://docs.microsoft.com/en-us/windows/win32/api/verrsrc/ns-verrsrc-vs_fixedfileinfo
+    pub struct FileFlags: u32 {
+        const VS_FF_DEBUG        = 0x01;
+        const VS_FF_PRERELEASE   = 0x02;
+        const VS_FF_PATCHED      = 0x04;
+        const VS_FF_PRIVATEBUILD = 0x08;
+        const VS_FF_INFOINFERRED = 0x10;
+        const VS_FF_SPECIALBUILD = 0x20;
+    }
+}
+
 /// Known values for the `signature` field of CodeView records
 ///
 /// In addition to the two CodeView record formats used for linking
@@ -599,6 +707,145 @@ pub struct MINIDUMP_THREAD {
     pub thread_context: MINIDUMP_LOCATION_DESCRIPTOR,
 }
 
+/// The header of the ThreadInfoListStream
+///
+/// This struct matches the [Microsoft struct][msdn] of the same name.
+///
+/// [msdn]: https://docs.microsoft.com/en-us/windows/win32/api/minidumpapiset/ns-minidumpapiset-minidump_thread_info_list
+#[derive(Debug, Clone, Pread, SizeWith)]
+pub struct MINIDUMP_THREAD_INFO_LIST {
+    /// The size of this header
+    pub size_of_header: u32,
+    /// The size of each entry in the list
+    pub size_of_entry: u32,
+    /// The number of entries in the list
+    pub number_of_entries: u32,
+}
+
+/// Extra information about a single thread from a minidump
+///
+/// This struct matches the [Microsoft struct][msdn] of the same name.
+///
+/// [msdn]: https://docs.microsoft.com/en-us/windows/win32/api/minidumpapiset/ns-minidumpapiset-minidump_thread_info
+#[derive(Debug, Clone, Default, PartialEq, Eq, Pread, SizeWith)]
+pub struct MINIDUMP_THREAD_INFO {
+    /// The identifier of the thread
+    pub thread_id: u32,
+    /// What went wrong while writing the thread to the minidump, if anything
+    ///
+    /// See [`ThreadInfoFlags`] for valid values.
+    pub dump_flags: u32,
+    /// An `HRESULT` for the error while writing the thread, if any
+    pub dump_error: u32,
+    /// The exit code of the thread, if it had exited
+    pub exit_status: u32,
+    /// When the thread was created, as a `FILETIME`
+    pub create_time: u64,
+    /// When the thread exited, as a `FILETIME`
+    pub exit_time: u64,
+    /// How long the thread spent running in kernel mode, in 100-nanosecond units
+    pub kernel_time: u64,
+    /// How long the thread spent running in user mode, in 100-nanosecond units
+    pub user_time: u64,
+    /// The address the thread started running at
+    pub start_address: u64,
+    /// The processors the thread was allowed to run on, as a bitmask
+    pub affinity: u64,
+}
+
+bitflags! {
+    /// Potential values for [`MINIDUMP_THREAD_INFO::dump_flags`]
+    ///
+    /// See [Microsoft's documentation][msdn] for details.
+    ///
+    /// [msdn]: https://docs.microsoft.com/en-us/windows/win32/api/minidumpapiset/ns-minidumpapiset-minidump_thread_info
+    pub struct ThreadInfoFlags: u32 {
+        const MINIDUMP_THREAD_INFO_ERROR_THREAD    = 0x01;
+        const MINIDUMP_THREAD_INFO_WRITING_THREAD  = 0x02;
+        const MINIDUMP_THREAD_INFO_EXITED_THREAD   = 0x04;
+        const MINIDUMP_THREAD_INFO_INVALID_INFO    = 0x08;
+        const MINIDUMP_THREAD_INFO_INVALID_CONTEXT = 0x10;
+        const MINIDUMP_THREAD_INFO_INVALID_TEB     = 0x20;
+    }
+}
+
+/// The header of the HandleDataStream
+///
+/// This struct matches the [Microsoft struct][msdn] of the same name.
+///
+/// [msdn]: https://docs.microsoft.com/en-us/windows/win32/api/minidumpapiset/ns-minidumpapiset-minidump_handle_data_stream
+#[derive(Debug, Clone, Pread, SizeWith)]
+pub struct MINIDUMP_HANDLE_DATA_STREAM {
+    /// The size of this header
+    pub size_of_header: u32,
+    /// The size of each descriptor in the stream
+    ///
+    /// This is the size of either [`MINIDUMP_HANDLE_DESCRIPTOR`] or
+    /// [`MINIDUMP_HANDLE_DESCRIPTOR_2`].
+    pub size_of_descriptor: u32,
+    /// The number of descriptors in the stream
+    pub number_of_descriptors: u32,
+    pub reserved: u32,
+}
+
+/// Information about a handle the process had open
+///
+/// This struct matches the [Microsoft struct][msdn] of the same name.
+///
+/// [msdn]: https://docs.microsoft.com/en-us/windows/win32/api/minidumpapiset/ns-minidumpapiset-minidump_handle_descriptor
+#[derive(Debug, Clone, Default, PartialEq, Eq, Pread, SizeWith)]
+pub struct MINIDUMP_HANDLE_DESCRIPTOR {
+    /// The value of the handle
+    pub handle: u64,
+    /// An offset to a length-prefixed UTF-16LE string containing the name of the object type
+    ///
+    /// 0 if there's no name.
+    pub type_name_rva: RVA,
+    /// An offset to a length-prefixed UTF-16LE string containing the name of the object
+    ///
+    /// 0 if there's no name.
+    pub object_name_rva: RVA,
+    /// The attributes of the handle, like `OBJ_INHERIT`
+    pub attributes: u32,
+    /// The access rights the handle was opened with
+    pub granted_access: u32,
+    /// The number of handles to the object
+    pub handle_count: u32,
+    /// The number of pointers to the object
+    pub pointer_count: u32,
+}
+
+/// Information about a handle the process had open, with extra information
+/// about the object
+///
+/// This struct matches the [Microsoft struct][msdn] of the same name.
+///
+/// [msdn]: https://docs.microsoft.com/en-us/windows/win32/api/minidumpapiset/ns-minidumpapiset-minidump_handle_descriptor_2
+#[derive(Debug, Clone, Default, PartialEq, Eq, Pread, SizeWith)]
+pub struct MINIDUMP_HANDLE_DESCRIPTOR_2 {
+    /// The value of the handle
+    pub handle: u64,
+    /// An offset to a length-prefixed UTF-16LE string containing the name of the object type
+    ///
+    /// 0 if there's no name.
+    pub type_name_rva: RVA,
+    /// An offset to a length-prefixed UTF-16LE string containing the name of the object
+    ///
+    /// 0 if there's no name.
+    pub object_name_rva: RVA,
+    /// The attributes of the handle, like `OBJ_INHERIT`
+    pub attributes: u32,
+    /// The access rights the handle was opened with
+    pub granted_access: u32,
+    /// The number of handles to the object
+    pub handle_count: u32,
+    /// The number of pointers to the object
+    pub pointer_count: u32,
+    /// An offset to a list of `MINIDUMP_HANDLE_OBJECT_INFORMATION` structs, or 0
+    pub object_info_rva: RVA,
+    pub reserved0: u32,
+}
+
 /// Information about the exception that caused the process to terminate.
 ///
 /// This struct matches the [Microsoft struct][msdn] of the same name.
@@ -689,6 +936,10 @@ pub enum ExceptionCodeWindows {
     OUT_OF_MEMORY = 0xe0000008,
     /// Per <http://support.microsoft.com/kb/185294>, generated by Visual C++ compiler
     UNHANDLED_CPP_EXCEPTION = 0xe06d7363,
+    /// Exception raised for an unhandled .NET exception by the CLR
+    CLR_EXCEPTION = 0xe0434352,
+    /// Exception raised by `SetThreadName` to name a thread for an attached debugger
+    SET_THREAD_NAME = 0x406d1388,
     /// Fake exception code used by Crashpad
     SIMULATED = 0x0517a7ed,
 }
@@ -3517,6 +3768,70 @@ pub enum WinErrorWindows {
     ERROR_API_UNAVAILABLE = 15841,
 }
 
+impl WinErrorWindows {
+    /// A short description of the error, like Windows' own (from
+    /// `FormatMessage`), for the most common errors.
+    pub fn description(self) -> Option<&'static str> {
+        use WinErrorWindows::*;
+        Some(match self {
+            ERROR_SUCCESS => "The operation completed successfully.",
+            ERROR_INVALID_FUNCTION => "Incorrect function.",
+            ERROR_FILE_NOT_FOUND => "The system cannot find the file specified.",
+            ERROR_PATH_NOT_FOUND => "The system cannot find the path specified.",
+            ERROR_TOO_MANY_OPEN_FILES => "The system cannot open the file.",
+            ERROR_ACCESS_DENIED => "Access is denied.",
+            ERROR_INVALID_HANDLE => "The handle is invalid.",
+            ERROR_NOT_ENOUGH_MEMORY => "Not enough memory resources are available to process this command.",
+            ERROR_INVALID_DATA => "The data is invalid.",
+            ERROR_OUTOFMEMORY => "Not enough memory resources are available to complete this operation.",
+            ERROR_WRITE_PROTECT => "The media is write protected.",
+            ERROR_NOT_READY => "The device is not ready.",
+            ERROR_GEN_FAILURE => "A device attached to the system is not functioning.",
+            ERROR_SHARING_VIOLATION => "The process cannot access the file because it is being used by another process.",
+            ERROR_LOCK_VIOLATION => "The process cannot access the file because another process has locked a portion of the file.",
+            ERROR_HANDLE_EOF => "Reached the end of the file.",
+            ERROR_NOT_SUPPORTED => "The request is not supported.",
+            ERROR_BAD_NETPATH => "The network path was not found.",
+            ERROR_NETNAME_DELETED => "The specified network name is no longer available.",
+            ERROR_FILE_EXISTS => "The file exists.",
+            ERROR_INVALID_PARAMETER => "The parameter is incorrect.",
+            ERROR_BROKEN_PIPE => "The pipe has been ended.",
+            ERROR_DISK_FULL => "There is not enough space on the disk.",
+            ERROR_SEM_TIMEOUT => "The semaphore timeout period has expired.",
+            ERROR_INSUFFICIENT_BUFFER => "The data area passed to a system call is too small.",
+            ERROR_INVALID_NAME => "The filename, directory name, or volume label syntax is incorrect.",
+            ERROR_MOD_NOT_FOUND => "The specified module could not be found.",
+            ERROR_PROC_NOT_FOUND => "The specified procedure could not be found.",
+            ERROR_DIR_NOT_EMPTY => "The directory is not empty.",
+            ERROR_BAD_PATHNAME => "The specified path is invalid.",
+            ERROR_ALREADY_EXISTS => "Cannot create a file when that file already exists.",
+            ERROR_BAD_EXE_FORMAT => "The file is not a valid Win32 application.",
+            ERROR_ENVVAR_NOT_FOUND => "The system could not find the environment option that was entered.",
+            ERROR_PIPE_BUSY => "All pipe instances are busy.",
+            ERROR_NO_DATA => "The pipe is being closed.",
+            ERROR_PIPE_NOT_CONNECTED => "No process is on the other end of the pipe.",
+            ERROR_MORE_DATA => "More data is available.",
+            ERROR_NO_MORE_ITEMS => "No more data is available.",
+            ERROR_PARTIAL_COPY => "Only part of a ReadProcessMemory or WriteProcessMemory request was completed.",
+            ERROR_INVALID_ADDRESS => "Attempt to access invalid address.",
+            ERROR_ELEVATION_REQUIRED => "The requested operation requires elevation.",
+            ERROR_OPERATION_ABORTED => "The I/O operation has been aborted because of either a thread exit or an application request.",
+            ERROR_IO_PENDING => "Overlapped I/O operation is in progress.",
+            ERROR_NOACCESS => "Invalid access to memory location.",
+            ERROR_STACK_OVERFLOW => "Recursion too deep; the stack overflowed.",
+            ERROR_DLL_INIT_FAILED => "A dynamic link library (DLL) initialization routine failed.",
+            ERROR_NOT_FOUND => "Element not found.",
+            ERROR_CANCELLED => "The operation was canceled by the user.",
+            ERROR_PRIVILEGE_NOT_HELD => "A required privilege is not held by the client.",
+            ERROR_INVALID_WINDOW_HANDLE => "Invalid window handle.",
+            ERROR_CLASS_ALREADY_EXISTS => "Class already exists.",
+            ERROR_COMMITMENT_LIMIT => "The paging file is too small for this operation to complete.",
+            ERROR_TIMEOUT => "This operation returned because the timeout period expired.",
+            _ => return None,
+        })
+    }
+}
+
 /// Values for [`MINIDUMP_EXCEPTION::exception_code`] for crashes on Windows and also
 /// for sub-codes and last reported errors
 ///
@@ -6566,6 +6881,8 @@ pub enum ExceptionCodeLinuxSigfpeKind {
     FPE_FLTRES = 6,
     FPE_FLTINV = 7,
     FPE_FLTSUB = 8,
+    FPE_FLTUNK = 14,
+    FPE_CONDTRAP = 15,
 }
 
 #[derive(Copy, Clone, PartialEq, Debug, Primitive)]
@@ -6574,6 +6891,11 @@ pub enum ExceptionCodeLinuxSigsegvKind {
     SEGV_ACCERR = 2,
     SEGV_BNDERR = 3,
     SEGV_PKUERR = 4,
+    SEGV_ACCADI = 5,
+    SEGV_ADIDERR = 6,
+    SEGV_ADIPERR = 7,
+    SEGV_MTEAERR = 8,
+    SEGV_MTESERR = 9,
 }
 
 #[derive(Copy, Clone, PartialEq, Debug, Primitive)]
@@ -6612,8 +6934,11 @@ pub enum ExceptionCodeMac {
     EXC_SYSCALL = 7,
     EXC_MACH_SYSCALL = 8,
     EXC_RPC_ALERT = 9,
+    /// abnormal process exit, code holds the signal and the original exception
+    EXC_CRASH = 10,
     EXC_RESOURCE = 11,
     EXC_GUARD = 12,
+    EXC_CORPSE_NOTIFY = 13,
     /// Fake exception code used by Crashpad's SimulateCrash ('CPsx')
     SIMULATED = 0x43507378,
 }
@@ -6628,10 +6953,14 @@ pub enum ExceptionCodeMacBadAccessKernType {
     // These are relevant kern_return_t values from mach/kern_return.h
     KERN_INVALID_ADDRESS = 1,
     KERN_PROTECTION_FAILURE = 2,
+    KERN_NO_SPACE = 3,
+    KERN_INVALID_ARGUMENT = 4,
     KERN_FAILURE = 5,
+    KERN_RESOURCE_SHORTAGE = 6,
     KERN_NO_ACCESS = 8,
     KERN_MEMORY_FAILURE = 9,
     KERN_MEMORY_ERROR = 10,
+    KERN_ABORTED = 14,
     KERN_CODESIGN_ERROR = 50,
 }
 
@@ -6640,6 +6969,9 @@ pub enum ExceptionCodeMacBadAccessKernType {
 pub enum ExceptionCodeMacBadAccessArmType {
     EXC_ARM_DA_ALIGN = 0x0101,
     EXC_ARM_DA_DEBUG = 0x0102,
+    EXC_ARM_SP_ALIGN = 0x0103,
+    EXC_ARM_SWP = 0x0104,
+    EXC_ARM_PAC_FAIL = 0x0105,
 }
 
 /// Mac/iOS Ppc Userland Bad Access Exceptions
@@ -6708,6 +7040,23 @@ pub enum ExceptionCodeMacBadInstructionX86Type {
     // EXC_I386_ENDPERR   = 33: should not occur
 }
 
+/// Mac/iOS Arm Arithmetic Exceptions
+#[derive(Copy, Clone, PartialEq, Debug, Primitive)]
+pub enum ExceptionCodeMacArithmeticArmType {
+    /// Float Invalid Operation
+    EXC_ARM_FP_IO = 1,
+    /// Float Divide-By-Zero
+    EXC_ARM_FP_DZ = 2,
+    /// Float Overflow
+    EXC_ARM_FP_OF = 3,
+    /// Float Underflow
+    EXC_ARM_FP_UF = 4,
+    /// Float Inexact
+    EXC_ARM_FP_IX = 5,
+    /// Float Input Denormal
+    EXC_ARM_FP_ID = 6,
+}
+
 /// Mac/iOS Ppc Arithmetic Exceptions
 #[derive(Copy, Clone, PartialEq, Debug, Primitive)]
 pub enum ExceptionCodeMacArithmeticPpcType {
@@ -6750,6 +7099,8 @@ pub enum ExceptionCodeMacArithmeticX86Type {
 #[derive(Copy, Clone, PartialEq, Debug, Primitive)]
 pub enum ExceptionCodeMacSoftwareType {
     SIGABRT = 0x00010002u32,
+    /// A signal delivered as a Mach exception, the signal number is in the subcode
+    EXC_SOFT_SIGNAL = 0x00010003,
     UNCAUGHT_NS_EXCEPTION = 0xDEADC0DE,
     EXC_PPC_TRAP = 0x00000001,
     EXC_PPC_MIGRATE = 0x00010100,
@@ -6915,13 +7266,19 @@ pub const CONTEXT_CPU_MASK: u32 = 0xffffff00;
 /// x86 and x64 contexts have this bit set in their `context_flags` when they have
 /// extra XSTATE beyond the traditional context definition.
 pub const CONTEXT_HAS_XSTATE: u32 = 0x00000040;
+/// x86 and x64 contexts have this bit set in their `context_flags` when their control
+/// registers (including the instruction and stack pointers) are filled in.
+pub const CONTEXT_CONTROL: u32 = 0x00000001;
+/// x86 and x64 contexts have this bit set in their `context_flags` when their integer
+/// registers are filled in.
+pub const CONTEXT_INTEGER: u32 = 0x00000002;
 
 bitflags! {
     /// CPU type values in the `context_flags` member of `CONTEXT_` structs
     ///
     /// This applies to the [`CONTEXT_ARM`], [`CONTEXT_PPC`], [`CONTEXT_MIPS`],
-    /// [`CONTEXT_AMD64`], [`CONTEXT_ARM64`], [`CONTEXT_PPC64`], [`CONTEXT_SPARC`] and
-    /// [`CONTEXT_ARM64_OLD`] structs.
+    /// [`CONTEXT_MIPS64`], [`CONTEXT_AMD64`], [`CONTEXT_ARM64`], [`CONTEXT_PPC64`],
+    /// [`CONTEXT_SPARC`], [`CONTEXT_RISCV64`] and [`CONTEXT_ARM64_OLD`] structs.
     pub struct ContextFlagsCpu: u32 {
         const CONTEXT_IA64 = 0x80000;
         /// Super-H, includes SH3, from winnt.h in the Windows CE 5.0 SDK
@@ -6941,6 +7298,7 @@ bitflags! {
         const CONTEXT_MIPS64 = 0x80000;
         const CONTEXT_PPC = 0x20000000;
         const CONTEXT_PPC64 = 0x1000000;
+        const CONTEXT_RISCV64 = 0x8000000;
         const CONTEXT_SPARC = 0x10000000;
         const CONTEXT_X86 = 0x10000;
     }
@@ -7182,7 +7540,7 @@ impl Arm64RegisterNumbers {
 }
 
 /// MIPS floating point state
-#[derive(Debug, Clone, Pread, SizeWith)]
+#[derive(Debug, Clone, Default, Pread, SizeWith)]
 #[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub struct FLOATING_SAVE_AREA_MIPS {
     pub regs: [u64; 32],
@@ -7193,7 +7551,7 @@ pub struct FLOATING_SAVE_AREA_MIPS {
 /// A MIPS CPU context
 ///
 /// This is a Breakpad extension, as there is no definition of `CONTEXT` for MIPS in WinNT.h.
-#[derive(Debug, Clone, Pread, SizeWith)]
+#[derive(Debug, Clone, Default, Pread, SizeWith)]
 #[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub struct CONTEXT_MIPS {
     pub context_flags: u32,
@@ -7212,7 +7570,31 @@ pub struct CONTEXT_MIPS {
     pub float_save: FLOATING_SAVE_AREA_MIPS,
 }
 
-/// Offsets into [`CONTEXT_MIPS::iregs`] for registers with a dedicated or conventional purpose
+/// A MIPS64 CPU context
+///
+/// This is a Breakpad extension. It has the same layout as [`CONTEXT_MIPS`], but
+/// `context_flags` contains `CONTEXT_MIPS64` and the registers are 64 bits wide.
+#[derive(Debug, Clone, Default, Pread, SizeWith)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub struct CONTEXT_MIPS64 {
+    pub context_flags: u32,
+    pub _pad0: u32,
+    pub iregs: [u64; 32],
+    pub mdhi: u64,
+    pub mdlo: u64,
+    pub hi: [u32; 3],
+    pub lo: [u32; 3],
+    pub dsp_control: u32,
+    pub _pad1: u32,
+    pub epc: u64,
+    pub badvaddr: u64,
+    pub status: u32,
+    pub cause: u32,
+    pub float_save: FLOATING_SAVE_AREA_MIPS,
+}
+
+/// Offsets into [`CONTEXT_MIPS::iregs`] and [`CONTEXT_MIPS64::iregs`] for registers
+/// with a dedicated or conventional purpose
 #[repr(usize)]
 #[derive(Copy, Clone, PartialEq, Debug)]
 pub enum MipsRegisterNumbers {
@@ -7230,8 +7612,27 @@ pub enum MipsRegisterNumbers {
     ReturnAddress = 31,
 }
 
+impl MipsRegisterNumbers {
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::S0 => "s0",
+            Self::S1 => "s1",
+            Self::S2 => "s2",
+            Self::S3 => "s3",
+            Self::S4 => "s4",
+            Self::S5 => "s5",
+            Self::S6 => "s6",
+            Self::S7 => "s7",
+            Self::GlobalPointer => "gp",
+            Self::StackPointer => "sp",
+            Self::FramePointer => "fp",
+            Self::ReturnAddress => "ra",
+        }
+    }
+}
+
 /// PPC floating point state
-#[derive(Debug, Clone, Pread, SizeWith)]
+#[derive(Debug, Clone, Default, Pread, SizeWith)]
 #[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub struct FLOATING_SAVE_AREA_PPC {
     pub fpregs: [u64; 32],
@@ -7240,7 +7641,7 @@ pub struct FLOATING_SAVE_AREA_PPC {
 }
 
 /// PPC vector state
-#[derive(Debug, Clone, Pread, SizeWith)]
+#[derive(Debug, Clone, Default, Pread, SizeWith)]
 #[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub struct VECTOR_SAVE_AREA_PPC {
     pub save_vr: [u128; 32],
@@ -7253,7 +7654,7 @@ pub struct VECTOR_SAVE_AREA_PPC {
 /// A PPC CPU context
 ///
 /// This is a Breakpad extension, as there is no definition of `CONTEXT` for PPC in WinNT.h.
-#[derive(Debug, Clone, Pread, SizeWith)]
+#[derive(Debug, Clone, Default, Pread, SizeWith)]
 #[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub struct CONTEXT_PPC {
     pub context_flags: u32,
@@ -7277,10 +7678,18 @@ pub enum PpcRegisterNumbers {
     StackPointer = 1,
 }
 
+impl PpcRegisterNumbers {
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::StackPointer => "r1",
+        }
+    }
+}
+
 /// A PPC64 CPU context
 ///
 /// This is a Breakpad extension, as there is no definition of `CONTEXT` for PPC64 in WinNT.h.
-#[derive(Debug, Clone, Pread, SizeWith)]
+#[derive(Debug, Clone, Default, Pread, SizeWith)]
 #[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub struct CONTEXT_PPC64 {
     pub context_flags: u64,
@@ -7301,10 +7710,20 @@ pub struct CONTEXT_PPC64 {
 #[derive(Copy, Clone, PartialEq, Debug)]
 pub enum Ppc64RegisterNumbers {
     StackPointer = 1,
+    TableOfContents = 2,
+}
+
+impl Ppc64RegisterNumbers {
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::StackPointer => "r1",
+            Self::TableOfContents => "r2",
+        }
+    }
 }
 
 /// SPARC floating point state
-#[derive(Debug, Clone, Pread, SizeWith)]
+#[derive(Debug, Clone, Default, Pread, SizeWith)]
 #[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub struct FLOATING_SAVE_AREA_SPARC {
     pub regs: [u64; 32],
@@ -7315,7 +7734,7 @@ pub struct FLOATING_SAVE_AREA_SPARC {
 /// A SPARC CPU context
 ///
 /// This is a Breakpad extension, as there is no definition of `CONTEXT` for SPARC in WinNT.h.
-#[derive(Debug, Clone, Pread, SizeWith)]
+#[derive(Debug, Clone, Default, Pread, SizeWith)]
 #[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub struct CONTEXT_SPARC {
     pub context_flags: u32,
@@ -7335,6 +7754,72 @@ pub struct CONTEXT_SPARC {
 #[derive(Copy, Clone, PartialEq, Debug)]
 pub enum SparcRegisterNumbers {
     StackPointer = 14,
+    FramePointer = 30,
+    ReturnAddress = 31,
+}
+
+impl SparcRegisterNumbers {
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::StackPointer => "o6",
+            Self::FramePointer => "i6",
+            Self::ReturnAddress => "i7",
+        }
+    }
+}
+
+/// RISC-V 64 floating point state
+#[derive(Debug, Clone, Default, Pread, SizeWith)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub struct FLOATING_SAVE_AREA_RISCV64 {
+    pub regs: [u64; 32],
+    pub fpcsr: u32,
+    pub _pad: u32,
+}
+
+/// A RISC-V 64 CPU context
+///
+/// This is a Breakpad extension, as there is no definition of `CONTEXT` for RISC-V in WinNT.h.
+/// The integer registers are listed by their ABI names, in order, with `pc` in the place
+/// of the hardwired zero register.
+#[derive(Debug, Clone, Default, Pread, SizeWith)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub struct CONTEXT_RISCV64 {
+    pub context_flags: u32,
+    pub version: u32,
+    pub pc: u64,
+    pub ra: u64,
+    pub sp: u64,
+    pub gp: u64,
+    pub tp: u64,
+    pub t0: u64,
+    pub t1: u64,
+    pub t2: u64,
+    pub s0: u64,
+    pub s1: u64,
+    pub a0: u64,
+    pub a1: u64,
+    pub a2: u64,
+    pub a3: u64,
+    pub a4: u64,
+    pub a5: u64,
+    pub a6: u64,
+    pub a7: u64,
+    pub s2: u64,
+    pub s3: u64,
+    pub s4: u64,
+    pub s5: u64,
+    pub s6: u64,
+    pub s7: u64,
+    pub s8: u64,
+    pub s9: u64,
+    pub s10: u64,
+    pub s11: u64,
+    pub t3: u64,
+    pub t4: u64,
+    pub t5: u64,
+    pub t6: u64,
+    pub float_save: FLOATING_SAVE_AREA_RISCV64,
 }
 
 /// x86 floating point state
@@ -7493,6 +7978,10 @@ pub enum ProcessorArchitecture {
     PROCESSOR_ARCHITECTURE_ARM64_OLD = 0x8003,
     /// Breakpad-defined value for MIPS64
     PROCESSOR_ARCHITECTURE_MIPS64 = 0x8004,
+    /// Breakpad-defined value for RISC-V (32-bit)
+    PROCESSOR_ARCHITECTURE_RISCV = 0x8005,
+    /// Breakpad-defined value for RISC-V 64
+    PROCESSOR_ARCHITECTURE_RISCV64 = 0x8006,
     PROCESSOR_ARCHITECTURE_UNKNOWN = 0xffff,
 }
 
@@ -0,0 +1,151 @@
+/*
+ * A C interface to minidump-processor.
+ *
+ * Process a minidump with mdp_process_path or mdp_process_bytes, then get
+ * the whole report as JSON with mdp_state_json, or look at parts of it with
+ * the accessors. The JSON is the same as minidump-stackwalk's --json output,
+ * described in minidump-processor's json-schema.md.
+ *
+ * Every string returned as `char *` is owned by the caller, and must be
+ * freed with mdp_string_free. Accessors return NULL (or 0, or false) when the
+ * value isn't known or an index is out of range.
+ *
+ * All functions are safe to call from any thread. An mdp_state can be read
+ * from several threads at once.
+ *
+ * No function unwinds into its caller. If one panics (which is a bug), it
+ * returns MDP_PANIC, or NULL (or 0, or false) if it doesn't return an
+ * mdp_status, and mdp_last_error says which function it was.
+ *
+ * This header is stable: functions and enum values are only ever added.
+ */
+
+#ifndef MINIDUMP_PROCESSOR_H
+#define MINIDUMP_PROCESSOR_H
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* A processed minidump. Free it with mdp_state_free. */
+typedef struct mdp_state mdp_state;
+
+/* What a function that can fail did. */
+typedef enum mdp_status {
+  MDP_OK = 0,
+  /* An argument was NULL, or a string wasn't valid UTF-8. */
+  MDP_INVALID_ARGUMENT = 1,
+  /* The minidump couldn't be read. */
+  MDP_READ_ERROR = 2,
+  /* The minidump was read, but couldn't be processed. */
+  MDP_PROCESS_ERROR = 3,
+  /* Something panicked. This is a bug. */
+  MDP_PANIC = 4,
+} mdp_status;
+
+/* Where to get symbols from. Any of the fields may be NULL (or 0), and a
+ * NULL mdp_symbol_options is the same as one that's all NULL, which
+ * processes the minidump without symbols. */
+typedef struct mdp_symbol_options {
+  /* Directories of symbol files, searched in order before any server. */
+  const char *const *symbol_paths;
+  size_t symbol_path_count;
+  /* The base urls of symbol servers, queried in order. */
+  const char *const *symbol_urls;
+  size_t symbol_url_count;
+  /* Where to cache downloaded symbols. */
+  const char *symbols_cache;
+  /* Where to download symbols to before moving them into the cache. */
+  const char *symbols_tmp;
+  /* The longest a symbol file download may take, in seconds, or 0 for the
+   * default. */
+  uint64_t timeout_secs;
+} mdp_symbol_options;
+
+/* Process the minidump at `path`. On MDP_OK, `*state` is the result. */
+mdp_status mdp_process_path(const char *path,
+                            const mdp_symbol_options *options,
+                            mdp_state **state);
+
+/* Process the `len` bytes of minidump at `data`. The bytes are only read
+ * during the call. On MDP_OK, `*state` is the result. */
+mdp_status mdp_process_bytes(const uint8_t *data, size_t len,
+                             const mdp_symbol_options *options,
+                             mdp_state **state);
+
+/* A description of why the last call on this thread that returns an
+ * mdp_status failed (or of a panic since then), or NULL if that call
+ * returned MDP_OK. The string is owned by the library and valid until the
+ * next such call on this thread. */
+const char *mdp_last_error(void);
+
+/* Free a processed minidump. NULL is ignored. */
+void mdp_state_free(mdp_state *state);
+
+/* Free a string returned by this library. NULL is ignored. */
+void mdp_string_free(char *string);
+
+/* The whole report as JSON, pretty-printed or on one line. */
+char *mdp_state_json(const mdp_state *state, bool pretty);
+
+/* The crash signature, like minidump-stackwalk's human output has. */
+char *mdp_state_signature(const mdp_state *state);
+
+/* The crash reason, like "EXCEPTION_ACCESS_VIOLATION_READ" or "SIGSEGV", or
+ * NULL if the process didn't crash. */
+char *mdp_state_crash_reason(const mdp_state *state);
+
+/* The address the crash was at, if the process crashed. */
+bool mdp_state_crash_address(const mdp_state *state, uint64_t *address);
+
+/* The index of the crashing thread, if it's known. */
+bool mdp_state_crashing_thread(const mdp_state *state, size_t *thread);
+
+/* The operating system, like "Windows NT" or "Linux". */
+char *mdp_state_os(const mdp_state *state);
+
+/* The CPU architecture, like "x86" or "arm64". */
+char *mdp_state_cpu(const mdp_state *state);
+
+size_t mdp_state_thread_count(const mdp_state *state);
+
+uint32_t mdp_thread_id(const mdp_state *state, size_t thread);
+
+char *mdp_thread_name(const mdp_state *state, size_t thread);
+
+size_t mdp_thread_frame_count(const mdp_state *state, size_t thread);
+
+/* The address of the instruction the frame is at. */
+uint64_t mdp_frame_instruction(const mdp_state *state, size_t thread,
+                               size_t frame);
+
+/* The code file of the module the frame's instruction is in. */
+char *mdp_frame_module(const mdp_state *state, size_t thread, size_t frame);
+
+/* The name of the function the frame's instruction is in, if symbols were
+ * found for it. */
+char *mdp_frame_function(const mdp_state *state, size_t thread, size_t frame);
+
+/* The source file and line the frame's instruction is at, if symbols were
+ * found for it. `line` may be NULL. */
+char *mdp_frame_source(const mdp_state *state, size_t thread, size_t frame,
+                       uint32_t *line);
+
+size_t mdp_state_module_count(const mdp_state *state);
+
+/* The code file of the module. */
+char *mdp_module_code_file(const mdp_state *state, size_t module);
+
+uint64_t mdp_module_base_address(const mdp_state *state, size_t module);
+
+uint64_t mdp_module_size(const mdp_state *state, size_t module);
+
+#ifdef __cplusplus
+} /* extern "C" */
+#endif
+
+#endif /* MINIDUMP_PROCESSOR_H */
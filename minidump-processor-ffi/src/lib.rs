@@ -0,0 +1,565 @@
+// Copyright 2015 Ted Mielczarek. See the COPYRIGHT
+// file at the top-level directory of this distribution.
+
+//! A C interface to [`minidump_processor`], for processing minidumps in
+//! programs that aren't written in Rust without going through
+//! minidump-stackwalk.
+//!
+//! `include/minidump_processor.h` declares everything here for C and C++,
+//! and is where the interface is documented. The Rust names are the C ones
+//! without the `mdp_` prefix, in Rust's style.
+
+use std::cell::RefCell;
+use std::ffi::{CStr, CString};
+use std::fmt::Display;
+use std::future::Future;
+use std::os::raw::c_char;
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+use std::slice;
+use std::time::Duration;
+
+use minidump::{MinidumpModule, Module};
+use minidump_processor::{ProcessError, ProcessState, SignatureOptions, StackFrame, SymbolConfig};
+
+/// A processed minidump (`mdp_state`).
+pub struct State {
+    state: ProcessState,
+    /// The modules of `state`, for looking them up by index.
+    modules: Vec<MinidumpModule>,
+}
+
+/// What a function that can fail did (`mdp_status`).
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    Ok = 0,
+    InvalidArgument = 1,
+    ReadError = 2,
+    ProcessError = 3,
+    Panic = 4,
+}
+
+/// Where to get symbols from (`mdp_symbol_options`).
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct SymbolOptions {
+    pub symbol_paths: *const *const c_char,
+    pub symbol_path_count: usize,
+    pub symbol_urls: *const *const c_char,
+    pub symbol_url_count: usize,
+    pub symbols_cache: *const c_char,
+    pub symbols_tmp: *const c_char,
+    pub timeout_secs: u64,
+}
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = RefCell::new(None);
+}
+
+/// Record `error` for [`mdp_last_error`] and return `status`.
+fn fail(status: Status, error: impl Display) -> Status {
+    let message = CString::new(error.to_string().replace('\0', "")).unwrap_or_default();
+    LAST_ERROR.with(|last_error| *last_error.borrow_mut() = Some(message));
+    status
+}
+
+/// Forget the last error, at the start of a call that returns a [`Status`],
+/// so [`mdp_last_error`] is only ever about the latest such call.
+fn clear_error() {
+    LAST_ERROR.with(|last_error| *last_error.borrow_mut() = None);
+}
+
+/// Run `f`, without letting it unwind into C. If it panics, that's recorded
+/// for [`mdp_last_error`], and `on_panic` is returned instead.
+fn guard<T>(function: &str, on_panic: T, f: impl FnOnce() -> T) -> T {
+    panic::catch_unwind(AssertUnwindSafe(f)).unwrap_or_else(|_| {
+        fail(Status::Panic, format!("{} panicked", function));
+        on_panic
+    })
+}
+
+/// A string for C to free with [`mdp_string_free`], or null if it has a nul
+/// in the middle.
+fn c_string(string: impl Into<Vec<u8>>) -> *mut c_char {
+    CString::new(string).map_or(ptr::null_mut(), CString::into_raw)
+}
+
+/// `string` as a `&str`, or `None` if it's null or not UTF-8.
+unsafe fn rust_str<'a>(string: *const c_char) -> Option<&'a str> {
+    if string.is_null() {
+        None
+    } else {
+        CStr::from_ptr(string).to_str().ok()
+    }
+}
+
+/// The `count` strings at `strings`.
+unsafe fn rust_strs<'a>(
+    strings: *const *const c_char,
+    count: usize,
+) -> Result<Vec<&'a str>, &'static str> {
+    if count == 0 {
+        return Ok(Vec::new());
+    }
+    if strings.is_null() {
+        return Err("a list of strings is NULL, but its count isn't 0");
+    }
+    slice::from_raw_parts(strings, count)
+        .iter()
+        .map(|&string| rust_str(string).ok_or("a string is NULL or not UTF-8"))
+        .collect()
+}
+
+/// The `SymbolConfig` for `options`, or the `Status` to fail with (and the
+/// error has been recorded).
+unsafe fn symbol_config(options: *const SymbolOptions) -> Result<SymbolConfig, Status> {
+    guard("Reading the symbol options", Err(Status::Panic), || {
+        symbol_config_unguarded(options).map_err(|e| fail(Status::InvalidArgument, e))
+    })
+}
+
+unsafe fn symbol_config_unguarded(
+    options: *const SymbolOptions,
+) -> Result<SymbolConfig, &'static str> {
+    let mut config = SymbolConfig::default();
+    let options = match options.as_ref() {
+        Some(options) => options,
+        None => return Ok(config),
+    };
+    for path in rust_strs(options.symbol_paths, options.symbol_path_count)? {
+        config = config.with_symbol_path(path);
+    }
+    for url in rust_strs(options.symbol_urls, options.symbol_url_count)? {
+        config = config.with_symbol_url(url);
+    }
+    if !options.symbols_cache.is_null() {
+        let path = rust_str(options.symbols_cache).ok_or("symbols_cache isn't UTF-8")?;
+        config = config.with_symbols_cache(path);
+    }
+    if !options.symbols_tmp.is_null() {
+        let path = rust_str(options.symbols_tmp).ok_or("symbols_tmp isn't UTF-8")?;
+        config = config.with_symbols_tmp(path);
+    }
+    if options.timeout_secs != 0 {
+        config = config.with_timeout(Duration::from_secs(options.timeout_secs));
+    }
+    Ok(config)
+}
+
+/// Run `process` to completion and hand its result to C through `out`.
+unsafe fn run_process<F>(out: *mut *mut State, process: F) -> Status
+where
+    F: Future<Output = Result<ProcessState, ProcessError>>,
+{
+    let runtime = match tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+    {
+        Ok(runtime) => runtime,
+        Err(e) => return fail(Status::ProcessError, e),
+    };
+    match panic::catch_unwind(AssertUnwindSafe(|| runtime.block_on(process))) {
+        Ok(Ok(state)) => {
+            let modules = state.modules.iter().cloned().collect();
+            *out = Box::into_raw(Box::new(State { state, modules }));
+            Status::Ok
+        }
+        Ok(Err(ProcessError::MinidumpReadError(e))) => {
+            fail(Status::ReadError, format!("Failed to read minidump: {}", e))
+        }
+        Ok(Err(e)) => fail(Status::ProcessError, e),
+        Err(_) => fail(Status::Panic, "Processing the minidump panicked"),
+    }
+}
+
+/// # Safety
+///
+/// `path` must be a nul-terminated string, `options` null or valid, and
+/// `state` valid to write to.
+#[no_mangle]
+pub unsafe extern "C" fn mdp_process_path(
+    path: *const c_char,
+    options: *const SymbolOptions,
+    state: *mut *mut State,
+) -> Status {
+    guard("mdp_process_path", Status::Panic, || {
+        clear_error();
+        if state.is_null() {
+            return fail(Status::InvalidArgument, "state is NULL");
+        }
+        let path = match rust_str(path) {
+            Some(path) => path,
+            None => return fail(Status::InvalidArgument, "path is NULL or not UTF-8"),
+        };
+        let symbols = match symbol_config(options) {
+            Ok(symbols) => symbols,
+            Err(status) => return status,
+        };
+        run_process(state, minidump_processor::process_path(path, &symbols))
+    })
+}
+
+/// # Safety
+///
+/// `data` must point to `len` readable bytes, `options` be null or valid,
+/// and `state` valid to write to.
+#[no_mangle]
+pub unsafe extern "C" fn mdp_process_bytes(
+    data: *const u8,
+    len: usize,
+    options: *const SymbolOptions,
+    state: *mut *mut State,
+) -> Status {
+    guard("mdp_process_bytes", Status::Panic, || {
+        clear_error();
+        if state.is_null() {
+            return fail(Status::InvalidArgument, "state is NULL");
+        }
+        if data.is_null() {
+            return fail(Status::InvalidArgument, "data is NULL");
+        }
+        let bytes = slice::from_raw_parts(data, len);
+        let symbols = match symbol_config(options) {
+            Ok(symbols) => symbols,
+            Err(status) => return status,
+        };
+        run_process(state, minidump_processor::process_bytes(bytes, &symbols))
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn mdp_last_error() -> *const c_char {
+    guard("mdp_last_error", ptr::null(), || {
+        LAST_ERROR.with(|last_error| {
+            last_error
+                .borrow()
+                .as_ref()
+                .map_or(ptr::null(), |error| error.as_ptr())
+        })
+    })
+}
+
+/// # Safety
+///
+/// `state` must be null or from [`mdp_process_path`] or
+/// [`mdp_process_bytes`], and not already freed.
+#[no_mangle]
+pub unsafe extern "C" fn mdp_state_free(state: *mut State) {
+    guard("mdp_state_free", (), || {
+        if !state.is_null() {
+            drop(Box::from_raw(state));
+        }
+    })
+}
+
+/// # Safety
+///
+/// `string` must be null or returned by this library, and not already freed.
+#[no_mangle]
+pub unsafe extern "C" fn mdp_string_free(string: *mut c_char) {
+    guard("mdp_string_free", (), || {
+        if !string.is_null() {
+            drop(CString::from_raw(string));
+        }
+    })
+}
+
+// The accessors all take a state that must be null or valid, and treat null
+// as having nothing.
+
+unsafe fn process_state<'a>(state: *const State) -> Option<&'a ProcessState> {
+    state.as_ref().map(|state| &state.state)
+}
+
+unsafe fn frame<'a>(state: *const State, thread: usize, frame: usize) -> Option<&'a StackFrame> {
+    process_state(state)?.threads.get(thread)?.frames.get(frame)
+}
+
+unsafe fn module<'a>(state: *const State, module: usize) -> Option<&'a MinidumpModule> {
+    state.as_ref()?.modules.get(module)
+}
+
+/// # Safety
+///
+/// `state` must be null or valid.
+#[no_mangle]
+pub unsafe extern "C" fn mdp_state_json(state: *const State, pretty: bool) -> *mut c_char {
+    guard("mdp_state_json", ptr::null_mut(), || {
+        let state = match process_state(state) {
+            Some(state) => state,
+            None => return ptr::null_mut(),
+        };
+        let mut json = Vec::new();
+        match state.print_json(&mut json, pretty) {
+            Ok(()) => c_string(json),
+            Err(_) => ptr::null_mut(),
+        }
+    })
+}
+
+/// # Safety
+///
+/// `state` must be null or valid.
+#[no_mangle]
+pub unsafe extern "C" fn mdp_state_signature(state: *const State) -> *mut c_char {
+    guard("mdp_state_signature", ptr::null_mut(), || {
+        process_state(state).map_or(ptr::null_mut(), |state| {
+            c_string(state.signature(&SignatureOptions::default()))
+        })
+    })
+}
+
+/// # Safety
+///
+/// `state` must be null or valid.
+#[no_mangle]
+pub unsafe extern "C" fn mdp_state_crash_reason(state: *const State) -> *mut c_char {
+    guard("mdp_state_crash_reason", ptr::null_mut(), || {
+        process_state(state)
+            .and_then(|state| state.crash_reason)
+            .map_or(ptr::null_mut(), |reason| c_string(reason.to_string()))
+    })
+}
+
+/// # Safety
+///
+/// `state` must be null or valid, and `address` valid to write to.
+#[no_mangle]
+pub unsafe extern "C" fn mdp_state_crash_address(state: *const State, address: *mut u64) -> bool {
+    guard("mdp_state_crash_address", false, || {
+        match (
+            process_state(state).and_then(|state| state.crash_address),
+            address.as_mut(),
+        ) {
+            (Some(crash_address), Some(address)) => {
+                *address = crash_address;
+                true
+            }
+            _ => false,
+        }
+    })
+}
+
+/// # Safety
+///
+/// `state` must be null or valid, and `thread` valid to write to.
+#[no_mangle]
+pub unsafe extern "C" fn mdp_state_crashing_thread(
+    state: *const State,
+    thread: *mut usize,
+) -> bool {
+    guard("mdp_state_crashing_thread", false, || {
+        match (
+            process_state(state).and_then(|state| state.requesting_thread),
+            thread.as_mut(),
+        ) {
+            (Some(requesting_thread), Some(thread)) => {
+                *thread = requesting_thread;
+                true
+            }
+            _ => false,
+        }
+    })
+}
+
+/// # Safety
+///
+/// `state` must be null or valid.
+#[no_mangle]
+pub unsafe extern "C" fn mdp_state_os(state: *const State) -> *mut c_char {
+    guard("mdp_state_os", ptr::null_mut(), || {
+        process_state(state).map_or(ptr::null_mut(), |state| {
+            c_string(state.system_info.os.long_name().into_owned())
+        })
+    })
+}
+
+/// # Safety
+///
+/// `state` must be null or valid.
+#[no_mangle]
+pub unsafe extern "C" fn mdp_state_cpu(state: *const State) -> *mut c_char {
+    guard("mdp_state_cpu", ptr::null_mut(), || {
+        process_state(state).map_or(ptr::null_mut(), |state| {
+            c_string(state.system_info.cpu.to_string())
+        })
+    })
+}
+
+/// # Safety
+///
+/// `state` must be null or valid.
+#[no_mangle]
+pub unsafe extern "C" fn mdp_state_thread_count(state: *const State) -> usize {
+    guard("mdp_state_thread_count", 0, || {
+        process_state(state).map_or(0, |state| state.threads.len())
+    })
+}
+
+/// # Safety
+///
+/// `state` must be null or valid.
+#[no_mangle]
+pub unsafe extern "C" fn mdp_thread_id(state: *const State, thread: usize) -> u32 {
+    guard("mdp_thread_id", 0, || {
+        process_state(state)
+            .and_then(|state| state.threads.get(thread))
+            .map_or(0, |thread| thread.thread_id)
+    })
+}
+
+/// # Safety
+///
+/// `state` must be null or valid.
+#[no_mangle]
+pub unsafe extern "C" fn mdp_thread_name(state: *const State, thread: usize) -> *mut c_char {
+    guard("mdp_thread_name", ptr::null_mut(), || {
+        process_state(state)
+            .and_then(|state| state.threads.get(thread))
+            .and_then(|thread| thread.thread_name.as_deref())
+            .map_or(ptr::null_mut(), c_string)
+    })
+}
+
+/// # Safety
+///
+/// `state` must be null or valid.
+#[no_mangle]
+pub unsafe extern "C" fn mdp_thread_frame_count(state: *const State, thread: usize) -> usize {
+    guard("mdp_thread_frame_count", 0, || {
+        process_state(state)
+            .and_then(|state| state.threads.get(thread))
+            .map_or(0, |thread| thread.frames.len())
+    })
+}
+
+/// # Safety
+///
+/// `state` must be null or valid.
+#[no_mangle]
+pub unsafe extern "C" fn mdp_frame_instruction(
+    state: *const State,
+    thread: usize,
+    index: usize,
+) -> u64 {
+    guard("mdp_frame_instruction", 0, || {
+        frame(state, thread, index).map_or(0, |frame| frame.instruction)
+    })
+}
+
+/// # Safety
+///
+/// `state` must be null or valid.
+#[no_mangle]
+pub unsafe extern "C" fn mdp_frame_module(
+    state: *const State,
+    thread: usize,
+    index: usize,
+) -> *mut c_char {
+    guard("mdp_frame_module", ptr::null_mut(), || {
+        frame(state, thread, index)
+            .and_then(|frame| frame.module.as_ref())
+            .map_or(ptr::null_mut(), |module| {
+                c_string(module.code_file().into_owned())
+            })
+    })
+}
+
+/// # Safety
+///
+/// `state` must be null or valid.
+#[no_mangle]
+pub unsafe extern "C" fn mdp_frame_function(
+    state: *const State,
+    thread: usize,
+    index: usize,
+) -> *mut c_char {
+    guard("mdp_frame_function", ptr::null_mut(), || {
+        frame(state, thread, index)
+            .and_then(|frame| frame.function_name.as_deref())
+            .map_or(ptr::null_mut(), c_string)
+    })
+}
+
+/// # Safety
+///
+/// `state` must be null or valid, and `line` null or valid to write to.
+#[no_mangle]
+pub unsafe extern "C" fn mdp_frame_source(
+    state: *const State,
+    thread: usize,
+    index: usize,
+    line: *mut u32,
+) -> *mut c_char {
+    guard("mdp_frame_source", ptr::null_mut(), || {
+        let frame = match frame(state, thread, index) {
+            Some(frame) => frame,
+            None => return ptr::null_mut(),
+        };
+        match (&frame.source_file_name, frame.source_line) {
+            (Some(file), Some(source_line)) => {
+                if let Some(line) = line.as_mut() {
+                    *line = source_line;
+                }
+                c_string(file.as_str())
+            }
+            _ => ptr::null_mut(),
+        }
+    })
+}
+
+/// # Safety
+///
+/// `state` must be null or valid.
+#[no_mangle]
+pub unsafe extern "C" fn mdp_state_module_count(state: *const State) -> usize {
+    guard("mdp_state_module_count", 0, || {
+        state.as_ref().map_or(0, |state| state.modules.len())
+    })
+}
+
+/// # Safety
+///
+/// `state` must be null or valid.
+#[no_mangle]
+pub unsafe extern "C" fn mdp_module_code_file(state: *const State, index: usize) -> *mut c_char {
+    guard("mdp_module_code_file", ptr::null_mut(), || {
+        module(state, index).map_or(ptr::null_mut(), |module| {
+            c_string(module.code_file().into_owned())
+        })
+    })
+}
+
+/// # Safety
+///
+/// `state` must be null or valid.
+#[no_mangle]
+pub unsafe extern "C" fn mdp_module_base_address(state: *const State, index: usize) -> u64 {
+    guard("mdp_module_base_address", 0, || {
+        module(state, index).map_or(0, |module| module.base_address())
+    })
+}
+
+/// # Safety
+///
+/// `state` must be null or valid.
+#[no_mangle]
+pub unsafe extern "C" fn mdp_module_size(state: *const State, index: usize) -> u64 {
+    guard("mdp_module_size", 0, || {
+        module(state, index).map_or(0, |module| module.size())
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_guard() {
+        assert_eq!(guard("mdp_test", 0, || 1), 1);
+        assert_eq!(guard("mdp_test", 0, || panic!("oops")), 0);
+        let error = unsafe { CStr::from_ptr(mdp_last_error()) };
+        assert_eq!(error.to_str().unwrap(), "mdp_test panicked");
+    }
+}
@@ -0,0 +1,163 @@
+// Copyright 2015 Ted Mielczarek. See the COPYRIGHT
+// file at the top-level directory of this distribution.
+
+use std::ffi::{CStr, CString};
+use std::os::raw::c_char;
+use std::ptr;
+
+use minidump_processor_ffi::*;
+
+fn test_minidump() -> CString {
+    CString::new(concat!(env!("CARGO_MANIFEST_DIR"), "/../testdata/test.dmp")).unwrap()
+}
+
+fn symbols_path() -> CString {
+    CString::new(concat!(env!("CARGO_MANIFEST_DIR"), "/../testdata/symbols")).unwrap()
+}
+
+/// Take a string the library returned, freeing it.
+unsafe fn take_string(string: *mut c_char) -> Option<String> {
+    if string.is_null() {
+        return None;
+    }
+    let result = CStr::from_ptr(string).to_str().unwrap().to_owned();
+    mdp_string_free(string);
+    Some(result)
+}
+
+fn options(symbol_paths: &[*const c_char]) -> SymbolOptions {
+    SymbolOptions {
+        symbol_paths: symbol_paths.as_ptr(),
+        symbol_path_count: symbol_paths.len(),
+        symbol_urls: ptr::null(),
+        symbol_url_count: 0,
+        symbols_cache: ptr::null(),
+        symbols_tmp: ptr::null(),
+        timeout_secs: 0,
+    }
+}
+
+#[test]
+fn test_process_path() {
+    let path = test_minidump();
+    let symbols = symbols_path();
+    let options = options(&[symbols.as_ptr()]);
+    unsafe {
+        let mut state = ptr::null_mut();
+        assert_eq!(
+            mdp_process_path(path.as_ptr(), &options, &mut state),
+            Status::Ok
+        );
+
+        assert_eq!(mdp_state_thread_count(state), 2);
+        let mut crashing_thread = 0;
+        assert!(mdp_state_crashing_thread(state, &mut crashing_thread));
+        assert_eq!(crashing_thread, 0);
+        let mut address = 0;
+        assert!(mdp_state_crash_address(state, &mut address));
+        assert_eq!(address, 0x45);
+        assert_eq!(
+            take_string(mdp_state_crash_reason(state)).unwrap(),
+            "EXCEPTION_ACCESS_VIOLATION_WRITE"
+        );
+        assert_eq!(take_string(mdp_state_os(state)).unwrap(), "Windows NT");
+        assert_eq!(take_string(mdp_state_cpu(state)).unwrap(), "x86");
+
+        assert_eq!(
+            take_string(mdp_frame_function(state, 0, 0)).unwrap(),
+            "`anonymous namespace'::CrashFunction"
+        );
+        assert_eq!(
+            take_string(mdp_frame_module(state, 0, 0)).unwrap(),
+            "c:\\test_app.exe"
+        );
+        let mut line = 0;
+        assert_eq!(
+            take_string(mdp_frame_source(state, 0, 0, &mut line)).unwrap(),
+            "c:\\test_app.cc"
+        );
+        assert_eq!(line, 58);
+        assert_eq!(mdp_frame_instruction(state, 0, 0), 0x0040429e);
+
+        assert_eq!(
+            take_string(mdp_module_code_file(state, 0)).unwrap(),
+            "c:\\test_app.exe"
+        );
+        assert_eq!(mdp_module_base_address(state, 0), 0x400000);
+
+        // Out of range is nothing, not a crash.
+        assert_eq!(mdp_thread_frame_count(state, 100), 0);
+        assert_eq!(take_string(mdp_frame_function(state, 0, 10000)), None);
+        assert_eq!(take_string(mdp_module_code_file(state, 10000)), None);
+
+        let json = take_string(mdp_state_json(state, false)).unwrap();
+        assert!(json.starts_with('{'));
+        assert!(json.contains("\"crash_info\""));
+
+        mdp_state_free(state);
+    }
+}
+
+#[test]
+fn test_process_bytes() {
+    let bytes = std::fs::read(test_minidump().to_str().unwrap()).unwrap();
+    unsafe {
+        let mut state = ptr::null_mut();
+        assert_eq!(
+            mdp_process_bytes(bytes.as_ptr(), bytes.len(), ptr::null(), &mut state),
+            Status::Ok
+        );
+        assert_eq!(mdp_state_thread_count(state), 2);
+        // No symbols, so no function names.
+        assert_eq!(take_string(mdp_frame_function(state, 0, 0)), None);
+        assert!(mdp_thread_frame_count(state, 0) > 0);
+        mdp_state_free(state);
+    }
+}
+
+#[test]
+fn test_errors() {
+    unsafe {
+        let mut state = ptr::null_mut();
+        let garbage = [0u8; 64];
+        assert_eq!(
+            mdp_process_bytes(garbage.as_ptr(), garbage.len(), ptr::null(), &mut state),
+            Status::ReadError
+        );
+        assert!(state.is_null());
+        let error = CStr::from_ptr(mdp_last_error()).to_str().unwrap();
+        assert!(error.starts_with("Failed to read minidump"));
+
+        // A success forgets the last failure.
+        let path = test_minidump();
+        assert_eq!(
+            mdp_process_path(path.as_ptr(), ptr::null(), &mut state),
+            Status::Ok
+        );
+        assert!(mdp_last_error().is_null());
+        mdp_state_free(state);
+        state = ptr::null_mut();
+
+        let missing = CString::new("/nonexistent/minidump.dmp").unwrap();
+        assert_eq!(
+            mdp_process_path(missing.as_ptr(), ptr::null(), &mut state),
+            Status::ReadError
+        );
+
+        assert_eq!(
+            mdp_process_path(ptr::null(), ptr::null(), &mut state),
+            Status::InvalidArgument
+        );
+        let options = options(&[ptr::null()]);
+        assert_eq!(
+            mdp_process_path(path.as_ptr(), &options, &mut state),
+            Status::InvalidArgument
+        );
+
+        // Null states have nothing in them.
+        assert_eq!(mdp_state_thread_count(ptr::null()), 0);
+        assert_eq!(take_string(mdp_state_json(ptr::null(), true)), None);
+        mdp_state_free(ptr::null_mut());
+        mdp_string_free(ptr::null_mut());
+    }
+}
@@ -1,10 +1,13 @@
 #![no_main]
 use libfuzzer_sys::fuzz_target;
 
-use minidump::{MinidumpContext, MinidumpContextValidity, MinidumpMemory};
+use minidump::{
+    MinidumpContext, MinidumpContextValidity, MinidumpMemory, UnifiedMemoryInfoList,
+    UnifiedMemoryList,
+};
 use minidump::{MinidumpModule, MinidumpModuleList};
 use minidump_processor::walk_stack;
-use minidump_processor::{string_symbol_supplier, CallStack, Symbolizer};
+use minidump_processor::{string_symbol_supplier, CallStack, ProcessorOptions, Symbolizer};
 use std::collections::HashMap;
 use test_assembler::Section;
 
@@ -50,8 +53,11 @@ impl TestFixture {
             walk_stack(
                 &Some(&context),
                 Some(&stack_memory),
+                &UnifiedMemoryList::default(),
+                &UnifiedMemoryInfoList::default(),
                 &self.modules,
                 &symbolizer,
+                &ProcessorOptions::default(),
             )
             .await,
         )
@@ -0,0 +1,298 @@
+// Copyright 2015 Ted Mielczarek. See the COPYRIGHT
+// file at the top-level directory of this distribution.
+
+//! The analyses run once the stacks have been walked.
+//!
+//! Each analysis is an [`AnalysisPass`] that fills in part of the
+//! [`ProcessState`], with the parts of the minidump it needs in an
+//! [`AnalysisContext`]. The built-in passes run first, in a fixed order, and
+//! then the ones in [`ProcessorOptions::analysis_passes`] in the order they
+//! were added, so each pass can use what the earlier ones found.
+
+use std::fmt;
+use std::time::Duration;
+
+use instant::Instant;
+use minidump::{
+    MinidumpContext, MinidumpHandleDataStream, MinidumpThread, UnifiedMemoryInfoList,
+    UnifiedMemoryList,
+};
+
+use crate::process_state::{basename, ProcessState};
+use crate::processor::ProcessorOptions;
+use crate::stack_overflow::CrashingThread;
+use crate::{
+    authenticode, crash_address, crash_memory, disassembly, handles, register_targets,
+    stack_overflow, suspicious_modules, thread_guess,
+};
+
+/// The parts of the minidump an [`AnalysisPass`] can look at, besides the
+/// [`ProcessState`] itself.
+#[non_exhaustive]
+pub struct AnalysisContext<'a> {
+    /// The options the minidump is being processed with.
+    pub options: &'a ProcessorOptions<'a>,
+    /// The registers of the thread that requested the dump, as of the crash
+    /// if the minidump has an exception stream.
+    pub crashing_context: Option<&'a MinidumpContext>,
+    /// The threads, in the same order as [`ProcessState::threads`].
+    pub threads: &'a [MinidumpThread<'a>],
+    /// All the memory the minidump has.
+    pub memory_list: &'a UnifiedMemoryList<'a>,
+    /// What's mapped where, if the minidump has memory info (or Linux maps).
+    pub memory_info: Option<&'a UnifiedMemoryInfoList<'a>>,
+    /// The handles the process had open, if the minidump lists them.
+    pub handles: Option<&'a MinidumpHandleDataStream>,
+    pub(crate) raw_streams: &'a (dyn Fn(u32) -> Option<&'a [u8]> + 'a),
+}
+
+impl<'a> AnalysisContext<'a> {
+    /// The contents of the minidump's stream of type `stream_type`, if it
+    /// has one.
+    ///
+    /// This is for reading custom streams the processor doesn't know about.
+    pub fn raw_stream(&self, stream_type: u32) -> Option<&'a [u8]> {
+        (self.raw_streams)(stream_type)
+    }
+}
+
+/// An analysis of a processed minidump.
+///
+/// Add one to [`ProcessorOptions::analysis_passes`] to have it run on every
+/// minidump, after the built-in analyses. It can update any part of the
+/// [`ProcessState`], and read parts of the minidump that don't end up in it
+/// from the [`AnalysisContext`].
+pub trait AnalysisPass: Send + Sync {
+    /// A short name for the pass, for logs.
+    fn name(&self) -> &str;
+    /// Analyze `state`, recording what was found in it.
+    fn analyze(&self, context: &AnalysisContext<'_>, state: &mut ProcessState);
+}
+
+impl fmt::Debug for dyn AnalysisPass + '_ {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "AnalysisPass({})", self.name())
+    }
+}
+
+/// Run the built-in passes and then the ones in `context.options` on
+/// `state`, returning how long each took.
+pub(crate) fn run_passes(
+    context: &AnalysisContext<'_>,
+    state: &mut ProcessState,
+) -> Vec<(String, Duration)> {
+    let builtin: [&dyn AnalysisPass; 9] = [
+        &RequestingThreadPass,
+        &CrashMemoryPass,
+        &DisassemblyPass,
+        &CrashAddressClassPass,
+        &StackOverflowPass,
+        &RegisterTargetsPass,
+        &HandlesPass,
+        &CertInfoPass,
+        &SuspiciousModulesPass,
+    ];
+    let custom = context.options.analysis_passes.iter().map(|pass| &**pass);
+    let mut times = Vec::new();
+    for pass in builtin.iter().copied().chain(custom) {
+        tracing::trace!("analysis: running {}", pass.name());
+        let started = Instant::now();
+        pass.analyze(context, state);
+        times.push((String::from(pass.name()), started.elapsed()));
+    }
+    times
+}
+
+/// Guesses [`ProcessState::requesting_thread`] if the minidump didn't say,
+/// and the processor was asked to.
+struct RequestingThreadPass;
+
+impl AnalysisPass for RequestingThreadPass {
+    fn name(&self) -> &str {
+        "requesting_thread"
+    }
+
+    fn analyze(&self, context: &AnalysisContext<'_>, state: &mut ProcessState) {
+        if context.options.guess_requesting_thread && state.requesting_thread.is_none() {
+            state.requesting_thread = thread_guess::guess_requesting_thread(state);
+            state.requesting_thread_guessed = state.requesting_thread.is_some();
+        }
+    }
+}
+
+/// Fills in [`ProcessState::crash_memory`].
+struct CrashMemoryPass;
+
+impl AnalysisPass for CrashMemoryPass {
+    fn name(&self) -> &str {
+        "crash_memory"
+    }
+
+    fn analyze(&self, context: &AnalysisContext<'_>, state: &mut ProcessState) {
+        if context.options.crash_memory_size > 0 {
+            state.crash_memory = Some(crash_memory::capture(
+                context.options.crash_memory_size,
+                state.crash_address,
+                context.crashing_context,
+                context.memory_list,
+                context.memory_info,
+            ));
+        }
+    }
+}
+
+/// Fills in [`ProcessState::disassembly`].
+struct DisassemblyPass;
+
+impl AnalysisPass for DisassemblyPass {
+    fn name(&self) -> &str {
+        "disassembly"
+    }
+
+    fn analyze(&self, context: &AnalysisContext<'_>, state: &mut ProcessState) {
+        if !context.options.disassemble {
+            return;
+        }
+        state.disassembly = context.crashing_context.and_then(|crashing_context| {
+            disassembly::disassemble(state.system_info.cpu, crashing_context, context.memory_list)
+        });
+    }
+}
+
+/// Fills in [`ProcessState::crash_address_class`].
+struct CrashAddressClassPass;
+
+impl AnalysisPass for CrashAddressClassPass {
+    fn name(&self) -> &str {
+        "crash_address_class"
+    }
+
+    fn analyze(&self, context: &AnalysisContext<'_>, state: &mut ProcessState) {
+        if let (Some(reason), Some(address)) = (state.crash_reason, state.crash_address) {
+            state.crash_address_class = crash_address::classify(
+                state.system_info.cpu,
+                reason,
+                address,
+                context.crashing_context,
+            );
+        }
+    }
+}
+
+/// Fills in [`ProcessState::stack_overflow`].
+struct StackOverflowPass;
+
+impl AnalysisPass for StackOverflowPass {
+    fn name(&self) -> &str {
+        "stack_overflow"
+    }
+
+    fn analyze(&self, context: &AnalysisContext<'_>, state: &mut ProcessState) {
+        if let (Some(reason), Some(address)) = (state.crash_reason, state.crash_address) {
+            let crashing = state.requesting_thread.map(|i| CrashingThread {
+                thread: &context.threads[i],
+                context: context.crashing_context,
+                stack: &state.threads[i],
+            });
+            state.stack_overflow = stack_overflow::classify(
+                state.system_info.cpu,
+                reason,
+                address,
+                crashing,
+                context.memory_list,
+                context.memory_info,
+            );
+        }
+    }
+}
+
+/// Fills in [`ProcessState::register_targets`].
+struct RegisterTargetsPass;
+
+impl AnalysisPass for RegisterTargetsPass {
+    fn name(&self) -> &str {
+        "register_targets"
+    }
+
+    fn analyze(&self, context: &AnalysisContext<'_>, state: &mut ProcessState) {
+        if let Some(crashing_context) = context.crashing_context {
+            state.register_targets = register_targets::find_targets(
+                crashing_context,
+                context.threads,
+                &state.modules,
+                context.memory_info,
+            );
+        }
+    }
+}
+
+/// Fills in [`ProcessState::handles`].
+struct HandlesPass;
+
+impl AnalysisPass for HandlesPass {
+    fn name(&self) -> &str {
+        "handles"
+    }
+
+    fn analyze(&self, context: &AnalysisContext<'_>, state: &mut ProcessState) {
+        state.handles = context.handles.map(|handles| {
+            handles::summarize(
+                handles,
+                state.crash_address,
+                context.crashing_context,
+                context.memory_list,
+            )
+        });
+    }
+}
+
+/// Adds who signed the modules the evil JSON doesn't know about to
+/// [`ProcessState::cert_info`], from their Authenticode signatures.
+struct CertInfoPass;
+
+impl AnalysisPass for CertInfoPass {
+    fn name(&self) -> &str {
+        "cert_info"
+    }
+
+    fn analyze(&self, context: &AnalysisContext<'_>, state: &mut ProcessState) {
+        for module in state.modules.iter() {
+            let code_file = module.code_file();
+            let name = basename(&code_file);
+            if state.cert_info.contains_key(name) {
+                continue;
+            }
+            let signer =
+                authenticode::signer_from_memory(context.memory_list, module.base_address())
+                    .or_else(|| {
+                        authenticode::signer_from_files(
+                            &context.options.module_binary_paths,
+                            module,
+                        )
+                    });
+            if let Some(signer) = signer {
+                state.cert_info.insert(String::from(name), signer);
+            }
+        }
+    }
+}
+
+/// Fills in [`ProcessState::suspicious_modules`], if the processor was
+/// asked to look for them.
+struct SuspiciousModulesPass;
+
+impl AnalysisPass for SuspiciousModulesPass {
+    fn name(&self) -> &str {
+        "suspicious_modules"
+    }
+
+    fn analyze(&self, context: &AnalysisContext<'_>, state: &mut ProcessState) {
+        if context.options.detect_suspicious_modules {
+            state.suspicious_modules = Some(suspicious_modules::find_suspicious_modules(
+                &state.modules,
+                context.memory_info,
+                &state.cert_info,
+            ));
+        }
+    }
+}
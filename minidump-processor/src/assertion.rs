@@ -0,0 +1,107 @@
+// Copyright 2015 Ted Mielczarek. See the COPYRIGHT
+// file at the top-level directory of this distribution.
+
+//! Assertions recorded in the minidump.
+//!
+//! Breakpad's Windows client writes an assertion info stream when the C
+//! runtime's invalid parameter or pure virtual call handlers are hit. The
+//! crash itself is then reported as whatever exception the client raised to
+//! write the minidump (usually a breakpoint), which says very little about
+//! what went wrong, so the assertion is the better description.
+
+use std::fmt;
+
+use minidump::format::AssertionType;
+use minidump::MinidumpAssertion;
+
+/// What kind of assertion an [`Assertion`] is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum AssertionKind {
+    /// An invalid parameter was passed to a C runtime function.
+    InvalidParameter,
+    /// A pure virtual function was called.
+    PureVirtualCall,
+    /// A kind of assertion we don't know, with its raw value.
+    Unknown(u32),
+}
+
+impl AssertionKind {
+    /// The name of the kind in the JSON schema.
+    pub fn json_name(self) -> String {
+        match self {
+            AssertionKind::InvalidParameter => String::from("invalid_parameter"),
+            AssertionKind::PureVirtualCall => String::from("pure_virtual_call"),
+            AssertionKind::Unknown(raw) => format!("{:#010x}", raw),
+        }
+    }
+}
+
+impl fmt::Display for AssertionKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AssertionKind::InvalidParameter => {
+                f.write_str("Invalid parameter passed to library function")
+            }
+            AssertionKind::PureVirtualCall => f.write_str("Pure virtual function called"),
+            AssertionKind::Unknown(raw) => write!(f, "Unknown assertion type {:#010x}", raw),
+        }
+    }
+}
+
+/// An assertion that was hit, from the minidump's assertion info stream.
+///
+/// This displays the same way Breakpad describes assertions, like
+/// `Invalid parameter passed to library function format != nullptr in
+/// function common_vfprintf, in file output.cpp at line 32`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Assertion {
+    /// What kind of assertion it is.
+    pub kind: AssertionKind,
+    /// The expression that failed, if it's known.
+    pub expression: Option<String>,
+    /// The function the assertion is in, if it's known.
+    pub function: Option<String>,
+    /// The source file the assertion is in, if it's known.
+    pub file: Option<String>,
+    /// The line in `file` the assertion is on, if it's known.
+    pub line: Option<u32>,
+}
+
+impl Assertion {
+    pub(crate) fn from_stream(assertion: &MinidumpAssertion) -> Assertion {
+        let non_empty = |s: Option<String>| s.filter(|s| !s.is_empty());
+        let kind = match assertion.raw._type {
+            raw if raw == AssertionType::InvalidParameter as u32 => AssertionKind::InvalidParameter,
+            raw if raw == AssertionType::PureVirtualCall as u32 => AssertionKind::PureVirtualCall,
+            raw => AssertionKind::Unknown(raw),
+        };
+        Assertion {
+            kind,
+            expression: non_empty(assertion.expression()),
+            function: non_empty(assertion.function()),
+            file: non_empty(assertion.file()),
+            line: Some(assertion.raw.line).filter(|&line| line != 0),
+        }
+    }
+}
+
+impl fmt::Display for Assertion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.kind)?;
+        if let Some(ref expression) = self.expression {
+            write!(f, " {}", expression)?;
+        }
+        if let Some(ref function) = self.function {
+            write!(f, " in function {}", function)?;
+        }
+        if let Some(ref file) = self.file {
+            write!(f, ", in file {}", file)?;
+        }
+        if let Some(line) = self.line {
+            write!(f, " at line {}", line)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,419 @@
+// Copyright 2015 Ted Mielczarek. See the COPYRIGHT
+// file at the top-level directory of this distribution.
+
+//! Reading who signed a module from its Authenticode signature.
+//!
+//! A signed PE file ends with a certificate table, found through the
+//! security data directory of its headers, holding a PKCS #7 `SignedData`.
+//! The signer is the certificate that the first `SignerInfo` of the
+//! `SignedData` names by issuer and serial number, and it's reported by the
+//! common name of its subject, like the evil JSON's certificates are.
+//!
+//! Unlike the rest of the file, the certificate table isn't part of any
+//! section, so it isn't loaded along with the module. A minidump only has it
+//! if the module was mapped as a plain file, which puts the whole file in
+//! memory as-is. Otherwise it takes a copy of the module's file, see
+//! [`ProcessorOptions::module_binary_paths`][crate::ProcessorOptions::module_binary_paths].
+
+use std::convert::{TryFrom, TryInto};
+use std::path::PathBuf;
+
+use minidump::{MinidumpModule, Module, UnifiedMemoryList};
+
+use crate::process_state::basename;
+
+const IMAGE_DOS_SIGNATURE: u16 = 0x5a4d; // MZ
+const IMAGE_NT_SIGNATURE: u32 = 0x0000_4550; // PE\0\0
+const IMAGE_NT_OPTIONAL_HDR32_MAGIC: u16 = 0x10b;
+const IMAGE_NT_OPTIONAL_HDR64_MAGIC: u16 = 0x20b;
+const IMAGE_DIRECTORY_ENTRY_SECURITY: u64 = 4;
+
+const WIN_CERT_REVISION_2_0: u16 = 0x0200;
+const WIN_CERT_TYPE_PKCS_SIGNED_DATA: u16 = 0x0002;
+
+/// The largest certificate table to read, in case the size we read is
+/// garbage.
+const MAX_CERTIFICATE_TABLE_SIZE: u64 = 1 << 20;
+
+const DER_INTEGER: u8 = 0x02;
+const DER_OID: u8 = 0x06;
+const DER_UTF8_STRING: u8 = 0x0c;
+const DER_PRINTABLE_STRING: u8 = 0x13;
+const DER_T61_STRING: u8 = 0x14;
+const DER_IA5_STRING: u8 = 0x16;
+const DER_BMP_STRING: u8 = 0x1e;
+const DER_SEQUENCE: u8 = 0x30;
+const DER_SET: u8 = 0x31;
+const DER_CONTEXT_0: u8 = 0xa0;
+const DER_CONTEXT_1: u8 = 0xa1;
+
+/// 1.2.840.113549.1.7.2, PKCS #7 `signedData`.
+const OID_SIGNED_DATA: &[u8] = &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x07, 0x02];
+/// 2.5.4.3, `commonName`.
+const OID_COMMON_NAME: &[u8] = &[0x55, 0x04, 0x03];
+
+/// Who signed the module loaded at `base`, if the minidump has its whole
+/// file mapped there.
+pub(crate) fn signer_from_memory(memory_list: &UnifiedMemoryList<'_>, base: u64) -> Option<String> {
+    let read = |offset: u64| {
+        let address = base.checked_add(offset)?;
+        let memory = memory_list.memory_at_address(address)?;
+        let start = usize::try_from(address - memory.base_address()).ok()?;
+        memory.bytes().get(start..)
+    };
+    signer(read)
+}
+
+/// Who signed `module`, if there's a copy of its file in one of `paths`.
+///
+/// The copies are looked for where symbol servers store them, at
+/// `<code file>/<code identifier>/<code file>`.
+pub(crate) fn signer_from_files(paths: &[PathBuf], module: &MinidumpModule) -> Option<String> {
+    let code_file = module.code_file();
+    let name = basename(&code_file);
+    let code_id = module.code_identifier();
+    paths.iter().find_map(|path| {
+        let file = std::fs::read(path.join(name).join(&*code_id).join(name)).ok()?;
+        signer_name(&file)
+    })
+}
+
+/// Who signed the PE file `file`, if it's signed.
+pub(crate) fn signer_name(file: &[u8]) -> Option<String> {
+    signer(|offset| file.get(usize::try_from(offset).ok()?..))
+}
+
+/// Who signed the PE file that `read(offset)` gives the bytes of, from
+/// `offset` on.
+fn signer<'a>(read: impl Fn(u64) -> Option<&'a [u8]>) -> Option<String> {
+    let read_u16 = |offset| Some(u16::from_le_bytes(read(offset)?.get(..2)?.try_into().ok()?));
+    let read_u32 = |offset| Some(u32::from_le_bytes(read(offset)?.get(..4)?.try_into().ok()?));
+
+    if read_u16(0)? != IMAGE_DOS_SIGNATURE {
+        return None;
+    }
+    let nt_headers = read_u32(0x3c)? as u64;
+    if read_u32(nt_headers)? != IMAGE_NT_SIGNATURE {
+        return None;
+    }
+    let optional_header = nt_headers + 24;
+    let data_directories = match read_u16(optional_header)? {
+        IMAGE_NT_OPTIONAL_HDR32_MAGIC => optional_header + 96,
+        IMAGE_NT_OPTIONAL_HDR64_MAGIC => optional_header + 112,
+        _ => return None,
+    };
+    // NumberOfRvaAndSizes
+    if read_u32(data_directories - 4)? as u64 <= IMAGE_DIRECTORY_ENTRY_SECURITY {
+        return None;
+    }
+    // Unlike the other data directories, this one has a file offset rather
+    // than an RVA.
+    let security = data_directories + IMAGE_DIRECTORY_ENTRY_SECURITY * 8;
+    let offset = read_u32(security)? as u64;
+    let size = read_u32(security + 4)? as u64;
+    if offset == 0 || size > MAX_CERTIFICATE_TABLE_SIZE {
+        return None;
+    }
+    let table = read(offset)?.get(..size as usize)?;
+    signer_from_certificate_table(table)
+}
+
+/// Who signed the first Authenticode signature in the certificate table
+/// `table`.
+fn signer_from_certificate_table(mut table: &[u8]) -> Option<String> {
+    while table.len() >= 8 {
+        // A WIN_CERTIFICATE
+        let length = u32::from_le_bytes(table[0..4].try_into().ok()?) as usize;
+        let revision = u16::from_le_bytes(table[4..6].try_into().ok()?);
+        let certificate_type = u16::from_le_bytes(table[6..8].try_into().ok()?);
+        let certificate = table.get(8..length)?;
+        if revision == WIN_CERT_REVISION_2_0 && certificate_type == WIN_CERT_TYPE_PKCS_SIGNED_DATA {
+            if let Some(signer) = signer_from_signed_data(certificate) {
+                return Some(signer);
+            }
+        }
+        // Each entry starts 8-byte aligned.
+        let next = length.checked_add(7)? & !7;
+        table = table.get(next..)?;
+    }
+    None
+}
+
+/// The common name of the signer of the DER-encoded PKCS #7 `ContentInfo`
+/// `data`, which should hold a `SignedData`.
+fn signer_from_signed_data(data: &[u8]) -> Option<String> {
+    let mut content_info = Der::new(Der::new(data).expect(DER_SEQUENCE)?);
+    if content_info.expect(DER_OID)? != OID_SIGNED_DATA {
+        return None;
+    }
+    let signed_data = Der::new(content_info.expect(DER_CONTEXT_0)?).expect(DER_SEQUENCE)?;
+    let mut signed_data = Der::new(signed_data);
+    signed_data.expect(DER_INTEGER)?; // version
+    signed_data.expect(DER_SET)?; // digestAlgorithms
+    signed_data.expect(DER_SEQUENCE)?; // encapContentInfo
+    let mut certificates = None;
+    let signer_infos = loop {
+        match signed_data.next()? {
+            (DER_CONTEXT_0, contents) => certificates = Some(contents),
+            (DER_CONTEXT_1, _) => {} // crls
+            (DER_SET, contents) => break contents,
+            _ => return None,
+        }
+    };
+
+    // The signer is identified by the issuer and serial number of its
+    // certificate.
+    let mut signer_info = Der::new(Der::new(signer_infos).expect(DER_SEQUENCE)?);
+    signer_info.expect(DER_INTEGER)?; // version
+    let mut signer_id = Der::new(signer_info.expect(DER_SEQUENCE)?);
+    let issuer = signer_id.expect(DER_SEQUENCE)?;
+    let serial = signer_id.expect(DER_INTEGER)?;
+
+    let mut certificates = Der::new(certificates?);
+    while let Some(certificate) = certificates.expect(DER_SEQUENCE) {
+        let mut tbs_certificate = Der::new(Der::new(certificate).expect(DER_SEQUENCE)?);
+        let mut field = tbs_certificate.next()?;
+        if field.0 == DER_CONTEXT_0 {
+            // version
+            field = tbs_certificate.next()?;
+        }
+        let certificate_serial = match field {
+            (DER_INTEGER, serial) => serial,
+            _ => return None,
+        };
+        tbs_certificate.expect(DER_SEQUENCE)?; // signature
+        let certificate_issuer = tbs_certificate.expect(DER_SEQUENCE)?;
+        tbs_certificate.expect(DER_SEQUENCE)?; // validity
+        let subject = tbs_certificate.expect(DER_SEQUENCE)?;
+        if certificate_serial == serial && certificate_issuer == issuer {
+            return common_name(subject);
+        }
+    }
+    None
+}
+
+/// The common name in the DER-encoded X.501 `Name` `name`.
+fn common_name(name: &[u8]) -> Option<String> {
+    let mut relative_names = Der::new(name);
+    while let Some(relative_name) = relative_names.expect(DER_SET) {
+        let mut attributes = Der::new(relative_name);
+        while let Some(attribute) = attributes.expect(DER_SEQUENCE) {
+            let mut attribute = Der::new(attribute);
+            if attribute.expect(DER_OID)? != OID_COMMON_NAME {
+                continue;
+            }
+            return match attribute.next()? {
+                (
+                    DER_UTF8_STRING | DER_PRINTABLE_STRING | DER_T61_STRING | DER_IA5_STRING,
+                    value,
+                ) => Some(String::from_utf8_lossy(value).into_owned()),
+                (DER_BMP_STRING, value) => {
+                    let units = value
+                        .chunks_exact(2)
+                        .map(|unit| u16::from_be_bytes([unit[0], unit[1]]))
+                        .collect::<Vec<_>>();
+                    Some(String::from_utf16_lossy(&units))
+                }
+                _ => None,
+            };
+        }
+    }
+    None
+}
+
+/// A reader of DER-encoded values, one after another.
+struct Der<'a> {
+    rest: &'a [u8],
+}
+
+impl<'a> Der<'a> {
+    fn new(data: &'a [u8]) -> Der<'a> {
+        Der { rest: data }
+    }
+
+    /// The tag and contents of the next value.
+    fn next(&mut self) -> Option<(u8, &'a [u8])> {
+        let (&tag, rest) = self.rest.split_first()?;
+        let (&first, mut rest) = rest.split_first()?;
+        let len = if first < 0x80 {
+            first as usize
+        } else {
+            // The long form, where the low bits say how many bytes the length
+            // takes. Indefinite lengths aren't allowed in DER.
+            let count = (first & 0x7f) as usize;
+            if count == 0 || count > 4 || rest.len() < count {
+                return None;
+            }
+            let (len, after) = rest.split_at(count);
+            rest = after;
+            len.iter().fold(0, |len, &byte| len << 8 | byte as usize)
+        };
+        let contents = rest.get(..len)?;
+        self.rest = &rest[len..];
+        Some((tag, contents))
+    }
+
+    /// The contents of the next value, if it has the tag `tag`.
+    fn expect(&mut self, tag: u8) -> Option<&'a [u8]> {
+        match self.next()? {
+            (actual, contents) if actual == tag => Some(contents),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// A DER value with tag `tag` and contents `contents`.
+    fn der(tag: u8, contents: &[&[u8]]) -> Vec<u8> {
+        let contents = contents.concat();
+        let mut value = vec![tag];
+        if contents.len() < 0x80 {
+            value.push(contents.len() as u8);
+        } else {
+            value.push(0x82);
+            value.extend_from_slice(&(contents.len() as u16).to_be_bytes());
+        }
+        value.extend(contents);
+        value
+    }
+
+    fn name(common_name: &str) -> Vec<u8> {
+        der(
+            DER_SEQUENCE,
+            &[&der(
+                DER_SET,
+                &[&der(
+                    DER_SEQUENCE,
+                    &[
+                        &der(DER_OID, &[OID_COMMON_NAME]),
+                        &der(DER_UTF8_STRING, &[common_name.as_bytes()]),
+                    ],
+                )],
+            )],
+        )
+    }
+
+    fn certificate(serial: u8, issuer: &str, subject: &str) -> Vec<u8> {
+        let tbs_certificate = der(
+            DER_SEQUENCE,
+            &[
+                &der(DER_CONTEXT_0, &[&der(DER_INTEGER, &[&[2]])]),
+                &der(DER_INTEGER, &[&[serial]]),
+                &der(DER_SEQUENCE, &[]),
+                &name(issuer),
+                &der(DER_SEQUENCE, &[]),
+                &name(subject),
+            ],
+        );
+        der(DER_SEQUENCE, &[&tbs_certificate])
+    }
+
+    /// A `SignedData` with a certificate chain, signed by the leaf.
+    fn signed_data() -> Vec<u8> {
+        let certificates = der(
+            DER_CONTEXT_0,
+            &[
+                &certificate(1, "Root CA", "Code Signing CA"),
+                &certificate(7, "Code Signing CA", "Mozilla Corporation"),
+            ],
+        );
+        let signer_info = der(
+            DER_SEQUENCE,
+            &[
+                &der(DER_INTEGER, &[&[1]]),
+                &der(
+                    DER_SEQUENCE,
+                    &[&name("Code Signing CA"), &der(DER_INTEGER, &[&[7]])],
+                ),
+            ],
+        );
+        let signed_data = der(
+            DER_SEQUENCE,
+            &[
+                &der(DER_INTEGER, &[&[1]]),
+                &der(DER_SET, &[]),
+                &der(DER_SEQUENCE, &[]),
+                &certificates,
+                &der(DER_SET, &[&signer_info]),
+            ],
+        );
+        der(
+            DER_SEQUENCE,
+            &[
+                &der(DER_OID, &[OID_SIGNED_DATA]),
+                &der(DER_CONTEXT_0, &[&signed_data]),
+            ],
+        )
+    }
+
+    /// A 32-bit PE file with `signed_data` in its certificate table.
+    fn pe_file(signed_data: &[u8]) -> Vec<u8> {
+        let mut file = vec![0; 0x200];
+        file[0..2].copy_from_slice(&IMAGE_DOS_SIGNATURE.to_le_bytes());
+        file[0x3c..0x40].copy_from_slice(&0x80u32.to_le_bytes());
+        file[0x80..0x84].copy_from_slice(&IMAGE_NT_SIGNATURE.to_le_bytes());
+        let optional_header = 0x80 + 24;
+        file[optional_header..optional_header + 2]
+            .copy_from_slice(&IMAGE_NT_OPTIONAL_HDR32_MAGIC.to_le_bytes());
+        let data_directories = optional_header + 96;
+        file[data_directories - 4..data_directories].copy_from_slice(&16u32.to_le_bytes());
+
+        let mut table = vec![];
+        table.extend_from_slice(&(8 + signed_data.len() as u32).to_le_bytes());
+        table.extend_from_slice(&WIN_CERT_REVISION_2_0.to_le_bytes());
+        table.extend_from_slice(&WIN_CERT_TYPE_PKCS_SIGNED_DATA.to_le_bytes());
+        table.extend_from_slice(signed_data);
+        let security = data_directories + 4 * 8;
+        file[security..security + 4].copy_from_slice(&(file.len() as u32).to_le_bytes());
+        file[security + 4..security + 8].copy_from_slice(&(table.len() as u32).to_le_bytes());
+        file.extend(table);
+        file
+    }
+
+    #[test]
+    fn test_signer_name() {
+        let file = pe_file(&signed_data());
+        assert_eq!(signer_name(&file).as_deref(), Some("Mozilla Corporation"));
+
+        // Not signed.
+        let mut unsigned = file.clone();
+        unsigned.truncate(0x200);
+        let security = 0x80 + 24 + 96 + 4 * 8;
+        unsigned[security..security + 8].fill(0);
+        assert_eq!(signer_name(&unsigned), None);
+
+        // Not a PE file.
+        assert_eq!(signer_name(b"\x7fELF"), None);
+        // A signature that's cut off.
+        assert_eq!(signer_name(&file[..file.len() - 10]), None);
+    }
+
+    #[test]
+    fn test_common_name() {
+        assert_eq!(common_name(&name("Foo")[2..]).as_deref(), Some("Foo"));
+        // BMPString is UTF-16BE.
+        let bmp_name = der(
+            DER_SET,
+            &[&der(
+                DER_SEQUENCE,
+                &[
+                    &der(DER_OID, &[OID_COMMON_NAME]),
+                    &der(DER_BMP_STRING, &[&[0x00, 0x46, 0x00, 0xf6, 0x00, 0x6f]]),
+                ],
+            )],
+        );
+        assert_eq!(common_name(&bmp_name).as_deref(), Some("Föo"));
+    }
+
+    #[test]
+    fn test_der_long_lengths() {
+        let value = der(DER_SEQUENCE, &[&[0; 0x100]]);
+        assert_eq!(&value[..4], &[DER_SEQUENCE, 0x82, 0x01, 0x00]);
+        let mut der = Der::new(&value);
+        assert_eq!(der.expect(DER_SEQUENCE).map(<[u8]>::len), Some(0x100));
+        assert_eq!(der.next(), None);
+    }
+}
@@ -0,0 +1,81 @@
+// Copyright 2015 Ted Mielczarek. See the COPYRIGHT
+// file at the top-level directory of this distribution.
+
+//! Information about the crash that isn't in the minidump itself.
+//!
+//! Crash reporters often send along things the minidump has no room for,
+//! like the names the application gave its threads or who signed the
+//! modules it loaded. An [`AuxiliaryDataProvider`] hands those to the
+//! processor. Mozilla's evil JSON is read by one of them,
+//! [`EvilJson`][crate::EvilJson], but anything else can be passed in
+//! directly as [`AuxiliaryData`].
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+
+/// Information about the crash from outside the minidump, see
+/// [`AuxiliaryDataProvider`].
+///
+/// This is itself a provider, for passing in information that's already at
+/// hand.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AuxiliaryData {
+    /// Names of threads (thread id => name), for threads the minidump
+    /// doesn't name.
+    pub thread_names: HashMap<u32, String>,
+    /// Who signed modules (module file name => cert name), see
+    /// [`ProcessState::cert_info`][crate::ProcessState::cert_info].
+    pub module_certs: HashMap<String, String>,
+    /// Anything else to keep with the results, see
+    /// [`ProcessState::annotations`][crate::ProcessState::annotations].
+    pub annotations: BTreeMap<String, String>,
+}
+
+impl AuxiliaryData {
+    /// Name the thread with id `thread_id` `name`.
+    pub fn with_thread_name(mut self, thread_id: u32, name: impl Into<String>) -> Self {
+        self.thread_names.insert(thread_id, name.into());
+        self
+    }
+
+    /// Record that the module `module` was signed by `cert`.
+    pub fn with_module_cert(mut self, module: impl Into<String>, cert: impl Into<String>) -> Self {
+        self.module_certs.insert(module.into(), cert.into());
+        self
+    }
+
+    /// Keep `value` under `key` in the results.
+    pub fn with_annotation(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.annotations.insert(key.into(), value.into());
+        self
+    }
+
+    /// Add everything in `other`, which wins over what's already here.
+    pub(crate) fn merge(&mut self, other: AuxiliaryData) {
+        self.thread_names.extend(other.thread_names);
+        self.module_certs.extend(other.module_certs);
+        self.annotations.extend(other.annotations);
+    }
+}
+
+/// A source of information about the crash from outside the minidump.
+///
+/// Add one to [`ProcessorOptions::auxiliary_data`][crate::ProcessorOptions::auxiliary_data]
+/// to have the processor use what it provides. It's asked once per minidump,
+/// before any stacks are walked.
+pub trait AuxiliaryDataProvider: Send + Sync {
+    /// The information this has about the minidump being processed.
+    fn auxiliary_data(&self) -> AuxiliaryData;
+}
+
+impl AuxiliaryDataProvider for AuxiliaryData {
+    fn auxiliary_data(&self) -> AuxiliaryData {
+        self.clone()
+    }
+}
+
+impl fmt::Debug for dyn AuxiliaryDataProvider + '_ {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("AuxiliaryDataProvider")
+    }
+}
@@ -0,0 +1,184 @@
+// Copyright 2015 Ted Mielczarek. See the COPYRIGHT
+// file at the top-level directory of this distribution.
+
+//! Processing lots of minidumps with the same symbols.
+//!
+//! Crashes tend to come in clusters from the same few builds, so a batch of
+//! minidumps mostly needs the same symbols over and over. Processing them
+//! one [`process_minidump`][crate::process_minidump] call at a time with a
+//! new symbolizer each would fetch and parse those symbols again for every
+//! minidump. A [`BatchProcessor`] keeps one symbol provider (and so one
+//! symbol cache) for all of them.
+
+use std::ops::Deref;
+use std::panic;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use futures_util::stream::{self, Stream, StreamExt};
+use minidump::Minidump;
+use tokio::task::JoinError;
+
+use crate::process_state::ProcessState;
+use crate::processor::{process_minidump_with_options, ProcessError, ProcessorOptions};
+use crate::symbols::SymbolProvider;
+
+/// The default for [`BatchProcessor::with_concurrency`].
+pub const DEFAULT_BATCH_CONCURRENCY: usize = 4;
+
+/// Processes many minidumps with one symbol provider.
+///
+/// Every minidump is processed with the same [`ProcessorOptions`], and up to
+/// [`concurrency`][Self::with_concurrency] of them at once, each in its own
+/// task spawned onto the current tokio runtime (so on a multi-threaded
+/// runtime, minidumps really are walked in parallel). The results come out
+/// as a stream, in whatever order the minidumps finish, each with the key
+/// (or path) it went in with.
+///
+/// Options can't borrow anything, since they're shared by every minidump.
+/// Use [`auxiliary_data`][ProcessorOptions::auxiliary_data] providers rather
+/// than [`evil_json`][ProcessorOptions::evil_json].
+///
+/// # Examples
+///
+/// ```
+/// use futures_util::StreamExt;
+/// use minidump_processor::{simple_symbol_supplier, BatchProcessor, Symbolizer};
+/// use std::path::PathBuf;
+///
+/// #[tokio::main]
+/// async fn main() {
+///     # std::env::set_current_dir(env!("CARGO_MANIFEST_DIR"));
+///     let symbols = vec![PathBuf::from("../testdata/symbols")];
+///     let batch = BatchProcessor::new(Symbolizer::new(simple_symbol_supplier(symbols)));
+///     let paths = vec![PathBuf::from("../testdata/test.dmp")];
+///     let mut results = batch.process_paths(paths);
+///     while let Some((path, result)) = results.next().await {
+///         match result {
+///             Ok(state) => println!("{:?}: {} threads", path, state.threads.len()),
+///             Err(e) => println!("{:?}: {}", path, e),
+///         }
+///     }
+/// }
+/// ```
+#[derive(Debug)]
+pub struct BatchProcessor<P> {
+    symbol_provider: Arc<P>,
+    options: ProcessorOptions<'static>,
+    concurrency: usize,
+}
+
+impl<P> BatchProcessor<P>
+where
+    P: SymbolProvider + Send + Sync + 'static,
+{
+    /// A batch processor getting symbols from `symbol_provider`, with the
+    /// default options.
+    pub fn new(symbol_provider: P) -> Self {
+        Self {
+            symbol_provider: Arc::new(symbol_provider),
+            options: ProcessorOptions::default(),
+            concurrency: DEFAULT_BATCH_CONCURRENCY,
+        }
+    }
+
+    /// Process every minidump with `options`.
+    pub fn with_options(mut self, options: ProcessorOptions<'static>) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Process up to `concurrency` minidumps at once.
+    ///
+    /// Each minidump's threads are walked concurrently as well (see
+    /// [`ProcessorOptions::thread_concurrency`]), so this mostly needs to be
+    /// high enough to keep a slow symbol server from holding up minidumps
+    /// whose symbols are already at hand.
+    pub fn with_concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency;
+        self
+    }
+
+    /// The symbol provider shared by every minidump, e.g. for its
+    /// [`stats`][SymbolProvider::stats].
+    pub fn symbol_provider(&self) -> &P {
+        &self.symbol_provider
+    }
+
+    /// Process the minidump `dump`, the same way as every minidump in a
+    /// batch.
+    pub async fn process<T>(&self, dump: &Minidump<'_, T>) -> Result<ProcessState, ProcessError>
+    where
+        T: Deref<Target = [u8]>,
+    {
+        process_minidump_with_options(dump, &self.symbol_provider, self.options.clone()).await
+    }
+
+    /// Read and process the minidumps at `paths`, yielding each path with
+    /// its result as it's done.
+    ///
+    /// This must be polled from within a tokio runtime.
+    pub fn process_paths<'s, I>(
+        &'s self,
+        paths: I,
+    ) -> impl Stream<Item = (PathBuf, Result<ProcessState, ProcessError>)> + 's
+    where
+        I: IntoIterator<Item = PathBuf>,
+        I::IntoIter: 's,
+    {
+        stream::iter(paths)
+            .map(move |path| {
+                let symbol_provider = self.symbol_provider.clone();
+                let options = self.options.clone();
+                tokio::spawn(async move {
+                    let result = match Minidump::read_path(&path) {
+                        Ok(dump) => {
+                            process_minidump_with_options(&dump, &*symbol_provider, options).await
+                        }
+                        Err(e) => Err(e.into()),
+                    };
+                    (path, result)
+                })
+            })
+            .buffer_unordered(self.concurrency.max(1))
+            .map(joined)
+    }
+
+    /// Process the minidumps in `dumps`, which are each some key to tell
+    /// them apart and the minidump's contents, yielding each key with its
+    /// result as it's done.
+    ///
+    /// This must be polled from within a tokio runtime.
+    pub fn process_bytes<'s, K, T, I>(
+        &'s self,
+        dumps: I,
+    ) -> impl Stream<Item = (K, Result<ProcessState, ProcessError>)> + 's
+    where
+        I: IntoIterator<Item = (K, T)>,
+        I::IntoIter: 's,
+        K: Send + 'static,
+        T: Deref<Target = [u8]> + Send + Sync + 'static,
+    {
+        stream::iter(dumps)
+            .map(move |(key, bytes)| {
+                let symbol_provider = self.symbol_provider.clone();
+                let options = self.options.clone();
+                tokio::spawn(async move {
+                    let result = match Minidump::read(bytes) {
+                        Ok(dump) => {
+                            process_minidump_with_options(&dump, &*symbol_provider, options).await
+                        }
+                        Err(e) => Err(e.into()),
+                    };
+                    (key, result)
+                })
+            })
+            .buffer_unordered(self.concurrency.max(1))
+            .map(joined)
+    }
+}
+
+/// The output of a minidump's task, passing on its panic if it had one.
+fn joined<O>(result: Result<O, JoinError>) -> O {
+    result.unwrap_or_else(|e| panic::resume_unwind(e.into_panic()))
+}
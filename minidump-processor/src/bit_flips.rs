@@ -0,0 +1,192 @@
+// Copyright 2015 Ted Mielczarek. See the COPYRIGHT
+// file at the top-level directory of this distribution.
+
+//! Guessing whether a crash was caused by a bit flip in RAM.
+//!
+//! Faulty hardware can flip a bit of a pointer, which then points to memory
+//! that doesn't exist. If the address a crash happened at (or the crashing
+//! instruction pointer) isn't mapped, but flipping a single one of its bits
+//! gives an address that is, a bit flip is a likely explanation.
+//!
+//! Memory is known to be mapped from the minidump's memory info if it has
+//! any, and otherwise from its modules and the memory it includes, so
+//! minidumps without memory info may miss some candidates.
+
+use std::fmt;
+
+use minidump::system_info::Cpu;
+use minidump::{MinidumpContext, MinidumpModuleList, UnifiedMemoryInfoList, UnifiedMemoryList};
+
+/// Addresses below this are null pointers (plus an offset), which are far
+/// more likely to be bugs.
+const NULL_REGION_SIZE: u64 = 64 * 1024;
+
+/// Where a possibly bit-flipped address came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum BitFlipSource {
+    /// [`ProcessState::crash_address`][crate::ProcessState::crash_address].
+    CrashAddress,
+    /// The instruction pointer of the crashing thread.
+    InstructionPointer,
+}
+
+impl BitFlipSource {
+    /// The name of the source in the JSON schema.
+    pub fn json_name(self) -> &'static str {
+        match self {
+            BitFlipSource::CrashAddress => "crash_address",
+            BitFlipSource::InstructionPointer => "instruction_pointer",
+        }
+    }
+}
+
+impl fmt::Display for BitFlipSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BitFlipSource::CrashAddress => f.write_str("crash address"),
+            BitFlipSource::InstructionPointer => f.write_str("instruction pointer"),
+        }
+    }
+}
+
+/// An address involved in a crash that may have had one of its bits
+/// flipped.
+///
+/// See [`ProcessorOptions::detect_bit_flips`][crate::ProcessorOptions::detect_bit_flips].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BitFlip {
+    /// Where the address came from.
+    pub source: BitFlipSource,
+    /// The address, which isn't mapped.
+    pub address: u64,
+    /// What the address would be without the flip, which is mapped.
+    pub corrected_address: u64,
+    /// Which bit would have been flipped, from 0 for the least significant.
+    pub bit: u32,
+}
+
+/// How a [`BitFlip`]'s addresses are checked.
+struct AddressSpace<'a> {
+    memory_info: Option<&'a UnifiedMemoryInfoList<'a>>,
+    memory_list: &'a UnifiedMemoryList<'a>,
+    modules: &'a MinidumpModuleList,
+}
+
+impl AddressSpace<'_> {
+    fn is_mapped(&self, address: u64) -> bool {
+        match self.memory_info {
+            Some(info) => info.region_containing(address).is_some(),
+            None => {
+                self.modules.module_at_address(address).is_some()
+                    || self.memory_list.memory_at_address(address).is_some()
+            }
+        }
+    }
+
+    /// Whether code could run at `address`, which (without memory info) means
+    /// it's in a module.
+    fn is_code(&self, address: u64) -> bool {
+        match self.memory_info {
+            Some(info) => info.is_executable(address),
+            None => self.modules.module_at_address(address).is_some(),
+        }
+    }
+}
+
+/// Look for bit flips in the crash address and crashing instruction
+/// pointer.
+pub(crate) fn find_bit_flips(
+    cpu: Cpu,
+    crash_address: Option<u64>,
+    context: Option<&MinidumpContext>,
+    memory_info: Option<&UnifiedMemoryInfoList>,
+    memory_list: &UnifiedMemoryList,
+    modules: &MinidumpModuleList,
+) -> Vec<BitFlip> {
+    let space = AddressSpace {
+        memory_info,
+        memory_list,
+        modules,
+    };
+    let bits = match cpu {
+        Cpu::X86 | Cpu::Ppc | Cpu::Sparc | Cpu::Arm | Cpu::Mips => 32,
+        _ => 64,
+    };
+
+    let mut flips = vec![];
+    if let Some(address) = crash_address {
+        flips.extend(candidates(
+            BitFlipSource::CrashAddress,
+            address,
+            bits,
+            |a| space.is_mapped(a),
+        ));
+    }
+    if let Some(context) = context {
+        let address = context.get_instruction_pointer();
+        flips.extend(candidates(
+            BitFlipSource::InstructionPointer,
+            address,
+            bits,
+            |a| space.is_code(a),
+        ));
+    }
+    flips
+}
+
+/// The single-bit corrections of `address` that are `valid`, if it isn't
+/// valid itself.
+fn candidates(
+    source: BitFlipSource,
+    address: u64,
+    bits: u32,
+    valid: impl Fn(u64) -> bool,
+) -> Vec<BitFlip> {
+    if address < NULL_REGION_SIZE || valid(address) {
+        return vec![];
+    }
+    (0..bits)
+        .map(|bit| (bit, address ^ (1 << bit)))
+        .filter(|&(_, corrected)| corrected >= NULL_REGION_SIZE && valid(corrected))
+        .map(|(bit, corrected_address)| BitFlip {
+            source,
+            address,
+            corrected_address,
+            bit,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_candidates() {
+        let mapped = |address: u64| (0x7f00_0000..0x7f01_0000).contains(&address);
+
+        // Bit 36 of a pointer into the mapping.
+        let flips = candidates(BitFlipSource::CrashAddress, 0x10_7f00_1234, 64, mapped);
+        assert_eq!(
+            flips,
+            vec![BitFlip {
+                source: BitFlipSource::CrashAddress,
+                address: 0x10_7f00_1234,
+                corrected_address: 0x7f00_1234,
+                bit: 36,
+            }]
+        );
+
+        // Bits above the pointer width aren't considered.
+        assert!(candidates(BitFlipSource::CrashAddress, 0x10_7f00_1234, 32, mapped).is_empty());
+        // Mapped addresses are fine as they are.
+        assert!(candidates(BitFlipSource::CrashAddress, 0x7f00_1234, 64, mapped).is_empty());
+        // Null pointers are just null pointers.
+        let low = |address: u64| (0x1_0000..0x2_0000).contains(&address);
+        assert!(candidates(BitFlipSource::CrashAddress, 0x45, 64, low).is_empty());
+        // Too far away.
+        assert!(candidates(BitFlipSource::CrashAddress, 0x41414141, 64, mapped).is_empty());
+    }
+}
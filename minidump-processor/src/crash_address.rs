@@ -0,0 +1,190 @@
+// Copyright 2015 Ted Mielczarek. See the COPYRIGHT
+// file at the top-level directory of this distribution.
+
+//! Classifying the address of a bad memory access.
+//!
+//! Most bad accesses are through null pointers, either directly or to a
+//! field of the struct the pointer should have pointed to. On x86-64, the
+//! CPU also refuses any address that isn't canonical (with bits 48 to 63
+//! all the same as bit 47), which usually means the pointer was garbage.
+//!
+//! The register the address was most likely computed from is the one
+//! closest to it, since the crashing instruction would have added an offset
+//! to a register to get the address.
+
+use std::fmt;
+
+use minidump::system_info::Cpu;
+use minidump::{CrashReason, MinidumpContext};
+
+use crate::exploitability;
+
+/// Addresses this close to null are considered null pointers (plus or
+/// minus an offset).
+const NULL_REGION_SIZE: u64 = 64 * 1024;
+
+/// Registers that don't hold pointers.
+const IGNORED_REGISTERS: &[&str] = &["eip", "rip", "pc", "srr0", "npc", "efl"];
+
+/// What kind of address a crash address is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum CrashAddressKind {
+    /// Null itself.
+    Null,
+    /// An offset from null, like the field of a struct through a null
+    /// pointer.
+    NearNull,
+    /// An x86-64 address that isn't canonical.
+    NonCanonical,
+}
+
+impl CrashAddressKind {
+    /// The name of the kind in the JSON schema.
+    pub fn json_name(self) -> &'static str {
+        match self {
+            CrashAddressKind::Null => "null",
+            CrashAddressKind::NearNull => "near_null",
+            CrashAddressKind::NonCanonical => "non_canonical",
+        }
+    }
+}
+
+impl fmt::Display for CrashAddressKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CrashAddressKind::Null => f.write_str("null pointer"),
+            CrashAddressKind::NearNull => f.write_str("near-null pointer"),
+            CrashAddressKind::NonCanonical => f.write_str("non-canonical address"),
+        }
+    }
+}
+
+/// What kind of address a crash address is, and where it likely came from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CrashAddressClass {
+    /// What kind of address it is.
+    pub kind: CrashAddressKind,
+    /// The general-purpose register of the crashing thread that the address
+    /// was likely computed from, if any is close enough.
+    pub register: Option<String>,
+}
+
+/// Classify the address of the crash with `reason` at `address`, in the
+/// crashing thread's `context`.
+///
+/// Crashes that aren't bad memory accesses, and addresses that are none of
+/// the [`CrashAddressKind`]s, aren't classified.
+pub(crate) fn classify(
+    cpu: Cpu,
+    reason: CrashReason,
+    address: u64,
+    context: Option<&MinidumpContext>,
+) -> Option<CrashAddressClass> {
+    if !exploitability::is_memory_access(reason) {
+        return None;
+    }
+    let mask = match cpu.pointer_width() {
+        Some(4) => u64::from(u32::MAX),
+        _ => u64::MAX,
+    };
+    let registers = context
+        .map(|context| {
+            context
+                .general_purpose_registers()
+                .iter()
+                .filter(|&&reg| !IGNORED_REGISTERS.contains(&reg))
+                .filter_map(|&reg| Some((reg, context.get_register(reg)? & mask)))
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+
+    if cpu == Cpu::X86_64 {
+        // The CPU doesn't say which address was bad when it's non-canonical,
+        // so Windows reports all ones and Linux reports null. Only a register
+        // holding a non-canonical value tells Linux's apart from a real null
+        // pointer.
+        if !is_canonical(address) {
+            let register = closest_register(&registers, address, mask);
+            return Some(class(CrashAddressKind::NonCanonical, register));
+        }
+        if address == 0 || address == u64::MAX {
+            let register = registers
+                .iter()
+                .find(|&&(_, value)| !is_canonical(value))
+                .map(|&(reg, _)| reg);
+            if register.is_some() || address == u64::MAX {
+                return Some(class(CrashAddressKind::NonCanonical, register));
+            }
+        }
+    }
+
+    let address = address & mask;
+    let kind = if address == 0 {
+        CrashAddressKind::Null
+    } else if distance(address, 0, mask) < NULL_REGION_SIZE {
+        CrashAddressKind::NearNull
+    } else {
+        return None;
+    };
+    Some(class(kind, closest_register(&registers, address, mask)))
+}
+
+fn class(kind: CrashAddressKind, register: Option<&str>) -> CrashAddressClass {
+    CrashAddressClass {
+        kind,
+        register: register.map(String::from),
+    }
+}
+
+/// Whether bits 48 to 63 of `address` are all the same as bit 47.
+fn is_canonical(address: u64) -> bool {
+    let top = address >> 47;
+    top == 0 || top == 0x1_ffff
+}
+
+/// How far apart `a` and `b` are, in either direction, wrapping around at
+/// `mask`.
+fn distance(a: u64, b: u64, mask: u64) -> u64 {
+    let up = a.wrapping_sub(b) & mask;
+    let down = b.wrapping_sub(a) & mask;
+    up.min(down)
+}
+
+/// The first of the `registers` whose value is closest to `address`, if any
+/// is within [`NULL_REGION_SIZE`] of it.
+fn closest_register<'a>(registers: &[(&'a str, u64)], address: u64, mask: u64) -> Option<&'a str> {
+    registers
+        .iter()
+        .map(|&(reg, value)| (reg, distance(address, value, mask)))
+        .filter(|&(_, distance)| distance < NULL_REGION_SIZE)
+        .min_by_key(|&(_, distance)| distance)
+        .map(|(reg, _)| reg)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_is_canonical() {
+        assert!(is_canonical(0));
+        assert!(is_canonical(0x7fff_ffff_ffff));
+        assert!(is_canonical(0xffff_8000_0000_0000));
+        assert!(is_canonical(u64::MAX));
+        assert!(!is_canonical(0x8000_0000_0000));
+        assert!(!is_canonical(0x4141_4141_4141_4141));
+    }
+
+    #[test]
+    fn test_closest_register() {
+        let mask = u64::from(u32::MAX);
+        let registers = [("eax", 0), ("ebx", 0x40), ("ecx", 0x7f00_0000)];
+        assert_eq!(closest_register(&registers, 0x45, mask), Some("ebx"));
+        assert_eq!(closest_register(&registers, 0x8, mask), Some("eax"));
+        // A negative offset from null wraps around.
+        assert_eq!(closest_register(&registers, 0xffff_fff8, mask), Some("eax"));
+        assert_eq!(closest_register(&registers, 0x4141_4141, mask), None);
+    }
+}
@@ -0,0 +1,142 @@
+// Copyright 2015 Ted Mielczarek. See the COPYRIGHT
+// file at the top-level directory of this distribution.
+
+//! Keeping the memory around the crash, for showing without the minidump.
+
+use std::fmt;
+
+use minidump::{MinidumpContext, UnifiedMemoryInfoList, UnifiedMemoryList};
+
+use crate::process_state::MemoryAccess;
+
+/// Which address a [`CrashMemory`] is around.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum CrashMemorySource {
+    /// [`ProcessState::crash_address`][crate::ProcessState::crash_address].
+    CrashAddress,
+    /// The instruction pointer of the crashing thread.
+    InstructionPointer,
+    /// The stack pointer of the crashing thread.
+    StackPointer,
+}
+
+impl CrashMemorySource {
+    /// The name of the source in the JSON schema.
+    pub fn json_name(self) -> &'static str {
+        match self {
+            CrashMemorySource::CrashAddress => "crash_address",
+            CrashMemorySource::InstructionPointer => "instruction_pointer",
+            CrashMemorySource::StackPointer => "stack_pointer",
+        }
+    }
+}
+
+impl fmt::Display for CrashMemorySource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CrashMemorySource::CrashAddress => f.write_str("crash address"),
+            CrashMemorySource::InstructionPointer => f.write_str("instruction pointer"),
+            CrashMemorySource::StackPointer => f.write_str("stack pointer"),
+        }
+    }
+}
+
+/// The memory around an address involved in the crash.
+///
+/// See [`ProcessorOptions::crash_memory_size`][crate::ProcessorOptions::crash_memory_size].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CrashMemory {
+    /// Which address this is around.
+    pub source: CrashMemorySource,
+    /// The address itself.
+    pub address: u64,
+    /// The memory permissions of `address`, if the minidump includes memory
+    /// info.
+    pub access: Option<MemoryAccess>,
+    /// The address of the first of `bytes`.
+    pub base_address: u64,
+    /// The memory starting at `base_address`, with `None` for each byte the
+    /// minidump doesn't include.
+    pub bytes: Vec<Option<u8>>,
+}
+
+impl CrashMemory {
+    /// The bytes as a string of hex digit pairs, with "??" for each missing
+    /// byte.
+    pub fn hex(&self) -> String {
+        self.bytes
+            .iter()
+            .map(|byte| match byte {
+                Some(byte) => format!("{:02x}", byte),
+                None => String::from("??"),
+            })
+            .collect()
+    }
+
+    /// Write a hexdump of the bytes to `f`, 16 to a line, each line starting
+    /// with `indent` and the address of its first byte.
+    pub(crate) fn print_hexdump<T: std::io::Write>(
+        &self,
+        f: &mut T,
+        indent: &str,
+    ) -> std::io::Result<()> {
+        for (i, line) in self.bytes.chunks(16).enumerate() {
+            let address = self.base_address.wrapping_add(i as u64 * 16);
+            write!(f, "{}{:#x} ", indent, address)?;
+            for byte in line {
+                match byte {
+                    Some(byte) => write!(f, " {:02x}", byte)?,
+                    None => write!(f, " ??")?,
+                }
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+/// Copy `size` bytes from around the crash address and the crashing
+/// thread's instruction and stack pointers, centered on each.
+pub(crate) fn capture(
+    size: usize,
+    crash_address: Option<u64>,
+    context: Option<&MinidumpContext>,
+    memory_list: &UnifiedMemoryList,
+    memory_info: Option<&UnifiedMemoryInfoList>,
+) -> Vec<CrashMemory> {
+    let mut addresses = vec![];
+    if let Some(address) = crash_address {
+        addresses.push((CrashMemorySource::CrashAddress, address));
+    }
+    if let Some(context) = context {
+        addresses.push((
+            CrashMemorySource::InstructionPointer,
+            context.get_instruction_pointer(),
+        ));
+        addresses.push((CrashMemorySource::StackPointer, context.get_stack_pointer()));
+    }
+
+    addresses
+        .into_iter()
+        .map(|(source, address)| {
+            let base_address = address.saturating_sub(size as u64 / 2);
+            let end = base_address.saturating_add(size as u64);
+            let bytes = (base_address..end)
+                .map(|addr| {
+                    memory_list
+                        .memory_at_address(addr)?
+                        .get_memory_at_address::<u8>(addr)
+                })
+                .collect();
+            CrashMemory {
+                source,
+                address,
+                access: memory_info.map(|info| MemoryAccess::at_address(info, address)),
+                base_address,
+                bytes,
+            }
+        })
+        .collect()
+}
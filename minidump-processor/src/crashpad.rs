@@ -0,0 +1,84 @@
+// Copyright 2015 Ted Mielczarek. See the COPYRIGHT
+// file at the top-level directory of this distribution.
+
+//! Annotations recorded by Crashpad.
+//!
+//! Crashpad's clients annotate the process and each of its modules with
+//! key/value pairs (and, for modules, plain lists of strings), which its
+//! handler writes to the minidump's Crashpad info stream. Products use them
+//! for things like the release channel or which feature flags were on, so
+//! they're kept here attributed to the module that set them.
+
+use std::collections::BTreeMap;
+
+use minidump::{MinidumpAnnotation, MinidumpCrashpadInfo, MinidumpModuleList, Module};
+
+/// The annotations in the minidump's Crashpad info stream.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CrashpadAnnotations {
+    /// The annotations of the process as a whole (key => value).
+    pub simple_annotations: BTreeMap<String, String>,
+    /// The annotations of each module that has any.
+    pub modules: Vec<ModuleAnnotations>,
+}
+
+/// The annotations Crashpad recorded for one module.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ModuleAnnotations {
+    /// The index of the module in [`ProcessState::modules`][crate::ProcessState::modules].
+    pub module_index: usize,
+    /// The module's code file, or `None` if the module list doesn't have a
+    /// module at `module_index`.
+    pub module: Option<String>,
+    /// Annotations that are just a string.
+    pub list_annotations: Vec<String>,
+    /// Key => value annotations.
+    pub simple_annotations: BTreeMap<String, String>,
+    /// Annotation objects (name => value). Only the string ones are kept, as
+    /// the others are in formats only their client knows.
+    pub annotation_objects: BTreeMap<String, String>,
+}
+
+impl CrashpadAnnotations {
+    /// Collect the annotations in `info`, matching them up with the modules
+    /// in `modules`.
+    pub fn from_stream(info: &MinidumpCrashpadInfo, modules: &MinidumpModuleList) -> Self {
+        let modules = info
+            .module_list
+            .iter()
+            .map(|module_info| ModuleAnnotations {
+                module_index: module_info.module_index,
+                module: modules
+                    .iter()
+                    .nth(module_info.module_index)
+                    .map(|module| module.code_file().into_owned()),
+                list_annotations: module_info.list_annotations.clone(),
+                simple_annotations: module_info.simple_annotations.clone(),
+                annotation_objects: module_info
+                    .annotation_objects
+                    .iter()
+                    .filter_map(|(name, annotation)| match annotation {
+                        MinidumpAnnotation::String(value) => Some((name.clone(), value.clone())),
+                        _ => None,
+                    })
+                    .collect(),
+            })
+            .collect();
+        CrashpadAnnotations {
+            simple_annotations: info.simple_annotations.clone(),
+            modules,
+        }
+    }
+
+    /// Whether there are no annotations at all.
+    pub fn is_empty(&self) -> bool {
+        self.simple_annotations.is_empty()
+            && self.modules.iter().all(|module| {
+                module.list_annotations.is_empty()
+                    && module.simple_annotations.is_empty()
+                    && module.annotation_objects.is_empty()
+            })
+    }
+}
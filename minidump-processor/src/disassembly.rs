@@ -0,0 +1,268 @@
+// Copyright 2015 Ted Mielczarek. See the COPYRIGHT
+// file at the top-level directory of this distribution.
+
+//! Disassembling the instructions around the crash.
+//!
+//! When the minidump includes the memory the crashing instruction is in (as
+//! minidumps written by Breakpad and Crashpad usually do), the instructions
+//! around it and the memory operand it faulted on tell a lot more about the
+//! crash than the instruction pointer alone.
+//!
+//! This needs the `disasm` feature, and only supports x86 and x86-64 for now.
+//! Without it, there's never any disassembly.
+
+use minidump::system_info::Cpu;
+use minidump::{MinidumpContext, UnifiedMemoryList};
+
+/// The instructions around the crashing instruction.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CrashDisassembly {
+    /// The instructions, in order.
+    pub instructions: Vec<DisassembledInstruction>,
+    /// The index of the crashing instruction in `instructions`.
+    pub crashing_instruction: usize,
+    /// The memory operand of the crashing instruction, if it has one.
+    pub memory_operand: Option<MemoryOperand>,
+}
+
+/// An instruction in a [`CrashDisassembly`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DisassembledInstruction {
+    /// Where the instruction is.
+    pub address: u64,
+    /// The instruction's encoding.
+    pub bytes: Vec<u8>,
+    /// The instruction in Intel syntax, like `mov dword [eax], 0x0`.
+    pub text: String,
+}
+
+/// The memory operand of the crashing instruction.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MemoryOperand {
+    /// The operand in Intel syntax, like `[eax + 0x10]`.
+    pub text: String,
+    /// The address the operand refers to, if all the registers it's made of
+    /// are known (and it isn't relative to the `fs` or `gs` segments).
+    pub address: Option<u64>,
+}
+
+/// How many instructions to show before the crashing one.
+#[cfg(feature = "disasm")]
+const INSTRUCTIONS_BEFORE: usize = 5;
+/// How many instructions to show after the crashing one.
+#[cfg(feature = "disasm")]
+const INSTRUCTIONS_AFTER: usize = 5;
+/// The longest an x86 instruction can be.
+#[cfg(feature = "disasm")]
+const MAX_INSTRUCTION_LEN: usize = 15;
+
+/// A memory operand as decoded, before its address is worked out.
+#[cfg(feature = "disasm")]
+struct DecodedOperand {
+    text: String,
+    /// `None` for segments with a base we don't know.
+    base: Option<Option<String>>,
+    index: Option<(String, u8)>,
+    displacement: i64,
+}
+
+/// An instruction as decoded.
+#[cfg(feature = "disasm")]
+struct Decoded {
+    len: usize,
+    text: String,
+    memory_operand: Option<DecodedOperand>,
+}
+
+/// Generates a function decoding one instruction with one of yaxpeax-x86's
+/// decoders, which all have the same API but different types.
+#[cfg(feature = "disasm")]
+macro_rules! x86_decoder {
+    ($name:ident, $mode:ident) => {
+        fn $name(bytes: &[u8]) -> Option<Decoded> {
+            use yaxpeax_arch::{Decoder, LengthedInstruction, U8Reader};
+            use yaxpeax_x86::$mode::{InstDecoder, Operand, Segment};
+
+            let mut reader = U8Reader::new(bytes);
+            let instruction = InstDecoder::default().decode(&mut reader).ok()?;
+            let memory_operand = (0..instruction.operand_count()).find_map(|i| {
+                let operand = instruction.operand(i);
+                if !operand.is_memory() {
+                    return None;
+                }
+                let unknown_segment = matches!(
+                    instruction.segment_override_for_op(i),
+                    Some(Segment::FS) | Some(Segment::GS)
+                );
+                let name = |reg: yaxpeax_x86::$mode::RegSpec| reg.to_string();
+                let (base, index, displacement) = match operand {
+                    Operand::DisplacementU32(disp) => (None, None, disp as i64),
+                    Operand::DisplacementU64(disp) => (None, None, disp as i64),
+                    Operand::RegDeref(base) => (Some(name(base)), None, 0),
+                    Operand::RegDisp(base, disp) => (Some(name(base)), None, disp as i64),
+                    Operand::RegScale(index, scale) => (None, Some((name(index), scale)), 0),
+                    Operand::RegScaleDisp(index, scale, disp) => {
+                        (None, Some((name(index), scale)), disp as i64)
+                    }
+                    Operand::RegIndexBase(base, index) => {
+                        (Some(name(base)), Some((name(index), 1)), 0)
+                    }
+                    Operand::RegIndexBaseDisp(base, index, disp) => {
+                        (Some(name(base)), Some((name(index), 1)), disp as i64)
+                    }
+                    Operand::RegIndexBaseScale(base, index, scale) => {
+                        (Some(name(base)), Some((name(index), scale)), 0)
+                    }
+                    Operand::RegIndexBaseScaleDisp(base, index, scale, disp) => {
+                        (Some(name(base)), Some((name(index), scale)), disp as i64)
+                    }
+                    // Masked (AVX-512) operands and the like.
+                    _ => return None,
+                };
+                Some(DecodedOperand {
+                    text: operand.to_string(),
+                    base: if unknown_segment { None } else { Some(base) },
+                    index,
+                    displacement,
+                })
+            });
+            Some(Decoded {
+                len: instruction.len().to_const() as usize,
+                text: instruction.to_string(),
+                memory_operand,
+            })
+        }
+    };
+}
+
+#[cfg(feature = "disasm")]
+x86_decoder!(decode_x86, protected_mode);
+#[cfg(feature = "disasm")]
+x86_decoder!(decode_amd64, long_mode);
+
+/// Disassemble the instructions around the instruction pointer of the
+/// crashing thread's `context`, if `memory_list` has them.
+#[cfg(feature = "disasm")]
+pub(crate) fn disassemble(
+    cpu: Cpu,
+    context: &MinidumpContext,
+    memory_list: &UnifiedMemoryList,
+) -> Option<CrashDisassembly> {
+    let decode = match cpu {
+        Cpu::X86 => decode_x86,
+        Cpu::X86_64 => decode_amd64,
+        _ => return None,
+    };
+    let ip = context.get_instruction_pointer();
+    let memory = memory_list.memory_at_address(ip)?;
+    let bytes = memory.bytes();
+    let offset = (ip - memory.base_address()) as usize;
+
+    // Instructions can't be decoded backwards, so find the earliest address
+    // before the crashing instruction that decodes into instructions ending
+    // right at it. Decoding x86 quickly falls into step with the real
+    // instructions, so the last few of those are almost always right.
+    let before = (1..=(INSTRUCTIONS_BEFORE * MAX_INSTRUCTION_LEN).min(offset))
+        .rev()
+        .find_map(|distance| {
+            let mut instructions = vec![];
+            let mut at = offset - distance;
+            while at < offset {
+                let decoded = decode(&bytes[at..])?;
+                let len = decoded.len;
+                instructions.push((at, decoded));
+                at += len;
+            }
+            if at == offset {
+                Some(instructions)
+            } else {
+                None
+            }
+        })
+        .unwrap_or_default();
+    let before = &before[before.len().saturating_sub(INSTRUCTIONS_BEFORE)..];
+
+    let mut after = vec![];
+    let mut at = offset;
+    while after.len() <= INSTRUCTIONS_AFTER && at < bytes.len() {
+        match decode(&bytes[at..]) {
+            Some(decoded) => {
+                let len = decoded.len;
+                after.push((at, decoded));
+                at += len;
+            }
+            None => break,
+        }
+    }
+    // Without the crashing instruction there's nothing to show.
+    if after.is_empty() {
+        return None;
+    }
+
+    let next_ip = ip + after[0].1.len as u64;
+    let memory_operand = after[0]
+        .1
+        .memory_operand
+        .as_ref()
+        .map(|operand| MemoryOperand {
+            text: operand.text.clone(),
+            address: operand_address(cpu, context, operand, next_ip),
+        });
+    let instructions = before
+        .iter()
+        .chain(after.iter())
+        .map(|(at, decoded)| DisassembledInstruction {
+            address: memory.base_address() + *at as u64,
+            bytes: bytes[*at..*at + decoded.len].to_vec(),
+            text: decoded.text.clone(),
+        })
+        .collect();
+    Some(CrashDisassembly {
+        instructions,
+        crashing_instruction: before.len(),
+        memory_operand,
+    })
+}
+
+#[cfg(not(feature = "disasm"))]
+pub(crate) fn disassemble(
+    _cpu: Cpu,
+    _context: &MinidumpContext,
+    _memory_list: &UnifiedMemoryList,
+) -> Option<CrashDisassembly> {
+    None
+}
+
+/// The address `operand` refers to, with the registers in `context`.
+/// `next_ip` is the address of the next instruction, which `rip`-relative
+/// operands are relative to.
+#[cfg(feature = "disasm")]
+fn operand_address(
+    cpu: Cpu,
+    context: &MinidumpContext,
+    operand: &DecodedOperand,
+    next_ip: u64,
+) -> Option<u64> {
+    let register = |name: &str| match name {
+        "rip" | "eip" => Some(next_ip),
+        _ => context.get_register(name),
+    };
+    let base = match operand.base.as_ref()? {
+        Some(base) => register(base)?,
+        None => 0,
+    };
+    let index = match &operand.index {
+        Some((index, scale)) => register(index)?.wrapping_mul(u64::from(*scale)),
+        None => 0,
+    };
+    let address = base
+        .wrapping_add(index)
+        .wrapping_add(operand.displacement as u64);
+    match cpu {
+        Cpu::X86 => Some(address & 0xffff_ffff),
+        _ => Some(address),
+    }
+}
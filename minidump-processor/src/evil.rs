@@ -1,22 +1,38 @@
-use log::error;
 use serde_json::map::Map;
 use serde_json::Value;
 use std::collections::HashMap;
 use std::fs::File;
 use std::io::BufReader;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
+use tracing::error;
 
-/// Things extracted from the Evil JSON File
-#[derive(Debug, Default)]
-pub(crate) struct Evil {
-    /// module name => cert
-    pub certs: HashMap<String, String>,
-    /// thread id => thread name
-    pub thread_names: HashMap<u32, String>,
+use crate::auxiliary::{AuxiliaryData, AuxiliaryDataProvider};
+
+/// The evil "raw json" mozilla's legacy infrastructure relies on (to be
+/// phased out), as an [`AuxiliaryDataProvider`].
+///
+/// This provides thread names and module certificates. A file that can't
+/// be read or parsed provides nothing (and logs an error).
+#[derive(Debug, Clone)]
+pub struct EvilJson {
+    path: PathBuf,
+}
+
+impl EvilJson {
+    /// The evil JSON at `path`.
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+}
+
+impl AuxiliaryDataProvider for EvilJson {
+    fn auxiliary_data(&self) -> AuxiliaryData {
+        handle_evil(&self.path).unwrap_or_default()
+    }
 }
 
-pub(crate) fn handle_evil(evil_path: &Path) -> Option<Evil> {
+fn handle_evil(evil_path: &Path) -> Option<AuxiliaryData> {
     // Get the evil json
     let evil_json = File::open(evil_path)
         .map_err(|e| {
@@ -35,11 +51,10 @@ pub(crate) fn handle_evil(evil_path: &Path) -> Option<Evil> {
 
     // Of course evil json contains a string-that-can-be-parsed-as-a-json-object
     // instead of having a normal json object!
-    fn evil_obj<K, V>(json: &mut Map<String, Value>, field_name: &str) -> Option<HashMap<K, V>>
-    where
-        K: for<'de> serde::de::Deserialize<'de> + Eq + std::hash::Hash,
-        V: for<'de> serde::de::Deserialize<'de>,
-    {
+    fn evil_obj(
+        json: &mut Map<String, Value>,
+        field_name: &str,
+    ) -> Option<HashMap<String, Vec<String>>> {
         json.remove(field_name).and_then(|val| {
             match val {
                 Value::Object(_) => serde_json::from_value(val).ok(),
@@ -96,8 +111,9 @@ pub(crate) fn handle_evil(evil_path: &Path) -> Option<Evil> {
         })
         .collect();
 
-    Some(Evil {
-        certs,
+    Some(AuxiliaryData {
         thread_names,
+        module_certs: certs,
+        annotations: Default::default(),
     })
 }
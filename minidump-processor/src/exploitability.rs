@@ -0,0 +1,331 @@
+// Copyright 2015 Ted Mielczarek. See the COPYRIGHT
+// file at the top-level directory of this distribution.
+
+//! Guessing how exploitable a crash is, like Breakpad's exploitability
+//! engines.
+//!
+//! This looks at the kind of crash, the address it happened at, and where
+//! the crashing thread's instruction and stack pointers were, along with the
+//! memory info of the minidump when it has any. Unlike Breakpad's Linux
+//! engine, it doesn't disassemble the crashing instruction, so it can't tell
+//! a read from a write on platforms that don't say which it was.
+//!
+//! This is a heuristic, and a coarse one. A rating of `None` doesn't mean a
+//! crash can't be exploited.
+
+use std::fmt;
+
+use minidump::format::{
+    ExceptionCodeLinux, ExceptionCodeMac, ExceptionCodeWindows, ExceptionCodeWindowsAccessType,
+    NtStatusWindows,
+};
+use minidump::{CrashReason, MinidumpContext, MinidumpModuleList, UnifiedMemoryInfoList};
+
+/// Addresses below this are considered null pointer dereferences (plus an
+/// offset), which are rarely exploitable.
+const NULL_REGION_SIZE: u64 = 64 * 1024;
+
+/// How likely a crash is to be exploitable, from least to most.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ExploitabilityRating {
+    /// Crashes that happen on purpose, like assertions and aborts.
+    None,
+    /// Crashes that aren't known to be exploitable, but are unusual enough to
+    /// be worth a look.
+    Interesting,
+    /// Crashes that are unlikely to be exploitable, like null pointer
+    /// dereferences.
+    Low,
+    /// Crashes that might be exploitable, like reads of wild pointers.
+    Medium,
+    /// Crashes that are likely to be exploitable, like executing data or
+    /// writing through wild pointers.
+    High,
+}
+
+impl ExploitabilityRating {
+    /// The name of the rating in the JSON schema.
+    pub fn json_name(self) -> &'static str {
+        match self {
+            ExploitabilityRating::None => "none",
+            ExploitabilityRating::Interesting => "interesting",
+            ExploitabilityRating::Low => "low",
+            ExploitabilityRating::Medium => "medium",
+            ExploitabilityRating::High => "high",
+        }
+    }
+}
+
+impl fmt::Display for ExploitabilityRating {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.json_name())
+    }
+}
+
+/// How exploitable a crash looks, and why.
+///
+/// See [`ProcessorOptions::analyze_exploitability`][crate::ProcessorOptions::analyze_exploitability].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Exploitability {
+    /// The highest rating of any of the `evidence`.
+    pub rating: ExploitabilityRating,
+    /// What was found about the crash, and how exploitable each thing made it
+    /// look, most exploitable first.
+    pub evidence: Vec<(ExploitabilityRating, String)>,
+}
+
+/// What kind of memory access a crash was.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Access {
+    Read,
+    Write,
+    Exec,
+    /// The platform doesn't say.
+    Unknown,
+}
+
+/// What a crash reason says about exploitability on its own.
+#[derive(Clone, Copy)]
+enum Kind {
+    /// A bad memory access, which depends on where it was.
+    Access(Access),
+    /// Everything else.
+    Rated(ExploitabilityRating, &'static str),
+}
+
+fn classify(reason: CrashReason) -> Kind {
+    use ExploitabilityRating as Rating;
+    match reason {
+        CrashReason::WindowsAccessViolation(access) => Kind::Access(match access {
+            ExceptionCodeWindowsAccessType::READ => Access::Read,
+            ExceptionCodeWindowsAccessType::WRITE => Access::Write,
+            ExceptionCodeWindowsAccessType::EXEC => Access::Exec,
+        }),
+        CrashReason::WindowsInPageError(..) => Kind::Access(Access::Unknown),
+        CrashReason::WindowsStackBufferOverrun(_) => {
+            Kind::Rated(Rating::High, "the stack buffer overrun check failed")
+        }
+        CrashReason::WindowsNtStatus(NtStatusWindows::STATUS_HEAP_CORRUPTION) => {
+            Kind::Rated(Rating::High, "the heap is corrupt")
+        }
+        CrashReason::WindowsGeneral(code) => match code {
+            ExceptionCodeWindows::EXCEPTION_GUARD_PAGE => {
+                Kind::Rated(Rating::High, "a guard page was accessed")
+            }
+            ExceptionCodeWindows::EXCEPTION_ILLEGAL_INSTRUCTION
+            | ExceptionCodeWindows::EXCEPTION_PRIV_INSTRUCTION => {
+                Kind::Rated(Rating::Medium, "an invalid instruction was executed")
+            }
+            ExceptionCodeWindows::EXCEPTION_BREAKPOINT
+            | ExceptionCodeWindows::EXCEPTION_SINGLE_STEP
+            | ExceptionCodeWindows::EXCEPTION_INT_DIVIDE_BY_ZERO
+            | ExceptionCodeWindows::EXCEPTION_STACK_OVERFLOW
+            | ExceptionCodeWindows::OUT_OF_MEMORY
+            | ExceptionCodeWindows::UNHANDLED_CPP_EXCEPTION
+            | ExceptionCodeWindows::SIMULATED => {
+                Kind::Rated(Rating::None, "the crash was deliberate or benign")
+            }
+            _ => Kind::Rated(Rating::Interesting, "the crash isn't a known kind"),
+        },
+        CrashReason::LinuxSigsegv(_) | CrashReason::LinuxSigbus(_) => Kind::Access(Access::Unknown),
+        CrashReason::LinuxSigill(_) => {
+            Kind::Rated(Rating::Medium, "an invalid instruction was executed")
+        }
+        CrashReason::LinuxSigtrap(_) | CrashReason::LinuxSigfpe(_) => {
+            Kind::Rated(Rating::None, "the crash was deliberate or benign")
+        }
+        CrashReason::LinuxGeneral(code, _) => match code {
+            ExceptionCodeLinux::SIGSEGV | ExceptionCodeLinux::SIGBUS => {
+                Kind::Access(Access::Unknown)
+            }
+            ExceptionCodeLinux::SIGILL => {
+                Kind::Rated(Rating::Medium, "an invalid instruction was executed")
+            }
+            ExceptionCodeLinux::SIGABRT
+            | ExceptionCodeLinux::SIGTRAP
+            | ExceptionCodeLinux::SIGFPE
+            | ExceptionCodeLinux::SIGKILL
+            | ExceptionCodeLinux::SIGTERM => {
+                Kind::Rated(Rating::None, "the crash was deliberate or benign")
+            }
+            _ => Kind::Rated(Rating::Interesting, "the crash isn't a known kind"),
+        },
+        CrashReason::MacBadAccessKern(_)
+        | CrashReason::MacBadAccessArm(_)
+        | CrashReason::MacBadAccessPpc(_)
+        | CrashReason::MacBadAccessX86(_) => Kind::Access(Access::Unknown),
+        CrashReason::MacBadInstructionArm(_)
+        | CrashReason::MacBadInstructionPpc(_)
+        | CrashReason::MacBadInstructionX86(_) => {
+            Kind::Rated(Rating::Medium, "an invalid instruction was executed")
+        }
+        CrashReason::MacGeneral(ExceptionCodeMac::EXC_BAD_ACCESS, _) => {
+            Kind::Access(Access::Unknown)
+        }
+        CrashReason::MacArithmeticArm(_)
+        | CrashReason::MacArithmeticPpc(_)
+        | CrashReason::MacArithmeticX86(_)
+        | CrashReason::MacSoftware(_)
+        | CrashReason::MacBreakpointArm(_)
+        | CrashReason::MacBreakpointPpc(_)
+        | CrashReason::MacBreakpointX86(_)
+        | CrashReason::MacResource(..) => {
+            Kind::Rated(Rating::None, "the crash was deliberate or benign")
+        }
+        CrashReason::MacGuard(..) => Kind::Rated(Rating::Medium, "a guarded resource was misused"),
+        _ => Kind::Rated(Rating::Interesting, "the crash isn't a known kind"),
+    }
+}
+
+/// Whether the crash with `reason` was a bad memory access.
+pub(crate) fn is_memory_access(reason: CrashReason) -> bool {
+    matches!(classify(reason), Kind::Access(_))
+}
+
+/// Rate the crash with `reason` at `address`, in the crashing thread's
+/// `context`.
+pub(crate) fn analyze(
+    reason: CrashReason,
+    address: u64,
+    context: Option<&MinidumpContext>,
+    memory_info: Option<&UnifiedMemoryInfoList>,
+    modules: &MinidumpModuleList,
+) -> Exploitability {
+    use ExploitabilityRating as Rating;
+    let mut evidence = vec![];
+    let instruction_pointer = context.map(|context| context.get_instruction_pointer());
+
+    let kind = classify(reason);
+    match kind {
+        Kind::Rated(rating, why) => evidence.push((rating, why.to_string())),
+        Kind::Access(access) => {
+            // Crashing on the instruction pointer means we jumped somewhere bad.
+            let access = if access == Access::Unknown && instruction_pointer == Some(address) {
+                Access::Exec
+            } else {
+                access
+            };
+            let (rating, what) = match access {
+                _ if address < NULL_REGION_SIZE => (Rating::Low, "near-null pointer access"),
+                Access::Exec => (Rating::High, "execution"),
+                Access::Write => (Rating::High, "write"),
+                Access::Read => (Rating::Medium, "read"),
+                Access::Unknown => (Rating::Medium, "access"),
+            };
+            evidence.push((rating, format!("{} at {:#x}", what, address)));
+        }
+    }
+
+    if let Some(instruction_pointer) = instruction_pointer {
+        let executable = memory_info.map(|info| info.is_executable(instruction_pointer));
+        if executable == Some(false) {
+            evidence.push((
+                Rating::High,
+                format!(
+                    "instruction pointer {:#x} isn't in executable memory",
+                    instruction_pointer
+                ),
+            ));
+        } else if modules.module_at_address(instruction_pointer).is_none() {
+            // It might just be JIT code.
+            evidence.push((
+                Rating::Interesting,
+                format!(
+                    "instruction pointer {:#x} isn't in any module",
+                    instruction_pointer
+                ),
+            ));
+        }
+    }
+
+    // A bad access with the stack pointer somewhere odd suggests the stack
+    // was pivoted.
+    if let (Kind::Access(_), Some(context), Some(info)) = (kind, context, memory_info) {
+        let stack_pointer = context.get_stack_pointer();
+        if !info.is_readable(stack_pointer) {
+            evidence.push((
+                Rating::Medium,
+                format!(
+                    "stack pointer {:#x} isn't in readable memory",
+                    stack_pointer
+                ),
+            ));
+        }
+    }
+
+    evidence.sort_by(|a, b| b.0.cmp(&a.0));
+    Exploitability {
+        rating: evidence[0].0,
+        evidence,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use minidump::format::CONTEXT_X86;
+    use minidump::MinidumpRawContext;
+
+    fn context(eip: u32, esp: u32) -> MinidumpContext {
+        MinidumpContext::from_raw(MinidumpRawContext::X86(CONTEXT_X86 {
+            eip,
+            esp,
+            ..CONTEXT_X86::default()
+        }))
+    }
+
+    fn rate(
+        reason: CrashReason,
+        address: u64,
+        context: Option<&MinidumpContext>,
+    ) -> Exploitability {
+        analyze(reason, address, context, None, &MinidumpModuleList::new())
+    }
+
+    #[test]
+    fn test_access_violations() {
+        let write = CrashReason::WindowsAccessViolation(ExceptionCodeWindowsAccessType::WRITE);
+        let read = CrashReason::WindowsAccessViolation(ExceptionCodeWindowsAccessType::READ);
+        let exec = CrashReason::WindowsAccessViolation(ExceptionCodeWindowsAccessType::EXEC);
+
+        let near_null = rate(write, 0x45, None);
+        assert_eq!(near_null.rating, ExploitabilityRating::Low);
+        assert_eq!(near_null.evidence[0].1, "near-null pointer access at 0x45");
+
+        assert_eq!(
+            rate(write, 0x41414141, None).rating,
+            ExploitabilityRating::High
+        );
+        assert_eq!(
+            rate(read, 0x41414141, None).rating,
+            ExploitabilityRating::Medium
+        );
+        assert_eq!(
+            rate(exec, 0x41414141, None).rating,
+            ExploitabilityRating::High
+        );
+
+        // Without any other details, crashing at the instruction pointer is
+        // executing bad memory.
+        let segv = CrashReason::LinuxGeneral(ExceptionCodeLinux::SIGSEGV, 0);
+        let ctx = context(0x41414141, 0x1000);
+        let jumped = rate(segv, 0x41414141, Some(&ctx));
+        assert_eq!(jumped.rating, ExploitabilityRating::High);
+        assert_eq!(jumped.evidence[0].1, "execution at 0x41414141");
+        assert_eq!(
+            jumped.evidence[1].1,
+            "instruction pointer 0x41414141 isn't in any module"
+        );
+    }
+
+    #[test]
+    fn test_deliberate_crashes() {
+        let abort = CrashReason::LinuxGeneral(ExceptionCodeLinux::SIGABRT, 0);
+        assert_eq!(rate(abort, 0, None).rating, ExploitabilityRating::None);
+
+        let overrun = CrashReason::WindowsStackBufferOverrun(2);
+        assert_eq!(rate(overrun, 0, None).rating, ExploitabilityRating::High);
+    }
+}
@@ -0,0 +1,195 @@
+// Copyright 2015 Ted Mielczarek. See the COPYRIGHT
+// file at the top-level directory of this distribution.
+
+//! Summarizing the handles the process had open.
+//!
+//! Windows minidumps can list every handle the process had open. Thousands
+//! of handles of one type usually mean they were leaked, which can cause
+//! crashes far from the code that leaked them. A handle whose value is in
+//! one of the crashing thread's registers, or whose name (like the name of
+//! a file) is in the memory the crash involved, was likely being used when
+//! the process crashed.
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use minidump::{MinidumpContext, MinidumpHandleDataStream, UnifiedMemoryList};
+
+use crate::process_state::basename;
+
+/// Having more handles of one type than this likely means they were leaked.
+pub const SUSPICIOUS_HANDLE_COUNT: usize = 10_000;
+
+/// Handle values below this are too likely to be in a register by
+/// coincidence to count as a match.
+const MIN_MATCHED_HANDLE: u64 = 0x100;
+
+/// Register values below this aren't pointers worth looking for names at.
+const MIN_POINTER: u64 = 64 * 1024;
+
+/// How much memory before each address to look for names in.
+const SEARCH_BEFORE: u64 = 0x100;
+/// How much memory after each address to look for names in.
+const SEARCH_AFTER: u64 = 0x400;
+
+/// Names shorter than this are too likely to be in memory by coincidence.
+const MIN_MATCHED_NAME_LEN: usize = 4;
+
+/// Registers that don't hold handles or pointers.
+const IGNORED_REGISTERS: &[&str] = &["efl"];
+
+/// The type name handles without one are counted under.
+const UNKNOWN_TYPE: &str = "<unknown>";
+
+/// What the handles the process had open look like.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct HandleSummary {
+    /// How many handles of each type the process had open, by type name
+    /// (like "File" or "Event").
+    pub counts: BTreeMap<String, usize>,
+    /// The types the process had more than [`SUSPICIOUS_HANDLE_COUNT`]
+    /// handles of.
+    pub suspicious_types: Vec<String>,
+    /// The handles that look like they were involved in the crash.
+    pub crash_handles: Vec<CrashHandle>,
+}
+
+/// A handle that looks like it was involved in the crash.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CrashHandle {
+    /// The value of the handle.
+    pub handle: u64,
+    /// The name of the type of the object, if known.
+    pub type_name: Option<String>,
+    /// The name of the object, if it has one.
+    pub object_name: Option<String>,
+    /// Why the handle looks involved.
+    pub reason: CrashHandleReason,
+}
+
+/// Why a [`CrashHandle`] looks like it was involved in the crash.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum CrashHandleReason {
+    /// The crashing thread's register with this name held the handle.
+    Register(String),
+    /// The file name part of the object's name is in memory at this address,
+    /// near the crash address or an address in one of the crashing thread's
+    /// registers.
+    NameInMemory(u64),
+}
+
+impl CrashHandleReason {
+    /// The name of the reason in the JSON schema.
+    pub fn json_name(&self) -> &'static str {
+        match self {
+            CrashHandleReason::Register(_) => "register",
+            CrashHandleReason::NameInMemory(_) => "name_in_memory",
+        }
+    }
+}
+
+impl fmt::Display for CrashHandleReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CrashHandleReason::Register(register) => write!(f, "in {}", register),
+            CrashHandleReason::NameInMemory(address) => {
+                write!(f, "name in memory at {:#x}", address)
+            }
+        }
+    }
+}
+
+/// Summarize `handles`, looking for the ones involved in the crash at
+/// `crash_address` in the crashing thread's `context`.
+pub(crate) fn summarize(
+    handles: &MinidumpHandleDataStream,
+    crash_address: Option<u64>,
+    context: Option<&MinidumpContext>,
+    memory_list: &UnifiedMemoryList,
+) -> HandleSummary {
+    let mut counts = BTreeMap::new();
+    for handle in handles.iter() {
+        let type_name = handle.type_name.as_deref().unwrap_or(UNKNOWN_TYPE);
+        *counts.entry(String::from(type_name)).or_insert(0) += 1;
+    }
+    let suspicious_types = counts
+        .iter()
+        .filter(|&(_, &count)| count > SUSPICIOUS_HANDLE_COUNT)
+        .map(|(type_name, _)| type_name.clone())
+        .collect();
+
+    let registers = context
+        .map(|context| {
+            context
+                .general_purpose_registers()
+                .iter()
+                .filter(|&&reg| !IGNORED_REGISTERS.contains(&reg))
+                .filter_map(|&reg| Some((reg, context.get_register(reg)?)))
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+    let addresses = crash_address
+        .into_iter()
+        .chain(registers.iter().map(|&(_, value)| value))
+        .filter(|&address| address >= MIN_POINTER)
+        .collect::<Vec<_>>();
+
+    let mut crash_handles = vec![];
+    for handle in handles.iter() {
+        let reason = registers
+            .iter()
+            .find(|&&(_, value)| value >= MIN_MATCHED_HANDLE && value == handle.raw.handle)
+            .map(|&(reg, _)| CrashHandleReason::Register(String::from(reg)))
+            .or_else(|| {
+                let name = basename(handle.object_name.as_deref()?);
+                if name.chars().count() < MIN_MATCHED_NAME_LEN {
+                    return None;
+                }
+                let address = addresses
+                    .iter()
+                    .find_map(|&address| find_name(memory_list, address, name))?;
+                Some(CrashHandleReason::NameInMemory(address))
+            });
+        if let Some(reason) = reason {
+            crash_handles.push(CrashHandle {
+                handle: handle.raw.handle,
+                type_name: handle.type_name.clone(),
+                object_name: handle.object_name.clone(),
+                reason,
+            });
+        }
+    }
+
+    HandleSummary {
+        counts,
+        suspicious_types,
+        crash_handles,
+    }
+}
+
+/// Where `name` is in the memory around `address`, as UTF-16LE (like Windows
+/// APIs take) or UTF-8, if it's anywhere.
+fn find_name(memory_list: &UnifiedMemoryList, address: u64, name: &str) -> Option<u64> {
+    let memory = memory_list.memory_at_address(address)?;
+    let bytes = memory.bytes();
+    let base = memory.base_address();
+    let start = address.saturating_sub(SEARCH_BEFORE).max(base) - base;
+    let end = address.saturating_add(SEARCH_AFTER) - base;
+    let window = &bytes[start as usize..(end as usize).min(bytes.len())];
+
+    let utf16 = name
+        .encode_utf16()
+        .flat_map(|unit| unit.to_le_bytes())
+        .collect::<Vec<_>>();
+    [utf16.as_slice(), name.as_bytes()]
+        .iter()
+        .find_map(|needle| {
+            window
+                .windows(needle.len())
+                .position(|candidate| candidate == *needle)
+        })
+        .map(|offset| base + start + offset as u64)
+}
@@ -0,0 +1,372 @@
+// Copyright 2015 Ted Mielczarek. See the COPYRIGHT
+// file at the top-level directory of this distribution.
+
+//! A [JSON Schema](https://json-schema.org) for the JSON report, for
+//! validating it and generating code to read it.
+//!
+//! This describes the same fields as the schema at the top of this crate's
+//! documentation, and follows its most important rule: every field can be
+//! null or absent. So no property is required, every type includes `null`,
+//! and fields with a limited set of values list the current ones as
+//! `examples` rather than an `enum`, since more may be added.
+
+use serde_json::{json, Value};
+
+/// The version of the schema [`json_schema`] returns.
+///
+/// Like the schema at the top of this crate's documentation, this is the
+/// version of the crate, which is published in lockstep with
+/// minidump-stackwalk.
+pub const JSON_SCHEMA_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// The JSON Schema of [`ProcessState::to_json`][crate::ProcessState::to_json]
+/// (and so of minidump-stackwalk's `--json` output), including the keys only
+/// output with [`JsonOptions::extended`][crate::JsonOptions::extended].
+pub fn json_schema() -> Value {
+    json!({
+        "$schema": "https://json-schema.org/draft/2020-12/schema",
+        "title": "minidump-processor JSON report",
+        "version": JSON_SCHEMA_VERSION,
+        "type": "object",
+        "properties": {
+            "status": names(&["OK"]),
+            "system_info": object(json!({
+                "os": string(),
+                "os_ver": string(),
+                "cpu_arch": names(&[
+                    "x86", "amd64", "arm", "arm64", "ppc", "ppc64", "sparc", "mips", "mips64",
+                    "riscv64",
+                ]),
+                "cpu_info": string(),
+                "cpu_vendor": string(),
+                "cpu_family": uint(),
+                "cpu_model": uint(),
+                "cpu_stepping": uint(),
+                "cpu_model_name": string(),
+                "cpu_count": uint(),
+                "cpu_physical_core_count": uint(),
+                "cpu_package_count": uint(),
+                "cpu_microcode_version": uint(),
+                "cpu_features": array(string()),
+            })),
+            "crash_info": crash_info(),
+            "signature": string(),
+            "crash_hash": string(),
+            "environment": map(string()),
+            "lsb_release": object(json!({
+                "id": string(),
+                "release": string(),
+                "codename": string(),
+                "description": string(),
+            })),
+            "proc_status": object(json!({
+                "state": string(),
+                "threads": uint(),
+                "vm_peak": uint(),
+                "vm_size": uint(),
+                "vm_rss": uint(),
+                "seccomp": uint(),
+            })),
+            "mac_crash_info": object(json!({
+                "num_records": uint(),
+                "records": array(object(json!({
+                    "thread": hex(),
+                    "dialog_mode": hex(),
+                    "abort_cause": hex(),
+                    "module": string(),
+                    "message": string(),
+                    "signature_string": string(),
+                    "backtrace": string(),
+                    "message2": string(),
+                }))),
+            })),
+            "main_module": uint(),
+            "modules_contains_cert_info": boolean(),
+            "modules": array(object(json!({
+                "base_addr": hex(),
+                "debug_file": string(),
+                "debug_id": string(),
+                "end_addr": hex(),
+                "filename": string(),
+                "code_id": string(),
+                "version": string(),
+                "version_info": object(json!({
+                    "file_version": string(),
+                    "product_version": string(),
+                    "file_flags": hex(),
+                    "file_os": hex(),
+                    "file_type": hex(),
+                })),
+                "cert_subject": string(),
+                "missing_symbols": boolean(),
+                "loaded_symbols": boolean(),
+                "corrupt_symbols": boolean(),
+                "symbol_url": string(),
+            }))),
+            "pid": uint(),
+            "process_uptime": uint(),
+            "process_security": object(json!({
+                "integrity_level": names(&[
+                    "untrusted", "low", "medium", "medium_plus", "high", "system", "protected",
+                ]),
+                "sandboxed": boolean(),
+                "elevated": boolean(),
+                "protected_process": boolean(),
+                "dep": object(json!({
+                    "enabled": boolean(),
+                    "permanent": boolean(),
+                    "atl_thunk_emulation": boolean(),
+                })),
+            })),
+            "thread_count": uint(),
+            "threads": array(reference("thread")),
+            "crashing_thread": reference("thread"),
+            "timed_out": boolean(),
+            "annotations": map(string()),
+            "crashpad_annotations": object(json!({
+                "simple_annotations": map(string()),
+                "modules": array(object(json!({
+                    "module_index": uint(),
+                    "module": string(),
+                    "list_annotations": array(string()),
+                    "simple_annotations": map(string()),
+                    "annotation_objects": map(string()),
+                }))),
+            })),
+            "timings": object(json!({
+                "total_ms": uint(),
+                "read_streams_ms": uint(),
+                "prefetch_symbols_ms": uint(),
+                "threads_ms": array(uint()),
+                "symbols_ms": map(uint()),
+                "analysis_passes": array(object(json!({
+                    "name": string(),
+                    "ms": uint(),
+                }))),
+            })),
+            "degradations": array(names(&[
+                "missing_system_info",
+                "inferred_cpu",
+                "synthesized_thread",
+                "missing_thread_list",
+            ])),
+            "handles": object(json!({
+                "counts": map(uint()),
+                "suspicious_types": array(string()),
+                "crash_handles": array(object(json!({
+                    "handle": hex(),
+                    "type_name": string(),
+                    "object_name": string(),
+                    "reason": names(&["register", "name_in_memory"]),
+                    "register": string(),
+                    "address": hex(),
+                }))),
+            })),
+            "unloaded_modules": array(object(json!({
+                "base_addr": hex(),
+                "code_id": string(),
+                "end_addr": hex(),
+                "filename": string(),
+                "cert_subject": string(),
+            }))),
+            "suspicious_modules": array(object(json!({
+                "filename": string(),
+                "base_addr": hex(),
+                "end_addr": hex(),
+                "reason": names(&["unusual_path", "unlisted", "unsigned"]),
+            }))),
+            "sensitive": object(json!({
+                "exploitability": names(&["none", "interesting", "low", "medium", "high"]),
+                "memory": array(object(json!({
+                    "source": names(&["crash_address", "instruction_pointer", "stack_pointer"]),
+                    "address": hex(),
+                    "access": reference("memory_access"),
+                    "base_address": hex(),
+                    "bytes": string(),
+                }))),
+            })),
+        },
+        "$defs": {
+            "memory_access": object(json!({
+                "mapped": boolean(),
+                "readable": boolean(),
+                "writable": boolean(),
+                "executable": boolean(),
+            })),
+            "thread": thread(),
+            "frame": frame(),
+        },
+    })
+}
+
+/// The schema of the `crash_info`.
+fn crash_info() -> Value {
+    object(json!({
+        "type": string(),
+        "address": hex(),
+        "address_access": reference("memory_access"),
+        "address_class": object(json!({
+            "kind": names(&["null", "near_null", "non_canonical"]),
+            "register": string(),
+        })),
+        "crashing_thread": uint(),
+        "crashing_thread_guessed": boolean(),
+        "assertion": string(),
+        "assertion_info": object(json!({
+            "kind": names(&["invalid_parameter", "pure_virtual_call"]),
+            "expression": string(),
+            "function": string(),
+            "file": string(),
+            "line": uint(),
+        })),
+        "disassembly": object(json!({
+            "instructions": array(object(json!({
+                "address": hex(),
+                "bytes": string(),
+                "text": string(),
+            }))),
+            "crashing_instruction": uint(),
+            "memory_operand": object(json!({
+                "text": string(),
+                "address": hex(),
+            })),
+        })),
+        "possible_bit_flips": array(object(json!({
+            "source": names(&["crash_address", "instruction_pointer"]),
+            "address": hex(),
+            "corrected_address": hex(),
+            "bit": uint(),
+        }))),
+        "register_targets": array(object(json!({
+            "register": string(),
+            "value": hex(),
+            "target": names(&["module", "stack", "heap", "mapped", "unmapped"]),
+            "module": string(),
+            "module_offset": hex(),
+            "thread": uint(),
+        }))),
+        "stack_overflow": names(&["reported", "guard_page", "recursion"]),
+    }))
+}
+
+/// The schema of each of the `threads`, and the `crashing_thread`.
+///
+/// `threads_index` is only in the `crashing_thread`.
+fn thread() -> Value {
+    object(json!({
+        "frame_count": uint(),
+        "last_error_value": string(),
+        "last_error_description": string(),
+        "thread_name": string(),
+        "recursion_collapsed": boolean(),
+        "registers": map(hex()),
+        "priority_class": uint(),
+        "priority": uint(),
+        "kernel_time_ms": uint(),
+        "user_time_ms": uint(),
+        "start_address": hex(),
+        "frames": array(reference("frame")),
+        "threads_index": uint(),
+    }))
+}
+
+/// The schema of each of a thread's `frames`.
+///
+/// `registers` is only in the first frame of the `crashing_thread`.
+fn frame() -> Value {
+    object(json!({
+        "frame": uint(),
+        "module": string(),
+        "function": string(),
+        "file": string(),
+        "line": uint(),
+        "source_url": string(),
+        "other_lines": array(object(json!({
+            "file": string(),
+            "line": uint(),
+        }))),
+        "source_context": object(json!({
+            "start_line": uint(),
+            "lines": array(string()),
+        })),
+        "offset": hex(),
+        "resume_address": hex(),
+        "module_offset": hex(),
+        "function_offset": hex(),
+        "missing_symbols": boolean(),
+        "trust": names(&[
+            "none",
+            "scan",
+            "cfi_scan",
+            "frame_pointer",
+            "cfi",
+            "context",
+            "prewalked",
+            "inlined",
+        ]),
+        "jit": boolean(),
+        "unloaded": boolean(),
+        "repeated": object(json!({
+            "count": uint(),
+            "period": uint(),
+        })),
+        "gap": object(json!({
+            "start": hex(),
+            "end": hex(),
+        })),
+        "unwind_diagnostics": array(object(json!({
+            "strategy": names(&[
+                "cfi",
+                "eh_frame",
+                "exidx",
+                "frame_pointer",
+                "link_register",
+                "scan",
+                "custom",
+            ]),
+            "succeeded": boolean(),
+            "rejections": array(string()),
+            "rejected_reads": array(hex()),
+        }))),
+        "registers": map(hex()),
+    }))
+}
+
+fn string() -> Value {
+    json!({ "type": ["string", "null"] })
+}
+
+/// A `<hexstring>`, like "0x000af123".
+fn hex() -> Value {
+    json!({ "type": ["string", "null"], "pattern": "^0x[0-9a-f]+$" })
+}
+
+fn uint() -> Value {
+    json!({ "type": ["integer", "null"], "minimum": 0 })
+}
+
+fn boolean() -> Value {
+    json!({ "type": ["boolean", "null"] })
+}
+
+/// A string that's currently one of `names`.
+fn names(names: &[&str]) -> Value {
+    json!({ "type": ["string", "null"], "examples": names })
+}
+
+fn array(items: Value) -> Value {
+    json!({ "type": ["array", "null"], "items": items })
+}
+
+fn object(properties: Value) -> Value {
+    json!({ "type": ["object", "null"], "properties": properties })
+}
+
+/// An object with arbitrary keys, and `values` as the values.
+fn map(values: Value) -> Value {
+    json!({ "type": ["object", "null"], "additionalProperties": values })
+}
+
+fn reference(name: &str) -> Value {
+    json!({ "$ref": format!("#/$defs/{}", name) })
+}
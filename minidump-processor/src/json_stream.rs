@@ -0,0 +1,149 @@
+// Copyright 2015 Ted Mielczarek. See the COPYRIGHT
+// file at the top-level directory of this distribution.
+
+//! Writing the JSON report a thread at a time.
+
+use std::io::Write;
+use std::sync::Mutex;
+
+use crate::process_state::{json_hex, CallStack, JsonOptions, ProcessState};
+use crate::stackwalker::ThreadObserver;
+use crate::system_info::SystemInfo;
+
+/// Writes the same JSON as [`ProcessState::print_json`], but writes each
+/// thread as soon as its stack has been walked.
+///
+/// Set it as the [`ProcessorOptions::thread_observer`], and call
+/// [`finish`][JsonStreamWriter::finish] with the processed [`ProcessState`]
+/// to write the rest. Together with [`ProcessorOptions::discard_frames`],
+/// only the frames of the threads being walked are in memory at once,
+/// rather than those of every thread.
+///
+/// The threads are written first, since the rest of the report (like which
+/// modules had symbols) isn't known until they've all been walked. Output
+/// is never pretty-printed.
+///
+/// ```
+/// use minidump::Minidump;
+/// use minidump_processor::{JsonStreamWriter, ProcessorOptions};
+/// use std::sync::Arc;
+///
+/// #[tokio::main]
+/// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+///     # std::env::set_current_dir(env!("CARGO_MANIFEST_DIR"));
+///     let dump = Minidump::read_path("../testdata/test.dmp")?;
+///     let writer = Arc::new(JsonStreamWriter::new(std::io::stdout()));
+///     let mut options = ProcessorOptions::default();
+///     options.thread_observer = Some(writer.clone());
+///     options.discard_frames = true;
+///     let provider = breakpad_symbols::Symbolizer::new(
+///         breakpad_symbols::SimpleSymbolSupplier::new(vec!["../testdata/symbols".into()]),
+///     );
+///     let state =
+///         minidump_processor::process_minidump_with_options(&dump, &provider, options).await?;
+///     writer.finish(&state)?;
+///     Ok(())
+/// }
+/// ```
+///
+/// [`ProcessorOptions::thread_observer`]: crate::ProcessorOptions::thread_observer
+/// [`ProcessorOptions::discard_frames`]: crate::ProcessorOptions::discard_frames
+pub struct JsonStreamWriter<W> {
+    inner: Mutex<Inner<W>>,
+}
+
+struct Inner<W> {
+    writer: W,
+    /// Which keys to write.
+    options: JsonOptions,
+    /// How many threads have been written.
+    threads: usize,
+    /// The first error writing a thread, which [`JsonStreamWriter::finish`]
+    /// returns.
+    error: Option<serde_json::Error>,
+}
+
+impl<W: Write> JsonStreamWriter<W> {
+    /// Write the report to `writer`.
+    pub fn new(writer: W) -> JsonStreamWriter<W> {
+        Self::with_options(writer, JsonOptions::default())
+    }
+
+    /// Write the report to `writer`, with the keys `options` asks for (see
+    /// [`ProcessState::print_json_with_options`]).
+    pub fn with_options(writer: W, options: JsonOptions) -> JsonStreamWriter<W> {
+        JsonStreamWriter {
+            inner: Mutex::new(Inner {
+                writer,
+                options,
+                threads: 0,
+                error: None,
+            }),
+        }
+    }
+
+    /// The writer the report was written to.
+    pub fn into_inner(self) -> W {
+        self.inner.into_inner().unwrap().writer
+    }
+
+    /// Write everything but the threads, which have to have been written
+    /// already, from the `state` they were walked for.
+    ///
+    /// Returns the first error writing any of the report.
+    pub fn finish(&self, state: &ProcessState) -> Result<(), serde_json::Error> {
+        let mut inner = self.inner.lock().unwrap();
+        let inner = &mut *inner;
+        if let Some(error) = inner.error.take() {
+            return Err(error);
+        }
+        let writer = &mut inner.writer;
+        let threads_end: &[u8] = if inner.threads == 0 {
+            b"{\"threads\":[]"
+        } else {
+            b"]"
+        };
+        writer
+            .write_all(threads_end)
+            .map_err(serde_json::Error::io)?;
+
+        // The crashing thread is copied out of the threads, so the requesting
+        // thread's frames are always kept.
+        let mut output = state.to_json_with_options(&inner.options);
+        let output = output.as_object_mut().unwrap();
+        output.remove("threads");
+        for (key, value) in output.iter() {
+            writer.write_all(b",").map_err(serde_json::Error::io)?;
+            serde_json::to_writer(&mut *writer, key)?;
+            writer.write_all(b":").map_err(serde_json::Error::io)?;
+            serde_json::to_writer(&mut *writer, value)?;
+        }
+        writer.write_all(b"}").map_err(serde_json::Error::io)?;
+        writer.flush().map_err(serde_json::Error::io)
+    }
+}
+
+impl<W: Write> Inner<W> {
+    fn write_thread(&mut self, system_info: &SystemInfo, stack: &CallStack) {
+        let cpu = system_info.cpu;
+        let json = stack.to_json(|val| json_hex(cpu, val), &self.options);
+        let writer = &mut self.writer;
+        let result = if self.threads == 0 {
+            writer.write_all(b"{\"threads\":[")
+        } else {
+            writer.write_all(b",")
+        }
+        .map_err(serde_json::Error::io)
+        .and_then(|()| serde_json::to_writer(writer, &json));
+        self.threads += 1;
+        if let Err(error) = result {
+            self.error.get_or_insert(error);
+        }
+    }
+}
+
+impl<W: Write + Send> ThreadObserver for JsonStreamWriter<W> {
+    fn on_thread(&self, system_info: &SystemInfo, _index: usize, stack: &CallStack) {
+        self.inner.lock().unwrap().write_thread(system_info, stack);
+    }
+}
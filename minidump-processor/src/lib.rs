@@ -5,6 +5,13 @@
 //! You can use the [minidump](https://crates.io/minidump) crate to parse a minidump file, and then
 //! use the [`process_minidump`] function to produce stack traces. If you provide paths to
 //! Breakpad-format .sym files, the stack traces will include function and source line information.
+//! [`process_path`] and [`process_bytes`] do all of that in one call, with the symbols to use
+//! described by a [`SymbolConfig`].
+//!
+//! This also builds for wasm32-unknown-unknown, to process minidumps in a browser. There's
+//! no filesystem there, so read minidumps with [`process_bytes`], and get symbols with
+//! `fetch_symbol_supplier` (enabled by the `fetch` feature), which downloads them with the
+//! browser's `fetch`.
 //!  
 //! For a CLI application that wraps this library, see [minidump-stackwalk][].
 //! **This is the primary and stable interface for minidump-processor, which
@@ -14,7 +21,7 @@
 //! the stabilized JSON output. The native APIs work fine and contain all the same
 //! information, we just haven't stabilized them yet, so updates are more likely
 //! to result in breakage. Here is a minimal example which gets the JSON output
-//! (and parses it with serde_json):
+//! (as a serde_json `Value`):
 //!  
 //! ```rust
 //! use minidump::Minidump;
@@ -50,12 +57,8 @@
 //!         .await
 //!         .map_err(|_| ())?;
 //!  
-//!     // Write the JSON output to an arbitrary writer (here, a Vec).
-//!     let mut json_output = Vec::new();
-//!     state.print_json(&mut json_output, false).map_err(|_| ())?;
-//!  
-//!     // Now parse it (here parsed into an arbitrary JSON Object for max flexibility).
-//!     let json: Value = serde_json::from_slice(&json_output).map_err(|_| ())?;
+//!     // Get the JSON output (`print_json` writes the same thing to a writer).
+//!     let json: Value = state.to_json();
 //!  
 //!     // Now read whatever values you want out of it
 //!     if let Some(Value::Number(pid)) = json.get("pid") {
@@ -76,15 +79,71 @@
 #[cfg(doctest)]
 doc_comment::doctest!("../README.md");
 
+mod analysis;
+mod assertion;
+mod authenticode;
+mod auxiliary;
+mod batch;
+mod bit_flips;
+mod crash_address;
+mod crash_memory;
+mod crashpad;
+mod disassembly;
 mod evil;
+mod exploitability;
+mod handles;
+mod json_schema;
+mod json_stream;
+mod plugin;
+mod process_diff;
+mod process_security;
 mod process_state;
 mod processor;
+#[cfg(feature = "protobuf")]
+pub mod protobuf;
+mod register_targets;
+#[cfg(feature = "serde")]
+mod serialize;
+pub mod signature;
+mod socorro;
+mod source_context;
+mod stack_overflow;
 mod stackwalker;
+mod suspicious_modules;
 pub mod symbols;
+#[cfg(feature = "symcache")]
+mod symcache;
 mod system_info;
+mod thread_guess;
+mod timings;
 
+pub use crate::analysis::*;
+pub use crate::assertion::*;
+pub use crate::auxiliary::*;
+pub use crate::batch::*;
+pub use crate::bit_flips::*;
+pub use crate::crash_address::*;
+pub use crate::crash_memory::*;
+pub use crate::crashpad::*;
+pub use crate::disassembly::*;
+pub use crate::evil::*;
+pub use crate::exploitability::*;
+pub use crate::handles::*;
+pub use crate::json_schema::*;
+pub use crate::json_stream::*;
+pub use crate::plugin::*;
+pub use crate::process_diff::*;
+pub use crate::process_security::*;
 pub use crate::process_state::*;
 pub use crate::processor::*;
+pub use crate::register_targets::*;
+pub use crate::signature::*;
+pub use crate::source_context::*;
+pub use crate::stack_overflow::*;
 pub use crate::stackwalker::*;
+pub use crate::suspicious_modules::*;
 pub use crate::symbols::*;
+#[cfg(feature = "symcache")]
+pub use crate::symcache::*;
 pub use crate::system_info::*;
+pub use crate::timings::*;
@@ -0,0 +1,1022 @@
+// Copyright 2015 Ted Mielczarek. See the COPYRIGHT
+// file at the top-level directory of this distribution.
+
+//! Symbol providers that are plugged in from outside of this crate.
+//!
+//! [`SymbolProvider`] is async, works on the processor's own frame and
+//! walker types, and changes whenever the processor needs something new from
+//! symbols, which makes it a poor fit for symbol sources that are built
+//! separately (like a proprietary symbol store's client). [`SymbolPlugin`]
+//! is the same job cut down to plain data: the plugin is told about the
+//! module and the instruction, and returns what it found. It's object safe,
+//! versioned with [`SYMBOL_PLUGIN_VERSION`], and only ever extended with
+//! defaulted methods.
+//!
+//! Wrap a plugin in a [`PluginSymbolProvider`] to hand it to the processor,
+//! on its own or in a [`MultiSymbolProvider`][crate::MultiSymbolProvider]
+//! alongside the usual providers.
+//!
+//! With the `plugins` feature, plugins can also be loaded from dynamic
+//! libraries that export one with [`export_symbol_plugin!`], using
+//! [`load_symbol_plugin`]. Those go through a C ABI ([`SymbolPluginVTable`]),
+//! so the library can be built with any version of Rust, or in another
+//! language.
+
+use std::collections::HashMap;
+use std::ffi::c_void;
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+use std::slice;
+use std::sync::Mutex;
+
+use async_trait::async_trait;
+use minidump::Module;
+
+use crate::process_state::basename;
+use crate::symbols::{FillSymbolError, FrameSymbolizer, FrameWalker, SymbolProvider, SymbolStats};
+
+/// The version of the [`SymbolPlugin`] interface.
+///
+/// This goes up when the interface changes in a way existing plugins need to
+/// be rebuilt for. Plugins report the version they were built with, and
+/// [`PluginSymbolProvider::new`] refuses the ones that don't match.
+pub const SYMBOL_PLUGIN_VERSION: u32 = 1;
+
+/// A module, as a [`SymbolPlugin`] is told about it.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PluginModule {
+    pub base_address: u64,
+    pub size: u64,
+    pub code_file: String,
+    pub code_identifier: String,
+    pub debug_file: Option<String>,
+    pub debug_identifier: Option<String>,
+    pub version: Option<String>,
+}
+
+impl PluginModule {
+    pub fn from_module(module: &dyn Module) -> PluginModule {
+        PluginModule {
+            base_address: module.base_address(),
+            size: module.size(),
+            code_file: module.code_file().into_owned(),
+            code_identifier: module.code_identifier().into_owned(),
+            debug_file: module.debug_file().map(|file| file.into_owned()),
+            debug_identifier: module.debug_identifier().map(|id| id.into_owned()),
+            version: module.version().map(|version| version.into_owned()),
+        }
+    }
+}
+
+/// What a [`SymbolPlugin`] knows about an instruction.
+///
+/// The default knows nothing, for instructions the module's symbols don't
+/// cover.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PluginSymbol {
+    /// The function the instruction is in.
+    pub function: Option<PluginFunction>,
+    /// The source line the instruction is on.
+    pub source_line: Option<PluginSourceLine>,
+    /// The calls inlined at the instruction, from the outermost to the
+    /// innermost.
+    pub inlines: Vec<PluginInline>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PluginFunction {
+    pub name: String,
+    /// The address the function starts at.
+    pub base: u64,
+    /// How many bytes the function's parameters take up on the stack.
+    pub parameter_size: u32,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PluginSourceLine {
+    pub file: String,
+    /// The (1-based) line number.
+    pub line: u32,
+    /// The address the line's instructions start at.
+    pub base: u64,
+    /// Where the source file can be found, if it's known.
+    pub url: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PluginInline {
+    /// The name of the inlined function.
+    pub name: String,
+    /// Where in the inlined function the instruction is.
+    pub file: Option<String>,
+    pub line: Option<u32>,
+}
+
+/// The frame a [`SymbolPlugin`] is asked to unwind.
+pub struct PluginFrame<'a> {
+    /// The instruction the frame is at.
+    pub instruction: u64,
+    /// How many bytes the callee's callee's parameters take up on the stack
+    /// (or 0 if that's not known), for STACK WIN-style unwinding.
+    pub grand_callee_parameter_size: u32,
+    /// Get the value of one of the frame's registers.
+    pub register: &'a dyn Fn(&str) -> Option<u64>,
+    /// Read a register-sized value from the stack.
+    pub read_memory: &'a dyn Fn(u64) -> Option<u64>,
+}
+
+/// The caller's frame, as a [`SymbolPlugin`] unwound it.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PluginCallerFrame {
+    /// The caller's registers that could be recovered (name, value).
+    pub registers: Vec<(String, u64)>,
+    /// The caller's registers that are known to be garbage.
+    pub cleared_registers: Vec<String>,
+    /// The canonical frame address, which sets the caller's stack pointer.
+    pub cfa: Option<u64>,
+    /// The return address, which sets the caller's instruction pointer.
+    pub return_address: Option<u64>,
+}
+
+/// A source of symbols that can be plugged into the processor.
+///
+/// This is a cut down, synchronous [`SymbolProvider`] that only deals in
+/// plain data, so it can be implemented outside of this crate (or in another
+/// library, see [`export_symbol_plugin!`]) and keep working as the processor
+/// changes. Use one with a [`PluginSymbolProvider`].
+///
+/// Plugins are called from whichever thread is walking a stack, and
+/// shouldn't block for long.
+pub trait SymbolPlugin: Send + Sync {
+    /// The version of this interface the plugin was built against, which
+    /// is [`SYMBOL_PLUGIN_VERSION`] as it was then.
+    fn version(&self) -> u32;
+
+    /// Look up `instruction` in the symbols for `module`.
+    ///
+    /// Returns `None` if the plugin doesn't have symbols for the module at
+    /// all, so other providers get a chance to, and a default
+    /// [`PluginSymbol`] if it does but they don't cover the instruction.
+    fn fill_symbol(&self, module: &PluginModule, instruction: u64) -> Option<PluginSymbol>;
+
+    /// Unwind `frame`, which is in `module`, to its caller.
+    ///
+    /// The default can't, leaving the processor to fall back on frame
+    /// pointers and scanning.
+    fn walk_frame(
+        &self,
+        _module: &PluginModule,
+        _frame: &PluginFrame,
+    ) -> Option<PluginCallerFrame> {
+        None
+    }
+}
+
+/// An error setting up a [`SymbolPlugin`].
+#[derive(Debug, thiserror::Error)]
+pub enum PluginError {
+    #[error("The plugin was built for version {found} of the plugin interface, not {expected}")]
+    WrongVersion { found: u32, expected: u32 },
+    #[cfg(feature = "plugins")]
+    #[error("Failed to load the plugin library")]
+    LoadError(#[from] libloading::Error),
+}
+
+/// A [`SymbolProvider`] that gets its symbols from a [`SymbolPlugin`].
+pub struct PluginSymbolProvider {
+    plugin: Box<dyn SymbolPlugin>,
+    stats: Mutex<HashMap<String, SymbolStats>>,
+}
+
+impl PluginSymbolProvider {
+    /// Use `plugin`, if it was built for this version of the interface.
+    pub fn new(plugin: Box<dyn SymbolPlugin>) -> Result<PluginSymbolProvider, PluginError> {
+        let found = plugin.version();
+        if found != SYMBOL_PLUGIN_VERSION {
+            return Err(PluginError::WrongVersion {
+                found,
+                expected: SYMBOL_PLUGIN_VERSION,
+            });
+        }
+        Ok(PluginSymbolProvider {
+            plugin,
+            stats: Mutex::new(HashMap::new()),
+        })
+    }
+
+    fn record_stats(&self, module: &PluginModule, loaded_symbols: bool) {
+        let mut stats = self.stats.lock().unwrap();
+        let stats = stats
+            .entry(String::from(basename(&module.code_file)))
+            .or_default();
+        stats.loaded_symbols |= loaded_symbols;
+    }
+}
+
+#[async_trait]
+impl SymbolProvider for PluginSymbolProvider {
+    async fn fill_symbol(
+        &self,
+        module: &(dyn Module + Sync),
+        frame: &mut (dyn FrameSymbolizer + Send),
+    ) -> Result<(), FillSymbolError> {
+        let module = PluginModule::from_module(module);
+        let symbol = self.plugin.fill_symbol(&module, frame.get_instruction());
+        self.record_stats(&module, symbol.is_some());
+        let symbol = symbol.ok_or(FillSymbolError {})?;
+
+        if let Some(function) = symbol.function {
+            frame.set_function(&function.name, function.base, function.parameter_size);
+        }
+        if let Some(source_line) = symbol.source_line {
+            frame.set_source_file(&source_line.file, source_line.line, source_line.base);
+            if let Some(url) = source_line.url {
+                frame.set_source_url(&url);
+            }
+        }
+        for inline in symbol.inlines {
+            frame.add_inline_frame(&inline.name, inline.file.as_deref(), inline.line);
+        }
+        Ok(())
+    }
+
+    async fn walk_frame(
+        &self,
+        module: &(dyn Module + Sync),
+        walker: &mut (dyn FrameWalker + Send),
+    ) -> Option<()> {
+        let module = PluginModule::from_module(module);
+        let caller = {
+            let walker = &*walker;
+            let register = |name: &str| walker.get_callee_register(name);
+            let read_memory = |address: u64| walker.get_register_at_address(address);
+            let frame = PluginFrame {
+                instruction: walker.get_instruction(),
+                grand_callee_parameter_size: walker.get_grand_callee_parameter_size(),
+                register: &register,
+                read_memory: &read_memory,
+            };
+            self.plugin.walk_frame(&module, &frame)?
+        };
+
+        for (name, value) in caller.registers.iter() {
+            walker.set_caller_register(name, *value)?;
+        }
+        for name in caller.cleared_registers.iter() {
+            walker.clear_caller_register(name);
+        }
+        if let Some(cfa) = caller.cfa {
+            walker.set_cfa(cfa)?;
+        }
+        if let Some(return_address) = caller.return_address {
+            walker.set_ra(return_address)?;
+        }
+        Some(())
+    }
+
+    fn stats(&self) -> HashMap<String, SymbolStats> {
+        self.stats.lock().unwrap().clone()
+    }
+}
+
+/// A string passed between a plugin and the processor: `len` bytes of UTF-8
+/// at `ptr`, which isn't nul-terminated. A null `ptr` is no string at all.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct PluginStr {
+    pub ptr: *const u8,
+    pub len: usize,
+}
+
+impl PluginStr {
+    const NONE: PluginStr = PluginStr {
+        ptr: ptr::null(),
+        len: 0,
+    };
+
+    fn new(string: &str) -> PluginStr {
+        PluginStr {
+            ptr: string.as_ptr(),
+            len: string.len(),
+        }
+    }
+
+    fn from_option(string: Option<&str>) -> PluginStr {
+        string.map_or(PluginStr::NONE, PluginStr::new)
+    }
+
+    /// The string, if there is one and it's UTF-8.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must be null or point to `len` bytes that live for `'a`.
+    unsafe fn as_str<'a>(&self) -> Option<&'a str> {
+        if self.ptr.is_null() {
+            return None;
+        }
+        std::str::from_utf8(slice::from_raw_parts(self.ptr, self.len)).ok()
+    }
+
+    /// # Safety
+    ///
+    /// As for [`as_str`][Self::as_str].
+    unsafe fn to_option(self) -> Option<String> {
+        self.as_str().map(String::from)
+    }
+}
+
+/// A [`PluginModule`], across the plugin boundary.
+#[repr(C)]
+#[derive(Debug)]
+pub struct RawPluginModule {
+    pub base_address: u64,
+    pub size: u64,
+    pub code_file: PluginStr,
+    pub code_identifier: PluginStr,
+    pub debug_file: PluginStr,
+    pub debug_identifier: PluginStr,
+    pub version: PluginStr,
+}
+
+impl RawPluginModule {
+    fn new(module: &PluginModule) -> RawPluginModule {
+        RawPluginModule {
+            base_address: module.base_address,
+            size: module.size,
+            code_file: PluginStr::new(&module.code_file),
+            code_identifier: PluginStr::new(&module.code_identifier),
+            debug_file: PluginStr::from_option(module.debug_file.as_deref()),
+            debug_identifier: PluginStr::from_option(module.debug_identifier.as_deref()),
+            version: PluginStr::from_option(module.version.as_deref()),
+        }
+    }
+
+    unsafe fn to_module(&self) -> PluginModule {
+        PluginModule {
+            base_address: self.base_address,
+            size: self.size,
+            code_file: self.code_file.to_option().unwrap_or_default(),
+            code_identifier: self.code_identifier.to_option().unwrap_or_default(),
+            debug_file: self.debug_file.to_option(),
+            debug_identifier: self.debug_identifier.to_option(),
+            version: self.version.to_option(),
+        }
+    }
+}
+
+/// A [`PluginSymbol`], across the plugin boundary. A null `function_name`
+/// or `source_file` means there's no function or source line.
+#[repr(C)]
+#[derive(Debug)]
+pub struct RawPluginSymbol {
+    pub function_name: PluginStr,
+    pub function_base: u64,
+    pub parameter_size: u32,
+    pub source_file: PluginStr,
+    pub source_line: u32,
+    pub source_line_base: u64,
+    pub source_url: PluginStr,
+    pub inlines: *const RawPluginInline,
+    pub inline_count: usize,
+}
+
+/// A [`PluginInline`], across the plugin boundary. A `line` of 0 is unknown.
+#[repr(C)]
+#[derive(Debug)]
+pub struct RawPluginInline {
+    pub name: PluginStr,
+    pub file: PluginStr,
+    pub line: u32,
+}
+
+impl RawPluginSymbol {
+    unsafe fn to_symbol(&self) -> PluginSymbol {
+        let function = self.function_name.to_option().map(|name| PluginFunction {
+            name,
+            base: self.function_base,
+            parameter_size: self.parameter_size,
+        });
+        let source_line = self.source_file.to_option().map(|file| PluginSourceLine {
+            file,
+            line: self.source_line,
+            base: self.source_line_base,
+            url: self.source_url.to_option(),
+        });
+        let inlines = raw_slice(self.inlines, self.inline_count)
+            .iter()
+            .map(|inline| PluginInline {
+                name: inline.name.to_option().unwrap_or_default(),
+                file: inline.file.to_option(),
+                line: Some(inline.line).filter(|&line| line != 0),
+            })
+            .collect();
+        PluginSymbol {
+            function,
+            source_line,
+            inlines,
+        }
+    }
+}
+
+/// A [`PluginFrame`], across the plugin boundary. The functions are called
+/// with `context`, and return whether they found a value (which they write
+/// to `value`).
+#[repr(C)]
+pub struct RawPluginFrame {
+    pub instruction: u64,
+    pub grand_callee_parameter_size: u32,
+    pub context: *const c_void,
+    pub register: unsafe extern "C" fn(*const c_void, PluginStr, *mut u64) -> bool,
+    pub read_memory: unsafe extern "C" fn(*const c_void, u64, *mut u64) -> bool,
+}
+
+/// A register of a [`RawPluginCallerFrame`].
+#[repr(C)]
+#[derive(Debug)]
+pub struct RawPluginRegister {
+    pub name: PluginStr,
+    pub value: u64,
+}
+
+/// A [`PluginCallerFrame`], across the plugin boundary.
+#[repr(C)]
+#[derive(Debug)]
+pub struct RawPluginCallerFrame {
+    pub registers: *const RawPluginRegister,
+    pub register_count: usize,
+    pub cleared_registers: *const PluginStr,
+    pub cleared_register_count: usize,
+    pub has_cfa: bool,
+    pub cfa: u64,
+    pub has_return_address: bool,
+    pub return_address: u64,
+}
+
+impl RawPluginCallerFrame {
+    unsafe fn to_caller_frame(&self) -> PluginCallerFrame {
+        PluginCallerFrame {
+            registers: raw_slice(self.registers, self.register_count)
+                .iter()
+                .filter_map(|register| Some((register.name.to_option()?, register.value)))
+                .collect(),
+            cleared_registers: raw_slice(self.cleared_registers, self.cleared_register_count)
+                .iter()
+                .filter_map(|name| name.to_option())
+                .collect(),
+            cfa: Some(self.cfa).filter(|_| self.has_cfa),
+            return_address: Some(self.return_address).filter(|_| self.has_return_address),
+        }
+    }
+}
+
+/// `len` values at `ptr`, which may be null if `len` is 0.
+unsafe fn raw_slice<'a, T>(ptr: *const T, len: usize) -> &'a [T] {
+    if ptr.is_null() || len == 0 {
+        &[]
+    } else {
+        slice::from_raw_parts(ptr, len)
+    }
+}
+
+/// A [`SymbolPlugin`] as a dynamic library exports it: plain C functions and
+/// data, so the library doesn't need to be built with the same version of
+/// Rust (or in Rust at all).
+///
+/// Everything the plugin returns is allocated by the plugin, and handed back
+/// to it to free once the processor has copied it: `fill_symbol`'s result to
+/// `free_symbol`, and `walk_frame`'s to `free_caller_frame`. Null results are
+/// `None`. `drop` is called with `plugin` once it won't be used again.
+///
+/// [`SymbolPluginVTable::new`] makes one out of any [`SymbolPlugin`], which
+/// is what [`export_symbol_plugin!`] does.
+#[repr(C)]
+#[derive(Debug)]
+pub struct SymbolPluginVTable {
+    /// The version of the plugin interface the plugin was built against.
+    pub version: u32,
+    /// The plugin's own state, passed to all of the functions.
+    pub plugin: *mut c_void,
+    pub fill_symbol:
+        unsafe extern "C" fn(*const c_void, *const RawPluginModule, u64) -> *mut RawPluginSymbol,
+    pub free_symbol: unsafe extern "C" fn(*const c_void, *mut RawPluginSymbol),
+    pub walk_frame: unsafe extern "C" fn(
+        *const c_void,
+        *const RawPluginModule,
+        *const RawPluginFrame,
+    ) -> *mut RawPluginCallerFrame,
+    pub free_caller_frame: unsafe extern "C" fn(*const c_void, *mut RawPluginCallerFrame),
+    pub drop: unsafe extern "C" fn(*mut c_void),
+}
+
+impl SymbolPluginVTable {
+    /// Export `plugin` through a vtable.
+    pub fn new(plugin: Box<dyn SymbolPlugin>) -> SymbolPluginVTable {
+        SymbolPluginVTable {
+            version: plugin.version(),
+            plugin: Box::into_raw(Box::new(plugin)) as *mut c_void,
+            fill_symbol: exported_fill_symbol,
+            free_symbol: exported_free_symbol,
+            walk_frame: exported_walk_frame,
+            free_caller_frame: exported_free_caller_frame,
+            drop: exported_drop,
+        }
+    }
+}
+
+// The plugin side of `SymbolPluginVTable::new`. These are compiled into the
+// plugin's library, so everything they allocate is the plugin's.
+
+/// A `RawPluginSymbol` and everything it points into.
+#[repr(C)]
+struct ExportedSymbol {
+    raw: RawPluginSymbol,
+    _inlines: Vec<RawPluginInline>,
+    _symbol: PluginSymbol,
+}
+
+/// A `RawPluginCallerFrame` and everything it points into.
+#[repr(C)]
+struct ExportedCallerFrame {
+    raw: RawPluginCallerFrame,
+    _registers: Vec<RawPluginRegister>,
+    _cleared_registers: Vec<PluginStr>,
+    _caller: PluginCallerFrame,
+}
+
+unsafe fn exported_plugin<'a>(plugin: *const c_void) -> &'a dyn SymbolPlugin {
+    &**(plugin as *const Box<dyn SymbolPlugin>)
+}
+
+/// Run `f` without unwinding into the processor, turning panics into null.
+fn null_on_panic<T>(f: impl FnOnce() -> *mut T) -> *mut T {
+    panic::catch_unwind(AssertUnwindSafe(f)).unwrap_or(ptr::null_mut())
+}
+
+unsafe extern "C" fn exported_fill_symbol(
+    plugin: *const c_void,
+    module: *const RawPluginModule,
+    instruction: u64,
+) -> *mut RawPluginSymbol {
+    null_on_panic(|| {
+        let module = (*module).to_module();
+        let symbol = match exported_plugin(plugin).fill_symbol(&module, instruction) {
+            Some(symbol) => symbol,
+            None => return ptr::null_mut(),
+        };
+        let inlines = symbol
+            .inlines
+            .iter()
+            .map(|inline| RawPluginInline {
+                name: PluginStr::new(&inline.name),
+                file: PluginStr::from_option(inline.file.as_deref()),
+                line: inline.line.unwrap_or(0),
+            })
+            .collect::<Vec<_>>();
+        let function = symbol.function.as_ref();
+        let source_line = symbol.source_line.as_ref();
+        let raw = RawPluginSymbol {
+            function_name: PluginStr::from_option(function.map(|function| &*function.name)),
+            function_base: function.map_or(0, |function| function.base),
+            parameter_size: function.map_or(0, |function| function.parameter_size),
+            source_file: PluginStr::from_option(source_line.map(|line| &*line.file)),
+            source_line: source_line.map_or(0, |line| line.line),
+            source_line_base: source_line.map_or(0, |line| line.base),
+            source_url: PluginStr::from_option(source_line.and_then(|line| line.url.as_deref())),
+            inlines: inlines.as_ptr(),
+            inline_count: inlines.len(),
+        };
+        // Moving the strings and the Vec doesn't move what they point to.
+        let exported = Box::new(ExportedSymbol {
+            raw,
+            _inlines: inlines,
+            _symbol: symbol,
+        });
+        Box::into_raw(exported) as *mut RawPluginSymbol
+    })
+}
+
+unsafe extern "C" fn exported_free_symbol(_plugin: *const c_void, symbol: *mut RawPluginSymbol) {
+    if !symbol.is_null() {
+        drop(Box::from_raw(symbol as *mut ExportedSymbol));
+    }
+}
+
+unsafe extern "C" fn exported_walk_frame(
+    plugin: *const c_void,
+    module: *const RawPluginModule,
+    frame: *const RawPluginFrame,
+) -> *mut RawPluginCallerFrame {
+    null_on_panic(|| {
+        let module = (*module).to_module();
+        let frame = &*frame;
+        let register = |name: &str| {
+            let mut value = 0;
+            if (frame.register)(frame.context, PluginStr::new(name), &mut value) {
+                Some(value)
+            } else {
+                None
+            }
+        };
+        let read_memory = |address: u64| {
+            let mut value = 0;
+            if (frame.read_memory)(frame.context, address, &mut value) {
+                Some(value)
+            } else {
+                None
+            }
+        };
+        let plugin_frame = PluginFrame {
+            instruction: frame.instruction,
+            grand_callee_parameter_size: frame.grand_callee_parameter_size,
+            register: &register,
+            read_memory: &read_memory,
+        };
+        let caller = match exported_plugin(plugin).walk_frame(&module, &plugin_frame) {
+            Some(caller) => caller,
+            None => return ptr::null_mut(),
+        };
+        let registers = caller
+            .registers
+            .iter()
+            .map(|(name, value)| RawPluginRegister {
+                name: PluginStr::new(name),
+                value: *value,
+            })
+            .collect::<Vec<_>>();
+        let cleared_registers = caller
+            .cleared_registers
+            .iter()
+            .map(|name| PluginStr::new(name))
+            .collect::<Vec<_>>();
+        let raw = RawPluginCallerFrame {
+            registers: registers.as_ptr(),
+            register_count: registers.len(),
+            cleared_registers: cleared_registers.as_ptr(),
+            cleared_register_count: cleared_registers.len(),
+            has_cfa: caller.cfa.is_some(),
+            cfa: caller.cfa.unwrap_or(0),
+            has_return_address: caller.return_address.is_some(),
+            return_address: caller.return_address.unwrap_or(0),
+        };
+        let exported = Box::new(ExportedCallerFrame {
+            raw,
+            _registers: registers,
+            _cleared_registers: cleared_registers,
+            _caller: caller,
+        });
+        Box::into_raw(exported) as *mut RawPluginCallerFrame
+    })
+}
+
+unsafe extern "C" fn exported_free_caller_frame(
+    _plugin: *const c_void,
+    caller: *mut RawPluginCallerFrame,
+) {
+    if !caller.is_null() {
+        drop(Box::from_raw(caller as *mut ExportedCallerFrame));
+    }
+}
+
+unsafe extern "C" fn exported_drop(plugin: *mut c_void) {
+    // Not much can be done about a plugin that panics while it's dropped.
+    let _ = panic::catch_unwind(AssertUnwindSafe(|| {
+        drop(Box::from_raw(plugin as *mut Box<dyn SymbolPlugin>));
+    }));
+}
+
+/// A [`SymbolPlugin`] that calls through a [`SymbolPluginVTable`].
+struct VTablePlugin {
+    vtable: SymbolPluginVTable,
+}
+
+// Plugins must be `Send + Sync`, whatever language they're written in.
+unsafe impl Send for VTablePlugin {}
+unsafe impl Sync for VTablePlugin {}
+
+impl Drop for VTablePlugin {
+    fn drop(&mut self) {
+        unsafe { (self.vtable.drop)(self.vtable.plugin) }
+    }
+}
+
+// The processor side of a `SymbolPluginVTable`.
+
+unsafe extern "C" fn frame_register(
+    frame: *const c_void,
+    name: PluginStr,
+    value: *mut u64,
+) -> bool {
+    let frame = &*(frame as *const PluginFrame);
+    let found = panic::catch_unwind(AssertUnwindSafe(|| (frame.register)(name.as_str()?)));
+    match found {
+        Ok(Some(found)) => {
+            *value = found;
+            true
+        }
+        _ => false,
+    }
+}
+
+unsafe extern "C" fn frame_read_memory(
+    frame: *const c_void,
+    address: u64,
+    value: *mut u64,
+) -> bool {
+    let frame = &*(frame as *const PluginFrame);
+    let found = panic::catch_unwind(AssertUnwindSafe(|| (frame.read_memory)(address)));
+    match found {
+        Ok(Some(found)) => {
+            *value = found;
+            true
+        }
+        _ => false,
+    }
+}
+
+impl SymbolPlugin for VTablePlugin {
+    fn version(&self) -> u32 {
+        self.vtable.version
+    }
+
+    fn fill_symbol(&self, module: &PluginModule, instruction: u64) -> Option<PluginSymbol> {
+        let vtable = &self.vtable;
+        let module = RawPluginModule::new(module);
+        unsafe {
+            let raw = (vtable.fill_symbol)(vtable.plugin, &module, instruction);
+            if raw.is_null() {
+                return None;
+            }
+            let symbol = (*raw).to_symbol();
+            (vtable.free_symbol)(vtable.plugin, raw);
+            Some(symbol)
+        }
+    }
+
+    fn walk_frame(&self, module: &PluginModule, frame: &PluginFrame) -> Option<PluginCallerFrame> {
+        let vtable = &self.vtable;
+        let module = RawPluginModule::new(module);
+        let raw_frame = RawPluginFrame {
+            instruction: frame.instruction,
+            grand_callee_parameter_size: frame.grand_callee_parameter_size,
+            context: frame as *const PluginFrame as *const c_void,
+            register: frame_register,
+            read_memory: frame_read_memory,
+        };
+        unsafe {
+            let raw = (vtable.walk_frame)(vtable.plugin, &module, &raw_frame);
+            if raw.is_null() {
+                return None;
+            }
+            let caller = (*raw).to_caller_frame();
+            (vtable.free_caller_frame)(vtable.plugin, raw);
+            Some(caller)
+        }
+    }
+}
+
+/// Export a [`SymbolPlugin`] from a dynamic library, for
+/// [`load_symbol_plugin`] to load.
+///
+/// The argument is an expression that makes the plugin. The library must be
+/// a `cdylib` built against the same [`SYMBOL_PLUGIN_VERSION`] as the program
+/// that loads it. The plugin is exported as a [`SymbolPluginVTable`], so the
+/// versions of Rust (and of the rest of this crate) don't need to match.
+///
+/// ```ignore
+/// struct MySymbolStore;
+///
+/// impl minidump_processor::SymbolPlugin for MySymbolStore {
+///     fn version(&self) -> u32 {
+///         minidump_processor::SYMBOL_PLUGIN_VERSION
+///     }
+///
+///     // ...
+/// }
+///
+/// minidump_processor::export_symbol_plugin!(MySymbolStore);
+/// ```
+///
+/// Plugins written in other languages export the same two functions:
+/// `uint32_t minidump_symbol_plugin_version(void)`, and
+/// `minidump_symbol_plugin_create`, which returns a [`SymbolPluginVTable`]
+/// by value.
+#[macro_export]
+macro_rules! export_symbol_plugin {
+    ($plugin:expr) => {
+        #[no_mangle]
+        pub extern "C" fn minidump_symbol_plugin_version() -> u32 {
+            $crate::SYMBOL_PLUGIN_VERSION
+        }
+
+        #[no_mangle]
+        pub extern "C" fn minidump_symbol_plugin_create() -> $crate::SymbolPluginVTable {
+            $crate::SymbolPluginVTable::new(Box::new($plugin))
+        }
+    };
+}
+
+/// Load the [`SymbolPlugin`] exported by the dynamic library at `path` with
+/// [`export_symbol_plugin!`].
+///
+/// The library's version of the plugin interface is checked before anything
+/// else in it is used.
+///
+/// # Safety
+///
+/// Loading a library runs its initialization code, and the library must
+/// export its plugin as [`export_symbol_plugin!`] says.
+#[cfg(feature = "plugins")]
+pub unsafe fn load_symbol_plugin(
+    path: impl AsRef<std::ffi::OsStr>,
+) -> Result<PluginSymbolProvider, PluginError> {
+    let library = libloading::Library::new(path)?;
+    let version = library.get::<extern "C" fn() -> u32>(b"minidump_symbol_plugin_version")?;
+    let found = version();
+    if found != SYMBOL_PLUGIN_VERSION {
+        return Err(PluginError::WrongVersion {
+            found,
+            expected: SYMBOL_PLUGIN_VERSION,
+        });
+    }
+    let create =
+        library.get::<extern "C" fn() -> SymbolPluginVTable>(b"minidump_symbol_plugin_create")?;
+    let plugin = VTablePlugin { vtable: create() };
+    PluginSymbolProvider::new(Box::new(LoadedPlugin {
+        plugin,
+        _library: library,
+    }))
+}
+
+/// A plugin that keeps the library it came from loaded.
+#[cfg(feature = "plugins")]
+struct LoadedPlugin {
+    // Dropped before the library its code is in.
+    plugin: VTablePlugin,
+    _library: libloading::Library,
+}
+
+#[cfg(feature = "plugins")]
+impl SymbolPlugin for LoadedPlugin {
+    fn version(&self) -> u32 {
+        self.plugin.version()
+    }
+
+    fn fill_symbol(&self, module: &PluginModule, instruction: u64) -> Option<PluginSymbol> {
+        self.plugin.fill_symbol(module, instruction)
+    }
+
+    fn walk_frame(&self, module: &PluginModule, frame: &PluginFrame) -> Option<PluginCallerFrame> {
+        self.plugin.walk_frame(module, frame)
+    }
+}
+
+#[cfg(all(test, feature = "breakpad-syms"))]
+mod test {
+    use super::*;
+    use breakpad_symbols::{SimpleFrame, SimpleModule};
+
+    /// A plugin with one function in `a`.
+    struct TestPlugin {
+        version: u32,
+    }
+
+    impl SymbolPlugin for TestPlugin {
+        fn version(&self) -> u32 {
+            self.version
+        }
+
+        fn fill_symbol(&self, module: &PluginModule, instruction: u64) -> Option<PluginSymbol> {
+            if module.code_file != "a" {
+                return None;
+            }
+            if !(0x1000..0x1030).contains(&instruction) {
+                return Some(PluginSymbol::default());
+            }
+            Some(PluginSymbol {
+                function: Some(PluginFunction {
+                    name: String::from("plugged in"),
+                    base: 0x1000,
+                    parameter_size: 0,
+                }),
+                source_line: Some(PluginSourceLine {
+                    file: String::from("a.c"),
+                    line: 12,
+                    base: 0x1000,
+                    url: None,
+                }),
+                inlines: vec![PluginInline {
+                    name: String::from("inlined"),
+                    file: Some(String::from("a.h")),
+                    line: None,
+                }],
+            })
+        }
+
+        fn walk_frame(
+            &self,
+            module: &PluginModule,
+            frame: &PluginFrame,
+        ) -> Option<PluginCallerFrame> {
+            if module.code_file != "a" {
+                return None;
+            }
+            let sp = (frame.register)("sp")?;
+            Some(PluginCallerFrame {
+                registers: vec![(String::from("sp"), sp + 8)],
+                cleared_registers: vec![String::from("x0")],
+                cfa: Some(sp + 8),
+                return_address: (frame.read_memory)(sp),
+            })
+        }
+    }
+
+    async fn lookup(
+        provider: &PluginSymbolProvider,
+        name: &str,
+        address: u64,
+    ) -> Option<SimpleFrame> {
+        let module = SimpleModule {
+            code_file: Some(name.to_string()),
+            ..SimpleModule::new(name, "abcd1234")
+        };
+        let mut frame = SimpleFrame::with_instruction(address);
+        provider.fill_symbol(&module, &mut frame).await.ok()?;
+        Some(frame)
+    }
+
+    #[tokio::test]
+    async fn test_plugin_symbol_provider() {
+        let provider = PluginSymbolProvider::new(Box::new(TestPlugin {
+            version: SYMBOL_PLUGIN_VERSION,
+        }))
+        .unwrap();
+
+        let frame = lookup(&provider, "a", 0x1010).await.unwrap();
+        assert_eq!(frame.function.as_deref(), Some("plugged in"));
+        assert_eq!(frame.source_file.as_deref(), Some("a.c"));
+        assert_eq!(frame.source_line, Some(12));
+        // Symbols that don't cover the address are still symbols.
+        let frame = lookup(&provider, "a", 0x2000).await.unwrap();
+        assert_eq!(frame.function, None);
+        assert!(lookup(&provider, "b", 0x1010).await.is_none());
+
+        let stats = provider.stats();
+        assert!(stats["a"].loaded_symbols);
+        assert!(!stats["b"].loaded_symbols);
+    }
+
+    #[test]
+    fn test_plugin_vtable() {
+        let plugin = VTablePlugin {
+            vtable: SymbolPluginVTable::new(Box::new(TestPlugin {
+                version: SYMBOL_PLUGIN_VERSION,
+            })),
+        };
+        assert_eq!(plugin.version(), SYMBOL_PLUGIN_VERSION);
+
+        let module = PluginModule {
+            code_file: String::from("a"),
+            debug_file: Some(String::from("a.pdb")),
+            ..PluginModule::default()
+        };
+        let direct = TestPlugin {
+            version: SYMBOL_PLUGIN_VERSION,
+        };
+        for &instruction in &[0x1010, 0x2000] {
+            assert_eq!(
+                plugin.fill_symbol(&module, instruction),
+                direct.fill_symbol(&module, instruction)
+            );
+        }
+        let other = PluginModule {
+            code_file: String::from("b"),
+            ..PluginModule::default()
+        };
+        assert_eq!(plugin.fill_symbol(&other, 0x1010), None);
+
+        let register = |name: &str| if name == "sp" { Some(0x100) } else { None };
+        let read_memory = |address| if address == 0x100 { Some(0x1234) } else { None };
+        let frame = PluginFrame {
+            instruction: 0x1010,
+            grand_callee_parameter_size: 0,
+            register: &register,
+            read_memory: &read_memory,
+        };
+        let caller = plugin.walk_frame(&module, &frame).unwrap();
+        assert_eq!(caller, direct.walk_frame(&module, &frame).unwrap());
+        assert_eq!(caller.return_address, Some(0x1234));
+        assert_eq!(plugin.walk_frame(&other, &frame), None);
+    }
+
+    #[test]
+    fn test_plugin_version() {
+        let result = PluginSymbolProvider::new(Box::new(TestPlugin {
+            version: SYMBOL_PLUGIN_VERSION + 1,
+        }));
+        assert!(matches!(
+            result,
+            Err(PluginError::WrongVersion { found, expected })
+                if found == SYMBOL_PLUGIN_VERSION + 1 && expected == SYMBOL_PLUGIN_VERSION
+        ));
+    }
+}
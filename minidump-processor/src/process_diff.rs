@@ -0,0 +1,195 @@
+// Copyright 2015 Ted Mielczarek. See the COPYRIGHT
+// file at the top-level directory of this distribution.
+
+//! Comparing two [`ProcessState`]s of the same minidump.
+//!
+//! Processing a minidump again (say, once more symbols are available, or
+//! with a newer version of this crate) should only ever improve what comes
+//! out. Comparing the results shows whether anything got worse: modules
+//! that appeared or disappeared, threads that were lost, a different crash
+//! reason, or a crashing stack that no longer looks the same.
+//!
+//! Stacks are compared by the [normalized names][crate::signature::normalize_frame]
+//! of their frames, so a frame that gains symbols counts as changed.
+
+use std::collections::BTreeMap;
+
+use minidump::{CrashReason, Module};
+
+use crate::process_state::{ProcessState, StackFrame};
+use crate::signature::{self, SignatureOptions};
+
+/// How two [`ProcessState`]s differ, from [`ProcessState::diff`].
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ProcessDiff {
+    /// The code files of the modules only the other state has.
+    pub added_modules: Vec<String>,
+    /// The code files of the modules only this state has.
+    pub removed_modules: Vec<String>,
+    /// The modules both states have, but with different versions.
+    pub changed_modules: Vec<ModuleVersionChange>,
+    /// How many threads this state has.
+    pub old_thread_count: usize,
+    /// How many threads the other state has.
+    pub new_thread_count: usize,
+    /// The crash reason of this state.
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::serialize::crash_reason")
+    )]
+    pub old_crash_reason: Option<CrashReason>,
+    /// The crash reason of the other state.
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::serialize::crash_reason")
+    )]
+    pub new_crash_reason: Option<CrashReason>,
+    /// How similar the crashing threads' stacks are, from 0.0 (nothing in
+    /// common) to 1.0 (the same frames), or `None` if either state has no
+    /// crashing thread.
+    pub crashing_stack_similarity: Option<f64>,
+}
+
+/// A module whose version differs between two [`ProcessState`]s.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ModuleVersionChange {
+    /// The code file of the module.
+    pub code_file: String,
+    /// The version in this state.
+    pub old_version: Option<String>,
+    /// The version in the other state.
+    pub new_version: Option<String>,
+}
+
+impl ProcessDiff {
+    /// `true` if the states don't differ in any of the ways compared.
+    pub fn is_empty(&self) -> bool {
+        self.added_modules.is_empty()
+            && self.removed_modules.is_empty()
+            && self.changed_modules.is_empty()
+            && self.old_thread_count == self.new_thread_count
+            && self.old_crash_reason == self.new_crash_reason
+            && self
+                .crashing_stack_similarity
+                .map_or(true, |similarity| similarity == 1.0)
+    }
+}
+
+/// Compare `old` to `new`.
+pub(crate) fn diff(old: &ProcessState, new: &ProcessState) -> ProcessDiff {
+    let old_modules = module_versions(old);
+    let new_modules = module_versions(new);
+    let added_modules = new_modules
+        .keys()
+        .filter(|code_file| !old_modules.contains_key(*code_file))
+        .cloned()
+        .collect();
+    let removed_modules = old_modules
+        .keys()
+        .filter(|code_file| !new_modules.contains_key(*code_file))
+        .cloned()
+        .collect();
+    let changed_modules = old_modules
+        .iter()
+        .filter_map(|(code_file, old_version)| {
+            let new_version = new_modules.get(code_file)?;
+            if old_version == new_version {
+                return None;
+            }
+            Some(ModuleVersionChange {
+                code_file: code_file.clone(),
+                old_version: old_version.clone(),
+                new_version: new_version.clone(),
+            })
+        })
+        .collect();
+
+    let crashing_stack_similarity = match (crashing_frames(old), crashing_frames(new)) {
+        (Some(old_frames), Some(new_frames)) => Some(stack_similarity(old_frames, new_frames)),
+        _ => None,
+    };
+
+    ProcessDiff {
+        added_modules,
+        removed_modules,
+        changed_modules,
+        old_thread_count: old.threads.len(),
+        new_thread_count: new.threads.len(),
+        old_crash_reason: old.crash_reason,
+        new_crash_reason: new.crash_reason,
+        crashing_stack_similarity,
+    }
+}
+
+/// The version of each module of `state`, by code file.
+fn module_versions(state: &ProcessState) -> BTreeMap<String, Option<String>> {
+    state
+        .modules
+        .iter()
+        .map(|module| {
+            (
+                module.code_file().into_owned(),
+                module.version().map(|version| version.into_owned()),
+            )
+        })
+        .collect()
+}
+
+fn crashing_frames(state: &ProcessState) -> Option<&[StackFrame]> {
+    let thread = state.threads.get(state.requesting_thread?)?;
+    Some(&thread.frames)
+}
+
+/// How many frames `old` and `new` have in common, in the same order, as a
+/// fraction of how many frames they have on average.
+///
+/// Frames that only one of the stacks has (like ones found by better
+/// unwinding) don't stop the frames around them from matching.
+fn stack_similarity(old: &[StackFrame], new: &[StackFrame]) -> f64 {
+    if old.is_empty() && new.is_empty() {
+        return 1.0;
+    }
+    let options = SignatureOptions::default();
+    let names = |frames: &[StackFrame]| {
+        frames
+            .iter()
+            .map(|frame| signature::normalize_frame(frame, &options))
+            .collect::<Vec<_>>()
+    };
+    let common = longest_common_subsequence(&names(old), &names(new));
+    2.0 * common as f64 / (old.len() + new.len()) as f64
+}
+
+/// The length of the longest sequence of items that both `a` and `b` have
+/// in the same order.
+fn longest_common_subsequence<T: PartialEq>(a: &[T], b: &[T]) -> usize {
+    let mut previous = vec![0; b.len() + 1];
+    let mut current = vec![0; b.len() + 1];
+    for item in a {
+        for (j, other) in b.iter().enumerate() {
+            current[j + 1] = if item == other {
+                previous[j] + 1
+            } else {
+                current[j].max(previous[j + 1])
+            };
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+    previous[b.len()]
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_longest_common_subsequence() {
+        assert_eq!(longest_common_subsequence::<u8>(&[], &[]), 0);
+        assert_eq!(longest_common_subsequence(&[1, 2, 3], &[1, 2, 3]), 3);
+        assert_eq!(longest_common_subsequence(&[1, 2, 3], &[1, 4, 2, 3]), 3);
+        assert_eq!(longest_common_subsequence(&[1, 2, 3], &[3, 2, 1]), 1);
+        assert_eq!(longest_common_subsequence(&[1, 2], &[3, 4]), 0);
+    }
+}
@@ -0,0 +1,229 @@
+// Copyright 2015 Ted Mielczarek. See the COPYRIGHT
+// file at the top-level directory of this distribution.
+
+//! Whether the process was sandboxed or elevated.
+//!
+//! Windows minidumps with version 3 or later misc info record the process's
+//! integrity level, whether it was a protected process, and its DEP
+//! settings. A crash in a low integrity (sandboxed) process can only do so
+//! much, while one in an elevated process is worth looking at more closely.
+
+use std::fmt;
+
+use minidump::MinidumpMiscInfo;
+
+/// `MEM_EXECUTE_OPTION_DISABLE`: non-executable memory can't be executed.
+const MEM_EXECUTE_OPTION_DISABLE: u32 = 0x1;
+/// `MEM_EXECUTE_OPTION_DISABLE_THUNK_EMULATION`: ATL thunks aren't emulated.
+const MEM_EXECUTE_OPTION_DISABLE_THUNK_EMULATION: u32 = 0x4;
+/// `MEM_EXECUTE_OPTION_PERMANENT`: the process can't change its DEP policy.
+const MEM_EXECUTE_OPTION_PERMANENT: u32 = 0x8;
+
+/// The security settings of the process, as far as the minidump records
+/// them. Each is `None` if it isn't known.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ProcessSecurity {
+    /// The integrity level of the process's token.
+    pub integrity_level: Option<IntegrityLevel>,
+    /// Whether the process was a protected process (or protected process
+    /// light), which even administrators can't inject code into.
+    pub protected_process: Option<bool>,
+    /// The process's data execution prevention policy.
+    pub dep: Option<DepPolicy>,
+}
+
+/// The mandatory integrity level of a Windows process, which limits what it
+/// can write to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum IntegrityLevel {
+    /// `SECURITY_MANDATORY_UNTRUSTED_RID`, the most locked down sandboxes.
+    Untrusted,
+    /// `SECURITY_MANDATORY_LOW_RID`, sandboxes and AppContainers.
+    Low,
+    /// `SECURITY_MANDATORY_MEDIUM_RID`, normal processes.
+    Medium,
+    /// `SECURITY_MANDATORY_MEDIUM_PLUS_RID`.
+    MediumPlus,
+    /// `SECURITY_MANDATORY_HIGH_RID`, elevated processes.
+    High,
+    /// `SECURITY_MANDATORY_SYSTEM_RID`, services running as the system.
+    System,
+    /// `SECURITY_MANDATORY_PROTECTED_PROCESS_RID`.
+    Protected,
+    /// Some other RID, which isn't one of the named levels.
+    Other(u32),
+}
+
+impl IntegrityLevel {
+    /// The level with the RID `rid`.
+    pub fn from_rid(rid: u32) -> IntegrityLevel {
+        match rid {
+            0x0000 => IntegrityLevel::Untrusted,
+            0x1000 => IntegrityLevel::Low,
+            0x2000 => IntegrityLevel::Medium,
+            0x2100 => IntegrityLevel::MediumPlus,
+            0x3000 => IntegrityLevel::High,
+            0x4000 => IntegrityLevel::System,
+            0x5000 => IntegrityLevel::Protected,
+            rid => IntegrityLevel::Other(rid),
+        }
+    }
+
+    /// The RID of the level.
+    pub fn rid(self) -> u32 {
+        match self {
+            IntegrityLevel::Untrusted => 0x0000,
+            IntegrityLevel::Low => 0x1000,
+            IntegrityLevel::Medium => 0x2000,
+            IntegrityLevel::MediumPlus => 0x2100,
+            IntegrityLevel::High => 0x3000,
+            IntegrityLevel::System => 0x4000,
+            IntegrityLevel::Protected => 0x5000,
+            IntegrityLevel::Other(rid) => rid,
+        }
+    }
+
+    /// Whether the level is below medium, which is what sandboxes run at.
+    pub fn is_sandboxed(self) -> bool {
+        self.rid() < IntegrityLevel::Medium.rid()
+    }
+
+    /// Whether the level is high or above, which is what elevated processes
+    /// and services run at.
+    pub fn is_elevated(self) -> bool {
+        self.rid() >= IntegrityLevel::High.rid()
+    }
+
+    /// The name of the level in the JSON schema, or `None` for
+    /// [`IntegrityLevel::Other`], which is given as its RID instead.
+    pub fn json_name(self) -> Option<&'static str> {
+        Some(match self {
+            IntegrityLevel::Untrusted => "untrusted",
+            IntegrityLevel::Low => "low",
+            IntegrityLevel::Medium => "medium",
+            IntegrityLevel::MediumPlus => "medium_plus",
+            IntegrityLevel::High => "high",
+            IntegrityLevel::System => "system",
+            IntegrityLevel::Protected => "protected",
+            IntegrityLevel::Other(_) => return None,
+        })
+    }
+}
+
+impl fmt::Display for IntegrityLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IntegrityLevel::Untrusted => f.write_str("untrusted"),
+            IntegrityLevel::Low => f.write_str("low"),
+            IntegrityLevel::Medium => f.write_str("medium"),
+            IntegrityLevel::MediumPlus => f.write_str("medium plus"),
+            IntegrityLevel::High => f.write_str("high"),
+            IntegrityLevel::System => f.write_str("system"),
+            IntegrityLevel::Protected => f.write_str("protected"),
+            IntegrityLevel::Other(rid) => write!(f, "{:#x}", rid),
+        }
+    }
+}
+
+/// A process's data execution prevention policy, from its execute flags.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DepPolicy {
+    /// Whether memory that isn't executable can't be executed.
+    pub enabled: bool,
+    /// Whether the process couldn't turn DEP off.
+    pub permanent: bool,
+    /// Whether the ATL thunks that DEP would break were emulated.
+    pub atl_thunk_emulation: bool,
+}
+
+impl DepPolicy {
+    /// The policy of the `MEM_EXECUTE_OPTION_*` flags `flags`.
+    pub fn from_execute_flags(flags: u32) -> DepPolicy {
+        DepPolicy {
+            enabled: flags & MEM_EXECUTE_OPTION_DISABLE != 0,
+            permanent: flags & MEM_EXECUTE_OPTION_PERMANENT != 0,
+            atl_thunk_emulation: flags & MEM_EXECUTE_OPTION_DISABLE_THUNK_EMULATION == 0,
+        }
+    }
+}
+
+impl fmt::Display for ProcessSecurity {
+    /// Formats the settings that are known, like "low integrity, DEP".
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut parts = Vec::new();
+        if let Some(level) = self.integrity_level {
+            parts.push(format!("{} integrity", level));
+        }
+        match self.protected_process {
+            Some(true) => parts.push(String::from("protected")),
+            Some(false) => parts.push(String::from("not protected")),
+            None => {}
+        }
+        if let Some(dep) = self.dep {
+            parts.push(String::from(match (dep.enabled, dep.permanent) {
+                (true, true) => "DEP (permanent)",
+                (true, false) => "DEP",
+                (false, _) => "no DEP",
+            }));
+        }
+        f.write_str(&parts.join(", "))
+    }
+}
+
+/// The security settings `misc_info` records, or `None` if it doesn't
+/// record any (as with misc info from before version 3, or non-Windows
+/// minidumps).
+pub(crate) fn process_security(misc_info: &MinidumpMiscInfo) -> Option<ProcessSecurity> {
+    let raw = &misc_info.raw;
+    let security = ProcessSecurity {
+        integrity_level: raw
+            .process_integrity_level()
+            .map(|&rid| IntegrityLevel::from_rid(rid)),
+        protected_process: raw.protected_process().map(|&protected| protected != 0),
+        dep: raw
+            .process_execute_flags()
+            .map(|&flags| DepPolicy::from_execute_flags(flags)),
+    };
+    if security == ProcessSecurity::default() {
+        None
+    } else {
+        Some(security)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_integrity_level() {
+        assert_eq!(IntegrityLevel::from_rid(0x1000), IntegrityLevel::Low);
+        assert!(IntegrityLevel::Low.is_sandboxed());
+        assert!(!IntegrityLevel::Medium.is_sandboxed());
+        assert!(!IntegrityLevel::MediumPlus.is_elevated());
+        assert!(IntegrityLevel::System.is_elevated());
+
+        // Levels between the named ones still compare by RID.
+        let level = IntegrityLevel::from_rid(0x1500);
+        assert_eq!(level, IntegrityLevel::Other(0x1500));
+        assert!(level.is_sandboxed());
+        assert_eq!(level.json_name(), None);
+        assert_eq!(level.to_string(), "0x1500");
+    }
+
+    #[test]
+    fn test_dep_policy() {
+        let dep = DepPolicy::from_execute_flags(0x1 | 0x4 | 0x8);
+        assert!(dep.enabled);
+        assert!(dep.permanent);
+        assert!(!dep.atl_thunk_emulation);
+
+        let dep = DepPolicy::from_execute_flags(0x2);
+        assert!(!dep.enabled);
+        assert!(!dep.permanent);
+        assert!(dep.atl_thunk_emulation);
+    }
+}
@@ -7,9 +7,26 @@ use std::borrow::{Borrow, Cow};
 use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::io;
 use std::io::prelude::*;
-use std::time::SystemTime;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
 
+use crate::assertion::Assertion;
+use crate::bit_flips::BitFlip;
+use crate::crash_address::CrashAddressClass;
+use crate::crash_memory::CrashMemory;
+use crate::crashpad::CrashpadAnnotations;
+use crate::disassembly::CrashDisassembly;
+use crate::exploitability::Exploitability;
+use crate::handles::{CrashHandleReason, HandleSummary, SUSPICIOUS_HANDLE_COUNT};
+use crate::process_diff::{self, ProcessDiff};
+use crate::process_security::{IntegrityLevel, ProcessSecurity};
+use crate::register_targets::{PointerTarget, RegisterTarget};
+use crate::signature::{self, SignatureOptions};
+use crate::source_context::SourceContext;
+use crate::stack_overflow::StackOverflow;
+use crate::suspicious_modules::SuspiciousModule;
 use crate::system_info::SystemInfo;
+use crate::timings::ProcessTimings;
 use crate::{FrameSymbolizer, SymbolStats};
 use minidump::system_info::Cpu;
 use minidump::*;
@@ -19,6 +36,7 @@ use serde_json::json;
 /// stack walking is trusted. Since the stack walker can resort to
 /// stack scanning, it can wind up with dubious frames.
 #[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum FrameTrust {
     /// Unknown
     None,
@@ -34,10 +52,13 @@ pub enum FrameTrust {
     PreWalked,
     /// Given as instruction pointer in a context.
     Context,
+    /// A function inlined into the next physical frame, found with symbols.
+    Inlined,
 }
 
 /// A single stack frame produced from unwinding a thread's stack.
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct StackFrame {
     /// The program counter location as an absolute virtual address.
     ///
@@ -56,14 +77,24 @@ pub struct StackFrame {
     ///   immediately after the call instruction is in an entirely different
     ///   function, perhaps even from a different source file.)
     ///
-    /// On some architectures, the return address as saved on the stack or in
-    /// a register is fine for looking up the point of the call. On others, it
-    /// requires adjustment. ReturnAddress returns the address as saved by the
-    /// machine.
+    /// This is the address used to symbolize the frame. The address the
+    /// caller resumes at is kept in `resume_address`.
     pub instruction: u64,
 
+    /// The address execution resumes at when this frame's callee returns, as
+    /// saved on the stack or in a register by the machine.
+    ///
+    /// For the innermost called frame, this is the exact program counter,
+    /// same as `instruction`. For all other frames, it's the return address
+    /// that `instruction` was adjusted back from. ARM's Thumb bit is cleared.
+    pub resume_address: u64,
+
     /// The module in which the instruction resides.
-    pub module: Option<MinidumpModule>,
+    ///
+    /// This is shared with the [`ProcessState::modules`] the frame is from,
+    /// and the rest of the frames in the module.
+    #[cfg_attr(feature = "serde", serde(serialize_with = "crate::serialize::module"))]
+    pub module: Option<Arc<MinidumpModule>>,
 
     /// Any unloaded modules which overlap with this address.
     ///
@@ -77,6 +108,29 @@ pub struct StackFrame {
     /// So this is a `BTreeMap<module_name, Set<offsets>>`.
     pub unloaded_modules: BTreeMap<String, BTreeSet<u64>>,
 
+    /// The unloaded module this frame's symbols were found in.
+    ///
+    /// This is only looked for if [`ProcessorOptions::symbolicate_unloaded_modules`]
+    /// is set. The symbols are the unloaded module's, so they may be wrong if
+    /// something else was loaded at the same address afterwards.
+    ///
+    /// [`ProcessorOptions::symbolicate_unloaded_modules`]: crate::ProcessorOptions::symbolicate_unloaded_modules
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::serialize::unloaded_module")
+    )]
+    pub symbolicated_unloaded_module: Option<MinidumpUnloadedModule>,
+
+    /// Whether `function_name` and `function_base` are those of the nearest
+    /// function the module exports, because there were no symbols for it.
+    ///
+    /// This is only done if [`ProcessorOptions::symbolicate_from_exports`] is
+    /// set, and the module's image is in the minidump. The instruction may be
+    /// in some other function that isn't exported.
+    ///
+    /// [`ProcessorOptions::symbolicate_from_exports`]: crate::ProcessorOptions::symbolicate_from_exports
+    pub function_from_exports: bool,
+
     /// The function name, may be omitted if debug symbols are not available.
     pub function_name: Option<String>,
 
@@ -99,16 +153,192 @@ pub struct StackFrame {
     /// are not available.
     pub source_line_base: Option<u64>,
 
+    /// The URL of the source file, if the symbols have SourceLink mappings or
+    /// source-indexed paths.
+    pub source_url: Option<String>,
+
+    /// Other source lines the instruction is also from, as the file and line.
+    ///
+    /// Optimized code often merges the instructions of several lines, so the
+    /// symbols can have more than one line record for an address.
+    pub other_source_lines: Vec<(String, u32)>,
+
+    /// The lines of source around `source_line`, if
+    /// [`ProcessorOptions::source_roots`] had the source file.
+    ///
+    /// [`ProcessorOptions::source_roots`]: crate::ProcessorOptions::source_roots
+    pub source_context: Option<SourceContext>,
+
     /// Amount of trust the stack walker has in the instruction pointer
     /// of this frame.
     pub trust: FrameTrust,
 
+    /// Whether the instruction is outside of any module, in executable memory
+    /// that wasn't mapped from a file. This is most likely JIT-compiled code.
+    pub is_jit: bool,
+
+    /// Whether the instruction is Thumb code (ARM only).
+    ///
+    /// ARM marks Thumb return addresses by setting their lowest bit. It's
+    /// cleared from the `instruction` of caller frames, so that they
+    /// symbolize correctly.
+    pub is_thumb: bool,
+
+    /// If set, this isn't a real frame, but a marker standing in for frames
+    /// that were left out because they repeat the frames above it.
+    ///
+    /// The rest of the marker is the first frame that was left out.
+    pub repeated: Option<RepeatedFrames>,
+
+    /// If set, this isn't a real frame, but a marker for a stretch of the
+    /// stack that no caller could be found in, which the stackwalker skipped
+    /// to find the frames above it. The marker has no instruction or module.
+    ///
+    /// This is only done if [`ProcessorOptions::resume_after_gaps`] is set.
+    ///
+    /// [`ProcessorOptions::resume_after_gaps`]: crate::ProcessorOptions::resume_after_gaps
+    pub gap: Option<StackGap>,
+
+    /// How the stackwalker went looking for this frame's caller.
+    ///
+    /// This is only recorded if [`ProcessorOptions::unwind_diagnostics`] is
+    /// set, and it's missing from inlined frames.
+    ///
+    /// [`ProcessorOptions::unwind_diagnostics`]: crate::ProcessorOptions::unwind_diagnostics
+    pub unwind_diagnostics: Option<UnwindDiagnostics>,
+
     /// The CPU context containing register state for this frame.
+    #[cfg_attr(feature = "serde", serde(serialize_with = "crate::serialize::context"))]
     pub context: MinidumpContext,
 }
 
+/// The ways the stackwalker tried to find a frame's caller, in the order it
+/// tried them.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct UnwindDiagnostics {
+    pub attempts: Vec<UnwindAttempt>,
+}
+
+/// One attempt at finding a frame's caller.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct UnwindAttempt {
+    /// How the caller was looked for.
+    pub strategy: UnwindStrategy,
+    /// Whether this attempt found the caller.
+    pub succeeded: bool,
+    /// Why results were thrown out, in the order it happened.
+    pub rejections: Vec<String>,
+    /// Addresses that had to be read, but aren't in the stack memory.
+    pub rejected_reads: Vec<u64>,
+}
+
+/// A way of finding a frame's caller.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum UnwindStrategy {
+    /// Call frame info from the symbol files.
+    CallFrameInfo,
+    /// The `.eh_frame` section of the module, from the minidump's memory.
+    EhFrame,
+    /// The ARM `.ARM.exidx` section of the module, from the minidump's memory.
+    Exidx,
+    /// The frame pointer, or whatever passes for it on the architecture.
+    FramePointer,
+    /// The link register.
+    LinkRegister,
+    /// Searching the stack for something that looks like a return address.
+    Scan,
+    /// An [`ArchUnwinder`][crate::ArchUnwinder] registered in the
+    /// [`ProcessorOptions`][crate::ProcessorOptions].
+    Custom,
+}
+
+impl UnwindDiagnostics {
+    /// Write a human-readable description of the attempts to `f`.
+    pub fn print<T: Write>(&self, f: &mut T) -> io::Result<()> {
+        writeln!(f, "    Caller search:")?;
+        for attempt in &self.attempts {
+            let result = if attempt.succeeded { "found" } else { "failed" };
+            write!(f, "      {}: {}", attempt.strategy.json_name(), result)?;
+            let reasons: Vec<_> = attempt
+                .rejections
+                .iter()
+                .cloned()
+                .chain(
+                    attempt
+                        .rejected_reads
+                        .iter()
+                        .map(|address| format!("couldn't read {:#x}", address)),
+                )
+                .collect();
+            if !reasons.is_empty() {
+                write!(f, " ({})", reasons.join("; "))?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+
+    fn to_json(&self, json_hex: impl Fn(u64) -> String) -> serde_json::Value {
+        json!(self
+            .attempts
+            .iter()
+            .map(|attempt| json!({
+                "strategy": attempt.strategy.json_name(),
+                "succeeded": attempt.succeeded,
+                "rejections": attempt.rejections,
+                "rejected_reads": attempt
+                    .rejected_reads
+                    .iter()
+                    .map(|&address| json_hex(address))
+                    .collect::<Vec<_>>(),
+            }))
+            .collect::<Vec<_>>())
+    }
+}
+
+impl UnwindStrategy {
+    fn json_name(&self) -> &'static str {
+        match *self {
+            UnwindStrategy::CallFrameInfo => "cfi",
+            UnwindStrategy::EhFrame => "eh_frame",
+            UnwindStrategy::Exidx => "exidx",
+            UnwindStrategy::FramePointer => "frame_pointer",
+            UnwindStrategy::LinkRegister => "link_register",
+            UnwindStrategy::Scan => "scan",
+            UnwindStrategy::Custom => "custom",
+        }
+    }
+}
+
+/// Frames left out of a [`CallStack`] because they kept repeating the same
+/// pattern, as happens with runaway recursion.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RepeatedFrames {
+    /// How many frames were left out.
+    pub count: usize,
+    /// How many frames long the repeating pattern is.
+    pub period: usize,
+}
+
+/// Part of a [`CallStack`]'s stack that the stackwalker skipped over, because
+/// the stack there is missing or trashed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct StackGap {
+    /// The stack pointer of the frame below the gap, or the start of the
+    /// stack memory if that wasn't in the stack.
+    pub start: u64,
+    /// The address the return address of the frame above the gap was found at.
+    pub end: u64,
+}
+
 /// Information about the results of unwinding a thread's stack.
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum CallStackInfo {
     /// Everything went great.
     Ok,
@@ -120,9 +350,70 @@ pub enum CallStackInfo {
     UnsupportedCpu,
     /// This thread wrote the minidump, it was skipped.
     DumpThreadSkipped,
+    /// `ProcessorOptions::thread_filter` left this thread out, it was
+    /// skipped.
+    ThreadSkipped,
+    /// The stack had more frames than `ProcessorOptions::max_frames`, so the
+    /// outermost ones were dropped.
+    FrameLimitReached,
+    /// `ProcessorOptions::frame_observer` asked for the walk to stop, so the
+    /// outermost frames weren't looked for.
+    WalkStopped,
+    /// `ProcessorOptions::deadline` passed (or processing was cancelled)
+    /// during the walk, so the outermost frames weren't looked for.
+    TimedOut,
+    /// The stack was bigger than `ProcessorOptions::max_stack_size` (or
+    /// `max_total_stack_size` was used up), so only part of it (or none of
+    /// it) was walked.
+    StackMemoryLimited,
+}
+
+/// Something a minidump was missing that processing made do without, in
+/// [`never_fail`][crate::ProcessorOptions::never_fail] mode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Degradation {
+    /// There was no system info stream, so nothing about the system is
+    /// known, besides what `InferredCpu` says.
+    MissingSystemInfo,
+    /// The CPU wasn't in the minidump, and was inferred from the exception's
+    /// context.
+    InferredCpu,
+    /// There was no thread list stream, so the only thread is the one that
+    /// crashed, made up from the exception stream.
+    SynthesizedThread,
+    /// There was no thread list stream, or anything to make a thread up
+    /// from, so there are no threads.
+    MissingThreadList,
+}
+
+impl Degradation {
+    /// The name of the degradation in the JSON schema.
+    pub fn json_name(self) -> &'static str {
+        match self {
+            Degradation::MissingSystemInfo => "missing_system_info",
+            Degradation::InferredCpu => "inferred_cpu",
+            Degradation::SynthesizedThread => "synthesized_thread",
+            Degradation::MissingThreadList => "missing_thread_list",
+        }
+    }
+}
+
+impl std::fmt::Display for Degradation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Degradation::MissingSystemInfo => "the system info is missing",
+            Degradation::InferredCpu => "the CPU was inferred from the exception context",
+            Degradation::SynthesizedThread => {
+                "the thread list is missing, the crashing thread was made up from the exception"
+            }
+            Degradation::MissingThreadList => "the thread list is missing",
+        })
+    }
 }
 
 /// A stack of `StackFrame`s produced as a result of unwinding a thread.
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct CallStack {
     /// The stack frames.
     /// By convention, the stack frame at index 0 is the innermost callee frame,
@@ -136,10 +427,46 @@ pub struct CallStack {
     /// The name of the thread, if known.
     pub thread_name: Option<String>,
     /// The GetLastError() value stored in the TEB.
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::serialize::crash_reason")
+    )]
     pub last_error_value: Option<CrashReason>,
+    /// Whether any repeating frames were collapsed into a
+    /// [`RepeatedFrames`] marker, which likely means the thread overflowed
+    /// its stack through runaway recursion.
+    pub recursion_collapsed: bool,
+    /// The registers the stack was walked from, if the thread had any.
+    ///
+    /// Unlike the contexts of the frames, this is kept even if the frames
+    /// aren't. For threads other than the one that requested the dump, it's
+    /// only kept if [`ProcessorOptions::all_thread_registers`] is set.
+    ///
+    /// [`ProcessorOptions::all_thread_registers`]: crate::ProcessorOptions::all_thread_registers
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::serialize::optional_context")
+    )]
+    pub context: Option<MinidumpContext>,
+    /// The priority class of the thread (0 if unknown).
+    pub priority_class: u32,
+    /// The priority level of the thread within its class (0 if unknown).
+    pub priority: u32,
+    /// How long the thread spent running in kernel mode, if the minidump
+    /// has a `ThreadInfoListStream`.
+    pub kernel_time: Option<Duration>,
+    /// How long the thread spent running in user mode, if the minidump has
+    /// a `ThreadInfoListStream`.
+    pub user_time: Option<Duration>,
+    /// The address the thread started running at, if the minidump has a
+    /// `ThreadInfoListStream`.
+    ///
+    /// Threads without a name are named after where this is instead.
+    pub start_address: Option<u64>,
 }
 
 #[derive(Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct LinuxStandardBase {
     pub id: String,
     pub release: String,
@@ -147,7 +474,89 @@ pub struct LinuxStandardBase {
     pub description: String,
 }
 
+/// What the minidump's copy of `/proc/self/status` says about the process
+/// (see `proc(5)`). Each field is `None` if it's missing or unparseable.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LinuxProcStatus {
+    /// The state of the process, like "R (running)" or "S (sleeping)".
+    pub state: Option<String>,
+    /// How many threads the process had.
+    pub threads: Option<u64>,
+    /// The most virtual memory the process ever had, in bytes.
+    pub vm_peak: Option<u64>,
+    /// How much virtual memory the process had, in bytes.
+    pub vm_size: Option<u64>,
+    /// How much of the process's memory was resident, in bytes.
+    pub vm_rss: Option<u64>,
+    /// The process's seccomp mode: 0 (none), 1 (strict) or 2 (filter).
+    pub seccomp: Option<u32>,
+}
+
+impl LinuxProcStatus {
+    /// The name of [`seccomp`][Self::seccomp]'s mode.
+    pub fn seccomp_name(&self) -> Option<&'static str> {
+        match self.seccomp? {
+            0 => Some("disabled"),
+            1 => Some("strict"),
+            2 => Some("filter"),
+            _ => None,
+        }
+    }
+}
+
+/// The memory permissions of an address, as recorded by the minidump's
+/// memory info (`MemoryInfoListStream` on Windows, `/proc/self/maps` on Linux).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MemoryAccess {
+    /// Whether the address was in mapped memory: a known memory region that
+    /// was committed, not just reserved or free. The other permissions are
+    /// only set for mapped memory.
+    pub mapped: bool,
+    /// Whether the address was readable.
+    pub readable: bool,
+    /// Whether the address was writable.
+    pub writable: bool,
+    /// Whether the address was executable.
+    pub executable: bool,
+}
+
+impl MemoryAccess {
+    /// Look up the permissions of `address` in `memory_info`.
+    pub fn at_address(memory_info: &UnifiedMemoryInfoList, address: u64) -> MemoryAccess {
+        let mapped = memory_info.is_committed(address);
+        MemoryAccess {
+            mapped,
+            readable: mapped && memory_info.is_readable(address),
+            writable: mapped && memory_info.is_writable(address),
+            executable: mapped && memory_info.is_executable(address),
+        }
+    }
+}
+
+impl std::fmt::Display for MemoryAccess {
+    /// Formats the permissions like `/proc/self/maps` does ("r-x"), or "unmapped".
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if !self.mapped {
+            return write!(f, "unmapped");
+        }
+        write!(
+            f,
+            "{}{}{}",
+            if self.readable { 'r' } else { '-' },
+            if self.writable { 'w' } else { '-' },
+            if self.executable { 'x' } else { '-' },
+        )
+    }
+}
+
 /// The state of a process as recorded by a `Minidump`.
+///
+/// With the `serde` feature, this (and everything in it) can be serialized.
+/// Unlike the output of [`ProcessState::print_json`], what that produces
+/// isn't stable.
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct ProcessState {
     /// The PID of the process.
     pub process_id: Option<u32>,
@@ -155,9 +564,30 @@ pub struct ProcessState {
     pub time: SystemTime,
     /// When the process started, if available
     pub process_create_time: Option<SystemTime>,
-    /// Known code signing certificates (module name => cert name)
+    /// Whether the process was sandboxed or elevated, if the minidump
+    /// records it (only Windows minidumps do).
+    pub process_security: Option<ProcessSecurity>,
+    /// The kind of minidump this is (normal, full memory, with handle data...).
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::serialize::dump_type")
+    )]
+    pub dump_type: format::MinidumpType,
+    /// Known code signing certificates (module name => cert name), from the
+    /// [`auxiliary_data`][crate::ProcessorOptions::auxiliary_data] (such as
+    /// the evil JSON), or the modules' own Authenticode signatures.
     pub cert_info: HashMap<String, String>,
+    /// Anything else that came with the minidump (key => value), from the
+    /// [`auxiliary_data`][crate::ProcessorOptions::auxiliary_data].
+    pub annotations: BTreeMap<String, String>,
+    /// The annotations Crashpad recorded for the process and its modules, if
+    /// the minidump has a Crashpad info stream.
+    pub crashpad_annotations: Option<CrashpadAnnotations>,
     /// If the process crashed, a `CrashReason` describing the crash reason.
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::serialize::crash_reason")
+    )]
     pub crash_reason: Option<CrashReason>,
     /// The memory address implicated in the crash.
     ///
@@ -167,8 +597,51 @@ pub struct ProcessState {
     /// errors, this will be the address of the instruction that caused the
     /// fault.
     pub crash_address: Option<u64>,
-    /// A string describing an assertion that was hit, if present.
-    pub assertion: Option<String>,
+    /// The memory permissions of `crash_address`, if the minidump includes memory info.
+    ///
+    /// This can help distinguish e.g. a wild jump into non-executable memory
+    /// from a write to read-only memory.
+    pub crash_address_access: Option<MemoryAccess>,
+    /// What kind of address `crash_address` is (like a null pointer), and
+    /// which register it likely came from, if the crash was a bad memory
+    /// access to a recognizable kind of address.
+    pub crash_address_class: Option<CrashAddressClass>,
+    /// Why the crash looks like a stack overflow, if it does.
+    ///
+    /// Outside of Windows, stack overflows are reported as ordinary bad
+    /// memory accesses, so this is a guess. See [`StackOverflow`].
+    pub stack_overflow: Option<StackOverflow>,
+    /// What each of the crashing thread's registers points at, for those
+    /// that point at something recognizable.
+    pub register_targets: Vec<RegisterTarget>,
+    /// How exploitable the crash looks, if the processor was asked to
+    /// analyze it and there was a crash.
+    ///
+    /// See [`ProcessorOptions::analyze_exploitability`][crate::ProcessorOptions::analyze_exploitability].
+    pub exploitability: Option<Exploitability>,
+    /// Addresses involved in the crash that look like they had a bit flipped
+    /// by bad hardware, if the processor was asked to look for them.
+    ///
+    /// See [`ProcessorOptions::detect_bit_flips`][crate::ProcessorOptions::detect_bit_flips].
+    pub possible_bit_flips: Option<Vec<BitFlip>>,
+    /// The memory around the crash address and the crashing thread's
+    /// instruction and stack pointers, if the processor was asked to keep it.
+    ///
+    /// See [`ProcessorOptions::crash_memory_size`][crate::ProcessorOptions::crash_memory_size].
+    pub crash_memory: Option<Vec<CrashMemory>>,
+    /// The instructions around the crashing thread's instruction pointer,
+    /// if the minidump has the memory they're in.
+    ///
+    /// Always `None` without the `disasm` feature.
+    pub disassembly: Option<CrashDisassembly>,
+    /// What the handles the process had open look like, if the minidump
+    /// lists them.
+    pub handles: Option<HandleSummary>,
+    /// An assertion that was hit, if the minidump records one.
+    ///
+    /// This is a better description of the crash than [`crash_reason`][Self::crash_reason],
+    /// which is usually just the exception raised to write the minidump.
+    pub assertion: Option<Assertion>,
     /// The index of the thread that requested a dump be written.
     /// If a dump was produced as a result of a crash, this
     /// will point to the thread that crashed.  If the dump was produced as
@@ -178,6 +651,14 @@ pub struct ProcessState {
     /// extended Breakpad information is present, this field will be
     /// `None`.
     pub requesting_thread: Option<usize>,
+    /// Whether `requesting_thread` wasn't in the minidump, but guessed from
+    /// the threads' stacks.
+    ///
+    /// This is only done if [`ProcessorOptions::guess_requesting_thread`] is
+    /// set.
+    ///
+    /// [`ProcessorOptions::guess_requesting_thread`]: crate::ProcessorOptions::guess_requesting_thread
+    pub requesting_thread_guessed: bool,
     /// Stacks for each thread (except possibly the exception handler
     /// thread) at the time of the crash.
     pub threads: Vec<CallStack>,
@@ -187,17 +668,74 @@ pub struct ProcessState {
     pub system_info: SystemInfo,
     /// Linux Standard Base Info
     pub linux_standard_base: Option<LinuxStandardBase>,
+    /// The process's status from the minidump's `LinuxProcStatus` stream,
+    /// which includes how much memory and how many threads it had.
+    pub linux_proc_status: Option<LinuxProcStatus>,
+    /// The environment variables of the process that were allowed by
+    /// [`ProcessorOptions::environment_allowlist`], or `None` if the
+    /// minidump has no `LinuxEnviron` stream.
+    ///
+    /// [`ProcessorOptions::environment_allowlist`]: crate::ProcessorOptions::environment_allowlist
+    pub environment: Option<BTreeMap<String, String>>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::serialize::mac_crash_info")
+    )]
     pub mac_crash_info: Option<Vec<RawMacCrashInfo>>,
     /// The modules that were loaded into the process represented by the
     /// `ProcessState`.
+    #[cfg_attr(feature = "serde", serde(serialize_with = "crate::serialize::modules"))]
     pub modules: MinidumpModuleList,
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::serialize::unloaded_modules")
+    )]
     pub unloaded_modules: MinidumpUnloadedModuleList,
+    /// Modules that look like they were injected into the process, if the
+    /// processor was asked to look for them.
+    ///
+    /// See [`ProcessorOptions::detect_suspicious_modules`][crate::ProcessorOptions::detect_suspicious_modules].
+    pub suspicious_modules: Option<Vec<SuspiciousModule>>,
     // modules_without_symbols
     // modules_with_corrupt_symbols
     // exploitability
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::serialize::unknown_streams")
+    )]
     pub unknown_streams: Vec<MinidumpUnknownStream>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::serialize::unimplemented_streams")
+    )]
     pub unimplemented_streams: Vec<MinidumpUnimplementedStream>,
+    /// Streams whose data was cut off by the end of the minidump.
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::serialize::truncated_streams")
+    )]
+    pub truncated_streams: Vec<MinidumpTruncatedStream>,
+    /// How many stream directory entries were cut off by the end of the minidump.
+    ///
+    /// The streams those entries describe are missing entirely.
+    pub missing_directory_entries: u32,
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::serialize::symbol_stats")
+    )]
     pub symbol_stats: HashMap<String, SymbolStats>,
+    /// Whether processing gave up early because
+    /// [`ProcessorOptions::deadline`][crate::ProcessorOptions::deadline]
+    /// passed or it was cancelled, so stacks and symbols may be missing.
+    pub timed_out: bool,
+    /// How long each part of processing took, if
+    /// [`ProcessorOptions::record_timings`][crate::ProcessorOptions::record_timings]
+    /// was set.
+    pub timings: Option<ProcessTimings>,
+    /// What the minidump was missing that processing made do without, if
+    /// [`ProcessorOptions::never_fail`][crate::ProcessorOptions::never_fail]
+    /// was set. The results are only as good as what was left.
+    pub degradations: Vec<Degradation>,
 }
 
 impl FrameTrust {
@@ -206,6 +744,7 @@ impl FrameTrust {
     pub fn description(&self) -> &'static str {
         match *self {
             FrameTrust::Context => "given as instruction pointer in context",
+            FrameTrust::Inlined => "inlining",
             FrameTrust::PreWalked => "recovered by external stack walker",
             FrameTrust::CallFrameInfo => "call frame info",
             FrameTrust::CfiScan => "call frame info with scanning",
@@ -215,33 +754,61 @@ impl FrameTrust {
         }
     }
 
-    fn json_name(&self) -> &'static str {
+    pub(crate) fn json_name(&self) -> &'static str {
         match *self {
             FrameTrust::Context => "context",
+            FrameTrust::Inlined => "inlined",
             FrameTrust::PreWalked => "prewalked",
             FrameTrust::CallFrameInfo => "cfi",
             FrameTrust::CfiScan => "cfi_scan",
             FrameTrust::FramePointer => "frame_pointer",
             FrameTrust::Scan => "scan",
-            FrameTrust::None => "non",
+            FrameTrust::None => "none",
         }
     }
 }
 
+/// The CPSR bit that's set while an ARM CPU is running Thumb code.
+const ARM_CPSR_THUMB: u32 = 1 << 5;
+
 impl StackFrame {
     /// Create a `StackFrame` from a `MinidumpContext`.
     pub fn from_context(context: MinidumpContext, trust: FrameTrust) -> StackFrame {
+        let mut instruction = context.get_instruction_pointer();
+        let mut is_thumb = false;
+        if let MinidumpRawContext::Arm(ref ctx) = context.raw {
+            // Return addresses into Thumb code have their lowest bit set, while
+            // the context frame's pc is exact and its instruction set is in
+            // the CPSR.
+            if trust == FrameTrust::Context {
+                is_thumb = ctx.cpsr & ARM_CPSR_THUMB != 0;
+            } else {
+                is_thumb = instruction & 1 != 0;
+                instruction &= !1;
+            }
+        }
         StackFrame {
-            instruction: context.get_instruction_pointer(),
+            instruction,
+            resume_address: instruction,
             module: None,
             unloaded_modules: BTreeMap::new(),
+            symbolicated_unloaded_module: None,
+            function_from_exports: false,
             function_name: None,
             function_base: None,
             parameter_size: None,
             source_file_name: None,
             source_line: None,
             source_line_base: None,
+            source_url: None,
+            other_source_lines: Vec::new(),
+            source_context: None,
             trust,
+            is_jit: false,
+            is_thumb,
+            repeated: None,
+            gap: None,
+            unwind_diagnostics: None,
             context,
         }
     }
@@ -249,7 +816,7 @@ impl StackFrame {
     /// Return the actual return address, as saved on the stack or in a
     /// register. See the comments for `StackFrame::instruction` for details.
     pub fn return_address(&self) -> u64 {
-        self.instruction
+        self.resume_address
     }
 }
 
@@ -267,15 +834,87 @@ impl FrameSymbolizer for StackFrame {
         self.source_line = Some(line);
         self.source_line_base = Some(base);
     }
+    fn set_source_url(&mut self, url: &str) {
+        self.source_url = Some(String::from(url));
+    }
+    fn add_source_line(&mut self, file: &str, line: u32) {
+        self.other_source_lines.push((String::from(file), line));
+    }
 }
 
-fn basename(f: &str) -> &str {
+/// `s`, with the separator of the machine-readable format replaced.
+fn strip_separator(s: &str) -> Cow<'_, str> {
+    if s.contains('|') {
+        Cow::Owned(s.replace('|', "_"))
+    } else {
+        Cow::Borrowed(s)
+    }
+}
+
+pub(crate) fn basename(f: &str) -> &str {
     match f.rfind(|c| c == '/' || c == '\\') {
         None => f,
         Some(index) => &f[(index + 1)..],
     }
 }
 
+/// A short description of a thread's last error, like WinDbg's `!gle` gives.
+fn last_error_description(error: CrashReason) -> Option<&'static str> {
+    match error {
+        CrashReason::WindowsWinError(error) => error.description(),
+        _ => None,
+    }
+}
+
+/// Writes where in its module `frame` is, as `!function [file : line + offset]`,
+/// or just the offset from `module_base` without symbols.
+fn print_frame_symbol<T: Write>(f: &mut T, frame: &StackFrame, module_base: u64) -> io::Result<()> {
+    let addr = frame.instruction;
+    if let (&Some(ref function), &Some(ref function_base)) =
+        (&frame.function_name, &frame.function_base)
+    {
+        write!(f, "!{}", function)?;
+        if let (&Some(ref source_file), &Some(ref source_line), &Some(ref source_line_base)) = (
+            &frame.source_file_name,
+            &frame.source_line,
+            &frame.source_line_base,
+        ) {
+            write!(f, " [{} : {}", basename(source_file), source_line)?;
+            print_other_source_lines(f, frame, source_file)?;
+            write!(f, " + {:#x}]", addr - source_line_base)?;
+        } else {
+            write!(f, " + {:#x}", addr - function_base)?;
+        }
+    } else {
+        write!(f, " + {:#x}", addr - module_base)?;
+    }
+    Ok(())
+}
+
+/// Writes the other lines `frame` is from, like ` (also 98, foo.h:12)`,
+/// with the file only for lines that aren't in `source_file`.
+fn print_other_source_lines<T: Write>(
+    f: &mut T,
+    frame: &StackFrame,
+    source_file: &str,
+) -> io::Result<()> {
+    if frame.other_source_lines.is_empty() {
+        return Ok(());
+    }
+    let lines = frame
+        .other_source_lines
+        .iter()
+        .map(|(file, line)| {
+            if file == source_file {
+                line.to_string()
+            } else {
+                format!("{}:{}", basename(file), line)
+            }
+        })
+        .collect::<Vec<_>>();
+    write!(f, " (also {})", lines.join(", "))
+}
+
 fn print_registers<T: Write>(f: &mut T, ctx: &MinidumpContext) -> io::Result<()> {
     let registers: Cow<HashSet<&str>> = match ctx.valid {
         MinidumpContextValidity::All => {
@@ -335,6 +974,13 @@ impl CallStack {
             thread_id: id,
             thread_name: None,
             last_error_value: None,
+            recursion_collapsed: false,
+            context: None,
+            priority_class: 0,
+            priority: 0,
+            kernel_time: None,
+            user_time: None,
+            start_address: None,
         }
     }
 
@@ -343,42 +989,73 @@ impl CallStack {
     /// This is very verbose, it implements the output format used by
     /// minidump_stackwalk.
     pub fn print<T: Write>(&self, f: &mut T) -> io::Result<()> {
+        self.print_frames(f, true)
+    }
+
+    fn print_frames<T: Write>(&self, f: &mut T, registers: bool) -> io::Result<()> {
+        if let Some(start_address) = self.start_address {
+            writeln!(
+                f,
+                "Start address: {:#x}, priority: {}",
+                start_address, self.priority
+            )?;
+        }
+        if let (Some(kernel_time), Some(user_time)) = (self.kernel_time, self.user_time) {
+            writeln!(
+                f,
+                "CPU time: {:.3}s kernel, {:.3}s user",
+                kernel_time.as_secs_f64(),
+                user_time.as_secs_f64()
+            )?;
+        }
+        if let Some(error) = self.last_error_value {
+            write!(f, "Last error: {}", error)?;
+            if let Some(description) = last_error_description(error) {
+                write!(f, " - {}", description)?;
+            }
+            writeln!(f)?;
+        }
         if self.frames.is_empty() {
             writeln!(f, "<no frames>")?;
+            if registers {
+                if let Some(ref context) = self.context {
+                    print_registers(f, context)?;
+                }
+            }
         }
         for (i, frame) in self.frames.iter().enumerate() {
             let addr = frame.instruction;
             write!(f, "{:2}  ", i)?;
+            if let Some(repeated) = frame.repeated {
+                writeln!(
+                    f,
+                    "... {} frames omitted, repeating the {} frames above",
+                    repeated.count, repeated.period
+                )?;
+                continue;
+            }
+            if let Some(gap) = frame.gap {
+                writeln!(
+                    f,
+                    "... {} bytes of stack skipped, from {:#x} to {:#x}",
+                    gap.end.saturating_sub(gap.start),
+                    gap.start,
+                    gap.end
+                )?;
+                continue;
+            }
             if let Some(ref module) = frame.module {
                 write!(f, "{}", basename(&module.code_file()))?;
-                if let (&Some(ref function), &Some(ref function_base)) =
-                    (&frame.function_name, &frame.function_base)
-                {
-                    write!(f, "!{}", function)?;
-                    if let (
-                        &Some(ref source_file),
-                        &Some(ref source_line),
-                        &Some(ref source_line_base),
-                    ) = (
-                        &frame.source_file_name,
-                        &frame.source_line,
-                        &frame.source_line_base,
-                    ) {
-                        write!(
-                            f,
-                            " [{} : {} + {:#x}]",
-                            basename(source_file),
-                            source_line,
-                            addr - source_line_base
-                        )?;
-                    } else {
-                        write!(f, " + {:#x}", addr - function_base)?;
-                    }
-                } else {
-                    write!(f, " + {:#x}", addr - module.base_address())?;
-                }
+                print_frame_symbol(f, frame, module.base_address())?;
+            } else if let Some(ref module) = frame.symbolicated_unloaded_module {
+                // Make it obvious these symbols came from an unloaded module.
+                write!(f, "(unloaded) {}", basename(&module.code_file()))?;
+                print_frame_symbol(f, frame, module.base_address())?;
             } else {
                 write!(f, "{:#x}", addr)?;
+                if frame.is_jit {
+                    write!(f, " (jit code)")?;
+                }
 
                 // List off overlapping unloaded modules.
 
@@ -401,8 +1078,172 @@ impl CallStack {
                 }
             }
             writeln!(f)?;
-            print_registers(f, &frame.context)?;
+            if let Some(ref url) = frame.source_url {
+                writeln!(f, "    Source: {}", url)?;
+            }
+            if let Some(ref context) = frame.source_context {
+                context.print(f, frame.source_line)?;
+            }
+            // Inlined frames share the registers of the physical frame below them.
+            if registers && frame.trust != FrameTrust::Inlined {
+                print_registers(f, &frame.context)?;
+            }
             writeln!(f, "    Found by: {}", frame.trust.description())?;
+            if let Some(ref diagnostics) = frame.unwind_diagnostics {
+                diagnostics.print(f)?;
+            }
+        }
+        if self.info == CallStackInfo::FrameLimitReached {
+            writeln!(f, "<truncated at the frame limit>")?;
+        }
+        if self.info == CallStackInfo::WalkStopped {
+            writeln!(f, "<stopped by the frame observer>")?;
+        }
+        if self.info == CallStackInfo::TimedOut {
+            writeln!(f, "<stopped at the deadline>")?;
+        }
+        if self.info == CallStackInfo::StackMemoryLimited {
+            writeln!(f, "<stack cut down to the memory limit>")?;
+        }
+        Ok(())
+    }
+
+    /// The JSON of this thread in the `threads` of
+    /// [`ProcessState::to_json_with_options`], with addresses formatted by
+    /// `json_hex`.
+    pub(crate) fn to_json(
+        &self,
+        json_hex: impl Fn(u64) -> String,
+        options: &JsonOptions,
+    ) -> serde_json::Value {
+        let mut output = json!({
+            "frame_count": self.frames.len(),
+            // optional
+            "last_error_value": self.last_error_value.map(|error| error.to_string()),
+            // optional
+            "last_error_description": self.last_error_value.and_then(last_error_description),
+            // optional
+            "thread_name": self.thread_name,
+            "recursion_collapsed": self.recursion_collapsed,
+            // optional
+            "registers": self.context.as_ref().map(json_registers),
+            "priority_class": self.priority_class,
+            "priority": self.priority,
+            // optional
+            "kernel_time_ms": self.kernel_time.map(|time| time.as_millis() as u64),
+            // optional
+            "user_time_ms": self.user_time.map(|time| time.as_millis() as u64),
+            // optional
+            "start_address": self.start_address.map(&json_hex),
+            "frames": self.frames.iter().enumerate().map(|(idx, frame)| {
+                // temporary hack: grab the first matching unloaded module
+                // (or the one we got symbols from) and pretend it's a real module.
+                let module_info = frame.module.as_ref().map(|module| {
+                    (basename(&module.name), frame.instruction - module.raw.base_of_image)
+                }).or_else(|| frame.symbolicated_unloaded_module.as_ref().map(|module| {
+                    (&*module.name, frame.instruction - module.raw.base_of_image)
+                })).or_else(|| frame.unloaded_modules.iter().next().and_then(|(name, offsets)| offsets.iter().next().map(|offset| {
+                    (&**name, *offset)
+                })));
+                json!({
+                    "frame": idx,
+                    // optional
+                    "module": module_info.map(|(name, _)| name),
+                    // optional
+                    "function": frame.function_name,
+                    // optional
+                    "file": frame.source_file_name,
+                    // optional
+                    "line": frame.source_line,
+                    // optional
+                    "source_url": frame.source_url,
+                    // optional
+                    "other_lines": Some(&frame.other_source_lines)
+                        .filter(|lines| !lines.is_empty())
+                        .map(|lines| lines.iter().map(|(file, line)| json!({
+                            "file": file,
+                            "line": line,
+                        })).collect::<Vec<_>>()),
+                    // optional
+                    "source_context": frame.source_context.as_ref().map(|context| json!({
+                        "start_line": context.start_line,
+                        "lines": context.lines,
+                    })),
+                    "offset": json_hex(frame.instruction),
+                    "resume_address": json_hex(frame.resume_address),
+                    // optional
+                    "module_offset": module_info
+                        .map(|(_, offset)| offset)
+                        .map(&json_hex),
+                    // optional
+                    "function_offset": frame
+                        .function_base
+                        .map(|func_base| frame.instruction - func_base)
+                        .map(&json_hex),
+                    "missing_symbols": frame.function_name.is_none(),
+                    // none | scan | cfi_scan | frame_pointer | cfi | context | prewalked | inlined
+                    "trust": frame.trust.json_name(),
+                    "jit": frame.is_jit,
+                    // Whether `module` was unloaded before the crash.
+                    "unloaded": frame.module.is_none() && module_info.is_some(),
+                    // optional
+                    "repeated": frame.repeated.map(|repeated| json!({
+                        "count": repeated.count,
+                        "period": repeated.period,
+                    })),
+                    // optional
+                    "gap": frame.gap.map(|gap| json!({
+                        "start": json_hex(gap.start),
+                        "end": json_hex(gap.end),
+                    })),
+                    // optional
+                    "unwind_diagnostics": frame
+                        .unwind_diagnostics
+                        .as_ref()
+                        .map(|diagnostics| diagnostics.to_json(&json_hex)),
+                })
+            }).collect::<Vec<_>>(),
+        });
+
+        if !options.extended {
+            remove_keys(&mut output, EXTENDED_THREAD_KEYS);
+            for frame in output["frames"].as_array_mut().unwrap() {
+                remove_keys(frame, EXTENDED_FRAME_KEYS);
+            }
+        }
+        output
+    }
+
+    /// Write a line for each frame in the format of
+    /// [`ProcessState::print_machine_readable`], as thread number `thread`.
+    fn print_machine_readable<T: Write>(&self, f: &mut T, thread: usize) -> io::Result<()> {
+        for (i, frame) in self.frames.iter().enumerate() {
+            write!(f, "{}|{}|", thread, i)?;
+            let address = frame.return_address();
+            let module = match frame.module {
+                Some(ref module) => module,
+                None => {
+                    writeln!(f, "||||{:#x}", address)?;
+                    continue;
+                }
+            };
+            write!(f, "{}|", strip_separator(basename(&module.code_file())))?;
+            match (&frame.function_name, frame.function_base) {
+                (Some(function), Some(function_base)) => {
+                    write!(f, "{}|", strip_separator(function))?;
+                    match (&frame.source_file_name, frame.source_line) {
+                        (Some(file), Some(line)) => writeln!(
+                            f,
+                            "{}|{}|{:#x}",
+                            strip_separator(file),
+                            line,
+                            address.wrapping_sub(frame.source_line_base.unwrap_or(address)),
+                        )?,
+                        _ => writeln!(f, "||{:#x}", address.wrapping_sub(function_base))?,
+                    }
+                }
+                _ => writeln!(f, "|||{:#x}", address.wrapping_sub(module.base_address()))?,
+            }
         }
         Ok(())
     }
@@ -415,27 +1256,292 @@ fn eq_some<T: PartialEq>(opt: Option<T>, val: T) -> bool {
     }
 }
 
+/// Which parts of the process state [`ProcessState::print_human`] writes.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct HumanOptions {
+    /// Whether to include the threads other than the one that requested the
+    /// dump.
+    pub other_threads: bool,
+    /// Whether to include the registers of each frame.
+    pub registers: bool,
+    /// Whether to include the signature of the crash (see
+    /// [`ProcessState::signature`]).
+    pub signature: bool,
+    /// Whether to include what kind of address the crash was at (see
+    /// [`ProcessState::crash_address_class`]) and what the crashing thread's
+    /// registers point to (see [`ProcessState::register_targets`]).
+    pub crash_details: bool,
+    /// Whether to include the lists of loaded and unloaded modules.
+    pub modules: bool,
+    /// Whether to include the streams the minidump has that we couldn't
+    /// read, and any truncation.
+    pub streams: bool,
+}
+
+impl HumanOptions {
+    /// Only the summary at the top and a backtrace of the requesting thread,
+    /// like [`ProcessState::print_brief`].
+    pub fn brief() -> Self {
+        Self {
+            other_threads: false,
+            registers: true,
+            signature: false,
+            crash_details: false,
+            modules: false,
+            streams: false,
+        }
+    }
+}
+
+impl Default for HumanOptions {
+    /// Everything but the signature and crash details, like
+    /// [`ProcessState::print`].
+    fn default() -> Self {
+        Self {
+            other_threads: true,
+            registers: true,
+            signature: false,
+            crash_details: false,
+            modules: true,
+            streams: true,
+        }
+    }
+}
+
+/// Which keys [`ProcessState::to_json_with_options`] outputs.
+#[derive(Debug, Clone, Default)]
+#[non_exhaustive]
+pub struct JsonOptions {
+    /// Whether to include the keys added after the stable schema (see the
+    /// top level documentation of this library), which tools written against
+    /// it don't expect. Off by default.
+    pub extended: bool,
+}
+
+impl JsonOptions {
+    /// Every key, including the ones added after the stable schema.
+    pub fn extended() -> Self {
+        Self { extended: true }
+    }
+}
+
+/// The keys of the JSON report that aren't in the stable schema, and so are
+/// only output with [`JsonOptions::extended`].
+const EXTENDED_KEYS: &[&str] = &[
+    "signature",
+    "crash_hash",
+    "environment",
+    "proc_status",
+    "process_uptime",
+    "process_security",
+    "timed_out",
+    "annotations",
+    "crashpad_annotations",
+    "timings",
+    "degradations",
+    "handles",
+    "suspicious_modules",
+];
+const EXTENDED_SYSTEM_INFO_KEYS: &[&str] = &[
+    "cpu_vendor",
+    "cpu_family",
+    "cpu_model",
+    "cpu_stepping",
+    "cpu_model_name",
+    "cpu_physical_core_count",
+    "cpu_package_count",
+    "cpu_features",
+];
+const EXTENDED_CRASH_INFO_KEYS: &[&str] = &[
+    "address_class",
+    "crashing_thread_guessed",
+    "assertion_info",
+    "disassembly",
+    "possible_bit_flips",
+    "register_targets",
+    "stack_overflow",
+];
+const EXTENDED_SENSITIVE_KEYS: &[&str] = &["memory"];
+const EXTENDED_THREAD_KEYS: &[&str] = &[
+    "last_error_description",
+    "registers",
+    "priority_class",
+    "priority",
+    "kernel_time_ms",
+    "user_time_ms",
+    "start_address",
+];
+const EXTENDED_FRAME_KEYS: &[&str] = &["other_lines", "source_context", "gap"];
+
+fn remove_keys(value: &mut serde_json::Value, keys: &[&str]) {
+    let object = value.as_object_mut().unwrap();
+    for key in keys {
+        object.remove(*key);
+    }
+}
+
 impl ProcessState {
     /// `true` if the minidump was written in response to a process crash.
     pub fn crashed(&self) -> bool {
         self.crash_reason.is_some() && self.crash_address.is_some()
     }
+    /// `true` if the minidump appears to have been cut off while it was being written.
+    pub fn is_truncated(&self) -> bool {
+        self.missing_directory_entries != 0 || !self.truncated_streams.is_empty()
+    }
+    /// The signature of the crash, for grouping it with others that
+    /// happened in the same place.
+    ///
+    /// See the [`signature`][crate::signature] module for how it's made.
+    pub fn signature(&self, options: &SignatureOptions) -> String {
+        match self.requesting_thread {
+            Some(requesting_thread) => {
+                signature::signature(&self.threads[requesting_thread].frames, options)
+            }
+            None => String::from("EMPTY: no crashing thread identified"),
+        }
+    }
+
+    /// A hash of the crashing thread's first few frames, for grouping the
+    /// crash with others that happened in the same place, or `None` if
+    /// there's no crashing thread or it has no frames.
+    ///
+    /// See [`signature::crash_hash`] for how it's made.
+    pub fn crash_hash(&self, options: &SignatureOptions) -> Option<String> {
+        let requesting_thread = self.requesting_thread?;
+        signature::crash_hash(&self.threads[requesting_thread].frames, options)
+    }
+
+    /// How long the process had been running when the minidump was written,
+    /// or `None` if the minidump doesn't say when the process started.
+    pub fn process_uptime(&self) -> Option<Duration> {
+        let process_create_time = self.process_create_time?;
+        // A create time after the dump time is clock skew, not a negative uptime.
+        Some(
+            self.time
+                .duration_since(process_create_time)
+                .unwrap_or_default(),
+        )
+    }
+
+    /// How `other` differs from this state, like after processing the same
+    /// minidump again with more symbols.
+    ///
+    /// See the [`ProcessDiff`] fields for what's compared.
+    pub fn diff(&self, other: &ProcessState) -> ProcessDiff {
+        process_diff::diff(self, other)
+    }
+
     /// Write a human-readable description of the process state to `f`.
     ///
     /// This is very verbose, it implements the output format used by
     /// minidump_stackwalk.
     pub fn print<T: Write>(&self, f: &mut T) -> io::Result<()> {
-        self.print_internal(f, false)
+        self.print_human(f, &HumanOptions::default())
     }
 
     /// Write a brief human-readable description of the process state to `f`.
     ///
     /// Only includes the summary at the top and a backtrace of the crashing thread.
     pub fn print_brief<T: Write>(&self, f: &mut T) -> io::Result<()> {
-        self.print_internal(f, true)
+        self.print_human(f, &HumanOptions::brief())
+    }
+
+    /// Write the process state to `f` in Breakpad's pipe-delimited
+    /// machine-readable format (the output of `minidump_stackwalk -m`).
+    ///
+    /// This is for tools that already read that format. It has a lot less in
+    /// it than [`print_json`][ProcessState::print_json], which is preferred
+    /// for anything new. The lines are:
+    ///
+    /// ```text
+    /// OS|{os}|{os version}
+    /// CPU|{cpu}|{cpu info}|{cpu count}
+    /// Crash|{crash reason}|{crash address}|{requesting thread}
+    /// Module|{code file}|{version}|{debug file}|{debug id}|{base}|{last address}|{main}
+    ///
+    /// {thread}|{frame}|{module}|{function}|{source file}|{line}|{offset}
+    /// ```
+    ///
+    /// With a `Module` line for each module, and a frame line for each frame
+    /// of each thread, the requesting thread's first. Any `|` in the values is
+    /// replaced with `_`.
+    pub fn print_machine_readable<T: Write>(&self, f: &mut T) -> io::Result<()> {
+        let sys = &self.system_info;
+        writeln!(
+            f,
+            "OS|{}|{}",
+            strip_separator(&sys.os.long_name()),
+            strip_separator(&sys.format_os_version().unwrap_or_default()),
+        )?;
+        writeln!(
+            f,
+            "CPU|{}|{}|{}",
+            strip_separator(&sys.cpu.to_string()),
+            strip_separator(sys.cpu_info.as_deref().unwrap_or_default()),
+            sys.cpu_count,
+        )?;
+
+        match (&self.crash_reason, &self.crash_address) {
+            (Some(reason), Some(address)) => write!(
+                f,
+                "Crash|{}|{:#x}|",
+                strip_separator(&reason.to_string()),
+                address
+            )?,
+            // Without a crash, the assertion (if any) stands in for the reason.
+            _ => write!(
+                f,
+                "Crash|{}||",
+                strip_separator(
+                    &self
+                        .assertion
+                        .as_ref()
+                        .map_or_else(|| String::from("No crash"), |a| a.to_string())
+                )
+            )?,
+        }
+        match self.requesting_thread {
+            Some(requesting_thread) => writeln!(f, "{}", requesting_thread)?,
+            None => writeln!(f)?,
+        }
+
+        let main_address = self.modules.main_module().map(|m| m.base_address());
+        for module in self.modules.iter() {
+            writeln!(
+                f,
+                "Module|{}|{}|{}|{}|{:#010x}|{:#010x}|{}",
+                strip_separator(basename(&module.code_file())),
+                strip_separator(&module.version().unwrap_or_default()),
+                strip_separator(basename(&module.debug_file().unwrap_or_default())),
+                strip_separator(&module.debug_identifier().unwrap_or_default()),
+                module.base_address(),
+                module.base_address() + module.size() - 1,
+                eq_some(main_address, module.base_address()) as u8,
+            )?;
+        }
+
+        // A blank line separates the threads.
+        writeln!(f)?;
+        if let Some(requesting_thread) = self.requesting_thread {
+            self.threads[requesting_thread].print_machine_readable(f, requesting_thread)?;
+        }
+        for (i, stack) in self.threads.iter().enumerate() {
+            if !eq_some(self.requesting_thread, i) {
+                stack.print_machine_readable(f, i)?;
+            }
+        }
+        Ok(())
     }
 
-    fn print_internal<T: Write>(&self, f: &mut T, brief: bool) -> io::Result<()> {
+    /// Write a human-readable description of the process state to `f`, with
+    /// the parts `options` asks for.
+    ///
+    /// The summary at the top and the backtrace of the requesting thread are
+    /// always included. Like [`print`][ProcessState::print], the format isn't
+    /// specified, and may change.
+    pub fn print_human<T: Write>(&self, f: &mut T, options: &HumanOptions) -> io::Result<()> {
         writeln!(f, "Operating system: {}", self.system_info.os.long_name())?;
         if let Some(ref ver) = self.system_info.format_os_version() {
             writeln!(f, "                  {}", ver)?;
@@ -444,7 +1550,10 @@ impl ProcessState {
         if let Some(ref info) = self.system_info.cpu_info {
             writeln!(f, "     {}", info)?;
         }
-        writeln!(
+        if let Some(ref name) = self.system_info.cpu_model_name {
+            writeln!(f, "     {}", name)?;
+        }
+        write!(
             f,
             "     {} CPU{}",
             self.system_info.cpu_count,
@@ -454,6 +1563,23 @@ impl ProcessState {
                 ""
             }
         )?;
+        if let Some(cores) = self.system_info.cpu_physical_core_count {
+            write!(
+                f,
+                ", {} physical core{}",
+                cores,
+                if cores > 1 { "s" } else { "" }
+            )?;
+        }
+        if let Some(packages) = self.system_info.cpu_package_count {
+            write!(
+                f,
+                " in {} package{}",
+                packages,
+                if packages > 1 { "s" } else { "" }
+            )?;
+        }
+        writeln!(f)?;
         if let Some(ref lsb) = self.linux_standard_base {
             writeln!(
                 f,
@@ -461,8 +1587,54 @@ impl ProcessState {
                 lsb.id, lsb.release, lsb.codename, lsb.description
             )?;
         }
+        if let Some(ref status) = self.linux_proc_status {
+            writeln!(f, "Process status:")?;
+            if let Some(ref state) = status.state {
+                writeln!(f, "  State: {}", state)?;
+            }
+            if let Some(threads) = status.threads {
+                writeln!(f, "  Threads: {}", threads)?;
+            }
+            let sizes = [
+                ("VmPeak", status.vm_peak),
+                ("VmSize", status.vm_size),
+                ("VmRSS", status.vm_rss),
+            ];
+            for (name, size) in sizes.iter().filter_map(|&(name, size)| Some((name, size?))) {
+                writeln!(f, "  {}: {} kB", name, size / 1024)?;
+            }
+            if let Some(seccomp) = status.seccomp {
+                let name = status.seccomp_name().unwrap_or("unknown");
+                writeln!(f, "  Seccomp: {} ({})", seccomp, name)?;
+            }
+        }
+        if let Some(ref environment) = self.environment {
+            if !environment.is_empty() {
+                writeln!(f, "Environment:")?;
+                for (name, value) in environment {
+                    writeln!(f, "  {}={}", name, value)?;
+                }
+            }
+        }
+        if !self.dump_type.is_empty() {
+            writeln!(f, "Dump type: {:?}", self.dump_type)?;
+        }
+        if self.timed_out {
+            writeln!(
+                f,
+                "Processing timed out, some stacks and symbols are missing"
+            )?;
+        }
+        for degradation in &self.degradations {
+            writeln!(f, "Degraded: {}", degradation)?;
+        }
         writeln!(f)?;
 
+        // An assertion describes the crash better than the exception raised
+        // to write the minidump does.
+        if let Some(ref assertion) = self.assertion {
+            writeln!(f, "Assertion: {}", assertion)?;
+        }
         if let (&Some(ref reason), &Some(ref address)) = (&self.crash_reason, &self.crash_address) {
             write!(
                 f,
@@ -471,11 +1643,110 @@ Crash address: {:#x}
 ",
                 reason, address
             )?;
+            if options.signature {
+                writeln!(
+                    f,
+                    "Signature: {}",
+                    self.signature(&SignatureOptions::default())
+                )?;
+            }
+            if let Some(ref access) = self.crash_address_access {
+                writeln!(f, "Crash address memory: {}", access)?;
+            }
+            if let Some(ref class) = self.crash_address_class {
+                if options.crash_details {
+                    write!(f, "Crash address kind: {}", class.kind)?;
+                    if let Some(ref register) = class.register {
+                        write!(f, " (from {})", register)?;
+                    }
+                    writeln!(f)?;
+                }
+            }
+            if let Some(ref stack_overflow) = self.stack_overflow {
+                writeln!(f, "Stack overflow: {}", stack_overflow)?;
+            }
+            if options.crash_details && !self.register_targets.is_empty() {
+                writeln!(f, "Registers point to:")?;
+                for target in &self.register_targets {
+                    writeln!(
+                        f,
+                        "  {} = {:#x} -> {}",
+                        target.register, target.value, target.target
+                    )?;
+                }
+            }
+            if let Some(ref exploitability) = self.exploitability {
+                writeln!(f, "Exploitability: {}", exploitability.rating)?;
+                for (rating, evidence) in &exploitability.evidence {
+                    writeln!(f, "  {} ({})", evidence, rating)?;
+                }
+            }
+            for flip in self.possible_bit_flips.iter().flatten() {
+                writeln!(
+                    f,
+                    "Possible bit flip: {} {:#x} is {:#x} with bit {} flipped",
+                    flip.source, flip.address, flip.corrected_address, flip.bit
+                )?;
+            }
         } else {
             writeln!(f, "No crash")?;
         }
-        if let Some(ref assertion) = self.assertion {
-            writeln!(f, "Assertion: {}", assertion)?;
+        for memory in self.crash_memory.iter().flatten() {
+            write!(f, "Memory around {} {:#x}", memory.source, memory.address)?;
+            if let Some(ref access) = memory.access {
+                write!(f, " ({})", access)?;
+            }
+            writeln!(f, ":")?;
+            memory.print_hexdump(f, "  ")?;
+        }
+        if let Some(ref disassembly) = self.disassembly {
+            writeln!(f, "Disassembly:")?;
+            for (i, instruction) in disassembly.instructions.iter().enumerate() {
+                let marker = if i == disassembly.crashing_instruction {
+                    "=>"
+                } else {
+                    "  "
+                };
+                writeln!(
+                    f,
+                    "{} {:#010x}  {}",
+                    marker, instruction.address, instruction.text
+                )?;
+            }
+            if let Some(ref operand) = disassembly.memory_operand {
+                write!(f, "Faulting operand: {}", operand.text)?;
+                if let Some(address) = operand.address {
+                    write!(f, " = {:#x}", address)?;
+                }
+                writeln!(f)?;
+            }
+        }
+        if let Some(ref handles) = self.handles {
+            let total: usize = handles.counts.values().sum();
+            writeln!(f, "Open handles: {}", total)?;
+            for (type_name, count) in &handles.counts {
+                writeln!(f, "  {}: {}", type_name, count)?;
+            }
+            for type_name in &handles.suspicious_types {
+                writeln!(
+                    f,
+                    "Suspiciously many {} handles (over {}), likely leaked",
+                    type_name, SUSPICIOUS_HANDLE_COUNT
+                )?;
+            }
+            if !handles.crash_handles.is_empty() {
+                writeln!(f, "Handles involved in the crash:")?;
+                for handle in &handles.crash_handles {
+                    writeln!(
+                        f,
+                        "  {:#x} {} {} ({})",
+                        handle.handle,
+                        handle.type_name.as_deref().unwrap_or(""),
+                        handle.object_name.as_deref().unwrap_or(""),
+                        handle.reason
+                    )?;
+                }
+            }
         }
         if let Some(ref info) = self.mac_crash_info {
             writeln!(f, "Mac Crash Info:")?;
@@ -509,13 +1780,67 @@ Crash address: {:#x}
             }
             writeln!(f)?;
         }
-        if let Some(ref time) = self.process_create_time {
-            let uptime = self.time.duration_since(*time).unwrap_or_default();
+        if let Some(uptime) = self.process_uptime() {
             writeln!(f, "Process uptime: {} seconds", uptime.as_secs())?;
         } else {
             writeln!(f, "Process uptime: not available")?;
         }
+        if let Some(ref security) = self.process_security {
+            writeln!(f, "Process security: {}", security)?;
+        }
         writeln!(f)?;
+        if !self.annotations.is_empty() {
+            writeln!(f, "Annotations:")?;
+            for (key, value) in self.annotations.iter() {
+                writeln!(f, "  {}: {}", key, value)?;
+            }
+            writeln!(f)?;
+        }
+        if let Some(ref crashpad) = self.crashpad_annotations {
+            if !crashpad.is_empty() {
+                writeln!(f, "Crashpad annotations:")?;
+                for (key, value) in crashpad.simple_annotations.iter() {
+                    writeln!(f, "  {}: {}", key, value)?;
+                }
+                for module in crashpad.modules.iter() {
+                    match module.module {
+                        Some(ref name) => writeln!(f, "  {}:", basename(name))?,
+                        None => writeln!(f, "  module {}:", module.module_index)?,
+                    }
+                    for value in module.list_annotations.iter() {
+                        writeln!(f, "    {}", value)?;
+                    }
+                    for (key, value) in module
+                        .simple_annotations
+                        .iter()
+                        .chain(module.annotation_objects.iter())
+                    {
+                        writeln!(f, "    {}: {}", key, value)?;
+                    }
+                }
+                writeln!(f)?;
+            }
+        }
+        if let Some(ref timings) = self.timings {
+            let ms = |time: Duration| time.as_millis();
+            writeln!(f, "Processing took {} ms:", ms(timings.total))?;
+            writeln!(f, "  reading streams: {} ms", ms(timings.read_streams))?;
+            writeln!(
+                f,
+                "  prefetching symbols: {} ms",
+                ms(timings.prefetch_symbols)
+            )?;
+            for (i, &time) in timings.threads.iter().enumerate() {
+                writeln!(f, "  thread {}: {} ms", i, ms(time))?;
+            }
+            for (module, &time) in timings.symbols.iter() {
+                writeln!(f, "  symbols for {}: {} ms", basename(module), ms(time))?;
+            }
+            for (name, time) in timings.analysis_passes.iter() {
+                writeln!(f, "  {} analysis: {} ms", name, ms(*time))?;
+            }
+            writeln!(f)?;
+        }
 
         if let Some(requesting_thread) = self.requesting_thread {
             let stack = &self.threads[requesting_thread];
@@ -526,25 +1851,38 @@ Crash address: {:#x}
                 stack.thread_name.as_deref().unwrap_or(""),
                 if self.crashed() {
                     "crashed"
+                } else if self.requesting_thread_guessed {
+                    "guessed, did not crash"
                 } else {
                     "requested dump, did not crash"
                 }
             )?;
-            stack.print(f)?;
+            stack.print_frames(f, options.registers)?;
             writeln!(f)?;
         }
 
-        // We're done if this is a brief report!
-        if brief {
-            return Ok(());
+        if options.other_threads {
+            self.print_other_threads(f, options)?;
         }
+        if options.modules {
+            self.print_modules(f)?;
+        }
+        if options.streams {
+            self.print_streams(f)?;
+        }
+        Ok(())
+    }
 
+    fn print_other_threads<T: Write>(&self, f: &mut T, options: &HumanOptions) -> io::Result<()> {
         for (i, stack) in self.threads.iter().enumerate() {
             if eq_some(self.requesting_thread, i) {
                 // Don't print the requesting thread again,
                 continue;
             }
-            if stack.info == CallStackInfo::DumpThreadSkipped {
+            if matches!(
+                stack.info,
+                CallStackInfo::DumpThreadSkipped | CallStackInfo::ThreadSkipped
+            ) {
                 continue;
             }
             writeln!(
@@ -553,8 +1891,12 @@ Crash address: {:#x}
                 i,
                 stack.thread_name.as_deref().unwrap_or("")
             )?;
-            stack.print(f)?;
+            stack.print_frames(f, options.registers)?;
         }
+        Ok(())
+    }
+
+    fn print_modules<T: Write>(&self, f: &mut T) -> io::Result<()> {
         write!(
             f,
             "
@@ -603,6 +1945,30 @@ Unloaded modules:
             }
             writeln!(f)?;
         }
+        if let Some(ref suspicious_modules) = self.suspicious_modules {
+            if !suspicious_modules.is_empty() {
+                write!(
+                    f,
+                    "
+Suspicious modules:
+"
+                )?;
+            }
+            for module in suspicious_modules {
+                writeln!(
+                    f,
+                    "{:#010x} - {:#010x}  {}: {}",
+                    module.base_address,
+                    module.base_address + module.size.max(1) - 1,
+                    module.code_file.as_deref().map_or("???", basename),
+                    module.reason
+                )?;
+            }
+        }
+        Ok(())
+    }
+
+    fn print_streams<T: Write>(&self, f: &mut T) -> io::Result<()> {
         if !self.unimplemented_streams.is_empty() {
             write!(
                 f,
@@ -636,6 +2002,32 @@ Unknown streams encountered:
                 )?;
             }
         }
+        if self.is_truncated() {
+            write!(
+                f,
+                "
+Minidump is truncated:
+"
+            )?;
+            if self.missing_directory_entries != 0 {
+                writeln!(
+                    f,
+                    "{} stream directory entries missing",
+                    self.missing_directory_entries
+                )?;
+            }
+            for stream in &self.truncated_streams {
+                writeln!(
+                    f,
+                    "Stream 0x{:08x} ({}) @ 0x{:08x}: {} of {} bytes",
+                    stream.stream_type,
+                    stream.vendor,
+                    stream.location.rva,
+                    stream.available_size,
+                    stream.location.data_size,
+                )?;
+            }
+        }
         Ok(())
     }
 
@@ -643,6 +2035,36 @@ Unknown streams encountered:
     ///
     /// See the top level documentation of this library for the stable JSON schema.
     pub fn print_json<T: Write>(&self, f: &mut T, pretty: bool) -> Result<(), serde_json::Error> {
+        self.print_json_with_options(f, pretty, &JsonOptions::default())
+    }
+
+    /// Like [`print_json`][ProcessState::print_json], but with the keys
+    /// `options` asks for.
+    pub fn print_json_with_options<T: Write>(
+        &self,
+        f: &mut T,
+        pretty: bool,
+        options: &JsonOptions,
+    ) -> Result<(), serde_json::Error> {
+        let output = self.to_json_with_options(options);
+        if pretty {
+            serde_json::to_writer_pretty(f, &output)
+        } else {
+            serde_json::to_writer(f, &output)
+        }
+    }
+
+    /// The JSON [`print_json`][ProcessState::print_json] outputs, for
+    /// processing it further without having to parse it again.
+    ///
+    /// See the top level documentation of this library for the stable JSON schema.
+    pub fn to_json(&self) -> serde_json::Value {
+        self.to_json_with_options(&JsonOptions::default())
+    }
+
+    /// Like [`to_json`][ProcessState::to_json], but with the keys `options`
+    /// asks for.
+    pub fn to_json_with_options(&self, options: &JsonOptions) -> serde_json::Value {
         // See ../json-schema.md for details on this format.
 
         let sys = &self.system_info;
@@ -661,17 +2083,104 @@ Unknown streams encountered:
                 // x86 | amd64 | arm | ppc | sparc
                 "cpu_arch": sys.cpu.to_string(),
                 "cpu_info": sys.cpu_info,
+                // optional
+                "cpu_vendor": sys.cpu_vendor,
+                // optional, x86 and amd64 only
+                "cpu_family": sys.cpu_family,
+                "cpu_model": sys.cpu_model,
+                "cpu_stepping": sys.cpu_stepping,
+                // optional
+                "cpu_model_name": sys.cpu_model_name,
                 "cpu_count": sys.cpu_count,
                 // optional
+                "cpu_physical_core_count": sys.cpu_physical_core_count,
+                "cpu_package_count": sys.cpu_package_count,
+                // optional
                 "cpu_microcode_version": sys.cpu_microcode_version,
+                "cpu_features": sys.cpu_features,
             },
             "crash_info": {
                 "type": self.crash_reason.map(|reason| reason.to_string()),
                 "address": self.crash_address.map(json_hex),
+                // optional
+                "address_access": self.crash_address_access.map(memory_access_json),
+                // optional
+                "address_class": self.crash_address_class.as_ref().map(|class| json!({
+                    "kind": class.kind.json_name(),
+                    "register": class.register,
+                })),
                 // thread index | null
                 "crashing_thread": self.requesting_thread,
-                "assertion": self.assertion,
+                // Whether `crashing_thread` was guessed, as the minidump didn't say.
+                "crashing_thread_guessed": self.requesting_thread_guessed,
+                "assertion": self.assertion.as_ref().map(|assertion| assertion.to_string()),
+                // optional
+                "assertion_info": self.assertion.as_ref().map(|assertion| json!({
+                    "kind": assertion.kind.json_name(),
+                    "expression": assertion.expression,
+                    "function": assertion.function,
+                    "file": assertion.file,
+                    "line": assertion.line,
+                })),
+                // optional
+                "disassembly": self.disassembly.as_ref().map(|disassembly| json!({
+                    "instructions": disassembly.instructions.iter().map(|instruction| json!({
+                        "address": json_hex(instruction.address),
+                        "bytes": instruction
+                            .bytes
+                            .iter()
+                            .map(|byte| format!("{:02x}", byte))
+                            .collect::<String>(),
+                        "text": instruction.text,
+                    })).collect::<Vec<_>>(),
+                    "crashing_instruction": disassembly.crashing_instruction,
+                    // optional
+                    "memory_operand": disassembly.memory_operand.as_ref().map(|operand| json!({
+                        "text": operand.text,
+                        // optional
+                        "address": operand.address.map(json_hex),
+                    })),
+                })),
+                // optional
+                "possible_bit_flips": self.possible_bit_flips.as_ref().map(|flips| {
+                    flips.iter().map(|flip| json!({
+                        "source": flip.source.json_name(),
+                        "address": json_hex(flip.address),
+                        "corrected_address": json_hex(flip.corrected_address),
+                        "bit": flip.bit,
+                    })).collect::<Vec<_>>()
+                }),
+                "register_targets": self.register_targets.iter().map(|target| {
+                    let (module, module_offset) = match target.target {
+                        PointerTarget::Module { ref name, offset } => {
+                            (Some(name), Some(json_hex(offset)))
+                        }
+                        _ => (None, None),
+                    };
+                    let thread = match target.target {
+                        PointerTarget::Stack { thread } => Some(thread),
+                        _ => None,
+                    };
+                    json!({
+                        "register": target.register,
+                        "value": json_hex(target.value),
+                        "target": target.target.json_name(),
+                        // optional
+                        "module": module,
+                        // optional
+                        "module_offset": module_offset,
+                        // optional
+                        "thread": thread,
+                    })
+                }).collect::<Vec<_>>(),
+                // optional
+                "stack_overflow": self.stack_overflow.map(|overflow| overflow.json_name()),
             },
+            "signature": self.signature(&SignatureOptions::default()),
+            // optional
+            "crash_hash": self.crash_hash(&SignatureOptions::default()),
+            // optional
+            "environment": self.environment,
             // optional
             "lsb_release": self.linux_standard_base.as_ref().map(|lsb| json!({
                 "id": lsb.id,
@@ -679,6 +2188,15 @@ Unknown streams encountered:
                 "codename": lsb.codename,
                 "description": lsb.description,
             })),
+            // optional, sizes are in bytes
+            "proc_status": self.linux_proc_status.as_ref().map(|status| json!({
+                "state": status.state,
+                "threads": status.threads,
+                "vm_peak": status.vm_peak,
+                "vm_size": status.vm_size,
+                "vm_rss": status.vm_rss,
+                "seccomp": status.seccomp,
+            })),
             // optional
             "mac_crash_info": self.mac_crash_info.as_ref().map(|info| json!({
                 "num_records": info.len(),
@@ -722,6 +2240,13 @@ Unknown streams encountered:
                     "filename": &name,
                     "code_id": module.code_identifier(),
                     "version": module.version(),
+                    "version_info": module.version_info().map(|info| json!({
+                        "file_version": info.file_version.to_string(),
+                        "product_version": info.product_version.to_string(),
+                        "file_flags": format!("{:#010x}", info.file_flags.bits()),
+                        "file_os": format!("{:#010x}", info.file_os),
+                        "file_type": format!("{:#010x}", info.file_type),
+                    })),
                     "cert_subject": self.cert_info.get(name),
 
                     // These are all just metrics for debugging minidump-processor's execution
@@ -737,47 +2262,79 @@ Unknown streams encountered:
                 })
             }).collect::<Vec<_>>(),
             "pid": self.process_id,
-            "thread_count": self.threads.len(),
-            "threads": self.threads.iter().map(|thread| json!({
-                "frame_count": thread.frames.len(),
+            // optional, in seconds
+            "process_uptime": self.process_uptime().map(|uptime| uptime.as_secs()),
+            // optional, only in Windows minidumps
+            "process_security": self.process_security.as_ref().map(|security| json!({
+                // optional, the name of the level, or its RID in hex for
+                // levels without one
+                "integrity_level": security.integrity_level.map(|level| {
+                    level
+                        .json_name()
+                        .map(String::from)
+                        .unwrap_or_else(|| format!("{:#x}", level.rid()))
+                }),
+                // optional, below medium integrity
+                "sandboxed": security.integrity_level.map(IntegrityLevel::is_sandboxed),
+                // optional, high integrity or above
+                "elevated": security.integrity_level.map(IntegrityLevel::is_elevated),
                 // optional
-                "last_error_value": thread.last_error_value.map(|error| error.to_string()),
+                "protected_process": security.protected_process,
                 // optional
-                "thread_name": thread.thread_name,
-                "frames": thread.frames.iter().enumerate().map(|(idx, frame)| {
-                    // temporary hack: grab the first matching unloaded module
-                    // and pretend it's a real module.
-                    let module_info = frame.module.as_ref().map(|module| {
-                        (basename(&module.name), frame.instruction - module.raw.base_of_image)
-                    }).or_else(|| frame.unloaded_modules.iter().next().and_then(|(name, offsets)| offsets.iter().next().map(|offset| {
-                        (&**name, *offset)
-                    })));
-                    json!({
-                        "frame": idx,
-                        // optional
-                        "module": module_info.map(|(name, _)| name),
-                        // optional
-                        "function": frame.function_name,
-                        // optional
-                        "file": frame.source_file_name,
-                        // optional
-                        "line": frame.source_line,
-                        "offset": json_hex(frame.instruction),
-                        // optional
-                        "module_offset": module_info
-                            .map(|(_, offset)| offset)
-                            .map(json_hex),
-                        // optional
-                        "function_offset": frame
-                            .function_base
-                            .map(|func_base| frame.instruction - func_base)
-                            .map(json_hex),
-                        "missing_symbols": frame.function_name.is_none(),
-                        // none | scan | cfi_scan | frame_pointer | cfi | context | prewalked
-                        "trust": frame.trust.json_name(),
-                    })
-                }).collect::<Vec<_>>(),
-            })).collect::<Vec<_>>(),
+                "dep": security.dep.map(|dep| json!({
+                    "enabled": dep.enabled,
+                    "permanent": dep.permanent,
+                    "atl_thunk_emulation": dep.atl_thunk_emulation,
+                })),
+            })),
+            "thread_count": self.threads.len(),
+            "threads": self.threads.iter().map(|thread| thread.to_json(json_hex, options)).collect::<Vec<_>>(),
+            // Whether processing gave up early, leaving stacks and symbols missing
+            "timed_out": self.timed_out,
+            // key => value, from outside the minidump
+            "annotations": self.annotations,
+            // optional, from the Crashpad info stream
+            "crashpad_annotations": self.crashpad_annotations.as_ref().map(|crashpad| json!({
+                // key => value, for the whole process
+                "simple_annotations": crashpad.simple_annotations,
+                "modules": crashpad.modules.iter().map(|module| json!({
+                    // the index in `modules`
+                    "module_index": module.module_index,
+                    // optional, the filename of the module
+                    "module": module.module.as_deref().map(basename),
+                    "list_annotations": module.list_annotations,
+                    "simple_annotations": module.simple_annotations,
+                    "annotation_objects": module.annotation_objects,
+                })).collect::<Vec<_>>(),
+            })),
+            // optional, how long each part of processing took
+            "timings": self.timings.as_ref().map(ProcessTimings::to_json),
+            // What the minidump was missing, that processing made do without
+            "degradations": self.degradations.iter().map(|degradation| degradation.json_name()).collect::<Vec<_>>(),
+            // optional
+            "handles": self.handles.as_ref().map(|handles| json!({
+                "counts": handles.counts,
+                "suspicious_types": handles.suspicious_types,
+                "crash_handles": handles.crash_handles.iter().map(|handle| json!({
+                    "handle": json_hex(handle.handle),
+                    // optional
+                    "type_name": handle.type_name,
+                    // optional
+                    "object_name": handle.object_name,
+                    // register | name_in_memory
+                    "reason": handle.reason.json_name(),
+                    // optional
+                    "register": match &handle.reason {
+                        CrashHandleReason::Register(register) => Some(register),
+                        _ => None,
+                    },
+                    // optional
+                    "address": match handle.reason {
+                        CrashHandleReason::NameInMemory(address) => Some(json_hex(address)),
+                        _ => None,
+                    },
+                })).collect::<Vec<_>>(),
+            })),
 
             "unloaded_modules": self.unloaded_modules.iter().map(|module| json!({
                 "base_addr": json_hex(module.raw.base_of_image),
@@ -786,14 +2343,44 @@ Unknown streams encountered:
                 "filename": module.name,
                 "cert_subject": self.cert_info.get(&module.name),
             })).collect::<Vec<_>>(),
+            // optional
+            "suspicious_modules": self.suspicious_modules.as_ref().map(|modules| {
+                modules.iter().map(|module| json!({
+                    // optional
+                    "filename": module.code_file.as_deref().map(basename),
+                    "base_addr": json_hex(module.base_address),
+                    "end_addr": json_hex(module.base_address + module.size),
+                    // unusual_path | unlisted | unsigned
+                    "reason": module.reason.json_name(),
+                })).collect::<Vec<_>>()
+            }),
 
             "sensitive": {
-                // TODO: Issue #25
                 // low | medium | high | interesting | none | ERROR: *
-                "exploitability": null,
+                "exploitability": self
+                    .exploitability
+                    .as_ref()
+                    .map(|exploitability| exploitability.rating.json_name()),
+                // optional
+                "memory": self.crash_memory.as_ref().map(|memory| {
+                    memory.iter().map(|memory| json!({
+                        "source": memory.source.json_name(),
+                        "address": json_hex(memory.address),
+                        "access": memory.access.map(memory_access_json),
+                        "base_address": json_hex(memory.base_address),
+                        "bytes": memory.hex(),
+                    })).collect::<Vec<_>>()
+                }),
             }
         });
 
+        if !options.extended {
+            remove_keys(&mut output, EXTENDED_KEYS);
+            remove_keys(&mut output["system_info"], EXTENDED_SYSTEM_INFO_KEYS);
+            remove_keys(&mut output["crash_info"], EXTENDED_CRASH_INFO_KEYS);
+            remove_keys(&mut output["sensitive"], EXTENDED_SENSITIVE_KEYS);
+        }
+
         if let Some(requesting_thread) = self.requesting_thread {
             // Copy the crashing thread into a top-level "crashing_thread" field and:
             // * Add a "threads_index" field to indicate which thread it was
@@ -825,22 +2412,32 @@ Unknown streams encountered:
                 .insert(String::from("crashing_thread"), thread);
         }
 
-        if pretty {
-            serde_json::to_writer_pretty(f, &output)
-        } else {
-            serde_json::to_writer(f, &output)
-        }
+        output
     }
 
     // Convert an integer to a hex string, with leading 0's for uniform width.
     fn json_hex(&self, val: u64) -> String {
-        match self.system_info.cpu {
-            Cpu::X86 | Cpu::Ppc | Cpu::Sparc | Cpu::Arm => {
-                format!("0x{:08x}", val)
-            }
-            Cpu::X86_64 | Cpu::Ppc64 | Cpu::Arm64 | Cpu::Unknown(_) => {
-                format!("0x{:016x}", val)
-            }
+        json_hex(self.system_info.cpu, val)
+    }
+}
+
+fn memory_access_json(access: MemoryAccess) -> serde_json::Value {
+    json!({
+        "mapped": access.mapped,
+        "readable": access.readable,
+        "writable": access.writable,
+        "executable": access.executable,
+    })
+}
+
+/// `val` as a hex string, with leading 0's for the pointer width of `cpu`.
+pub(crate) fn json_hex(cpu: Cpu, val: u64) -> String {
+    match cpu {
+        Cpu::X86 | Cpu::Ppc | Cpu::Sparc | Cpu::Arm | Cpu::Mips => {
+            format!("0x{:08x}", val)
+        }
+        Cpu::X86_64 | Cpu::Ppc64 | Cpu::Arm64 | Cpu::Mips64 | Cpu::Riscv64 | Cpu::Unknown(_) => {
+            format!("0x{:016x}", val)
         }
     }
 }
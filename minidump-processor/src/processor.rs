@@ -3,23 +3,785 @@
 
 use std::collections::{BTreeMap, BTreeSet};
 use std::ops::Deref;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
 use std::time::{Duration, SystemTime};
 
+use futures_util::stream::{self, StreamExt};
+use instant::Instant;
+use minidump::system_info::{Cpu, Os};
 use minidump::{self, *};
+use tracing::{field, info_span, Instrument, Span};
 
-use crate::evil;
-use crate::process_state::{CallStack, CallStackInfo, LinuxStandardBase, ProcessState};
-use crate::stackwalker;
+use crate::analysis::{self, AnalysisContext, AnalysisPass};
+use crate::assertion::Assertion;
+use crate::auxiliary::AuxiliaryDataProvider;
+use crate::bit_flips;
+use crate::crashpad::CrashpadAnnotations;
+use crate::evil::EvilJson;
+use crate::exploitability;
+use crate::process_security;
+use crate::process_state::{
+    basename, CallStack, CallStackInfo, Degradation, LinuxProcStatus, LinuxStandardBase,
+    MemoryAccess, ProcessState,
+};
+use crate::source_context::{self, SourceRoot};
+use crate::stackwalker::{
+    self, FrameObserver, ProgressEvent, ProgressObserver, ThreadObserver, UnwinderRegistry,
+};
 use crate::symbols::*;
 use crate::system_info::SystemInfo;
+use crate::timings::ProcessTimings;
+
+/// The default for [`ProcessorOptions::max_frames`].
+pub const DEFAULT_MAX_FRAMES: usize = 1024;
+
+/// The default for [`ProcessorOptions::scan_depth`].
+pub const DEFAULT_SCAN_DEPTH: usize = 40;
+
+/// The default for [`ProcessorOptions::max_stack_gap`].
+pub const DEFAULT_MAX_STACK_GAP: u64 = 64 * 1024;
+
+/// The default for [`ProcessorOptions::thread_concurrency`].
+pub const DEFAULT_THREAD_CONCURRENCY: usize = 16;
+
+/// The default for [`ProcessorOptions::prefetch_concurrency`].
+pub const DEFAULT_PREFETCH_CONCURRENCY: usize = 16;
+
+/// The default for [`ProcessorOptions::source_context_lines`].
+pub const DEFAULT_SOURCE_CONTEXT_LINES: u32 = 5;
+
+/// The default for [`ProcessorOptions::environment_allowlist`].
+///
+/// These say how the process was set up (its locale and time zone, and the
+/// container or sandbox it ran in), without saying anything about the user.
+pub const DEFAULT_ENVIRONMENT_ALLOWLIST: &[&str] = &[
+    "LANG",
+    "LANGUAGE",
+    "LC_ALL",
+    "LC_CTYPE",
+    "LC_MESSAGES",
+    "TZ",
+    "XDG_CURRENT_DESKTOP",
+    "XDG_SESSION_TYPE",
+    // Set by systemd-nspawn, podman and other container managers.
+    "container",
+    "FLATPAK_ID",
+    "SNAP_NAME",
+    "SNAP_REVISION",
+    // Set in every Kubernetes pod.
+    "KUBERNETES_SERVICE_HOST",
+];
 
 /// Various advanced options for the processor.
-#[derive(Default, Debug, Clone)]
+#[derive(Debug, Clone)]
 #[non_exhaustive]
 pub struct ProcessorOptions<'a> {
     /// The evil "raw json" mozilla's legacy infrastructure relies on (to be phased out).
+    ///
+    /// This is read with [`EvilJson`], before any of the
+    /// [`auxiliary_data`][Self::auxiliary_data] providers.
     pub evil_json: Option<&'a Path>,
+    /// Where to get information about the crash that isn't in the minidump,
+    /// like thread names and module certificates.
+    ///
+    /// If several providers have something for the same thread, module, or
+    /// annotation, the last one wins. See [`AuxiliaryDataProvider`].
+    pub auxiliary_data: Vec<Arc<dyn AuxiliaryDataProvider>>,
+    /// How the arm64 stackwalker treats the link register of the context frame.
+    pub link_register_mode: LinkRegisterMode,
+    /// The bits of arm64 pointers that hold pointer authentication
+    /// signatures, for minidumps of arm64e processes.
+    ///
+    /// Return addresses and frame pointers saved by processes using pointer
+    /// authentication have signatures in their high bits, which are stripped
+    /// with this mask before the stackwalker checks and symbolicates them.
+    /// Minidumps don't record the mask. Without one, the stackwalker strips
+    /// any bits above the highest module address from saved return addresses
+    /// that then point into a module.
+    pub arm64_pac_mask: Option<u64>,
+    /// The most frames to unwind for a single thread, not counting inlined frames.
+    ///
+    /// A corrupt stack can send the stackwalker around in circles, so stacks
+    /// are cut off at this many frames and marked with
+    /// [`CallStackInfo::FrameLimitReached`].
+    pub max_frames: usize,
+    /// Whether to search the stack for something that looks like a return
+    /// address when no more reliable way of finding a caller works.
+    ///
+    /// Scanning finds callers that would otherwise be lost, but it also
+    /// produces bogus frames. Turn it off if precision matters more.
+    pub allow_scan: bool,
+    /// How many pointer-sized values a stack scan looks through.
+    pub scan_depth: usize,
+    /// The most bytes of stack memory to walk for a single thread.
+    ///
+    /// Malformed minidumps can have "stacks" hundreds of megabytes long,
+    /// which the stackwalker has no business scanning. A bigger stack is cut
+    /// down to this many bytes from the stack pointer up, and its thread is
+    /// marked with [`CallStackInfo::StackMemoryLimited`]. `None` walks any
+    /// size of stack.
+    pub max_stack_size: Option<u64>,
+    /// The most bytes of stack memory to walk over all threads.
+    ///
+    /// Stacks are counted against this (roughly) in the order their threads
+    /// are in the minidump, after [`max_stack_size`][Self::max_stack_size] has cut
+    /// them down. Once it's used up, the threads left are walked with only
+    /// their registers, and marked with
+    /// [`CallStackInfo::StackMemoryLimited`]. `None` walks all of them.
+    pub max_total_stack_size: Option<u64>,
+    /// Whether scans for the context frame's caller look through four times
+    /// as much of the stack, since the context frame is most likely to be in
+    /// an odd state.
+    pub extended_context_scan: bool,
+    /// Whether to keep walking a stack past a stretch that no caller could be
+    /// found in, because the stack there is missing or trashed.
+    ///
+    /// The stackwalker scans on past where the unwinder gave up for something
+    /// that looks like a return address into a module, for up to
+    /// [`max_stack_gap`][Self::max_stack_gap] bytes. The stretch skipped is
+    /// marked by a frame with [`StackFrame::gap`] set. The frames past a gap
+    /// are only as good as a scan's, and a stack that really ended can pick
+    /// up stale frames from past its end, so this is off by default.
+    ///
+    /// [`StackFrame::gap`]: crate::StackFrame::gap
+    pub resume_after_gaps: bool,
+    /// How many bytes of stack to look through for a caller past a gap, see
+    /// [`resume_after_gaps`][Self::resume_after_gaps].
+    pub max_stack_gap: u64,
+    /// How many threads to walk at once.
+    ///
+    /// Threads are walked concurrently on the current task, so this mostly
+    /// helps symbol providers that have to wait on the network or the disk.
+    /// The threads are always reported in the order they're in the minidump.
+    pub thread_concurrency: usize,
+    /// Whether to have the symbol provider fetch the symbols of every module
+    /// before walking any stacks (see [`SymbolProvider::prefetch`]).
+    ///
+    /// Symbols are otherwise fetched as the stackwalker comes across each
+    /// module, one after another. Prefetching fetches them side by side, but
+    /// also fetches symbols for modules that aren't on any stack, so it helps
+    /// most when symbols come from a server.
+    pub prefetch_symbols: bool,
+    /// How many modules' symbols to fetch at once when prefetching.
+    pub prefetch_concurrency: usize,
+    /// Whether to record how the stackwalker went looking for each frame's
+    /// caller, in [`StackFrame::unwind_diagnostics`].
+    ///
+    /// This is for figuring out why a stack came out wrong.
+    ///
+    /// [`StackFrame::unwind_diagnostics`]: crate::StackFrame::unwind_diagnostics
+    pub unwind_diagnostics: bool,
+    /// Unwinders to try before the built-in ones, for each architecture.
+    ///
+    /// See [`ArchUnwinder`][crate::ArchUnwinder].
+    pub unwinders: UnwinderRegistry,
+    /// Called with each frame as the stackwalker finds it.
+    ///
+    /// See [`FrameObserver`].
+    pub frame_observer: Option<Arc<dyn FrameObserver>>,
+    /// Called with each thread once its stack has been walked.
+    ///
+    /// See [`ThreadObserver`].
+    pub thread_observer: Option<Arc<dyn ThreadObserver>>,
+    /// Told how processing is coming along, for showing progress.
+    ///
+    /// See [`ProgressObserver`].
+    pub progress_observer: Option<Arc<dyn ProgressObserver>>,
+    /// Whether to drop the frames of each thread once the
+    /// [`thread_observer`][Self::thread_observer] has seen them, except for
+    /// the requesting thread's.
+    ///
+    /// For minidumps with a lot of threads, this keeps the processor from
+    /// holding every frame of every thread in memory when the observer has
+    /// already written them out. The threads are still in the
+    /// [`ProcessState`], just without their frames.
+    pub discard_frames: bool,
+    /// Whether to look up symbols for frames that aren't in any loaded module,
+    /// but are in a module that was unloaded before the crash.
+    ///
+    /// Unloaded modules only have a code file and code identifier, so this
+    /// only finds symbols stored under those, which the usual symbol directory
+    /// layout has as `<code file>/<code identifier>/<code file stem>.sym`.
+    /// Frames symbolicated this way record the module in
+    /// [`StackFrame::symbolicated_unloaded_module`].
+    ///
+    /// [`StackFrame::symbolicated_unloaded_module`]: crate::StackFrame::symbolicated_unloaded_module
+    pub symbolicate_unloaded_modules: bool,
+    /// Whether to name the functions of modules we have no symbols for after
+    /// the nearest function they export (from the export table of a PE
+    /// module, or the dynamic symbols of an ELF module).
+    ///
+    /// This only works if the module's image is in the minidump, as it is in
+    /// full-memory dumps. Frames named this way have
+    /// [`StackFrame::function_from_exports`] set. Off by default.
+    ///
+    /// [`StackFrame::function_from_exports`]: crate::StackFrame::function_from_exports
+    pub symbolicate_from_exports: bool,
+    /// Rules for rewriting the source file paths of frames.
+    ///
+    /// Symbols record the paths sources were built from, which are rarely
+    /// where anyone looking at the crash has them. Each path is rewritten by
+    /// the first rule it matches, if any. Frames' `source_url`s are still
+    /// found from the original paths.
+    pub source_path_mappings: Vec<SourcePathMapping>,
+    /// Where to read source files from, for the lines around each frame's
+    /// source line in [`StackFrame::source_context`]. Empty (the default)
+    /// reads none.
+    ///
+    /// Each file is looked for in each root in turn, at its path (after the
+    /// [`source_path_mappings`][Self::source_path_mappings]) relative to the
+    /// root, with any drive letter or leading separator dropped. Paths that
+    /// go up out of the root are never read.
+    ///
+    /// [`StackFrame::source_context`]: crate::StackFrame::source_context
+    pub source_roots: Vec<SourceRoot>,
+    /// How many lines of source to keep before and after each frame's source
+    /// line, see [`source_roots`][Self::source_roots].
+    pub source_context_lines: u32,
+    /// Whether to guess how exploitable the crash is, and put it in
+    /// [`ProcessState::exploitability`].
+    ///
+    /// This is only a heuristic, see [`Exploitability`][crate::Exploitability].
+    pub analyze_exploitability: bool,
+    /// Whether to look for addresses involved in the crash that seem to have
+    /// had a bit flipped by bad RAM, and put them in
+    /// [`ProcessState::possible_bit_flips`].
+    ///
+    /// See [`BitFlip`][crate::BitFlip].
+    pub detect_bit_flips: bool,
+    /// Whether to disassemble the instructions around the crashing
+    /// instruction, into [`ProcessState::disassembly`].
+    ///
+    /// This needs the `disasm` feature, and only supports x86 and x86-64.
+    /// Off by default.
+    pub disassemble: bool,
+    /// How many bytes of memory to keep from around the crash address and
+    /// the crashing thread's instruction and stack pointers, in
+    /// [`ProcessState::crash_memory`]. 0 keeps none.
+    ///
+    /// This is for showing what was there without the minidump, so only a
+    /// few lines of a hexdump are useful.
+    pub crash_memory_size: usize,
+    /// Whether to keep the registers of every thread in their
+    /// [`CallStack::context`], rather than only those of the thread that
+    /// requested the dump.
+    pub all_thread_registers: bool,
+    /// Directories with copies of the files of modules, for reading who
+    /// signed them into [`ProcessState::cert_info`].
+    ///
+    /// Files are looked for where symbol servers store them, at
+    /// `<code file>/<code identifier>/<code file>`. This is only needed for
+    /// modules whose signers aren't in the
+    /// [`auxiliary_data`][Self::auxiliary_data] or the minidump itself.
+    pub module_binary_paths: Vec<PathBuf>,
+    /// Whether to look for modules that look like they were injected into
+    /// the process, and put them in [`ProcessState::suspicious_modules`].
+    ///
+    /// See [`SuspiciousModule`][crate::SuspiciousModule].
+    pub detect_suspicious_modules: bool,
+    /// Whether to guess which thread is most interesting when the minidump
+    /// doesn't say which thread requested it (as with hang dumps), and make
+    /// it the [`ProcessState::requesting_thread`], with
+    /// [`ProcessState::requesting_thread_guessed`] set.
+    ///
+    /// Threads that aren't waiting on anything are preferred, and then the
+    /// main thread. The guess is made once the stacks have been walked, so
+    /// the guessed thread only has all its registers if
+    /// [`all_thread_registers`][Self::all_thread_registers] is set.
+    pub guess_requesting_thread: bool,
+    /// The names of the environment variables to keep from the minidump's
+    /// `LinuxEnviron` stream, in [`ProcessState::environment`].
+    ///
+    /// The environment often has paths, user names and credentials in it,
+    /// so everything not listed here is left out. Names are case-sensitive.
+    /// Defaults to [`DEFAULT_ENVIRONMENT_ALLOWLIST`].
+    pub environment_allowlist: Vec<String>,
+    /// Which threads to walk the stacks of.
+    ///
+    /// Threads that aren't walked are still in [`ProcessState::threads`],
+    /// with no frames and [`CallStackInfo::ThreadSkipped`].
+    pub thread_filter: ThreadFilter,
+    /// When to give up on walking stacks and fetching symbols.
+    ///
+    /// Past the deadline, each stack being walked is cut off at the frame
+    /// it's at, and the rest (and the frames of stacks not yet walked) are
+    /// left without symbols. The [`ProcessState`] is still returned, with
+    /// [`ProcessState::timed_out`] set. A symbol fetch that's already in
+    /// flight isn't interrupted, so how far past the deadline processing
+    /// goes depends on the symbol provider's own timeouts.
+    pub deadline: Option<Instant>,
+    /// A token for giving up on processing from elsewhere, which works the
+    /// same way as passing the [`deadline`][Self::deadline].
+    pub cancellation: Option<CancellationToken>,
+    /// Analyses to run on the [`ProcessState`] once the stacks have been
+    /// walked, after the built-in ones, in order.
+    ///
+    /// See [`AnalysisPass`].
+    pub analysis_passes: Vec<Arc<dyn AnalysisPass>>,
+    /// Whether to time each part of processing, in
+    /// [`ProcessState::timings`].
+    pub record_timings: bool,
+    /// Whether to make do without the system info and thread list streams,
+    /// rather than failing with [`ProcessError::MissingSystemInfo`] or
+    /// [`ProcessError::MissingThreadList`].
+    ///
+    /// Without system info, the CPU is inferred from the exception's context
+    /// (if there is one), and nothing else about the system is known. Without
+    /// a thread list or an exception stream with a context to make the
+    /// crashing thread up from (which is always done), there are no threads.
+    /// Whatever was missing is listed in [`ProcessState::degradations`].
+    pub never_fail: bool,
+}
+
+impl<'a> ProcessorOptions<'a> {
+    /// Use the evil JSON at `path`, see [`evil_json`][Self::evil_json].
+    pub fn with_evil_json(mut self, path: &'a Path) -> Self {
+        self.evil_json = Some(path);
+        self
+    }
+
+    /// Get information about the crash from `provider` too, see
+    /// [`auxiliary_data`][Self::auxiliary_data].
+    pub fn with_auxiliary_data(mut self, provider: Arc<dyn AuxiliaryDataProvider>) -> Self {
+        self.auxiliary_data.push(provider);
+        self
+    }
+
+    /// Strip `mask` from arm64 pointers, see
+    /// [`arm64_pac_mask`][Self::arm64_pac_mask].
+    pub fn with_arm64_pac_mask(mut self, mask: u64) -> Self {
+        self.arm64_pac_mask = Some(mask);
+        self
+    }
+
+    /// Unwind at most `max_frames` frames of each thread, see
+    /// [`max_frames`][Self::max_frames].
+    pub fn with_max_frames(mut self, max_frames: usize) -> Self {
+        self.max_frames = max_frames;
+        self
+    }
+
+    /// Turn stack scanning on or off, see [`allow_scan`][Self::allow_scan].
+    pub fn with_scan(mut self, allow_scan: bool) -> Self {
+        self.allow_scan = allow_scan;
+        self
+    }
+
+    /// Scan through `scan_depth` pointer-sized values, see
+    /// [`scan_depth`][Self::scan_depth].
+    pub fn with_scan_depth(mut self, scan_depth: usize) -> Self {
+        self.scan_depth = scan_depth;
+        self
+    }
+
+    /// Turn walking past missing or trashed parts of stacks on or off, see
+    /// [`resume_after_gaps`][Self::resume_after_gaps].
+    pub fn with_gap_resume(mut self, resume: bool) -> Self {
+        self.resume_after_gaps = resume;
+        self
+    }
+
+    /// Look through at most `size` bytes of stack for a caller past a gap,
+    /// see [`max_stack_gap`][Self::max_stack_gap].
+    pub fn with_max_stack_gap(mut self, size: u64) -> Self {
+        self.max_stack_gap = size;
+        self
+    }
+
+    /// Walk at most `size` bytes of each thread's stack, see
+    /// [`max_stack_size`][Self::max_stack_size].
+    pub fn with_max_stack_size(mut self, size: u64) -> Self {
+        self.max_stack_size = Some(size);
+        self
+    }
+
+    /// Walk at most `size` bytes of stack over all threads, see
+    /// [`max_total_stack_size`][Self::max_total_stack_size].
+    pub fn with_max_total_stack_size(mut self, size: u64) -> Self {
+        self.max_total_stack_size = Some(size);
+        self
+    }
+
+    /// Walk `thread_concurrency` threads at once, see
+    /// [`thread_concurrency`][Self::thread_concurrency].
+    pub fn with_thread_concurrency(mut self, thread_concurrency: usize) -> Self {
+        self.thread_concurrency = thread_concurrency;
+        self
+    }
+
+    /// Turn fetching every module's symbols before walking any stacks on
+    /// or off, see [`prefetch_symbols`][Self::prefetch_symbols].
+    pub fn with_prefetch_symbols(mut self, prefetch: bool) -> Self {
+        self.prefetch_symbols = prefetch;
+        self
+    }
+
+    /// Fetch `prefetch_concurrency` modules' symbols at once when
+    /// prefetching, see [`prefetch_concurrency`][Self::prefetch_concurrency].
+    pub fn with_prefetch_concurrency(mut self, prefetch_concurrency: usize) -> Self {
+        self.prefetch_concurrency = prefetch_concurrency;
+        self
+    }
+
+    /// Only walk the stacks of the threads `filter` lets through, see
+    /// [`thread_filter`][Self::thread_filter].
+    pub fn with_thread_filter(mut self, filter: ThreadFilter) -> Self {
+        self.thread_filter = filter;
+        self
+    }
+
+    /// Only walk the stacks of the threads with the ids in `thread_ids`
+    /// (and the requesting thread's).
+    pub fn with_only_threads(self, thread_ids: impl IntoIterator<Item = u32>) -> Self {
+        self.with_thread_filter(ThreadFilter::Only(thread_ids.into_iter().collect()))
+    }
+
+    /// Walk the stacks of every thread except those with the ids in
+    /// `thread_ids` (though the requesting thread's is always walked).
+    pub fn with_excluded_threads(self, thread_ids: impl IntoIterator<Item = u32>) -> Self {
+        self.with_thread_filter(ThreadFilter::Except(thread_ids.into_iter().collect()))
+    }
+
+    /// Only walk the stack of the thread that requested the dump.
+    ///
+    /// The modules, system info, and the rest are still all there, so this
+    /// is for when only the crash matters, and the stacks of dozens of idle
+    /// threads would just take time.
+    pub fn with_requesting_thread_only(self) -> Self {
+        self.with_thread_filter(ThreadFilter::RequestingThread)
+    }
+
+    /// Only walk the stacks of the thread that requested the dump and the
+    /// threads named one of `names`.
+    pub fn with_requesting_and_named_threads(
+        self,
+        names: impl IntoIterator<Item = String>,
+    ) -> Self {
+        self.with_thread_filter(ThreadFilter::RequestingThreadAndNamed(
+            names.into_iter().collect(),
+        ))
+    }
+
+    /// Turn exploitability analysis on or off, see
+    /// [`analyze_exploitability`][Self::analyze_exploitability].
+    pub fn with_exploitability(mut self, analyze: bool) -> Self {
+        self.analyze_exploitability = analyze;
+        self
+    }
+
+    /// Turn looking for bit flips on or off, see
+    /// [`detect_bit_flips`][Self::detect_bit_flips].
+    pub fn with_bit_flips(mut self, detect: bool) -> Self {
+        self.detect_bit_flips = detect;
+        self
+    }
+
+    /// Turn disassembling the crashing instruction on or off, see
+    /// [`disassemble`][Self::disassemble].
+    pub fn with_disassembly(mut self, disassemble: bool) -> Self {
+        self.disassemble = disassemble;
+        self
+    }
+
+    /// Keep `size` bytes of memory around the crash, see
+    /// [`crash_memory_size`][Self::crash_memory_size].
+    pub fn with_crash_memory(mut self, size: usize) -> Self {
+        self.crash_memory_size = size;
+        self
+    }
+
+    /// Give up on processing at `deadline`, see [`deadline`][Self::deadline].
+    pub fn with_deadline(mut self, deadline: Instant) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    /// Give up on processing `timeout` from now, see
+    /// [`deadline`][Self::deadline].
+    pub fn with_timeout(self, timeout: Duration) -> Self {
+        self.with_deadline(Instant::now() + timeout)
+    }
+
+    /// Give up on processing once `token` is cancelled, see
+    /// [`cancellation`][Self::cancellation].
+    pub fn with_cancellation(mut self, token: CancellationToken) -> Self {
+        self.cancellation = Some(token);
+        self
+    }
+
+    /// Whether the deadline has passed or processing was cancelled.
+    pub(crate) fn out_of_time(&self) -> bool {
+        self.deadline
+            .map_or(false, |deadline| Instant::now() >= deadline)
+            || self
+                .cancellation
+                .as_ref()
+                .map_or(false, CancellationToken::is_cancelled)
+    }
+
+    /// Tell `observer` how processing is coming along, see
+    /// [`progress_observer`][Self::progress_observer].
+    pub fn with_progress_observer(mut self, observer: Arc<dyn ProgressObserver>) -> Self {
+        self.progress_observer = Some(observer);
+        self
+    }
+
+    /// Run `pass` after the built-in analyses and any passes added before
+    /// it, see [`analysis_passes`][Self::analysis_passes].
+    pub fn with_analysis_pass(mut self, pass: Arc<dyn AnalysisPass>) -> Self {
+        self.analysis_passes.push(pass);
+        self
+    }
+
+    /// Turn timing each part of processing on or off, see
+    /// [`record_timings`][Self::record_timings].
+    pub fn with_timings(mut self, record: bool) -> Self {
+        self.record_timings = record;
+        self
+    }
+
+    /// Turn making do without the system info and thread list on or off, see
+    /// [`never_fail`][Self::never_fail].
+    pub fn with_never_fail(mut self, never_fail: bool) -> Self {
+        self.never_fail = never_fail;
+        self
+    }
+
+    /// Add somewhere to read source files from, see
+    /// [`source_roots`][Self::source_roots].
+    pub fn with_source_root(mut self, root: SourceRoot) -> Self {
+        self.source_roots.push(root);
+        self
+    }
+
+    /// Set how many lines of source to keep around each frame's source line,
+    /// see [`source_context_lines`][Self::source_context_lines].
+    pub fn with_source_context_lines(mut self, lines: u32) -> Self {
+        self.source_context_lines = lines;
+        self
+    }
+
+    /// Turn looking for injected modules on or off, see
+    /// [`detect_suspicious_modules`][Self::detect_suspicious_modules].
+    pub fn with_suspicious_modules(mut self, detect: bool) -> Self {
+        self.detect_suspicious_modules = detect;
+        self
+    }
+
+    /// Turn guessing the requesting thread of minidumps that don't say on or
+    /// off, see [`guess_requesting_thread`][Self::guess_requesting_thread].
+    pub fn with_requesting_thread_guess(mut self, guess: bool) -> Self {
+        self.guess_requesting_thread = guess;
+        self
+    }
+
+    /// Keep the environment variable `name` as well, see
+    /// [`environment_allowlist`][Self::environment_allowlist].
+    pub fn with_environment_variable(mut self, name: impl Into<String>) -> Self {
+        self.environment_allowlist.push(name.into());
+        self
+    }
+}
+
+/// A token for giving up on processing from another task or thread, see
+/// [`ProcessorOptions::cancellation`].
+///
+/// Clones share the same state, so cancelling any of them cancels them all.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    /// A token that isn't cancelled yet.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Give up on any processing using this token.
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    /// Whether [`cancel`][Self::cancel] was called.
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// Which threads the processor walks the stacks of, see
+/// [`ProcessorOptions::thread_filter`].
+///
+/// The stack of the thread that requested the dump is always walked, since
+/// most of the analysis of the crash relies on it.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum ThreadFilter {
+    /// Every thread.
+    #[default]
+    All,
+    /// Only the thread that requested the dump.
+    RequestingThread,
+    /// Only the thread that requested the dump, and the threads with these
+    /// names (from the minidump's thread names or the
+    /// [`auxiliary_data`][ProcessorOptions::auxiliary_data]).
+    RequestingThreadAndNamed(BTreeSet<String>),
+    /// Only the threads with these ids.
+    Only(BTreeSet<u32>),
+    /// Every thread except those with these ids.
+    Except(BTreeSet<u32>),
+    /// The threads with any of these ids or names (or every thread, if
+    /// there are none), except those whose names match `skip_names`.
+    Select {
+        ids: BTreeSet<u32>,
+        names: BTreeSet<String>,
+        skip_names: Option<ThreadNamePattern>,
+    },
+}
+
+impl ThreadFilter {
+    /// Whether the stack of the thread with `id` and `name` should be
+    /// walked, if it isn't the requesting thread.
+    fn walks(&self, id: u32, name: Option<&str>) -> bool {
+        match self {
+            ThreadFilter::All => true,
+            ThreadFilter::RequestingThread => false,
+            ThreadFilter::RequestingThreadAndNamed(names) => {
+                name.map_or(false, |name| names.contains(name))
+            }
+            ThreadFilter::Only(ids) => ids.contains(&id),
+            ThreadFilter::Except(ids) => !ids.contains(&id),
+            ThreadFilter::Select {
+                ids,
+                names,
+                skip_names,
+            } => {
+                let selected = (ids.is_empty() && names.is_empty())
+                    || ids.contains(&id)
+                    || name.map_or(false, |name| names.contains(name));
+                let skipped = match (skip_names, name) {
+                    (Some(pattern), Some(name)) => pattern.0.is_match(name),
+                    _ => false,
+                };
+                selected && !skipped
+            }
+        }
+    }
+}
+
+/// A regular expression thread names are matched against, for
+/// [`ThreadFilter::Select`].
+#[derive(Debug, Clone)]
+pub struct ThreadNamePattern(regex::Regex);
+
+impl ThreadNamePattern {
+    /// Parse `pattern`, which has the syntax of the `regex` crate. It
+    /// matches anywhere in a name, unless it's anchored with `^` or `$`.
+    pub fn new(pattern: &str) -> Result<Self, regex::Error> {
+        regex::Regex::new(pattern).map(Self)
+    }
+
+    /// The pattern this was made from.
+    pub fn as_str(&self) -> &str {
+        self.0.as_str()
+    }
+}
+
+impl PartialEq for ThreadNamePattern {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_str() == other.as_str()
+    }
+}
+
+impl Eq for ThreadNamePattern {}
+
+/// A rule for rewriting source file paths, see
+/// [`ProcessorOptions::source_path_mappings`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourcePathMapping {
+    /// The prefix of the paths to rewrite.
+    pub from: String,
+    /// What to replace the prefix with.
+    pub to: String,
+}
+
+impl SourcePathMapping {
+    /// Rewrite `path` with the first of `mappings` it starts with.
+    fn apply(mappings: &[SourcePathMapping], path: &mut String) {
+        if let Some(mapping) = mappings.iter().find(|m| path.starts_with(&m.from)) {
+            path.replace_range(..mapping.from.len(), &mapping.to);
+        }
+    }
+}
+
+impl<'a> Default for ProcessorOptions<'a> {
+    fn default() -> Self {
+        Self {
+            evil_json: None,
+            auxiliary_data: Vec::new(),
+            link_register_mode: LinkRegisterMode::default(),
+            arm64_pac_mask: None,
+            max_frames: DEFAULT_MAX_FRAMES,
+            allow_scan: true,
+            scan_depth: DEFAULT_SCAN_DEPTH,
+            max_stack_size: None,
+            max_total_stack_size: None,
+            extended_context_scan: true,
+            resume_after_gaps: false,
+            max_stack_gap: DEFAULT_MAX_STACK_GAP,
+            thread_concurrency: DEFAULT_THREAD_CONCURRENCY,
+            prefetch_symbols: false,
+            prefetch_concurrency: DEFAULT_PREFETCH_CONCURRENCY,
+            unwind_diagnostics: false,
+            unwinders: UnwinderRegistry::default(),
+            frame_observer: None,
+            thread_observer: None,
+            progress_observer: None,
+            discard_frames: false,
+            symbolicate_unloaded_modules: false,
+            symbolicate_from_exports: false,
+            source_path_mappings: Vec::new(),
+            source_roots: Vec::new(),
+            source_context_lines: DEFAULT_SOURCE_CONTEXT_LINES,
+            analyze_exploitability: false,
+            detect_bit_flips: false,
+            disassemble: false,
+            crash_memory_size: 0,
+            all_thread_registers: false,
+            module_binary_paths: Vec::new(),
+            detect_suspicious_modules: false,
+            guess_requesting_thread: false,
+            environment_allowlist: DEFAULT_ENVIRONMENT_ALLOWLIST
+                .iter()
+                .map(|&name| String::from(name))
+                .collect(),
+            thread_filter: ThreadFilter::default(),
+            deadline: None,
+            cancellation: None,
+            analysis_passes: Vec::new(),
+            record_timings: false,
+            never_fail: false,
+        }
+    }
+}
+
+/// Whether the link register of an arm64 context frame holds its return address.
+///
+/// A leaf function never saves the link register or sets up a frame record,
+/// so its caller can only be found through the link register. Any other
+/// function has saved its return address in its frame record, and the link
+/// register is left over from whatever it called last. This only matters when
+/// there is no CFI for the context frame.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum LinkRegisterMode {
+    /// Use the link register unless our symbols say it points back into the
+    /// context frame's own function, in which case it must be stale.
+    #[default]
+    Heuristic,
+    /// Always use the link register as the context frame's return address.
+    Always,
+    /// Never use the link register; always read the return address from the
+    /// context frame's frame record.
+    Never,
 }
 
 /// An error encountered during minidump processing.
@@ -69,7 +831,59 @@ where
     process_minidump_with_options(dump, symbol_provider, ProcessorOptions::default()).await
 }
 
+/// Read the minidump at `path` and process it with the default options,
+/// looking for symbols where `symbols` says to.
+///
+/// This is the same as reading the minidump with [`Minidump::read_path`] and
+/// passing it and a [`SymbolConfig::symbolizer`] to [`process_minidump`].
+///
+/// # Examples
+///
+/// ```
+/// use minidump_processor::{ProcessError, SymbolConfig};
+///
+/// #[tokio::main]
+/// async fn main() -> Result<(), ProcessError> {
+///     # std::env::set_current_dir(env!("CARGO_MANIFEST_DIR"));
+///     let symbols = SymbolConfig::default().with_symbol_path("../testdata/symbols");
+///     let state = minidump_processor::process_path("../testdata/test.dmp", &symbols).await?;
+///     assert_eq!(state.threads.len(), 2);
+///     Ok(())
+/// }
+/// ```
+pub async fn process_path(
+    path: impl AsRef<Path>,
+    symbols: &SymbolConfig,
+) -> Result<ProcessState, ProcessError> {
+    let dump = Minidump::read_path(path)?;
+    process_minidump(&dump, &symbols.symbolizer()).await
+}
+
+/// The same as [`process_path`], for a minidump that's already in memory.
+pub async fn process_bytes<T>(
+    bytes: T,
+    symbols: &SymbolConfig,
+) -> Result<ProcessState, ProcessError>
+where
+    T: Deref<Target = [u8]>,
+{
+    let dump = Minidump::read(bytes)?;
+    process_minidump(&dump, &symbols.symbolizer()).await
+}
+
 /// The same as [`process_minidump`] but with extra options.
+#[tracing::instrument(
+    name = "process_minidump",
+    skip_all,
+    fields(
+        process_id = field::Empty,
+        os = field::Empty,
+        cpu = field::Empty,
+        thread_count = field::Empty,
+        module_count = field::Empty,
+        timed_out = field::Empty,
+    )
+)]
 pub async fn process_minidump_with_options<'a, T, P>(
     dump: &Minidump<'a, T>,
     symbol_provider: &P,
@@ -79,19 +893,47 @@ where
     T: Deref<Target = [u8]> + 'a,
     P: SymbolProvider + Sync,
 {
-    // Thread list is required for processing.
-    let thread_list = dump
-        .get_stream::<MinidumpThreadList>()
-        .or(Err(ProcessError::MissingThreadList))?;
+    let processing_started = Instant::now();
+    // Every thread shares one cache of the CFI lookups that came up empty.
+    let symbol_provider = &stackwalker::CfiCache::new(symbol_provider);
+    // And one of the symbols found for each address.
+    let symbol_provider = &stackwalker::SymbolCache::new(symbol_provider);
+    // Which also tells the progress observer (if any) about symbol fetches,
+    // and times them.
+    let symbol_provider = &stackwalker::ProgressReporter::new(
+        symbol_provider,
+        options.progress_observer.clone(),
+        options.record_timings,
+    );
+
+    let mut degradations = Vec::new();
+
+    // Thread list is required for processing, unless there's an exception
+    // stream to make up the crashing thread from (see below).
+    let thread_list = dump.get_stream::<MinidumpThreadList>().ok();
     // Try to get thread names, but it's only a nice-to-have.
     let thread_names = dump
         .get_stream::<MinidumpThreadNames>()
         .unwrap_or_else(|_| MinidumpThreadNames::default());
 
-    // System info is required for processing.
-    let dump_system_info = dump
-        .get_stream::<MinidumpSystemInfo>()
-        .or(Err(ProcessError::MissingSystemInfo))?;
+    let exception_stream = dump.get_stream::<MinidumpException>().ok();
+
+    // System info is required for processing too, unless we're to make do
+    // with whatever the exception's context says about the CPU.
+    let dump_system_info = match dump.get_stream::<MinidumpSystemInfo>() {
+        Ok(system_info) => system_info,
+        Err(_) if options.never_fail => {
+            degradations.push(Degradation::MissingSystemInfo);
+            let inferred = exception_stream
+                .as_ref()
+                .and_then(|exception| exception.infer_system_info());
+            if inferred.is_some() {
+                degradations.push(Degradation::InferredCpu);
+            }
+            inferred.unwrap_or_else(MinidumpSystemInfo::unknown)
+        }
+        Err(_) => return Err(ProcessError::MissingSystemInfo),
+    };
 
     let os_version = format!(
         "{}.{}.{}",
@@ -109,8 +951,8 @@ where
     let linux_cpu_info = dump
         .get_stream::<MinidumpLinuxCpuInfo>()
         .unwrap_or_default();
-    let _linux_environ = dump.get_stream::<MinidumpLinuxEnviron>().ok();
-    let _linux_proc_status = dump.get_stream::<MinidumpLinuxProcStatus>().ok();
+    let linux_environ = dump.get_stream::<MinidumpLinuxEnviron>().ok();
+    let linux_proc_status = dump.get_stream::<MinidumpLinuxProcStatus>().ok();
 
     // Extract everything we care about from linux streams here.
     // We don't eagerly process them in the minidump crate because there's just
@@ -118,15 +960,47 @@ where
     // would care about. So just providing an iterator and letting minidump-processor
     // pull out the things it cares about is simple and effective.
 
+    // `/proc/cpuinfo` has a block of lines for each processor, which mostly
+    // say the same things, so the first one's are kept. The ids of the
+    // package and core each one is in are counted.
     let mut cpu_microcode_version = None;
+    let mut cpu_vendor = None;
+    let mut cpu_model_name = None;
+    let mut cpu_features = Vec::new();
+    let mut cpu_packages = BTreeSet::new();
+    let mut cpu_cores = BTreeSet::new();
+    let mut physical_id = None;
     for (key, val) in linux_cpu_info.iter() {
-        if key.as_bytes() == b"microcode" {
-            cpu_microcode_version = val
-                .to_str()
-                .ok()
-                .and_then(|val| val.strip_prefix("0x"))
-                .and_then(|val| u64::from_str_radix(val, 16).ok());
-            break;
+        match key.as_bytes() {
+            b"microcode" if cpu_microcode_version.is_none() => {
+                cpu_microcode_version = val
+                    .to_str()
+                    .ok()
+                    .and_then(|val| val.strip_prefix("0x"))
+                    .and_then(|val| u64::from_str_radix(val, 16).ok());
+            }
+            b"vendor_id" if cpu_vendor.is_none() => {
+                cpu_vendor = Some(val.to_string_lossy().into_owned());
+            }
+            b"model name" if cpu_model_name.is_none() => {
+                cpu_model_name = Some(val.to_string_lossy().into_owned());
+            }
+            b"flags" | b"Features" if cpu_features.is_empty() => {
+                cpu_features = val
+                    .to_string_lossy()
+                    .split_whitespace()
+                    .map(String::from)
+                    .collect();
+            }
+            b"physical id" => {
+                let id = val.to_string_lossy().into_owned();
+                cpu_packages.insert(id.clone());
+                physical_id = Some(id);
+            }
+            b"core id" => {
+                cpu_cores.insert((physical_id.clone(), val.to_string_lossy().into_owned()));
+            }
+            _ => {}
         }
     }
 
@@ -150,18 +1024,83 @@ where
         lsb
     });
 
+    let linux_proc_status = linux_proc_status.map(|linux_proc_status| {
+        let mut status = LinuxProcStatus::default();
+        for (key, val) in linux_proc_status.iter() {
+            let val = val.to_string_lossy();
+            match key.as_bytes() {
+                b"State" => status.state = Some(val.into_owned()),
+                b"Threads" => status.threads = val.parse().ok(),
+                b"VmPeak" => status.vm_peak = parse_proc_status_size(&val),
+                b"VmSize" => status.vm_size = parse_proc_status_size(&val),
+                b"VmRSS" => status.vm_rss = parse_proc_status_size(&val),
+                b"Seccomp" => status.seccomp = val.parse().ok(),
+                _ => {}
+            }
+        }
+        status
+    });
+
+    // Only the variables that are asked for are kept, as the rest may well
+    // be private.
+    let environment = linux_environ.map(|linux_environ| {
+        let mut environment = BTreeMap::new();
+        for (key, val) in linux_environ.iter() {
+            let key = key.to_string_lossy();
+            if options
+                .environment_allowlist
+                .iter()
+                .any(|name| *name == key)
+            {
+                // Like getenv, the first definition wins.
+                environment
+                    .entry(key.into_owned())
+                    .or_insert_with(|| val.to_string_lossy().into_owned());
+            }
+        }
+        environment
+    });
+
     let cpu_info = dump_system_info
         .cpu_info()
         .map(|string| string.into_owned());
 
+    // The system info of x86 minidumps has the CPUID vendor string, and
+    // the family, model and stepping of x86 and amd64 ones.
+    let raw_system_info = &dump_system_info.raw;
+    if dump_system_info.cpu == Cpu::X86 {
+        let vendor = String::from_utf8_lossy(&raw_system_info.cpu.data[..12]);
+        let vendor = vendor.trim_end_matches('\0');
+        if !vendor.is_empty() {
+            cpu_vendor = Some(String::from(vendor));
+        }
+    }
+    let (cpu_family, cpu_model, cpu_stepping) = match dump_system_info.cpu {
+        // There's no family 0, so that's system info made up without it.
+        Cpu::X86 | Cpu::X86_64 if raw_system_info.processor_level != 0 => (
+            Some(raw_system_info.processor_level as u32),
+            Some(((raw_system_info.processor_revision >> 8) & 0xff) as u32),
+            Some((raw_system_info.processor_revision & 0xff) as u32),
+        ),
+        _ => (None, None, None),
+    };
+
     let system_info = SystemInfo {
         os: dump_system_info.os,
         os_version: Some(os_version),
         os_build,
         cpu: dump_system_info.cpu,
         cpu_info,
+        cpu_vendor,
+        cpu_family,
+        cpu_model,
+        cpu_stepping,
+        cpu_model_name,
         cpu_microcode_version,
-        cpu_count: dump_system_info.raw.number_of_processors as usize,
+        cpu_features,
+        cpu_count: raw_system_info.number_of_processors as usize,
+        cpu_physical_core_count: Some(cpu_cores.len()).filter(|&count| count > 0),
+        cpu_package_count: Some(cpu_packages.len()).filter(|&count| count > 0),
     };
 
     let mac_crash_info = dump
@@ -179,6 +1118,9 @@ where
     } else {
         (None, None)
     };
+    let process_security = misc_info
+        .as_ref()
+        .and_then(process_security::process_security);
     // If Breakpad info exists in dump, get dump and requesting thread ids.
     let breakpad_info = dump.get_stream::<MinidumpBreakpadInfo>();
     let (dump_thread_id, requesting_thread_id) = if let Ok(info) = breakpad_info {
@@ -187,7 +1129,6 @@ where
         (None, None)
     };
     // Get exception info if it exists.
-    let exception_stream = dump.get_stream::<MinidumpException>().ok();
     let exception_ref = exception_stream.as_ref();
     let (crash_reason, crash_address, crashing_thread_id) = if let Some(exception) = exception_ref {
         (
@@ -201,7 +1142,10 @@ where
     let exception_context =
         exception_ref.and_then(|e| e.context(&dump_system_info, misc_info.as_ref()));
     // Get assertion
-    let assertion = None;
+    let assertion = dump
+        .get_stream::<MinidumpAssertion>()
+        .ok()
+        .map(|assertion| Assertion::from_stream(&assertion));
     let modules = match dump.get_stream::<MinidumpModuleList>() {
         Ok(module_list) => module_list,
         // Just give an empty list, simplifies things.
@@ -212,103 +1156,664 @@ where
         // Just give an empty list, simplifies things.
         Err(_) => MinidumpUnloadedModuleList::new(),
     };
+    let thread_infos = dump
+        .get_stream::<MinidumpThreadInfoList>()
+        .unwrap_or_default();
     let memory_list = dump.get_stream::<MinidumpMemoryList>().unwrap_or_default();
+    let dump_threads = match thread_list {
+        Some(thread_list) => thread_list.threads,
+        // Some writers leave the thread list out, but the exception stream
+        // still has the thread that crashed: its id and context. Its stack
+        // is wherever its stack pointer is.
+        None => match (exception_ref, exception_context.as_deref()) {
+            (Some(exception), Some(context)) => {
+                degradations.push(Degradation::SynthesizedThread);
+                let stack = memory_list
+                    .memory_at_address(context.get_stack_pointer())
+                    .cloned();
+                vec![exception.synthesize_thread(stack)]
+            }
+            _ if options.never_fail => {
+                degradations.push(Degradation::MissingThreadList);
+                Vec::new()
+            }
+            _ => return Err(ProcessError::MissingThreadList),
+        },
+    };
+    // The stackwalker also reads the memory of modules, which is only
+    // included in full-memory dumps.
+    let unified_memory_list = UnifiedMemoryList::new(
+        dump.get_stream::<MinidumpMemoryList>().ok(),
+        dump.get_stream::<MinidumpMemory64List>().ok(),
+    )
+    .unwrap_or_default();
     let memory_info_list = dump.get_stream::<MinidumpMemoryInfoList>().ok();
     let linux_maps = dump.get_stream::<MinidumpLinuxMaps>().ok();
-    let _memory_info = UnifiedMemoryInfoList::new(memory_info_list, linux_maps).unwrap_or_default();
+    let memory_info = UnifiedMemoryInfoList::new(memory_info_list, linux_maps);
+    let crash_address_access = crash_address
+        .zip(memory_info.as_ref())
+        .map(|(address, info)| MemoryAccess::at_address(info, address));
+    let exploitability = match (crash_reason, crash_address) {
+        (Some(reason), Some(address)) if options.analyze_exploitability => {
+            Some(exploitability::analyze(
+                reason,
+                address,
+                exception_context.as_deref(),
+                memory_info.as_ref(),
+                &modules,
+            ))
+        }
+        _ => None,
+    };
+    let possible_bit_flips = if options.detect_bit_flips {
+        Some(bit_flips::find_bit_flips(
+            system_info.cpu,
+            crash_address,
+            exception_context.as_deref(),
+            memory_info.as_ref(),
+            &unified_memory_list,
+            &modules,
+        ))
+    } else {
+        None
+    };
+    // Used to tell JIT code apart from random pointers while walking stacks.
+    let empty_memory_info = UnifiedMemoryInfoList::default();
+    let stack_memory_info = memory_info.as_ref().unwrap_or(&empty_memory_info);
 
-    // Get the evil JSON file (thread names and module certificates)
-    let evil = options
+    // Get the thread names, module certificates, and annotations that aren't
+    // in the minidump, from the evil JSON and whatever else was provided.
+    let mut auxiliary = options
         .evil_json
-        .and_then(evil::handle_evil)
+        .map(|path| EvilJson::new(path).auxiliary_data())
         .unwrap_or_default();
+    for provider in options.auxiliary_data.iter() {
+        auxiliary.merge(provider.auxiliary_data());
+    }
 
-    let mut threads = vec![];
-    let mut requesting_thread = None;
-    for (i, thread) in thread_list.threads.iter().enumerate() {
+    // 32-bit processes on 64-bit Windows run on top of the WOW64 layer,
+    // which always loads wow64cpu.dll.
+    let is_wow64 = system_info.os == Os::Windows
+        && system_info.cpu == Cpu::X86_64
+        && modules.iter().any(is_wow64_cpu_module);
+
+    let is_requesting_thread = |id: u32| {
+        // Prefer the exception stream's thread id over the breakpad info
+        // stream's thread id.
+        crashing_thread_id
+            .or(requesting_thread_id)
+            .map(|requesting_id| requesting_id == id)
+            .unwrap_or(false)
+    };
+    let is_dump_thread = |id: u32| dump_thread_id.map(|dump_id| dump_id == id).unwrap_or(false);
+    let requesting_thread = dump_threads.iter().position(|thread| {
         let id = thread.raw.thread_id;
+        !is_dump_thread(id) && is_requesting_thread(id)
+    });
 
-        // If this is the thread that wrote the dump, skip processing it.
-        if dump_thread_id.is_some() && dump_thread_id.unwrap() == id {
-            threads.push(CallStack::with_info(id, CallStackInfo::DumpThreadSkipped));
-            continue;
+    let progress = |event: ProgressEvent| {
+        if let Some(ref observer) = options.progress_observer {
+            observer.on_progress(&event);
         }
+    };
+    let thread_count = dump_threads.len();
+    let module_count = modules.iter().count();
+    progress(ProgressEvent::StreamsRead {
+        thread_count,
+        module_count,
+    });
 
-        let thread_context = thread.context(&dump_system_info, misc_info.as_ref());
-        // If this thread requested the dump then try to use the exception
-        // context if it exists. (prefer the exception stream's thread id over
-        // the breakpad info stream's thread id.)
-        let context = if crashing_thread_id
-            .or(requesting_thread_id)
-            .map(|id| id == thread.raw.thread_id)
-            .unwrap_or(false)
-        {
-            requesting_thread = Some(i);
-            exception_context
-                .as_deref()
-                .or_else(|| thread_context.as_deref())
-        } else {
-            thread_context.as_deref()
-        };
-
-        let stack = thread.stack_memory(&memory_list);
-
-        let mut stack =
-            stackwalker::walk_stack(&context, stack.as_deref(), &modules, symbol_provider).await;
-        stack.thread_id = id;
-        for frame in &mut stack.frames {
-            // If the frame doesn't have a loaded module, try to find an unloaded module
-            // that overlaps with its address range. The may be multiple, so record all
-            // of them and the offsets this frame has in them.
-            if frame.module.is_none() {
-                let mut offsets = BTreeMap::new();
-                for unloaded in unloaded_modules.modules_at_address(frame.instruction) {
-                    let offset = frame.instruction - unloaded.raw.base_of_image;
-                    offsets
-                        .entry(unloaded.name.clone())
-                        .or_insert_with(BTreeSet::new)
-                        .insert(offset);
+    let span = Span::current();
+    if let Some(process_id) = process_id {
+        span.record("process_id", &process_id);
+    }
+    span.record("os", &field::display(system_info.os));
+    span.record("cpu", &field::display(system_info.cpu));
+    span.record("thread_count", &thread_count);
+    span.record("module_count", &module_count);
+    let read_streams = processing_started.elapsed();
+
+    let prefetch_started = Instant::now();
+    if options.prefetch_symbols && !options.out_of_time() {
+        let modules: Vec<&(dyn Module + Sync)> = modules
+            .iter()
+            .map(|module| module as &(dyn Module + Sync))
+            .collect();
+        symbol_provider
+            .prefetch(&modules, options.prefetch_concurrency)
+            .await;
+    }
+    let prefetch_symbols = prefetch_started.elapsed();
+
+    // Walking a thread mostly waits on the symbol provider, so walk several
+    // at a time. `buffered` keeps the results in the original thread order.
+    let mut thread_times = Vec::with_capacity(thread_count);
+    let stack_memory_used = AtomicU64::new(0);
+    let threads = {
+        let options = &options;
+        let stack_memory_used = &stack_memory_used;
+        let dump_system_info = &dump_system_info;
+        let misc_info = misc_info.as_ref();
+        let exception_context = exception_context.as_deref();
+        let memory_list = &memory_list;
+        let unified_memory_list = &unified_memory_list;
+        let modules = &modules;
+        let unloaded_modules = &unloaded_modules;
+        let thread_names = &thread_names;
+        let thread_infos = &thread_infos;
+        let auxiliary_thread_names = &auxiliary.thread_names;
+        let cpu = system_info.cpu;
+        let os = system_info.os;
+        let progress = &progress;
+        let indexed_threads = dump_threads.iter().enumerate();
+        let walk_threads = indexed_threads.map(|(index, thread)| async move {
+            let id = thread.raw.thread_id;
+            progress(ProgressEvent::ThreadStarted {
+                index,
+                thread_id: id,
+                thread_count,
+            });
+
+            // If this is the thread that wrote the dump, skip processing it.
+            if is_dump_thread(id) {
+                return CallStack::with_info(id, CallStackInfo::DumpThreadSkipped);
+            }
+            let listed_name = thread_names
+                .get_name(id)
+                .map(|cow| cow.into_owned())
+                .or_else(|| auxiliary_thread_names.get(&id).cloned());
+            if !is_requesting_thread(id) && !options.thread_filter.walks(id, listed_name.as_deref())
+            {
+                let mut stack = CallStack::with_info(id, CallStackInfo::ThreadSkipped);
+                stack.thread_name = listed_name;
+                return stack;
+            }
+
+            let thread_context = thread.context(dump_system_info, misc_info);
+            // If this thread requested the dump then try to use the exception
+            // context if it exists, topped up with any callee-saved registers
+            // only the thread's own context has.
+            let merged_context = match (exception_context, thread_context.as_deref()) {
+                (Some(exception), Some(thread)) if is_requesting_thread(id) => {
+                    stackwalker::merge_contexts(exception, thread)
                 }
+                _ => None,
+            };
+            let context = if is_requesting_thread(id) {
+                merged_context
+                    .as_ref()
+                    .or(exception_context)
+                    .or_else(|| thread_context.as_deref())
+            } else {
+                thread_context.as_deref()
+            };
 
-                frame.unloaded_modules = offsets;
+            // The 32-bit stack of a WOW64 thread isn't the one recorded for the
+            // thread, so find the memory its stack pointer is in instead.
+            let wow64_stack = match context {
+                Some(ctx) if is_wow64 && matches!(ctx.raw, MinidumpRawContext::X86(_)) => {
+                    memory_containing(unified_memory_list, ctx.get_stack_pointer())
+                }
+                _ => None,
+            };
+            // The TEB says where the stack really is, which the memory found
+            // for it can go past.
+            let stack_bounds = if os != Os::Windows {
+                None
+            } else if wow64_stack.is_some() {
+                thread.wow64_stack_bounds(cpu, unified_memory_list)
+            } else {
+                thread.stack_bounds(cpu, unified_memory_list)
+            };
+            // Full-memory minidumps may only have the stack in their
+            // Memory64List.
+            let stack = wow64_stack
+                .or_else(|| {
+                    thread
+                        .stack_memory(memory_list)
+                        .map(|stack| stack.into_owned())
+                })
+                .or_else(|| {
+                    memory_containing(unified_memory_list, thread.raw.stack.start_of_memory_range)
+                });
+            let stack_pointer = context.map(|ctx| ctx.get_stack_pointer());
+            let stack = clip_stack(stack, stack_bounds, stack_pointer);
+
+            let (stack_memory, mut stack_limited) =
+                limit_stack(stack.as_ref(), stack_pointer, options, stack_memory_used);
+
+            let mut stack = stackwalker::walk_thread_stack(
+                id,
+                &context,
+                stack_memory.as_ref(),
+                unified_memory_list,
+                stack_memory_info,
+                modules,
+                symbol_provider,
+                options,
+            )
+            .await;
+
+            // If the thread was in the 64-bit WOW64 layer, also walk the 32-bit
+            // code it was running on behalf of (unless we were told to stop).
+            let wow64_context = match context {
+                Some(ctx)
+                    if is_wow64
+                        && matches!(ctx.raw, MinidumpRawContext::Amd64(_))
+                        && !matches!(
+                            stack.info,
+                            CallStackInfo::WalkStopped | CallStackInfo::TimedOut
+                        ) =>
+                {
+                    thread.wow64_context(cpu, unified_memory_list)
+                }
+                _ => None,
+            };
+            if let Some(wow64_context) = wow64_context {
+                let stack_pointer = wow64_context.get_stack_pointer();
+                let wow64_stack = memory_containing(unified_memory_list, stack_pointer);
+                let wow64_bounds = thread.wow64_stack_bounds(cpu, unified_memory_list);
+                let wow64_stack = clip_stack(wow64_stack, wow64_bounds, Some(stack_pointer));
+                let (wow64_stack, wow64_limited) = limit_stack(
+                    wow64_stack.as_ref(),
+                    Some(stack_pointer),
+                    options,
+                    stack_memory_used,
+                );
+                stack_limited |= wow64_limited;
+                let wow64_stack = stackwalker::walk_thread_stack(
+                    id,
+                    &Some(&wow64_context),
+                    wow64_stack.as_ref(),
+                    unified_memory_list,
+                    stack_memory_info,
+                    modules,
+                    symbol_provider,
+                    options,
+                )
+                .await;
+                stitch_wow64_stack(&mut stack, wow64_stack);
             }
-        }
+            // Anything else that cut the walk short says more about the stack.
+            if stack_limited && stack.info == CallStackInfo::Ok {
+                stack.info = CallStackInfo::StackMemoryLimited;
+            }
+            for frame in &mut stack.frames {
+                // If the frame doesn't have a loaded module, try to find an unloaded module
+                // that overlaps with its address range. The may be multiple, so record all
+                // of them and the offsets this frame has in them.
+                if frame.module.is_none() {
+                    let mut offsets = BTreeMap::new();
+                    for unloaded in unloaded_modules.modules_at_address(frame.instruction) {
+                        let offset = frame.instruction - unloaded.raw.base_of_image;
+                        offsets
+                            .entry(unloaded.name.clone())
+                            .or_insert_with(BTreeSet::new)
+                            .insert(offset);
 
-        let name = thread_names
-            .get_name(thread.raw.thread_id)
-            .map(|cow| cow.into_owned())
-            .or_else(|| evil.thread_names.get(&thread.raw.thread_id).cloned());
-        stack.thread_name = name;
+                        // Use the symbols of the first of them that has any.
+                        if options.symbolicate_unloaded_modules
+                            && !options.out_of_time()
+                            && frame.symbolicated_unloaded_module.is_none()
+                            && symbol_provider.fill_symbol(unloaded, frame).await.is_ok()
+                            && frame.function_name.is_some()
+                        {
+                            frame.symbolicated_unloaded_module = Some(unloaded.clone());
+                        }
+                    }
 
-        stack.last_error_value = thread.last_error(system_info.cpu, &memory_list);
+                    frame.unloaded_modules = offsets;
+                }
 
-        threads.push(stack);
-    }
+                if let Some(ref mut path) = frame.source_file_name {
+                    SourcePathMapping::apply(&options.source_path_mappings, path);
+                }
+                for (path, _) in &mut frame.other_source_lines {
+                    SourcePathMapping::apply(&options.source_path_mappings, path);
+                }
+            }
+
+            stack.priority_class = thread.raw.priority_class;
+            stack.priority = thread.raw.priority;
+            if let Some(info) = thread_infos.get_thread_info(id) {
+                stack.kernel_time = Some(info.kernel_time());
+                stack.user_time = Some(info.user_time());
+                stack.start_address = Some(info.raw.start_address).filter(|&addr| addr != 0);
+            }
+
+            let mut name = listed_name;
+            if let (None, Some(start_address)) = (&name, stack.start_address) {
+                if !options.out_of_time() {
+                    name = start_address_name(start_address, modules, symbol_provider).await;
+                }
+            }
+            if let Some(ref name) = name {
+                Span::current().record("name", &name.as_str());
+            }
+            stack.thread_name = name;
+
+            stack.last_error_value = thread.last_error(cpu, memory_list);
+
+            stack
+        });
+        // Each thread's traces go in its own span, and its walk is timed.
+        let walk_threads = walk_threads.enumerate().map(|(index, walk)| {
+            let thread_id = dump_threads[index].raw.thread_id;
+            let span = info_span!("thread", index, thread_id, name = field::Empty);
+            async move {
+                let started = Instant::now();
+                let stack = walk.await;
+                (stack, started.elapsed())
+            }
+            .instrument(span)
+        });
+        stream::iter(walk_threads)
+            .buffered(options.thread_concurrency.max(1))
+            .enumerate()
+            .map(|(i, (mut stack, time))| {
+                thread_times.push(time);
+                progress(ProgressEvent::ThreadFinished {
+                    index: i,
+                    thread_id: stack.thread_id,
+                    thread_count,
+                });
+                if !options.all_thread_registers && requesting_thread != Some(i) {
+                    stack.context = None;
+                }
+                if let Some(ref observer) = options.thread_observer {
+                    observer.on_thread(&system_info, i, &stack);
+                    if options.discard_frames && requesting_thread != Some(i) {
+                        stack.frames = Vec::new();
+                    }
+                }
+                stack
+            })
+            .collect::<Vec<_>>()
+            .await
+    };
+    // Anything skipped for lack of time leaves the results partial.
+    let timed_out = options.out_of_time()
+        || threads
+            .iter()
+            .any(|stack| stack.info == CallStackInfo::TimedOut);
+    span.record("timed_out", &timed_out);
+
+    // The crashing thread's registers, for looking at where the crash
+    // address came from.
+    let thread_context = requesting_thread
+        .and_then(|i| dump_threads[i].context(&dump_system_info, misc_info.as_ref()));
+    let crashing_context = exception_context.as_deref().or(thread_context.as_deref());
+    let handle_data = dump.get_stream::<MinidumpHandleDataStream>().ok();
+    let crashpad_annotations = dump
+        .get_stream::<MinidumpCrashpadInfo>()
+        .ok()
+        .map(|info| CrashpadAnnotations::from_stream(&info, &modules));
+    let raw_streams = |stream_type: u32| dump.get_raw_stream(stream_type).ok();
 
     // Collect up info on unimplemented/unknown modules
     let unknown_streams = dump.unknown_streams().collect();
     let unimplemented_streams = dump.unimplemented_streams().collect();
+    let truncated_streams = dump.truncated_streams().collect();
+    let missing_directory_entries = dump.missing_directory_entries();
 
     // Get symbol stats from the symbolizer
     let symbol_stats = symbol_provider.stats();
 
-    Ok(ProcessState {
+    let mut state = ProcessState {
         process_id,
         time: SystemTime::UNIX_EPOCH + Duration::from_secs(dump.header.time_date_stamp as u64),
         process_create_time,
-        cert_info: evil.certs,
+        process_security,
+        dump_type: dump.header.dump_type(),
+        cert_info: auxiliary.module_certs,
+        annotations: auxiliary.annotations,
+        crashpad_annotations,
         crash_reason,
         crash_address,
+        crash_address_access,
+        crash_address_class: None,
+        stack_overflow: None,
+        register_targets: Vec::new(),
+        exploitability,
+        possible_bit_flips,
+        crash_memory: None,
+        disassembly: None,
+        handles: None,
         assertion,
         requesting_thread,
+        requesting_thread_guessed: false,
         system_info,
         linux_standard_base,
+        linux_proc_status,
+        environment,
         mac_crash_info,
         threads,
         modules,
         unloaded_modules,
+        suspicious_modules: None,
         unknown_streams,
         unimplemented_streams,
+        truncated_streams,
+        missing_directory_entries,
         symbol_stats,
+        timed_out,
+        timings: None,
+        degradations,
+    };
+
+    // Everything else we can tell about the crash is up to the analysis
+    // passes, built-in and otherwise.
+    let context = AnalysisContext {
+        options: &options,
+        crashing_context,
+        threads: &dump_threads,
+        memory_list: &unified_memory_list,
+        memory_info: memory_info.as_ref(),
+        handles: handle_data.as_ref(),
+        raw_streams: &raw_streams,
+    };
+    let analysis_passes = analysis::run_passes(&context, &mut state);
+
+    if !options.out_of_time() {
+        source_context::add_source_context(&options, &mut state).await;
+    }
+
+    if options.record_timings {
+        state.timings = Some(ProcessTimings {
+            total: processing_started.elapsed(),
+            read_streams,
+            prefetch_symbols,
+            threads: thread_times,
+            symbols: symbol_provider.symbol_times(),
+            analysis_passes,
+        });
+    }
+
+    Ok(state)
+}
+
+/// A name for a thread that started running at `address`, like WinDbg gives
+/// them: `module!function`, or `module+0xoffset` without symbols.
+async fn start_address_name<P>(
+    address: u64,
+    modules: &MinidumpModuleList,
+    symbol_provider: &P,
+) -> Option<String>
+where
+    P: SymbolProvider + Sync,
+{
+    struct DummyFrame {
+        instruction: u64,
+        function_name: Option<String>,
+    }
+    impl FrameSymbolizer for DummyFrame {
+        fn get_instruction(&self) -> u64 {
+            self.instruction
+        }
+        fn set_function(&mut self, name: &str, _base: u64, _parameter_size: u32) {
+            self.function_name = Some(String::from(name));
+        }
+        fn set_source_file(&mut self, _file: &str, _line: u32, _base: u64) {
+            // Do nothing
+        }
+    }
+
+    let module = modules.module_at_address(address)?;
+    let code_file = module.code_file();
+    let module_name = basename(&code_file);
+    let mut frame = DummyFrame {
+        instruction: address,
+        function_name: None,
+    };
+    // Without symbols for the module, the function is just left unknown.
+    let _ = symbol_provider.fill_symbol(module, &mut frame).await;
+    let name = match frame.function_name {
+        Some(function_name) => format!("{}!{}", module_name, function_name),
+        None => format!("{}+{:#x}", module_name, address - module.base_address()),
+    };
+    Some(name)
+}
+
+/// Whether `module` is the part of WOW64 that switches between 32-bit and
+/// 64-bit code.
+fn is_wow64_cpu_module(module: &MinidumpModule) -> bool {
+    let code_file = module.code_file();
+    let name = code_file.rsplit(['\\', '/']).next();
+    matches!(name, Some(name) if name.eq_ignore_ascii_case("wow64cpu.dll"))
+}
+
+/// Puts the 32-bit frames of a WOW64 thread below the 64-bit frames that
+/// were running on its behalf, in place of the frames of the WOW64 layer's
+/// own setup code.
+fn stitch_wow64_stack(stack: &mut CallStack, wow64_stack: CallStack) {
+    // The 64-bit frames that matter end where wow64cpu.dll was entered from
+    // 32-bit code.
+    let transition = stack
+        .frames
+        .iter()
+        .position(|frame| matches!(frame.module, Some(ref module) if is_wow64_cpu_module(module)));
+    if let Some(transition) = transition {
+        stack.frames.truncate(transition + 1);
+    }
+    stack.frames.extend(wow64_stack.frames);
+    stack.recursion_collapsed |= wow64_stack.recursion_collapsed;
+    if matches!(
+        wow64_stack.info,
+        CallStackInfo::FrameLimitReached | CallStackInfo::WalkStopped
+    ) {
+        stack.info = wow64_stack.info;
+    }
+}
+
+/// Gets the region of memory containing `address`, as a `MinidumpMemory`
+/// that the stackwalker can use as a stack.
+/// Cut `stack` down to the stack memory `options` allow, with `used` bytes
+/// of the total already walked, returning whether anything was cut.
+///
+/// A stack bigger than [`ProcessorOptions::max_stack_size`] keeps the
+/// memory from `stack_pointer` up (or from its start, if the stack pointer
+/// isn't in it), which is where the frames are.
+fn limit_stack<'a>(
+    stack: Option<&MinidumpMemory<'a>>,
+    stack_pointer: Option<u64>,
+    options: &ProcessorOptions,
+    used: &AtomicU64,
+) -> (Option<MinidumpMemory<'a>>, bool) {
+    let stack = match stack {
+        Some(stack) => stack,
+        None => return (None, false),
+    };
+    let end = stack.base_address.saturating_add(stack.size);
+    let (mut start, mut size) = (stack.base_address, stack.size);
+    if let Some(max_size) = options.max_stack_size {
+        if size > max_size {
+            start = stack_pointer
+                .filter(|&sp| sp >= stack.base_address && sp < end)
+                .unwrap_or(stack.base_address);
+            size = max_size.min(end - start);
+        }
+    }
+    if let Some(max_total) = options.max_total_stack_size {
+        // Each thread claims its stack before it first awaits anything, so
+        // this goes in thread order (but for the 32-bit stacks of WOW64
+        // threads, which are claimed after their 64-bit ones are walked).
+        let mut claimed = size;
+        let _ = used.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |used| {
+            claimed = size.min(max_total.saturating_sub(used));
+            Some(used + claimed)
+        });
+        size = claimed;
+    }
+    if (start, size) == (stack.base_address, stack.size) {
+        return (Some(stack.clone()), false);
+    }
+    if size == 0 {
+        return (None, true);
+    }
+    let offset = (start - stack.base_address) as usize;
+    let bytes = stack.bytes.get(offset..).unwrap_or_default();
+    let bytes = &bytes[..bytes.len().min(size as usize)];
+    let memory = MinidumpMemory {
+        desc: Default::default(),
+        base_address: start,
+        size: bytes.len() as u64,
+        bytes,
+    };
+    (Some(memory), true)
+}
+
+/// `stack`, cut down to the `bounds` of the thread's stack from its TEB.
+///
+/// The bottom of the stack is only raised as far as the stack pointer, which
+/// can be below the TEB's limit when the thread overflowed its stack. If the
+/// stack pointer is above the base, the bounds are some other stack's, and
+/// `stack` is left alone.
+fn clip_stack<'a>(
+    stack: Option<MinidumpMemory<'a>>,
+    bounds: Option<(u64, u64)>,
+    stack_pointer: Option<u64>,
+) -> Option<MinidumpMemory<'a>> {
+    let (stack, (limit, base)) = match (stack, bounds) {
+        (Some(stack), Some(bounds)) => (stack, bounds),
+        (stack, _) => return stack,
+    };
+    if stack_pointer.map_or(false, |sp| sp >= base) {
+        return Some(stack);
+    }
+    let stack_end = stack.base_address.saturating_add(stack.size);
+    let start = stack
+        .base_address
+        .max(stack_pointer.map_or(limit, |sp| sp.min(limit)));
+    let end = stack_end.min(base);
+    if start >= end || (start, end) == (stack.base_address, stack_end) {
+        return Some(stack);
+    }
+    let offset = (start - stack.base_address) as usize;
+    let bytes = stack.bytes.get(offset..).unwrap_or_default();
+    let bytes = &bytes[..bytes.len().min((end - start) as usize)];
+    Some(MinidumpMemory {
+        desc: Default::default(),
+        base_address: start,
+        size: bytes.len() as u64,
+        bytes,
+    })
+}
+
+fn memory_containing<'a>(
+    memory_list: &UnifiedMemoryList<'a>,
+    address: u64,
+) -> Option<MinidumpMemory<'a>> {
+    let memory = memory_list.memory_at_address(address)?;
+    Some(MinidumpMemory {
+        desc: Default::default(),
+        base_address: memory.base_address(),
+        size: memory.size(),
+        bytes: memory.bytes(),
     })
 }
+
+/// The size in bytes of a `/proc/self/status` size like "123456 kB".
+fn parse_proc_status_size(size: &str) -> Option<u64> {
+    let kilobytes = size.strip_suffix("kB")?.trim_end().parse::<u64>().ok()?;
+    kilobytes.checked_mul(1024)
+}
@@ -0,0 +1,232 @@
+// Copyright 2015 Ted Mielczarek. See the COPYRIGHT
+// file at the top-level directory of this distribution.
+
+//! A [`ProcessState`] as protobuf, for storing processed crashes in bulk.
+//!
+//! The messages here are those of `processed-crash.proto` at the root of
+//! this crate, which is the schema to generate readers from. They're a
+//! smaller, faster to encode subset of the JSON report: the system, the
+//! crash, the modules, and the threads' frames.
+//!
+//! This needs the `protobuf` feature.
+
+use std::collections::{BTreeMap, HashMap};
+
+use minidump::Module as _;
+use prost::Message;
+
+use crate::process_state::{basename, CallStack, ProcessState, StackFrame};
+use crate::signature::SignatureOptions;
+
+/// A processed crash, from [`ProcessState::to_protobuf`].
+#[derive(Clone, PartialEq, Message)]
+pub struct ProcessedCrash {
+    #[prost(message, optional, tag = "1")]
+    pub system_info: Option<SystemInfo>,
+    #[prost(message, optional, tag = "2")]
+    pub crash_info: Option<CrashInfo>,
+    #[prost(string, tag = "3")]
+    pub signature: String,
+    #[prost(string, optional, tag = "4")]
+    pub crash_hash: Option<String>,
+    #[prost(uint32, optional, tag = "5")]
+    pub pid: Option<u32>,
+    /// The index in `modules` of the main executable.
+    #[prost(uint32, optional, tag = "6")]
+    pub main_module: Option<u32>,
+    #[prost(message, repeated, tag = "7")]
+    pub modules: Vec<Module>,
+    #[prost(message, repeated, tag = "8")]
+    pub threads: Vec<Thread>,
+    #[prost(btree_map = "string, string", tag = "9")]
+    pub annotations: BTreeMap<String, String>,
+}
+
+/// The system the crash happened on.
+#[derive(Clone, PartialEq, Message)]
+pub struct SystemInfo {
+    #[prost(string, tag = "1")]
+    pub os: String,
+    #[prost(string, optional, tag = "2")]
+    pub os_version: Option<String>,
+    #[prost(string, tag = "3")]
+    pub cpu_arch: String,
+    #[prost(string, optional, tag = "4")]
+    pub cpu_info: Option<String>,
+    #[prost(uint32, tag = "5")]
+    pub cpu_count: u32,
+}
+
+/// What went wrong.
+#[derive(Clone, PartialEq, Message)]
+pub struct CrashInfo {
+    #[prost(string, optional, tag = "1")]
+    pub r#type: Option<String>,
+    #[prost(uint64, optional, tag = "2")]
+    pub address: Option<u64>,
+    /// The index in `threads` of the crashing thread.
+    #[prost(uint32, optional, tag = "3")]
+    pub crashing_thread: Option<u32>,
+    #[prost(string, optional, tag = "4")]
+    pub assertion: Option<String>,
+}
+
+/// A module loaded in the process.
+#[derive(Clone, PartialEq, Message)]
+pub struct Module {
+    #[prost(uint64, tag = "1")]
+    pub base_address: u64,
+    #[prost(uint64, tag = "2")]
+    pub size: u64,
+    #[prost(string, tag = "3")]
+    pub filename: String,
+    #[prost(string, tag = "4")]
+    pub code_id: String,
+    #[prost(string, optional, tag = "5")]
+    pub debug_file: Option<String>,
+    #[prost(string, optional, tag = "6")]
+    pub debug_id: Option<String>,
+    #[prost(string, optional, tag = "7")]
+    pub version: Option<String>,
+    #[prost(bool, tag = "8")]
+    pub missing_symbols: bool,
+    #[prost(bool, tag = "9")]
+    pub loaded_symbols: bool,
+    #[prost(bool, tag = "10")]
+    pub corrupt_symbols: bool,
+}
+
+/// A thread and its stack.
+#[derive(Clone, PartialEq, Message)]
+pub struct Thread {
+    #[prost(uint32, tag = "1")]
+    pub thread_id: u32,
+    #[prost(string, optional, tag = "2")]
+    pub thread_name: Option<String>,
+    #[prost(string, optional, tag = "3")]
+    pub last_error: Option<String>,
+    #[prost(message, repeated, tag = "4")]
+    pub frames: Vec<Frame>,
+}
+
+/// A frame of a thread's stack.
+#[derive(Clone, PartialEq, Message)]
+pub struct Frame {
+    #[prost(uint64, tag = "1")]
+    pub instruction: u64,
+    /// The index in `modules` of the module the instruction is in.
+    #[prost(uint32, optional, tag = "2")]
+    pub module: Option<u32>,
+    #[prost(string, optional, tag = "3")]
+    pub function: Option<String>,
+    #[prost(uint64, optional, tag = "4")]
+    pub function_base: Option<u64>,
+    #[prost(string, optional, tag = "5")]
+    pub file: Option<String>,
+    #[prost(uint32, optional, tag = "6")]
+    pub line: Option<u32>,
+    #[prost(string, tag = "7")]
+    pub trust: String,
+}
+
+impl ProcessState {
+    /// This state as a [`ProcessedCrash`] message, with the signature made
+    /// with the default [`SignatureOptions`].
+    pub fn to_protobuf(&self) -> ProcessedCrash {
+        let options = SignatureOptions::default();
+        let sys = &self.system_info;
+        let module_indices: HashMap<u64, u32> = self
+            .modules
+            .iter()
+            .enumerate()
+            .map(|(index, module)| (module.base_address(), index as u32))
+            .collect();
+
+        ProcessedCrash {
+            system_info: Some(SystemInfo {
+                os: String::from(sys.os.long_name()),
+                os_version: sys.format_os_version().map(String::from),
+                cpu_arch: sys.cpu.to_string(),
+                cpu_info: sys.cpu_info.clone(),
+                cpu_count: sys.cpu_count as u32,
+            }),
+            crash_info: Some(CrashInfo {
+                r#type: self.crash_reason.map(|reason| reason.to_string()),
+                address: self.crash_address,
+                crashing_thread: self.requesting_thread.map(|index| index as u32),
+                assertion: self
+                    .assertion
+                    .as_ref()
+                    .map(|assertion| assertion.to_string()),
+            }),
+            signature: self.signature(&options),
+            crash_hash: self.crash_hash(&options),
+            pid: self.process_id,
+            main_module: self.modules.main_module().map(|_| 0),
+            modules: self
+                .modules
+                .iter()
+                .map(|module| {
+                    let code_file = module.code_file();
+                    let filename = basename(&code_file);
+                    let stats = self.symbol_stats.get(filename);
+                    Module {
+                        base_address: module.base_address(),
+                        size: module.size(),
+                        filename: String::from(filename),
+                        code_id: module.code_identifier().into_owned(),
+                        debug_file: module
+                            .debug_file()
+                            .map(|debug_file| String::from(basename(&debug_file))),
+                        debug_id: module.debug_identifier().map(String::from),
+                        version: module.version().map(String::from),
+                        // Like in the JSON, symbols are only missing if they
+                        // were looked for.
+                        missing_symbols: stats.map_or(false, |stats| !stats.loaded_symbols),
+                        loaded_symbols: stats.map_or(false, |stats| stats.loaded_symbols),
+                        corrupt_symbols: stats.map_or(false, |stats| stats.corrupt_symbols),
+                    }
+                })
+                .collect(),
+            threads: self
+                .threads
+                .iter()
+                .map(|thread| thread_protobuf(thread, &module_indices))
+                .collect(),
+            annotations: self.annotations.clone(),
+        }
+    }
+
+    /// This state encoded as a [`ProcessedCrash`] message.
+    pub fn encode_protobuf(&self) -> Vec<u8> {
+        self.to_protobuf().encode_to_vec()
+    }
+}
+
+fn thread_protobuf(thread: &CallStack, module_indices: &HashMap<u64, u32>) -> Thread {
+    Thread {
+        thread_id: thread.thread_id,
+        thread_name: thread.thread_name.clone(),
+        last_error: thread.last_error_value.map(|error| error.to_string()),
+        frames: thread
+            .frames
+            .iter()
+            .map(|frame| frame_protobuf(frame, module_indices))
+            .collect(),
+    }
+}
+
+fn frame_protobuf(frame: &StackFrame, module_indices: &HashMap<u64, u32>) -> Frame {
+    Frame {
+        instruction: frame.instruction,
+        module: frame
+            .module
+            .as_ref()
+            .and_then(|module| module_indices.get(&module.base_address()).copied()),
+        function: frame.function_name.clone(),
+        function_base: frame.function_base,
+        file: frame.source_file_name.clone(),
+        line: frame.source_line,
+        trust: String::from(frame.trust.json_name()),
+    }
+}
@@ -0,0 +1,175 @@
+// Copyright 2015 Ted Mielczarek. See the COPYRIGHT
+// file at the top-level directory of this distribution.
+
+//! Finding out what the crashing thread's registers point at.
+//!
+//! Knowing that one register points into a module and another into some
+//! thread's stack is often enough to tell what the crashing code was doing
+//! with them, without needing to read the disassembly.
+
+use std::fmt;
+
+use minidump::{
+    MinidumpContext, MinidumpLinuxMapKind, MinidumpModuleList, MinidumpThread, Module,
+    UnifiedMemoryInfo, UnifiedMemoryInfoList,
+};
+
+use crate::process_state::basename;
+
+/// Values below this are taken to be small integers rather than pointers.
+const MIN_POINTER: u64 = 64 * 1024;
+
+/// Registers that don't hold pointers.
+const IGNORED_REGISTERS: &[&str] = &["efl"];
+
+/// What a register points at.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum PointerTarget {
+    /// A loaded module.
+    Module {
+        /// The file name of the module.
+        name: String,
+        /// How far into the module the pointer is.
+        offset: u64,
+    },
+    /// The stack of a thread.
+    Stack {
+        /// The index of the thread in [`ProcessState::threads`][crate::ProcessState::threads].
+        thread: usize,
+    },
+    /// Memory that isn't backed by a file, which is usually the heap.
+    Heap,
+    /// Memory that's backed by a file that isn't a loaded module.
+    Mapped,
+    /// Memory that isn't mapped at all.
+    Unmapped,
+}
+
+impl PointerTarget {
+    /// The name of the kind of target in the JSON schema.
+    pub fn json_name(&self) -> &'static str {
+        match self {
+            PointerTarget::Module { .. } => "module",
+            PointerTarget::Stack { .. } => "stack",
+            PointerTarget::Heap => "heap",
+            PointerTarget::Mapped => "mapped",
+            PointerTarget::Unmapped => "unmapped",
+        }
+    }
+}
+
+impl fmt::Display for PointerTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PointerTarget::Module { name, offset } => write!(f, "{} + {:#x}", name, offset),
+            PointerTarget::Stack { thread } => write!(f, "thread {} stack", thread),
+            PointerTarget::Heap => f.write_str("heap"),
+            PointerTarget::Mapped => f.write_str("mapped file"),
+            PointerTarget::Unmapped => f.write_str("unmapped"),
+        }
+    }
+}
+
+/// A register of the crashing thread and what it points at.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RegisterTarget {
+    /// The name of the register.
+    pub register: String,
+    /// The value of the register.
+    pub value: u64,
+    /// What the value points at.
+    pub target: PointerTarget,
+}
+
+/// Find what each register of the crashing thread's `context` points at.
+///
+/// Registers holding small integers, and registers pointing at memory
+/// nothing is known about, are left out. Without memory info, only modules
+/// and stacks are recognized.
+pub(crate) fn find_targets(
+    context: &MinidumpContext,
+    threads: &[MinidumpThread],
+    modules: &MinidumpModuleList,
+    memory_info: Option<&UnifiedMemoryInfoList>,
+) -> Vec<RegisterTarget> {
+    let stacks = threads
+        .iter()
+        .map(|thread| stack_range(thread, memory_info))
+        .collect::<Vec<_>>();
+
+    context
+        .general_purpose_registers()
+        .iter()
+        .filter(|&&reg| !IGNORED_REGISTERS.contains(&reg))
+        .filter_map(|&reg| {
+            let value = context.get_register(reg)?;
+            if value < MIN_POINTER {
+                return None;
+            }
+            let target = pointer_target(value, &stacks, modules, memory_info)?;
+            Some(RegisterTarget {
+                register: String::from(reg),
+                value,
+                target,
+            })
+        })
+        .collect()
+}
+
+/// What `address` points at, if anything is known about it.
+fn pointer_target(
+    address: u64,
+    stacks: &[Option<std::ops::Range<u64>>],
+    modules: &MinidumpModuleList,
+    memory_info: Option<&UnifiedMemoryInfoList>,
+) -> Option<PointerTarget> {
+    if let Some(module) = modules.module_at_address(address) {
+        return Some(PointerTarget::Module {
+            name: String::from(basename(&module.code_file())),
+            offset: address - module.base_address(),
+        });
+    }
+    if let Some(thread) = stacks
+        .iter()
+        .position(|stack| matches!(stack, Some(stack) if stack.contains(&address)))
+    {
+        return Some(PointerTarget::Stack { thread });
+    }
+    let region = match memory_info?.region_containing(address) {
+        Some(region) => region,
+        None => return Some(PointerTarget::Unmapped),
+    };
+    // Linux doesn't consider its heap anonymous, since it has a name.
+    let is_heap = match region {
+        UnifiedMemoryInfo::Map(map) => !matches!(
+            map.kind,
+            MinidumpLinuxMapKind::File(_) | MinidumpLinuxMapKind::DeletedFile(_)
+        ),
+        UnifiedMemoryInfo::Info(_) => region.is_anonymous(),
+    };
+    if is_heap {
+        Some(PointerTarget::Heap)
+    } else {
+        Some(PointerTarget::Mapped)
+    }
+}
+
+/// The addresses of `thread`'s stack: the whole region it's in if the
+/// minidump includes memory info, and otherwise just the part that was saved.
+fn stack_range(
+    thread: &MinidumpThread,
+    memory_info: Option<&UnifiedMemoryInfoList>,
+) -> Option<std::ops::Range<u64>> {
+    let start = thread.raw.stack.start_of_memory_range;
+    let size = u64::from(thread.raw.stack.memory.data_size);
+    if start == 0 || size == 0 {
+        return None;
+    }
+    // Memory info ranges include their last address.
+    memory_info
+        .and_then(|info| info.region_containing(start)?.memory_range())
+        .map(|range| range.start..range.end.saturating_add(1))
+        .or_else(|| Some(start..start.checked_add(size)?))
+}
@@ -0,0 +1,255 @@
+// Copyright 2015 Ted Mielczarek. See the COPYRIGHT
+// file at the top-level directory of this distribution.
+
+//! Serializing the parts of a [`ProcessState`][crate::ProcessState] that
+//! come from other crates, for the `serde` feature.
+//!
+//! These are used through `#[serde(serialize_with = "...")]`, and write out
+//! roughly what [`ProcessState::print_json`][crate::ProcessState::print_json]
+//! does, but with addresses as plain numbers. Unlike the JSON schema, what
+//! they write isn't stable.
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+use minidump::format::MinidumpType;
+use minidump::system_info::{Cpu, Os};
+use minidump::{
+    CrashReason, MinidumpContext, MinidumpContextValidity, MinidumpModule, MinidumpModuleList,
+    MinidumpTruncatedStream, MinidumpUnimplementedStream, MinidumpUnknownStream,
+    MinidumpUnloadedModule, MinidumpUnloadedModuleList, Module, RawMacCrashInfo,
+};
+use serde::ser::{SerializeMap, Serializer};
+use serde::Serialize;
+
+use crate::SymbolStats;
+
+/// The identifying parts of a module.
+#[derive(Serialize)]
+struct ModuleInfo<'a> {
+    base_address: u64,
+    size: u64,
+    code_file: Cow<'a, str>,
+    code_identifier: Cow<'a, str>,
+    debug_file: Option<Cow<'a, str>>,
+    debug_identifier: Option<Cow<'a, str>>,
+    version: Option<Cow<'a, str>>,
+}
+
+impl<'a> ModuleInfo<'a> {
+    fn new(module: &'a dyn Module) -> ModuleInfo<'a> {
+        ModuleInfo {
+            base_address: module.base_address(),
+            size: module.size(),
+            code_file: module.code_file(),
+            code_identifier: module.code_identifier(),
+            debug_file: module.debug_file(),
+            debug_identifier: module.debug_identifier(),
+            version: module.version(),
+        }
+    }
+}
+
+pub(crate) fn module<S: Serializer>(
+    module: &Option<Arc<MinidumpModule>>,
+    s: S,
+) -> Result<S::Ok, S::Error> {
+    module
+        .as_ref()
+        .map(|module| ModuleInfo::new(&**module))
+        .serialize(s)
+}
+
+pub(crate) fn unloaded_module<S: Serializer>(
+    module: &Option<MinidumpUnloadedModule>,
+    s: S,
+) -> Result<S::Ok, S::Error> {
+    module
+        .as_ref()
+        .map(|module| ModuleInfo::new(module))
+        .serialize(s)
+}
+
+pub(crate) fn modules<S: Serializer>(
+    modules: &MinidumpModuleList,
+    s: S,
+) -> Result<S::Ok, S::Error> {
+    s.collect_seq(modules.iter().map(|module| ModuleInfo::new(module)))
+}
+
+pub(crate) fn unloaded_modules<S: Serializer>(
+    modules: &MinidumpUnloadedModuleList,
+    s: S,
+) -> Result<S::Ok, S::Error> {
+    s.collect_seq(modules.iter().map(|module| ModuleInfo::new(module)))
+}
+
+/// The valid general purpose registers of `context`, by name, formatted as
+/// hex strings like the JSON output's.
+pub(crate) fn context<S: Serializer>(context: &MinidumpContext, s: S) -> Result<S::Ok, S::Error> {
+    let registers = context.general_purpose_registers();
+    let mut map = s.serialize_map(None)?;
+    for &reg in registers {
+        let valid = match context.valid {
+            MinidumpContextValidity::All => true,
+            MinidumpContextValidity::Some(ref which) => which.contains(reg),
+        };
+        if valid {
+            map.serialize_entry(reg, &context.format_register(reg))?;
+        }
+    }
+    map.end()
+}
+
+pub(crate) fn optional_context<S: Serializer>(
+    context: &Option<MinidumpContext>,
+    s: S,
+) -> Result<S::Ok, S::Error> {
+    match context {
+        Some(context) => self::context(context, s),
+        None => s.serialize_none(),
+    }
+}
+
+pub(crate) fn crash_reason<S: Serializer>(
+    reason: &Option<CrashReason>,
+    s: S,
+) -> Result<S::Ok, S::Error> {
+    reason.map(|reason| reason.to_string()).serialize(s)
+}
+
+pub(crate) fn dump_type<S: Serializer>(dump_type: &MinidumpType, s: S) -> Result<S::Ok, S::Error> {
+    s.serialize_u64(dump_type.bits())
+}
+
+pub(crate) fn os<S: Serializer>(os: &Os, s: S) -> Result<S::Ok, S::Error> {
+    s.collect_str(os)
+}
+
+pub(crate) fn cpu<S: Serializer>(cpu: &Cpu, s: S) -> Result<S::Ok, S::Error> {
+    s.collect_str(cpu)
+}
+
+/// The fields of a mac crash info record that are present.
+#[derive(Serialize)]
+struct MacCrashInfo<'a> {
+    thread: Option<&'a u64>,
+    dialog_mode: Option<&'a u64>,
+    abort_cause: Option<&'a u64>,
+    module_path: Option<&'a str>,
+    message: Option<&'a str>,
+    signature_string: Option<&'a str>,
+    backtrace: Option<&'a str>,
+    message2: Option<&'a str>,
+}
+
+pub(crate) fn mac_crash_info<S: Serializer>(
+    info: &Option<Vec<RawMacCrashInfo>>,
+    s: S,
+) -> Result<S::Ok, S::Error> {
+    info.as_ref()
+        .map(|records| {
+            records
+                .iter()
+                .map(|record| MacCrashInfo {
+                    thread: record.thread(),
+                    dialog_mode: record.dialog_mode(),
+                    abort_cause: record.abort_cause(),
+                    module_path: record.module_path(),
+                    message: record.message(),
+                    signature_string: record.signature_string(),
+                    backtrace: record.backtrace(),
+                    message2: record.message2(),
+                })
+                .collect::<Vec<_>>()
+        })
+        .serialize(s)
+}
+
+/// Where a stream is in the minidump.
+#[derive(Serialize)]
+struct StreamInfo {
+    stream_type: u32,
+    vendor: &'static str,
+    rva: u32,
+    data_size: u32,
+    /// Only for truncated streams.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    available_size: Option<u32>,
+}
+
+pub(crate) fn unknown_streams<S: Serializer>(
+    streams: &[MinidumpUnknownStream],
+    s: S,
+) -> Result<S::Ok, S::Error> {
+    s.collect_seq(streams.iter().map(|stream| StreamInfo {
+        stream_type: stream.stream_type,
+        vendor: stream.vendor,
+        rva: stream.location.rva,
+        data_size: stream.location.data_size,
+        available_size: None,
+    }))
+}
+
+pub(crate) fn unimplemented_streams<S: Serializer>(
+    streams: &[MinidumpUnimplementedStream],
+    s: S,
+) -> Result<S::Ok, S::Error> {
+    s.collect_seq(streams.iter().map(|stream| StreamInfo {
+        stream_type: stream.stream_type as u32,
+        vendor: stream.vendor,
+        rva: stream.location.rva,
+        data_size: stream.location.data_size,
+        available_size: None,
+    }))
+}
+
+pub(crate) fn truncated_streams<S: Serializer>(
+    streams: &[MinidumpTruncatedStream],
+    s: S,
+) -> Result<S::Ok, S::Error> {
+    s.collect_seq(streams.iter().map(|stream| StreamInfo {
+        stream_type: stream.stream_type,
+        vendor: stream.vendor,
+        rva: stream.location.rva,
+        data_size: stream.location.data_size,
+        available_size: Some(stream.available_size),
+    }))
+}
+
+/// [`SymbolStats`], which comes from whichever symbolizer is in use.
+#[derive(Serialize)]
+struct SymbolStatsInfo<'a> {
+    symbol_url: Option<&'a str>,
+    symbol_path: Option<&'a Path>,
+    symbol_file_size: Option<u64>,
+    fetch_duration: Option<Duration>,
+    parse_duration: Option<Duration>,
+    cache_hit: bool,
+    loaded_symbols: bool,
+    corrupt_symbols: bool,
+}
+
+pub(crate) fn symbol_stats<S: Serializer>(
+    stats: &HashMap<String, SymbolStats>,
+    s: S,
+) -> Result<S::Ok, S::Error> {
+    s.collect_map(stats.iter().map(|(name, stats)| {
+        (
+            name,
+            SymbolStatsInfo {
+                symbol_url: stats.symbol_url.as_deref(),
+                symbol_path: stats.symbol_path.as_deref(),
+                symbol_file_size: stats.symbol_file_size,
+                fetch_duration: stats.fetch_duration,
+                parse_duration: stats.parse_duration,
+                cache_hit: stats.cache_hit,
+                loaded_symbols: stats.loaded_symbols,
+                corrupt_symbols: stats.corrupt_symbols,
+            },
+        )
+    }))
+}
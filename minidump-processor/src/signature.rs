@@ -0,0 +1,505 @@
+// Copyright 2015 Ted Mielczarek. See the COPYRIGHT
+// file at the top-level directory of this distribution.
+
+//! Crash signatures, for grouping crashes that happened in the same place.
+//!
+//! A signature is made from the frames of the crashing thread, in the same
+//! way as [Socorro]'s: each frame is normalized into a short name for where
+//! it is, frames that say nothing about the crash (like `abort`) are skipped,
+//! and the first interesting frame is taken, along with its callers for as
+//! long as the frames are ones that are only interesting together with
+//! their caller (like `malloc`). The names are joined with `" | "`.
+//!
+//! Which frames are skipped or have their callers added is decided by lists
+//! of patterns in [`SignatureOptions`]. Patterns are matched against the
+//! whole normalized name of a frame, and may have any number of `*`
+//! wildcards, which match any (possibly empty) run of characters.
+//!
+//! A [`crash_hash`] is a shorter key for grouping crashes, made from the
+//! normalized names of the first few frames instead. Unlike a signature, it
+//! never changes between versions of this crate unless the normalization
+//! does, so it can be stored and compared without a backend that knows how
+//! signatures are made.
+//!
+//! [Socorro]: https://github.com/mozilla-services/socorro
+
+use crate::process_state::{basename, StackFrame};
+use minidump::Module;
+
+/// The default for [`SignatureOptions::max_len`].
+pub const DEFAULT_SIGNATURE_MAX_LEN: usize = 255;
+
+/// The default for [`SignatureOptions::hash_depth`].
+pub const DEFAULT_HASH_DEPTH: usize = 10;
+
+/// The default for [`SignatureOptions::irrelevant`].
+pub const DEFAULT_IRRELEVANT: &[&str] = &[
+    // Frames with no module, or in system libraries we have no symbols for.
+    "@0x*",
+    "libc.so*@0x*",
+    "libc-*.so@0x*",
+    "libpthread*.so*@0x*",
+    "ntdll.dll@0x*",
+    "kernel32.dll@0x*",
+    "KERNELBASE.dll@0x*",
+    "libsystem_kernel.dylib@0x*",
+    "libsystem_c.dylib@0x*",
+    // How crashes are raised, rather than why.
+    "abort",
+    "raise",
+    "__GI_abort",
+    "__GI_raise",
+    "__abort_with_payload",
+    "__pthread_kill",
+    "pthread_kill",
+    "_CxxThrowException",
+    "CxxThrowException",
+    "RaiseException",
+    "RaiseFailFastException",
+    "KiFastSystemCallRet",
+    "NtWaitForMultipleObjects",
+    "NtWaitForSingleObject",
+    "WaitForMultipleObjects*",
+    "WaitForSingleObject*",
+    "_invalid_parameter_noinfo*",
+    "__kernel_vsyscall",
+    "__rust_start_panic",
+    "rust_begin_unwind",
+    "rust_panic",
+    "panic_abort::*",
+    "std::panicking::*",
+    "std::process::abort",
+    "std::sys::*::abort_internal",
+    "core::panicking::*",
+];
+
+/// The default for [`SignatureOptions::prefix`].
+pub const DEFAULT_PREFIX: &[&str] = &[
+    // Allocators and C string functions crash because of what they're given.
+    "malloc",
+    "calloc",
+    "realloc",
+    "free",
+    "operator new*",
+    "operator delete*",
+    "moz_xmalloc",
+    "moz_xcalloc",
+    "moz_xrealloc",
+    "je_malloc",
+    "je_free",
+    "HeapAlloc",
+    "HeapFree",
+    "RtlAllocateHeap",
+    "RtlFreeHeap",
+    "memcpy",
+    "memmove",
+    "memset",
+    "memcmp",
+    "strlen",
+    "strcmp",
+    "strncmp",
+    "strcpy",
+    "wcslen",
+    "objc_msgSend",
+    "objc_release",
+    "objc_retain",
+    // Rust panics in the standard library are its caller's fault.
+    "core::option::expect_failed",
+    "core::option::unwrap_failed",
+    "core::result::unwrap_failed",
+    "core::option::Option<T>::expect",
+    "core::option::Option<T>::unwrap",
+    "core::result::Result<T>::expect",
+    "core::result::Result<T>::unwrap",
+    "core::slice::index::*",
+    "core::str::slice_error_fail*",
+    "alloc::alloc::handle_alloc_error*",
+];
+
+/// The default for [`SignatureOptions::sentinels`].
+pub const DEFAULT_SENTINELS: &[&str] = &["_purecall", "__report_gsfailure"];
+
+/// How [`signature`] computes a signature.
+///
+/// The pattern lists are matched against normalized frame names, see the
+/// [module docs][self].
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct SignatureOptions {
+    /// Frames that are never part of a signature.
+    pub irrelevant: Vec<String>,
+    /// Frames that are part of a signature together with their caller.
+    pub prefix: Vec<String>,
+    /// Frames that a signature starts at if they're anywhere on the stack,
+    /// ignoring the frames above them.
+    ///
+    /// These are for functions that crash on purpose when something has gone
+    /// wrong much earlier, like `_purecall`, which would otherwise be
+    /// irrelevant and skipped.
+    pub sentinels: Vec<String>,
+    /// Whether to remove the argument list of functions, like the `(int)` of
+    /// `foo(int)`, so overloads are grouped together.
+    pub strip_arguments: bool,
+    /// Whether to replace the arguments of templates with `T`, like
+    /// `Vec<T>` for `Vec<u8>`, so instantiations are grouped together.
+    pub collapse_templates: bool,
+    /// The longest a signature can be, in bytes. Longer signatures are cut
+    /// off with `...`.
+    pub max_len: usize,
+    /// How many frames a [`crash_hash`] is made from.
+    pub hash_depth: usize,
+}
+
+impl Default for SignatureOptions {
+    fn default() -> Self {
+        let owned = |patterns: &[&str]| patterns.iter().map(|p| p.to_string()).collect();
+        Self {
+            irrelevant: owned(DEFAULT_IRRELEVANT),
+            prefix: owned(DEFAULT_PREFIX),
+            sentinels: owned(DEFAULT_SENTINELS),
+            strip_arguments: true,
+            collapse_templates: true,
+            max_len: DEFAULT_SIGNATURE_MAX_LEN,
+            hash_depth: DEFAULT_HASH_DEPTH,
+        }
+    }
+}
+
+/// The signature of a crash in the thread with `frames`.
+pub fn signature(frames: &[StackFrame], options: &SignatureOptions) -> String {
+    let names: Vec<String> = frames
+        .iter()
+        // Collapsed recursion and skipped stack aren't frames of their own.
+        .filter(|frame| frame.repeated.is_none() && frame.gap.is_none())
+        .map(|frame| normalize_frame(frame, options))
+        .collect();
+    if names.is_empty() {
+        return String::from("EMPTY: no frame data available");
+    }
+
+    let sentinel = names
+        .iter()
+        .position(|name| matches_any(&options.sentinels, name));
+    let start = sentinel.unwrap_or(0);
+    let mut parts = vec![];
+    for (i, name) in names.iter().enumerate().skip(start) {
+        if sentinel != Some(i) && matches_any(&options.irrelevant, name) {
+            continue;
+        }
+        parts.push(name.as_str());
+        if !matches_any(&options.prefix, name) {
+            break;
+        }
+    }
+    if parts.is_empty() {
+        // Everything was irrelevant, but that's still better than nothing.
+        parts.push(&names[start]);
+    }
+
+    let mut signature = parts.join(" | ");
+    if signature.len() > options.max_len {
+        let mut end = options.max_len.saturating_sub(3);
+        while !signature.is_char_boundary(end) {
+            end -= 1;
+        }
+        signature.truncate(end);
+        signature.push_str("...");
+    }
+    signature
+}
+
+/// A hash of the first [`SignatureOptions::hash_depth`] frames of a crash in
+/// the thread with `frames`, as 16 hex digits, or `None` if there are no
+/// frames.
+///
+/// Frames are named as in a signature, except that frames in no module are
+/// all named `?`, since their addresses change from run to run. The names
+/// are hashed with 64-bit FNV-1a, which (unlike Rust's own hashers) is the
+/// same everywhere and forever.
+pub fn crash_hash(frames: &[StackFrame], options: &SignatureOptions) -> Option<String> {
+    let names: Vec<String> = frames
+        .iter()
+        .filter(|frame| frame.repeated.is_none() && frame.gap.is_none())
+        .take(options.hash_depth)
+        .map(|frame| {
+            if frame.function_name.is_none() && frame.module.is_none() {
+                String::from("?")
+            } else {
+                normalize_frame(frame, options)
+            }
+        })
+        .collect();
+    if names.is_empty() {
+        return None;
+    }
+    Some(format!("{:016x}", fnv1a(names.join("\n").as_bytes())))
+}
+
+/// The 64-bit FNV-1a hash of `bytes`.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0000_0100_0000_01b3;
+    bytes.iter().fold(OFFSET_BASIS, |hash, &byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(PRIME)
+    })
+}
+
+/// The name of `frame` in a signature: its normalized function name, or
+/// `module@0x<offset>` if it has no symbols, or `@0x<address>` if it isn't in
+/// a module at all.
+pub fn normalize_frame(frame: &StackFrame, options: &SignatureOptions) -> String {
+    if let Some(ref function) = frame.function_name {
+        return normalize_function(function, options);
+    }
+    match frame.module {
+        Some(ref module) => format!(
+            "{}@{:#x}",
+            basename(&module.code_file()),
+            frame.instruction.wrapping_sub(module.base_address())
+        ),
+        None => format!("@{:#x}", frame.instruction),
+    }
+}
+
+/// Normalize a function name for a signature, as `options` asks.
+///
+/// The hash at the end of mangled Rust names (like `::h0123456789abcdef`) is
+/// always removed.
+pub fn normalize_function(function: &str, options: &SignatureOptions) -> String {
+    let mut function = strip_rust_hash(function.trim());
+    if options.strip_arguments {
+        function = strip_arguments(function);
+    }
+    // The `<` and `>` of operators aren't brackets.
+    if options.collapse_templates && !function.contains("operator") {
+        collapse_templates(function)
+    } else {
+        function.to_string()
+    }
+}
+
+/// `function` without a trailing `::h<16 hex digits>`.
+fn strip_rust_hash(function: &str) -> &str {
+    match function.rsplit_once("::h") {
+        Some((rest, hash)) if hash.len() == 16 && hash.bytes().all(|b| b.is_ascii_hexdigit()) => {
+            rest
+        }
+        _ => function,
+    }
+}
+
+/// `function` without its argument list (and any qualifiers after it).
+fn strip_arguments(function: &str) -> &str {
+    let without_const = function.strip_suffix(" const").unwrap_or(function);
+    if !without_const.ends_with(')') {
+        return function;
+    }
+    let mut depth = 0;
+    for (i, c) in without_const.char_indices().rev() {
+        match c {
+            ')' => depth += 1,
+            '(' => {
+                depth -= 1;
+                if depth == 0 {
+                    // Something like `(anonymous namespace)` isn't arguments.
+                    return match without_const[..i].trim_end() {
+                        "" => function,
+                        name => name,
+                    };
+                }
+            }
+            _ => {}
+        }
+    }
+    function
+}
+
+/// `function` with the contents of all outermost template argument lists
+/// replaced by `T`.
+///
+/// Only a `<` right after a name starts template arguments, so Rust's
+/// `<Foo as Bar>::baz` keeps its brackets (though `Foo`'s arguments are still
+/// collapsed).
+fn collapse_templates(function: &str) -> String {
+    let mut collapsed = String::with_capacity(function.len());
+    let mut depth = 0;
+    let mut prev = None;
+    for c in function.chars() {
+        let after_name = prev.map_or(false, |p: char| p.is_alphanumeric() || p == '_');
+        prev = Some(c);
+        match c {
+            '<' if depth > 0 => depth += 1,
+            '<' if after_name => {
+                collapsed.push_str("<T");
+                depth += 1;
+            }
+            '>' if depth > 0 => {
+                depth -= 1;
+                if depth == 0 {
+                    collapsed.push('>');
+                }
+            }
+            _ if depth == 0 => collapsed.push(c),
+            _ => {}
+        }
+    }
+    // Unbalanced brackets aren't templates after all.
+    if depth != 0 {
+        return function.to_string();
+    }
+    collapsed
+}
+
+fn matches_any(patterns: &[String], name: &str) -> bool {
+    patterns.iter().any(|pattern| matches(pattern, name))
+}
+
+/// Whether `name` matches `pattern`, where `*` in `pattern` matches any run
+/// of characters.
+fn matches(pattern: &str, name: &str) -> bool {
+    let mut pieces = pattern.split('*');
+    // There's always a first piece, which `name` has to start with.
+    let first = pieces.next().unwrap_or("");
+    let mut rest = match name.strip_prefix(first) {
+        Some(rest) => rest,
+        None => return false,
+    };
+    let mut pieces: Vec<&str> = pieces.collect();
+    // Without any `*`, the whole name had to match.
+    let last = match pieces.pop() {
+        Some(last) => last,
+        None => return rest.is_empty(),
+    };
+    for piece in pieces {
+        match rest.find(piece) {
+            Some(i) => rest = &rest[i + piece.len()..],
+            None => return false,
+        }
+    }
+    rest.len() >= last.len() && rest.ends_with(last)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::FrameTrust;
+    use minidump::format::CONTEXT_X86;
+    use minidump::{MinidumpContext, MinidumpRawContext};
+
+    fn frame(function: Option<&str>, instruction: u64) -> StackFrame {
+        let context = MinidumpContext::from_raw(MinidumpRawContext::X86(CONTEXT_X86::default()));
+        let mut frame = StackFrame::from_context(context, FrameTrust::Scan);
+        frame.function_name = function.map(String::from);
+        frame.instruction = instruction;
+        frame
+    }
+
+    #[test]
+    fn test_matches() {
+        assert!(matches("abort", "abort"));
+        assert!(!matches("abort", "abort2"));
+        assert!(!matches("abort", "my_abort"));
+        assert!(matches("std::panicking::*", "std::panicking::begin_panic"));
+        assert!(matches("libc.so*@0x*", "libc.so.6@0x1234"));
+        assert!(!matches("libc.so*@0x*", "libc.so.6"));
+        assert!(matches("*a*a", "aa"));
+        assert!(!matches("*a*a", "a"));
+        assert!(matches("*", ""));
+    }
+
+    #[test]
+    fn test_normalize_function() {
+        let options = SignatureOptions::default();
+        let normalize = |function| normalize_function(function, &options);
+        assert_eq!(normalize("foo(int, char*)"), "foo");
+        assert_eq!(normalize("Foo::bar(int) const"), "Foo::bar");
+        assert_eq!(
+            normalize("(anonymous namespace)::foo(std::function<void ()>)"),
+            "(anonymous namespace)::foo"
+        );
+        assert_eq!(
+            normalize("std::vector<int, std::allocator<int> >::push_back(int const&)"),
+            "std::vector<T>::push_back"
+        );
+        assert_eq!(normalize("operator<<(std::ostream&)"), "operator<<");
+        assert_eq!(
+            normalize("core::result::unwrap_failed::h0123456789abcdef"),
+            "core::result::unwrap_failed"
+        );
+        assert_eq!(
+            normalize("<alloc::vec::Vec<u8> as core::ops::drop::Drop>::drop"),
+            "<alloc::vec::Vec<T> as core::ops::drop::Drop>::drop"
+        );
+
+        let mut options = SignatureOptions::default();
+        options.strip_arguments = false;
+        options.collapse_templates = false;
+        assert_eq!(
+            normalize_function("std::vector<int>::at(int)", &options),
+            "std::vector<int>::at(int)"
+        );
+    }
+
+    #[test]
+    fn test_signature() {
+        let options = SignatureOptions::default();
+        let frames = vec![
+            frame(Some("raise"), 0x10),
+            frame(Some("abort"), 0x20),
+            frame(Some("malloc(unsigned long)"), 0x30),
+            frame(Some("Foo::Alloc(int)"), 0x40),
+            frame(Some("main"), 0x50),
+        ];
+        assert_eq!(signature(&frames, &options), "malloc | Foo::Alloc");
+
+        // Sentinels win over whatever is above them.
+        let frames = vec![
+            frame(Some("raise"), 0x10),
+            frame(Some("_purecall"), 0x20),
+            frame(Some("main"), 0x30),
+        ];
+        assert_eq!(signature(&frames, &options), "_purecall");
+
+        let frames = vec![frame(None, 0x1234), frame(Some("main"), 0x50)];
+        assert_eq!(signature(&frames, &options), "main");
+
+        // If everything is irrelevant, the top frame will have to do.
+        let frames = vec![frame(None, 0x1234), frame(Some("abort"), 0x50)];
+        assert_eq!(signature(&frames, &options), "@0x1234");
+
+        assert_eq!(signature(&[], &options), "EMPTY: no frame data available");
+
+        let mut options = SignatureOptions::default();
+        options.max_len = 10;
+        let frames = vec![frame(Some("a_very_long_function_name"), 0x10)];
+        assert_eq!(signature(&frames, &options), "a_very_...");
+    }
+
+    #[test]
+    fn test_crash_hash() {
+        // Known FNV-1a values.
+        assert_eq!(fnv1a(b""), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(fnv1a(b"a"), 0xaf63_dc4c_8601_ec8c);
+
+        let options = SignatureOptions::default();
+        let frames = vec![frame(Some("foo(int)"), 0x10), frame(Some("main"), 0x20)];
+        let hash = crash_hash(&frames, &options).unwrap();
+        assert_eq!(hash.len(), 16);
+        // Arguments are stripped, as in signatures.
+        let other = vec![frame(Some("foo(long)"), 0x30), frame(Some("main"), 0x40)];
+        assert_eq!(crash_hash(&other, &options).unwrap(), hash);
+        // Frames past the depth don't count.
+        let mut options = SignatureOptions::default();
+        options.hash_depth = 1;
+        let other = vec![frame(Some("foo(int)"), 0x10), frame(Some("bar"), 0x20)];
+        assert_eq!(crash_hash(&other, &options), crash_hash(&frames, &options));
+        assert_ne!(crash_hash(&frames, &options).unwrap(), hash);
+
+        // Addresses outside of modules don't matter.
+        assert_eq!(
+            crash_hash(&[frame(None, 0x1234)], &options),
+            crash_hash(&[frame(None, 0x5678)], &options)
+        );
+        assert_eq!(crash_hash(&[], &options), None);
+    }
+}
@@ -0,0 +1,98 @@
+// Copyright 2015 Ted Mielczarek. See the COPYRIGHT
+// file at the top-level directory of this distribution.
+
+//! The processed crash of Mozilla's [Socorro](https://github.com/mozilla-services/socorro).
+//!
+//! Socorro runs the stackwalker on each minidump, keeps its JSON report as
+//! `json_dump`, and copies the parts it searches and aggregates on up to the
+//! top level of the processed crash. Producing those fields here means a
+//! pipeline built around Socorro's processed crashes can use this crate
+//! without translating its report first.
+
+use std::io::Write;
+
+use serde_json::{json, Value};
+
+use crate::process_state::ProcessState;
+use crate::signature::{self, SignatureOptions};
+
+impl ProcessState {
+    /// Outputs the processed crash JSON Socorro produces for this crash,
+    /// with [`print_json`][ProcessState::print_json]'s report as its
+    /// `json_dump`.
+    ///
+    /// The signature is made with the default [`SignatureOptions`].
+    pub fn print_socorro_json<T: Write>(
+        &self,
+        f: &mut T,
+        pretty: bool,
+    ) -> Result<(), serde_json::Error> {
+        let output = self.to_socorro_json(&SignatureOptions::default());
+        if pretty {
+            serde_json::to_writer_pretty(f, &output)
+        } else {
+            serde_json::to_writer(f, &output)
+        }
+    }
+
+    /// The JSON [`print_socorro_json`][ProcessState::print_socorro_json]
+    /// outputs, with the signature made with `options`.
+    ///
+    /// This only has the fields that come from the minidump: Socorro adds
+    /// the crash id, the annotations the crash reporter sent along, and the
+    /// like itself.
+    pub fn to_socorro_json(&self, options: &SignatureOptions) -> Value {
+        let json_dump = self.to_json();
+        let crashing_thread = self
+            .requesting_thread
+            .map(|requesting_thread| &self.threads[requesting_thread]);
+        let status = &json_dump["status"];
+        json!({
+            "signature": self.signature(options),
+            // The crashing thread's frames, all of them and unabridged.
+            "proto_signature": crashing_thread.map(|thread| {
+                thread
+                    .frames
+                    .iter()
+                    .map(|frame| signature::normalize_frame(frame, options))
+                    .collect::<Vec<_>>()
+                    .join(" | ")
+            }),
+            "mdsw_status_string": status,
+            "success": status == "OK",
+            "crashing_thread": json_dump["crash_info"]["crashing_thread"],
+            "crashing_thread_name": crashing_thread.and_then(|thread| thread.thread_name.as_deref()),
+            "address": json_dump["crash_info"]["address"],
+            "reason": json_dump["crash_info"]["type"],
+            "os_name": json_dump["system_info"]["os"],
+            "os_version": json_dump["system_info"]["os_ver"],
+            "cpu_arch": json_dump["system_info"]["cpu_arch"],
+            "cpu_info": json_dump["system_info"]["cpu_info"],
+            "cpu_count": json_dump["system_info"]["cpu_count"],
+            "modules_in_stack": modules_in_stack(&json_dump),
+            "json_dump": json_dump,
+        })
+    }
+}
+
+/// The `debug_file/debug_id` of the module of each of the crashing thread's
+/// frames, separated by `;`, or `None` if there's no crashing thread.
+fn modules_in_stack(json_dump: &Value) -> Option<String> {
+    let frames = json_dump["crashing_thread"]["frames"].as_array()?;
+    let modules = json_dump["modules"].as_array()?;
+    let in_stack = frames
+        .iter()
+        .filter_map(|frame| {
+            let filename = frame["module"].as_str()?;
+            let module = modules
+                .iter()
+                .find(|module| module["filename"].as_str() == Some(filename))?;
+            Some(format!(
+                "{}/{}",
+                module["debug_file"].as_str().unwrap_or_default(),
+                module["debug_id"].as_str().unwrap_or_default()
+            ))
+        })
+        .collect::<Vec<_>>();
+    Some(in_stack.join(";"))
+}
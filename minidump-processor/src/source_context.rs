@@ -0,0 +1,263 @@
+// Copyright 2015 Ted Mielczarek. See the COPYRIGHT
+// file at the top-level directory of this distribution.
+
+//! The lines of source around each frame's source line.
+//!
+//! Symbols say which file and line each frame is at, and seeing the code
+//! there is usually the first thing anyone looking at a crash does. With
+//! [`ProcessorOptions::source_roots`] set, the processor reads each frame's
+//! source file from them once the stacks are walked, and keeps the few lines
+//! around the frame's line in [`StackFrame::source_context`].
+//!
+//! [`StackFrame::source_context`]: crate::StackFrame::source_context
+
+use std::collections::HashMap;
+use std::io::{self, Write};
+use std::path::{Component, Path, PathBuf};
+
+use futures_util::stream::{self, StreamExt};
+use tracing::debug;
+
+use crate::{ProcessState, ProcessorOptions};
+
+/// How many source files to fetch at once.
+const SOURCE_FETCH_CONCURRENCY: usize = 8;
+
+/// Somewhere to read source files from, see
+/// [`ProcessorOptions::source_roots`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SourceRoot {
+    /// A local directory, which source paths are relative to.
+    Path(PathBuf),
+    /// The base url of a server, which source paths are relative to.
+    ///
+    /// This needs the `http-sources` feature, and is skipped without it.
+    Url(String),
+    /// Each frame's own [`StackFrame::source_url`], from the SourceLink
+    /// mappings or source index of its symbols.
+    ///
+    /// This needs the `http-sources` feature, and is skipped without it.
+    ///
+    /// [`StackFrame::source_url`]: crate::StackFrame::source_url
+    FrameUrls,
+}
+
+/// The lines of source around a frame's source line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SourceContext {
+    /// The (1-based) line number of the first of `lines`.
+    pub start_line: u32,
+    /// The lines, without their line endings.
+    pub lines: Vec<String>,
+}
+
+impl SourceContext {
+    /// The lines of `source` within `context` lines of `line`, or `None` if
+    /// the source doesn't have that line, so isn't the one that was built.
+    fn around(source: &[String], line: u32, context: u32) -> Option<SourceContext> {
+        if line == 0 || line as usize > source.len() {
+            return None;
+        }
+        let start_line = line.saturating_sub(context).max(1);
+        let end_line = line.saturating_add(context).min(source.len() as u32);
+        Some(SourceContext {
+            start_line,
+            lines: source[start_line as usize - 1..end_line as usize].to_vec(),
+        })
+    }
+
+    /// Write the lines to `f` with their line numbers, pointing out `line`.
+    pub fn print<T: Write>(&self, f: &mut T, line: Option<u32>) -> io::Result<()> {
+        writeln!(f, "    Source context:")?;
+        let end_line = self.start_line as usize + self.lines.len() - 1;
+        let width = end_line.to_string().len();
+        for (number, text) in (self.start_line..).zip(&self.lines) {
+            let marker = if Some(number) == line { '>' } else { ' ' };
+            writeln!(
+                f,
+                "    {} {:>width$}: {}",
+                marker,
+                number,
+                text,
+                width = width
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Fill in the [`StackFrame::source_context`] of every frame of `state` with
+/// a source line, from the first of `options.source_roots` that has its file.
+///
+/// [`StackFrame::source_context`]: crate::StackFrame::source_context
+pub(crate) async fn add_source_context(options: &ProcessorOptions<'_>, state: &mut ProcessState) {
+    if options.source_roots.is_empty() {
+        return;
+    }
+    let fetcher = &SourceFetcher::new(&options.source_roots);
+
+    // Each file is only fetched once, however many frames are in it.
+    let mut files = state
+        .threads
+        .iter()
+        .flat_map(|thread| &thread.frames)
+        .filter(|frame| frame.source_line.is_some())
+        .filter_map(|frame| {
+            let file = frame.source_file_name.clone()?;
+            Some((file, frame.source_url.clone()))
+        })
+        .collect::<Vec<_>>();
+    files.sort();
+    files.dedup();
+    let sources = stream::iter(files)
+        .map(|key| async move {
+            let source = fetcher.fetch(&key.0, key.1.as_deref()).await;
+            let lines = source.map(|source| source.lines().map(String::from).collect::<Vec<_>>());
+            (key, lines)
+        })
+        .buffer_unordered(SOURCE_FETCH_CONCURRENCY)
+        .filter_map(|(key, lines)| async move { Some((key, lines?)) })
+        .collect::<HashMap<_, _>>()
+        .await;
+
+    for frame in state
+        .threads
+        .iter_mut()
+        .flat_map(|thread| &mut thread.frames)
+    {
+        let (file, line) = match (&frame.source_file_name, frame.source_line) {
+            (Some(file), Some(line)) => (file.clone(), line),
+            _ => continue,
+        };
+        if let Some(source) = sources.get(&(file, frame.source_url.clone())) {
+            frame.source_context =
+                SourceContext::around(source, line, options.source_context_lines);
+        }
+    }
+}
+
+/// `path` made relative, so it can be looked for under a [`SourceRoot`].
+///
+/// Drive letters and leading separators are dropped, and backslashes become
+/// slashes. Paths that go up out of the root with `..` are `None`, as they
+/// come from symbol files, which shouldn't be able to read anything else.
+fn relative_source_path(path: &str) -> Option<String> {
+    let path = path.replace('\\', "/");
+    let path = match path.as_bytes() {
+        [drive, b':', ..] if drive.is_ascii_alphabetic() => &path[2..],
+        _ => &path[..],
+    };
+    let components = Path::new(path)
+        .components()
+        .filter_map(|component| match component {
+            Component::Normal(name) => name.to_str().map(Some),
+            Component::ParentDir => Some(None),
+            _ => None,
+        })
+        .collect::<Option<Vec<_>>>()?;
+    if components.is_empty() {
+        return None;
+    }
+    Some(components.join("/"))
+}
+
+/// Reads source files from a list of [`SourceRoot`]s.
+struct SourceFetcher<'a> {
+    roots: &'a [SourceRoot],
+    #[cfg(feature = "http-sources")]
+    client: reqwest::Client,
+}
+
+impl<'a> SourceFetcher<'a> {
+    fn new(roots: &'a [SourceRoot]) -> Self {
+        SourceFetcher {
+            roots,
+            #[cfg(feature = "http-sources")]
+            client: reqwest::Client::new(),
+        }
+    }
+
+    /// The source of `file`, from the first of the roots that has it.
+    async fn fetch(&self, file: &str, source_url: Option<&str>) -> Option<String> {
+        let relative_path = relative_source_path(file);
+        for root in self.roots {
+            let source = match (root, &relative_path) {
+                (SourceRoot::Path(dir), Some(path)) => read_file(&dir.join(path)),
+                (SourceRoot::Url(base), Some(path)) => {
+                    let url = format!("{}/{}", base.trim_end_matches('/'), path);
+                    self.fetch_url(&url).await
+                }
+                (SourceRoot::FrameUrls, _) => match source_url {
+                    Some(url) => self.fetch_url(url).await,
+                    None => None,
+                },
+                (_, None) => None,
+            };
+            if source.is_some() {
+                return source;
+            }
+        }
+        debug!("Didn't find source file {}", file);
+        None
+    }
+
+    #[cfg(feature = "http-sources")]
+    async fn fetch_url(&self, url: &str) -> Option<String> {
+        let response = self.client.get(url).send().await.ok()?;
+        let response = response.error_for_status().ok()?;
+        response.text().await.ok()
+    }
+
+    #[cfg(not(feature = "http-sources"))]
+    async fn fetch_url(&self, _url: &str) -> Option<String> {
+        None
+    }
+}
+
+fn read_file(path: &Path) -> Option<String> {
+    let bytes = std::fs::read(path).ok()?;
+    Some(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_relative_source_path() {
+        let relative = |path| relative_source_path(path);
+        assert_eq!(relative("src/main.rs").as_deref(), Some("src/main.rs"));
+        assert_eq!(
+            relative("/builds/src/main.rs").as_deref(),
+            Some("builds/src/main.rs")
+        );
+        assert_eq!(
+            relative(r"c:\build\src\main.cpp").as_deref(),
+            Some("build/src/main.cpp")
+        );
+        assert_eq!(relative("./src//main.rs").as_deref(), Some("src/main.rs"));
+        assert_eq!(relative("src/../../etc/passwd"), None);
+        assert_eq!(relative("/"), None);
+    }
+
+    #[test]
+    fn test_source_context_around() {
+        let source = (1..=10).map(|i| format!("line {}", i)).collect::<Vec<_>>();
+        let context = SourceContext::around(&source, 5, 2).unwrap();
+        assert_eq!(context.start_line, 3);
+        assert_eq!(context.lines, &source[2..7]);
+
+        // Clamped to the start and end of the file.
+        let context = SourceContext::around(&source, 2, 3).unwrap();
+        assert_eq!(context.start_line, 1);
+        assert_eq!(context.lines, &source[..5]);
+        let context = SourceContext::around(&source, 10, 3).unwrap();
+        assert_eq!(context.start_line, 7);
+        assert_eq!(context.lines, &source[6..]);
+
+        // Lines the file doesn't have mean it's the wrong file.
+        assert_eq!(SourceContext::around(&source, 11, 3), None);
+        assert_eq!(SourceContext::around(&source, 0, 3), None);
+    }
+}
@@ -0,0 +1,107 @@
+// Copyright 2015 Ted Mielczarek. See the COPYRIGHT
+// file at the top-level directory of this distribution.
+
+//! Recognizing crashes that were stack overflows.
+//!
+//! Only Windows reports stack overflows as such, and only the first time a
+//! thread overflows. Everywhere else (and on Windows, if the overflow
+//! wasn't handled) they're reported as bad memory accesses, which look the
+//! same as any other. What gives them away is that the bad address is just
+//! past the end of the crashing thread's stack, or that the thread was deep
+//! in recursion.
+//!
+//! The end of the stack is found from the crashing thread's TEB on Windows,
+//! if the minidump includes it, and otherwise from the memory info region
+//! the stack pointer is in.
+
+use std::fmt;
+
+use minidump::format::ExceptionCodeWindows;
+use minidump::system_info::Cpu;
+use minidump::{
+    CrashReason, MinidumpContext, MinidumpThread, UnifiedMemoryInfoList, UnifiedMemoryList,
+};
+
+use crate::exploitability;
+use crate::process_state::CallStack;
+
+/// How far past the end of the stack a bad access can be and still count as
+/// an overflow. Functions with large frames can skip past a guard page.
+const GUARD_DISTANCE: u64 = 64 * 1024;
+
+/// Why a crash looks like a stack overflow.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum StackOverflow {
+    /// The OS said it was one.
+    Reported,
+    /// The crash address is just past the end of the crashing thread's
+    /// stack.
+    GuardPage,
+    /// The crashing thread's stack had recursion collapsed out of it (see
+    /// [`CallStack::recursion_collapsed`]).
+    Recursion,
+}
+
+impl StackOverflow {
+    /// The name of the reason in the JSON schema.
+    pub fn json_name(self) -> &'static str {
+        match self {
+            StackOverflow::Reported => "reported",
+            StackOverflow::GuardPage => "guard_page",
+            StackOverflow::Recursion => "recursion",
+        }
+    }
+}
+
+impl fmt::Display for StackOverflow {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StackOverflow::Reported => f.write_str("reported by the OS"),
+            StackOverflow::GuardPage => f.write_str("crashed just past the end of the stack"),
+            StackOverflow::Recursion => f.write_str("crashed in deep recursion"),
+        }
+    }
+}
+
+/// The state of the crashing thread, which a [`StackOverflow`] is looked for
+/// in.
+pub(crate) struct CrashingThread<'a> {
+    pub thread: &'a MinidumpThread<'a>,
+    pub context: Option<&'a MinidumpContext>,
+    pub stack: &'a CallStack,
+}
+
+/// Whether the crash with `reason` at `address` was a stack overflow.
+pub(crate) fn classify(
+    cpu: Cpu,
+    reason: CrashReason,
+    address: u64,
+    crashing: Option<CrashingThread>,
+    memory_list: &UnifiedMemoryList,
+    memory_info: Option<&UnifiedMemoryInfoList>,
+) -> Option<StackOverflow> {
+    if let CrashReason::WindowsGeneral(ExceptionCodeWindows::EXCEPTION_STACK_OVERFLOW) = reason {
+        return Some(StackOverflow::Reported);
+    }
+    if !exploitability::is_memory_access(reason) {
+        return None;
+    }
+    let crashing = crashing?;
+
+    let stack_pointer = crashing.context.map(|context| context.get_stack_pointer());
+    let teb_limit = crashing.thread.stack_bounds(cpu, memory_list);
+    let limit = teb_limit.map(|(limit, _)| limit).or_else(|| {
+        let region = memory_info?.region_containing(stack_pointer?)?;
+        Some(region.memory_range()?.start)
+    });
+    if let Some(limit) = limit {
+        if address < limit && limit - address <= GUARD_DISTANCE {
+            return Some(StackOverflow::GuardPage);
+        }
+    }
+    if crashing.stack.recursion_collapsed {
+        return Some(StackOverflow::Recursion);
+    }
+    None
+}
@@ -2,17 +2,25 @@
 // file at the top-level directory of this distribution.
 
 use crate::process_state::*;
-use crate::stackwalker::walk_stack;
-use crate::{string_symbol_supplier, Symbolizer};
+use crate::stackwalker::{merge_contexts, walk_stack, CfiCache, SymbolCache};
+use crate::{
+    string_symbol_supplier, ArchUnwinder, FillSymbolError, FrameSymbolizer, FrameWalker,
+    ProcessorOptions, SymbolProvider, SymbolStats, Symbolizer,
+};
 use minidump::format::CONTEXT_AMD64;
+use minidump::system_info::Cpu;
 use minidump::*;
 use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use test_assembler::*;
 
 struct TestFixture {
     pub raw: CONTEXT_AMD64,
     pub modules: MinidumpModuleList,
     pub symbols: HashMap<String, String>,
+    pub memory: Vec<(u64, Vec<u8>)>,
+    pub memory_info: UnifiedMemoryInfoList<'static>,
+    pub options: ProcessorOptions<'static>,
 }
 
 impl TestFixture {
@@ -26,10 +34,21 @@ impl TestFixture {
                 MinidumpModule::new(0x00007500b0000000, 0x10000, "module2"),
             ]),
             symbols: HashMap::new(),
+            memory: vec![],
+            memory_info: UnifiedMemoryInfoList::default(),
+            options: ProcessorOptions::default(),
         }
     }
 
     pub async fn walk_stack(&self, stack: Section) -> CallStack {
+        let symbolizer = Symbolizer::new(string_symbol_supplier(self.symbols.clone()));
+        self.walk_stack_with_provider(stack, &symbolizer).await
+    }
+
+    pub async fn walk_stack_with_provider<P>(&self, stack: Section, provider: &P) -> CallStack
+    where
+        P: SymbolProvider + Sync,
+    {
         let context = MinidumpContext {
             raw: MinidumpRawContext::Amd64(self.raw.clone()),
             valid: MinidumpContextValidity::All,
@@ -43,12 +62,25 @@ impl TestFixture {
             size,
             bytes: &stack,
         };
-        let symbolizer = Symbolizer::new(string_symbol_supplier(self.symbols.clone()));
+        let memory_list = UnifiedMemoryList::Memory(MinidumpMemoryList::from_regions(
+            self.memory
+                .iter()
+                .map(|(base, bytes)| MinidumpMemory {
+                    desc: Default::default(),
+                    base_address: *base,
+                    size: bytes.len() as u64,
+                    bytes,
+                })
+                .collect(),
+        ));
         walk_stack(
             &Some(&context),
             Some(&stack_memory),
+            &memory_list,
+            &self.memory_info,
             &self.modules,
-            &symbolizer,
+            provider,
+            &self.options,
         )
         .await
     }
@@ -56,6 +88,19 @@ impl TestFixture {
     pub fn add_symbols(&mut self, name: String, symbols: String) {
         self.symbols.insert(name, symbols);
     }
+
+    pub fn add_memory(&mut self, base: u64, section: Section) {
+        self.memory.push((base, section.get_contents().unwrap()));
+    }
+
+    pub fn set_linux_maps(&mut self, maps: &'static [&'static [u8]]) {
+        let maps = maps
+            .iter()
+            .map(|line| MinidumpLinuxMapInfo::from_line(strings::LinuxOsStr::from_bytes(line)))
+            .collect::<Result<_, _>>()
+            .unwrap();
+        self.memory_info = UnifiedMemoryInfoList::Maps(MinidumpLinuxMaps::from_regions(maps));
+    }
 }
 
 #[tokio::test]
@@ -149,6 +194,97 @@ async fn test_caller_pushed_rbp() {
     }
 }
 
+#[tokio::test]
+async fn test_inline_frames() {
+    // Functions inlined at a frame's instruction should show up as extra
+    // frames above it, without getting in the way of unwinding.
+    let mut f = TestFixture::new();
+    let mut stack = Section::new();
+    let stack_start = 0x8000000080000000;
+    let return_address = 0x00007500b0000110;
+    stack.start().set_const(stack_start);
+
+    let frame0_rbp = Label::new();
+    let frame1_rbp = Label::new();
+
+    stack = stack
+        // frame 0
+        .append_repeated(0, 16) // space
+        .mark(&frame0_rbp)
+        .D64(&frame1_rbp) // caller-pushed %rbp
+        .D64(return_address) // actual return address
+        // frame 1
+        .append_repeated(0, 32) // body of frame1
+        .mark(&frame1_rbp) // end of stack
+        .D64(0);
+
+    f.raw.rip = 0x00007400c0000200;
+    f.raw.rbp = frame0_rbp.value().unwrap();
+    f.raw.rsp = stack.start().value().unwrap();
+
+    f.add_symbols(
+        String::from("module1"),
+        // The youngest frame's function, with two levels of inlining.
+        String::from(
+            "FILE 1 monotreme.c
+FILE 2 echidna.h
+INLINE_ORIGIN 0 echidna
+INLINE_ORIGIN 1 platypus
+FUNC 100 400 10 monotreme
+INLINE 0 20 1 0 180 100
+INLINE 1 30 2 1 1f0 20
+180 100 40 2
+",
+        ),
+    );
+    f.add_symbols(
+        String::from("module2"),
+        // The calling frame's function.
+        String::from("FUNC 100 400 10 marsupial\n"),
+    );
+
+    let s = f.walk_stack(stack).await;
+    assert_eq!(s.frames.len(), 4);
+
+    {
+        // The innermost inlined function
+        let f0 = &s.frames[0];
+        assert_eq!(f0.trust, FrameTrust::Inlined);
+        assert_eq!(f0.instruction, 0x00007400c0000200);
+        assert_eq!(f0.resume_address, 0x00007400c0000200);
+        assert_eq!(f0.module.as_ref().unwrap().code_file(), "module1");
+        assert_eq!(f0.function_name.as_deref(), Some("platypus"));
+        assert_eq!(f0.source_file_name.as_deref(), Some("echidna.h"));
+        assert_eq!(f0.source_line, Some(40));
+    }
+
+    {
+        let f1 = &s.frames[1];
+        assert_eq!(f1.trust, FrameTrust::Inlined);
+        assert_eq!(f1.function_name.as_deref(), Some("echidna"));
+        assert_eq!(f1.source_file_name.as_deref(), Some("echidna.h"));
+        assert_eq!(f1.source_line, Some(30));
+    }
+
+    {
+        // The physical frame the functions were inlined into
+        let f2 = &s.frames[2];
+        assert_eq!(f2.trust, FrameTrust::Context);
+        assert_eq!(f2.function_name.as_deref(), Some("monotreme"));
+        assert_eq!(f2.source_file_name.as_deref(), Some("monotreme.c"));
+        assert_eq!(f2.source_line, Some(20));
+    }
+
+    {
+        let f3 = &s.frames[3];
+        assert_eq!(f3.trust, FrameTrust::FramePointer);
+        assert_eq!(f3.instruction, return_address - 1);
+        assert_eq!(f3.resume_address, return_address);
+        assert_eq!(f3.return_address(), return_address);
+        assert_eq!(f3.function_name.as_deref(), Some("marsupial"));
+    }
+}
+
 #[tokio::test]
 async fn test_scan_without_symbols() {
     // When the stack walker resorts to scanning the stack,
@@ -663,3 +799,732 @@ async fn test_frame_pointer_barely_no_overflow() {
         }
     }
 }
+
+/// A 64-bit ELF module with an `.eh_frame_hdr` at 0x100 and `.eh_frame` at
+/// 0x200, describing a function at 0x1000 that sets up a frame pointer.
+fn eh_frame_module() -> Section {
+    Section::new()
+        // Elf64_Ehdr
+        .D32(0x464c457f) // magic
+        .D8(2) // ELFCLASS64
+        .D8(1) // ELFDATA2LSB
+        .append_repeated(0, 10)
+        .D16(3) // e_type
+        .D16(62) // e_machine
+        .D32(1) // e_version
+        .D64(0) // e_entry
+        .D64(0x40) // e_phoff
+        .D64(0) // e_shoff
+        .D32(0) // e_flags
+        .D16(0x40) // e_ehsize
+        .D16(56) // e_phentsize
+        .D16(2) // e_phnum
+        .D16(0) // e_shentsize
+        .D16(0) // e_shnum
+        .D16(0) // e_shstrndx
+        // PT_LOAD
+        .D32(1)
+        .D32(5)
+        .D64(0)
+        .D64(0) // p_vaddr
+        .D64(0)
+        .D64(0x10000)
+        .D64(0x10000) // p_memsz
+        .D64(0x1000)
+        // PT_GNU_EH_FRAME
+        .D32(0x6474e550)
+        .D32(4)
+        .D64(0x100)
+        .D64(0x100) // p_vaddr
+        .D64(0x100)
+        .D64(0x14)
+        .D64(0x14) // p_memsz
+        .D64(4)
+        .append_repeated(0, 0x100 - 0xb0)
+        // .eh_frame_hdr
+        .D8(1) // version
+        .D8(0x1b) // eh_frame_ptr: pcrel sdata4
+        .D8(0x03) // fde_count: udata4
+        .D8(0x3b) // table: datarel sdata4
+        .D32(0x200 - 0x104) // eh_frame_ptr
+        .D32(1) // fde_count
+        .D32(0x1000 - 0x100) // initial location
+        .D32(0x218 - 0x100) // FDE address
+        .append_repeated(0, 0x200 - 0x114)
+        // CIE
+        .D32(20) // length
+        .D32(0) // CIE id
+        .D8(1) // version
+        .append_bytes(b"zR\0") // augmentation
+        .D8(1) // code alignment
+        .D8(0x78) // data alignment: -8
+        .D8(16) // return address register: rip
+        .D8(1) // augmentation data length
+        .D8(0x1b) // FDE pointers: pcrel sdata4
+        .append_bytes(&[0x0c, 7, 8]) // DW_CFA_def_cfa: rsp + 8
+        .append_bytes(&[0x90, 1]) // DW_CFA_offset: rip at cfa - 8
+        .append_bytes(&[0, 0]) // DW_CFA_nop
+        // FDE
+        .D32(24) // length
+        .D32(0x21c - 0x200) // CIE pointer
+        .D32(0x1000 - 0x220) // initial location
+        .D32(0x100) // address range
+        .D8(0) // augmentation data length
+        .append_bytes(&[0x41]) // DW_CFA_advance_loc: 1 (push %rbp)
+        .append_bytes(&[0x0e, 16]) // DW_CFA_def_cfa_offset: 16
+        .append_bytes(&[0x86, 2]) // DW_CFA_offset: rbp at cfa - 16
+        .append_bytes(&[0x43]) // DW_CFA_advance_loc: 3 (mov %rsp, %rbp)
+        .append_bytes(&[0x0d, 6]) // DW_CFA_def_cfa_register: rbp
+        .append_bytes(&[0, 0, 0]) // DW_CFA_nop
+}
+
+#[tokio::test]
+async fn test_eh_frame() {
+    let mut f = TestFixture::new();
+    let mut stack = Section::new();
+    let stack_start = 0x8000000080000000;
+    stack.start().set_const(stack_start);
+
+    let return_address1 = 0x00007400c0001020;
+    let return_address2 = 0x00007500b0000110;
+    let frame1_sp = Label::new();
+    let frame1_rbp = Label::new();
+    let frame2_sp = Label::new();
+
+    stack = stack
+        // frame 0, which has just pushed %rbp
+        .D64(&frame1_rbp) // saved %rbp
+        .D64(return_address1)
+        // frame 1, which has a frame pointer
+        .mark(&frame1_sp)
+        .append_repeated(0, 16) // locals
+        .mark(&frame1_rbp)
+        .D64(0) // saved %rbp
+        .D64(return_address2)
+        // frame 2
+        .mark(&frame2_sp)
+        .append_repeated(0, 16); // end of stack
+
+    f.raw.rip = 0x00007400c0001002;
+    f.raw.rbp = 0x1111111111111111;
+    f.raw.rbx = 0x2222222222222222;
+    f.raw.rsp = stack.start().value().unwrap();
+    f.add_memory(0x00007400c0000000, eh_frame_module());
+
+    let s = f.walk_stack(stack).await;
+    assert_eq!(s.frames.len(), 3);
+
+    {
+        // Frame 1
+        let frame = &s.frames[1];
+        assert_eq!(frame.trust, FrameTrust::CallFrameInfo);
+        assert_eq!(frame.instruction, return_address1 - 1);
+        if let MinidumpRawContext::Amd64(ctx) = &frame.context.raw {
+            assert_eq!(ctx.rip, return_address1);
+            assert_eq!(ctx.rsp, frame1_sp.value().unwrap());
+            assert_eq!(ctx.rbp, frame1_rbp.value().unwrap());
+            assert_eq!(ctx.rbx, 0x2222222222222222);
+        } else {
+            unreachable!();
+        }
+    }
+
+    {
+        // Frame 2
+        let frame = &s.frames[2];
+        assert_eq!(frame.trust, FrameTrust::CallFrameInfo);
+        assert_eq!(frame.instruction, return_address2 - 1);
+        if let MinidumpRawContext::Amd64(ctx) = &frame.context.raw {
+            assert_eq!(ctx.rip, return_address2);
+            assert_eq!(ctx.rsp, frame2_sp.value().unwrap());
+            assert_eq!(ctx.rbp, 0);
+        } else {
+            unreachable!();
+        }
+    }
+}
+
+/// A 64-bit PE module exporting `first` at 0x1000, an unnamed function at
+/// 0x2000, and `forwarded` to another module.
+fn pe_module() -> Section {
+    Section::new()
+        // IMAGE_DOS_HEADER
+        .D16(0x5a4d) // e_magic
+        .append_repeated(0, 0x3a)
+        .D32(0x40) // e_lfanew
+        // IMAGE_NT_HEADERS64
+        .D32(0x4550) // Signature
+        .append_repeated(0, 20) // FileHeader
+        .D16(0x20b) // OptionalHeader.Magic
+        .append_repeated(0, 106)
+        .D32(16) // NumberOfRvaAndSizes
+        .D32(0x200) // export directory
+        .D32(0x100) // export directory size
+        .append_repeated(0, 0x200 - 0xd0)
+        // IMAGE_EXPORT_DIRECTORY
+        .append_repeated(0, 16)
+        .D32(1) // Base
+        .D32(3) // NumberOfFunctions
+        .D32(2) // NumberOfNames
+        .D32(0x228) // AddressOfFunctions
+        .D32(0x234) // AddressOfNames
+        .D32(0x23c) // AddressOfNameOrdinals
+        // functions
+        .D32(0x1000)
+        .D32(0x2000)
+        .D32(0x250) // forwarded
+        // names
+        .D32(0x240)
+        .D32(0x246)
+        // name ordinals
+        .D16(0)
+        .D16(2)
+        .append_bytes(b"first\0")
+        .append_bytes(b"forwarded\0")
+        .append_bytes(b"other.forwarded\0")
+}
+
+/// A 64-bit ELF module with dynamic symbols for `first` (0x1000-0x1100) and
+/// `second` (0x2000-0x2010).
+fn elf_module() -> Section {
+    let symbol = |name: u32, value: u64, size: u64| {
+        Section::new()
+            .D32(name)
+            .D8(0x12) // STB_GLOBAL, STT_FUNC
+            .D8(0)
+            .D16(1) // st_shndx
+            .D64(value)
+            .D64(size)
+    };
+    Section::new()
+        // Elf64_Ehdr
+        .D32(0x464c457f) // magic
+        .D8(2) // ELFCLASS64
+        .D8(1) // ELFDATA2LSB
+        .append_repeated(0, 10)
+        .D16(3) // e_type
+        .D16(62) // e_machine
+        .D32(1) // e_version
+        .D64(0) // e_entry
+        .D64(0x40) // e_phoff
+        .D64(0) // e_shoff
+        .D32(0) // e_flags
+        .D16(0x40) // e_ehsize
+        .D16(56) // e_phentsize
+        .D16(2) // e_phnum
+        .D16(0) // e_shentsize
+        .D16(0) // e_shnum
+        .D16(0) // e_shstrndx
+        // PT_LOAD
+        .D32(1)
+        .D32(5)
+        .D64(0)
+        .D64(0) // p_vaddr
+        .D64(0)
+        .D64(0x10000)
+        .D64(0x10000) // p_memsz
+        .D64(0x1000)
+        // PT_DYNAMIC
+        .D32(2)
+        .D32(6)
+        .D64(0x100)
+        .D64(0x100) // p_vaddr
+        .D64(0x100)
+        .D64(0x50)
+        .D64(0x50) // p_memsz
+        .D64(8)
+        .append_repeated(0, 0x100 - 0xb0)
+        // .dynamic, with pointers the dynamic loader didn't relocate
+        .D64(4) // DT_HASH
+        .D64(0x150)
+        .D64(5) // DT_STRTAB
+        .D64(0x200)
+        .D64(6) // DT_SYMTAB
+        .D64(0x160)
+        .D64(11) // DT_SYMENT
+        .D64(24)
+        .D64(0) // DT_NULL
+        .D64(0)
+        // .hash
+        .D32(1) // nbucket
+        .D32(3) // nchain
+        .append_repeated(0, 8)
+        // .dynsym
+        .append_repeated(0, 24)
+        .append_section(symbol(1, 0x1000, 0x100))
+        .append_section(symbol(7, 0x2000, 0x10))
+        .append_repeated(0, 0x200 - 0x1a8)
+        // .dynstr
+        .append_bytes(b"\0first\0second\0")
+}
+
+#[tokio::test]
+async fn test_exports() {
+    async fn context_frame(f: &mut TestFixture, rip: u64) -> StackFrame {
+        let stack = Section::new();
+        stack.start().set_const(0x80000000);
+        f.raw.rip = rip;
+        f.walk_stack(stack).await.frames.remove(0)
+    }
+
+    let mut f = TestFixture::new();
+    f.options.symbolicate_from_exports = true;
+    f.add_memory(0x00007400c0000000, pe_module());
+    f.add_memory(0x00007500b0000000, elf_module());
+
+    let frame = context_frame(&mut f, 0x00007400c0001010).await;
+    assert_eq!(frame.function_name.as_deref(), Some("first"));
+    assert_eq!(frame.function_base, Some(0x00007400c0001000));
+    assert!(frame.function_from_exports);
+    let frame = context_frame(&mut f, 0x00007400c0002010).await;
+    assert_eq!(frame.function_name.as_deref(), Some("Ordinal2"));
+    assert_eq!(frame.function_base, Some(0x00007400c0002000));
+
+    let frame = context_frame(&mut f, 0x00007500b0001010).await;
+    assert_eq!(frame.function_name.as_deref(), Some("first"));
+    assert_eq!(frame.function_base, Some(0x00007500b0001000));
+    assert!(frame.function_from_exports);
+    // Past the end of `second`.
+    let frame = context_frame(&mut f, 0x00007500b0002010).await;
+    assert_eq!(frame.function_name, None);
+    assert!(!frame.function_from_exports);
+
+    // Real symbols take precedence.
+    f.add_symbols(
+        String::from("module1"),
+        String::from("MODULE Windows x86_64 C0FFEE module1\nFUNC 1000 20 0 real\n"),
+    );
+    let frame = context_frame(&mut f, 0x00007400c0001010).await;
+    assert_eq!(frame.function_name.as_deref(), Some("real"));
+    assert!(!frame.function_from_exports);
+
+    f.options.symbolicate_from_exports = false;
+    let frame = context_frame(&mut f, 0x00007500b0001010).await;
+    assert_eq!(frame.function_name, None);
+}
+
+async fn scan_into_jit_code(mut f: TestFixture) -> CallStack {
+    let mut stack = Section::new();
+    let stack_start = 0x80000000;
+    let jit_return_address = 0x10000200;
+    stack.start().set_const(stack_start);
+
+    stack = stack
+        // frame 0
+        .append_repeated(0, 16) // space
+        .D64(jit_return_address) // return address into JIT code
+        // frame 1
+        .append_repeated(0, 32); // body of frame1
+
+    f.raw.rip = 0x00007400c0000200;
+    f.raw.rbp = 0;
+    f.raw.rsp = stack.start().value().unwrap();
+    f.walk_stack(stack).await
+}
+
+#[tokio::test]
+async fn test_scan_jit_code() {
+    // A return address that isn't in any module is accepted by the scanner
+    // if it points into anonymous executable memory.
+    let mut f = TestFixture::new();
+    f.set_linux_maps(&[
+        b"10000000-10001000 rwxp 00000000 00:00 0",
+        b"7400c0000000-7400c0010000 r-xp 00000000 08:01 1234 /usr/lib/module1",
+    ]);
+
+    let s = scan_into_jit_code(f).await;
+    assert_eq!(s.frames.len(), 2);
+
+    let f0 = &s.frames[0];
+    assert_eq!(f0.trust, FrameTrust::Context);
+    assert!(!f0.is_jit);
+
+    let f1 = &s.frames[1];
+    assert_eq!(f1.trust, FrameTrust::Scan);
+    assert_eq!(f1.instruction + 1, 0x10000200);
+    assert!(f1.module.is_none());
+    assert!(f1.is_jit);
+}
+
+#[tokio::test]
+async fn test_scan_jit_code_not_executable() {
+    // The same address is rejected when its memory isn't executable,
+    // or when we don't know anything about the memory at all.
+    let s = scan_into_jit_code(TestFixture::new()).await;
+    assert_eq!(s.frames.len(), 1);
+
+    let mut f = TestFixture::new();
+    f.set_linux_maps(&[b"10000000-10001000 rw-p 00000000 00:00 0"]);
+    let s = scan_into_jit_code(f).await;
+    assert_eq!(s.frames.len(), 1);
+}
+
+#[tokio::test]
+async fn test_scan_skips_module_data() {
+    // A pointer into a module's data looks just like a return address to a
+    // module without symbols, but the memory info knows better.
+    let mut f = TestFixture::new();
+    f.set_linux_maps(&[
+        b"7400c0000000-7400c0008000 r-xp 00000000 08:01 1234 /usr/lib/module1",
+        b"7400c0008000-7400c0010000 rw-p 00008000 08:01 1234 /usr/lib/module1",
+        b"7500b0000000-7500b0010000 r-xp 00000000 08:01 5678 /usr/lib/module2",
+    ]);
+    let mut stack = Section::new();
+    stack.start().set_const(0x80000000);
+    stack = stack
+        .D64(0x00007400c0008010) // pointer into module1's data
+        .D64(0x00007500b0000110) // return address into module2
+        .append_repeated(0, 32);
+
+    f.raw.rip = 0x00007400c0000200;
+    f.raw.rbp = 0;
+    f.raw.rsp = stack.start().value().unwrap();
+
+    let s = f.walk_stack(stack).await;
+    assert_eq!(s.frames.len(), 2);
+    assert_eq!(s.frames[1].trust, FrameTrust::Scan);
+    assert_eq!(s.frames[1].instruction + 1, 0x00007500b0000110);
+}
+
+#[tokio::test]
+async fn test_resume_after_gap() {
+    // A stretch of zeroed stack too long for the scan to get past, with a
+    // return address after it.
+    let mut f = TestFixture::new();
+    f.options.scan_depth = 4;
+    let stack = || {
+        let stack = Section::new();
+        stack.start().set_const(0x80000000);
+        stack
+            .append_repeated(0, 64 * 8)
+            .D64(0x00007500b0000110) // return address into module2
+            .append_repeated(0, 8 * 8)
+    };
+
+    f.raw.rip = 0x00007400c0000200;
+    f.raw.rbp = 0;
+    f.raw.rsp = 0x80000000;
+
+    let s = f.walk_stack(stack()).await;
+    assert_eq!(s.frames.len(), 1);
+
+    f.options.resume_after_gaps = true;
+    let s = f.walk_stack(stack()).await;
+    assert_eq!(s.frames.len(), 3);
+    assert_eq!(s.frames[0].trust, FrameTrust::Context);
+    assert_eq!(s.frames[0].gap, None);
+
+    let marker = &s.frames[1];
+    assert_eq!(
+        marker.gap,
+        Some(StackGap {
+            start: 0x80000000,
+            end: 0x80000000 + 64 * 8,
+        })
+    );
+    assert_eq!(marker.instruction, 0);
+
+    assert_eq!(s.frames[2].trust, FrameTrust::Scan);
+    assert_eq!(s.frames[2].instruction + 1, 0x00007500b0000110);
+    assert_eq!(s.frames[2].context.get_stack_pointer(), 0x80000000 + 65 * 8);
+
+    // The gap has to be within reach.
+    f.options.max_stack_gap = 32 * 8;
+    let s = f.walk_stack(stack()).await;
+    assert_eq!(s.frames.len(), 1);
+}
+
+#[tokio::test]
+async fn test_collapse_recursion() {
+    // Two functions that call each other until the stack runs out, which
+    // frame pointers unwind through one frame at a time.
+    let mut f = TestFixture::new();
+    let mut stack = Section::new();
+    let stack_start = 0x80000000;
+    stack.start().set_const(stack_start);
+
+    let return_addresses = [0x00007500b0000110u64, 0x00007500b0000220u64];
+    let frame_count = 300;
+    let frame_pointers: Vec<_> = (0..=frame_count).map(|_| Label::new()).collect();
+    for i in 0..frame_count {
+        stack = stack
+            .mark(&frame_pointers[i])
+            .D64(&frame_pointers[i + 1]) // caller-pushed %rbp
+            .D64(return_addresses[i % 2]); // return address
+    }
+    stack = stack.mark(&frame_pointers[frame_count]).D64(0).D64(0);
+
+    f.raw.rip = 0x00007400c0000200;
+    f.raw.rbp = frame_pointers[0].value().unwrap();
+    f.raw.rsp = stack.start().value().unwrap();
+
+    let s = f.walk_stack(stack).await;
+    assert!(s.recursion_collapsed);
+    // The context frame, the first repetition, the marker, and the last
+    // repetition.
+    assert_eq!(s.frames.len(), 6);
+    assert_eq!(s.frames[0].trust, FrameTrust::Context);
+    assert_eq!(s.frames[0].repeated, None);
+    assert_eq!(s.frames[1].instruction + 1, return_addresses[0]);
+    assert_eq!(s.frames[2].instruction + 1, return_addresses[1]);
+
+    let marker = &s.frames[3];
+    assert_eq!(
+        marker.repeated,
+        Some(RepeatedFrames {
+            count: 296,
+            period: 2,
+        })
+    );
+    assert_eq!(marker.instruction + 1, return_addresses[0]);
+
+    assert_eq!(s.frames[4].instruction + 1, return_addresses[0]);
+    assert_eq!(s.frames[4].repeated, None);
+    assert_eq!(s.frames[5].instruction + 1, return_addresses[1]);
+}
+
+/// A `SymbolProvider` that counts how often it's asked to symbolize and walk
+/// a frame.
+struct CountingProvider {
+    inner: Symbolizer,
+    fills: AtomicUsize,
+    walks: AtomicUsize,
+}
+
+#[async_trait::async_trait]
+impl SymbolProvider for CountingProvider {
+    async fn fill_symbol(
+        &self,
+        module: &(dyn Module + Sync),
+        frame: &mut (dyn FrameSymbolizer + Send),
+    ) -> Result<(), FillSymbolError> {
+        self.fills.fetch_add(1, Ordering::SeqCst);
+        self.inner.fill_symbol(module, frame).await
+    }
+    async fn walk_frame(
+        &self,
+        module: &(dyn Module + Sync),
+        walker: &mut (dyn FrameWalker + Send),
+    ) -> Option<()> {
+        self.walks.fetch_add(1, Ordering::SeqCst);
+        self.inner.walk_frame(module, walker).await
+    }
+    fn stats(&self) -> HashMap<String, SymbolStats> {
+        self.inner.stats()
+    }
+}
+
+#[tokio::test]
+async fn test_cfi_cache() {
+    // The context frame's instruction isn't covered by any CFI, so only the
+    // first walk should have to ask the symbol provider about it.
+    let mut f = TestFixture::new();
+    f.add_symbols(
+        String::from("module1"),
+        String::from("STACK CFI INIT 4000 100 .cfa: $rsp 8 + .ra: .cfa 8 - ^\n"),
+    );
+    f.raw.rip = 0x00007400c0000200;
+    f.raw.rsp = 0x80000000;
+
+    let provider = CountingProvider {
+        inner: Symbolizer::new(string_symbol_supplier(f.symbols.clone())),
+        fills: AtomicUsize::new(0),
+        walks: AtomicUsize::new(0),
+    };
+    let cache = CfiCache::new(&provider);
+    for _ in 0..3 {
+        let stack = Section::new();
+        stack.start().set_const(0x80000000);
+        let s = f.walk_stack_with_provider(stack, &cache).await;
+        assert_eq!(s.frames.len(), 1);
+    }
+    assert_eq!(provider.walks.load(Ordering::SeqCst), 1);
+
+    // CFI that was actually evaluated depends on the frame's registers, so
+    // it has to be looked up every time.
+    let (mut f, _, _, _) = init_cfi_state();
+    f.raw.set_register("rip", 0x00007400c0004000);
+    let provider = CountingProvider {
+        inner: Symbolizer::new(string_symbol_supplier(f.symbols.clone())),
+        fills: AtomicUsize::new(0),
+        walks: AtomicUsize::new(0),
+    };
+    let cache = CfiCache::new(&provider);
+    for _ in 0..3 {
+        let (_, stack, _, _) = init_cfi_state();
+        let stack = stack.D64(0x00007400c0005510).append_repeated(0, 1000);
+        let s = f.walk_stack_with_provider(stack, &cache).await;
+        assert_eq!(s.frames[1].trust, FrameTrust::CallFrameInfo);
+    }
+    assert!(provider.walks.load(Ordering::SeqCst) >= 3);
+}
+
+#[tokio::test]
+async fn test_symbol_cache() {
+    // Each walk symbolizes the same frame, which has inlined functions, so
+    // only the first should have to ask the symbol provider about it.
+    let mut f = TestFixture::new();
+    f.add_symbols(
+        String::from("module1"),
+        String::from(
+            "FILE 1 monotreme.c
+FILE 2 echidna.h
+INLINE_ORIGIN 0 echidna
+INLINE_ORIGIN 1 platypus
+FUNC 100 400 10 monotreme
+INLINE 0 20 1 0 180 100
+INLINE 1 30 2 1 1f0 20
+180 100 40 2
+",
+        ),
+    );
+    f.raw.rip = 0x00007400c0000200;
+    f.raw.rsp = 0x80000000;
+
+    let stack = Section::new();
+    stack.start().set_const(0x80000000);
+    let uncached = f.walk_stack(stack).await;
+
+    let provider = CountingProvider {
+        inner: Symbolizer::new(string_symbol_supplier(f.symbols.clone())),
+        fills: AtomicUsize::new(0),
+        walks: AtomicUsize::new(0),
+    };
+    let cache = SymbolCache::new(&provider);
+    let mut fills = 0;
+    for _ in 0..3 {
+        let stack = Section::new();
+        stack.start().set_const(0x80000000);
+        let s = f.walk_stack_with_provider(stack, &cache).await;
+        assert_eq!(s.frames.len(), 3);
+        for (frame, expected) in s.frames.iter().zip(&uncached.frames) {
+            assert_eq!(frame.function_name, expected.function_name);
+            assert_eq!(frame.source_file_name, expected.source_file_name);
+            assert_eq!(frame.source_line, expected.source_line);
+        }
+        if fills == 0 {
+            fills = provider.fills.load(Ordering::SeqCst);
+        }
+    }
+    assert!(fills > 0);
+    assert_eq!(provider.fills.load(Ordering::SeqCst), fills);
+}
+
+#[test]
+fn test_merge_contexts() {
+    let mut raw = CONTEXT_AMD64 {
+        rip: 0x00007400c0000200,
+        rsp: 0x80000000,
+        ..CONTEXT_AMD64::default()
+    };
+    let exception = MinidumpContext {
+        raw: MinidumpRawContext::Amd64(raw.clone()),
+        valid: MinidumpContextValidity::Some(["rip", "rsp"].iter().copied().collect()),
+    };
+    raw.rip = 0x00007400c0000400;
+    raw.rax = 0x1111;
+    raw.rbx = 0x2222;
+    raw.r15 = 0x3333;
+    let thread = MinidumpContext {
+        raw: MinidumpRawContext::Amd64(raw),
+        valid: MinidumpContextValidity::All,
+    };
+
+    let merged = merge_contexts(&exception, &thread).unwrap();
+    let ctx = match &merged.raw {
+        MinidumpRawContext::Amd64(ctx) => ctx,
+        _ => unreachable!(),
+    };
+    // The exception's own registers win, and only callee-saved registers
+    // come from the thread.
+    assert_eq!(
+        ctx.get_register("rip", &merged.valid),
+        Some(0x00007400c0000200)
+    );
+    assert_eq!(ctx.get_register("rbx", &merged.valid), Some(0x2222));
+    assert_eq!(ctx.get_register("r15", &merged.valid), Some(0x3333));
+    assert_eq!(ctx.get_register("rax", &merged.valid), None);
+
+    // An exception context with everything has nothing to merge.
+    let full = MinidumpContext {
+        valid: MinidumpContextValidity::All,
+        ..exception
+    };
+    assert!(merge_contexts(&full, &thread).is_none());
+}
+
+/// An `ArchUnwinder` that knows where exactly one function returns to.
+struct TrampolineUnwinder {
+    replaces_builtin: bool,
+}
+
+#[async_trait::async_trait]
+impl ArchUnwinder for TrampolineUnwinder {
+    async fn get_caller_frame(
+        &self,
+        callee: &StackFrame,
+        _grand_callee: Option<&StackFrame>,
+        _stack_memory: Option<&MinidumpMemory<'_>>,
+        _memory_list: &UnifiedMemoryList<'_>,
+        _modules: &MinidumpModuleList,
+        _symbol_provider: &(dyn SymbolProvider + Sync),
+    ) -> Option<StackFrame> {
+        if callee.instruction != 0x00007400c0000200 {
+            return None;
+        }
+        let caller = CONTEXT_AMD64 {
+            rip: 0x00007500b0000110,
+            rsp: 0x80000008,
+            ..CONTEXT_AMD64::default()
+        };
+        let context = MinidumpContext {
+            raw: MinidumpRawContext::Amd64(caller),
+            valid: MinidumpContextValidity::Some(["rip", "rsp"].iter().copied().collect()),
+        };
+        Some(StackFrame::from_context(context, FrameTrust::PreWalked))
+    }
+
+    fn replaces_builtin(&self) -> bool {
+        self.replaces_builtin
+    }
+}
+
+#[tokio::test]
+async fn test_custom_unwinder() {
+    let mut f = TestFixture::new();
+    let stack = || {
+        let stack = Section::new();
+        stack.start().set_const(0x80000000);
+        // Something for the built-in unwinder to find by scanning from the
+        // caller the custom unwinder finds.
+        stack.D64(0).D64(0x00007500b0000300)
+    };
+    f.raw.rip = 0x00007400c0000200;
+    f.raw.rsp = 0x80000000;
+    f.options.unwinders.register(
+        Cpu::X86_64,
+        TrampolineUnwinder {
+            replaces_builtin: false,
+        },
+    );
+    f.options.unwind_diagnostics = true;
+
+    let s = f.walk_stack(stack()).await;
+    assert_eq!(s.frames[1].trust, FrameTrust::PreWalked);
+    assert_eq!(s.frames[1].instruction, 0x00007500b0000110);
+    let attempts = &s.frames[0].unwind_diagnostics.as_ref().unwrap().attempts;
+    assert_eq!(attempts[0].strategy, UnwindStrategy::Custom);
+    assert!(attempts[0].succeeded);
+    // The custom unwinder doesn't know about the caller, so the built-in one
+    // gets to have a go at it.
+    assert!(s.frames.len() > 2);
+
+    f.options.unwinders.register(
+        Cpu::X86_64,
+        TrampolineUnwinder {
+            replaces_builtin: true,
+        },
+    );
+    let s = f.walk_stack(stack()).await;
+    assert_eq!(s.frames.len(), 2);
+}
@@ -4,16 +4,17 @@
 // NOTE: arm64_old.rs and arm64.rs should be identical except for the names of
 // their context types.
 
-use crate::process_state::{FrameTrust, StackFrame};
+use crate::process_state::{FrameTrust, StackFrame, UnwindStrategy};
+use crate::stackwalker::arm_ehabi::{self, EhabiRegisters};
 use crate::stackwalker::unwind::Unwind;
-use crate::stackwalker::CfiStackWalker;
-use crate::SymbolProvider;
-use log::trace;
+use crate::stackwalker::{CfiStackWalker, UnwindRecorder};
+use crate::{ProcessorOptions, SymbolProvider};
 use minidump::{
     CpuContext, MinidumpContext, MinidumpContextValidity, MinidumpMemory, MinidumpModuleList,
-    MinidumpRawContext,
+    MinidumpRawContext, Module, UnifiedMemoryInfoList, UnifiedMemoryList,
 };
 use std::collections::HashSet;
+use tracing::trace;
 
 type ArmContext = minidump::format::CONTEXT_ARM;
 type Pointer = <ArmContext as CpuContext>::Register;
@@ -24,7 +25,12 @@ const FRAME_POINTER: &str = Registers::FramePointer.name();
 const STACK_POINTER: &str = Registers::StackPointer.name();
 const PROGRAM_COUNTER: &str = Registers::ProgramCounter.name();
 const LINK_REGISTER: &str = Registers::LinkRegister.name();
-const CALLEE_SAVED_REGS: &[&str] = &["r4", "r5", "r6", "r7", "r8", "r9", "r10", "r11"];
+pub(super) const CALLEE_SAVED_REGS: &[&str] = &["r4", "r5", "r6", "r7", "r8", "r9", "r10", "r11"];
+/// The integer registers, indexed by number.
+const REGISTERS: &[&str] = &[
+    "r0", "r1", "r2", "r3", "r4", "r5", "r6", "r7", "r8", "r9", "r10", "r11", "r12", "r13", "r14",
+    "r15",
+];
 
 async fn get_caller_by_cfi<P>(
     ctx: &ArmContext,
@@ -33,6 +39,7 @@ async fn get_caller_by_cfi<P>(
     stack_memory: &MinidumpMemory<'_>,
     modules: &MinidumpModuleList,
     symbol_provider: &P,
+    recorder: &mut UnwindRecorder,
 ) -> Option<StackFrame>
 where
     P: SymbolProvider + Sync,
@@ -57,11 +64,16 @@ where
         caller_validity: callee_forwarded_regs(valid),
 
         stack_memory,
+        modules,
+        rejected_reads: Default::default(),
     };
 
-    symbol_provider
-        .walk_frame(module, &mut stack_walker)
-        .await?;
+    let walked = symbol_provider.walk_frame(module, &mut stack_walker).await;
+    recorder.cfi_rejected_reads(&stack_walker);
+    if walked.is_none() {
+        recorder.reject("no cfi could be evaluated for the instruction");
+        return None;
+    }
     let caller_pc = stack_walker.caller_ctx.get_register_always(PROGRAM_COUNTER);
     let caller_sp = stack_walker.caller_ctx.get_register_always(STACK_POINTER);
 
@@ -94,12 +106,73 @@ fn callee_forwarded_regs(valid: &MinidumpContextValidity) -> HashSet<&'static st
     }
 }
 
+fn get_caller_by_exidx(
+    ctx: &ArmContext,
+    callee: &StackFrame,
+    stack_memory: &MinidumpMemory<'_>,
+    memory_list: &UnifiedMemoryList<'_>,
+    modules: &MinidumpModuleList,
+    recorder: &mut UnwindRecorder,
+) -> Option<StackFrame> {
+    trace!("unwind: trying exidx");
+    // Without symbols, the module's own EHABI unwind tables are the next best
+    // thing, if they were included in the minidump.
+    let valid = &callee.context.valid;
+    let module = modules.module_at_address(callee.instruction)?;
+    let opcodes =
+        arm_ehabi::find_unwind_opcodes(memory_list, module.base_address(), callee.instruction);
+    let opcodes = match opcodes {
+        Some(opcodes) => opcodes,
+        None => {
+            recorder.reject("no exidx entry covers the instruction");
+            return None;
+        }
+    };
+
+    let mut regs = EhabiRegisters::default();
+    for (reg, &name) in regs.regs.iter_mut().zip(REGISTERS) {
+        *reg = ctx.get_register(name, valid);
+    }
+    if arm_ehabi::execute(&opcodes, &mut regs, stack_memory).is_none() {
+        recorder.reject("exidx entry couldn't be executed");
+        return None;
+    }
+
+    let mut caller_ctx = ArmContext::default();
+    let mut caller_valid = HashSet::new();
+    for (i, (&val, &name)) in regs.regs.iter().zip(REGISTERS).enumerate() {
+        // Only the callee-saved registers are preserved across the call,
+        // the rest are only known if they were restored from the stack.
+        let preserved = CALLEE_SAVED_REGS.contains(&name)
+            || name == STACK_POINTER
+            || name == PROGRAM_COUNTER
+            || regs.popped & (1 << i) != 0;
+        if let (true, Some(val)) = (preserved, val) {
+            caller_ctx.set_register(name, val);
+            caller_valid.insert(name);
+        }
+    }
+
+    trace!(
+        "unwind: exidx evaluation was successful -- caller_pc: 0x{:08x}, caller_sp: 0x{:08x}",
+        caller_ctx.get_register_always(PROGRAM_COUNTER),
+        caller_ctx.get_register_always(STACK_POINTER),
+    );
+
+    let context = MinidumpContext {
+        raw: MinidumpRawContext::Arm(caller_ctx),
+        valid: MinidumpContextValidity::Some(caller_valid),
+    };
+    Some(StackFrame::from_context(context, FrameTrust::CallFrameInfo))
+}
+
 fn get_caller_by_frame_pointer<P>(
     ctx: &ArmContext,
     callee: &StackFrame,
     stack_memory: &MinidumpMemory<'_>,
     _modules: &MinidumpModuleList,
     _symbol_provider: &P,
+    recorder: &mut UnwindRecorder,
 ) -> Option<StackFrame>
 where
     P: SymbolProvider + Sync,
@@ -135,8 +208,8 @@ where
         // drowning the rest of the code in checked_add.
         return None;
     }
-    let caller_fp = stack_memory.get_memory_at_address(last_fp as u64)?;
-    let caller_lr = stack_memory.get_memory_at_address(last_fp as u64 + POINTER_WIDTH as u64)?;
+    let caller_fp = recorder.read(stack_memory, last_fp as u64)?;
+    let caller_lr = recorder.read(stack_memory, last_fp as u64 + POINTER_WIDTH as u64)?;
     let caller_pc = last_lr;
 
     // TODO: why does breakpad do this? how can fp be null by here?
@@ -173,12 +246,16 @@ where
     Some(StackFrame::from_context(context, FrameTrust::FramePointer))
 }
 
+#[allow(clippy::too_many_arguments)]
 async fn get_caller_by_scan<P>(
     ctx: &ArmContext,
     callee: &StackFrame,
     stack_memory: &MinidumpMemory<'_>,
+    memory_info: &UnifiedMemoryInfoList<'_>,
     modules: &MinidumpModuleList,
     symbol_provider: &P,
+    options: &ProcessorOptions<'_>,
+    recorder: &mut UnwindRecorder,
 ) -> Option<StackFrame>
 where
     P: SymbolProvider + Sync,
@@ -194,21 +271,12 @@ where
     let last_sp = ctx.get_register(STACK_POINTER, valid)?;
 
     // Number of pointer-sized values to scan through in our search.
-    let default_scan_range = 40;
-    let extended_scan_range = default_scan_range * 4;
-
-    // Breakpad devs found that the first frame of an unwind can be really messed up,
-    // and therefore benefits from a longer scan. Let's do it too.
-    let scan_range = if let FrameTrust::Context = callee.trust {
-        extended_scan_range
-    } else {
-        default_scan_range
-    };
+    let scan_range = super::scan_range(callee, options) as Pointer;
 
     for i in 0..scan_range {
         let address_of_pc = last_sp.checked_add(i * POINTER_WIDTH)?;
-        let caller_pc = stack_memory.get_memory_at_address(address_of_pc as u64)?;
-        if instruction_seems_valid(caller_pc, modules, symbol_provider).await {
+        let caller_pc = recorder.read(stack_memory, address_of_pc as u64)?;
+        if instruction_seems_valid(caller_pc, memory_info, modules, symbol_provider).await {
             // pc is pushed by CALL, so sp is just address_of_pc + ptr
             let caller_sp = address_of_pc.checked_add(POINTER_WIDTH)?;
 
@@ -266,13 +334,21 @@ where
 #[allow(clippy::match_like_matches_macro)]
 async fn instruction_seems_valid<P>(
     instruction: Pointer,
+    memory_info: &UnifiedMemoryInfoList<'_>,
     modules: &MinidumpModuleList,
     symbol_provider: &P,
 ) -> bool
 where
     P: SymbolProvider + Sync,
 {
-    super::instruction_seems_valid_by_symbols(instruction as u64, modules, symbol_provider).await
+    // Ignore the Thumb bit, which would throw off the symbol lookup.
+    super::instruction_seems_valid_by_symbols(
+        (instruction & !1) as u64,
+        memory_info,
+        modules,
+        symbol_provider,
+    )
+    .await
 }
 
 /*
@@ -302,8 +378,12 @@ impl Unwind for ArmContext {
         callee: &StackFrame,
         grand_callee: Option<&StackFrame>,
         stack_memory: Option<&MinidumpMemory<'_>>,
+        memory_list: &UnifiedMemoryList<'_>,
+        memory_info: &UnifiedMemoryInfoList<'_>,
         modules: &MinidumpModuleList,
         syms: &P,
+        options: &ProcessorOptions<'_>,
+        recorder: &mut UnwindRecorder,
     ) -> Option<StackFrame>
     where
         P: SymbolProvider + Sync,
@@ -313,13 +393,31 @@ impl Unwind for ArmContext {
         // .await doesn't like closures, so don't use Option chaining
         let mut frame = None;
         if frame.is_none() {
-            frame = get_caller_by_cfi(self, callee, grand_callee, stack, modules, syms).await;
+            recorder.attempt(UnwindStrategy::CallFrameInfo);
+            frame =
+                get_caller_by_cfi(self, callee, grand_callee, stack, modules, syms, recorder).await;
         }
         if frame.is_none() {
-            frame = get_caller_by_frame_pointer(self, callee, stack, modules, syms);
+            recorder.attempt(UnwindStrategy::Exidx);
+            frame = get_caller_by_exidx(self, callee, stack, memory_list, modules, recorder);
         }
         if frame.is_none() {
-            frame = get_caller_by_scan(self, callee, stack, modules, syms).await;
+            recorder.attempt(UnwindStrategy::FramePointer);
+            frame = get_caller_by_frame_pointer(self, callee, stack, modules, syms, recorder);
+        }
+        if frame.is_none() && options.allow_scan {
+            recorder.attempt(UnwindStrategy::Scan);
+            frame = get_caller_by_scan(
+                self,
+                callee,
+                stack,
+                memory_info,
+                modules,
+                syms,
+                options,
+                recorder,
+            )
+            .await;
         }
         let mut frame = frame?;
 
@@ -331,6 +429,7 @@ impl Unwind for ArmContext {
         // null, and we can assume unwinding is complete.
         if frame.context.get_instruction_pointer() < 4096 {
             trace!("unwind: instruction pointer was nullish, assuming unwind complete");
+            recorder.reject("instruction pointer was nullish");
             return None;
         }
         // If the new stack pointer is at a lower address than the old,
@@ -347,6 +446,7 @@ impl Unwind for ArmContext {
             let is_leaf = callee.trust == FrameTrust::Context && sp == last_sp;
             if !is_leaf {
                 trace!("unwind: stack pointer went backwards, assuming unwind complete");
+                recorder.reject("stack pointer went backwards");
                 return None;
             }
         }
@@ -358,8 +458,9 @@ impl Unwind for ArmContext {
         // the value to 2 less than that, so it points to the CALL instruction
         // (arm instructions are all 2 bytes wide). This is important because
         // we use this value to lookup the CFI we need to unwind the next frame.
-        let ip = frame.context.get_instruction_pointer() as u64;
-        frame.instruction = ip - 2;
+        //
+        // The Thumb bit has already been cleared from the instruction.
+        frame.instruction -= 2;
 
         Some(frame)
     }
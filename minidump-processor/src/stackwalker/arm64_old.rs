@@ -4,16 +4,19 @@
 // NOTE: arm64_old.rs and arm64.rs should be identical except for the names of
 // their context types.
 
-use crate::process_state::{FrameTrust, StackFrame};
+use crate::process_state::{FrameTrust, StackFrame, UnwindStrategy};
+use crate::stackwalker::eh_frame;
 use crate::stackwalker::unwind::Unwind;
-use crate::stackwalker::CfiStackWalker;
-use crate::SymbolProvider;
-use log::trace;
+use crate::stackwalker::{
+    function_base_by_symbols, strip_pac_mask, CfiStackWalker, UnwindRecorder,
+};
+use crate::{LinkRegisterMode, ProcessorOptions, SymbolProvider};
 use minidump::{
     CpuContext, MinidumpContext, MinidumpContextValidity, MinidumpMemory, MinidumpModuleList,
-    MinidumpRawContext, Module,
+    MinidumpRawContext, Module, UnifiedMemoryInfoList, UnifiedMemoryList,
 };
 use std::collections::HashSet;
+use tracing::trace;
 
 type ArmContext = minidump::format::CONTEXT_ARM64_OLD;
 type Pointer = <ArmContext as CpuContext>::Register;
@@ -24,10 +27,18 @@ const FRAME_POINTER: &str = Registers::FramePointer.name();
 const STACK_POINTER: &str = Registers::StackPointer.name();
 const LINK_REGISTER: &str = Registers::LinkRegister.name();
 const PROGRAM_COUNTER: &str = Registers::ProgramCounter.name();
-const CALLEE_SAVED_REGS: &[&str] = &[
+pub(super) const CALLEE_SAVED_REGS: &[&str] = &[
     "x19", "x20", "x21", "x22", "x23", "x24", "x25", "x26", "x27", "x28", "x29",
 ];
 
+/// DWARF register numbers for arm64, as used by `.eh_frame`.
+const DWARF_REGISTERS: &[&str] = &[
+    "x0", "x1", "x2", "x3", "x4", "x5", "x6", "x7", "x8", "x9", "x10", "x11", "x12", "x13", "x14",
+    "x15", "x16", "x17", "x18", "x19", "x20", "x21", "x22", "x23", "x24", "x25", "x26", "x27",
+    "x28", "x29", "x30", "sp",
+];
+
+#[allow(clippy::too_many_arguments)]
 async fn get_caller_by_cfi<P>(
     ctx: &ArmContext,
     callee: &StackFrame,
@@ -35,6 +46,8 @@ async fn get_caller_by_cfi<P>(
     stack_memory: &MinidumpMemory<'_>,
     modules: &MinidumpModuleList,
     symbol_provider: &P,
+    pac_mask: Option<u64>,
+    recorder: &mut UnwindRecorder,
 ) -> Option<StackFrame>
 where
     P: SymbolProvider + Sync,
@@ -60,11 +73,28 @@ where
         caller_validity: callee_forwarded_regs(valid),
 
         stack_memory,
+        modules,
+        rejected_reads: Default::default(),
     };
 
-    symbol_provider
-        .walk_frame(module, &mut stack_walker)
-        .await?;
+    let walked = symbol_provider.walk_frame(module, &mut stack_walker).await;
+    recorder.cfi_rejected_reads(&stack_walker);
+    if walked.is_none() {
+        recorder.reject("no cfi could be evaluated for the instruction");
+        return None;
+    }
+
+    // If we know exactly which bits of the recovered pointers are signatures,
+    // strip them here.
+    if let Some(mask) = pac_mask {
+        let caller_ctx = &mut stack_walker.caller_ctx;
+        let caller_pc = caller_ctx.get_register_always(PROGRAM_COUNTER);
+        caller_ctx.set_register(PROGRAM_COUNTER, caller_pc & !mask);
+        if stack_walker.caller_validity.contains(LINK_REGISTER) {
+            let caller_lr = caller_ctx.get_register_always(LINK_REGISTER);
+            caller_ctx.set_register(LINK_REGISTER, caller_lr & !mask);
+        }
+    }
 
     let caller_pc = stack_walker.caller_ctx.get_register_always(PROGRAM_COUNTER);
     let caller_sp = stack_walker.caller_ctx.get_register_always(STACK_POINTER);
@@ -91,6 +121,71 @@ where
     Some(StackFrame::from_context(context, FrameTrust::CallFrameInfo))
 }
 
+#[allow(clippy::too_many_arguments)]
+fn get_caller_by_eh_frame(
+    ctx: &ArmContext,
+    callee: &StackFrame,
+    grand_callee: Option<&StackFrame>,
+    stack_memory: &MinidumpMemory<'_>,
+    memory_list: &UnifiedMemoryList<'_>,
+    modules: &MinidumpModuleList,
+    pac_mask: Option<u64>,
+    recorder: &mut UnwindRecorder,
+) -> Option<StackFrame> {
+    trace!("unwind: trying eh_frame");
+
+    let valid = &callee.context.valid;
+    let _last_sp = ctx.get_register(STACK_POINTER, valid)?;
+    let module = modules.module_at_address(callee.instruction)?;
+    let grand_callee_parameter_size = grand_callee.and_then(|f| f.parameter_size).unwrap_or(0);
+
+    let mut stack_walker = CfiStackWalker {
+        instruction: callee.instruction,
+        grand_callee_parameter_size,
+
+        callee_ctx: ctx,
+        callee_validity: valid,
+
+        caller_ctx: *ctx,
+        caller_validity: callee_forwarded_regs(valid),
+
+        stack_memory,
+        modules,
+        rejected_reads: Default::default(),
+    };
+
+    let walked = eh_frame::walk_frame(
+        memory_list,
+        module.base_address(),
+        DWARF_REGISTERS,
+        &mut stack_walker,
+    );
+    recorder.cfi_rejected_reads(&stack_walker);
+    if walked.is_none() {
+        recorder.reject("no eh_frame entry could be evaluated for the instruction");
+        return None;
+    }
+
+    // The return address was saved signed, just like in the CFI case.
+    if let Some(mask) = pac_mask {
+        let caller_ctx = &mut stack_walker.caller_ctx;
+        let caller_pc = caller_ctx.get_register_always(PROGRAM_COUNTER);
+        caller_ctx.set_register(PROGRAM_COUNTER, caller_pc & !mask);
+    }
+
+    trace!(
+        "unwind: eh_frame evaluation was successful -- caller_pc: 0x{:016x}, caller_sp: 0x{:016x}",
+        stack_walker.caller_ctx.get_register_always(PROGRAM_COUNTER),
+        stack_walker.caller_ctx.get_register_always(STACK_POINTER),
+    );
+
+    let context = MinidumpContext {
+        raw: MinidumpRawContext::OldArm64(stack_walker.caller_ctx),
+        valid: MinidumpContextValidity::Some(stack_walker.caller_validity),
+    };
+    Some(StackFrame::from_context(context, FrameTrust::CallFrameInfo))
+}
+
 fn callee_forwarded_regs(valid: &MinidumpContextValidity) -> HashSet<&'static str> {
     match valid {
         MinidumpContextValidity::All => CALLEE_SAVED_REGS.iter().copied().collect(),
@@ -102,6 +197,111 @@ fn callee_forwarded_regs(valid: &MinidumpContextValidity) -> HashSet<&'static st
     }
 }
 
+/// Whether the context frame's lr still holds its return address.
+async fn context_link_register_is_valid<P>(
+    ctx: &ArmContext,
+    callee: &StackFrame,
+    modules: &MinidumpModuleList,
+    symbol_provider: &P,
+    mode: LinkRegisterMode,
+    pac_mask: Option<u64>,
+) -> bool
+where
+    P: SymbolProvider + Sync,
+{
+    match mode {
+        LinkRegisterMode::Always => true,
+        LinkRegisterMode::Never => false,
+        LinkRegisterMode::Heuristic => {
+            // If lr points back into the context frame's own function, then it's
+            // the return address of a call that function already made, which
+            // means it isn't a leaf and has saved its real return address in
+            // its frame record.
+            let function_base = match callee.function_base {
+                Some(base) => base,
+                None => return true,
+            };
+            let lr = match ctx.get_register(LINK_REGISTER, &callee.context.valid) {
+                Some(lr) => ptr_auth_strip(pac_mask, modules, lr),
+                None => return true,
+            };
+            let call = lr.wrapping_sub(4);
+            function_base_by_symbols(call, modules, symbol_provider).await != Some(function_base)
+        }
+    }
+}
+
+/// Unwinds a context frame which has set up its own frame record, so its
+/// return address has to be read from the stack instead of lr.
+fn get_caller_by_frame_record(
+    ctx: &ArmContext,
+    callee: &StackFrame,
+    stack_memory: &MinidumpMemory<'_>,
+    modules: &MinidumpModuleList,
+    pac_mask: Option<u64>,
+    recorder: &mut UnwindRecorder,
+) -> Option<StackFrame> {
+    trace!("unwind: trying frame record");
+    // The record at fp is the callee's own, so it holds the caller's fp and
+    // the callee's return address:
+    //
+    // pc := *(fp + ptr)
+    // sp := fp + ptr*2
+    // fp := *fp
+    let valid = &callee.context.valid;
+    let last_fp = ctx.get_register(FRAME_POINTER, valid)?;
+    if last_fp >= u64::MAX - POINTER_WIDTH * 2 {
+        return None;
+    }
+    let caller_fp: Pointer = recorder.read(stack_memory, last_fp)?;
+    let caller_fp = strip_pac_mask(pac_mask, caller_fp);
+    let caller_pc = recorder.read(stack_memory, last_fp + POINTER_WIDTH)?;
+    let caller_pc = ptr_auth_strip(pac_mask, modules, caller_pc);
+    let caller_sp = last_fp + POINTER_WIDTH * 2;
+
+    if is_non_canonical(caller_pc) {
+        trace!("unwind: rejecting frame record result for unreasonable instruction pointer");
+        recorder.reject("unreasonable instruction pointer");
+        return None;
+    }
+
+    trace!(
+        "unwind: frame record seems valid -- caller_pc: 0x{:016x}, caller_sp: 0x{:016x}",
+        caller_pc,
+        caller_sp,
+    );
+
+    let mut caller_ctx = ArmContext::default();
+    caller_ctx.set_register(PROGRAM_COUNTER, caller_pc);
+    caller_ctx.set_register(STACK_POINTER, caller_sp);
+
+    let mut valid = HashSet::new();
+    valid.insert(PROGRAM_COUNTER);
+    valid.insert(STACK_POINTER);
+
+    // Frame pointer unwinding expects every frame to look like the caller of a
+    // leaf function: lr holding its return address and fp pointing at its
+    // caller's frame record. So follow the caller's record too.
+    if caller_fp < u64::MAX - POINTER_WIDTH * 2 {
+        let caller_caller_fp: Option<Pointer> = stack_memory.get_memory_at_address(caller_fp);
+        let caller_lr: Option<Pointer> =
+            stack_memory.get_memory_at_address(caller_fp + POINTER_WIDTH);
+        if let (Some(fp), Some(lr)) = (caller_caller_fp, caller_lr) {
+            caller_ctx.set_register(FRAME_POINTER, strip_pac_mask(pac_mask, fp));
+            caller_ctx.set_register(LINK_REGISTER, ptr_auth_strip(pac_mask, modules, lr));
+            valid.insert(FRAME_POINTER);
+            valid.insert(LINK_REGISTER);
+        }
+    }
+
+    let context = MinidumpContext {
+        raw: MinidumpRawContext::OldArm64(caller_ctx),
+        valid: MinidumpContextValidity::Some(valid),
+    };
+    Some(StackFrame::from_context(context, FrameTrust::FramePointer))
+}
+
+#[allow(clippy::too_many_arguments)]
 fn get_caller_by_frame_pointer<P>(
     ctx: &ArmContext,
     callee: &StackFrame,
@@ -109,6 +309,8 @@ fn get_caller_by_frame_pointer<P>(
     stack_memory: &MinidumpMemory<'_>,
     modules: &MinidumpModuleList,
     _symbol_provider: &P,
+    pac_mask: Option<u64>,
+    recorder: &mut UnwindRecorder,
 ) -> Option<StackFrame>
 where
     P: SymbolProvider + Sync,
@@ -136,10 +338,17 @@ where
     let last_fp = ctx.get_register(FRAME_POINTER, valid)?;
     let last_sp = ctx.get_register(STACK_POINTER, valid)?;
     let last_lr = match ctx.get_register(LINK_REGISTER, valid) {
-        Some(lr) => ptr_auth_strip(modules, lr),
+        Some(lr) => ptr_auth_strip(pac_mask, modules, lr),
         None => {
             // FIXME: it would be good to write this back to the callee's ctx/validity
-            get_link_register_by_frame_pointer(ctx, valid, stack_memory, grand_callee, modules)?
+            get_link_register_by_frame_pointer(
+                ctx,
+                valid,
+                stack_memory,
+                grand_callee,
+                modules,
+                pac_mask,
+            )?
         }
     };
 
@@ -149,9 +358,10 @@ where
         // drowning the rest of the code in checked_add.
         return None;
     }
-    let caller_fp = stack_memory.get_memory_at_address(last_fp as u64)?;
-    let caller_lr = stack_memory.get_memory_at_address(last_fp + POINTER_WIDTH as u64)?;
-    let caller_lr = ptr_auth_strip(modules, caller_lr);
+    let caller_fp = recorder.read(stack_memory, last_fp as u64)?;
+    let caller_fp = strip_pac_mask(pac_mask, caller_fp);
+    let caller_lr = recorder.read(stack_memory, last_fp + POINTER_WIDTH as u64)?;
+    let caller_lr = ptr_auth_strip(pac_mask, modules, caller_lr);
     let caller_pc = last_lr;
 
     // TODO: why does breakpad do this? How could we get this far with a null fp?
@@ -170,6 +380,7 @@ where
     // Don't accept obviously wrong instruction pointers.
     if is_non_canonical(caller_pc) {
         trace!("unwind: rejecting frame pointer result for unreasonable instruction pointer");
+        recorder.reject("unreasonable instruction pointer");
         return None;
     }
 
@@ -207,6 +418,7 @@ fn get_link_register_by_frame_pointer(
     stack_memory: &MinidumpMemory<'_>,
     grand_callee: Option<&StackFrame>,
     modules: &MinidumpModuleList,
+    pac_mask: Option<u64>,
 ) -> Option<Pointer> {
     // It may happen that whatever unwinding strategy we're using managed to
     // restore %fp but didn't restore %lr. Frame-pointer-based unwinding requires
@@ -241,17 +453,22 @@ fn get_link_register_by_frame_pointer(
     // the callee's %lr, which should be right next to where its %fp is saved.
     let last_lr = stack_memory.get_memory_at_address(last_last_fp + POINTER_WIDTH)?;
 
-    Some(ptr_auth_strip(modules, last_lr))
+    Some(ptr_auth_strip(pac_mask, modules, last_lr))
 }
 
-fn ptr_auth_strip(modules: &MinidumpModuleList, ptr: Pointer) -> Pointer {
+fn ptr_auth_strip(pac_mask: Option<u64>, modules: &MinidumpModuleList, ptr: Pointer) -> Pointer {
     // ARMv8.3 introduced a code hardening system called "Pointer Authentication"
     // which is used on Apple platforms. It adds some extra high bits to the
     // several pointers when they get pushed to memory. Interestingly
     // this doesn't seem to affect return addresses pushed by a function call,
     // but it does affect lr/fp registers that get pushed to the stack.
     //
-    // Rather than actually thinking about how to recover the key and properly
+    // If we were told which bits are the signature, just use that.
+    if let Some(mask) = pac_mask {
+        return ptr & !mask;
+    }
+
+    // Otherwise, rather than actually thinking about how to recover the key and properly
     // decode this, let's apply a simple heuristic. We get the maximum address
     // that's contained in a module we know about, which will have some highest
     // bit that is set. We can then safely mask out any bit that's higher than
@@ -284,12 +501,16 @@ fn ptr_auth_strip(modules: &MinidumpModuleList, ptr: Pointer) -> Pointer {
     ptr
 }
 
+#[allow(clippy::too_many_arguments)]
 async fn get_caller_by_scan<P>(
     ctx: &ArmContext,
     callee: &StackFrame,
     stack_memory: &MinidumpMemory<'_>,
+    memory_info: &UnifiedMemoryInfoList<'_>,
     modules: &MinidumpModuleList,
     symbol_provider: &P,
+    options: &ProcessorOptions<'_>,
+    recorder: &mut UnwindRecorder,
 ) -> Option<StackFrame>
 where
     P: SymbolProvider + Sync,
@@ -305,21 +526,13 @@ where
     let last_sp = ctx.get_register(STACK_POINTER, valid)?;
 
     // Number of pointer-sized values to scan through in our search.
-    let default_scan_range = 40;
-    let extended_scan_range = default_scan_range * 4;
-
-    // Breakpad devs found that the first frame of an unwind can be really messed up,
-    // and therefore benefits from a longer scan. Let's do it too.
-    let scan_range = if let FrameTrust::Context = callee.trust {
-        extended_scan_range
-    } else {
-        default_scan_range
-    };
+    let scan_range = super::scan_range(callee, options) as Pointer;
 
     for i in 0..scan_range {
         let address_of_pc = last_sp.checked_add(i * POINTER_WIDTH)?;
-        let caller_pc = stack_memory.get_memory_at_address(address_of_pc as u64)?;
-        if instruction_seems_valid(caller_pc, modules, symbol_provider).await {
+        let caller_pc = recorder.read(stack_memory, address_of_pc as u64)?;
+        let caller_pc = strip_pac_mask(options.arm64_pac_mask, caller_pc);
+        if instruction_seems_valid(caller_pc, memory_info, modules, symbol_provider).await {
             // pc is pushed by CALL, so sp is just address_of_pc + ptr
             let caller_sp = address_of_pc.checked_add(POINTER_WIDTH)?;
 
@@ -376,6 +589,7 @@ where
 /// and immediately start doing unreliable scans.
 async fn instruction_seems_valid<P>(
     instruction: Pointer,
+    memory_info: &UnifiedMemoryInfoList<'_>,
     modules: &MinidumpModuleList,
     symbol_provider: &P,
 ) -> bool
@@ -386,7 +600,13 @@ where
         return false;
     }
 
-    super::instruction_seems_valid_by_symbols(instruction as u64, modules, symbol_provider).await
+    super::instruction_seems_valid_by_symbols(
+        instruction as u64,
+        memory_info,
+        modules,
+        symbol_provider,
+    )
+    .await
 }
 
 fn is_non_canonical(instruction: Pointer) -> bool {
@@ -421,8 +641,12 @@ impl Unwind for ArmContext {
         callee: &StackFrame,
         grand_callee: Option<&StackFrame>,
         stack_memory: Option<&MinidumpMemory<'_>>,
+        memory_list: &UnifiedMemoryList<'_>,
+        memory_info: &UnifiedMemoryInfoList<'_>,
         modules: &MinidumpModuleList,
         syms: &P,
+        options: &ProcessorOptions<'_>,
+        recorder: &mut UnwindRecorder,
     ) -> Option<StackFrame>
     where
         P: SymbolProvider + Sync,
@@ -432,13 +656,80 @@ impl Unwind for ArmContext {
         // .await doesn't like closures, so don't use Option chaining
         let mut frame = None;
         if frame.is_none() {
-            frame = get_caller_by_cfi(self, callee, grand_callee, stack, modules, syms).await;
+            recorder.attempt(UnwindStrategy::CallFrameInfo);
+            frame = get_caller_by_cfi(
+                self,
+                callee,
+                grand_callee,
+                stack,
+                modules,
+                syms,
+                options.arm64_pac_mask,
+                recorder,
+            )
+            .await;
         }
         if frame.is_none() {
-            frame = get_caller_by_frame_pointer(self, callee, grand_callee, stack, modules, syms);
+            recorder.attempt(UnwindStrategy::EhFrame);
+            frame = get_caller_by_eh_frame(
+                self,
+                callee,
+                grand_callee,
+                stack,
+                memory_list,
+                modules,
+                options.arm64_pac_mask,
+                recorder,
+            );
+        }
+        if frame.is_none()
+            && callee.trust == FrameTrust::Context
+            && !context_link_register_is_valid(
+                self,
+                callee,
+                modules,
+                syms,
+                options.link_register_mode,
+                options.arm64_pac_mask,
+            )
+            .await
+        {
+            recorder.attempt(UnwindStrategy::FramePointer);
+            frame = get_caller_by_frame_record(
+                self,
+                callee,
+                stack,
+                modules,
+                options.arm64_pac_mask,
+                recorder,
+            );
         }
         if frame.is_none() {
-            frame = get_caller_by_scan(self, callee, stack, modules, syms).await;
+            recorder.attempt(UnwindStrategy::FramePointer);
+            frame = get_caller_by_frame_pointer(
+                self,
+                callee,
+                grand_callee,
+                stack,
+                modules,
+                syms,
+                options.arm64_pac_mask,
+                recorder,
+            );
+        }
+        if frame.is_none() && options.allow_scan {
+            recorder.attempt(UnwindStrategy::Scan);
+            frame = get_caller_by_scan(
+                self,
+                callee,
+                stack,
+                memory_info,
+                modules,
+                syms,
+                options,
+                recorder,
+            )
+            .await;
         }
         let mut frame = frame?;
 
@@ -450,6 +741,7 @@ impl Unwind for ArmContext {
         // null, and we can assume unwinding is complete.
         if frame.context.get_instruction_pointer() < 4096 {
             trace!("unwind: instruction pointer was nullish, assuming unwind complete");
+            recorder.reject("instruction pointer was nullish");
             return None;
         }
 
@@ -468,6 +760,7 @@ impl Unwind for ArmContext {
             let is_leaf = callee.trust == FrameTrust::Context && sp == last_sp;
             if !is_leaf {
                 trace!("unwind: stack pointer went backwards, assuming unwind complete");
+                recorder.reject("stack pointer went backwards");
                 return None;
             }
         }
@@ -6,7 +6,7 @@
 
 use crate::process_state::*;
 use crate::stackwalker::walk_stack;
-use crate::{string_symbol_supplier, Symbolizer};
+use crate::{string_symbol_supplier, LinkRegisterMode, ProcessorOptions, Symbolizer};
 use minidump::*;
 use std::collections::HashMap;
 use test_assembler::*;
@@ -17,6 +17,7 @@ struct TestFixture {
     pub raw: Context,
     pub modules: MinidumpModuleList,
     pub symbols: HashMap<String, String>,
+    pub options: ProcessorOptions<'static>,
 }
 
 impl TestFixture {
@@ -30,6 +31,7 @@ impl TestFixture {
                 MinidumpModule::new(0x50000000, 0x10000, "module2"),
             ]),
             symbols: HashMap::new(),
+            options: ProcessorOptions::default(),
         }
     }
 
@@ -51,8 +53,11 @@ impl TestFixture {
         walk_stack(
             &Some(&context),
             Some(&stack_memory),
+            &UnifiedMemoryList::default(),
+            &UnifiedMemoryInfoList::default(),
             &self.modules,
             &symbolizer,
+            &self.options,
         )
         .await
     }
@@ -408,6 +413,169 @@ async fn test_frame_pointer() {
     }
 }
 
+/// A context frame which has already set up its frame record and made a
+/// call, so lr points back into its own function.
+fn non_leaf_stack(f: &mut TestFixture) -> (Section, Label, Label) {
+    let mut stack = Section::new();
+    stack.start().set_const(0x80000000);
+
+    let return_address1 = 0x50000100u64;
+    let return_address2 = 0x50000900u64;
+    let frame0_fp = Label::new();
+    let frame1_fp = Label::new();
+    let frame1_sp = Label::new();
+    let frame2_fp = Label::new();
+
+    stack = stack
+        // frame 0
+        .append_repeated(0, 32) // space
+        .mark(&frame0_fp)
+        .D64(&frame1_fp) // saved fp
+        .D64(return_address1) // saved lr
+        .mark(&frame1_sp)
+        // frame 1
+        .append_repeated(0, 32) // space
+        .mark(&frame1_fp)
+        .D64(&frame2_fp) // saved fp
+        .D64(return_address2) // saved lr
+        // frame 2
+        .append_repeated(0, 32) // space
+        .mark(&frame2_fp)
+        .D64(0)
+        .D64(0);
+
+    f.raw.set_register("pc", 0x40005510);
+    // The return address of a call the context frame made.
+    f.raw.set_register("lr", 0x40005508);
+    f.raw.set_register("fp", frame0_fp.value().unwrap());
+    f.raw.set_register("sp", stack.start().value().unwrap());
+    f.add_symbols(
+        String::from("module1"),
+        String::from("FUNC 5500 100 0 monotreme\n"),
+    );
+
+    (stack, frame1_sp, frame2_fp)
+}
+
+#[tokio::test]
+async fn test_link_register_heuristic_non_leaf() {
+    let mut f = TestFixture::new();
+    let (stack, frame1_sp, frame2_fp) = non_leaf_stack(&mut f);
+
+    let s = f.walk_stack(stack).await;
+    assert_eq!(s.frames.len(), 3);
+
+    {
+        // Frame 1
+        let frame = &s.frames[1];
+        let valid = &frame.context.valid;
+        assert_eq!(frame.trust, FrameTrust::FramePointer);
+        if let MinidumpContextValidity::Some(ref which) = valid {
+            assert_eq!(which.len(), 4);
+        } else {
+            unreachable!();
+        }
+
+        if let MinidumpRawContext::Arm64(ctx) = &frame.context.raw {
+            assert_eq!(ctx.get_register("pc", valid).unwrap(), 0x50000100);
+            assert_eq!(ctx.get_register("lr", valid).unwrap(), 0x50000900);
+            assert_eq!(
+                ctx.get_register("sp", valid).unwrap(),
+                frame1_sp.value().unwrap()
+            );
+            assert_eq!(
+                ctx.get_register("fp", valid).unwrap(),
+                frame2_fp.value().unwrap()
+            );
+        } else {
+            unreachable!();
+        }
+    }
+
+    {
+        // Frame 2
+        let frame = &s.frames[2];
+        let valid = &frame.context.valid;
+        assert_eq!(frame.trust, FrameTrust::FramePointer);
+        if let MinidumpRawContext::Arm64(ctx) = &frame.context.raw {
+            assert_eq!(ctx.get_register("pc", valid).unwrap(), 0x50000900);
+        } else {
+            unreachable!();
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_link_register_always() {
+    // Trusting lr anyway produces a bogus caller in the context frame's own function.
+    let mut f = TestFixture::new();
+    f.options.link_register_mode = LinkRegisterMode::Always;
+    let (stack, _, _) = non_leaf_stack(&mut f);
+
+    let s = f.walk_stack(stack).await;
+    let frame = &s.frames[1];
+    assert_eq!(frame.trust, FrameTrust::FramePointer);
+    assert_eq!(frame.context.get_instruction_pointer(), 0x40005508);
+}
+
+/// A leaf function, whose lr points outside of it.
+fn leaf_stack(f: &mut TestFixture) -> Section {
+    let mut stack = Section::new();
+    stack.start().set_const(0x80000000);
+
+    let return_address2 = 0x50000900u64;
+    let frame1_fp = Label::new();
+    let frame2_fp = Label::new();
+
+    stack = stack
+        // frame 1, the leaf's caller
+        .append_repeated(0, 32) // space
+        .mark(&frame1_fp)
+        .D64(&frame2_fp) // saved fp
+        .D64(return_address2) // saved lr
+        // frame 2
+        .append_repeated(0, 32) // space
+        .mark(&frame2_fp)
+        .D64(0)
+        .D64(0);
+
+    f.raw.set_register("pc", 0x40005510);
+    f.raw.set_register("lr", 0x50000100);
+    f.raw.set_register("fp", frame1_fp.value().unwrap());
+    f.raw.set_register("sp", stack.start().value().unwrap());
+    f.add_symbols(
+        String::from("module1"),
+        String::from("FUNC 5500 100 0 monotreme\n"),
+    );
+
+    stack
+}
+
+#[tokio::test]
+async fn test_link_register_heuristic_leaf() {
+    let mut f = TestFixture::new();
+    let stack = leaf_stack(&mut f);
+
+    let s = f.walk_stack(stack).await;
+    assert_eq!(s.frames.len(), 3);
+    assert_eq!(s.frames[1].trust, FrameTrust::FramePointer);
+    assert_eq!(s.frames[1].context.get_instruction_pointer(), 0x50000100);
+    assert_eq!(s.frames[2].trust, FrameTrust::FramePointer);
+    assert_eq!(s.frames[2].context.get_instruction_pointer(), 0x50000900);
+}
+
+#[tokio::test]
+async fn test_link_register_never() {
+    // Refusing to trust lr skips over the leaf's caller.
+    let mut f = TestFixture::new();
+    f.options.link_register_mode = LinkRegisterMode::Never;
+    let stack = leaf_stack(&mut f);
+
+    let s = f.walk_stack(stack).await;
+    assert_eq!(s.frames[1].trust, FrameTrust::FramePointer);
+    assert_eq!(s.frames[1].context.get_instruction_pointer(), 0x50000900);
+}
+
 #[tokio::test]
 async fn test_ptr_auth_strip() {
     // Same as the basic frame pointer test but extra high bits have been set which
@@ -518,6 +686,149 @@ async fn test_ptr_auth_strip() {
     }
 }
 
+#[tokio::test]
+async fn test_ptr_auth_masks() {
+    // When we're told exactly which bits hold the signature, we should strip
+    // them even if the result doesn't point into a module we know about.
+    let mut f = TestFixture::new();
+    f.options = ProcessorOptions::default().with_arm64_pac_mask(0xffff800000000000);
+    let mut stack = Section::new();
+    stack.start().set_const(0x80000000);
+
+    let return_address1 = 0x60000100u64;
+    let return_address2 = 0x60000900u64;
+    let authenticated_return_address1 = return_address1 | 0x2a5b000000000000u64;
+    let authenticated_return_address2 = return_address2 | 0x00f1800000000000u64;
+
+    let frame1_sp = Label::new();
+    let frame2_sp = Label::new();
+    let frame1_fp = Label::new();
+    let frame2_fp = Label::new();
+
+    stack = stack
+        // frame 0
+        .append_repeated(0, 64) // space
+        .mark(&frame1_fp) // next fp will point to the next value
+        .D64(&frame2_fp) // save current frame pointer
+        .D64(authenticated_return_address2) // save current link register
+        .mark(&frame1_sp)
+        // frame 1
+        .append_repeated(0, 64) // space
+        .mark(&frame2_fp)
+        .D64(0)
+        .D64(0)
+        .mark(&frame2_sp)
+        // frame 2
+        .append_repeated(0, 64); // Whatever values on the stack.
+
+    f.raw.set_register("pc", 0x40005510);
+    f.raw.set_register("lr", authenticated_return_address1);
+    f.raw.set_register("fp", frame1_fp.value().unwrap());
+    f.raw.set_register("sp", stack.start().value().unwrap());
+
+    let s = f.walk_stack(stack).await;
+    assert_eq!(s.frames.len(), 3);
+
+    {
+        // Frame 1
+        let frame = &s.frames[1];
+        let valid = &frame.context.valid;
+        assert_eq!(frame.trust, FrameTrust::FramePointer);
+
+        if let MinidumpRawContext::Arm64(ctx) = &frame.context.raw {
+            assert_eq!(ctx.get_register("pc", valid).unwrap(), return_address1);
+            assert_eq!(ctx.get_register("lr", valid).unwrap(), return_address2);
+            assert_eq!(
+                ctx.get_register("sp", valid).unwrap(),
+                frame1_sp.value().unwrap()
+            );
+            assert_eq!(
+                ctx.get_register("fp", valid).unwrap(),
+                frame2_fp.value().unwrap()
+            );
+        } else {
+            unreachable!();
+        }
+    }
+
+    {
+        // Frame 2
+        let frame = &s.frames[2];
+        let valid = &frame.context.valid;
+        assert_eq!(frame.trust, FrameTrust::FramePointer);
+
+        if let MinidumpRawContext::Arm64(ctx) = &frame.context.raw {
+            assert_eq!(ctx.get_register("pc", valid).unwrap(), return_address2);
+            assert_eq!(
+                ctx.get_register("sp", valid).unwrap(),
+                frame2_sp.value().unwrap()
+            );
+        } else {
+            unreachable!();
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_pac_mask_option() {
+    // The configured mask strips signed return addresses before scanning
+    // checks them.
+    let return_address1 = 0x50000100u64;
+    let return_address2 = 0x50000900u64;
+    // The stack, and the stack pointers of frames 1 and 2.
+    let signed_stack = || {
+        let frame1_sp = Label::new();
+        let frame2_sp = Label::new();
+        let stack = Section::new();
+        stack.start().set_const(0x80000000);
+        let stack = stack
+            // frame 0
+            .append_repeated(0, 16) // space
+            .D64(0x40090000) // junk that's not
+            .D64(0x60000000) // a return address
+            .D64(return_address1 | 0x2a5b000000000000) // signed return address
+            // frame 1
+            .mark(&frame1_sp)
+            .append_repeated(0, 16) // space
+            .D64(0xF0000000) // more junk
+            .D64(0x0000000D)
+            .D64(return_address2 | 0x00f1800000000000) // signed return address
+            // frame 2
+            .mark(&frame2_sp)
+            .append_repeated(0, 64); // end of stack
+        (
+            stack,
+            frame1_sp.value().unwrap(),
+            frame2_sp.value().unwrap(),
+        )
+    };
+
+    let mut f = TestFixture::new();
+    f.raw.set_register("pc", 0x40005510);
+    f.raw.set_register("sp", 0x80000000);
+
+    // The signed values don't look like code without stripping.
+    let s = f.walk_stack(signed_stack().0).await;
+    assert_eq!(s.frames.len(), 1);
+
+    f.options = ProcessorOptions::default().with_arm64_pac_mask(0xffff800000000000);
+    let (stack, frame1_sp, frame2_sp) = signed_stack();
+    let s = f.walk_stack(stack).await;
+    assert_eq!(s.frames.len(), 3);
+
+    let expected = [(return_address1, frame1_sp), (return_address2, frame2_sp)];
+    for (frame, &(pc, sp)) in s.frames[1..].iter().zip(expected.iter()) {
+        let valid = &frame.context.valid;
+        assert_eq!(frame.trust, FrameTrust::Scan);
+        if let MinidumpRawContext::Arm64(ctx) = &frame.context.raw {
+            assert_eq!(ctx.get_register("pc", valid).unwrap(), pc);
+            assert_eq!(ctx.get_register("sp", valid).unwrap(), sp);
+        } else {
+            unreachable!();
+        }
+    }
+}
+
 const CALLEE_SAVE_REGS: &[&str] = &[
     "pc", "sp", "fp", "x19", "x20", "x21", "x22", "x23", "x24", "x25", "x26", "x27", "x28",
 ];
@@ -1063,3 +1374,148 @@ async fn test_frame_pointer_infinite_equality() {
 
     // Never get to frame 2, alas!
 }
+
+async fn walk_frame_pointers_with_limit(max_frames: usize) -> CallStack {
+    // The same stack as test_frame_pointer, which unwinds to three frames.
+    let mut f = TestFixture::new();
+    f.options.max_frames = max_frames;
+    let mut stack = Section::new();
+    stack.start().set_const(0x80000000);
+
+    let return_address1 = 0x50000100u64;
+    let return_address2 = 0x50000900u64;
+    let frame1_fp = Label::new();
+    let frame2_fp = Label::new();
+
+    stack = stack
+        // frame 0
+        .append_repeated(0, 64) // space
+        .mark(&frame1_fp) // next fp will point to the next value
+        .D64(&frame2_fp) // save current frame pointer
+        .D64(return_address2) // save current link register
+        // frame 1
+        .append_repeated(0, 64) // space
+        .mark(&frame2_fp)
+        .D64(0)
+        .D64(0)
+        // frame 2
+        .append_repeated(0, 64); // Whatever values on the stack.
+
+    f.raw.set_register("pc", 0x40005510);
+    f.raw.set_register("lr", return_address1);
+    f.raw.set_register("fp", frame1_fp.value().unwrap());
+    f.raw.set_register("sp", stack.start().value().unwrap());
+
+    f.walk_stack(stack).await
+}
+
+#[tokio::test]
+async fn test_frame_limit() {
+    let s = walk_frame_pointers_with_limit(2).await;
+    assert_eq!(s.frames.len(), 2);
+    assert_eq!(s.info, CallStackInfo::FrameLimitReached);
+
+    // A stack that exactly fits in the limit isn't truncated.
+    let s = walk_frame_pointers_with_limit(3).await;
+    assert_eq!(s.frames.len(), 3);
+    assert_eq!(s.info, CallStackInfo::Ok);
+}
+
+async fn scan_with_options(options: ProcessorOptions<'static>) -> CallStack {
+    // Each return address is 10 pointers away from its callee's stack pointer.
+    let mut f = TestFixture::new();
+    f.options = options;
+    let mut stack = Section::new();
+    stack.start().set_const(0x80000000);
+
+    stack = stack
+        // frame 0
+        .append_repeated(0, 80) // space
+        .D64(0x50000100u64) // return address
+        // frame 1
+        .append_repeated(0, 80) // space
+        .D64(0x50000900u64) // return address
+        // frame 2
+        .append_repeated(0, 64); // end of stack
+
+    f.raw.set_register("pc", 0x40005510);
+    f.raw.set_register("sp", stack.start().value().unwrap());
+
+    f.walk_stack(stack).await
+}
+
+#[tokio::test]
+async fn test_scan_options() {
+    let s = scan_with_options(ProcessorOptions::default()).await;
+    assert_eq!(s.frames.len(), 3);
+    assert_eq!(s.frames[1].trust, FrameTrust::Scan);
+    assert_eq!(s.frames[2].trust, FrameTrust::Scan);
+
+    let s = scan_with_options(ProcessorOptions {
+        allow_scan: false,
+        ..ProcessorOptions::default()
+    })
+    .await;
+    assert_eq!(s.frames.len(), 1);
+
+    // Too shallow to find frame 2, but the context frame's extended scan
+    // still finds frame 1.
+    let s = scan_with_options(ProcessorOptions {
+        scan_depth: 8,
+        ..ProcessorOptions::default()
+    })
+    .await;
+    assert_eq!(s.frames.len(), 2);
+
+    let s = scan_with_options(ProcessorOptions {
+        scan_depth: 8,
+        extended_context_scan: false,
+        ..ProcessorOptions::default()
+    })
+    .await;
+    assert_eq!(s.frames.len(), 1);
+}
+
+#[tokio::test]
+async fn test_unwind_diagnostics() {
+    let s = scan_with_options(ProcessorOptions::default()).await;
+    assert!(s.frames.iter().all(|f| f.unwind_diagnostics.is_none()));
+
+    let s = scan_with_options(ProcessorOptions {
+        unwind_diagnostics: true,
+        ..ProcessorOptions::default()
+    })
+    .await;
+    assert_eq!(s.frames.len(), 3);
+
+    // Without symbols or frame pointers, only scanning finds frame 1.
+    let attempts = &s.frames[0].unwind_diagnostics.as_ref().unwrap().attempts;
+    let strategies: Vec<_> = attempts.iter().map(|a| a.strategy).collect();
+    assert_eq!(
+        strategies,
+        [
+            UnwindStrategy::CallFrameInfo,
+            UnwindStrategy::EhFrame,
+            UnwindStrategy::FramePointer,
+            UnwindStrategy::Scan,
+        ]
+    );
+    assert!(!attempts[0].succeeded);
+    assert_eq!(attempts[0].rejections.len(), 1);
+    // fp is 0, so its frame record is nowhere near the stack.
+    assert!(!attempts[2].succeeded);
+    assert_eq!(attempts[2].rejected_reads, [0]);
+    assert!(attempts[3].succeeded);
+
+    // The scan for frame 2's caller runs off the end of the stack.
+    let attempts = &s.frames[2].unwind_diagnostics.as_ref().unwrap().attempts;
+    let scan = attempts.last().unwrap();
+    assert_eq!(scan.strategy, UnwindStrategy::Scan);
+    assert!(!scan.succeeded);
+    assert_eq!(scan.rejected_reads, [0x800000f0]);
+
+    let mut output = Vec::new();
+    s.print(&mut output).unwrap();
+    let output = String::from_utf8(output).unwrap();
+    assert!(output.contains("      scan: failed (couldn't read 0x800000f0)\n"));
+}
@@ -0,0 +1,260 @@
+// Copyright 2015 Ted Mielczarek. See the COPYRIGHT
+// file at the top-level directory of this distribution.
+
+//! Unwinding with the ARM Exception Handling ABI (EHABI) tables.
+//!
+//! ARM32 ELF binaries carry their own unwind tables in the `.ARM.exidx`
+//! and `.ARM.extab` sections, which are loaded into memory along with the
+//! rest of the module. When the minidump includes that memory, we can find
+//! the tables through the module's program headers and evaluate them
+//! without any symbol files.
+//!
+//! See "Exception Handling ABI for the ARM Architecture" (ARM IHI 0038)
+//! for the details of the format.
+
+use minidump::{MinidumpMemory, UnifiedMemoryList};
+use tracing::trace;
+
+use super::elf::{self, PT_ARM_EXIDX};
+
+/// An exidx entry marking a function which can't be unwound.
+const EXIDX_CANTUNWIND: u32 = 1;
+
+const SP: usize = 13;
+const LR: usize = 14;
+const PC: usize = 15;
+
+/// The registers of a frame while it's being unwound, indexed by number.
+#[derive(Debug, Clone, Default)]
+pub struct EhabiRegisters {
+    pub regs: [Option<u32>; 16],
+    /// The registers which were restored from the stack.
+    pub popped: u16,
+}
+
+fn read_u32(memory_list: &UnifiedMemoryList<'_>, address: u64) -> Option<u32> {
+    elf::read_memory(memory_list, address)
+}
+
+/// Resolve a place-relative 31-bit offset stored at `address`.
+fn prel31(address: u64, word: u32) -> u64 {
+    // Sign-extend from bit 30.
+    let offset = ((word << 1) as i32 >> 1) as i64;
+    address.wrapping_add(offset as u64)
+}
+
+/// Find the `.ARM.exidx` table of the module loaded at `base`.
+///
+/// Returns the address and size of the table.
+fn find_exidx(memory_list: &UnifiedMemoryList<'_>, base: u64) -> Option<(u64, u64)> {
+    let segment = elf::find_segment(memory_list, base, PT_ARM_EXIDX)?;
+    if segment.is_64 {
+        return None;
+    }
+    Some((segment.address, segment.size))
+}
+
+/// Binary search the exidx table for the entry covering `address`.
+fn find_exidx_entry(
+    memory_list: &UnifiedMemoryList<'_>,
+    exidx: u64,
+    size: u64,
+    address: u64,
+) -> Option<u64> {
+    // Entries are sorted by function address, and each one covers everything
+    // up to the next, so we want the last entry that starts at or before
+    // our address.
+    let mut low = 0;
+    let mut high = size / 8;
+    while low < high {
+        let mid = low + (high - low) / 2;
+        let entry = exidx + mid * 8;
+        let start = prel31(entry, read_u32(memory_list, entry)?);
+        if start <= address {
+            low = mid + 1;
+        } else {
+            high = mid;
+        }
+    }
+    let index = low.checked_sub(1)?;
+    Some(exidx + index * 8)
+}
+
+/// Collect the unwind opcodes for an exidx entry, following it into
+/// `.ARM.extab` if necessary.
+fn unwind_opcodes(memory_list: &UnifiedMemoryList<'_>, entry: u64) -> Option<Vec<u8>> {
+    let data = read_u32(memory_list, entry + 4)?;
+    if data == EXIDX_CANTUNWIND {
+        trace!("unwind: exidx entry can't unwind");
+        return None;
+    }
+
+    // Either the data is inline in the exidx entry, or it's an offset to
+    // the data in the extab.
+    let (address, word) = if data & 0x8000_0000 != 0 {
+        (entry + 4, data)
+    } else {
+        let extab = prel31(entry + 4, data);
+        (extab, read_u32(memory_list, extab)?)
+    };
+
+    let mut opcodes = Vec::new();
+    let (extra_words, words_start) = if word & 0x8000_0000 != 0 {
+        // The compact model, which has the personality routine's index in
+        // bits 24-27.
+        match (word >> 24) & 0xf {
+            0 => {
+                opcodes.extend_from_slice(&word.to_be_bytes()[1..]);
+                (0, address + 4)
+            }
+            1 | 2 => {
+                opcodes.extend_from_slice(&word.to_be_bytes()[2..]);
+                ((word >> 16) & 0xff, address + 4)
+            }
+            _ => return None,
+        }
+    } else {
+        // A generic personality routine. GCC's routines are followed by
+        // opcodes in the same format as the compact model's 1 and 2.
+        let word = read_u32(memory_list, address + 4)?;
+        opcodes.extend_from_slice(&word.to_be_bytes()[1..]);
+        (word >> 24, address + 8)
+    };
+    for i in 0..extra_words as u64 {
+        let word = read_u32(memory_list, words_start + i * 4)?;
+        opcodes.extend_from_slice(&word.to_be_bytes());
+    }
+    Some(opcodes)
+}
+
+/// Look up the unwind opcodes for `address` in the exidx table of the
+/// module loaded at `module_base`.
+pub fn find_unwind_opcodes(
+    memory_list: &UnifiedMemoryList<'_>,
+    module_base: u64,
+    address: u64,
+) -> Option<Vec<u8>> {
+    let (exidx, size) = find_exidx(memory_list, module_base)?;
+    let entry = find_exidx_entry(memory_list, exidx, size, address)?;
+    unwind_opcodes(memory_list, entry)
+}
+
+/// Execute unwind opcodes on `regs`, which start out as the callee's
+/// registers and end up as the caller's.
+///
+/// On success the caller's sp and pc are always set.
+pub fn execute(
+    opcodes: &[u8],
+    regs: &mut EhabiRegisters,
+    stack_memory: &MinidumpMemory<'_>,
+) -> Option<()> {
+    let mut vsp = regs.regs[SP]?;
+    let mut opcodes = opcodes.iter().copied();
+
+    // Pop the registers in `mask` from the stack, lowest first.
+    let pop = |regs: &mut EhabiRegisters, vsp: &mut u32, mask: u16| -> Option<()> {
+        for reg in 0..16 {
+            if mask & (1 << reg) != 0 {
+                regs.regs[reg] = Some(stack_memory.get_memory_at_address(*vsp as u64)?);
+                regs.popped |= 1 << reg;
+                *vsp = vsp.checked_add(4)?;
+            }
+        }
+        if mask & (1 << SP) != 0 {
+            *vsp = regs.regs[SP]?;
+        }
+        Some(())
+    };
+
+    // Running out of opcodes is an implicit finish.
+    while let Some(op) = opcodes.next() {
+        match op {
+            // vsp = vsp + (xxxxxx << 2) + 4
+            0x00..=0x3f => vsp = vsp.checked_add(((op as u32 & 0x3f) << 2) + 4)?,
+            // vsp = vsp - (xxxxxx << 2) - 4
+            0x40..=0x7f => vsp = vsp.checked_sub(((op as u32 & 0x3f) << 2) + 4)?,
+            // Pop up to 12 integer registers under masks {r15-r12}, {r11-r4}
+            0x80..=0x8f => {
+                let mask = ((op as u16 & 0xf) << 8) | opcodes.next()? as u16;
+                if mask == 0 {
+                    // Refuse to unwind
+                    return None;
+                }
+                pop(regs, &mut vsp, mask << 4)?;
+            }
+            // Reserved as prefix for ARM register to register moves
+            0x9d | 0x9f => return None,
+            // vsp = r[nnnn]
+            0x90..=0x9f => vsp = regs.regs[op as usize & 0xf]?,
+            // Pop r4-r[4+nnn], and r14 if the 0x08 bit is set
+            0xa0..=0xaf => {
+                let mut mask = ((1u16 << ((op & 0x7) + 1)) - 1) << 4;
+                if op & 0x08 != 0 {
+                    mask |= 1 << LR;
+                }
+                pop(regs, &mut vsp, mask)?;
+            }
+            // Finish
+            0xb0 => break,
+            // Pop integer registers under mask {r3, r2, r1, r0}
+            0xb1 => {
+                let mask = opcodes.next()?;
+                if mask == 0 || mask & 0xf0 != 0 {
+                    // Spare
+                    return None;
+                }
+                pop(regs, &mut vsp, mask as u16)?;
+            }
+            // vsp = vsp + 0x204 + (uleb128 << 2)
+            0xb2 => {
+                let mut value = 0u32;
+                let mut shift = 0;
+                loop {
+                    let byte = opcodes.next()?;
+                    value |= ((byte & 0x7f) as u32).checked_shl(shift)?;
+                    shift += 7;
+                    if byte & 0x80 == 0 {
+                        break;
+                    }
+                }
+                vsp = vsp.checked_add(0x204)?.checked_add(value.checked_shl(2)?)?;
+            }
+            // Pop VFP double-precision registers saved by FSTMFDX
+            0xb3 => {
+                let count = (opcodes.next()? as u32 & 0xf) + 1;
+                vsp = vsp.checked_add(count * 8 + 4)?;
+            }
+            0xb8..=0xbf => vsp = vsp.checked_add((op as u32 & 0x7) * 8 + 12)?,
+            // Pop Intel Wireless MMX registers
+            0xc0..=0xc5 => vsp = vsp.checked_add((op as u32 & 0x7) * 8 + 8)?,
+            0xc6 => {
+                let count = (opcodes.next()? as u32 & 0xf) + 1;
+                vsp = vsp.checked_add(count * 8)?;
+            }
+            0xc7 => {
+                let mask = opcodes.next()?;
+                if mask == 0 || mask & 0xf0 != 0 {
+                    // Spare
+                    return None;
+                }
+                vsp = vsp.checked_add(mask.count_ones() * 4)?;
+            }
+            // Pop VFP double-precision registers saved by VPUSH
+            0xc8 | 0xc9 => {
+                let count = (opcodes.next()? as u32 & 0xf) + 1;
+                vsp = vsp.checked_add(count * 8)?;
+            }
+            0xd0..=0xd7 => vsp = vsp.checked_add((op as u32 & 0x7) * 8 + 8)?,
+            // Spare
+            _ => return None,
+        }
+    }
+
+    regs.regs[SP] = Some(vsp);
+    // If the pc wasn't restored, the return address is in the link register.
+    if regs.popped & (1 << PC) == 0 {
+        regs.regs[PC] = regs.regs[LR];
+    }
+    regs.regs[PC]?;
+    Some(())
+}
@@ -3,7 +3,7 @@
 
 use crate::process_state::*;
 use crate::stackwalker::walk_stack;
-use crate::{string_symbol_supplier, Symbolizer};
+use crate::{string_symbol_supplier, ProcessorOptions, Symbolizer};
 use minidump::format::CONTEXT_ARM;
 use minidump::*;
 use std::collections::HashMap;
@@ -13,6 +13,8 @@ struct TestFixture {
     pub raw: CONTEXT_ARM,
     pub modules: MinidumpModuleList,
     pub symbols: HashMap<String, String>,
+    /// Extra memory included in the minidump, as (base address, contents).
+    pub memory: Vec<(u64, Vec<u8>)>,
 }
 
 impl TestFixture {
@@ -26,6 +28,7 @@ impl TestFixture {
                 MinidumpModule::new(0x50000000, 0x10000, "module2"),
             ]),
             symbols: HashMap::new(),
+            memory: vec![],
         }
     }
 
@@ -43,12 +46,26 @@ impl TestFixture {
             size,
             bytes: &stack,
         };
+        let memory_list = UnifiedMemoryList::Memory(MinidumpMemoryList::from_regions(
+            self.memory
+                .iter()
+                .map(|(base, bytes)| MinidumpMemory {
+                    desc: Default::default(),
+                    base_address: *base,
+                    size: bytes.len() as u64,
+                    bytes,
+                })
+                .collect(),
+        ));
         let symbolizer = Symbolizer::new(string_symbol_supplier(self.symbols.clone()));
         walk_stack(
             &Some(&context),
             Some(&stack_memory),
+            &memory_list,
+            &UnifiedMemoryInfoList::default(),
             &self.modules,
             &symbolizer,
+            &ProcessorOptions::default(),
         )
         .await
     }
@@ -56,6 +73,10 @@ impl TestFixture {
     pub fn add_symbols(&mut self, name: String, symbols: String) {
         self.symbols.insert(name, symbols);
     }
+
+    pub fn add_memory(&mut self, base: u64, section: Section) {
+        self.memory.push((base, section.get_contents().unwrap()));
+    }
 }
 
 #[tokio::test]
@@ -231,6 +252,67 @@ async fn test_scan_first_frame() {
     }
 }
 
+#[tokio::test]
+async fn test_scan_thumb() {
+    // Return addresses into Thumb code have their lowest bit set, which
+    // should be stripped for symbolication but remembered on the frame.
+    let mut f = TestFixture::new();
+    let mut stack = Section::new();
+    stack.start().set_const(0x80000000);
+
+    let return_address = 0x50000201u32;
+    let frame1_sp = Label::new();
+
+    stack = stack
+        // frame 0
+        .append_repeated(0, 16) // space
+        .D32(return_address) // actual return address
+        // frame 1
+        .mark(&frame1_sp)
+        .append_repeated(0, 32); // end of stack
+
+    f.raw.set_register("pc", 0x40005510);
+    f.raw
+        .set_register("sp", stack.start().value().unwrap() as u32);
+    f.raw.cpsr = 1 << 5;
+    f.add_symbols(
+        String::from("module2"),
+        String::from("FUNC 100 400 10 thumb_function\n"),
+    );
+
+    let s = f.walk_stack(stack).await;
+    assert_eq!(s.frames.len(), 2);
+
+    {
+        // Frame 0
+        let frame = &s.frames[0];
+        assert_eq!(frame.trust, FrameTrust::Context);
+        assert_eq!(frame.instruction, 0x40005510);
+        assert!(frame.is_thumb);
+    }
+
+    {
+        // Frame 1
+        let frame = &s.frames[1];
+        let valid = &frame.context.valid;
+        assert_eq!(frame.trust, FrameTrust::Scan);
+        assert_eq!(frame.instruction, 0x500001fe);
+        assert!(frame.is_thumb);
+        assert_eq!(frame.function_name.as_deref(), Some("thumb_function"));
+        assert_eq!(frame.function_base, Some(0x50000100));
+
+        if let MinidumpRawContext::Arm(ctx) = &frame.context.raw {
+            assert_eq!(ctx.get_register("pc", valid).unwrap(), return_address);
+            assert_eq!(
+                ctx.get_register("sp", valid).unwrap(),
+                frame1_sp.value().unwrap() as u32
+            );
+        } else {
+            unreachable!();
+        }
+    }
+}
+
 #[tokio::test]
 async fn test_frame_pointer() {
     // Frame-pointer-based unwinding
@@ -709,6 +791,65 @@ async fn test_cfi_at_4006() {
     check_cfi(f, stack, expected, expected_valid).await;
 }
 
+#[tokio::test]
+async fn test_cfi_register_aliases() {
+    // Symbol files don't agree on what to call the registers, so make sure
+    // all of the names work and end up as the same register.
+    let mut f = TestFixture::new();
+    let symbols = [
+        "FUNC 4000 1000 10 enchiridion\n",
+        "STACK CFI INIT 4000 100 .cfa: r13 12 + .ra: .cfa 4 - ^",
+        " fp: .cfa 8 - ^ ip: .cfa 12 - ^ r14: lr\n",
+        "FUNC 5000 1000 10 epictetus\n",
+        "STACK CFI INIT 5000 1000 .cfa: 0 .ra: 0\n",
+    ];
+    f.add_symbols(String::from("module1"), symbols.concat());
+
+    let mut stack = Section::new();
+    stack.start().set_const(0x80000000);
+    let frame1_sp = Label::new();
+    stack = stack
+        .D32(0x12121212) // saved ip
+        .D32(0x8112e110) // saved fp
+        .D32(0x40005510) // return address
+        .mark(&frame1_sp)
+        .append_repeated(0, 120);
+
+    f.raw.set_register("pc", 0x40004000);
+    f.raw
+        .set_register("sp", stack.start().value().unwrap() as u32);
+    f.raw.set_register("lr", 0x40005555);
+
+    let s = f.walk_stack(stack).await;
+    assert_eq!(s.frames.len(), 2);
+
+    let frame = &s.frames[1];
+    assert_eq!(frame.trust, FrameTrust::CallFrameInfo);
+    let valid = &frame.context.valid;
+    if let MinidumpContextValidity::Some(ref which) = valid {
+        let mut which = which.iter().copied().collect::<Vec<_>>();
+        which.sort_unstable();
+        assert_eq!(
+            which,
+            ["lr", "pc", "r10", "r11", "r12", "r4", "r5", "r6", "r7", "r8", "r9", "sp"]
+        );
+    } else {
+        unreachable!();
+    }
+    if let MinidumpRawContext::Arm(ctx) = &frame.context.raw {
+        assert_eq!(ctx.get_register("pc", valid).unwrap(), 0x40005510);
+        assert_eq!(
+            ctx.get_register("r13", valid).unwrap(),
+            frame1_sp.value().unwrap() as u32
+        );
+        assert_eq!(ctx.get_register("fp", valid).unwrap(), 0x8112e110);
+        assert_eq!(ctx.get_register("r12", valid).unwrap(), 0x12121212);
+        assert_eq!(ctx.get_register("r14", valid).unwrap(), 0x40005555);
+    } else {
+        unreachable!();
+    }
+}
+
 #[tokio::test]
 async fn test_cfi_reject_backwards() {
     // Check that we reject rules that would cause the stack pointer to
@@ -895,3 +1036,168 @@ async fn test_frame_pointer_barely_no_overflow() {
         }
     }
 }
+
+/// Build the start of an ELF module with an exidx table at offset 0x100
+/// describing three functions:
+///
+/// * 0x1000: `push {r4, r11, lr}; sub sp, sp, #8`, with inline opcodes
+/// * 0x2000: a function that can't be unwound
+/// * 0x3000: `push {r4, lr}; sub sp, sp, #16`, with opcodes in the extab
+fn exidx_module() -> Section {
+    Section::new()
+        // Elf32_Ehdr
+        .D32(0x464c457f) // magic
+        .D8(1) // ELFCLASS32
+        .D8(1) // ELFDATA2LSB
+        .append_repeated(0, 10)
+        .D16(3) // e_type
+        .D16(40) // e_machine
+        .D32(1) // e_version
+        .D32(0) // e_entry
+        .D32(0x34) // e_phoff
+        .D32(0) // e_shoff
+        .D32(0) // e_flags
+        .D16(0x34) // e_ehsize
+        .D16(32) // e_phentsize
+        .D16(2) // e_phnum
+        .D16(0) // e_shentsize
+        .D16(0) // e_shnum
+        .D16(0) // e_shstrndx
+        // PT_LOAD
+        .D32(1)
+        .D32(0)
+        .D32(0) // p_vaddr
+        .D32(0)
+        .D32(0x10000)
+        .D32(0x10000) // p_memsz
+        .D32(5)
+        .D32(0x1000)
+        // PT_ARM_EXIDX
+        .D32(0x70000001)
+        .D32(0x100)
+        .D32(0x100) // p_vaddr
+        .D32(0x100)
+        .D32(0x18)
+        .D32(0x18) // p_memsz
+        .D32(4)
+        .D32(4)
+        .append_repeated(0, 0x100 - 0x74)
+        // .ARM.exidx
+        .D32(0x1000 - 0x100) // prel31 to the function
+        .D32(0x80018481) // vsp += 8; pop {r4, r11, r14}
+        .D32(0x2000 - 0x108)
+        .D32(1) // EXIDX_CANTUNWIND
+        .D32(0x3000 - 0x110)
+        .D32(0x200 - 0x114) // prel31 to the extab
+        .append_repeated(0, 0x200 - 0x118)
+        // .ARM.extab
+        .D32(0x810103a8) // personality 1, 1 extra word; vsp += 16; pop {r4, r14}
+        .D32(0xb0b0b0b0) // finish
+}
+
+#[tokio::test]
+async fn test_exidx() {
+    let mut f = TestFixture::new();
+    let mut stack = Section::new();
+    stack.start().set_const(0x80000000);
+
+    let return_address1 = 0x40003020u32;
+    let return_address2 = 0x50000100u32;
+    let frame1_sp = Label::new();
+    let frame2_sp = Label::new();
+
+    stack = stack
+        // frame 0
+        .append_repeated(0, 8) // locals
+        .D32(0xbeef0004) // saved r4
+        .D32(0xbeef000b) // saved r11
+        .D32(return_address1) // saved lr
+        // frame 1
+        .mark(&frame1_sp)
+        .append_repeated(0, 16) // locals
+        .D32(0xcafe0004) // saved r4
+        .D32(return_address2) // saved lr
+        // frame 2
+        .mark(&frame2_sp)
+        .append_repeated(0, 16); // end of stack
+
+    f.raw.set_register("pc", 0x40001010);
+    f.raw.set_register("r4", 0x44444444);
+    f.raw.set_register("r5", 0x55555555);
+    f.raw
+        .set_register("sp", stack.start().value().unwrap() as u32);
+    f.add_memory(0x40000000, exidx_module());
+
+    let s = f.walk_stack(stack).await;
+    assert_eq!(s.frames.len(), 3);
+
+    {
+        // Frame 1
+        let frame = &s.frames[1];
+        let valid = &frame.context.valid;
+        assert_eq!(frame.trust, FrameTrust::CallFrameInfo);
+        assert_eq!(frame.instruction, return_address1 as u64 - 2);
+        if let MinidumpContextValidity::Some(ref which) = valid {
+            // The callee-saved registers, plus the restored r14.
+            assert_eq!(which.len(), 11);
+        } else {
+            unreachable!();
+        }
+
+        if let MinidumpRawContext::Arm(ctx) = &frame.context.raw {
+            assert_eq!(ctx.get_register("pc", valid).unwrap(), return_address1);
+            assert_eq!(
+                ctx.get_register("sp", valid).unwrap(),
+                frame1_sp.value().unwrap() as u32
+            );
+            assert_eq!(ctx.get_register("r4", valid).unwrap(), 0xbeef0004);
+            assert_eq!(ctx.get_register("r5", valid).unwrap(), 0x55555555);
+            assert_eq!(ctx.get_register("fp", valid).unwrap(), 0xbeef000b);
+            assert_eq!(ctx.get_register("lr", valid).unwrap(), return_address1);
+        } else {
+            unreachable!();
+        }
+    }
+
+    {
+        // Frame 2
+        let frame = &s.frames[2];
+        let valid = &frame.context.valid;
+        assert_eq!(frame.trust, FrameTrust::CallFrameInfo);
+        if let MinidumpRawContext::Arm(ctx) = &frame.context.raw {
+            assert_eq!(ctx.get_register("pc", valid).unwrap(), return_address2);
+            assert_eq!(
+                ctx.get_register("sp", valid).unwrap(),
+                frame2_sp.value().unwrap() as u32
+            );
+            assert_eq!(ctx.get_register("r4", valid).unwrap(), 0xcafe0004);
+            assert_eq!(ctx.get_register("fp", valid).unwrap(), 0xbeef000b);
+        } else {
+            unreachable!();
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_exidx_cantunwind() {
+    // A function marked as impossible to unwind should fall back to scanning.
+    let mut f = TestFixture::new();
+    let mut stack = Section::new();
+    stack.start().set_const(0x80000000);
+
+    let return_address = 0x50000100u32;
+
+    stack = stack
+        .append_repeated(0, 16) // space
+        .D32(return_address)
+        .append_repeated(0, 16); // end of stack
+
+    f.raw.set_register("pc", 0x40002010);
+    f.raw
+        .set_register("sp", stack.start().value().unwrap() as u32);
+    f.add_memory(0x40000000, exidx_module());
+
+    let s = f.walk_stack(stack).await;
+    assert_eq!(s.frames.len(), 2);
+    assert_eq!(s.frames[1].trust, FrameTrust::Scan);
+}
@@ -0,0 +1,148 @@
+// Copyright 2015 Ted Mielczarek. See the COPYRIGHT
+// file at the top-level directory of this distribution.
+
+//! A cache in front of the [`SymbolProvider`]'s CFI lookups.
+//!
+//! Every frame of every thread asks the symbol provider to walk it, and dumps
+//! with lots of threads tend to have lots of frames in the same few hot
+//! modules. Whether the provider can find CFI for a given instruction doesn't
+//! depend on the registers of the frame being walked, so once the provider has
+//! come back empty-handed for an instruction without even looking at the
+//! frame, we can remember that and skip asking again for the rest of the run.
+
+use std::cell::Cell;
+use std::collections::{HashMap, HashSet};
+use std::sync::Mutex;
+
+use minidump::Module;
+
+use crate::{FillSymbolError, FrameSymbolizer, FrameWalker, SymbolProvider, SymbolStats};
+
+/// Wraps a [`SymbolProvider`], remembering which instructions it has no CFI for.
+///
+/// This is meant to live for a single processing run and be shared by all of
+/// its threads.
+pub(crate) struct CfiCache<'a, P> {
+    provider: &'a P,
+    /// Instructions the provider has no CFI for, keyed by the address range of
+    /// the module they're in.
+    missing: Mutex<HashMap<(u64, u64), HashSet<u64>>>,
+}
+
+impl<'a, P> CfiCache<'a, P> {
+    pub(crate) fn new(provider: &'a P) -> Self {
+        Self {
+            provider,
+            missing: Mutex::default(),
+        }
+    }
+
+    fn is_missing(&self, key: (u64, u64), instruction: u64) -> bool {
+        self.missing
+            .lock()
+            .unwrap()
+            .get(&key)
+            .map(|missing| missing.contains(&instruction))
+            .unwrap_or(false)
+    }
+
+    fn set_missing(&self, key: (u64, u64), instruction: u64) {
+        self.missing
+            .lock()
+            .unwrap()
+            .entry(key)
+            .or_default()
+            .insert(instruction);
+    }
+}
+
+#[async_trait::async_trait]
+impl<'a, P> SymbolProvider for CfiCache<'a, P>
+where
+    P: SymbolProvider + Sync,
+{
+    async fn fill_symbol(
+        &self,
+        module: &(dyn Module + Sync),
+        frame: &mut (dyn FrameSymbolizer + Send),
+    ) -> Result<(), FillSymbolError> {
+        self.provider.fill_symbol(module, frame).await
+    }
+
+    async fn walk_frame(
+        &self,
+        module: &(dyn Module + Sync),
+        walker: &mut (dyn FrameWalker + Send),
+    ) -> Option<()> {
+        let key = (module.base_address(), module.size());
+        let instruction = walker.get_instruction();
+        if self.is_missing(key, instruction) {
+            return None;
+        }
+
+        let mut walker = ProbeWalker {
+            inner: walker,
+            touched: Cell::new(false),
+        };
+        let result = self.provider.walk_frame(module, &mut walker).await;
+        // If the provider never looked past the instruction, it didn't find
+        // anything to evaluate, and it won't for any other frame there either.
+        if result.is_none() && !walker.touched.get() {
+            self.set_missing(key, instruction);
+        }
+        result
+    }
+
+    fn stats(&self) -> HashMap<String, SymbolStats> {
+        self.provider.stats()
+    }
+
+    async fn prefetch(&self, modules: &[&(dyn Module + Sync)], concurrency: usize) {
+        self.provider.prefetch(modules, concurrency).await
+    }
+}
+
+/// A [`FrameWalker`] that notes whether anything other than the instruction
+/// was asked for.
+struct ProbeWalker<'w> {
+    inner: &'w mut (dyn FrameWalker + Send),
+    touched: Cell<bool>,
+}
+
+impl<'w> FrameWalker for ProbeWalker<'w> {
+    fn get_instruction(&self) -> u64 {
+        self.inner.get_instruction()
+    }
+    fn get_grand_callee_parameter_size(&self) -> u32 {
+        self.touched.set(true);
+        self.inner.get_grand_callee_parameter_size()
+    }
+    fn get_register_at_address(&self, address: u64) -> Option<u64> {
+        self.touched.set(true);
+        self.inner.get_register_at_address(address)
+    }
+    fn get_callee_register(&self, name: &str) -> Option<u64> {
+        self.touched.set(true);
+        self.inner.get_callee_register(name)
+    }
+    fn set_caller_register(&mut self, name: &str, val: u64) -> Option<()> {
+        self.touched.set(true);
+        self.inner.set_caller_register(name, val)
+    }
+    fn clear_caller_register(&mut self, name: &str) {
+        self.touched.set(true);
+        self.inner.clear_caller_register(name)
+    }
+    fn set_cfa(&mut self, val: u64) -> Option<()> {
+        self.touched.set(true);
+        self.inner.set_cfa(val)
+    }
+    fn set_ra(&mut self, val: u64) -> Option<()> {
+        self.touched.set(true);
+        self.inner.set_ra(val)
+    }
+    fn instruction_seems_valid(&self, address: u64) -> bool {
+        self.touched.set(true);
+        self.inner.instruction_seems_valid(address)
+    }
+}
@@ -0,0 +1,97 @@
+// Copyright 2015 Ted Mielczarek. See the COPYRIGHT
+// file at the top-level directory of this distribution.
+
+//! Unwinders supplied by whoever is embedding the processor.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+
+use minidump::system_info::Cpu;
+use minidump::{MinidumpMemory, MinidumpModuleList, MinidumpRawContext, UnifiedMemoryList};
+
+use crate::process_state::StackFrame;
+use crate::SymbolProvider;
+
+/// A way of finding a frame's caller on a particular architecture.
+///
+/// Register one in [`ProcessorOptions::unwinders`][crate::ProcessorOptions::unwinders]
+/// to handle frames the built-in unwinders don't understand, such as the
+/// frames of a coroutine runtime that switches stacks behind their back.
+#[async_trait::async_trait]
+pub trait ArchUnwinder: Send + Sync {
+    /// Get the caller of `callee`.
+    ///
+    /// Returning `None` hands the frame over to the built-in unwinder, unless
+    /// [`ArchUnwinder::replaces_builtin`] says otherwise. A returned frame is
+    /// used as-is, so its `instruction` should already point into the call
+    /// instruction, and its `trust` should say how it was found
+    /// ([`FrameTrust::PreWalked`][crate::FrameTrust::PreWalked] if nothing
+    /// else fits).
+    async fn get_caller_frame(
+        &self,
+        callee: &StackFrame,
+        grand_callee: Option<&StackFrame>,
+        stack_memory: Option<&MinidumpMemory<'_>>,
+        memory_list: &UnifiedMemoryList<'_>,
+        modules: &MinidumpModuleList,
+        symbol_provider: &(dyn SymbolProvider + Sync),
+    ) -> Option<StackFrame>;
+
+    /// Whether this unwinder is the only one to use for its architecture.
+    ///
+    /// By default the built-in unwinder gets to try any frame this one
+    /// couldn't find the caller of.
+    fn replaces_builtin(&self) -> bool {
+        false
+    }
+}
+
+/// The [`ArchUnwinder`]s to use for each architecture.
+#[derive(Clone, Default)]
+pub struct UnwinderRegistry {
+    unwinders: HashMap<Cpu, Arc<dyn ArchUnwinder>>,
+}
+
+impl UnwinderRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Use `unwinder` for frames running on `cpu`, in place of any unwinder
+    /// registered for it before.
+    pub fn register(&mut self, cpu: Cpu, unwinder: impl ArchUnwinder + 'static) {
+        self.unwinders.insert(cpu, Arc::new(unwinder));
+    }
+
+    /// Get the unwinder registered for `cpu`.
+    pub fn get(&self, cpu: Cpu) -> Option<&dyn ArchUnwinder> {
+        self.unwinders.get(&cpu).map(|unwinder| &**unwinder)
+    }
+
+    /// Get the unwinder registered for the cpu `context` is from.
+    pub(crate) fn for_context(&self, context: &MinidumpRawContext) -> Option<&dyn ArchUnwinder> {
+        if self.unwinders.is_empty() {
+            return None;
+        }
+        let cpu = match context {
+            MinidumpRawContext::X86(_) => Cpu::X86,
+            MinidumpRawContext::Amd64(_) => Cpu::X86_64,
+            MinidumpRawContext::Ppc(_) => Cpu::Ppc,
+            MinidumpRawContext::Ppc64(_) => Cpu::Ppc64,
+            MinidumpRawContext::Sparc(_) => Cpu::Sparc,
+            MinidumpRawContext::Arm(_) => Cpu::Arm,
+            MinidumpRawContext::Arm64(_) | MinidumpRawContext::OldArm64(_) => Cpu::Arm64,
+            MinidumpRawContext::Mips(_) => Cpu::Mips,
+            MinidumpRawContext::Mips64(_) => Cpu::Mips64,
+            MinidumpRawContext::Riscv64(_) => Cpu::Riscv64,
+        };
+        self.get(cpu)
+    }
+}
+
+impl fmt::Debug for UnwinderRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.unwinders.keys()).finish()
+    }
+}
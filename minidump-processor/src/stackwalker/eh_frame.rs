@@ -0,0 +1,642 @@
+// Copyright 2015 Ted Mielczarek. See the COPYRIGHT
+// file at the top-level directory of this distribution.
+
+//! Unwinding with the DWARF CFI in a module's `.eh_frame` section.
+//!
+//! ELF binaries usually carry `.eh_frame` (and its sorted index,
+//! `.eh_frame_hdr`) for C++ exceptions, and both are loaded into memory along
+//! with the rest of the module. When a full-memory dump includes them, we can
+//! find them through the `PT_GNU_EH_FRAME` program header and evaluate the
+//! CFI for a frame without any symbol files.
+//!
+//! Only the subset of DWARF that compilers emit in practice is supported:
+//! CFA rules and register rules that are expressions make the frame fail to
+//! unwind so we can fall back to other heuristics.
+//!
+//! See the "Linux Standard Base Core Specification" (sections 10.5 and 10.6)
+//! and the DWARF 4 specification (section 6.4) for the details of the format.
+
+use std::collections::BTreeMap;
+use std::convert::{TryFrom, TryInto};
+
+use minidump::UnifiedMemoryList;
+use tracing::trace;
+
+use super::elf::{self, PT_GNU_EH_FRAME};
+use crate::FrameWalker;
+
+const DW_EH_PE_OMIT: u8 = 0xff;
+const DW_EH_PE_INDIRECT: u8 = 0x80;
+/// The encoding of the binary search table that every linker emits.
+const DW_EH_PE_DATAREL_SDATA4: u8 = 0x3b;
+
+/// A little-endian reader that keeps track of the address it's reading from.
+#[derive(Clone)]
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+    address: u64,
+}
+
+impl<'a> Reader<'a> {
+    fn new(data: &'a [u8], address: u64) -> Self {
+        Self {
+            data,
+            pos: 0,
+            address,
+        }
+    }
+
+    /// The address of the next byte to be read.
+    fn address(&self) -> u64 {
+        self.address.wrapping_add(self.pos as u64)
+    }
+
+    fn is_empty(&self) -> bool {
+        self.pos >= self.data.len()
+    }
+
+    fn bytes(&mut self, len: usize) -> Option<&'a [u8]> {
+        let end = self.pos.checked_add(len)?;
+        let bytes = self.data.get(self.pos..end)?;
+        self.pos = end;
+        Some(bytes)
+    }
+
+    /// Split off the next `len` bytes into their own reader.
+    fn sub_reader(&mut self, len: usize) -> Option<Reader<'a>> {
+        let address = self.address();
+        Some(Reader::new(self.bytes(len)?, address))
+    }
+
+    fn u8(&mut self) -> Option<u8> {
+        Some(self.bytes(1)?[0])
+    }
+
+    fn u16(&mut self) -> Option<u16> {
+        Some(u16::from_le_bytes(self.bytes(2)?.try_into().ok()?))
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        Some(u32::from_le_bytes(self.bytes(4)?.try_into().ok()?))
+    }
+
+    fn u64(&mut self) -> Option<u64> {
+        Some(u64::from_le_bytes(self.bytes(8)?.try_into().ok()?))
+    }
+
+    fn uleb128(&mut self) -> Option<u64> {
+        let mut result = 0u64;
+        let mut shift = 0;
+        loop {
+            let byte = self.u8()?;
+            if shift < 64 {
+                result |= ((byte & 0x7f) as u64) << shift;
+            }
+            shift += 7;
+            if byte & 0x80 == 0 {
+                return Some(result);
+            }
+        }
+    }
+
+    fn sleb128(&mut self) -> Option<i64> {
+        let mut result = 0i64;
+        let mut shift = 0;
+        loop {
+            let byte = self.u8()?;
+            if shift < 64 {
+                result |= ((byte & 0x7f) as i64) << shift;
+            }
+            shift += 7;
+            if byte & 0x80 == 0 {
+                if shift < 64 && byte & 0x40 != 0 {
+                    result |= -1 << shift;
+                }
+                return Some(result);
+            }
+        }
+    }
+
+    fn cstr(&mut self) -> Option<&'a [u8]> {
+        let len = self.data.get(self.pos..)?.iter().position(|&b| b == 0)?;
+        let s = self.bytes(len)?;
+        self.pos += 1;
+        Some(s)
+    }
+
+    /// Read a pointer in one of the `DW_EH_PE_*` encodings.
+    ///
+    /// Returns `Some(None)` for `DW_EH_PE_omit`. Indirect pointers are not
+    /// followed; they're only used for things we don't need, like the
+    /// personality routine.
+    fn encoded(&mut self, encoding: u8, bases: &Bases) -> Option<Option<u64>> {
+        if encoding == DW_EH_PE_OMIT {
+            return Some(None);
+        }
+        let field = self.address();
+        let value = match encoding & 0x0f {
+            0x00 if bases.is_64 => self.u64()?,
+            0x00 => self.u32()? as u64,
+            0x01 => self.uleb128()?,
+            0x02 => self.u16()? as u64,
+            0x03 => self.u32()? as u64,
+            0x04 => self.u64()?,
+            0x09 => self.sleb128()? as u64,
+            0x0a => self.u16()? as i16 as u64,
+            0x0b => self.u32()? as i32 as u64,
+            0x0c => self.u64()?,
+            _ => return None,
+        };
+        let base = match encoding & 0x70 {
+            0x00 => 0,
+            0x10 => field,
+            0x30 => bases.data?,
+            _ => return None,
+        };
+        Some(Some(base.wrapping_add(value)))
+    }
+}
+
+/// The addresses that pointers can be relative to.
+struct Bases {
+    /// Whether absolute pointers are 64-bit.
+    is_64: bool,
+    /// The start of `.eh_frame_hdr`, for `DW_EH_PE_datarel`.
+    data: Option<u64>,
+}
+
+/// How to compute the CFA.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CfaRule {
+    RegisterOffset(u16, i64),
+    Expression,
+}
+
+/// How to recover one of the caller's registers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RegisterRule {
+    Undefined,
+    SameValue,
+    /// Saved at CFA + offset.
+    Offset(i64),
+    /// The value is CFA + offset.
+    ValOffset(i64),
+    /// Saved in another register.
+    Register(u16),
+    /// An expression, which we can't evaluate.
+    Unsupported,
+}
+
+#[derive(Debug, Clone)]
+struct Row {
+    cfa: CfaRule,
+    registers: BTreeMap<u16, RegisterRule>,
+}
+
+/// A Common Information Entry, which holds the parts shared between FDEs.
+struct Cie<'a> {
+    code_alignment: u64,
+    data_alignment: i64,
+    return_address_register: u16,
+    fde_encoding: u8,
+    has_augmentation_data: bool,
+    instructions: Reader<'a>,
+}
+
+/// Read the length of a CIE or FDE, and return a reader for its contents.
+fn read_entry<'a>(reader: &mut Reader<'a>) -> Option<Reader<'a>> {
+    let length = match reader.u32()? {
+        0xffff_ffff => reader.u64()?,
+        0 => return None,
+        length => length as u64,
+    };
+    reader.sub_reader(usize::try_from(length).ok()?)
+}
+
+fn parse_cie<'a>(
+    memory_list: &UnifiedMemoryList<'a>,
+    address: u64,
+    bases: &Bases,
+) -> Option<Cie<'a>> {
+    let mut reader = Reader::new(elf::memory_from(memory_list, address)?, address);
+    let mut reader = read_entry(&mut reader)?;
+    if reader.u32()? != 0 {
+        return None;
+    }
+    let version = reader.u8()?;
+    if !matches!(version, 1 | 3 | 4) {
+        return None;
+    }
+    let augmentation = reader.cstr()?;
+    if version == 4 {
+        // address_size and segment_selector_size
+        reader.bytes(2)?;
+    }
+    let code_alignment = reader.uleb128()?;
+    let data_alignment = reader.sleb128()?;
+    let return_address_register = if version == 1 {
+        reader.u8()? as u16
+    } else {
+        u16::try_from(reader.uleb128()?).ok()?
+    };
+
+    let mut fde_encoding = 0;
+    let has_augmentation_data = augmentation.first() == Some(&b'z');
+    if has_augmentation_data {
+        let len = reader.uleb128()?;
+        let mut data = reader.sub_reader(usize::try_from(len).ok()?)?;
+        for &c in &augmentation[1..] {
+            match c {
+                b'L' => {
+                    data.u8()?;
+                }
+                b'P' => {
+                    let encoding = data.u8()?;
+                    data.encoded(encoding & !DW_EH_PE_INDIRECT, bases)?;
+                }
+                b'R' => fde_encoding = data.u8()?,
+                b'S' => {}
+                // Anything we don't understand is safe to skip, because we
+                // already know where the augmentation data ends.
+                _ => break,
+            }
+        }
+    } else if !augmentation.is_empty() {
+        return None;
+    }
+    if fde_encoding & DW_EH_PE_INDIRECT != 0 {
+        return None;
+    }
+
+    Some(Cie {
+        code_alignment,
+        data_alignment,
+        return_address_register,
+        fde_encoding,
+        has_augmentation_data,
+        instructions: reader,
+    })
+}
+
+/// Binary search `.eh_frame_hdr`'s table for the FDE that might cover `address`.
+fn find_fde_address(
+    memory_list: &UnifiedMemoryList<'_>,
+    hdr: u64,
+    bases: &Bases,
+    address: u64,
+) -> Option<u64> {
+    let mut reader = Reader::new(elf::memory_from(memory_list, hdr)?, hdr);
+    if reader.u8()? != 1 {
+        return None;
+    }
+    let eh_frame_ptr_encoding = reader.u8()?;
+    let fde_count_encoding = reader.u8()?;
+    let table_encoding = reader.u8()?;
+    reader.encoded(eh_frame_ptr_encoding, bases)?;
+    let fde_count = reader.encoded(fde_count_encoding, bases)??;
+    if table_encoding != DW_EH_PE_DATAREL_SDATA4 {
+        trace!(
+            "unwind: unsupported eh_frame_hdr table encoding {:#x}",
+            table_encoding
+        );
+        return None;
+    }
+
+    let table = reader.bytes(usize::try_from(fde_count.checked_mul(8)?).ok()?)?;
+    let entry = |i: usize| {
+        let field = |offset| i32::from_le_bytes(table[i * 8 + offset..][..4].try_into().unwrap());
+        (
+            hdr.wrapping_add(field(0) as i64 as u64),
+            hdr.wrapping_add(field(4) as i64 as u64),
+        )
+    };
+
+    // The table is sorted by initial location, so we want the last entry
+    // that starts at or before our address.
+    let mut low = 0;
+    let mut high = fde_count as usize;
+    while low < high {
+        let mid = low + (high - low) / 2;
+        if entry(mid).0 <= address {
+            low = mid + 1;
+        } else {
+            high = mid;
+        }
+    }
+    let (_, fde) = entry(low.checked_sub(1)?);
+    Some(fde)
+}
+
+/// Evaluate the CFI for `address` and return the row of the table for it.
+fn find_row(
+    memory_list: &UnifiedMemoryList<'_>,
+    module_base: u64,
+    address: u64,
+) -> Option<(Row, u16)> {
+    let hdr = elf::find_segment(memory_list, module_base, PT_GNU_EH_FRAME)?;
+    let bases = Bases {
+        is_64: hdr.is_64,
+        data: Some(hdr.address),
+    };
+    let fde_address = find_fde_address(memory_list, hdr.address, &bases, address)?;
+
+    let mut reader = Reader::new(elf::memory_from(memory_list, fde_address)?, fde_address);
+    let mut fde = read_entry(&mut reader)?;
+    let cie_pointer_field = fde.address();
+    let cie_pointer = fde.u32()?;
+    if cie_pointer == 0 {
+        // That's a CIE, not an FDE.
+        return None;
+    }
+    let cie_address = cie_pointer_field.checked_sub(cie_pointer as u64)?;
+    let cie = parse_cie(memory_list, cie_address, &bases)?;
+
+    let start = fde.encoded(cie.fde_encoding, &bases)??;
+    let range = fde.encoded(cie.fde_encoding & 0x0f, &bases)??;
+    if address < start || address - start >= range {
+        trace!("unwind: no eh_frame FDE covers {:#x}", address);
+        return None;
+    }
+    if cie.has_augmentation_data {
+        let len = fde.uleb128()?;
+        fde.bytes(usize::try_from(len).ok()?)?;
+    }
+
+    let mut evaluator = Evaluator {
+        cie: &cie,
+        bases: &bases,
+        target: address,
+        location: start,
+        row: Row {
+            cfa: CfaRule::Expression,
+            registers: BTreeMap::new(),
+        },
+        initial: BTreeMap::new(),
+        stack: Vec::new(),
+    };
+    let mut initial_instructions = cie.instructions.clone();
+    evaluator.execute(&mut initial_instructions)?;
+    evaluator.initial = evaluator.row.registers.clone();
+    evaluator.execute(&mut fde)?;
+    Some((evaluator.row, cie.return_address_register))
+}
+
+/// Executes CFA instructions until the row for the target address is built.
+struct Evaluator<'c, 'a> {
+    cie: &'c Cie<'a>,
+    bases: &'c Bases,
+    target: u64,
+    location: u64,
+    row: Row,
+    /// The register rules after the CIE's initial instructions.
+    initial: BTreeMap<u16, RegisterRule>,
+    /// Rows saved by DW_CFA_remember_state.
+    stack: Vec<Row>,
+}
+
+impl Evaluator<'_, '_> {
+    /// Move the location forward; returns false if we've gone past the target.
+    fn advance(&mut self, delta: u64) -> bool {
+        self.location = self
+            .location
+            .wrapping_add(delta.wrapping_mul(self.cie.code_alignment));
+        self.location <= self.target
+    }
+
+    fn set(&mut self, register: u64, rule: RegisterRule) -> Option<()> {
+        self.row
+            .registers
+            .insert(u16::try_from(register).ok()?, rule);
+        Some(())
+    }
+
+    fn restore(&mut self, register: u64) -> Option<()> {
+        let register = u16::try_from(register).ok()?;
+        match self.initial.get(&register) {
+            Some(&rule) => self.row.registers.insert(register, rule),
+            None => self.row.registers.remove(&register),
+        };
+        Some(())
+    }
+
+    fn factored(&self, offset: i64) -> i64 {
+        offset.wrapping_mul(self.cie.data_alignment)
+    }
+
+    fn execute(&mut self, reader: &mut Reader<'_>) -> Option<()> {
+        while !reader.is_empty() {
+            let op = reader.u8()?;
+            match (op >> 6, op & 0x3f) {
+                // DW_CFA_advance_loc
+                (1, delta) => {
+                    if !self.advance(delta as u64) {
+                        return Some(());
+                    }
+                }
+                // DW_CFA_offset
+                (2, register) => {
+                    let offset = self.factored(reader.uleb128()? as i64);
+                    self.set(register as u64, RegisterRule::Offset(offset))?;
+                }
+                // DW_CFA_restore
+                (3, register) => self.restore(register as u64)?,
+                (_, _) => match op {
+                    // DW_CFA_nop
+                    0x00 => {}
+                    // DW_CFA_set_loc
+                    0x01 => {
+                        self.location = reader.encoded(self.cie.fde_encoding, self.bases)??;
+                        if self.location > self.target {
+                            return Some(());
+                        }
+                    }
+                    // DW_CFA_advance_loc1, 2, 4
+                    0x02..=0x04 => {
+                        let delta = match op {
+                            0x02 => reader.u8()? as u64,
+                            0x03 => reader.u16()? as u64,
+                            _ => reader.u32()? as u64,
+                        };
+                        if !self.advance(delta) {
+                            return Some(());
+                        }
+                    }
+                    // DW_CFA_offset_extended
+                    0x05 => {
+                        let register = reader.uleb128()?;
+                        let offset = self.factored(reader.uleb128()? as i64);
+                        self.set(register, RegisterRule::Offset(offset))?;
+                    }
+                    // DW_CFA_restore_extended
+                    0x06 => self.restore(reader.uleb128()?)?,
+                    // DW_CFA_undefined
+                    0x07 => self.set(reader.uleb128()?, RegisterRule::Undefined)?,
+                    // DW_CFA_same_value
+                    0x08 => self.set(reader.uleb128()?, RegisterRule::SameValue)?,
+                    // DW_CFA_register
+                    0x09 => {
+                        let register = reader.uleb128()?;
+                        let other = u16::try_from(reader.uleb128()?).ok()?;
+                        self.set(register, RegisterRule::Register(other))?;
+                    }
+                    // DW_CFA_remember_state
+                    0x0a => self.stack.push(self.row.clone()),
+                    // DW_CFA_restore_state
+                    0x0b => {
+                        // The CFA isn't part of the saved state.
+                        let cfa = self.row.cfa;
+                        self.row = self.stack.pop()?;
+                        self.row.cfa = cfa;
+                    }
+                    // DW_CFA_def_cfa
+                    0x0c => {
+                        let register = u16::try_from(reader.uleb128()?).ok()?;
+                        let offset = reader.uleb128()? as i64;
+                        self.row.cfa = CfaRule::RegisterOffset(register, offset);
+                    }
+                    // DW_CFA_def_cfa_register
+                    0x0d => {
+                        let register = u16::try_from(reader.uleb128()?).ok()?;
+                        match &mut self.row.cfa {
+                            CfaRule::RegisterOffset(r, _) => *r = register,
+                            CfaRule::Expression => return None,
+                        }
+                    }
+                    // DW_CFA_def_cfa_offset
+                    0x0e => {
+                        let offset = reader.uleb128()? as i64;
+                        match &mut self.row.cfa {
+                            CfaRule::RegisterOffset(_, o) => *o = offset,
+                            CfaRule::Expression => return None,
+                        }
+                    }
+                    // DW_CFA_def_cfa_expression
+                    0x0f => {
+                        let len = reader.uleb128()?;
+                        reader.bytes(usize::try_from(len).ok()?)?;
+                        self.row.cfa = CfaRule::Expression;
+                    }
+                    // DW_CFA_expression and DW_CFA_val_expression
+                    0x10 | 0x16 => {
+                        let register = reader.uleb128()?;
+                        let len = reader.uleb128()?;
+                        reader.bytes(usize::try_from(len).ok()?)?;
+                        self.set(register, RegisterRule::Unsupported)?;
+                    }
+                    // DW_CFA_offset_extended_sf
+                    0x11 => {
+                        let register = reader.uleb128()?;
+                        let offset = self.factored(reader.sleb128()?);
+                        self.set(register, RegisterRule::Offset(offset))?;
+                    }
+                    // DW_CFA_def_cfa_sf
+                    0x12 => {
+                        let register = u16::try_from(reader.uleb128()?).ok()?;
+                        let offset = self.factored(reader.sleb128()?);
+                        self.row.cfa = CfaRule::RegisterOffset(register, offset);
+                    }
+                    // DW_CFA_def_cfa_offset_sf
+                    0x13 => {
+                        let offset = self.factored(reader.sleb128()?);
+                        match &mut self.row.cfa {
+                            CfaRule::RegisterOffset(_, o) => *o = offset,
+                            CfaRule::Expression => return None,
+                        }
+                    }
+                    // DW_CFA_val_offset
+                    0x14 => {
+                        let register = reader.uleb128()?;
+                        let offset = self.factored(reader.uleb128()? as i64);
+                        self.set(register, RegisterRule::ValOffset(offset))?;
+                    }
+                    // DW_CFA_val_offset_sf
+                    0x15 => {
+                        let register = reader.uleb128()?;
+                        let offset = self.factored(reader.sleb128()?);
+                        self.set(register, RegisterRule::ValOffset(offset))?;
+                    }
+                    // DW_CFA_AARCH64_negate_ra_state, which only matters for
+                    // pointer authentication, and the caller strips that anyway.
+                    0x2d => {}
+                    // DW_CFA_GNU_args_size
+                    0x2e => {
+                        reader.uleb128()?;
+                    }
+                    // DW_CFA_GNU_negative_offset_extended
+                    0x2f => {
+                        let register = reader.uleb128()?;
+                        let offset = self.factored(-(reader.uleb128()? as i64));
+                        self.set(register, RegisterRule::Offset(offset))?;
+                    }
+                    _ => {
+                        trace!("unwind: unknown CFA instruction {:#x}", op);
+                        return None;
+                    }
+                },
+            }
+        }
+        Some(())
+    }
+}
+
+/// Unwind the frame that `walker` is at using the `.eh_frame` of the module
+/// loaded at `module_base`.
+///
+/// `registers` maps DWARF register numbers to the names the walker uses.
+pub fn walk_frame(
+    memory_list: &UnifiedMemoryList<'_>,
+    module_base: u64,
+    registers: &[&str],
+    walker: &mut dyn FrameWalker,
+) -> Option<()> {
+    let (row, return_address_register) =
+        find_row(memory_list, module_base, walker.get_instruction())?;
+    let name = |register: u16| registers.get(register as usize).copied();
+
+    let cfa = match row.cfa {
+        CfaRule::RegisterOffset(register, offset) => walker
+            .get_callee_register(name(register)?)?
+            .wrapping_add(offset as u64),
+        CfaRule::Expression => {
+            trace!("unwind: eh_frame CFA expressions are unsupported");
+            return None;
+        }
+    };
+
+    let recover = |walker: &dyn FrameWalker, rule: RegisterRule, register: u16| match rule {
+        RegisterRule::Undefined | RegisterRule::Unsupported => None,
+        RegisterRule::SameValue => walker.get_callee_register(name(register)?),
+        RegisterRule::Offset(offset) => {
+            walker.get_register_at_address(cfa.wrapping_add(offset as u64))
+        }
+        RegisterRule::ValOffset(offset) => Some(cfa.wrapping_add(offset as u64)),
+        RegisterRule::Register(other) => walker.get_callee_register(name(other)?),
+    };
+
+    // The return address column is unwound into the caller's instruction
+    // pointer; if it has no rule, it's still in the callee's register.
+    let return_address = match row.registers.get(&return_address_register) {
+        Some(&rule) => recover(walker, rule, return_address_register)?,
+        None => walker.get_callee_register(name(return_address_register)?)?,
+    };
+
+    walker.set_cfa(cfa)?;
+    walker.set_ra(return_address)?;
+    for (&register, &rule) in &row.registers {
+        if register == return_address_register {
+            continue;
+        }
+        let name = match name(register) {
+            Some(name) => name,
+            None => continue,
+        };
+        match recover(walker, rule, register) {
+            Some(value) => {
+                walker.set_caller_register(name, value);
+            }
+            None => walker.clear_caller_register(name),
+        }
+    }
+    Some(())
+}
@@ -0,0 +1,120 @@
+// Copyright 2015 Ted Mielczarek. See the COPYRIGHT
+// file at the top-level directory of this distribution.
+
+//! Just enough ELF parsing to find the segments of a module that was
+//! mapped into memory, for unwinders which read the module's own tables.
+
+use minidump::UnifiedMemoryList;
+use scroll::ctx::{SizeWith, TryFromCtx};
+use scroll::Endian;
+use std::convert::TryFrom;
+
+const ELF_MAGIC: u32 = 0x464c_457f;
+const ELFCLASS32: u8 = 1;
+const ELFCLASS64: u8 = 2;
+const ELFDATA2LSB: u8 = 1;
+
+pub const PT_LOAD: u32 = 1;
+pub const PT_DYNAMIC: u32 = 2;
+pub const PT_GNU_EH_FRAME: u32 = 0x6474_e550;
+pub const PT_ARM_EXIDX: u32 = 0x7000_0001;
+
+/// A segment of a module, as loaded in memory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Segment {
+    /// The address the segment was loaded at.
+    pub address: u64,
+    /// The size of the segment in memory.
+    pub size: u64,
+    /// Whether the module is a 64-bit ELF.
+    pub is_64: bool,
+    /// How far the module was loaded from the addresses it was linked at.
+    pub load_bias: u64,
+}
+
+/// Read a little-endian value from whichever region of memory contains `address`.
+pub fn read_memory<'a, T>(memory_list: &UnifiedMemoryList<'a>, address: u64) -> Option<T>
+where
+    T: TryFromCtx<'a, Endian, [u8], Error = scroll::Error> + SizeWith<Endian>,
+{
+    memory_list
+        .memory_at_address(address)?
+        .get_memory_at_address(address)
+}
+
+/// Get all the memory from `address` to the end of the region containing it.
+pub fn memory_from<'a>(memory_list: &UnifiedMemoryList<'a>, address: u64) -> Option<&'a [u8]> {
+    let memory = memory_list.memory_at_address(address)?;
+    let offset = usize::try_from(address - memory.base_address()).ok()?;
+    memory.bytes().get(offset..)
+}
+
+/// Find the first segment of type `p_type` in the module loaded at `base`,
+/// using the ELF program headers that are mapped along with it.
+pub fn find_segment(
+    memory_list: &UnifiedMemoryList<'_>,
+    base: u64,
+    p_type: u32,
+) -> Option<Segment> {
+    if read_memory::<u32>(memory_list, base)? != ELF_MAGIC {
+        return None;
+    }
+    if read_memory::<u8>(memory_list, base + 5)? != ELFDATA2LSB {
+        return None;
+    }
+    let is_64 = match read_memory::<u8>(memory_list, base + 4)? {
+        ELFCLASS32 => false,
+        ELFCLASS64 => true,
+        _ => return None,
+    };
+
+    let (phoff, phentsize, phnum) = if is_64 {
+        (
+            read_memory::<u64>(memory_list, base + 0x20)?,
+            read_memory::<u16>(memory_list, base + 0x36)?,
+            read_memory::<u16>(memory_list, base + 0x38)?,
+        )
+    } else {
+        (
+            read_memory::<u32>(memory_list, base + 0x1c)? as u64,
+            read_memory::<u16>(memory_list, base + 0x2a)?,
+            read_memory::<u16>(memory_list, base + 0x2c)?,
+        )
+    };
+
+    let mut found = None;
+    let mut first_load = None;
+    for i in 0..phnum as u64 {
+        let phdr = base
+            .checked_add(phoff)?
+            .checked_add(i.checked_mul(phentsize as u64)?)?;
+        let this_type = read_memory::<u32>(memory_list, phdr)?;
+        let (vaddr, memsz) = if is_64 {
+            (
+                read_memory::<u64>(memory_list, phdr + 16)?,
+                read_memory::<u64>(memory_list, phdr + 40)?,
+            )
+        } else {
+            (
+                read_memory::<u32>(memory_list, phdr + 8)? as u64,
+                read_memory::<u32>(memory_list, phdr + 20)? as u64,
+            )
+        };
+        if this_type == PT_LOAD && first_load.is_none() {
+            first_load = Some(vaddr & !0xfff);
+        }
+        if this_type == p_type && found.is_none() {
+            found = Some((vaddr, memsz));
+        }
+    }
+
+    // The module's base is where its first segment was loaded.
+    let load_bias = base.checked_sub(first_load?)?;
+    let (vaddr, size) = found?;
+    Some(Segment {
+        address: load_bias.checked_add(vaddr)?,
+        size,
+        is_64,
+        load_bias,
+    })
+}
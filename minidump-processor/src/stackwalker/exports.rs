@@ -0,0 +1,221 @@
+// Copyright 2015 Ted Mielczarek. See the COPYRIGHT
+// file at the top-level directory of this distribution.
+
+//! Coarse symbols from the functions a module exports, for modules we have
+//! no symbols for, but whose image was included in the minidump.
+//!
+//! PE modules list their exports in the export table. ELF modules list them
+//! in the dynamic symbol table, which is found through the `PT_DYNAMIC`
+//! segment. Either way, only exported functions are listed, so the nearest
+//! export before an instruction may not be the function it's actually in.
+
+use super::elf::{self, read_memory, PT_DYNAMIC};
+use minidump::{MinidumpModule, Module, UnifiedMemoryList};
+
+/// The most exports (or dynamic symbols) to look through in one module, in
+/// case the count we read is garbage.
+const MAX_EXPORTS: u64 = 1 << 20;
+/// The longest export name to read.
+const MAX_NAME_LEN: usize = 4096;
+
+const IMAGE_DOS_SIGNATURE: u16 = 0x5a4d; // MZ
+const IMAGE_NT_SIGNATURE: u32 = 0x0000_4550; // PE\0\0
+const IMAGE_NT_OPTIONAL_HDR32_MAGIC: u16 = 0x10b;
+const IMAGE_NT_OPTIONAL_HDR64_MAGIC: u16 = 0x20b;
+
+const DT_NULL: u64 = 0;
+const DT_HASH: u64 = 4;
+const DT_STRTAB: u64 = 5;
+const DT_SYMTAB: u64 = 6;
+const DT_SYMENT: u64 = 11;
+const STT_FUNC: u8 = 2;
+const SHN_UNDEF: u16 = 0;
+
+/// An exported function.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Export {
+    pub name: String,
+    /// The address the function starts at.
+    pub address: u64,
+}
+
+/// Find the exported function of `module` that `address` is most likely in,
+/// using the module's image in `memory_list`.
+pub fn nearest_export(
+    memory_list: &UnifiedMemoryList<'_>,
+    module: &MinidumpModule,
+    address: u64,
+) -> Option<Export> {
+    let base = module.base_address();
+    nearest_pe_export(memory_list, base, address)
+        .or_else(|| nearest_elf_export(memory_list, base, module.size(), address))
+}
+
+/// Read the NUL-terminated string at `address`.
+fn read_c_string(memory_list: &UnifiedMemoryList<'_>, address: u64) -> Option<String> {
+    let bytes = elf::memory_from(memory_list, address)?;
+    let len = bytes.iter().take(MAX_NAME_LEN).position(|&b| b == 0)?;
+    Some(String::from_utf8_lossy(&bytes[..len]).into_owned())
+}
+
+/// Find the nearest export at or before `address` in the export table of the
+/// PE module loaded at `base`.
+///
+/// Exports without a name are called `Ordinal<n>`.
+fn nearest_pe_export(
+    memory_list: &UnifiedMemoryList<'_>,
+    base: u64,
+    address: u64,
+) -> Option<Export> {
+    let read_u16 = |rva: u64| read_memory::<u16>(memory_list, base.checked_add(rva)?);
+    let read_u32 = |rva: u64| read_memory::<u32>(memory_list, base.checked_add(rva)?);
+
+    if read_u16(0)? != IMAGE_DOS_SIGNATURE {
+        return None;
+    }
+    let nt_headers = read_u32(0x3c)? as u64;
+    if read_u32(nt_headers)? != IMAGE_NT_SIGNATURE {
+        return None;
+    }
+    let optional_header = nt_headers + 24;
+    let data_directories = match read_u16(optional_header)? {
+        IMAGE_NT_OPTIONAL_HDR32_MAGIC => optional_header + 96,
+        IMAGE_NT_OPTIONAL_HDR64_MAGIC => optional_header + 112,
+        _ => return None,
+    };
+    // NumberOfRvaAndSizes, the export table is the first data directory.
+    if read_u32(data_directories - 4)? == 0 {
+        return None;
+    }
+    let export_dir = read_u32(data_directories)? as u64;
+    let export_dir_size = read_u32(data_directories + 4)? as u64;
+    if export_dir == 0 {
+        return None;
+    }
+
+    let ordinal_base = read_u32(export_dir + 16)?;
+    let num_functions = read_u32(export_dir + 20)? as u64;
+    let num_names = read_u32(export_dir + 24)? as u64;
+    let functions = read_u32(export_dir + 28)? as u64;
+    let names = read_u32(export_dir + 32)? as u64;
+    let name_ordinals = read_u32(export_dir + 36)? as u64;
+
+    let target = address.checked_sub(base)?;
+    let mut nearest: Option<(u64, u64)> = None;
+    for index in 0..num_functions.min(MAX_EXPORTS) {
+        let rva = read_u32(functions + index * 4)? as u64;
+        // Forwarded exports point into the export directory, at the name of
+        // the function they forward to.
+        let forwarded = (export_dir..export_dir + export_dir_size).contains(&rva);
+        if rva == 0 || forwarded || rva > target {
+            continue;
+        }
+        if nearest.map_or(true, |(_, nearest_rva)| rva > nearest_rva) {
+            nearest = Some((index, rva));
+        }
+    }
+    let (index, rva) = nearest?;
+
+    let name = (0..num_names.min(MAX_EXPORTS))
+        .find(|&i| read_u16(name_ordinals + i * 2).map(u64::from) == Some(index))
+        .and_then(|i| read_c_string(memory_list, base + read_u32(names + i * 4)? as u64))
+        .unwrap_or_else(|| format!("Ordinal{}", ordinal_base as u64 + index));
+    Some(Export {
+        name,
+        address: base + rva,
+    })
+}
+
+/// Find the exported function containing `address` in the dynamic symbol
+/// table of the ELF module loaded at `base`.
+fn nearest_elf_export(
+    memory_list: &UnifiedMemoryList<'_>,
+    base: u64,
+    size: u64,
+    address: u64,
+) -> Option<Export> {
+    let dynamic = elf::find_segment(memory_list, base, PT_DYNAMIC)?;
+    let is_64 = dynamic.is_64;
+    let read_word = |address: u64| {
+        if is_64 {
+            read_memory::<u64>(memory_list, address)
+        } else {
+            read_memory::<u32>(memory_list, address).map(u64::from)
+        }
+    };
+    // Some dynamic loaders (like glibc's, on most architectures) relocate the
+    // pointers in the dynamic section, and some don't.
+    let pointer = |value: u64| {
+        if value >= base && value - base < size {
+            Some(value)
+        } else {
+            dynamic.load_bias.checked_add(value)
+        }
+    };
+
+    let word_size = if is_64 { 8 } else { 4 };
+    let (mut hash, mut strtab, mut symtab) = (None, None, None);
+    let mut syment = if is_64 { 24 } else { 16 };
+    for entry in (0..dynamic.size / (word_size * 2)).map(|i| dynamic.address + i * word_size * 2) {
+        let value = read_word(entry + word_size)?;
+        match read_word(entry)? {
+            DT_NULL => break,
+            DT_HASH => hash = pointer(value),
+            DT_STRTAB => strtab = pointer(value),
+            DT_SYMTAB => symtab = pointer(value),
+            DT_SYMENT => syment = value,
+            _ => {}
+        }
+    }
+    let (strtab, symtab) = (strtab?, symtab?);
+    if syment == 0 {
+        return None;
+    }
+    // The chain of DT_HASH has an entry for every symbol. Modules that only
+    // have a DT_GNU_HASH don't say how many symbols there are, but linkers
+    // put the string table right after the symbol table.
+    let num_symbols = match hash {
+        Some(hash) => read_memory::<u32>(memory_list, hash.checked_add(4)?)? as u64,
+        None => strtab.checked_sub(symtab)? / syment,
+    };
+
+    let mut nearest: Option<(u64, u64, u32)> = None;
+    for sym in (0..num_symbols.min(MAX_EXPORTS)).map(|i| symtab + i * syment) {
+        let name = read_memory::<u32>(memory_list, sym)?;
+        let (value, sym_size, info, shndx) = if is_64 {
+            (
+                read_memory::<u64>(memory_list, sym + 8)?,
+                read_memory::<u64>(memory_list, sym + 16)?,
+                read_memory::<u8>(memory_list, sym + 4)?,
+                read_memory::<u16>(memory_list, sym + 6)?,
+            )
+        } else {
+            (
+                read_memory::<u32>(memory_list, sym + 4)? as u64,
+                read_memory::<u32>(memory_list, sym + 8)? as u64,
+                read_memory::<u8>(memory_list, sym + 12)?,
+                read_memory::<u16>(memory_list, sym + 14)?,
+            )
+        };
+        if info & 0xf != STT_FUNC || shndx == SHN_UNDEF || value == 0 {
+            continue;
+        }
+        let sym_address = match dynamic.load_bias.checked_add(value) {
+            Some(sym_address) if sym_address <= address => sym_address,
+            _ => continue,
+        };
+        if nearest.map_or(true, |nearest| sym_address > nearest.0) {
+            nearest = Some((sym_address, sym_size, name));
+        }
+    }
+    let (sym_address, sym_size, name) = nearest?;
+    // Unlike PE exports, dynamic symbols know how big they are, so there's no
+    // need to guess.
+    if sym_size != 0 && address - sym_address >= sym_size {
+        return None;
+    }
+    Some(Export {
+        name: read_c_string(memory_list, strtab.checked_add(name as u64)?)?,
+        address: sym_address,
+    })
+}
@@ -0,0 +1,345 @@
+// Copyright 2015 Ted Mielczarek. See the COPYRIGHT
+// file at the top-level directory of this distribution.
+
+// NOTE: mips.rs and mips64.rs should be identical except for the names of
+// their context types (and therefore their pointer widths).
+
+use crate::process_state::{FrameTrust, StackFrame, UnwindStrategy};
+use crate::stackwalker::unwind::Unwind;
+use crate::stackwalker::{CfiStackWalker, UnwindRecorder};
+use crate::{ProcessorOptions, SymbolProvider};
+use minidump::{
+    CpuContext, MinidumpContext, MinidumpContextValidity, MinidumpMemory, MinidumpModuleList,
+    MinidumpRawContext, UnifiedMemoryInfoList, UnifiedMemoryList,
+};
+use std::collections::HashSet;
+use tracing::trace;
+
+type MipsContext = minidump::format::CONTEXT_MIPS64;
+type Pointer = <MipsContext as CpuContext>::Register;
+type Registers = minidump::format::MipsRegisterNumbers;
+
+const POINTER_WIDTH: Pointer = std::mem::size_of::<Pointer>() as Pointer;
+const FRAME_POINTER: &str = Registers::FramePointer.name();
+const STACK_POINTER: &str = Registers::StackPointer.name();
+const PROGRAM_COUNTER: &str = "pc";
+pub(super) const CALLEE_SAVED_REGS: &[&str] =
+    &["s0", "s1", "s2", "s3", "s4", "s5", "s6", "s7", "gp", "fp"];
+
+/// How far (in pointers) above the frame pointer we look for the saved frame pointer.
+const MAX_FRAME_SIZE: Pointer = 1024;
+
+async fn get_caller_by_cfi<P>(
+    ctx: &MipsContext,
+    callee: &StackFrame,
+    grand_callee: Option<&StackFrame>,
+    stack_memory: &MinidumpMemory<'_>,
+    modules: &MinidumpModuleList,
+    symbol_provider: &P,
+    recorder: &mut UnwindRecorder,
+) -> Option<StackFrame>
+where
+    P: SymbolProvider + Sync,
+{
+    trace!("unwind: trying cfi");
+    let valid = &callee.context.valid;
+    let _last_sp = ctx.get_register(STACK_POINTER, valid)?;
+    let module = modules.module_at_address(callee.instruction)?;
+    let grand_callee_parameter_size = grand_callee.and_then(|f| f.parameter_size).unwrap_or(0);
+
+    let mut stack_walker = CfiStackWalker {
+        instruction: callee.instruction,
+        grand_callee_parameter_size,
+
+        callee_ctx: ctx,
+        callee_validity: valid,
+
+        // Default to forwarding all callee-saved regs verbatim.
+        // The CFI evaluator may clear or overwrite these values.
+        // The stack pointer and instruction pointer are not included.
+        caller_ctx: ctx.clone(),
+        caller_validity: callee_forwarded_regs(valid),
+
+        stack_memory,
+        modules,
+        rejected_reads: Default::default(),
+    };
+
+    let walked = symbol_provider.walk_frame(module, &mut stack_walker).await;
+    recorder.cfi_rejected_reads(&stack_walker);
+    if walked.is_none() {
+        recorder.reject("no cfi could be evaluated for the instruction");
+        return None;
+    }
+    let caller_pc = stack_walker.caller_ctx.get_register_always(PROGRAM_COUNTER);
+    let caller_sp = stack_walker.caller_ctx.get_register_always(STACK_POINTER);
+
+    trace!(
+        "unwind: cfi evaluation was successful -- caller_pc: 0x{:016x}, caller_sp: 0x{:016x}",
+        caller_pc,
+        caller_sp,
+    );
+
+    // Like ARM, do no further validation of the CFI results.
+
+    let context = MinidumpContext {
+        raw: MinidumpRawContext::Mips64(stack_walker.caller_ctx),
+        valid: MinidumpContextValidity::Some(stack_walker.caller_validity),
+    };
+    Some(StackFrame::from_context(context, FrameTrust::CallFrameInfo))
+}
+
+fn callee_forwarded_regs(valid: &MinidumpContextValidity) -> HashSet<&'static str> {
+    match valid {
+        MinidumpContextValidity::All => CALLEE_SAVED_REGS.iter().copied().collect(),
+        MinidumpContextValidity::Some(ref which) => CALLEE_SAVED_REGS
+            .iter()
+            .filter(|&reg| which.contains(reg))
+            .copied()
+            .collect(),
+    }
+}
+
+fn get_caller_by_frame_pointer<P>(
+    ctx: &MipsContext,
+    callee: &StackFrame,
+    stack_memory: &MinidumpMemory<'_>,
+    _modules: &MinidumpModuleList,
+    _symbol_provider: &P,
+    recorder: &mut UnwindRecorder,
+) -> Option<StackFrame>
+where
+    P: SymbolProvider + Sync,
+{
+    trace!("unwind: trying frame pointer");
+    // Unlike ARM, the o32 and n64 ABIs don't put the saved frame pointer and
+    // return address at the frame pointer. A function built with frame pointers does:
+    //
+    // sp := sp - N
+    // *(sp + N - ptr)   := ra
+    // *(sp + N - ptr*2) := fp
+    // fp := sp
+    //
+    // and the size of the frame (N) is only known from the instructions. However,
+    // when the caller also uses a frame pointer, its fp is equal to its sp, which
+    // is fp + N. So the saved fp is the only slot above fp which contains its own
+    // address plus two pointers, and we can search for it. Once it is found,
+    // we restore the caller's registers with:
+    //
+    // pc := *(slot + ptr)
+    // sp := slot + ptr*2
+    // fp := *slot
+    //
+    // If the caller doesn't use a frame pointer this will fail, and we'll fall
+    // back to scanning.
+    let valid = &callee.context.valid;
+    let last_fp = ctx.get_register(FRAME_POINTER, valid)?;
+    let last_sp = ctx.get_register(STACK_POINTER, valid)?;
+
+    if last_fp == 0 || last_fp < last_sp {
+        return None;
+    }
+
+    let mut found = None;
+    for i in 0..MAX_FRAME_SIZE {
+        let slot = last_fp.checked_add(i.checked_mul(POINTER_WIDTH)?)?;
+        // Give up if we run off the end of the stack.
+        let saved_fp: Pointer = recorder.read(stack_memory, slot as u64)?;
+        if Some(saved_fp) == slot.checked_add(POINTER_WIDTH * 2) {
+            found = Some(slot);
+            break;
+        }
+    }
+    let slot = found?;
+    let caller_fp = slot.checked_add(POINTER_WIDTH * 2)?;
+    let caller_pc = recorder.read(stack_memory, slot as u64 + POINTER_WIDTH as u64)?;
+    let caller_sp = caller_fp;
+
+    trace!(
+        "unwind: frame pointer seems valid -- caller_pc: 0x{:016x}, caller_sp: 0x{:016x}",
+        caller_pc,
+        caller_sp,
+    );
+
+    let mut caller_ctx = MipsContext::default();
+    caller_ctx.set_register(PROGRAM_COUNTER, caller_pc);
+    caller_ctx.set_register(FRAME_POINTER, caller_fp);
+    caller_ctx.set_register(STACK_POINTER, caller_sp);
+
+    let mut valid = HashSet::new();
+    valid.insert(PROGRAM_COUNTER);
+    valid.insert(FRAME_POINTER);
+    valid.insert(STACK_POINTER);
+
+    let context = MinidumpContext {
+        raw: MinidumpRawContext::Mips64(caller_ctx),
+        valid: MinidumpContextValidity::Some(valid),
+    };
+    Some(StackFrame::from_context(context, FrameTrust::FramePointer))
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn get_caller_by_scan<P>(
+    ctx: &MipsContext,
+    callee: &StackFrame,
+    stack_memory: &MinidumpMemory<'_>,
+    memory_info: &UnifiedMemoryInfoList<'_>,
+    modules: &MinidumpModuleList,
+    symbol_provider: &P,
+    options: &ProcessorOptions<'_>,
+    recorder: &mut UnwindRecorder,
+) -> Option<StackFrame>
+where
+    P: SymbolProvider + Sync,
+{
+    trace!("unwind: trying scan");
+    // Stack scanning is just walking from the end of the frame until we encounter
+    // a value on the stack that looks like a pointer into some code (it's an address
+    // in a range covered by one of our modules). If we find such a value, we assume
+    // it's a saved ra, and that the next frame ends just before it.
+    let valid = &callee.context.valid;
+    let last_sp = ctx.get_register(STACK_POINTER, valid)?;
+
+    // Number of pointer-sized values to scan through in our search.
+    let scan_range = super::scan_range(callee, options) as Pointer;
+
+    for i in 0..scan_range {
+        let address_of_pc = last_sp.checked_add(i * POINTER_WIDTH)?;
+        let caller_pc = recorder.read(stack_memory, address_of_pc as u64)?;
+        if instruction_seems_valid(caller_pc, memory_info, modules, symbol_provider).await {
+            // ra is saved at the top of the frame, so sp is just address_of_pc + ptr
+            let caller_sp = address_of_pc.checked_add(POINTER_WIDTH)?;
+
+            trace!(
+                "unwind: scan seems valid -- caller_pc: 0x{:016x}, caller_sp: 0x{:016x}",
+                caller_pc,
+                caller_sp,
+            );
+
+            let mut caller_ctx = MipsContext::default();
+            caller_ctx.set_register(PROGRAM_COUNTER, caller_pc);
+            caller_ctx.set_register(STACK_POINTER, caller_sp);
+
+            let mut valid = HashSet::new();
+            valid.insert(PROGRAM_COUNTER);
+            valid.insert(STACK_POINTER);
+
+            let context = MinidumpContext {
+                raw: MinidumpRawContext::Mips64(caller_ctx),
+                valid: MinidumpContextValidity::Some(valid),
+            };
+            return Some(StackFrame::from_context(context, FrameTrust::Scan));
+        }
+    }
+
+    None
+}
+
+/// The most strict validation we have for instruction pointers.
+///
+/// This is only used for stack-scanning, see the ARM implementation for details.
+async fn instruction_seems_valid<P>(
+    instruction: Pointer,
+    memory_info: &UnifiedMemoryInfoList<'_>,
+    modules: &MinidumpModuleList,
+    symbol_provider: &P,
+) -> bool
+where
+    P: SymbolProvider + Sync,
+{
+    super::instruction_seems_valid_by_symbols(
+        instruction as u64,
+        memory_info,
+        modules,
+        symbol_provider,
+    )
+    .await
+}
+
+#[async_trait::async_trait]
+impl Unwind for MipsContext {
+    async fn get_caller_frame<P>(
+        &self,
+        callee: &StackFrame,
+        grand_callee: Option<&StackFrame>,
+        stack_memory: Option<&MinidumpMemory<'_>>,
+        _memory_list: &UnifiedMemoryList<'_>,
+        memory_info: &UnifiedMemoryInfoList<'_>,
+        modules: &MinidumpModuleList,
+        syms: &P,
+        options: &ProcessorOptions<'_>,
+        recorder: &mut UnwindRecorder,
+    ) -> Option<StackFrame>
+    where
+        P: SymbolProvider + Sync,
+    {
+        let stack = stack_memory.as_ref()?;
+
+        // .await doesn't like closures, so don't use Option chaining
+        let mut frame = None;
+        if frame.is_none() {
+            recorder.attempt(UnwindStrategy::CallFrameInfo);
+            frame =
+                get_caller_by_cfi(self, callee, grand_callee, stack, modules, syms, recorder).await;
+        }
+        if frame.is_none() {
+            recorder.attempt(UnwindStrategy::FramePointer);
+            frame = get_caller_by_frame_pointer(self, callee, stack, modules, syms, recorder);
+        }
+        if frame.is_none() && options.allow_scan {
+            recorder.attempt(UnwindStrategy::Scan);
+            frame = get_caller_by_scan(
+                self,
+                callee,
+                stack,
+                memory_info,
+                modules,
+                syms,
+                options,
+                recorder,
+            )
+            .await;
+        }
+        let mut frame = frame?;
+
+        // We now check the frame to see if it looks like unwinding is complete,
+        // based on the frame we computed having a nonsense value. Returning
+        // None signals to the unwinder to stop unwinding.
+
+        // if the instruction is within the first ~page of memory, it's basically
+        // null, and we can assume unwinding is complete.
+        if frame.context.get_instruction_pointer() < 4096 {
+            trace!("unwind: instruction pointer was nullish, assuming unwind complete");
+            recorder.reject("instruction pointer was nullish");
+            return None;
+        }
+        // If the new stack pointer is at a lower address than the old,
+        // then that's clearly incorrect. Treat this as end-of-stack to
+        // enforce progress and avoid infinite loops.
+        let sp = frame.context.get_stack_pointer();
+        let last_sp = self.get_register_always(STACK_POINTER) as u64;
+        if sp <= last_sp {
+            // Leaf functions may not touch the stack (the return address is
+            // in ra), so permit the stack pointer to not change for the first
+            // frame of the unwind.
+            let is_leaf = callee.trust == FrameTrust::Context && sp == last_sp;
+            if !is_leaf {
+                trace!("unwind: stack pointer went backwards, assuming unwind complete");
+                recorder.reject("stack pointer went backwards");
+                return None;
+            }
+        }
+
+        // Ok, the frame now seems well and truly valid, do final cleanup.
+
+        // A caller's pc is the return address, which is the instruction *after*
+        // the branch delay slot of the call that caused us to arrive at the callee.
+        // Set the value to 8 less than that, so it points to the call instruction.
+        // This is important because we use this value to lookup the CFI we need
+        // to unwind the next frame.
+        let ip = frame.context.get_instruction_pointer();
+        frame.instruction = ip - 8;
+
+        Some(frame)
+    }
+}
@@ -7,16 +7,40 @@ mod amd64;
 mod arm;
 mod arm64;
 mod arm64_old;
+mod arm_ehabi;
+mod cfi_cache;
+mod custom;
+mod eh_frame;
+mod elf;
+mod exports;
+mod mips;
+// Shares its code with mips.rs, where the pointer casts are necessary.
+#[allow(clippy::unnecessary_cast)]
+mod mips64;
+mod observer;
+mod ppc;
+mod ppc64;
+mod riscv64;
+mod sparc;
+mod symbol_cache;
 mod unwind;
 mod x86;
 
 use crate::process_state::*;
-use crate::{FrameWalker, SymbolProvider};
-use log::trace;
+use crate::{FrameSymbolizer, FrameWalker, ProcessorOptions, SymbolProvider};
 use minidump::*;
 use scroll::ctx::{SizeWith, TryFromCtx};
+use tracing::{trace, trace_span, Instrument};
 
+pub(crate) use self::cfi_cache::CfiCache;
+pub use self::custom::{ArchUnwinder, UnwinderRegistry};
+pub(crate) use self::observer::ProgressReporter;
+pub use self::observer::{
+    FrameObserver, ProgressEvent, ProgressObserver, ThreadObserver, WalkControl,
+};
+pub(crate) use self::symbol_cache::SymbolCache;
 use self::unwind::Unwind;
+use std::cell::RefCell;
 use std::collections::HashSet;
 use std::convert::TryFrom;
 
@@ -31,6 +55,9 @@ struct CfiStackWalker<'a, C: CpuContext> {
     caller_validity: HashSet<&'static str>,
 
     stack_memory: &'a MinidumpMemory<'a>,
+    modules: &'a MinidumpModuleList,
+    /// Addresses the CFI wanted to read that aren't in `stack_memory`.
+    rejected_reads: RefCell<Vec<u64>>,
 }
 
 impl<'a, C> FrameWalker for CfiStackWalker<'a, C>
@@ -48,6 +75,9 @@ where
     }
     fn get_register_at_address(&self, address: u64) -> Option<u64> {
         let result: Option<C::Register> = self.stack_memory.get_memory_at_address(address);
+        if result.is_none() {
+            self.rejected_reads.borrow_mut().push(address);
+        }
         result.and_then(|val| u64::try_from(val).ok())
     }
     fn get_callee_register(&self, name: &str) -> Option<u64> {
@@ -62,6 +92,9 @@ where
         self.caller_ctx.set_register(name, val)
     }
     fn clear_caller_register(&mut self, name: &str) {
+        if let Some(memoized) = self.caller_ctx.memoize_register(name) {
+            self.caller_validity.remove(memoized);
+        }
         self.caller_validity.remove(name);
     }
     fn set_cfa(&mut self, val: u64) -> Option<()> {
@@ -78,32 +111,125 @@ where
         self.caller_validity.insert(instruction_pointer_reg);
         self.caller_ctx.set_register(instruction_pointer_reg, val)
     }
+    fn instruction_seems_valid(&self, address: u64) -> bool {
+        self.modules.module_at_address(address).is_some()
+    }
+}
+
+/// Collects [`UnwindDiagnostics`] while looking for a frame's caller, if
+/// they were asked for.
+pub(crate) struct UnwindRecorder {
+    diagnostics: Option<UnwindDiagnostics>,
 }
 
+impl UnwindRecorder {
+    fn new(options: &ProcessorOptions<'_>) -> Self {
+        Self {
+            diagnostics: options.unwind_diagnostics.then(UnwindDiagnostics::default),
+        }
+    }
+
+    fn current(&mut self) -> Option<&mut UnwindAttempt> {
+        self.diagnostics.as_mut()?.attempts.last_mut()
+    }
+
+    /// Starts recording an attempt to find the caller with `strategy`.
+    pub(crate) fn attempt(&mut self, strategy: UnwindStrategy) {
+        if let Some(diagnostics) = &mut self.diagnostics {
+            diagnostics.attempts.push(UnwindAttempt {
+                strategy,
+                succeeded: false,
+                rejections: vec![],
+                rejected_reads: vec![],
+            });
+        }
+    }
+
+    /// Records why the current attempt threw out a result.
+    pub(crate) fn reject(&mut self, reason: &str) {
+        if let Some(attempt) = self.current() {
+            attempt.rejections.push(reason.to_owned());
+        }
+    }
+
+    /// Records reads the current attempt couldn't make.
+    pub(crate) fn rejected_reads(&mut self, addresses: impl IntoIterator<Item = u64>) {
+        if let Some(attempt) = self.current() {
+            attempt.rejected_reads.extend(addresses);
+        }
+    }
+
+    /// Reads a value from the stack, recording the read if it fails.
+    pub(crate) fn read<'a, T>(&mut self, memory: &MinidumpMemory<'a>, address: u64) -> Option<T>
+    where
+        T: TryFromCtx<'a, Endian, [u8], Error = scroll::Error> + SizeWith<Endian>,
+    {
+        let result = memory.get_memory_at_address(address);
+        if result.is_none() {
+            self.rejected_reads(Some(address));
+        }
+        result
+    }
+
+    /// Records the reads a `CfiStackWalker` couldn't make.
+    fn cfi_rejected_reads<C: CpuContext>(&mut self, walker: &CfiStackWalker<C>) {
+        self.rejected_reads(walker.rejected_reads.borrow_mut().drain(..));
+    }
+
+    fn finish(mut self, found: bool) -> Option<UnwindDiagnostics> {
+        if found {
+            if let Some(attempt) = self.current() {
+                attempt.succeeded = true;
+            }
+        }
+        self.diagnostics
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
 async fn get_caller_frame<P>(
     callee_frame: &StackFrame,
     grand_callee_frame: Option<&StackFrame>,
     stack_memory: Option<&MinidumpMemory<'_>>,
+    memory_list: &UnifiedMemoryList<'_>,
+    memory_info: &UnifiedMemoryInfoList<'_>,
     modules: &MinidumpModuleList,
     symbol_provider: &P,
+    options: &ProcessorOptions<'_>,
+    recorder: &mut UnwindRecorder,
 ) -> Option<StackFrame>
 where
     P: SymbolProvider + Sync,
 {
+    if let Some(unwinder) = options.unwinders.for_context(&callee_frame.context.raw) {
+        recorder.attempt(UnwindStrategy::Custom);
+        let caller = unwinder
+            .get_caller_frame(
+                callee_frame,
+                grand_callee_frame,
+                stack_memory,
+                memory_list,
+                modules,
+                symbol_provider,
+            )
+            .await;
+        if caller.is_some() || unwinder.replaces_builtin() {
+            return caller;
+        }
+    }
+
     match callee_frame.context.raw {
-        /*
-        MinidumpRawContext::PPC(ctx) => ctx.get_caller_frame(stack_memory),
-        MinidumpRawContext::PPC64(ctx) => ctx.get_caller_frame(stack_memory),
-        MinidumpRawContext::SPARC(ctx) => ctx.get_caller_frame(stack_memory),
-        MinidumpRawContext::MIPS(ctx) => ctx.get_caller_frame(stack_memory),
-         */
         MinidumpRawContext::Arm(ref ctx) => {
             ctx.get_caller_frame(
                 callee_frame,
                 grand_callee_frame,
                 stack_memory,
+                memory_list,
+                memory_info,
                 modules,
                 symbol_provider,
+                options,
+                recorder,
             )
             .await
         }
@@ -112,8 +238,12 @@ where
                 callee_frame,
                 grand_callee_frame,
                 stack_memory,
+                memory_list,
+                memory_info,
                 modules,
                 symbol_provider,
+                options,
+                recorder,
             )
             .await
         }
@@ -122,8 +252,12 @@ where
                 callee_frame,
                 grand_callee_frame,
                 stack_memory,
+                memory_list,
+                memory_info,
                 modules,
                 symbol_provider,
+                options,
+                recorder,
             )
             .await
         }
@@ -132,8 +266,96 @@ where
                 callee_frame,
                 grand_callee_frame,
                 stack_memory,
+                memory_list,
+                memory_info,
+                modules,
+                symbol_provider,
+                options,
+                recorder,
+            )
+            .await
+        }
+        MinidumpRawContext::Mips(ref ctx) => {
+            ctx.get_caller_frame(
+                callee_frame,
+                grand_callee_frame,
+                stack_memory,
+                memory_list,
+                memory_info,
+                modules,
+                symbol_provider,
+                options,
+                recorder,
+            )
+            .await
+        }
+        MinidumpRawContext::Mips64(ref ctx) => {
+            ctx.get_caller_frame(
+                callee_frame,
+                grand_callee_frame,
+                stack_memory,
+                memory_list,
+                memory_info,
+                modules,
+                symbol_provider,
+                options,
+                recorder,
+            )
+            .await
+        }
+        MinidumpRawContext::Ppc(ref ctx) => {
+            ctx.get_caller_frame(
+                callee_frame,
+                grand_callee_frame,
+                stack_memory,
+                memory_list,
+                memory_info,
                 modules,
                 symbol_provider,
+                options,
+                recorder,
+            )
+            .await
+        }
+        MinidumpRawContext::Ppc64(ref ctx) => {
+            ctx.get_caller_frame(
+                callee_frame,
+                grand_callee_frame,
+                stack_memory,
+                memory_list,
+                memory_info,
+                modules,
+                symbol_provider,
+                options,
+                recorder,
+            )
+            .await
+        }
+        MinidumpRawContext::Riscv64(ref ctx) => {
+            ctx.get_caller_frame(
+                callee_frame,
+                grand_callee_frame,
+                stack_memory,
+                memory_list,
+                memory_info,
+                modules,
+                symbol_provider,
+                options,
+                recorder,
+            )
+            .await
+        }
+        MinidumpRawContext::Sparc(ref ctx) => {
+            ctx.get_caller_frame(
+                callee_frame,
+                grand_callee_frame,
+                stack_memory,
+                memory_list,
+                memory_info,
+                modules,
+                symbol_provider,
+                options,
+                recorder,
             )
             .await
         }
@@ -142,101 +364,807 @@ where
                 callee_frame,
                 grand_callee_frame,
                 stack_memory,
+                memory_list,
+                memory_info,
                 modules,
                 symbol_provider,
+                options,
+                recorder,
             )
             .await
         }
-        _ => None,
     }
 }
 
+/// Symbolizes a physical frame, collecting the functions inlined at its
+/// instruction as extra frames.
+struct InlineCollector<'a> {
+    frame: &'a mut StackFrame,
+    /// The inlined frames, from the outermost to the innermost.
+    inlines: Vec<StackFrame>,
+}
+
+impl<'a> FrameSymbolizer for InlineCollector<'a> {
+    fn get_instruction(&self) -> u64 {
+        self.frame.instruction
+    }
+    fn set_function(&mut self, name: &str, base: u64, parameter_size: u32) {
+        self.frame.set_function(name, base, parameter_size);
+    }
+    fn set_source_file(&mut self, file: &str, line: u32, base: u64) {
+        self.frame.set_source_file(file, line, base);
+    }
+    fn add_inline_frame(&mut self, name: &str, file: Option<&str>, line: Option<u32>) {
+        // Inlined frames have no registers of their own, so they just share
+        // everything but the symbols with the physical frame.
+        let mut inline = StackFrame::from_context(self.frame.context.clone(), FrameTrust::Inlined);
+        inline.instruction = self.frame.instruction;
+        inline.resume_address = self.frame.resume_address;
+        inline.is_thumb = self.frame.is_thumb;
+        inline.module = self.frame.module.clone();
+        inline.function_name = Some(String::from(name));
+        inline.function_base = self.frame.function_base;
+        inline.source_file_name = file.map(String::from);
+        inline.source_line = line;
+        if file.is_some() && line.is_some() {
+            inline.source_line_base = self.frame.source_line_base.or(self.frame.function_base);
+        }
+        self.inlines.push(inline);
+    }
+    fn set_source_url(&mut self, url: &str) {
+        let url = Some(String::from(url));
+        match self.inlines.last_mut() {
+            Some(inline) => inline.source_url = url,
+            None => self.frame.source_url = url,
+        }
+    }
+    fn add_source_line(&mut self, file: &str, line: u32) {
+        let line = (String::from(file), line);
+        match self.inlines.last_mut() {
+            Some(inline) => inline.other_source_lines.push(line),
+            None => self.frame.other_source_lines.push(line),
+        }
+    }
+}
+
+/// Fills in the module and symbols of `frame`, and returns the frames of any
+/// functions inlined at its instruction, from the outermost to the innermost.
 async fn fill_source_line_info<P>(
     frame: &mut StackFrame,
+    memory_list: &UnifiedMemoryList<'_>,
     modules: &MinidumpModuleList,
     symbol_provider: &P,
-) where
+    options: &ProcessorOptions<'_>,
+) -> Vec<StackFrame>
+where
     P: SymbolProvider + Sync,
 {
     // Find the module whose address range covers this frame's instruction.
-    if let Some(module) = modules.module_at_address(frame.instruction) {
-        // FIXME: this shouldn't need to clone, we should be able to use
-        // the same lifetime as the module list that's passed in.
+    if let Some(module) = modules.shared_module_at_address(frame.instruction) {
         frame.module = Some(module.clone());
+        let module = &**module;
+
+        // Past the deadline, don't wait on any more symbols.
+        if options.out_of_time() {
+            return Vec::new();
+        }
+
+        let mut collector = InlineCollector {
+            frame,
+            inlines: Vec::new(),
+        };
+        // This is best effort, so the only thing that matters about errors is
+        // that they mean there are no symbols for the module.
+        let has_symbols = symbol_provider
+            .fill_symbol(module, &mut collector)
+            .await
+            .is_ok();
+        let inlines = collector.inlines;
+
+        // Without symbols, the module's own exports are better than nothing.
+        if !has_symbols && options.symbolicate_from_exports {
+            if let Some(export) = exports::nearest_export(memory_list, module, frame.instruction) {
+                frame.set_function(&export.name, export.address, 0);
+                frame.function_from_exports = true;
+            }
+        }
+        inlines
+    } else {
+        Vec::new()
+    }
+}
+
+/// Copies `regs` from `src` into `dst` wherever only `src` has them.
+fn merge_registers<C: CpuContext>(
+    dst: &mut C,
+    dst_valid: &mut HashSet<&'static str>,
+    src: &C,
+    src_valid: &MinidumpContextValidity,
+    regs: &[&'static str],
+) -> bool {
+    let mut merged = false;
+    for &reg in regs {
+        if dst_valid.contains(reg) {
+            continue;
+        }
+        if let Some(val) = src.get_register(reg, src_valid) {
+            dst.set_register(reg, val);
+            dst_valid.insert(reg);
+            merged = true;
+        }
+    }
+    merged
+}
 
-        // This is best effort, so ignore any errors.
-        let _ = symbol_provider.fill_symbol(module, frame).await;
+/// Fills in the callee-saved registers an exception context is missing from
+/// the context of the thread it happened on.
+///
+/// Exception records don't always capture every register, but the
+/// callee-saved ones can't have changed between the exception and the
+/// thread's context being captured, and the unwinder wants them for the
+/// caller frames. Returns `None` if there was nothing to merge.
+pub(crate) fn merge_contexts(
+    exception: &MinidumpContext,
+    thread: &MinidumpContext,
+) -> Option<MinidumpContext> {
+    let mut valid = match &exception.valid {
+        MinidumpContextValidity::All => return None,
+        MinidumpContextValidity::Some(which) => which.clone(),
+    };
+    let mut merged = exception.clone();
+    let thread_valid = &thread.valid;
+    let changed = match (&mut merged.raw, &thread.raw) {
+        (MinidumpRawContext::Amd64(dst), MinidumpRawContext::Amd64(src)) => {
+            merge_registers(dst, &mut valid, src, thread_valid, amd64::CALLEE_SAVED_REGS)
+        }
+        (MinidumpRawContext::X86(dst), MinidumpRawContext::X86(src)) => {
+            merge_registers(dst, &mut valid, src, thread_valid, x86::CALLEE_SAVED_REGS)
+        }
+        (MinidumpRawContext::Arm(dst), MinidumpRawContext::Arm(src)) => {
+            merge_registers(dst, &mut valid, src, thread_valid, arm::CALLEE_SAVED_REGS)
+        }
+        (MinidumpRawContext::Arm64(dst), MinidumpRawContext::Arm64(src)) => {
+            merge_registers(dst, &mut valid, src, thread_valid, arm64::CALLEE_SAVED_REGS)
+        }
+        (MinidumpRawContext::OldArm64(dst), MinidumpRawContext::OldArm64(src)) => merge_registers(
+            dst,
+            &mut valid,
+            src,
+            thread_valid,
+            arm64_old::CALLEE_SAVED_REGS,
+        ),
+        (MinidumpRawContext::Mips(dst), MinidumpRawContext::Mips(src)) => {
+            merge_registers(dst, &mut valid, src, thread_valid, mips::CALLEE_SAVED_REGS)
+        }
+        (MinidumpRawContext::Mips64(dst), MinidumpRawContext::Mips64(src)) => merge_registers(
+            dst,
+            &mut valid,
+            src,
+            thread_valid,
+            mips64::CALLEE_SAVED_REGS,
+        ),
+        (MinidumpRawContext::Ppc(dst), MinidumpRawContext::Ppc(src)) => {
+            merge_registers(dst, &mut valid, src, thread_valid, ppc::CALLEE_SAVED_REGS)
+        }
+        (MinidumpRawContext::Ppc64(dst), MinidumpRawContext::Ppc64(src)) => {
+            merge_registers(dst, &mut valid, src, thread_valid, ppc64::CALLEE_SAVED_REGS)
+        }
+        (MinidumpRawContext::Riscv64(dst), MinidumpRawContext::Riscv64(src)) => merge_registers(
+            dst,
+            &mut valid,
+            src,
+            thread_valid,
+            riscv64::CALLEE_SAVED_REGS,
+        ),
+        // SPARC's register windows take care of themselves, and contexts
+        // for different cpus can't be merged at all.
+        _ => false,
+    };
+    if !changed {
+        return None;
     }
+    merged.valid = MinidumpContextValidity::Some(valid);
+    Some(merged)
 }
 
 pub async fn walk_stack<P>(
     maybe_context: &Option<&MinidumpContext>,
     stack_memory: Option<&MinidumpMemory<'_>>,
+    memory_list: &UnifiedMemoryList<'_>,
+    memory_info: &UnifiedMemoryInfoList<'_>,
     modules: &MinidumpModuleList,
     symbol_provider: &P,
+    options: &ProcessorOptions<'_>,
 ) -> CallStack
 where
     P: SymbolProvider + Sync,
 {
-    // Begin with the context frame, and keep getting callers until there are
-    // no more.
-    let mut frames = vec![];
-    let mut info = CallStackInfo::Ok;
-    if let Some(context) = *maybe_context {
-        trace!("unwind: starting stack unwind");
-        let ctx = context.clone();
-        let mut maybe_frame = Some(StackFrame::from_context(ctx, FrameTrust::Context));
-        while let Some(mut frame) = maybe_frame {
-            fill_source_line_info(&mut frame, modules, symbol_provider).await;
-            trace!(
-                "unwind: unwinding {}",
-                frame
-                    .function_name
-                    .clone()
-                    .unwrap_or_else(|| frame.instruction.to_string())
-            );
-            frames.push(frame);
-            let callee_frame = &frames.last().unwrap();
-            let grand_callee_frame = frames.len().checked_sub(2).and_then(|idx| frames.get(idx));
-            maybe_frame = get_caller_frame(
-                callee_frame,
-                grand_callee_frame,
-                stack_memory,
-                modules,
-                symbol_provider,
+    walk_thread_stack(
+        0,
+        maybe_context,
+        stack_memory,
+        memory_list,
+        memory_info,
+        modules,
+        symbol_provider,
+        options,
+    )
+    .await
+}
+
+/// Walk a stack that wasn't read out of a minidump.
+///
+/// This is for contexts captured some other way (e.g. by a hang detector
+/// sampling a thread), which only come with a copy of the thread's stack:
+/// `stack` holds the bytes of the stack starting at `stack_base`, and is also
+/// the only memory the unwinders can read. If you do have a minidump,
+/// [`process_minidump`][crate::process_minidump] will do all of this for you.
+///
+/// The returned [`CallStack`] has a `thread_id` of 0 and no thread name,
+/// for the caller to fill in if they know better.
+pub async fn walk_stack_from_context<P>(
+    context: &MinidumpContext,
+    stack_base: u64,
+    stack: &[u8],
+    modules: &MinidumpModuleList,
+    symbol_provider: &P,
+    options: &ProcessorOptions<'_>,
+) -> CallStack
+where
+    P: SymbolProvider + Sync,
+{
+    let stack_memory = MinidumpMemory {
+        desc: Default::default(),
+        base_address: stack_base,
+        size: stack.len() as u64,
+        bytes: stack,
+    };
+    let memory_list =
+        UnifiedMemoryList::Memory(MinidumpMemoryList::from_regions(vec![stack_memory.clone()]));
+    walk_stack(
+        &Some(context),
+        Some(&stack_memory),
+        &memory_list,
+        &UnifiedMemoryInfoList::default(),
+        modules,
+        symbol_provider,
+        options,
+    )
+    .await
+}
+
+/// The same as [`walk_stack`], for the thread with id `thread_id`.
+#[allow(clippy::too_many_arguments)]
+pub(crate) async fn walk_thread_stack<P>(
+    thread_id: u32,
+    maybe_context: &Option<&MinidumpContext>,
+    stack_memory: Option<&MinidumpMemory<'_>>,
+    memory_list: &UnifiedMemoryList<'_>,
+    memory_info: &UnifiedMemoryInfoList<'_>,
+    modules: &MinidumpModuleList,
+    symbol_provider: &P,
+    options: &ProcessorOptions<'_>,
+) -> CallStack
+where
+    P: SymbolProvider + Sync,
+{
+    let mut frames = FrameIterator::new(
+        *maybe_context,
+        stack_memory,
+        memory_list,
+        memory_info,
+        modules,
+        symbol_provider,
+        options,
+    );
+    frames.thread_id = thread_id;
+    while frames.next().await.is_some() {}
+    frames.into_call_stack()
+}
+
+/// Walks a stack a frame at a time, for callers that don't want to wait for
+/// [`walk_stack`] to walk all of it.
+///
+/// Each call to [`FrameIterator::next`] unwinds one more frame, and yields
+/// it after the functions inlined into it (and, with
+/// [`ProcessorOptions::resume_after_gaps`], the marker for any gap before
+/// it), in the same order as the frames of the [`CallStack`] that
+/// [`walk_stack`] would return. A frame is only yielded once its caller has
+/// been found, so it's complete, but repeated frames from runaway recursion
+/// are only collapsed by [`FrameIterator::into_call_stack`].
+///
+/// Stopping early is just not calling `next` any more: `into_call_stack`
+/// then returns the frames so far, with [`CallStackInfo::WalkStopped`].
+pub struct FrameIterator<'a, P> {
+    thread_id: u32,
+    context: Option<&'a MinidumpContext>,
+    stack_memory: Option<&'a MinidumpMemory<'a>>,
+    memory_list: &'a UnifiedMemoryList<'a>,
+    memory_info: &'a UnifiedMemoryInfoList<'a>,
+    modules: &'a MinidumpModuleList,
+    symbol_provider: &'a P,
+    options: &'a ProcessorOptions<'a>,
+    /// Every frame so far, in the order they're yielded.
+    frames: Vec<StackFrame>,
+    /// The indices in `frames` of the physical frames.
+    physical: Vec<usize>,
+    /// How many of `frames` have been yielded.
+    yielded: usize,
+    /// The next physical frame to walk, and the gap skipped to find it, or
+    /// `None` once the walk is over.
+    caller: Option<(StackFrame, Option<StackGap>)>,
+    /// How many gaps have been skipped.
+    gap_count: usize,
+    info: CallStackInfo,
+}
+
+impl<'a, P> FrameIterator<'a, P>
+where
+    P: SymbolProvider + Sync,
+{
+    /// Start walking the stack of `maybe_context`, with the same arguments as
+    /// [`walk_stack`].
+    pub fn new(
+        maybe_context: Option<&'a MinidumpContext>,
+        stack_memory: Option<&'a MinidumpMemory<'a>>,
+        memory_list: &'a UnifiedMemoryList<'a>,
+        memory_info: &'a UnifiedMemoryInfoList<'a>,
+        modules: &'a MinidumpModuleList,
+        symbol_provider: &'a P,
+        options: &'a ProcessorOptions<'a>,
+    ) -> Self {
+        let caller = maybe_context.map(|context| {
+            trace!("unwind: starting stack unwind");
+            let ctx = context.clone();
+            (StackFrame::from_context(ctx, FrameTrust::Context), None)
+        });
+        let info = match caller {
+            Some(_) => CallStackInfo::Ok,
+            None => CallStackInfo::MissingContext,
+        };
+        FrameIterator {
+            thread_id: 0,
+            context: maybe_context,
+            stack_memory,
+            memory_list,
+            memory_info,
+            modules,
+            symbol_provider,
+            options,
+            frames: Vec::new(),
+            physical: Vec::new(),
+            yielded: 0,
+            caller,
+            gap_count: 0,
+            info,
+        }
+    }
+
+    /// The next frame of the stack, or `None` once there are no more.
+    pub async fn next(&mut self) -> Option<&StackFrame> {
+        if self.yielded == self.frames.len() {
+            self.walk_frame().await;
+        }
+        let frame = self.frames.get(self.yielded)?;
+        self.yielded += 1;
+        Some(frame)
+    }
+
+    /// The stack, with the frames walked so far.
+    pub fn into_call_stack(self) -> CallStack {
+        let info = match self.caller {
+            Some(_) => CallStackInfo::WalkStopped,
+            None => self.info,
+        };
+        let (frames, recursion_collapsed) = collapse_recursion(self.frames);
+        CallStack {
+            frames,
+            info,
+            thread_id: self.thread_id,
+            thread_name: None,
+            last_error_value: None,
+            recursion_collapsed,
+            context: self.context.cloned(),
+            priority_class: 0,
+            priority: 0,
+            kernel_time: None,
+            user_time: None,
+            start_address: None,
+        }
+    }
+
+    /// Symbolizes the next physical frame and finds its caller, adding it
+    /// (after the frames inlined into it) to `frames`.
+    async fn walk_frame(&mut self) {
+        let (mut frame, gap) = match self.caller.take() {
+            Some(caller) => caller,
+            None => return,
+        };
+        let options = self.options;
+        // Everything the stackwalker does for this frame (symbolizing it
+        // and finding its caller) goes in its span.
+        let span = trace_span!(
+            "frame",
+            index = self.physical.len(),
+            instruction = frame.instruction,
+            trust = frame.trust.description(),
+        );
+        let inlines = fill_source_line_info(
+            &mut frame,
+            self.memory_list,
+            self.modules,
+            self.symbol_provider,
+            options,
+        )
+        .instrument(span.clone())
+        .await;
+        if frame.module.is_none() {
+            frame.is_jit = is_jit_code(frame.instruction, self.memory_info);
+        }
+        trace!(
+            "unwind: unwinding {}",
+            frame
+                .function_name
+                .clone()
+                .unwrap_or_else(|| frame.instruction.to_string())
+        );
+        let mut stopped = None;
+        if let Some(ref observer) = options.frame_observer {
+            if observer.on_frame(self.thread_id, &frame) == WalkControl::Stop {
+                trace!("unwind: stopped by the frame observer");
+                stopped = Some(CallStackInfo::WalkStopped);
+            }
+        }
+        if stopped.is_none() && options.out_of_time() {
+            trace!("unwind: out of time");
+            stopped = Some(CallStackInfo::TimedOut);
+        }
+
+        // The inlined frames go in above the physical frame, innermost
+        // first, after a marker for any gap below it. The unwinders only
+        // see the physical frames.
+        if let Some(gap) = gap {
+            let mut marker = StackFrame::from_context(frame.context.clone(), FrameTrust::None);
+            marker.instruction = 0;
+            marker.resume_address = 0;
+            marker.gap = Some(gap);
+            self.frames.push(marker);
+            self.gap_count += 1;
+        }
+        self.frames.extend(inlines.into_iter().rev());
+        let callee_index = self.frames.len();
+        self.physical.push(callee_index);
+        self.frames.push(frame);
+        if let Some(info) = stopped {
+            self.finish(info);
+            return;
+        }
+
+        let callee_frame = &self.frames[callee_index];
+        let grand_callee_frame = self
+            .physical
+            .len()
+            .checked_sub(2)
+            .map(|idx| &self.frames[self.physical[idx]]);
+        let mut recorder = UnwindRecorder::new(options);
+        let mut caller = get_caller_frame(
+            callee_frame,
+            grand_callee_frame,
+            self.stack_memory,
+            self.memory_list,
+            self.memory_info,
+            self.modules,
+            self.symbol_provider,
+            options,
+            &mut recorder,
+        )
+        .instrument(span.clone())
+        .await;
+        self.frames[callee_index].unwind_diagnostics = recorder.finish(caller.is_some());
+        let mut gap = None;
+        if caller.is_none() && options.resume_after_gaps && self.gap_count < MAX_STACK_GAPS {
+            if let Some((found, skipped)) = find_caller_past_gap(
+                &self.frames[callee_index],
+                self.stack_memory,
+                self.memory_info,
+                self.modules,
+                self.symbol_provider,
+                options,
             )
-            .await;
+            .instrument(span)
+            .await
+            {
+                caller = Some(found);
+                gap = Some(skipped);
+            }
+        }
+        match caller {
+            Some(_) if self.physical.len() >= options.max_frames => {
+                trace!("unwind: giving up after {} frames", self.physical.len());
+                self.finish(CallStackInfo::FrameLimitReached);
+            }
+            Some(caller) => self.caller = Some((caller, gap)),
+            None => self.finish(CallStackInfo::Ok),
         }
+    }
+
+    fn finish(&mut self, info: CallStackInfo) {
         trace!("unwind: finished stack unwind\n");
+        self.caller = None;
+        self.info = info;
+    }
+}
+
+/// The longest pattern of frames that `collapse_recursion` looks for.
+const MAX_RECURSION_PERIOD: usize = 32;
+/// How many frames a pattern must repeat over before it gets collapsed.
+const MIN_RECURSION_FRAMES: usize = 200;
+
+/// Replaces long runs of a repeating pattern of frames with a single marker
+/// frame, keeping the first and last repetitions. Returns whether anything was
+/// collapsed.
+///
+/// Stack overflows from runaway recursion otherwise produce thousands of
+/// identical frames that bury everything else in the stack.
+fn collapse_recursion(frames: Vec<StackFrame>) -> (Vec<StackFrame>, bool) {
+    // Frames are compared by where they are in their module (so that this
+    // still works if a module's address is randomized), and by function name
+    // so that inlined frames don't match the physical frame they're in.
+    let keys: Vec<_> = frames
+        .iter()
+        .map(|frame| {
+            let (module, offset) = match frame.module {
+                Some(ref module) => (
+                    Some(module.code_file()),
+                    frame.instruction.wrapping_sub(module.base_address()),
+                ),
+                None => (None, frame.instruction),
+            };
+            (module, offset, frame.function_name.as_deref())
+        })
+        .collect();
+
+    // Find the runs to collapse as (start, period, length).
+    let mut runs = vec![];
+    let mut start = 0;
+    while start < keys.len() {
+        let run = (1..=MAX_RECURSION_PERIOD).find_map(|period| {
+            let repeats = (start..keys.len().saturating_sub(period))
+                .take_while(|&i| keys[i] == keys[i + period])
+                .count();
+            let len = repeats + period;
+            // Keep the first and last repetitions, so there needs to be at
+            // least one more in between to leave out.
+            if len >= MIN_RECURSION_FRAMES && len / period > 2 {
+                Some((period, len))
+            } else {
+                None
+            }
+        });
+        match run {
+            Some((period, len)) => {
+                runs.push((start, period, len));
+                start += len;
+            }
+            None => start += 1,
+        }
+    }
+
+    if runs.is_empty() {
+        return (frames, false);
+    }
+
+    let mut collapsed = Vec::with_capacity(frames.len());
+    let mut runs = runs.into_iter().peekable();
+    let mut frames = frames.into_iter().enumerate();
+    while let Some((i, frame)) = frames.next() {
+        match runs.peek() {
+            Some(&(start, period, len)) if i == start + period => {
+                runs.next();
+                let count = (len / period - 2) * period;
+                trace!(
+                    "unwind: collapsing {} frames repeating a pattern of {}",
+                    count,
+                    period
+                );
+                let mut marker = frame;
+                marker.repeated = Some(RepeatedFrames { count, period });
+                collapsed.push(marker);
+                frames.by_ref().take(count - 1).for_each(drop);
+            }
+            _ => collapsed.push(frame),
+        }
+    }
+    (collapsed, true)
+}
+
+/// How many pointer-sized values a stack scan for `callee`'s caller looks through.
+fn scan_range(callee: &StackFrame, options: &ProcessorOptions<'_>) -> usize {
+    // Breakpad devs found that the first frame of an unwind can be really messed up,
+    // and therefore benefits from a longer scan. Let's do it too.
+    if callee.trust == FrameTrust::Context && options.extended_context_scan {
+        options.scan_depth.saturating_mul(4)
     } else {
-        info = CallStackInfo::MissingContext;
+        options.scan_depth
     }
-    CallStack {
-        frames,
-        info,
-        thread_id: 0,
-        thread_name: None,
-        last_error_value: None,
+}
+
+/// The most gaps [`ProcessorOptions::resume_after_gaps`] skips in one stack.
+const MAX_STACK_GAPS: usize = 4;
+
+/// Looks further up the stack for a caller of `callee` than its unwinder
+/// did, for when there was no finding one because the stack in between is
+/// missing or trashed.
+///
+/// This is a stack scan, like the unwinders' last resort, but it starts where
+/// theirs stopped, goes on for up to [`ProcessorOptions::max_stack_gap`]
+/// bytes, and only accepts return addresses in modules. It's only done on
+/// x86, x86-64, ARM and ARM64, where return addresses end up on the stack.
+/// Returns the caller, and the part of the stack that was skipped to find it.
+async fn find_caller_past_gap<P>(
+    callee: &StackFrame,
+    stack_memory: Option<&MinidumpMemory<'_>>,
+    memory_info: &UnifiedMemoryInfoList<'_>,
+    modules: &MinidumpModuleList,
+    symbol_provider: &P,
+    options: &ProcessorOptions<'_>,
+) -> Option<(StackFrame, StackGap)>
+where
+    P: SymbolProvider + Sync,
+{
+    let stack_memory = stack_memory?;
+    let (pointer_width, pac_mask): (u64, _) = match callee.context.raw {
+        MinidumpRawContext::Arm64(_) | MinidumpRawContext::OldArm64(_) => {
+            (8, options.arm64_pac_mask)
+        }
+        MinidumpRawContext::Amd64(_) => (8, None),
+        MinidumpRawContext::X86(_) | MinidumpRawContext::Arm(_) => (4, None),
+        _ => return None,
+    };
+    let stack_start = stack_memory.base_address;
+    let stack_end = stack_start.checked_add(stack_memory.size)?;
+
+    // Skip what the unwinder's own scan looked through. A stack pointer
+    // that's not in the stack is trashed too, so then the whole stack is fair
+    // game.
+    let sp = callee.context.get_stack_pointer();
+    let (gap_start, scan_start) = if (stack_start..stack_end).contains(&sp) {
+        let scanned = if options.allow_scan {
+            scan_range(callee, options) as u64 * pointer_width
+        } else {
+            0
+        };
+        (sp, sp.saturating_add(scanned))
+    } else {
+        (stack_start, stack_start)
+    };
+    let scan_start = scan_start.checked_add(pointer_width - 1)? & !(pointer_width - 1);
+    let scan_end = scan_start
+        .saturating_add(options.max_stack_gap)
+        .min(stack_end);
+
+    let mut address = scan_start;
+    while address.checked_add(pointer_width)? <= scan_end {
+        let value = if pointer_width == 8 {
+            stack_memory.get_memory_at_address::<u64>(address)
+        } else {
+            stack_memory
+                .get_memory_at_address::<u32>(address)
+                .map(u64::from)
+        };
+        let return_address = value.map(|value| strip_pac_mask(pac_mask, value));
+        if let Some(return_address) = return_address {
+            if modules.module_at_address(return_address).is_some()
+                && instruction_seems_valid_by_symbols(
+                    return_address,
+                    memory_info,
+                    modules,
+                    symbol_provider,
+                )
+                .await
+            {
+                trace!(
+                    "unwind: resuming past a gap at 0x{:08x}, caller_ip: 0x{:08x}",
+                    address,
+                    return_address,
+                );
+                let caller_sp = address + pointer_width;
+                let caller = resumed_frame(&callee.context, return_address, caller_sp)?;
+                let gap = StackGap {
+                    start: gap_start,
+                    end: address,
+                };
+                return Some((caller, gap));
+            }
+        }
+        address += pointer_width;
     }
+    None
 }
 
-/// Checks if we can dismiss the validity of an instruction based on our symbols,
-/// to refine the quality of each unwinder's instruction_seems_valid implementation.
+/// Remove the arm64 pointer authentication signature from `ptr`, if
+/// [`ProcessorOptions::arm64_pac_mask`] (passed as `pac_mask`) says which bits
+/// hold it.
+fn strip_pac_mask(pac_mask: Option<u64>, ptr: u64) -> u64 {
+    match pac_mask {
+        Some(mask) => ptr & !mask,
+        None => ptr,
+    }
+}
+
+/// The frame of a caller that returns to `return_address`, with its stack
+/// pointer at `sp` and every other register unknown.
+fn resumed_frame(callee: &MinidumpContext, return_address: u64, sp: u64) -> Option<StackFrame> {
+    let mut raw = callee.raw.clone();
+    // The size of the call instruction to back up over, see
+    // `StackFrame::instruction`.
+    let (pc_name, sp_name, call_size) = match raw {
+        MinidumpRawContext::Amd64(ref mut ctx) => {
+            ctx.set_register("rip", return_address)?;
+            ctx.set_register("rsp", sp)?;
+            ("rip", "rsp", 1)
+        }
+        MinidumpRawContext::X86(ref mut ctx) => {
+            ctx.set_register("eip", return_address as u32)?;
+            ctx.set_register("esp", sp as u32)?;
+            ("eip", "esp", 1)
+        }
+        MinidumpRawContext::Arm64(ref mut ctx) => {
+            ctx.set_register("pc", return_address)?;
+            ctx.set_register("sp", sp)?;
+            ("pc", "sp", 4)
+        }
+        MinidumpRawContext::OldArm64(ref mut ctx) => {
+            ctx.set_register("pc", return_address)?;
+            ctx.set_register("sp", sp)?;
+            ("pc", "sp", 4)
+        }
+        MinidumpRawContext::Arm(ref mut ctx) => {
+            ctx.set_register("pc", return_address as u32)?;
+            ctx.set_register("sp", sp as u32)?;
+            ("pc", "sp", 2)
+        }
+        _ => return None,
+    };
+    let mut valid = HashSet::new();
+    valid.insert(pc_name);
+    valid.insert(sp_name);
+    let context = MinidumpContext {
+        raw,
+        valid: MinidumpContextValidity::Some(valid),
+    };
+    let mut frame = StackFrame::from_context(context, FrameTrust::Scan);
+    // The Thumb bit has already been cleared from an ARM instruction.
+    frame.instruction = frame.instruction.checked_sub(call_size)?;
+    Some(frame)
+}
+
+/// Checks if we can dismiss the validity of an instruction based on our symbols
+/// and memory info, to refine the quality of each unwinder's
+/// instruction_seems_valid implementation.
 async fn instruction_seems_valid_by_symbols<P>(
     instruction: u64,
+    memory_info: &UnifiedMemoryInfoList<'_>,
     modules: &MinidumpModuleList,
     symbol_provider: &P,
 ) -> bool
 where
     P: SymbolProvider + Sync,
 {
+    // If we know what kind of memory the instruction is in, it had better be
+    // executable. This keeps scans from picking up pointers to data that just
+    // happens to be in a module, or on the heap.
+    if let Some(region) = memory_info.region_containing(instruction) {
+        if !region.is_executable() {
+            return false;
+        }
+    }
+
     if let Some(module) = modules.module_at_address(instruction as u64) {
         // Create a dummy frame symbolizing implementation to feed into
         // our symbol provider with the address we're interested in. If
         // it tries to set a non-empty function name, then we can reasonably
         // assume the instruction address is valid.
-        use crate::FrameSymbolizer;
-
         struct DummyFrame {
             instruction: u64,
             has_name: bool,
@@ -272,16 +1200,52 @@ where
             true
         }
     } else {
-        // We couldn't even map this address to a module. Reject the pointer
-        // so that we have *some* way to distinguish "normal" pointers
-        // from instruction address.
-        //
-        // FIXME: this will reject any pointer into JITed code which otherwise
-        // isn't part of a normal well-defined module. We can potentially use
-        // MemoryInfoListStream (windows) and /proc/self/maps (linux) to refine
-        // this analysis and allow scans to walk through JITed code.
-        false
+        // We couldn't even map this address to a module. Only accept the
+        // pointer if it points into code that was generated at runtime, so
+        // that we have *some* way to distinguish "normal" pointers from
+        // instruction addresses.
+        is_jit_code(instruction, memory_info)
+    }
+}
+
+/// Checks if `instruction` is in executable memory that doesn't belong to any
+/// mapped file, which is where JIT compilers put the code they generate.
+fn is_jit_code(instruction: u64, memory_info: &UnifiedMemoryInfoList<'_>) -> bool {
+    memory_info.is_executable(instruction) && memory_info.is_anonymous(instruction)
+}
+
+/// Looks up the start of the function containing `instruction` in our symbols.
+async fn function_base_by_symbols<P>(
+    instruction: u64,
+    modules: &MinidumpModuleList,
+    symbol_provider: &P,
+) -> Option<u64>
+where
+    P: SymbolProvider + Sync,
+{
+    struct DummyFrame {
+        instruction: u64,
+        function_base: Option<u64>,
+    }
+    impl FrameSymbolizer for DummyFrame {
+        fn get_instruction(&self) -> u64 {
+            self.instruction
+        }
+        fn set_function(&mut self, _name: &str, base: u64, _parameter_size: u32) {
+            self.function_base = Some(base);
+        }
+        fn set_source_file(&mut self, _file: &str, _line: u32, _base: u64) {
+            // Do nothing
+        }
     }
+
+    let module = modules.module_at_address(instruction)?;
+    let mut frame = DummyFrame {
+        instruction,
+        function_base: None,
+    };
+    symbol_provider.fill_symbol(module, &mut frame).await.ok()?;
+    frame.function_base
 }
 
 #[cfg(test)]
@@ -291,4 +1255,16 @@ mod arm64_unittest;
 #[cfg(test)]
 mod arm_unittest;
 #[cfg(test)]
+mod mips64_unittest;
+#[cfg(test)]
+mod mips_unittest;
+#[cfg(test)]
+mod ppc64_unittest;
+#[cfg(test)]
+mod ppc_unittest;
+#[cfg(test)]
+mod riscv64_unittest;
+#[cfg(test)]
+mod sparc_unittest;
+#[cfg(test)]
 mod x86_unittest;
@@ -0,0 +1,246 @@
+// Copyright 2015 Ted Mielczarek. See the COPYRIGHT
+// file at the top-level directory of this distribution.
+
+//! Hooks for watching the stackwalker work.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use instant::Instant;
+use minidump::Module;
+
+use crate::process_state::{CallStack, StackFrame};
+use crate::system_info::SystemInfo;
+use crate::{FillSymbolError, FrameSymbolizer, FrameWalker, SymbolProvider, SymbolStats};
+
+/// Whether a [`FrameObserver`] wants the stackwalker to keep going.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WalkControl {
+    /// Go on to look for the frame's caller.
+    Continue,
+    /// Stop walking this thread's stack, keeping the frames found so far.
+    Stop,
+}
+
+/// Something that gets told about every frame the stackwalker finds.
+///
+/// Set one in [`ProcessorOptions::frame_observer`][crate::ProcessorOptions::frame_observer]
+/// to report progress or cut walks short. It's called with each physical
+/// frame as soon as it's been symbolicated, before its caller is looked for;
+/// how the frame was found is in its `trust`. Threads may be walked
+/// concurrently, so frames of different threads can be interleaved.
+///
+/// This is implemented for closures taking the same arguments as
+/// [`FrameObserver::on_frame`].
+pub trait FrameObserver: Send + Sync {
+    /// Called with each frame of the thread with id `thread_id`.
+    fn on_frame(&self, thread_id: u32, frame: &StackFrame) -> WalkControl;
+}
+
+impl<F> FrameObserver for F
+where
+    F: Fn(u32, &StackFrame) -> WalkControl + Send + Sync,
+{
+    fn on_frame(&self, thread_id: u32, frame: &StackFrame) -> WalkControl {
+        self(thread_id, frame)
+    }
+}
+
+impl fmt::Debug for dyn FrameObserver + '_ {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("FrameObserver")
+    }
+}
+
+/// Something that gets told about every thread once its stack has been
+/// walked.
+///
+/// Set one in [`ProcessorOptions::thread_observer`][crate::ProcessorOptions::thread_observer]
+/// to deal with each thread as soon as it's done, rather than once the whole
+/// minidump has been processed, like [`JsonStreamWriter`][crate::JsonStreamWriter]
+/// does. Threads are reported in the order they're in the minidump, even if
+/// they're walked concurrently.
+///
+/// This is implemented for closures taking the same arguments as
+/// [`ThreadObserver::on_thread`].
+pub trait ThreadObserver: Send + Sync {
+    /// Called with the stack of the thread at `index` in
+    /// [`ProcessState::threads`][crate::ProcessState::threads], and the
+    /// system the minidump is from.
+    fn on_thread(&self, system_info: &SystemInfo, index: usize, stack: &CallStack);
+}
+
+impl<F> ThreadObserver for F
+where
+    F: Fn(&SystemInfo, usize, &CallStack) + Send + Sync,
+{
+    fn on_thread(&self, system_info: &SystemInfo, index: usize, stack: &CallStack) {
+        self(system_info, index, stack)
+    }
+}
+
+impl fmt::Debug for dyn ThreadObserver + '_ {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ThreadObserver")
+    }
+}
+
+/// A step of processing, reported to a [`ProgressObserver`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ProgressEvent {
+    /// The minidump's streams have been read, and the stacks of its threads
+    /// are about to be walked.
+    StreamsRead {
+        /// How many threads the minidump has.
+        thread_count: usize,
+        /// How many modules the minidump has.
+        module_count: usize,
+    },
+    /// The processor started walking the stack of the thread at `index` in
+    /// [`ProcessState::threads`][crate::ProcessState::threads].
+    ThreadStarted {
+        index: usize,
+        thread_id: u32,
+        thread_count: usize,
+    },
+    /// The processor finished walking the stack of the thread at `index`.
+    ThreadFinished {
+        index: usize,
+        thread_id: u32,
+        thread_count: usize,
+    },
+    /// The symbol provider was asked for the symbols of a module for the
+    /// first time.
+    SymbolsFetched {
+        /// The module's code file.
+        module: String,
+        /// Whether the provider had symbols for it.
+        found: bool,
+    },
+}
+
+/// Something that gets told how processing is coming along.
+///
+/// Set one in [`ProcessorOptions::progress_observer`][crate::ProcessorOptions::progress_observer]
+/// to show a progress bar or report progress elsewhere. Threads may be
+/// walked concurrently, so the events of different threads can be
+/// interleaved, but each thread's [`ProgressEvent::ThreadFinished`] is
+/// reported in the order they're in the minidump, like with a
+/// [`ThreadObserver`].
+///
+/// This is implemented for closures taking the same arguments as
+/// [`ProgressObserver::on_progress`].
+pub trait ProgressObserver: Send + Sync {
+    /// Called with each step of processing.
+    fn on_progress(&self, event: &ProgressEvent);
+}
+
+impl<F> ProgressObserver for F
+where
+    F: Fn(&ProgressEvent) + Send + Sync,
+{
+    fn on_progress(&self, event: &ProgressEvent) {
+        self(event)
+    }
+}
+
+impl fmt::Debug for dyn ProgressObserver + '_ {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ProgressObserver")
+    }
+}
+
+/// Wraps a [`SymbolProvider`], telling a [`ProgressObserver`] the first time
+/// each module's symbols are asked for, and (if asked to) timing how long
+/// getting each module's symbols takes.
+pub(crate) struct ProgressReporter<'a, P> {
+    provider: &'a P,
+    observer: Option<Arc<dyn ProgressObserver>>,
+    /// The code files of the modules already reported.
+    seen: Mutex<HashSet<String>>,
+    /// The time spent getting each module's symbols, by code file, if
+    /// they're being timed.
+    symbol_times: Option<Mutex<BTreeMap<String, Duration>>>,
+}
+
+impl<'a, P> ProgressReporter<'a, P> {
+    pub(crate) fn new(
+        provider: &'a P,
+        observer: Option<Arc<dyn ProgressObserver>>,
+        record_timings: bool,
+    ) -> Self {
+        Self {
+            provider,
+            observer,
+            seen: Mutex::default(),
+            symbol_times: record_timings.then(Mutex::default),
+        }
+    }
+
+    /// The time spent getting each module's symbols so far, by code file.
+    pub(crate) fn symbol_times(&self) -> BTreeMap<String, Duration> {
+        self.symbol_times
+            .as_ref()
+            .map(|times| times.lock().unwrap().clone())
+            .unwrap_or_default()
+    }
+
+    fn add_symbol_time(&self, module: &(dyn Module + Sync), started: Instant) {
+        if let Some(ref times) = self.symbol_times {
+            let elapsed = started.elapsed();
+            *times
+                .lock()
+                .unwrap()
+                .entry(module.code_file().into_owned())
+                .or_default() += elapsed;
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl<'a, P> SymbolProvider for ProgressReporter<'a, P>
+where
+    P: SymbolProvider + Sync,
+{
+    async fn fill_symbol(
+        &self,
+        module: &(dyn Module + Sync),
+        frame: &mut (dyn FrameSymbolizer + Send),
+    ) -> Result<(), FillSymbolError> {
+        let started = Instant::now();
+        let result = self.provider.fill_symbol(module, frame).await;
+        self.add_symbol_time(module, started);
+        if let Some(ref observer) = self.observer {
+            let code_file = module.code_file().into_owned();
+            if self.seen.lock().unwrap().insert(code_file.clone()) {
+                observer.on_progress(&ProgressEvent::SymbolsFetched {
+                    module: code_file,
+                    found: result.is_ok(),
+                });
+            }
+        }
+        result
+    }
+
+    async fn walk_frame(
+        &self,
+        module: &(dyn Module + Sync),
+        walker: &mut (dyn FrameWalker + Send),
+    ) -> Option<()> {
+        let started = Instant::now();
+        let result = self.provider.walk_frame(module, walker).await;
+        self.add_symbol_time(module, started);
+        result
+    }
+
+    fn stats(&self) -> HashMap<String, SymbolStats> {
+        self.provider.stats()
+    }
+
+    async fn prefetch(&self, modules: &[&(dyn Module + Sync)], concurrency: usize) {
+        self.provider.prefetch(modules, concurrency).await
+    }
+}
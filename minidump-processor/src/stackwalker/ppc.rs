@@ -0,0 +1,402 @@
+// Copyright 2015 Ted Mielczarek. See the COPYRIGHT
+// file at the top-level directory of this distribution.
+
+use crate::process_state::{FrameTrust, StackFrame, UnwindStrategy};
+use crate::stackwalker::unwind::Unwind;
+use crate::stackwalker::{CfiStackWalker, UnwindRecorder};
+use crate::{ProcessorOptions, SymbolProvider};
+use minidump::{
+    CpuContext, MinidumpContext, MinidumpContextValidity, MinidumpMemory, MinidumpModuleList,
+    MinidumpRawContext, UnifiedMemoryInfoList, UnifiedMemoryList,
+};
+use std::collections::HashSet;
+use tracing::trace;
+
+type PpcContext = minidump::format::CONTEXT_PPC;
+type Pointer = <PpcContext as CpuContext>::Register;
+type Registers = minidump::format::PpcRegisterNumbers;
+
+const POINTER_WIDTH: Pointer = std::mem::size_of::<Pointer>() as Pointer;
+const STACK_POINTER: &str = Registers::StackPointer.name();
+const PROGRAM_COUNTER: &str = "srr0";
+const LINK_REGISTER: &str = "lr";
+pub(super) const CALLEE_SAVED_REGS: &[&str] = &[
+    "r13", "r14", "r15", "r16", "r17", "r18", "r19", "r20", "r21", "r22", "r23", "r24", "r25",
+    "r26", "r27", "r28", "r29", "r30", "r31",
+];
+
+/// Offsets from the caller's stack pointer where a callee may have saved the
+/// link register, in order of preference: Darwin (and Breakpad) use the third
+/// slot of the caller's frame, SysV/EABI use the second.
+const SAVED_LR_OFFSETS: &[Pointer] = &[2 * POINTER_WIDTH, POINTER_WIDTH];
+
+async fn get_caller_by_cfi<P>(
+    ctx: &PpcContext,
+    callee: &StackFrame,
+    grand_callee: Option<&StackFrame>,
+    stack_memory: &MinidumpMemory<'_>,
+    modules: &MinidumpModuleList,
+    symbol_provider: &P,
+    recorder: &mut UnwindRecorder,
+) -> Option<StackFrame>
+where
+    P: SymbolProvider + Sync,
+{
+    trace!("unwind: trying cfi");
+    let valid = &callee.context.valid;
+    let _last_sp = ctx.get_register(STACK_POINTER, valid)?;
+    let module = modules.module_at_address(callee.instruction)?;
+    let grand_callee_parameter_size = grand_callee.and_then(|f| f.parameter_size).unwrap_or(0);
+
+    let mut stack_walker = CfiStackWalker {
+        instruction: callee.instruction,
+        grand_callee_parameter_size,
+
+        callee_ctx: ctx,
+        callee_validity: valid,
+
+        // Default to forwarding all callee-saved regs verbatim.
+        // The CFI evaluator may clear or overwrite these values.
+        // The stack pointer and instruction pointer are not included.
+        caller_ctx: ctx.clone(),
+        caller_validity: callee_forwarded_regs(valid),
+
+        stack_memory,
+        modules,
+        rejected_reads: Default::default(),
+    };
+
+    let walked = symbol_provider.walk_frame(module, &mut stack_walker).await;
+    recorder.cfi_rejected_reads(&stack_walker);
+    if walked.is_none() {
+        recorder.reject("no cfi could be evaluated for the instruction");
+        return None;
+    }
+    let caller_pc = stack_walker.caller_ctx.get_register_always(PROGRAM_COUNTER);
+    let caller_sp = stack_walker.caller_ctx.get_register_always(STACK_POINTER);
+
+    trace!(
+        "unwind: cfi evaluation was successful -- caller_pc: 0x{:08x}, caller_sp: 0x{:08x}",
+        caller_pc,
+        caller_sp,
+    );
+
+    // Like ARM, do no further validation of the CFI results.
+
+    let context = MinidumpContext {
+        raw: MinidumpRawContext::Ppc(stack_walker.caller_ctx),
+        valid: MinidumpContextValidity::Some(stack_walker.caller_validity),
+    };
+    Some(StackFrame::from_context(context, FrameTrust::CallFrameInfo))
+}
+
+fn callee_forwarded_regs(valid: &MinidumpContextValidity) -> HashSet<&'static str> {
+    match valid {
+        MinidumpContextValidity::All => CALLEE_SAVED_REGS.iter().copied().collect(),
+        MinidumpContextValidity::Some(ref which) => CALLEE_SAVED_REGS
+            .iter()
+            .filter(|&reg| which.contains(reg))
+            .copied()
+            .collect(),
+    }
+}
+
+fn get_caller_by_back_chain<P>(
+    ctx: &PpcContext,
+    callee: &StackFrame,
+    stack_memory: &MinidumpMemory<'_>,
+    modules: &MinidumpModuleList,
+    _symbol_provider: &P,
+    recorder: &mut UnwindRecorder,
+) -> Option<StackFrame>
+where
+    P: SymbolProvider + Sync,
+{
+    trace!("unwind: trying back chain");
+    // PPC has no dedicated frame pointer. Instead every function that allocates
+    // a frame does so by decrementing r1 and storing the old value of r1 at the
+    // new top of the stack, forming a linked list of frames (the "back chain"):
+    //
+    // *(sp) := caller_sp
+    //
+    // The return address isn't saved in the callee's frame, but in a slot the
+    // caller reserves for it in its own frame, so we restore the caller with:
+    //
+    // sp := *(sp)
+    // pc := *(caller_sp + offset)
+    //
+    // where the offset depends on the ABI (see SAVED_LR_OFFSETS). We pick the
+    // first offset which contains an address inside a module.
+    let valid = &callee.context.valid;
+    let last_sp = ctx.get_register(STACK_POINTER, valid)?;
+
+    let caller_sp: Pointer = recorder.read(stack_memory, last_sp as u64)?;
+    if caller_sp <= last_sp {
+        // The back chain must move up the stack. This catches the terminating
+        // null pointer as well.
+        recorder.reject("back chain went backwards");
+        return None;
+    }
+
+    let caller_pc = SAVED_LR_OFFSETS.iter().find_map(|&offset| {
+        let address_of_pc = caller_sp.checked_add(offset)?;
+        let caller_pc: Pointer = recorder.read(stack_memory, address_of_pc as u64)?;
+        modules
+            .module_at_address(caller_pc as u64)
+            .map(|_| caller_pc)
+    })?;
+
+    trace!(
+        "unwind: back chain seems valid -- caller_pc: 0x{:08x}, caller_sp: 0x{:08x}",
+        caller_pc,
+        caller_sp,
+    );
+
+    let mut caller_ctx = PpcContext::default();
+    caller_ctx.set_register(PROGRAM_COUNTER, caller_pc);
+    caller_ctx.set_register(STACK_POINTER, caller_sp);
+
+    let mut valid = HashSet::new();
+    valid.insert(PROGRAM_COUNTER);
+    valid.insert(STACK_POINTER);
+
+    let context = MinidumpContext {
+        raw: MinidumpRawContext::Ppc(caller_ctx),
+        valid: MinidumpContextValidity::Some(valid),
+    };
+    Some(StackFrame::from_context(context, FrameTrust::FramePointer))
+}
+
+/// The ABI terminates the back chain with a null pointer in the outermost frame.
+fn back_chain_terminated(
+    ctx: &PpcContext,
+    callee: &StackFrame,
+    stack_memory: &MinidumpMemory<'_>,
+) -> bool {
+    ctx.get_register(STACK_POINTER, &callee.context.valid)
+        .and_then(|sp| stack_memory.get_memory_at_address::<Pointer>(sp as u64))
+        == Some(0)
+}
+
+fn get_caller_by_link_register<P>(
+    ctx: &PpcContext,
+    callee: &StackFrame,
+    _stack_memory: &MinidumpMemory<'_>,
+    modules: &MinidumpModuleList,
+    _symbol_provider: &P,
+    recorder: &mut UnwindRecorder,
+) -> Option<StackFrame>
+where
+    P: SymbolProvider + Sync,
+{
+    trace!("unwind: trying link register");
+    // A leaf function may not set up a frame at all, in which case the return
+    // address is still in lr and the stack pointer is unchanged. lr is only
+    // meaningful for the context frame, as it's clobbered by every call.
+    if callee.trust != FrameTrust::Context {
+        return None;
+    }
+    let valid = &callee.context.valid;
+    let caller_pc = ctx.get_register(LINK_REGISTER, valid)?;
+    let caller_sp = ctx.get_register(STACK_POINTER, valid)?;
+    if modules.module_at_address(caller_pc as u64).is_none() {
+        recorder.reject("link register isn't in a module");
+        return None;
+    }
+
+    trace!(
+        "unwind: link register seems valid -- caller_pc: 0x{:08x}, caller_sp: 0x{:08x}",
+        caller_pc,
+        caller_sp,
+    );
+
+    // The callee hasn't touched the stack, so the callee-saved registers
+    // are still the caller's.
+    let mut caller_ctx = ctx.clone();
+    caller_ctx.set_register(PROGRAM_COUNTER, caller_pc);
+
+    let mut valid = callee_forwarded_regs(valid);
+    valid.insert(PROGRAM_COUNTER);
+    valid.insert(STACK_POINTER);
+
+    let context = MinidumpContext {
+        raw: MinidumpRawContext::Ppc(caller_ctx),
+        valid: MinidumpContextValidity::Some(valid),
+    };
+    Some(StackFrame::from_context(context, FrameTrust::FramePointer))
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn get_caller_by_scan<P>(
+    ctx: &PpcContext,
+    callee: &StackFrame,
+    stack_memory: &MinidumpMemory<'_>,
+    memory_info: &UnifiedMemoryInfoList<'_>,
+    modules: &MinidumpModuleList,
+    symbol_provider: &P,
+    options: &ProcessorOptions<'_>,
+    recorder: &mut UnwindRecorder,
+) -> Option<StackFrame>
+where
+    P: SymbolProvider + Sync,
+{
+    trace!("unwind: trying scan");
+    // Stack scanning is just walking from the end of the frame until we encounter
+    // a value on the stack that looks like a pointer into some code (it's an address
+    // in a range covered by one of our modules). If we find such a value, we assume
+    // it's a saved lr, and that the next frame ends just before it.
+    let valid = &callee.context.valid;
+    let last_sp = ctx.get_register(STACK_POINTER, valid)?;
+
+    // Number of pointer-sized values to scan through in our search.
+    let scan_range = super::scan_range(callee, options) as Pointer;
+
+    for i in 0..scan_range {
+        let address_of_pc = last_sp.checked_add(i * POINTER_WIDTH)?;
+        let caller_pc = recorder.read(stack_memory, address_of_pc as u64)?;
+        if instruction_seems_valid(caller_pc, memory_info, modules, symbol_provider).await {
+            // lr is saved at the top of the frame, so sp is just address_of_pc + ptr
+            let caller_sp = address_of_pc.checked_add(POINTER_WIDTH)?;
+
+            trace!(
+                "unwind: scan seems valid -- caller_pc: 0x{:08x}, caller_sp: 0x{:08x}",
+                caller_pc,
+                caller_sp,
+            );
+
+            let mut caller_ctx = PpcContext::default();
+            caller_ctx.set_register(PROGRAM_COUNTER, caller_pc);
+            caller_ctx.set_register(STACK_POINTER, caller_sp);
+
+            let mut valid = HashSet::new();
+            valid.insert(PROGRAM_COUNTER);
+            valid.insert(STACK_POINTER);
+
+            let context = MinidumpContext {
+                raw: MinidumpRawContext::Ppc(caller_ctx),
+                valid: MinidumpContextValidity::Some(valid),
+            };
+            return Some(StackFrame::from_context(context, FrameTrust::Scan));
+        }
+    }
+
+    None
+}
+
+/// The most strict validation we have for instruction pointers.
+///
+/// This is only used for stack-scanning, see the ARM implementation for details.
+async fn instruction_seems_valid<P>(
+    instruction: Pointer,
+    memory_info: &UnifiedMemoryInfoList<'_>,
+    modules: &MinidumpModuleList,
+    symbol_provider: &P,
+) -> bool
+where
+    P: SymbolProvider + Sync,
+{
+    super::instruction_seems_valid_by_symbols(
+        instruction as u64,
+        memory_info,
+        modules,
+        symbol_provider,
+    )
+    .await
+}
+
+#[async_trait::async_trait]
+impl Unwind for PpcContext {
+    async fn get_caller_frame<P>(
+        &self,
+        callee: &StackFrame,
+        grand_callee: Option<&StackFrame>,
+        stack_memory: Option<&MinidumpMemory<'_>>,
+        _memory_list: &UnifiedMemoryList<'_>,
+        memory_info: &UnifiedMemoryInfoList<'_>,
+        modules: &MinidumpModuleList,
+        syms: &P,
+        options: &ProcessorOptions<'_>,
+        recorder: &mut UnwindRecorder,
+    ) -> Option<StackFrame>
+    where
+        P: SymbolProvider + Sync,
+    {
+        let stack = stack_memory.as_ref()?;
+
+        // .await doesn't like closures, so don't use Option chaining
+        let mut frame = None;
+        if frame.is_none() {
+            recorder.attempt(UnwindStrategy::CallFrameInfo);
+            frame =
+                get_caller_by_cfi(self, callee, grand_callee, stack, modules, syms, recorder).await;
+        }
+        if frame.is_none() && back_chain_terminated(self, callee, stack) {
+            trace!("unwind: back chain was null, assuming unwind complete");
+            recorder.reject("back chain was null");
+            return None;
+        }
+        if frame.is_none() {
+            recorder.attempt(UnwindStrategy::FramePointer);
+            frame = get_caller_by_back_chain(self, callee, stack, modules, syms, recorder);
+        }
+        if frame.is_none() {
+            recorder.attempt(UnwindStrategy::LinkRegister);
+            frame = get_caller_by_link_register(self, callee, stack, modules, syms, recorder);
+        }
+        if frame.is_none() && options.allow_scan {
+            recorder.attempt(UnwindStrategy::Scan);
+            frame = get_caller_by_scan(
+                self,
+                callee,
+                stack,
+                memory_info,
+                modules,
+                syms,
+                options,
+                recorder,
+            )
+            .await;
+        }
+        let mut frame = frame?;
+
+        // We now check the frame to see if it looks like unwinding is complete,
+        // based on the frame we computed having a nonsense value. Returning
+        // None signals to the unwinder to stop unwinding.
+
+        // if the instruction is within the first ~page of memory, it's basically
+        // null, and we can assume unwinding is complete.
+        if frame.context.get_instruction_pointer() < 4096 {
+            trace!("unwind: instruction pointer was nullish, assuming unwind complete");
+            recorder.reject("instruction pointer was nullish");
+            return None;
+        }
+        // If the new stack pointer is at a lower address than the old,
+        // then that's clearly incorrect. Treat this as end-of-stack to
+        // enforce progress and avoid infinite loops.
+        let sp = frame.context.get_stack_pointer();
+        let last_sp = self.get_register_always(STACK_POINTER) as u64;
+        if sp <= last_sp {
+            // Leaf functions may not touch the stack (the return address is
+            // in lr), so permit the stack pointer to not change for the first
+            // frame of the unwind.
+            let is_leaf = callee.trust == FrameTrust::Context && sp == last_sp;
+            if !is_leaf {
+                trace!("unwind: stack pointer went backwards, assuming unwind complete");
+                recorder.reject("stack pointer went backwards");
+                return None;
+            }
+        }
+
+        // Ok, the frame now seems well and truly valid, do final cleanup.
+
+        // A caller's srr0 is the return address, which is the instruction
+        // after the bl that caused us to arrive at the callee. Set the value
+        // to 4 less than that, so it points to the bl instruction.
+        // This is important because we use this value to lookup the CFI we need
+        // to unwind the next frame.
+        let ip = frame.context.get_instruction_pointer();
+        frame.instruction = ip - 4;
+
+        Some(frame)
+    }
+}
@@ -0,0 +1,309 @@
+// Copyright 2015 Ted Mielczarek. See the COPYRIGHT
+// file at the top-level directory of this distribution.
+
+use crate::process_state::*;
+use crate::stackwalker::walk_stack;
+use crate::{string_symbol_supplier, ProcessorOptions, Symbolizer};
+use minidump::format::CONTEXT_PPC64;
+use minidump::*;
+use std::collections::HashMap;
+use test_assembler::*;
+
+struct TestFixture {
+    pub raw: CONTEXT_PPC64,
+    pub modules: MinidumpModuleList,
+    pub symbols: HashMap<String, String>,
+}
+
+impl TestFixture {
+    pub fn new() -> TestFixture {
+        TestFixture {
+            raw: CONTEXT_PPC64::default(),
+            // Give the two modules reasonable standard locations and names
+            // for tests to play with.
+            modules: MinidumpModuleList::from_modules(vec![
+                MinidumpModule::new(0x40000000c0000000, 0x10000, "module1"),
+                MinidumpModule::new(0x50000000b0000000, 0x10000, "module2"),
+            ]),
+            symbols: HashMap::new(),
+        }
+    }
+
+    pub async fn walk_stack(&self, stack: Section) -> CallStack {
+        let context = MinidumpContext {
+            raw: MinidumpRawContext::Ppc64(self.raw.clone()),
+            valid: MinidumpContextValidity::All,
+        };
+        let base = stack.start().value().unwrap();
+        let size = stack.size();
+        let stack = stack.get_contents().unwrap();
+        let stack_memory = MinidumpMemory {
+            desc: Default::default(),
+            base_address: base,
+            size,
+            bytes: &stack,
+        };
+        let symbolizer = Symbolizer::new(string_symbol_supplier(self.symbols.clone()));
+        walk_stack(
+            &Some(&context),
+            Some(&stack_memory),
+            &UnifiedMemoryList::default(),
+            &UnifiedMemoryInfoList::default(),
+            &self.modules,
+            &symbolizer,
+            &ProcessorOptions::default(),
+        )
+        .await
+    }
+
+    pub fn add_symbols(&mut self, name: String, symbols: String) {
+        self.symbols.insert(name, symbols);
+    }
+}
+
+#[tokio::test]
+async fn test_simple() {
+    let mut f = TestFixture::new();
+    let stack = Section::new();
+    stack.start().set_const(0x80000000);
+    // There should be no references to the stack in this walk: we don't
+    // provide any call frame information, so trying to reconstruct the
+    // context frame's caller should fail. So there's no need for us to
+    // provide stack contents.
+    f.raw.set_register("srr0", 0x40000000c000c020);
+    f.raw.set_register("r1", 0x80000000);
+
+    let s = f.walk_stack(stack).await;
+    assert_eq!(s.frames.len(), 1);
+    let f = &s.frames[0];
+    let m = f.module.as_ref().unwrap();
+    assert_eq!(m.code_file(), "module1");
+}
+
+/// Walks two frames up the back chain: first to a caller in the same module,
+/// then to a caller in another module whose TOC was saved at `toc_offset`.
+async fn check_back_chain(toc_offset: u64) {
+    let mut f = TestFixture::new();
+    let mut stack = Section::new();
+    stack.start().set_const(0x80000000);
+
+    let return_address1 = 0x40000000c0000100u64;
+    let return_address2 = 0x50000000b0000900u64;
+    let toc1 = 0x40000000c0008000u64;
+    let toc2 = 0x50000000b0008000u64;
+    let frame1_sp = Label::new();
+    let frame2_sp = Label::new();
+
+    stack = stack
+        // frame 0
+        .D64(&frame1_sp) // back chain
+        .append_repeated(0, 24) // locals
+        // frame 1
+        .mark(&frame1_sp)
+        .D64(&frame2_sp) // back chain
+        .D64(0) // saved cr
+        .D64(return_address1) // saved lr
+        .append_repeated(0, 24) // locals
+        // frame 2
+        .mark(&frame2_sp)
+        .D64(0) // back chain
+        .D64(0) // saved cr
+        .D64(return_address2) // saved lr
+        .append_repeated(0, toc_offset as usize - 24)
+        .D64(toc2) // saved toc
+        .append_repeated(0, 16); // locals
+
+    f.raw.set_register("srr0", 0x40000000c0005510);
+    f.raw.set_register("r1", stack.start().value().unwrap());
+    f.raw.set_register("r2", toc1);
+
+    let s = f.walk_stack(stack).await;
+    assert_eq!(s.frames.len(), 3);
+
+    {
+        // Frame 0
+        let frame = &s.frames[0];
+        assert_eq!(frame.trust, FrameTrust::Context);
+        assert_eq!(frame.context.valid, MinidumpContextValidity::All);
+    }
+
+    for (frame, return_address, sp, toc) in [
+        (&s.frames[1], return_address1, &frame1_sp, toc1),
+        (&s.frames[2], return_address2, &frame2_sp, toc2),
+    ] {
+        let valid = &frame.context.valid;
+        assert_eq!(frame.trust, FrameTrust::FramePointer);
+        if let MinidumpContextValidity::Some(ref which) = valid {
+            assert_eq!(which.len(), 3);
+        } else {
+            unreachable!();
+        }
+
+        if let MinidumpRawContext::Ppc64(ctx) = &frame.context.raw {
+            assert_eq!(ctx.get_register("srr0", valid).unwrap(), return_address);
+            assert_eq!(ctx.get_register("r1", valid).unwrap(), sp.value().unwrap());
+            assert_eq!(ctx.get_register("r2", valid).unwrap(), toc);
+        } else {
+            unreachable!();
+        }
+        assert_eq!(frame.instruction, return_address - 4);
+    }
+}
+
+#[tokio::test]
+async fn test_back_chain_elfv2() {
+    check_back_chain(24).await;
+}
+
+#[tokio::test]
+async fn test_back_chain_elfv1() {
+    check_back_chain(40).await;
+}
+
+#[tokio::test]
+async fn test_link_register() {
+    // A leaf function which hasn't set up a frame returns through lr.
+    let mut f = TestFixture::new();
+    let mut stack = Section::new();
+    stack.start().set_const(0x80000000);
+    let frame1_sp = Label::new();
+    stack = stack
+        // The caller's frame, which the leaf hasn't pushed anything onto.
+        .D64(&frame1_sp) // back chain
+        .append_repeated(0, 24) // locals
+        // The caller hasn't saved its own return address yet.
+        .mark(&frame1_sp)
+        .D64(0) // back chain
+        .append_repeated(0, 24); // locals
+
+    f.raw.set_register("srr0", 0x40000000c0005510);
+    f.raw.set_register("lr", 0x40000000c0000100);
+    f.raw.set_register("r1", stack.start().value().unwrap());
+    f.raw.set_register("r2", 0x40000000c0008000);
+    f.raw.set_register("r31", 0xb5d55e68);
+
+    let s = f.walk_stack(stack).await;
+    assert_eq!(s.frames.len(), 2);
+
+    let frame = &s.frames[1];
+    let valid = &frame.context.valid;
+    assert_eq!(frame.trust, FrameTrust::FramePointer);
+    assert_eq!(frame.instruction, 0x40000000c0000100 - 4);
+    if let MinidumpRawContext::Ppc64(ctx) = &frame.context.raw {
+        assert_eq!(ctx.get_register("srr0", valid), Some(0x40000000c0000100));
+        assert_eq!(ctx.get_register("r1", valid), Some(0x80000000));
+        // The caller is in the same module, so it shares the TOC.
+        assert_eq!(ctx.get_register("r2", valid), Some(0x40000000c0008000));
+        // Callee-saved registers are forwarded.
+        assert_eq!(ctx.get_register("r31", valid), Some(0xb5d55e68));
+        // Others are not.
+        assert_eq!(ctx.get_register("r3", valid), None);
+    } else {
+        unreachable!();
+    }
+}
+
+#[tokio::test]
+async fn test_scan_without_symbols() {
+    // Scanning should work without any symbols
+    let mut f = TestFixture::new();
+    let mut stack = Section::new();
+    stack.start().set_const(0x80000000);
+
+    let return_address = 0x50000000b0000100u64;
+    let frame1_sp = Label::new();
+
+    stack = stack
+        // frame 0, with a broken back chain
+        .D64(0x7ffffff0) // back chain
+        .append_repeated(0, 16) // locals
+        .D64(0x40000000b0000000u64) // junk that's not a return address
+        // frame 1
+        .mark(&frame1_sp)
+        .D64(0) // back chain
+        .D64(0) // saved cr
+        .D64(return_address) // saved lr
+        .append_repeated(0, 24); // locals
+
+    f.raw.set_register("srr0", 0x40000000c0005510);
+    f.raw.set_register("r1", stack.start().value().unwrap());
+
+    let s = f.walk_stack(stack).await;
+    assert_eq!(s.frames.len(), 2);
+
+    let frame = &s.frames[1];
+    let valid = &frame.context.valid;
+    assert_eq!(frame.trust, FrameTrust::Scan);
+    if let MinidumpRawContext::Ppc64(ctx) = &frame.context.raw {
+        assert_eq!(ctx.get_register("srr0", valid), Some(return_address));
+        assert_eq!(
+            ctx.get_register("r1", valid),
+            Some(frame1_sp.value().unwrap())
+        );
+    } else {
+        unreachable!();
+    }
+}
+
+#[tokio::test]
+async fn test_cfi() {
+    let mut f = TestFixture::new();
+    let symbols = [
+        // The youngest frame's function.
+        "FUNC 4000 1000 10 enchiridion\n",
+        // Allocate a 32 byte frame, with lr saved in the caller's frame
+        // and r31 saved at the top of ours.
+        "STACK CFI INIT 4000 100 .cfa: $r1 32 + .ra: .cfa 16 + ^",
+        " $r31: .cfa 8 - ^\n",
+        // The calling function.
+        "FUNC 5000 1000 10 epictetus\n",
+        // Mark it as end of stack.
+        "STACK CFI INIT 5000 1000 .cfa: 0 .ra: 0\n",
+    ];
+    f.add_symbols(String::from("module1"), symbols.concat());
+
+    let frame1_sp = Label::new();
+    let mut stack = Section::new();
+    stack.start().set_const(0x80000000);
+    stack = stack
+        .D64(0x12345678) // junk
+        .D64(0x00000000) // junk
+        .D64(0x00000000) // junk
+        .D64(0xb5d55e68) // saved r31
+        .mark(&frame1_sp)
+        .D64(0x00000000) // back chain
+        .D64(0x00000000) // saved cr
+        .D64(0x40000000c0005510u64) // saved lr
+        .append_repeated(0, 120);
+
+    f.raw.set_register("srr0", 0x40000000c0004010);
+    f.raw.set_register("r1", stack.start().value().unwrap());
+    f.raw.set_register("r2", 0x40000000c0008000);
+    f.raw.set_register("r31", 0xdeadbeef);
+    f.raw.set_register("r30", 0xebd134f3);
+
+    let s = f.walk_stack(stack).await;
+    assert_eq!(s.frames.len(), 2);
+
+    let frame = &s.frames[1];
+    let valid = &frame.context.valid;
+    assert_eq!(frame.trust, FrameTrust::CallFrameInfo);
+    assert_eq!(frame.instruction, 0x40000000c0005510 - 4);
+    assert_eq!(frame.function_name.as_deref(), Some("epictetus"));
+    if let MinidumpRawContext::Ppc64(ctx) = &frame.context.raw {
+        assert_eq!(ctx.get_register("srr0", valid), Some(0x40000000c0005510));
+        assert_eq!(
+            ctx.get_register("r1", valid),
+            Some(frame1_sp.value().unwrap())
+        );
+        assert_eq!(ctx.get_register("r31", valid), Some(0xb5d55e68));
+        // The caller is in the same module, so it shares the TOC.
+        assert_eq!(ctx.get_register("r2", valid), Some(0x40000000c0008000));
+        // Callee-saved registers are forwarded.
+        assert_eq!(ctx.get_register("r30", valid), Some(0xebd134f3));
+        // Others are not.
+        assert_eq!(ctx.get_register("r3", valid), None);
+    } else {
+        unreachable!();
+    }
+}
@@ -0,0 +1,293 @@
+// Copyright 2015 Ted Mielczarek. See the COPYRIGHT
+// file at the top-level directory of this distribution.
+
+use crate::process_state::*;
+use crate::stackwalker::walk_stack;
+use crate::{string_symbol_supplier, ProcessorOptions, Symbolizer};
+use minidump::format::CONTEXT_PPC;
+use minidump::*;
+use std::collections::HashMap;
+use test_assembler::*;
+
+struct TestFixture {
+    pub raw: CONTEXT_PPC,
+    pub modules: MinidumpModuleList,
+    pub symbols: HashMap<String, String>,
+}
+
+impl TestFixture {
+    pub fn new() -> TestFixture {
+        TestFixture {
+            raw: CONTEXT_PPC::default(),
+            // Give the two modules reasonable standard locations and names
+            // for tests to play with.
+            modules: MinidumpModuleList::from_modules(vec![
+                MinidumpModule::new(0x40000000, 0x10000, "module1"),
+                MinidumpModule::new(0x50000000, 0x10000, "module2"),
+            ]),
+            symbols: HashMap::new(),
+        }
+    }
+
+    pub async fn walk_stack(&self, stack: Section) -> CallStack {
+        let context = MinidumpContext {
+            raw: MinidumpRawContext::Ppc(self.raw.clone()),
+            valid: MinidumpContextValidity::All,
+        };
+        let base = stack.start().value().unwrap();
+        let size = stack.size();
+        let stack = stack.get_contents().unwrap();
+        let stack_memory = MinidumpMemory {
+            desc: Default::default(),
+            base_address: base,
+            size,
+            bytes: &stack,
+        };
+        let symbolizer = Symbolizer::new(string_symbol_supplier(self.symbols.clone()));
+        walk_stack(
+            &Some(&context),
+            Some(&stack_memory),
+            &UnifiedMemoryList::default(),
+            &UnifiedMemoryInfoList::default(),
+            &self.modules,
+            &symbolizer,
+            &ProcessorOptions::default(),
+        )
+        .await
+    }
+
+    pub fn add_symbols(&mut self, name: String, symbols: String) {
+        self.symbols.insert(name, symbols);
+    }
+}
+
+#[tokio::test]
+async fn test_simple() {
+    let mut f = TestFixture::new();
+    let stack = Section::new();
+    stack.start().set_const(0x80000000);
+    // There should be no references to the stack in this walk: we don't
+    // provide any call frame information, so trying to reconstruct the
+    // context frame's caller should fail. So there's no need for us to
+    // provide stack contents.
+    f.raw.set_register("srr0", 0x4000c020);
+    f.raw.set_register("r1", 0x80000000);
+
+    let s = f.walk_stack(stack).await;
+    assert_eq!(s.frames.len(), 1);
+    let f = &s.frames[0];
+    let m = f.module.as_ref().unwrap();
+    assert_eq!(m.code_file(), "module1");
+}
+
+#[tokio::test]
+async fn test_back_chain() {
+    // Walk the back chain, with return addresses saved in the Darwin slot.
+    let mut f = TestFixture::new();
+    let mut stack = Section::new();
+    stack.start().set_const(0x80000000);
+
+    let return_address1 = 0x50000100u32;
+    let return_address2 = 0x50000900u32;
+    let frame1_sp = Label::new();
+    let frame2_sp = Label::new();
+
+    stack = stack
+        // frame 0
+        .D32(&frame1_sp) // back chain
+        .append_repeated(0, 12) // locals
+        // frame 1
+        .mark(&frame1_sp)
+        .D32(&frame2_sp) // back chain
+        .D32(0) // saved cr
+        .D32(return_address1) // saved lr
+        .append_repeated(0, 20) // locals
+        // frame 2
+        .mark(&frame2_sp)
+        .D32(0) // back chain
+        .D32(0) // saved cr
+        .D32(return_address2) // saved lr
+        .append_repeated(0, 20); // locals
+
+    f.raw.set_register("srr0", 0x40005510);
+    f.raw
+        .set_register("r1", stack.start().value().unwrap() as u32);
+
+    let s = f.walk_stack(stack).await;
+    assert_eq!(s.frames.len(), 3);
+
+    {
+        // Frame 0
+        let frame = &s.frames[0];
+        assert_eq!(frame.trust, FrameTrust::Context);
+        assert_eq!(frame.context.valid, MinidumpContextValidity::All);
+    }
+
+    for (frame, return_address, sp) in [
+        (&s.frames[1], return_address1, &frame1_sp),
+        (&s.frames[2], return_address2, &frame2_sp),
+    ] {
+        let valid = &frame.context.valid;
+        assert_eq!(frame.trust, FrameTrust::FramePointer);
+        if let MinidumpContextValidity::Some(ref which) = valid {
+            assert_eq!(which.len(), 2);
+        } else {
+            unreachable!();
+        }
+
+        if let MinidumpRawContext::Ppc(ctx) = &frame.context.raw {
+            assert_eq!(ctx.get_register("srr0", valid).unwrap(), return_address);
+            assert_eq!(
+                ctx.get_register("r1", valid).unwrap(),
+                sp.value().unwrap() as u32
+            );
+        } else {
+            unreachable!();
+        }
+        assert_eq!(frame.instruction, return_address as u64 - 4);
+    }
+}
+
+#[tokio::test]
+async fn test_back_chain_sysv() {
+    // The SysV ABI saves the return address in the second slot of the
+    // caller's frame instead.
+    let mut f = TestFixture::new();
+    let mut stack = Section::new();
+    stack.start().set_const(0x80000000);
+
+    let return_address = 0x50000100u32;
+    let frame1_sp = Label::new();
+
+    stack = stack
+        // frame 0
+        .D32(&frame1_sp) // back chain
+        .append_repeated(0, 12) // locals
+        // frame 1
+        .mark(&frame1_sp)
+        .D32(0) // back chain
+        .D32(return_address) // saved lr
+        .append_repeated(0, 24); // locals
+
+    f.raw.set_register("srr0", 0x40005510);
+    f.raw
+        .set_register("r1", stack.start().value().unwrap() as u32);
+
+    let s = f.walk_stack(stack).await;
+    assert_eq!(s.frames.len(), 2);
+
+    let frame = &s.frames[1];
+    let valid = &frame.context.valid;
+    assert_eq!(frame.trust, FrameTrust::FramePointer);
+    if let MinidumpRawContext::Ppc(ctx) = &frame.context.raw {
+        assert_eq!(ctx.get_register("srr0", valid), Some(return_address));
+        assert_eq!(
+            ctx.get_register("r1", valid),
+            Some(frame1_sp.value().unwrap() as u32)
+        );
+    } else {
+        unreachable!();
+    }
+}
+
+#[tokio::test]
+async fn test_link_register() {
+    // A leaf function which hasn't set up a frame returns through lr.
+    let mut f = TestFixture::new();
+    let mut stack = Section::new();
+    stack.start().set_const(0x80000000);
+    let frame1_sp = Label::new();
+    stack = stack
+        // The caller's frame, which the leaf hasn't pushed anything onto.
+        .D32(&frame1_sp) // back chain
+        .append_repeated(0, 12) // locals
+        // The caller hasn't saved its own return address yet.
+        .mark(&frame1_sp)
+        .D32(0) // back chain
+        .append_repeated(0, 12); // locals
+
+    f.raw.set_register("srr0", 0x40005510);
+    f.raw.set_register("lr", 0x50000100);
+    f.raw
+        .set_register("r1", stack.start().value().unwrap() as u32);
+    f.raw.set_register("r31", 0xb5d55e68);
+
+    let s = f.walk_stack(stack).await;
+    assert_eq!(s.frames.len(), 2);
+
+    let frame = &s.frames[1];
+    let valid = &frame.context.valid;
+    assert_eq!(frame.trust, FrameTrust::FramePointer);
+    assert_eq!(frame.instruction, 0x50000100 - 4);
+    if let MinidumpRawContext::Ppc(ctx) = &frame.context.raw {
+        assert_eq!(ctx.get_register("srr0", valid), Some(0x50000100));
+        assert_eq!(ctx.get_register("r1", valid), Some(0x80000000));
+        // Callee-saved registers are forwarded.
+        assert_eq!(ctx.get_register("r31", valid), Some(0xb5d55e68));
+        // Others are not.
+        assert_eq!(ctx.get_register("r3", valid), None);
+    } else {
+        unreachable!();
+    }
+}
+
+#[tokio::test]
+async fn test_cfi() {
+    let mut f = TestFixture::new();
+    let symbols = [
+        // The youngest frame's function.
+        "FUNC 4000 1000 10 enchiridion\n",
+        // Allocate a 16 byte frame, with lr saved in the caller's frame
+        // and r31 saved at the top of ours.
+        "STACK CFI INIT 4000 100 .cfa: $r1 16 + .ra: .cfa 8 + ^",
+        " $r31: .cfa 4 - ^\n",
+        // The calling function.
+        "FUNC 5000 1000 10 epictetus\n",
+        // Mark it as end of stack.
+        "STACK CFI INIT 5000 1000 .cfa: 0 .ra: 0\n",
+    ];
+    f.add_symbols(String::from("module1"), symbols.concat());
+
+    let frame1_sp = Label::new();
+    let mut stack = Section::new();
+    stack.start().set_const(0x80000000);
+    stack = stack
+        .D32(0x12345678) // junk
+        .D32(0x00000000) // junk
+        .D32(0x00000000) // junk
+        .D32(0xb5d55e68) // saved r31
+        .mark(&frame1_sp)
+        .D32(0x00000000) // back chain
+        .D32(0x00000000) // saved cr
+        .D32(0x40005510) // saved lr
+        .append_repeated(0, 116);
+
+    f.raw.set_register("srr0", 0x40004010);
+    f.raw
+        .set_register("r1", stack.start().value().unwrap() as u32);
+    f.raw.set_register("r31", 0xdeadbeef);
+    f.raw.set_register("r30", 0xebd134f3);
+
+    let s = f.walk_stack(stack).await;
+    assert_eq!(s.frames.len(), 2);
+
+    let frame = &s.frames[1];
+    let valid = &frame.context.valid;
+    assert_eq!(frame.trust, FrameTrust::CallFrameInfo);
+    assert_eq!(frame.instruction, 0x40005510 - 4);
+    assert_eq!(frame.function_name.as_deref(), Some("epictetus"));
+    if let MinidumpRawContext::Ppc(ctx) = &frame.context.raw {
+        assert_eq!(ctx.get_register("srr0", valid), Some(0x40005510));
+        assert_eq!(
+            ctx.get_register("r1", valid),
+            Some(frame1_sp.value().unwrap() as u32)
+        );
+        assert_eq!(ctx.get_register("r31", valid), Some(0xb5d55e68));
+        // Callee-saved registers are forwarded.
+        assert_eq!(ctx.get_register("r30", valid), Some(0xebd134f3));
+        // Others are not.
+        assert_eq!(ctx.get_register("r3", valid), None);
+    } else {
+        unreachable!();
+    }
+}
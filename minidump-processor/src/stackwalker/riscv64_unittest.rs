@@ -0,0 +1,272 @@
+// Copyright 2015 Ted Mielczarek. See the COPYRIGHT
+// file at the top-level directory of this distribution.
+
+use crate::process_state::*;
+use crate::stackwalker::walk_stack;
+use crate::{string_symbol_supplier, ProcessorOptions, Symbolizer};
+use minidump::format::CONTEXT_RISCV64;
+use minidump::*;
+use std::collections::HashMap;
+use test_assembler::*;
+
+struct TestFixture {
+    pub raw: CONTEXT_RISCV64,
+    pub modules: MinidumpModuleList,
+    pub symbols: HashMap<String, String>,
+}
+
+impl TestFixture {
+    pub fn new() -> TestFixture {
+        TestFixture {
+            raw: CONTEXT_RISCV64::default(),
+            // Give the two modules reasonable standard locations and names
+            // for tests to play with.
+            modules: MinidumpModuleList::from_modules(vec![
+                MinidumpModule::new(0x120000000, 0x10000, "module1"),
+                MinidumpModule::new(0x130000000, 0x10000, "module2"),
+            ]),
+            symbols: HashMap::new(),
+        }
+    }
+
+    pub async fn walk_stack(&self, stack: Section) -> CallStack {
+        let context = MinidumpContext {
+            raw: MinidumpRawContext::Riscv64(self.raw.clone()),
+            valid: MinidumpContextValidity::All,
+        };
+        let base = stack.start().value().unwrap();
+        let size = stack.size();
+        let stack = stack.get_contents().unwrap();
+        let stack_memory = MinidumpMemory {
+            desc: Default::default(),
+            base_address: base,
+            size,
+            bytes: &stack,
+        };
+        let symbolizer = Symbolizer::new(string_symbol_supplier(self.symbols.clone()));
+        walk_stack(
+            &Some(&context),
+            Some(&stack_memory),
+            &UnifiedMemoryList::default(),
+            &UnifiedMemoryInfoList::default(),
+            &self.modules,
+            &symbolizer,
+            &ProcessorOptions::default(),
+        )
+        .await
+    }
+
+    pub fn add_symbols(&mut self, name: String, symbols: String) {
+        self.symbols.insert(name, symbols);
+    }
+}
+
+#[tokio::test]
+async fn test_simple() {
+    let mut f = TestFixture::new();
+    let stack = Section::new();
+    stack.start().set_const(0x7fff00000000);
+    // There should be no references to the stack in this walk: we don't
+    // provide any call frame information, so trying to reconstruct the
+    // context frame's caller should fail. So there's no need for us to
+    // provide stack contents.
+    f.raw.set_register("pc", 0x12000c020);
+    f.raw.set_register("s0", 0x7fff00000000);
+
+    let s = f.walk_stack(stack).await;
+    assert_eq!(s.frames.len(), 1);
+    let f = &s.frames[0];
+    let m = f.module.as_ref().unwrap();
+    assert_eq!(m.code_file(), "module1");
+}
+
+#[tokio::test]
+async fn test_scan_without_symbols() {
+    // Scanning should work without any symbols
+    let mut f = TestFixture::new();
+    let mut stack = Section::new();
+    stack.start().set_const(0x7fff00000000);
+
+    let return_address1 = 0x130000100u64;
+    let return_address2 = 0x130000900u64;
+    let frame1_sp = Label::new();
+    let frame2_sp = Label::new();
+
+    stack = stack
+        // frame 0
+        .append_repeated(0, 16) // space
+        .D64(0x120090000) // junk that's not
+        .D64(0x60000000) // a return address
+        .D64(return_address1) // actual return address
+        // frame 1
+        .mark(&frame1_sp)
+        .append_repeated(0, 16) // space
+        .D64(0xF0000000) // more junk
+        .D64(0x0000000D)
+        .D64(return_address2) // actual return address
+        // frame 2
+        .mark(&frame2_sp)
+        .append_repeated(0, 32); // end of stack
+
+    f.raw.set_register("pc", 0x120005510);
+    f.raw.set_register("sp", stack.start().value().unwrap());
+
+    let s = f.walk_stack(stack).await;
+    assert_eq!(s.frames.len(), 3);
+
+    {
+        // Frame 0
+        let frame = &s.frames[0];
+        assert_eq!(frame.trust, FrameTrust::Context);
+        assert_eq!(frame.context.valid, MinidumpContextValidity::All);
+    }
+
+    for (frame, return_address, sp) in [
+        (&s.frames[1], return_address1, &frame1_sp),
+        (&s.frames[2], return_address2, &frame2_sp),
+    ] {
+        let valid = &frame.context.valid;
+        assert_eq!(frame.trust, FrameTrust::Scan);
+        if let MinidumpContextValidity::Some(ref which) = valid {
+            assert_eq!(which.len(), 2);
+        } else {
+            unreachable!();
+        }
+
+        if let MinidumpRawContext::Riscv64(ctx) = &frame.context.raw {
+            assert_eq!(ctx.get_register("pc", valid).unwrap(), return_address);
+            assert_eq!(ctx.get_register("sp", valid).unwrap(), sp.value().unwrap());
+        } else {
+            unreachable!();
+        }
+        // The instruction points inside the call, whatever its size.
+        assert_eq!(frame.instruction, return_address - 2);
+    }
+}
+
+#[tokio::test]
+async fn test_frame_pointer() {
+    // Frame-pointer-based unwinding
+    let mut f = TestFixture::new();
+    let mut stack = Section::new();
+    stack.start().set_const(0x7fff00000000);
+
+    let return_address1 = 0x130000100u64;
+    let return_address2 = 0x130000900u64;
+    let frame0_fp = Label::new();
+    let frame1_fp = Label::new();
+    let frame2_fp = Label::new();
+
+    // Frame pointers point at the top of their frames, just above the
+    // saved fp and ra.
+    stack = stack
+        // frame 0
+        .append_repeated(0, 16) // locals
+        .D64(&frame1_fp) // saved fp
+        .D64(return_address1) // saved ra
+        .mark(&frame0_fp)
+        // frame 1
+        .append_repeated(0, 32) // locals
+        .D64(&frame2_fp) // saved fp
+        .D64(return_address2) // saved ra
+        .mark(&frame1_fp)
+        // frame 2
+        .append_repeated(0, 16) // locals
+        .D64(0) // saved fp
+        .D64(0) // saved ra
+        .mark(&frame2_fp);
+
+    f.raw.set_register("pc", 0x120005510);
+    f.raw.set_register("s0", frame0_fp.value().unwrap());
+    f.raw.set_register("sp", stack.start().value().unwrap());
+
+    let s = f.walk_stack(stack).await;
+    assert_eq!(s.frames.len(), 3);
+
+    {
+        // Frame 0
+        let frame = &s.frames[0];
+        assert_eq!(frame.trust, FrameTrust::Context);
+        assert_eq!(frame.context.valid, MinidumpContextValidity::All);
+    }
+
+    for (frame, return_address, sp, fp) in [
+        (&s.frames[1], return_address1, &frame0_fp, &frame1_fp),
+        (&s.frames[2], return_address2, &frame1_fp, &frame2_fp),
+    ] {
+        let valid = &frame.context.valid;
+        assert_eq!(frame.trust, FrameTrust::FramePointer);
+        if let MinidumpContextValidity::Some(ref which) = valid {
+            assert_eq!(which.len(), 3);
+        } else {
+            unreachable!();
+        }
+
+        if let MinidumpRawContext::Riscv64(ctx) = &frame.context.raw {
+            assert_eq!(ctx.get_register("pc", valid).unwrap(), return_address);
+            assert_eq!(ctx.get_register("sp", valid).unwrap(), sp.value().unwrap());
+            assert_eq!(ctx.get_register("s0", valid).unwrap(), fp.value().unwrap());
+        } else {
+            unreachable!();
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_cfi() {
+    let mut f = TestFixture::new();
+    let symbols = [
+        // The youngest frame's function.
+        "FUNC 4000 1000 10 enchiridion\n",
+        // Allocate a 32 byte frame and save ra, s0 and s1 at the top of it.
+        "STACK CFI INIT 4000 100 .cfa: $sp 32 + .ra: .cfa -8 + ^",
+        " $s0: .cfa -16 + ^ $s1: .cfa -24 + ^\n",
+        // The calling function.
+        "FUNC 5000 1000 10 epictetus\n",
+        // Mark it as end of stack.
+        "STACK CFI INIT 5000 1000 .cfa: 0 .ra: 0\n",
+    ];
+    f.add_symbols(String::from("module1"), symbols.concat());
+
+    let frame1_sp = Label::new();
+    let mut stack = Section::new();
+    stack.start().set_const(0x7fff00000000);
+    stack = stack
+        .D64(0x1234567812345678) // junk
+        .D64(0x5a5a5a5ab5d55e68) // saved s1
+        .D64(0x7fff00001000) // saved s0
+        .D64(0x120005510) // saved ra
+        .mark(&frame1_sp)
+        .append_repeated(0, 120);
+
+    f.raw.set_register("pc", 0x120004010);
+    f.raw.set_register("sp", stack.start().value().unwrap());
+    f.raw.set_register("s0", 0x7fff00000020);
+    f.raw.set_register("s1", 0xdeadbeef);
+    f.raw.set_register("s2", 0xebd134f3);
+    f.raw.set_register("a0", 0x48bf8ca7);
+
+    let s = f.walk_stack(stack).await;
+    assert_eq!(s.frames.len(), 2);
+
+    let frame = &s.frames[1];
+    let valid = &frame.context.valid;
+    assert_eq!(frame.trust, FrameTrust::CallFrameInfo);
+    assert_eq!(frame.instruction, 0x120005510 - 2);
+    assert_eq!(frame.function_name.as_deref(), Some("epictetus"));
+    if let MinidumpRawContext::Riscv64(ctx) = &frame.context.raw {
+        assert_eq!(ctx.get_register("pc", valid), Some(0x120005510));
+        assert_eq!(
+            ctx.get_register("sp", valid),
+            Some(frame1_sp.value().unwrap())
+        );
+        assert_eq!(ctx.get_register("s0", valid), Some(0x7fff00001000));
+        assert_eq!(ctx.get_register("s1", valid), Some(0x5a5a5a5ab5d55e68));
+        // Callee-saved registers are forwarded.
+        assert_eq!(ctx.get_register("s2", valid), Some(0xebd134f3));
+        // Others are not.
+        assert_eq!(ctx.get_register("a0", valid), None);
+    } else {
+        unreachable!();
+    }
+}
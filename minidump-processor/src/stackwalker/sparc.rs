@@ -0,0 +1,290 @@
+// Copyright 2015 Ted Mielczarek. See the COPYRIGHT
+// file at the top-level directory of this distribution.
+
+use crate::process_state::{FrameTrust, StackFrame, UnwindStrategy};
+use crate::stackwalker::unwind::Unwind;
+use crate::stackwalker::UnwindRecorder;
+use crate::{ProcessorOptions, SymbolProvider};
+use minidump::{
+    CpuContext, MinidumpContext, MinidumpContextValidity, MinidumpMemory, MinidumpModuleList,
+    MinidumpRawContext, UnifiedMemoryInfoList, UnifiedMemoryList,
+};
+use std::collections::HashSet;
+use tracing::trace;
+
+type SparcContext = minidump::format::CONTEXT_SPARC;
+type Pointer = <SparcContext as CpuContext>::Register;
+type Registers = minidump::format::SparcRegisterNumbers;
+
+const STACK_POINTER: &str = Registers::StackPointer.name();
+const FRAME_POINTER: &str = Registers::FramePointer.name();
+const RETURN_ADDRESS: &str = Registers::ReturnAddress.name();
+const PROGRAM_COUNTER: &str = "pc";
+
+/// The callee's in registers, which become the caller's out registers.
+const IN_REGS: &[&str] = &["i0", "i1", "i2", "i3", "i4", "i5", "i6", "i7"];
+const OUT_REGS: &[&str] = &["o0", "o1", "o2", "o3", "o4", "o5", "o6", "o7"];
+
+/// The registers spilled to a window save area, in the order they're stored.
+const WINDOW_REGS: &[&str] = &[
+    "l0", "l1", "l2", "l3", "l4", "l5", "l6", "l7", "i0", "i1", "i2", "i3", "i4", "i5", "i6", "i7",
+];
+
+/// The 64-bit ABI offsets %sp and %fp by this bias, which makes them odd.
+const STACK_BIAS: Pointer = 2047;
+
+/// A return address is the address of the call, followed by its delay slot.
+const CALL_SIZE: Pointer = 8;
+
+/// Read a register from the window save area at the given stack pointer.
+///
+/// 32-bit (V8) frames store 4 byte registers, while 64-bit (V9) frames are
+/// recognized by their biased stack pointer and store 8 byte registers.
+fn read_window_reg(stack_memory: &MinidumpMemory<'_>, sp: Pointer, reg: &str) -> Option<Pointer> {
+    let idx = WINDOW_REGS.iter().position(|&r| r == reg)? as Pointer;
+    if sp & 1 == 1 {
+        let address = sp.checked_add(STACK_BIAS)?.checked_add(idx * 8)?;
+        stack_memory.get_memory_at_address::<u64>(address)
+    } else {
+        let address = sp.checked_add(idx * 4)?;
+        stack_memory
+            .get_memory_at_address::<u32>(address)
+            .map(Pointer::from)
+    }
+}
+
+fn get_caller_by_frame_pointer<P>(
+    ctx: &SparcContext,
+    callee: &StackFrame,
+    stack_memory: &MinidumpMemory<'_>,
+    _modules: &MinidumpModuleList,
+    _symbol_provider: &P,
+    recorder: &mut UnwindRecorder,
+) -> Option<StackFrame>
+where
+    P: SymbolProvider + Sync,
+{
+    trace!("unwind: trying frame pointer");
+    // Every non-leaf function starts with a `save`, which gives it a new register
+    // window: the caller's out registers become the callee's in registers, so the
+    // callee's %fp (%i6) is the caller's %sp (%o6), and the callee's %i7 is the
+    // address of the call. The caller's own locals and ins are spilled to the
+    // window save area at the bottom of its frame when we run out of windows
+    // (which always happens before a dump is written), so we restore the caller
+    // with:
+    //
+    // sp := fp
+    // pc := i7 + 8
+    // o0..o7 := i0..i7
+    // l0..l7, i0..i7 := *(sp)..*(sp + 15 * ptr)
+    let valid = &callee.context.valid;
+    let last_sp = ctx.get_register(STACK_POINTER, valid)?;
+    let last_fp = ctx.get_register(FRAME_POINTER, valid)?;
+    let last_ra = ctx.get_register(RETURN_ADDRESS, valid)?;
+
+    if last_fp <= last_sp {
+        // The caller's frame must be above ours. This also catches the null
+        // frame pointer of the outermost frame.
+        recorder.reject("frame pointer isn't above the stack pointer");
+        return None;
+    }
+
+    let caller_sp = last_fp;
+    let caller_pc = last_ra.checked_add(CALL_SIZE)?;
+
+    trace!(
+        "unwind: frame pointer seems valid -- caller_pc: 0x{:016x}, caller_sp: 0x{:016x}",
+        caller_pc,
+        caller_sp,
+    );
+
+    let mut caller_ctx = SparcContext::default();
+    let mut caller_valid = HashSet::new();
+    caller_ctx.set_register(PROGRAM_COUNTER, caller_pc);
+    caller_valid.insert(PROGRAM_COUNTER);
+
+    for (&in_reg, &out_reg) in IN_REGS.iter().zip(OUT_REGS) {
+        if let Some(val) = ctx.get_register(in_reg, valid) {
+            caller_ctx.set_register(out_reg, val);
+            caller_valid.insert(out_reg);
+        }
+    }
+
+    for &reg in WINDOW_REGS {
+        if let Some(val) = read_window_reg(stack_memory, caller_sp, reg) {
+            caller_ctx.set_register(reg, val);
+            caller_valid.insert(reg);
+        }
+    }
+
+    let context = MinidumpContext {
+        raw: MinidumpRawContext::Sparc(caller_ctx),
+        valid: MinidumpContextValidity::Some(caller_valid),
+    };
+    Some(StackFrame::from_context(context, FrameTrust::FramePointer))
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn get_caller_by_scan<P>(
+    ctx: &SparcContext,
+    callee: &StackFrame,
+    stack_memory: &MinidumpMemory<'_>,
+    memory_info: &UnifiedMemoryInfoList<'_>,
+    modules: &MinidumpModuleList,
+    symbol_provider: &P,
+    options: &ProcessorOptions<'_>,
+    recorder: &mut UnwindRecorder,
+) -> Option<StackFrame>
+where
+    P: SymbolProvider + Sync,
+{
+    trace!("unwind: trying scan");
+    // Stack scanning is just walking from the end of the frame until we encounter
+    // a value on the stack that looks like a pointer into some code (it's an address
+    // in a range covered by one of our modules). If we find such a value, we assume
+    // it's a saved %i7 (the address of a call) in some window save area.
+    //
+    // Unlike other architectures, the saved return address doesn't tell us where
+    // the caller's frame starts, so we just assume it's right after it.
+    let valid = &callee.context.valid;
+    let last_sp = ctx.get_register(STACK_POINTER, valid)?;
+    let (base, width) = if last_sp & 1 == 1 {
+        (last_sp.checked_add(STACK_BIAS)?, 8)
+    } else {
+        (last_sp, 4)
+    };
+
+    // Number of pointer-sized values to scan through in our search.
+    let scan_range = super::scan_range(callee, options) as Pointer;
+
+    for i in 0..scan_range {
+        let address_of_ra = base.checked_add(i * width)?;
+        let caller_ra = if width == 8 {
+            recorder.read::<u64>(stack_memory, address_of_ra)?
+        } else {
+            Pointer::from(recorder.read::<u32>(stack_memory, address_of_ra)?)
+        };
+        let caller_pc = caller_ra.wrapping_add(CALL_SIZE);
+        if instruction_seems_valid(caller_pc, memory_info, modules, symbol_provider).await {
+            let caller_sp = address_of_ra.checked_add(width)?;
+            let caller_sp = if width == 8 {
+                caller_sp.checked_sub(STACK_BIAS)?
+            } else {
+                caller_sp
+            };
+
+            trace!(
+                "unwind: scan seems valid -- caller_pc: 0x{:016x}, caller_sp: 0x{:016x}",
+                caller_pc,
+                caller_sp,
+            );
+
+            let mut caller_ctx = SparcContext::default();
+            caller_ctx.set_register(PROGRAM_COUNTER, caller_pc);
+            caller_ctx.set_register(STACK_POINTER, caller_sp);
+
+            let mut valid = HashSet::new();
+            valid.insert(PROGRAM_COUNTER);
+            valid.insert(STACK_POINTER);
+
+            let context = MinidumpContext {
+                raw: MinidumpRawContext::Sparc(caller_ctx),
+                valid: MinidumpContextValidity::Some(valid),
+            };
+            return Some(StackFrame::from_context(context, FrameTrust::Scan));
+        }
+    }
+
+    None
+}
+
+/// The most strict validation we have for instruction pointers.
+///
+/// This is only used for stack-scanning, see the ARM implementation for details.
+async fn instruction_seems_valid<P>(
+    instruction: Pointer,
+    memory_info: &UnifiedMemoryInfoList<'_>,
+    modules: &MinidumpModuleList,
+    symbol_provider: &P,
+) -> bool
+where
+    P: SymbolProvider + Sync,
+{
+    super::instruction_seems_valid_by_symbols(instruction, memory_info, modules, symbol_provider)
+        .await
+}
+
+#[async_trait::async_trait]
+impl Unwind for SparcContext {
+    async fn get_caller_frame<P>(
+        &self,
+        callee: &StackFrame,
+        _grand_callee: Option<&StackFrame>,
+        stack_memory: Option<&MinidumpMemory<'_>>,
+        _memory_list: &UnifiedMemoryList<'_>,
+        memory_info: &UnifiedMemoryInfoList<'_>,
+        modules: &MinidumpModuleList,
+        syms: &P,
+        options: &ProcessorOptions<'_>,
+        recorder: &mut UnwindRecorder,
+    ) -> Option<StackFrame>
+    where
+        P: SymbolProvider + Sync,
+    {
+        let stack = stack_memory.as_ref()?;
+
+        // .await doesn't like closures, so don't use Option chaining
+        let mut frame = None;
+        if frame.is_none() {
+            recorder.attempt(UnwindStrategy::FramePointer);
+            frame = get_caller_by_frame_pointer(self, callee, stack, modules, syms, recorder);
+        }
+        if frame.is_none() && options.allow_scan {
+            recorder.attempt(UnwindStrategy::Scan);
+            frame = get_caller_by_scan(
+                self,
+                callee,
+                stack,
+                memory_info,
+                modules,
+                syms,
+                options,
+                recorder,
+            )
+            .await;
+        }
+        let mut frame = frame?;
+
+        // We now check the frame to see if it looks like unwinding is complete,
+        // based on the frame we computed having a nonsense value. Returning
+        // None signals to the unwinder to stop unwinding.
+
+        // if the instruction is within the first ~page of memory, it's basically
+        // null, and we can assume unwinding is complete.
+        if frame.context.get_instruction_pointer() < 4096 {
+            trace!("unwind: instruction pointer was nullish, assuming unwind complete");
+            recorder.reject("instruction pointer was nullish");
+            return None;
+        }
+        // If the new stack pointer is at a lower address than the old,
+        // then that's clearly incorrect. Treat this as end-of-stack to
+        // enforce progress and avoid infinite loops.
+        let sp = frame.context.get_stack_pointer();
+        let last_sp = self.get_register_always(STACK_POINTER);
+        if sp <= last_sp {
+            trace!("unwind: stack pointer went backwards, assuming unwind complete");
+            recorder.reject("stack pointer went backwards");
+            return None;
+        }
+
+        // Ok, the frame now seems well and truly valid, do final cleanup.
+
+        // A caller's pc is the return address, which is the instruction *after*
+        // the delay slot of the call that caused us to arrive at the callee.
+        // Set the value to 8 less than that, so it points to the call instruction.
+        let ip = frame.context.get_instruction_pointer();
+        frame.instruction = ip - CALL_SIZE;
+
+        Some(frame)
+    }
+}
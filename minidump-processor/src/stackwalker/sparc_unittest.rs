@@ -0,0 +1,241 @@
+// Copyright 2015 Ted Mielczarek. See the COPYRIGHT
+// file at the top-level directory of this distribution.
+
+use crate::process_state::*;
+use crate::stackwalker::walk_stack;
+use crate::{string_symbol_supplier, ProcessorOptions, Symbolizer};
+use minidump::format::CONTEXT_SPARC;
+use minidump::*;
+use std::collections::HashMap;
+use test_assembler::*;
+
+struct TestFixture {
+    pub raw: CONTEXT_SPARC,
+    pub modules: MinidumpModuleList,
+    pub symbols: HashMap<String, String>,
+}
+
+impl TestFixture {
+    pub fn new() -> TestFixture {
+        TestFixture {
+            raw: CONTEXT_SPARC::default(),
+            // Give the two modules reasonable standard locations and names
+            // for tests to play with.
+            modules: MinidumpModuleList::from_modules(vec![
+                MinidumpModule::new(0x40000000, 0x10000, "module1"),
+                MinidumpModule::new(0x50000000, 0x10000, "module2"),
+            ]),
+            symbols: HashMap::new(),
+        }
+    }
+
+    pub async fn walk_stack(&self, stack: Section) -> CallStack {
+        let context = MinidumpContext {
+            raw: MinidumpRawContext::Sparc(self.raw.clone()),
+            valid: MinidumpContextValidity::All,
+        };
+        let base = stack.start().value().unwrap();
+        let size = stack.size();
+        let stack = stack.get_contents().unwrap();
+        let stack_memory = MinidumpMemory {
+            desc: Default::default(),
+            base_address: base,
+            size,
+            bytes: &stack,
+        };
+        let symbolizer = Symbolizer::new(string_symbol_supplier(self.symbols.clone()));
+        walk_stack(
+            &Some(&context),
+            Some(&stack_memory),
+            &UnifiedMemoryList::default(),
+            &UnifiedMemoryInfoList::default(),
+            &self.modules,
+            &symbolizer,
+            &ProcessorOptions::default(),
+        )
+        .await
+    }
+}
+
+/// Appends a 32-bit window save area with the given saved %i6 and %i7.
+fn window(section: Section, l0: u32, fp: &Label, ra: u32) -> Section {
+    section
+        .D32(l0) // l0
+        .append_repeated(0, 7 * 4) // l1-l7
+        .append_repeated(0, 6 * 4) // i0-i5
+        .D32(fp) // i6
+        .D32(ra) // i7
+}
+
+#[tokio::test]
+async fn test_simple() {
+    let mut f = TestFixture::new();
+    let stack = Section::new();
+    stack.start().set_const(0x80000000);
+    // There should be no references to the stack in this walk: the frame
+    // pointer is null, and there's no stack to scan.
+    f.raw.set_register("pc", 0x4000c020);
+    f.raw.set_register("o6", 0x80000000);
+
+    let s = f.walk_stack(stack).await;
+    assert_eq!(s.frames.len(), 1);
+    let f = &s.frames[0];
+    let m = f.module.as_ref().unwrap();
+    assert_eq!(m.code_file(), "module1");
+}
+
+#[tokio::test]
+async fn test_frame_pointer() {
+    let mut f = TestFixture::new();
+    let mut stack = Section::new();
+    stack.start().set_const(0x80000000);
+
+    let call_address1 = 0x50000100u32;
+    let call_address2 = 0x50000900u32;
+    let frame1_sp = Label::new();
+    let frame2_sp = Label::new();
+    let null = Label::new();
+    null.set_const(0);
+
+    // frame 0
+    stack = window(stack, 0, &null, 0).append_repeated(0, 32);
+    // frame 1
+    stack = stack.mark(&frame1_sp);
+    stack = window(stack, 0x11111111, &frame2_sp, call_address2).append_repeated(0, 32);
+    // frame 2
+    stack = stack.mark(&frame2_sp);
+    stack = window(stack, 0x22222222, &null, 0).append_repeated(0, 32);
+
+    f.raw.set_register("pc", 0x40005510);
+    f.raw.set_register("o6", stack.start().value().unwrap());
+    f.raw.set_register("i6", frame1_sp.value().unwrap());
+    f.raw.set_register("i7", call_address1 as u64);
+    f.raw.set_register("i0", 0xb5d55e68);
+
+    let s = f.walk_stack(stack).await;
+    assert_eq!(s.frames.len(), 3);
+
+    {
+        // Frame 0
+        let frame = &s.frames[0];
+        assert_eq!(frame.trust, FrameTrust::Context);
+        assert_eq!(frame.context.valid, MinidumpContextValidity::All);
+    }
+
+    for (frame, call_address, sp, l0) in [
+        (&s.frames[1], call_address1, &frame1_sp, 0x11111111),
+        (&s.frames[2], call_address2, &frame2_sp, 0x22222222),
+    ] {
+        let valid = &frame.context.valid;
+        assert_eq!(frame.trust, FrameTrust::FramePointer);
+        assert_eq!(frame.instruction, call_address as u64);
+
+        if let MinidumpRawContext::Sparc(ctx) = &frame.context.raw {
+            assert_eq!(
+                ctx.get_register("pc", valid).unwrap(),
+                call_address as u64 + 8
+            );
+            assert_eq!(ctx.get_register("o6", valid).unwrap(), sp.value().unwrap());
+            // The caller's window is restored from its save area.
+            assert_eq!(ctx.get_register("l0", valid).unwrap(), l0);
+            // And its outs are the callee's ins.
+            assert_eq!(ctx.get_register("o7", valid).unwrap(), call_address as u64);
+        } else {
+            unreachable!();
+        }
+    }
+
+    if let MinidumpRawContext::Sparc(ctx) = &s.frames[1].context.raw {
+        let valid = &s.frames[1].context.valid;
+        assert_eq!(ctx.get_register("o0", valid), Some(0xb5d55e68));
+        // Globals aren't preserved.
+        assert_eq!(ctx.get_register("g1", valid), None);
+    } else {
+        unreachable!();
+    }
+}
+
+#[tokio::test]
+async fn test_frame_pointer_v9() {
+    // 64-bit frames have a biased stack pointer and 8 byte save slots.
+    let mut f = TestFixture::new();
+    let mut stack = Section::new();
+    stack.start().set_const(0x80000000);
+
+    let call_address = 0x50000100u64;
+    let frame1 = Label::new();
+
+    stack = stack
+        // frame 0
+        .append_repeated(0, 16 * 8)
+        // frame 1
+        .mark(&frame1)
+        .D64(0x11111111) // l0
+        .append_repeated(0, 13 * 8) // l1-l7, i0-i5
+        .D64(0) // i6
+        .D64(0) // i7
+        .append_repeated(0, 16);
+
+    let bias = 2047;
+    f.raw.set_register("pc", 0x40005510);
+    f.raw
+        .set_register("o6", stack.start().value().unwrap() - bias);
+    f.raw.set_register("i6", frame1.value().unwrap() - bias);
+    f.raw.set_register("i7", call_address);
+
+    let s = f.walk_stack(stack).await;
+    assert_eq!(s.frames.len(), 2);
+
+    let frame = &s.frames[1];
+    let valid = &frame.context.valid;
+    assert_eq!(frame.trust, FrameTrust::FramePointer);
+    if let MinidumpRawContext::Sparc(ctx) = &frame.context.raw {
+        assert_eq!(ctx.get_register("pc", valid), Some(call_address + 8));
+        assert_eq!(
+            ctx.get_register("o6", valid),
+            Some(frame1.value().unwrap() - bias)
+        );
+        assert_eq!(ctx.get_register("l0", valid), Some(0x11111111));
+    } else {
+        unreachable!();
+    }
+}
+
+#[tokio::test]
+async fn test_scan_without_symbols() {
+    // Scanning should work without any symbols
+    let mut f = TestFixture::new();
+    let mut stack = Section::new();
+    stack.start().set_const(0x80000000);
+
+    let call_address = 0x50000100u32;
+    let frame1_sp = Label::new();
+
+    stack = stack
+        .append_repeated(0, 16) // space
+        .D32(0x40090000) // junk that's not
+        .D32(0x60000000) // a return address
+        .D32(call_address) // actual return address
+        .mark(&frame1_sp)
+        .append_repeated(0, 32);
+
+    f.raw.set_register("pc", 0x40005510);
+    f.raw.set_register("o6", stack.start().value().unwrap());
+
+    let s = f.walk_stack(stack).await;
+    assert_eq!(s.frames.len(), 2);
+
+    let frame = &s.frames[1];
+    let valid = &frame.context.valid;
+    assert_eq!(frame.trust, FrameTrust::Scan);
+    assert_eq!(frame.instruction, call_address as u64);
+    if let MinidumpRawContext::Sparc(ctx) = &frame.context.raw {
+        assert_eq!(ctx.get_register("pc", valid), Some(call_address as u64 + 8));
+        assert_eq!(
+            ctx.get_register("o6", valid),
+            Some(frame1_sp.value().unwrap())
+        );
+    } else {
+        unreachable!();
+    }
+}
@@ -0,0 +1,187 @@
+// Copyright 2015 Ted Mielczarek. See the COPYRIGHT
+// file at the top-level directory of this distribution.
+
+//! A cache in front of the [`SymbolProvider`]'s symbol lookups.
+//!
+//! Besides symbolizing each frame, the stackwalker asks the symbol provider
+//! about the same few addresses over and over: every candidate a stack scan
+//! considers is checked against the symbols, and hot functions turn up in
+//! the frames of many threads. What the symbols say about an address doesn't
+//! change during a run, so each lookup is recorded the first time and
+//! replayed onto the frame after that.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use minidump::Module;
+
+use crate::{FillSymbolError, FrameSymbolizer, FrameWalker, SymbolProvider, SymbolStats};
+
+/// Wraps a [`SymbolProvider`], remembering what it filled in for each
+/// address.
+///
+/// This is meant to live for a single processing run and be shared by all of
+/// its threads.
+pub(crate) struct SymbolCache<'a, P> {
+    provider: &'a P,
+    /// What the provider told each frame, keyed by the address range of the
+    /// module and the instruction, or `None` if it had no symbols for the
+    /// module.
+    filled: Mutex<HashMap<(u64, u64, u64), Option<Vec<SymbolCall>>>>,
+}
+
+impl<'a, P> SymbolCache<'a, P> {
+    pub(crate) fn new(provider: &'a P) -> Self {
+        Self {
+            provider,
+            filled: Mutex::default(),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl<'a, P> SymbolProvider for SymbolCache<'a, P>
+where
+    P: SymbolProvider + Sync,
+{
+    async fn fill_symbol(
+        &self,
+        module: &(dyn Module + Sync),
+        frame: &mut (dyn FrameSymbolizer + Send),
+    ) -> Result<(), FillSymbolError> {
+        let key = (
+            module.base_address(),
+            module.size(),
+            frame.get_instruction(),
+        );
+        let cached = self.filled.lock().unwrap().get(&key).cloned();
+        if let Some(calls) = cached {
+            return match calls {
+                Some(calls) => {
+                    for call in &calls {
+                        call.replay(frame);
+                    }
+                    Ok(())
+                }
+                None => Err(FillSymbolError {}),
+            };
+        }
+
+        let mut recorder = RecordingFrame {
+            inner: frame,
+            calls: Vec::new(),
+        };
+        let result = self.provider.fill_symbol(module, &mut recorder).await;
+        let calls = result.as_ref().ok().map(|_| recorder.calls);
+        self.filled.lock().unwrap().insert(key, calls);
+        result
+    }
+
+    async fn walk_frame(
+        &self,
+        module: &(dyn Module + Sync),
+        walker: &mut (dyn FrameWalker + Send),
+    ) -> Option<()> {
+        self.provider.walk_frame(module, walker).await
+    }
+
+    fn stats(&self) -> HashMap<String, SymbolStats> {
+        self.provider.stats()
+    }
+
+    async fn prefetch(&self, modules: &[&(dyn Module + Sync)], concurrency: usize) {
+        self.provider.prefetch(modules, concurrency).await
+    }
+}
+
+/// Something the provider told a [`FrameSymbolizer`].
+#[derive(Clone)]
+enum SymbolCall {
+    Function {
+        name: String,
+        base: u64,
+        parameter_size: u32,
+    },
+    SourceFile {
+        file: String,
+        line: u32,
+        base: u64,
+    },
+    InlineFrame {
+        name: String,
+        file: Option<String>,
+        line: Option<u32>,
+    },
+    SourceUrl(String),
+    SourceLine {
+        file: String,
+        line: u32,
+    },
+}
+
+impl SymbolCall {
+    fn replay(&self, frame: &mut (dyn FrameSymbolizer + Send)) {
+        match self {
+            SymbolCall::Function {
+                name,
+                base,
+                parameter_size,
+            } => frame.set_function(name, *base, *parameter_size),
+            SymbolCall::SourceFile { file, line, base } => {
+                frame.set_source_file(file, *line, *base)
+            }
+            SymbolCall::InlineFrame { name, file, line } => {
+                frame.add_inline_frame(name, file.as_deref(), *line)
+            }
+            SymbolCall::SourceUrl(url) => frame.set_source_url(url),
+            SymbolCall::SourceLine { file, line } => frame.add_source_line(file, *line),
+        }
+    }
+}
+
+/// A [`FrameSymbolizer`] that notes down everything it's told.
+struct RecordingFrame<'f> {
+    inner: &'f mut (dyn FrameSymbolizer + Send),
+    calls: Vec<SymbolCall>,
+}
+
+impl<'f> FrameSymbolizer for RecordingFrame<'f> {
+    fn get_instruction(&self) -> u64 {
+        self.inner.get_instruction()
+    }
+    fn set_function(&mut self, name: &str, base: u64, parameter_size: u32) {
+        self.calls.push(SymbolCall::Function {
+            name: String::from(name),
+            base,
+            parameter_size,
+        });
+        self.inner.set_function(name, base, parameter_size)
+    }
+    fn set_source_file(&mut self, file: &str, line: u32, base: u64) {
+        self.calls.push(SymbolCall::SourceFile {
+            file: String::from(file),
+            line,
+            base,
+        });
+        self.inner.set_source_file(file, line, base)
+    }
+    fn add_inline_frame(&mut self, name: &str, file: Option<&str>, line: Option<u32>) {
+        self.calls.push(SymbolCall::InlineFrame {
+            name: String::from(name),
+            file: file.map(String::from),
+            line,
+        });
+        self.inner.add_inline_frame(name, file, line)
+    }
+    fn set_source_url(&mut self, url: &str) {
+        self.calls.push(SymbolCall::SourceUrl(String::from(url)));
+        self.inner.set_source_url(url)
+    }
+    fn add_source_line(&mut self, file: &str, line: u32) {
+        self.calls.push(SymbolCall::SourceLine {
+            file: String::from(file),
+            line,
+        });
+        self.inner.add_source_line(file, line)
+    }
+}
@@ -1,21 +1,27 @@
 // Copyright 2015 Ted Mielczarek. See the COPYRIGHT
 // file at the top-level directory of this distribution.
 
+use super::UnwindRecorder;
 use crate::process_state::StackFrame;
-use crate::SymbolProvider;
-use minidump::{MinidumpMemory, MinidumpModuleList};
+use crate::{ProcessorOptions, SymbolProvider};
+use minidump::{MinidumpMemory, MinidumpModuleList, UnifiedMemoryInfoList, UnifiedMemoryList};
 
 /// A trait for things that can unwind to a caller.
 #[async_trait::async_trait]
 pub trait Unwind {
     /// Get the caller frame of this frame.
+    #[allow(clippy::too_many_arguments)]
     async fn get_caller_frame<P>(
         &self,
         callee: &StackFrame,
         grand_callee: Option<&StackFrame>,
         stack_memory: Option<&MinidumpMemory<'_>>,
+        memory_list: &UnifiedMemoryList<'_>,
+        memory_info: &UnifiedMemoryInfoList<'_>,
         modules: &MinidumpModuleList,
         symbol_provider: &P,
+        options: &ProcessorOptions<'_>,
+        recorder: &mut UnwindRecorder,
     ) -> Option<StackFrame>
     where
         P: SymbolProvider + Sync;
@@ -6,24 +6,30 @@
 // so that it can be copied between the two with minimal changes. It's not
 // worth the effort to *actually* unify the implementations.
 
-use crate::process_state::{FrameTrust, StackFrame};
+use crate::process_state::{FrameTrust, StackFrame, UnwindStrategy};
+use crate::stackwalker::eh_frame;
 use crate::stackwalker::unwind::Unwind;
-use crate::stackwalker::CfiStackWalker;
-use crate::SymbolProvider;
-use log::trace;
+use crate::stackwalker::{CfiStackWalker, UnwindRecorder};
+use crate::{ProcessorOptions, SymbolProvider};
 use minidump::format::CONTEXT_X86;
 use minidump::{
     MinidumpContext, MinidumpContextValidity, MinidumpMemory, MinidumpModuleList,
-    MinidumpRawContext,
+    MinidumpRawContext, Module, UnifiedMemoryInfoList, UnifiedMemoryList,
 };
 use std::collections::HashSet;
+use tracing::trace;
 
 type Pointer = u32;
 const POINTER_WIDTH: Pointer = 4;
 const INSTRUCTION_REGISTER: &str = "eip";
 const STACK_POINTER_REGISTER: &str = "esp";
 const FRAME_POINTER_REGISTER: &str = "ebp";
-const CALLEE_SAVED_REGS: &[&str] = &["ebp", "ebx", "edi", "esi"];
+pub(super) const CALLEE_SAVED_REGS: &[&str] = &["ebp", "ebx", "edi", "esi"];
+
+/// DWARF register numbers for x86, as used by `.eh_frame`.
+const DWARF_REGISTERS: &[&str] = &[
+    "eax", "ecx", "edx", "ebx", "esp", "ebp", "esi", "edi", "eip",
+];
 
 async fn get_caller_by_cfi<P>(
     ctx: &CONTEXT_X86,
@@ -32,6 +38,7 @@ async fn get_caller_by_cfi<P>(
     stack_memory: &MinidumpMemory<'_>,
     modules: &MinidumpModuleList,
     symbol_provider: &P,
+    recorder: &mut UnwindRecorder,
 ) -> Option<StackFrame>
 where
     P: SymbolProvider + Sync,
@@ -63,11 +70,16 @@ where
         caller_validity: callee_forwarded_regs(valid),
 
         stack_memory,
+        modules,
+        rejected_reads: Default::default(),
     };
 
-    symbol_provider
-        .walk_frame(module, &mut stack_walker)
-        .await?;
+    let walked = symbol_provider.walk_frame(module, &mut stack_walker).await;
+    recorder.cfi_rejected_reads(&stack_walker);
+    if walked.is_none() {
+        recorder.reject("no cfi could be evaluated for the instruction");
+        return None;
+    }
     let caller_ip = stack_walker.caller_ctx.eip;
     let caller_sp = stack_walker.caller_ctx.esp;
 
@@ -104,6 +116,68 @@ where
     Some(StackFrame::from_context(context, FrameTrust::CallFrameInfo))
 }
 
+fn get_caller_by_eh_frame(
+    ctx: &CONTEXT_X86,
+    callee: &StackFrame,
+    grand_callee: Option<&StackFrame>,
+    stack_memory: &MinidumpMemory<'_>,
+    memory_list: &UnifiedMemoryList<'_>,
+    modules: &MinidumpModuleList,
+    recorder: &mut UnwindRecorder,
+) -> Option<StackFrame> {
+    trace!("unwind: trying eh_frame");
+
+    let valid = &callee.context.valid;
+    if let MinidumpContextValidity::Some(ref which) = valid {
+        if !which.contains(STACK_POINTER_REGISTER) {
+            return None;
+        }
+    }
+
+    let module = modules.module_at_address(callee.instruction)?;
+
+    let grand_callee_parameter_size = grand_callee.and_then(|f| f.parameter_size).unwrap_or(0);
+
+    let mut stack_walker = CfiStackWalker {
+        instruction: callee.instruction,
+        grand_callee_parameter_size,
+
+        callee_ctx: ctx,
+        callee_validity: valid,
+
+        caller_ctx: ctx.clone(),
+        caller_validity: callee_forwarded_regs(valid),
+
+        stack_memory,
+        modules,
+        rejected_reads: Default::default(),
+    };
+
+    let walked = eh_frame::walk_frame(
+        memory_list,
+        module.base_address(),
+        DWARF_REGISTERS,
+        &mut stack_walker,
+    );
+    recorder.cfi_rejected_reads(&stack_walker);
+    if walked.is_none() {
+        recorder.reject("no eh_frame entry could be evaluated for the instruction");
+        return None;
+    }
+
+    trace!(
+        "unwind: eh_frame evaluation was successful -- caller_ip: 0x{:016x}, caller_sp: 0x{:016x}",
+        stack_walker.caller_ctx.eip,
+        stack_walker.caller_ctx.esp,
+    );
+
+    let context = MinidumpContext {
+        raw: MinidumpRawContext::X86(stack_walker.caller_ctx),
+        valid: MinidumpContextValidity::Some(stack_walker.caller_validity),
+    };
+    Some(StackFrame::from_context(context, FrameTrust::CallFrameInfo))
+}
+
 fn callee_forwarded_regs(valid: &MinidumpContextValidity) -> HashSet<&'static str> {
     match valid {
         MinidumpContextValidity::All => CALLEE_SAVED_REGS.iter().copied().collect(),
@@ -121,6 +195,7 @@ fn get_caller_by_frame_pointer<P>(
     stack_memory: &MinidumpMemory<'_>,
     _modules: &MinidumpModuleList,
     _symbol_provider: &P,
+    recorder: &mut UnwindRecorder,
 ) -> Option<StackFrame>
 where
     P: SymbolProvider + Sync,
@@ -162,8 +237,8 @@ where
         // drowning the rest of the code in checked_add.
         return None;
     }
-    let caller_ip = stack_memory.get_memory_at_address(last_bp as u64 + POINTER_WIDTH as u64)?;
-    let caller_bp = stack_memory.get_memory_at_address(last_bp as u64)?;
+    let caller_ip = recorder.read(stack_memory, last_bp as u64 + POINTER_WIDTH as u64)?;
+    let caller_bp = recorder.read(stack_memory, last_bp as u64)?;
     let caller_sp = last_bp + POINTER_WIDTH * 2;
 
     // NOTE: minor divergence from x64 impl here: doing extra validation on the
@@ -195,12 +270,16 @@ where
     Some(StackFrame::from_context(context, FrameTrust::FramePointer))
 }
 
+#[allow(clippy::too_many_arguments)]
 async fn get_caller_by_scan<P>(
     ctx: &CONTEXT_X86,
     callee: &StackFrame,
     stack_memory: &MinidumpMemory<'_>,
+    memory_info: &UnifiedMemoryInfoList<'_>,
     modules: &MinidumpModuleList,
     symbol_provider: &P,
+    options: &ProcessorOptions<'_>,
+    recorder: &mut UnwindRecorder,
 ) -> Option<StackFrame>
 where
     P: SymbolProvider + Sync,
@@ -217,6 +296,7 @@ where
         MinidumpContextValidity::Some(ref which) => {
             if !which.contains(STACK_POINTER_REGISTER) {
                 trace!("unwind: cannot scan without stack pointer");
+                recorder.reject("no stack pointer to scan from");
                 return None;
             }
             if which.contains(FRAME_POINTER_REGISTER) {
@@ -229,21 +309,12 @@ where
     let last_sp = ctx.esp;
 
     // Number of pointer-sized values to scan through in our search.
-    let default_scan_range = 40;
-    let extended_scan_range = default_scan_range * 4;
-
-    // Breakpad devs found that the first frame of an unwind can be really messed up,
-    // and therefore benefits from a longer scan. Let's do it too.
-    let scan_range = if let FrameTrust::Context = callee.trust {
-        extended_scan_range
-    } else {
-        default_scan_range
-    };
+    let scan_range = super::scan_range(callee, options) as Pointer;
 
     for i in 0..scan_range {
         let address_of_ip = last_sp.checked_add(i * POINTER_WIDTH)?;
-        let caller_ip = stack_memory.get_memory_at_address(address_of_ip as u64)?;
-        if instruction_seems_valid(caller_ip, modules, symbol_provider).await {
+        let caller_ip = recorder.read(stack_memory, address_of_ip as u64)?;
+        if instruction_seems_valid(caller_ip, memory_info, modules, symbol_provider).await {
             // ip is pushed by CALL, so sp is just address_of_ip + ptr
             let caller_sp = address_of_ip.checked_add(POINTER_WIDTH)?;
 
@@ -269,7 +340,7 @@ where
             const MAX_REASONABLE_GAP_BETWEEN_FRAMES: Pointer = 128 * 1024;
 
             let address_of_bp = address_of_ip - POINTER_WIDTH;
-            let bp = stack_memory.get_memory_at_address(address_of_bp as u64)?;
+            let bp = recorder.read(stack_memory, address_of_bp as u64)?;
             if bp > address_of_ip && bp - address_of_bp <= MAX_REASONABLE_GAP_BETWEEN_FRAMES {
                 // Sanity check that resulting bp is still inside stack memory.
                 if stack_memory
@@ -344,6 +415,7 @@ where
 /// and immediately start doing unreliable scans.
 async fn instruction_seems_valid<P>(
     instruction: Pointer,
+    memory_info: &UnifiedMemoryInfoList<'_>,
     modules: &MinidumpModuleList,
     symbol_provider: &P,
 ) -> bool
@@ -354,7 +426,13 @@ where
         return false;
     }
 
-    super::instruction_seems_valid_by_symbols(instruction as u64, modules, symbol_provider).await
+    super::instruction_seems_valid_by_symbols(
+        instruction as u64,
+        memory_info,
+        modules,
+        symbol_provider,
+    )
+    .await
 }
 
 /*
@@ -384,8 +462,12 @@ impl Unwind for CONTEXT_X86 {
         callee: &StackFrame,
         grand_callee: Option<&StackFrame>,
         stack_memory: Option<&MinidumpMemory<'_>>,
+        memory_list: &UnifiedMemoryList<'_>,
+        memory_info: &UnifiedMemoryInfoList<'_>,
         modules: &MinidumpModuleList,
         syms: &P,
+        options: &ProcessorOptions<'_>,
+        recorder: &mut UnwindRecorder,
     ) -> Option<StackFrame>
     where
         P: SymbolProvider + Sync,
@@ -395,13 +477,39 @@ impl Unwind for CONTEXT_X86 {
         // .await doesn't like closures, so don't use Option chaining
         let mut frame = None;
         if frame.is_none() {
-            frame = get_caller_by_cfi(self, callee, grand_callee, stack, modules, syms).await;
+            recorder.attempt(UnwindStrategy::CallFrameInfo);
+            frame =
+                get_caller_by_cfi(self, callee, grand_callee, stack, modules, syms, recorder).await;
         }
         if frame.is_none() {
-            frame = get_caller_by_frame_pointer(self, callee, stack, modules, syms);
+            recorder.attempt(UnwindStrategy::EhFrame);
+            frame = get_caller_by_eh_frame(
+                self,
+                callee,
+                grand_callee,
+                stack,
+                memory_list,
+                modules,
+                recorder,
+            );
         }
         if frame.is_none() {
-            frame = get_caller_by_scan(self, callee, stack, modules, syms).await;
+            recorder.attempt(UnwindStrategy::FramePointer);
+            frame = get_caller_by_frame_pointer(self, callee, stack, modules, syms, recorder);
+        }
+        if frame.is_none() && options.allow_scan {
+            recorder.attempt(UnwindStrategy::Scan);
+            frame = get_caller_by_scan(
+                self,
+                callee,
+                stack,
+                memory_info,
+                modules,
+                syms,
+                options,
+                recorder,
+            )
+            .await;
         }
         let mut frame = frame?;
 
@@ -413,6 +521,7 @@ impl Unwind for CONTEXT_X86 {
         // null, and we can assume unwinding is complete.
         if frame.context.get_instruction_pointer() < 4096 {
             trace!("unwind: instruction pointer was nullish, assuming unwind complete");
+            recorder.reject("instruction pointer was nullish");
             return None;
         }
         // If the new stack pointer is at a lower address than the old,
@@ -420,6 +529,7 @@ impl Unwind for CONTEXT_X86 {
         // enforce progress and avoid infinite loops.
         if frame.context.get_stack_pointer() <= self.esp as u64 {
             trace!("unwind: stack pointer went backwards, assuming unwind complete");
+            recorder.reject("stack pointer went backwards");
             return None;
         }
 
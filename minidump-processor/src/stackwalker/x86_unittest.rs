@@ -3,7 +3,7 @@
 
 use crate::process_state::*;
 use crate::stackwalker::walk_stack;
-use crate::{string_symbol_supplier, Symbolizer};
+use crate::{string_symbol_supplier, ProcessorOptions, Symbolizer};
 use minidump::format::CONTEXT_X86;
 use minidump::*;
 use std::collections::HashMap;
@@ -47,8 +47,11 @@ impl TestFixture {
         walk_stack(
             &Some(&context),
             Some(&stack_memory),
+            &UnifiedMemoryList::default(),
+            &UnifiedMemoryInfoList::default(),
             &self.modules,
             &symbolizer,
+            &ProcessorOptions::default(),
         )
         .await
     }
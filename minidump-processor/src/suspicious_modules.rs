@@ -0,0 +1,186 @@
+// Copyright 2015 Ted Mielczarek. See the COPYRIGHT
+// file at the top-level directory of this distribution.
+
+//! Finding modules that look like they were injected into the process.
+//!
+//! Malware and other unwanted software gets its code into a process by
+//! loading a DLL (or shared library) into it, and such modules tend to stand
+//! out: they're loaded from somewhere no installed software lives, they're
+//! hidden from the module list, or they're the only unsigned code in an
+//! otherwise signed process. None of these prove anything on their own, so
+//! they're only reported.
+
+use std::collections::HashMap;
+use std::fmt;
+
+use minidump::format::MemoryType;
+use minidump::{
+    MinidumpLinuxMapKind, MinidumpModuleList, Module, UnifiedMemoryInfo, UnifiedMemoryInfoList,
+};
+
+use crate::process_state::basename;
+
+/// Parts of paths (lowercased, with `/` separators) that only temporary or
+/// user-writable directories have.
+const UNUSUAL_PATH_PARTS: &[&str] = &[
+    "/temp/",
+    "/tmp/",
+    "/downloads/",
+    "/users/public/",
+    "/$recycle.bin/",
+    "/var/folders/",
+    "/dev/shm/",
+];
+
+/// Why a module is suspicious.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SuspiciousModuleReason {
+    /// The module was loaded from a temporary or user-writable directory,
+    /// or from memory (like a Linux `memfd`).
+    UnusualPath,
+    /// The memory info has an image mapped where the module list has no
+    /// module, so the module was hidden or loaded by hand.
+    Unlisted,
+    /// The module isn't signed, but the main module is.
+    Unsigned,
+}
+
+impl SuspiciousModuleReason {
+    /// The name of the reason in the JSON schema.
+    pub fn json_name(self) -> &'static str {
+        match self {
+            SuspiciousModuleReason::UnusualPath => "unusual_path",
+            SuspiciousModuleReason::Unlisted => "unlisted",
+            SuspiciousModuleReason::Unsigned => "unsigned",
+        }
+    }
+}
+
+impl fmt::Display for SuspiciousModuleReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SuspiciousModuleReason::UnusualPath => f.write_str("loaded from an unusual path"),
+            SuspiciousModuleReason::Unlisted => f.write_str("missing from the module list"),
+            SuspiciousModuleReason::Unsigned => f.write_str("unsigned in a signed process"),
+        }
+    }
+}
+
+/// A module that looks like it was injected into the process.
+///
+/// See [`ProcessorOptions::detect_suspicious_modules`][crate::ProcessorOptions::detect_suspicious_modules].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SuspiciousModule {
+    /// The module's code file, or `None` if it isn't in the module list.
+    pub code_file: Option<String>,
+    /// Where the module starts.
+    pub base_address: u64,
+    /// How big the module is, in bytes.
+    pub size: u64,
+    /// Why the module is suspicious.
+    pub reason: SuspiciousModuleReason,
+}
+
+/// Look for suspicious modules among `modules`, and in `memory_info` for
+/// modules missing from them.
+pub(crate) fn find_suspicious_modules(
+    modules: &MinidumpModuleList,
+    memory_info: Option<&UnifiedMemoryInfoList>,
+    cert_info: &HashMap<String, String>,
+) -> Vec<SuspiciousModule> {
+    let is_signed = |code_file: &str| cert_info.contains_key(basename(code_file));
+    let signed_process = modules
+        .main_module()
+        .map_or(false, |main| is_signed(&main.code_file()));
+
+    let mut suspicious = vec![];
+    for module in modules.by_addr() {
+        let code_file = module.code_file();
+        let mut report = |reason| {
+            suspicious.push(SuspiciousModule {
+                code_file: Some(code_file.clone().into_owned()),
+                base_address: module.base_address(),
+                size: module.size(),
+                reason,
+            })
+        };
+        if is_unusual_path(&code_file) {
+            report(SuspiciousModuleReason::UnusualPath);
+        }
+        if signed_process && !is_signed(&code_file) {
+            report(SuspiciousModuleReason::Unsigned);
+        }
+    }
+
+    // Images take several regions (one for each section), so merge the
+    // adjacent ones.
+    let mut unlisted: Vec<std::ops::Range<u64>> = vec![];
+    for region in memory_info.into_iter().flat_map(|info| info.by_addr()) {
+        // Memory info ranges include their last address.
+        let range = match region.memory_range() {
+            Some(range) if is_image(&region) => range.start..range.end.saturating_add(1),
+            _ => continue,
+        };
+        if modules.module_at_address(range.start).is_some() {
+            continue;
+        }
+        match unlisted.last_mut() {
+            Some(last) if last.end == range.start => last.end = range.end,
+            _ => unlisted.push(range),
+        }
+    }
+    suspicious.extend(unlisted.into_iter().map(|range| SuspiciousModule {
+        code_file: None,
+        base_address: range.start,
+        size: range.end - range.start,
+        reason: SuspiciousModuleReason::Unlisted,
+    }));
+
+    // Keep the reasons for each module together.
+    suspicious.sort_by_key(|module| module.base_address);
+    suspicious
+}
+
+/// Whether `code_file` is somewhere installed software doesn't live.
+fn is_unusual_path(code_file: &str) -> bool {
+    let path = code_file.replace('\\', "/").to_lowercase();
+    path.starts_with("/memfd:") || UNUSUAL_PATH_PARTS.iter().any(|part| path.contains(part))
+}
+
+/// Whether `region` has a module's code mapped into it.
+fn is_image(region: &UnifiedMemoryInfo) -> bool {
+    match region {
+        UnifiedMemoryInfo::Info(info) => info.ty.contains(MemoryType::MEM_IMAGE),
+        UnifiedMemoryInfo::Map(map) => {
+            map.is_exec
+                && matches!(
+                    map.kind,
+                    MinidumpLinuxMapKind::File(_) | MinidumpLinuxMapKind::DeletedFile(_)
+                )
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_is_unusual_path() {
+        assert!(is_unusual_path(
+            r"C:\Users\me\AppData\Local\Temp\inject.dll"
+        ));
+        assert!(is_unusual_path(r"C:\Users\Public\x.dll"));
+        assert!(is_unusual_path("/tmp/libevil.so"));
+        assert!(is_unusual_path("/memfd:payload (deleted)"));
+        assert!(is_unusual_path(
+            "/private/var/folders/xy/abc/T/libevil.dylib"
+        ));
+
+        assert!(!is_unusual_path(r"C:\Windows\System32\kernel32.dll"));
+        assert!(!is_unusual_path(r"C:\Program Files\App\app.exe"));
+        assert!(!is_unusual_path("/usr/lib/libc.so.6"));
+    }
+}
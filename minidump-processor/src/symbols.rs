@@ -13,9 +13,13 @@
 //! * [SymbolProvider][] - provides symbolication, cfi evaluation, and debug statistics
 //!     * Implemented by [Symbolizer][]
 //!     * This actually doesn't need to be a trait in the current design, it exists to allow
-//!       multiple symbolicators to be used together, via [MultiSymbolProvider][]. The other
-//!       SymbolProviders have been removed, but I figured it would be a waste to throw out
-//!       this minimally intrusive machinery.
+//!       multiple symbolicators to be used together, via [MultiSymbolProvider][], which asks
+//!       each of them in turn (e.g. a local directory, then a symbol server).
+//!     * Symbol sources built outside of this crate can implement the simpler, versioned
+//!       [SymbolPlugin][crate::SymbolPlugin] instead, and be wrapped in a
+//!       [PluginSymbolProvider][crate::PluginSymbolProvider].
+//!     * With the `symcache` feature, `SymCacheSymbolProvider` gets symbols and CFI from the
+//!       SymCache and CfiCache files of the `symbolic` crate.
 //!
 //! * [SymbolSupplier][] - maps a [Module][] to a [SymbolFile][]
 //!     * minidump-processor does not directly use this, it's just there so the Symbolizer can
@@ -38,6 +42,7 @@
 //! minidump-processor uses to select and configure the symbol fetching strategy:
 //!
 //! * [http_symbol_supplier][] - a [SymbolSupplier][] that can find symbols over HTTP (and cache).
+//!     * Not on wasm32, which has `fetch_symbol_supplier` (with the `fetch` feature) instead.
 //! * [simple_symbol_supplier][] - a [SymbolSupplier][] that can find symbols on disk.
 //! * [string_symbol_supplier][] - a mock [SymbolSupplier][] for tests.
 //!
@@ -98,9 +103,15 @@
 
 use async_trait::async_trait;
 use minidump::Module;
+use std::collections::hash_map::Entry;
 use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::Duration;
 pub use symbols_shim::*;
 
+/// The default for [`SymbolConfig::timeout`].
+pub const DEFAULT_SYMBOL_DOWNLOAD_TIMEOUT: Duration = Duration::from_secs(1000);
+
 #[async_trait]
 pub trait SymbolProvider {
     async fn fill_symbol(
@@ -114,8 +125,60 @@ pub trait SymbolProvider {
         walker: &mut (dyn FrameWalker + Send),
     ) -> Option<()>;
     fn stats(&self) -> HashMap<String, SymbolStats>;
+    /// Get ready to provide symbols for all of `modules`, with up to
+    /// `concurrency` of them being fetched at once (see
+    /// [`ProcessorOptions::prefetch_symbols`][crate::ProcessorOptions::prefetch_symbols]).
+    ///
+    /// The default does nothing, leaving symbols to be fetched as they're
+    /// needed.
+    async fn prefetch(&self, _modules: &[&(dyn Module + Sync)], _concurrency: usize) {}
 }
 
+/// Boxed providers are providers too, so a provider assembled from runtime
+/// configuration can be passed to the processor as a
+/// `Box<dyn SymbolProvider + Send + Sync>`.
+#[async_trait]
+impl<P: SymbolProvider + Sync + ?Sized> SymbolProvider for Box<P> {
+    async fn fill_symbol(
+        &self,
+        module: &(dyn Module + Sync),
+        frame: &mut (dyn FrameSymbolizer + Send),
+    ) -> Result<(), FillSymbolError> {
+        (**self).fill_symbol(module, frame).await
+    }
+
+    async fn walk_frame(
+        &self,
+        module: &(dyn Module + Sync),
+        walker: &mut (dyn FrameWalker + Send),
+    ) -> Option<()> {
+        (**self).walk_frame(module, walker).await
+    }
+
+    fn stats(&self) -> HashMap<String, SymbolStats> {
+        (**self).stats()
+    }
+
+    async fn prefetch(&self, modules: &[&(dyn Module + Sync)], concurrency: usize) {
+        (**self).prefetch(modules, concurrency).await
+    }
+}
+
+/// A [`SymbolProvider`] that chains several others together.
+///
+/// Providers are asked in the order they were added, so they should go from
+/// most to least preferred (e.g. a local directory, then an internal symbol
+/// server, then a public one):
+///
+/// * `fill_symbol` uses the first provider that has symbols for the module
+///   (returns `Ok`), even if they don't cover the frame's instruction. Later
+///   providers are never asked about that module.
+/// * `walk_frame` uses the first provider that manages to walk the frame.
+/// * `stats` has an entry for every module any provider has stats for. If
+///   several providers do, the first one that loaded symbols for it wins, or
+///   else the first one that tried.
+/// * `prefetch` only prefetches from the first provider, since the others are
+///   only asked about modules it doesn't have symbols for.
 #[derive(Default)]
 pub struct MultiSymbolProvider {
     providers: Vec<Box<dyn SymbolProvider + Send + Sync>>,
@@ -126,6 +189,7 @@ impl MultiSymbolProvider {
         Default::default()
     }
 
+    /// Add a provider, to be asked after all the ones already added.
     pub fn add(&mut self, provider: Box<dyn SymbolProvider + Send + Sync>) {
         self.providers.push(provider);
     }
@@ -138,15 +202,16 @@ impl SymbolProvider for MultiSymbolProvider {
         module: &(dyn Module + Sync),
         frame: &mut (dyn FrameSymbolizer + Send),
     ) -> Result<(), FillSymbolError> {
-        // Return Ok if *any* symbol provider came back with Ok, so that the user can
-        // distinguish between having no symbols at all and just not being able to
-        // symbolize this particular frame.
-        let mut best_result = Err(FillSymbolError {});
+        // An Ok means the provider has symbols for this module, which are
+        // presumably the same as any other provider's, so stop there. That
+        // also lets the user distinguish between having no symbols at all and
+        // just not being able to symbolize this particular frame.
         for p in self.providers.iter() {
-            let new_result = p.fill_symbol(module, frame).await;
-            best_result = best_result.or(new_result);
+            if p.fill_symbol(module, frame).await.is_ok() {
+                return Ok(());
+            }
         }
-        best_result
+        Err(FillSymbolError {})
     }
 
     async fn walk_frame(
@@ -166,18 +231,144 @@ impl SymbolProvider for MultiSymbolProvider {
     fn stats(&self) -> HashMap<String, SymbolStats> {
         let mut result = HashMap::new();
         for p in self.providers.iter() {
-            // FIXME: do more intelligent merging of the stats
-            // (currently doesn't matter as only one provider reports non-empty stats).
-            result.extend(p.stats());
+            for (name, stats) in p.stats() {
+                match result.entry(name) {
+                    Entry::Vacant(entry) => {
+                        entry.insert(stats);
+                    }
+                    Entry::Occupied(mut entry) => {
+                        if !entry.get().loaded_symbols && stats.loaded_symbols {
+                            entry.insert(stats);
+                        }
+                    }
+                }
+            }
         }
         result
     }
+
+    async fn prefetch(&self, modules: &[&(dyn Module + Sync)], concurrency: usize) {
+        if let Some(p) = self.providers.first() {
+            p.prefetch(modules, concurrency).await;
+        }
+    }
+}
+
+/// Where to look for symbols, for [`process_path`][crate::process_path] and
+/// [`process_bytes`][crate::process_bytes].
+///
+/// The defaults are the same as minidump-stackwalk's, and look nowhere.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct SymbolConfig {
+    /// Directories of symbol files, searched in order before any server.
+    pub symbol_paths: Vec<PathBuf>,
+    /// The base urls of symbol servers, queried in order.
+    pub symbol_urls: Vec<String>,
+    /// Where to cache symbols downloaded from the servers (see
+    /// [`http_symbol_supplier`]), or a `rust-minidump-cache` subdirectory
+    /// of [`std::env::temp_dir`] if this is `None`.
+    pub symbols_cache: Option<PathBuf>,
+    /// Where to download symbols to before moving them into the cache, or
+    /// [`std::env::temp_dir`] if this is `None`.
+    pub symbols_tmp: Option<PathBuf>,
+    /// The longest a symbol file download is allowed to take.
+    pub timeout: Duration,
+}
+
+impl Default for SymbolConfig {
+    fn default() -> Self {
+        Self {
+            symbol_paths: Vec::new(),
+            symbol_urls: Vec::new(),
+            symbols_cache: None,
+            symbols_tmp: None,
+            timeout: DEFAULT_SYMBOL_DOWNLOAD_TIMEOUT,
+        }
+    }
+}
+
+impl SymbolConfig {
+    /// Look for symbol files in `path`, after the paths already added.
+    pub fn with_symbol_path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.symbol_paths.push(path.into());
+        self
+    }
+
+    /// Download symbols from the server at `url`, after the servers already
+    /// added.
+    pub fn with_symbol_url(mut self, url: impl Into<String>) -> Self {
+        self.symbol_urls.push(url.into());
+        self
+    }
+
+    /// Cache downloaded symbols in `path`, see
+    /// [`symbols_cache`][Self::symbols_cache].
+    pub fn with_symbols_cache(mut self, path: impl Into<PathBuf>) -> Self {
+        self.symbols_cache = Some(path.into());
+        self
+    }
+
+    /// Download symbols to `path` first, see
+    /// [`symbols_tmp`][Self::symbols_tmp].
+    pub fn with_symbols_tmp(mut self, path: impl Into<PathBuf>) -> Self {
+        self.symbols_tmp = Some(path.into());
+        self
+    }
+
+    /// Give up on symbol file downloads after `timeout`, see
+    /// [`timeout`][Self::timeout].
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// A [`Symbolizer`] that looks for symbols where this says to.
+    ///
+    /// On wasm32 there are no symbol paths or caches, so symbols are only
+    /// downloaded, with `fetch_symbol_supplier` (which needs the `fetch`
+    /// feature).
+    pub fn symbolizer(&self) -> Symbolizer {
+        if self.symbol_urls.is_empty() {
+            return Symbolizer::new(simple_symbol_supplier(self.symbol_paths.clone()));
+        }
+        self.download_symbolizer()
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn download_symbolizer(&self) -> Symbolizer {
+        let temp_dir = std::env::temp_dir();
+        let symbols_cache = self
+            .symbols_cache
+            .clone()
+            .unwrap_or_else(|| temp_dir.join("rust-minidump-cache"));
+        let symbols_tmp = self.symbols_tmp.clone().unwrap_or(temp_dir);
+        Symbolizer::new(http_symbol_supplier(
+            self.symbol_paths.clone(),
+            self.symbol_urls.clone(),
+            symbols_cache,
+            symbols_tmp,
+            self.timeout,
+        ))
+    }
+
+    #[cfg(all(target_arch = "wasm32", feature = "fetch"))]
+    fn download_symbolizer(&self) -> Symbolizer {
+        Symbolizer::new(fetch_symbol_supplier(self.symbol_urls.clone()))
+    }
+
+    #[cfg(all(target_arch = "wasm32", not(feature = "fetch")))]
+    fn download_symbolizer(&self) -> Symbolizer {
+        Symbolizer::new(simple_symbol_supplier(self.symbol_paths.clone()))
+    }
 }
 
 #[cfg(feature = "breakpad-syms")]
 mod symbols_shim {
     use super::SymbolProvider;
     use async_trait::async_trait;
+    #[cfg(not(target_arch = "wasm32"))]
+    pub use breakpad_symbols::{FetchEvent, HttpSymbolSupplier};
     pub use breakpad_symbols::{
         FillSymbolError, FrameSymbolizer, FrameWalker, SymbolError, SymbolFile, SymbolStats,
         SymbolSupplier, Symbolizer,
@@ -185,6 +376,7 @@ mod symbols_shim {
     use minidump::Module;
     use std::collections::HashMap;
     use std::path::PathBuf;
+    #[cfg(not(target_arch = "wasm32"))]
     use std::time::Duration;
 
     #[async_trait]
@@ -206,6 +398,9 @@ mod symbols_shim {
         fn stats(&self) -> HashMap<String, SymbolStats> {
             self.stats()
         }
+        async fn prefetch(&self, modules: &[&(dyn Module + Sync)], concurrency: usize) {
+            self.prefetch(modules.iter().copied(), concurrency).await
+        }
     }
 
     /// Gets a SymbolSupplier that looks up symbols by path or with urls.
@@ -232,6 +427,10 @@ mod symbols_shim {
     ///   As of this writing, minidump-stackwalk defaults this to 1000 seconds. In
     ///   the event of a timeout, the supplier may still try to parse the truncated
     ///   download.
+    ///
+    /// This isn't available on wasm32, which has nowhere to cache symbols; use
+    /// `fetch_symbol_supplier` there instead.
+    #[cfg(not(target_arch = "wasm32"))]
     pub fn http_symbol_supplier(
         symbol_paths: Vec<PathBuf>,
         symbol_urls: Vec<String>,
@@ -248,6 +447,17 @@ mod symbols_shim {
         )
     }
 
+    /// Gets a SymbolSupplier that looks up symbols with urls, without caching
+    /// them anywhere.
+    ///
+    /// `symbols_urls` is a list of "base urls", queried in order as for
+    /// [`http_symbol_supplier`]. This works on wasm32, where the browser's
+    /// `fetch` is used to download symbols.
+    #[cfg(feature = "fetch")]
+    pub fn fetch_symbol_supplier(symbol_urls: Vec<String>) -> impl SymbolSupplier {
+        breakpad_symbols::FetchSymbolSupplier::new(symbol_urls)
+    }
+
     /// Gets a SymbolSupplier that looks up symbols by path.
     ///
     /// Paths are queried in order until one returns a payload.
@@ -297,6 +507,24 @@ mod symbols_shim {
         fn set_function(&mut self, name: &str, base: u64, parameter_size: u32);
         /// Set the source file and (1-based) line number this frame represents.
         fn set_source_file(&mut self, file: &str, line: u32, base: u64);
+        /// Add a function which was inlined at this frame's instruction.
+        ///
+        /// This is called after `set_function` once for each inlined call, from
+        /// the outermost to the innermost, with the source position in the inlined
+        /// function. Frames which don't care about inlining can ignore this.
+        fn add_inline_frame(&mut self, _name: &str, _file: Option<&str>, _line: Option<u32>) {}
+        /// Set the URL the source file can be found at.
+        ///
+        /// This is called right after `set_source_file` or `add_inline_frame`, for
+        /// the source file that was just given, if the symbols know its URL.
+        fn set_source_url(&mut self, _url: &str) {}
+        /// Add another source line this frame's instruction is also from.
+        ///
+        /// Optimized code often merges the instructions of several lines. This is
+        /// called for each line past the first, for the function that was last
+        /// given to `set_source_file` or `add_inline_frame`. Frames which only want
+        /// one line can ignore this.
+        fn add_source_line(&mut self, _file: &str, _line: u32) {}
     }
 
     pub trait FrameWalker {
@@ -318,6 +546,13 @@ mod symbols_shim {
         fn set_cfa(&mut self, val: u64) -> Option<()>;
         /// Set whatever registers in the caller should be set based on the return address (e.g. rip).
         fn set_ra(&mut self, val: u64) -> Option<()>;
+        /// Whether `address` could plausibly be a return address (e.g. it's in a loaded module).
+        ///
+        /// This is used to search the stack when STACK WIN info points at the wrong spot.
+        /// By default every address is plausible, which disables those searches.
+        fn instruction_seems_valid(&self, _address: u64) -> bool {
+            true
+        }
     }
 
     /// Symbolicate stack frames.
@@ -351,6 +586,11 @@ mod symbols_shim {
                 supplier: Box::new(supplier),
             }
         }
+
+        /// Keep the memory used by loaded symbols to roughly `max_resident_bytes`.
+        pub fn with_memory_budget(self, _max_resident_bytes: usize) -> Symbolizer {
+            self
+        }
     }
 
     #[async_trait]
@@ -466,10 +706,22 @@ mod symbols_shim {
     pub struct SymbolFile {}
 
     /// Statistics on the symbols of a module.
-    #[derive(Default, Debug)]
+    #[derive(Default, Debug, Clone)]
     pub struct SymbolStats {
         /// If the module's symbols were downloaded, this is the url used.
         pub symbol_url: Option<String>,
+        /// If the module's symbols were loaded from local disk, this is the path
+        /// used.
+        pub symbol_path: Option<PathBuf>,
+        /// The size of the symbol file, in bytes.
+        pub symbol_file_size: Option<u64>,
+        /// How long it took to download the symbols, if they were downloaded.
+        pub fetch_duration: Option<Duration>,
+        /// How long it took to parse the symbols.
+        pub parse_duration: Option<Duration>,
+        /// If the symbols were found in a cache of downloaded (or converted)
+        /// symbols.
+        pub cache_hit: bool,
         /// If the symbols were found and loaded into memory.
         pub loaded_symbols: bool,
         /// If we tried to parse the symbols, but failed.
@@ -515,3 +767,71 @@ mod symbols_shim {
         }
     }
 }
+
+#[cfg(all(test, feature = "breakpad-syms"))]
+mod test {
+    use super::*;
+    use breakpad_symbols::{SimpleFrame, SimpleModule};
+
+    /// A provider with symbols for each of `(module name, function name)`.
+    fn provider(modules: &[(&str, &str)]) -> Box<dyn SymbolProvider + Send + Sync> {
+        let modules = modules
+            .iter()
+            .map(|(name, func)| {
+                let symbols = format!(
+                    "MODULE Linux x86 abcd1234 {}\nFUNC 1000 30 10 {}\n",
+                    name, func
+                );
+                (name.to_string(), symbols)
+            })
+            .collect();
+        Box::new(Symbolizer::new(string_symbol_supplier(modules)))
+    }
+
+    async fn lookup(provider: &MultiSymbolProvider, name: &str, address: u64) -> Option<String> {
+        let module = SimpleModule {
+            code_file: Some(name.to_string()),
+            ..SimpleModule::new(name, "abcd1234")
+        };
+        let mut frame = SimpleFrame::with_instruction(address);
+        provider.fill_symbol(&module, &mut frame).await.ok()?;
+        Some(frame.function.unwrap_or_default())
+    }
+
+    #[tokio::test]
+    async fn test_multi_symbol_provider() {
+        let mut multi = MultiSymbolProvider::new();
+        multi.add(provider(&[("a", "first a")]));
+        multi.add(provider(&[("a", "second a"), ("b", "second b")]));
+
+        // The first provider with symbols for a module wins, even for
+        // addresses its symbols don't cover.
+        assert_eq!(lookup(&multi, "a", 0x1010).await.unwrap(), "first a");
+        assert_eq!(lookup(&multi, "a", 0x2000).await.unwrap(), "");
+        assert_eq!(lookup(&multi, "b", 0x1010).await.unwrap(), "second b");
+        assert_eq!(lookup(&multi, "c", 0x1010).await, None);
+
+        let stats = multi.stats();
+        assert_eq!(stats.len(), 3);
+        assert!(stats["a"].loaded_symbols);
+        assert!(stats["b"].loaded_symbols);
+        assert!(!stats["c"].loaded_symbols);
+    }
+
+    #[tokio::test]
+    async fn test_boxed_symbol_provider() {
+        async fn lookup<P: SymbolProvider + Sync>(provider: &P, address: u64) -> Option<String> {
+            let module = SimpleModule {
+                code_file: Some(String::from("a")),
+                ..SimpleModule::new("a", "abcd1234")
+            };
+            let mut frame = SimpleFrame::with_instruction(address);
+            provider.fill_symbol(&module, &mut frame).await.ok()?;
+            frame.function
+        }
+
+        let boxed = provider(&[("a", "boxed a")]);
+        assert_eq!(lookup(&boxed, 0x1010).await.unwrap(), "boxed a");
+        assert!(boxed.stats()["a"].loaded_symbols);
+    }
+}
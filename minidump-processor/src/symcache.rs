@@ -0,0 +1,190 @@
+// Copyright 2015 Ted Mielczarek. See the COPYRIGHT
+// file at the top-level directory of this distribution.
+
+//! Symbols from the [`symbolic`] crate's SymCache and CfiCache files.
+//!
+//! Pipelines built around Sentry's tools already turn every build's debug
+//! files into SymCaches (for symbolication) and CfiCaches (for unwinding),
+//! so they can be used as they are rather than also generating Breakpad
+//! `.sym` files.
+//!
+//! This needs the `symcache` feature.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+use breakpad_symbols::{relative_symbol_path, SymbolFile};
+use minidump::Module;
+use symbolic::minidump::cfi::CfiCache;
+use symbolic::common::ByteView;
+use symbolic::demangle::{Demangle, DemangleOptions};
+use symbolic::symcache::{LineInfo, SymCache};
+use tracing::warn;
+
+use crate::process_state::basename;
+use crate::symbols::{FillSymbolError, FrameSymbolizer, FrameWalker, SymbolProvider, SymbolStats};
+
+/// A [`SymbolProvider`] that reads SymCache and CfiCache files.
+///
+/// The files are looked for in each of the directories in turn, in the same
+/// layout as Breakpad symbols but with `.symcache` and `.cficache`
+/// extensions, e.g. `test_app.pdb/5A9832E5287241C1838ED98914E9B7FF1/test_app.symcache`.
+/// A module needs a SymCache for its frames to be symbolicated, and a
+/// CfiCache for its CFI to be used when unwinding, but can have either one
+/// without the other.
+///
+/// Files are read the first time a module is asked about, and kept around
+/// from then on.
+pub struct SymCacheSymbolProvider {
+    paths: Vec<PathBuf>,
+    caches: Mutex<HashMap<String, Arc<ModuleCaches>>>,
+}
+
+/// What was found for one module.
+#[derive(Default)]
+struct ModuleCaches {
+    /// The module's code file, which its stats are reported under.
+    name: String,
+    /// The bytes of the SymCache, which was checked to parse.
+    symcache: Option<ByteView<'static>>,
+    /// The CfiCache's unwind info, which is Breakpad's `STACK` records.
+    cfi: Option<SymbolFile>,
+    stats: SymbolStats,
+}
+
+impl SymCacheSymbolProvider {
+    /// Look for caches in each of `paths`, in order.
+    pub fn new(paths: Vec<PathBuf>) -> SymCacheSymbolProvider {
+        SymCacheSymbolProvider {
+            paths,
+            caches: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// The caches for `module`, reading them if they haven't been yet.
+    fn caches(&self, module: &(dyn Module + Sync)) -> Option<Arc<ModuleCaches>> {
+        // Both files have the same path, so that's the key.
+        let key = relative_symbol_path(module, "symcache")?;
+        let mut caches = self.caches.lock().unwrap();
+        let caches = caches
+            .entry(key)
+            .or_insert_with(|| Arc::new(self.read_caches(module)));
+        Some(caches.clone())
+    }
+
+    fn read_caches(&self, module: &(dyn Module + Sync)) -> ModuleCaches {
+        let mut caches = ModuleCaches {
+            name: String::from(basename(&module.code_file())),
+            ..ModuleCaches::default()
+        };
+        if let Some((path, bytes)) = self.find_file(module, "symcache") {
+            caches.stats.loaded_symbols = true;
+            caches.stats.symbol_path = Some(path.clone());
+            caches.stats.symbol_file_size = Some(bytes.len() as u64);
+            match SymCache::parse(&bytes) {
+                Ok(_) => caches.symcache = Some(bytes),
+                Err(e) => {
+                    warn!("Failed to parse SymCache {}: {}", path.display(), e);
+                    caches.stats.corrupt_symbols = true;
+                }
+            }
+        }
+        if let Some((path, bytes)) = self.find_file(module, "cficache") {
+            let cfi = CfiCache::from_bytes(bytes)
+                .map_err(|e| e.to_string())
+                .and_then(|cache| {
+                    SymbolFile::from_bytes(cache.as_slice()).map_err(|e| e.to_string())
+                });
+            match cfi {
+                Ok(cfi) => caches.cfi = Some(cfi),
+                Err(e) => warn!("Failed to parse CfiCache {}: {}", path.display(), e),
+            }
+        }
+        caches
+    }
+
+    /// The first file for `module` with `extension` in any of the paths.
+    fn find_file(
+        &self,
+        module: &(dyn Module + Sync),
+        extension: &str,
+    ) -> Option<(PathBuf, ByteView<'static>)> {
+        let relative_path = relative_symbol_path(module, extension)?;
+        self.paths.iter().find_map(|dir| {
+            let path = dir.join(Path::new(&relative_path));
+            ByteView::open(&path).ok().map(|bytes| (path, bytes))
+        })
+    }
+}
+
+fn function_name(line: &LineInfo) -> String {
+    let name = line.function_name();
+    name.try_demangle(DemangleOptions::complete()).into_owned()
+}
+
+#[async_trait]
+impl SymbolProvider for SymCacheSymbolProvider {
+    async fn fill_symbol(
+        &self,
+        module: &(dyn Module + Sync),
+        frame: &mut (dyn FrameSymbolizer + Send),
+    ) -> Result<(), FillSymbolError> {
+        let caches = self.caches(module).ok_or(FillSymbolError {})?;
+        let bytes = caches.symcache.as_ref().ok_or(FillSymbolError {})?;
+        let symcache = SymCache::parse(bytes).map_err(|_| FillSymbolError {})?;
+
+        // Having symbols that don't cover the instruction is still success.
+        let base = module.base_address();
+        let address = match frame.get_instruction().checked_sub(base) {
+            Some(address) => address,
+            None => return Ok(()),
+        };
+        let lines = match symcache.lookup(address) {
+            Ok(lines) => lines.filter_map(Result::ok).collect::<Vec<_>>(),
+            Err(_) => return Ok(()),
+        };
+
+        // The lookup goes from the innermost inlined function out, and the
+        // frame wants the function the instruction is really in first.
+        let (outer, inlines) = match lines.split_last() {
+            Some(split) => split,
+            None => return Ok(()),
+        };
+        frame.set_function(&function_name(outer), outer.function_address() + base, 0);
+        if outer.line() != 0 {
+            frame.set_source_file(&outer.path(), outer.line(), outer.line_address() + base);
+        }
+        for inline in inlines.iter().rev() {
+            let file = inline.path();
+            let line = Some(inline.line()).filter(|&line| line != 0);
+            frame.add_inline_frame(
+                &function_name(inline),
+                Some(&file)
+                    .filter(|file| !file.is_empty())
+                    .map(|file| &file[..]),
+                line,
+            );
+        }
+        Ok(())
+    }
+
+    async fn walk_frame(
+        &self,
+        module: &(dyn Module + Sync),
+        walker: &mut (dyn FrameWalker + Send),
+    ) -> Option<()> {
+        let caches = self.caches(module)?;
+        caches.cfi.as_ref()?.walk_frame(module, walker)
+    }
+
+    fn stats(&self) -> HashMap<String, SymbolStats> {
+        self.caches
+            .lock()
+            .unwrap()
+            .values()
+            .map(|caches| (caches.name.clone(), caches.stats.clone()))
+            .collect()
+    }
+}
@@ -3,8 +3,10 @@ use std::borrow::Cow;
 use minidump::system_info::{Cpu, Os};
 
 /// Information about the system that produced a `Minidump`.
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct SystemInfo {
     /// The operating system that produced the minidump
+    #[cfg_attr(feature = "serde", serde(serialize_with = "crate::serialize::os"))]
     pub os: Os,
     /// A string identifying the version of the operating system.
     ///
@@ -16,17 +18,44 @@ pub struct SystemInfo {
     /// version, on Linux extended build information.
     pub os_build: Option<String>,
     /// The CPU on which the dump was produced
+    #[cfg_attr(feature = "serde", serde(serialize_with = "crate::serialize::cpu"))]
     pub cpu: Cpu,
     /// A string further identifying the specific CPU
     ///
     /// For example,  "GenuineIntel level 6 model 13 stepping 8", if present.
     pub cpu_info: Option<String>,
+    /// The CPU's vendor, like "GenuineIntel" or "AuthenticAMD", if known.
+    ///
+    /// This is from the system info on x86, and Linux's `/proc/cpuinfo`
+    /// elsewhere.
+    pub cpu_vendor: Option<String>,
+    /// The CPU's family (x86 only).
+    pub cpu_family: Option<u32>,
+    /// The CPU's model within its family (x86 only).
+    pub cpu_model: Option<u32>,
+    /// The CPU's stepping, its revision of the model (x86 only).
+    pub cpu_stepping: Option<u32>,
+    /// The CPU's marketing name, like "Intel(R) Core(TM) i7-8700K CPU @
+    /// 3.70GHz", from Linux's `/proc/cpuinfo`.
+    pub cpu_model_name: Option<String>,
     /// The microcode version of the cpu
     pub cpu_microcode_version: Option<u64>,
+    /// The features the CPU supports, like "sse4_2" or "avx2", from the
+    /// flags (on x86) or features (on ARM) in Linux's `/proc/cpuinfo`.
+    ///
+    /// This is empty if they aren't known.
+    pub cpu_features: Vec<String>,
     /// The number of processors in the system
     ///
     /// Will be greater than one for multi-core systems.
     pub cpu_count: usize,
+    /// The number of physical cores in the system, which is fewer than
+    /// [`cpu_count`][Self::cpu_count] with hyper-threading, from Linux's
+    /// `/proc/cpuinfo`.
+    pub cpu_physical_core_count: Option<usize>,
+    /// The number of physical CPU packages (sockets) in the system, from
+    /// Linux's `/proc/cpuinfo`.
+    pub cpu_package_count: Option<usize>,
 }
 
 impl SystemInfo {
@@ -0,0 +1,104 @@
+// Copyright 2015 Ted Mielczarek. See the COPYRIGHT
+// file at the top-level directory of this distribution.
+
+//! Guessing which thread a dump is about, when it doesn't say.
+//!
+//! Hang dumps, and dumps written without an exception stream or Breakpad
+//! info, don't say which thread requested them. Most threads of a process
+//! spend their time waiting for something, so the thread that isn't is the
+//! one most likely to be doing whatever the dump was written to catch.
+
+use crate::process_state::{CallStack, FrameTrust, ProcessState};
+
+/// Functions that threads sit in while they wait for something, at the top
+/// of their stacks. Leading underscores are ignored, and names match if they
+/// start with one of these.
+const WAIT_FUNCTIONS: &[&str] = &[
+    // Windows
+    "NtWaitFor",
+    "ZwWaitFor",
+    "NtDelayExecution",
+    "ZwDelayExecution",
+    "NtRemoveIoCompletion",
+    "ZwRemoveIoCompletion",
+    "NtUserGetMessage",
+    "NtUserMsgWaitForMultipleObjects",
+    "NtUserWaitMessage",
+    "NtAlertThreadByThreadId",
+    "ZwAlertThreadByThreadId",
+    "NtWaitForAlertByThreadId",
+    "NtWaitForWorkViaWorkerFactory",
+    "ZwWaitForWorkViaWorkerFactory",
+    "NtReplyWaitReceivePort",
+    "ZwReplyWaitReceivePort",
+    "NtSignalAndWaitForSingleObject",
+    // Linux
+    "futex_wait",
+    "futex_abstimed_wait",
+    "lll_lock_wait",
+    "pthread_cond_wait",
+    "pthread_cond_timedwait",
+    "epoll_wait",
+    "epoll_pwait",
+    "poll",
+    "ppoll",
+    "GI___poll",
+    "select",
+    "pselect",
+    "nanosleep",
+    "clock_nanosleep",
+    "GI___clock_nanosleep",
+    "sigtimedwait",
+    "sigwait",
+    "syscall",
+    // macOS
+    "mach_msg_trap",
+    "mach_msg2_trap",
+    "psynch_cvwait",
+    "psynch_mutexwait",
+    "semwait_signal",
+    "workq_kernreturn",
+    "kevent",
+    "ulock_wait",
+];
+
+/// Whether the thread of `stack` is waiting for something.
+///
+/// The innermost frame says what the thread is in, but without symbols for
+/// system libraries that's a function in some other library, so the first
+/// few frames are checked.
+fn is_waiting(stack: &CallStack) -> bool {
+    stack
+        .frames
+        .iter()
+        .take(3)
+        .take_while(|frame| frame.trust != FrameTrust::Scan)
+        .filter_map(|frame| frame.function_name.as_deref())
+        .any(|name| {
+            let name = name.trim_start_matches('_');
+            WAIT_FUNCTIONS.iter().any(|wait| name.starts_with(wait))
+        })
+}
+
+/// The index of the thread in `state` most likely to be the one the dump
+/// was written for, or `None` if no thread has any frames.
+///
+/// Threads that aren't waiting are preferred, and then the main thread (the
+/// one with the process's id, or else the first one).
+pub(crate) fn guess_requesting_thread(state: &ProcessState) -> Option<usize> {
+    let main_thread = state
+        .threads
+        .iter()
+        .position(|stack| Some(stack.thread_id) == state.process_id)
+        .unwrap_or(0);
+    let mut candidates = state
+        .threads
+        .iter()
+        .enumerate()
+        .filter(|(_, stack)| !stack.frames.is_empty())
+        .map(|(i, stack)| (i, is_waiting(stack)))
+        .collect::<Vec<_>>();
+    // Busy threads first, then the main thread, then in order.
+    candidates.sort_by_key(|&(i, waiting)| (waiting, i != main_thread, i));
+    candidates.first().map(|&(i, _)| i)
+}
@@ -0,0 +1,68 @@
+// Copyright 2015 Ted Mielczarek. See the COPYRIGHT
+// file at the top-level directory of this distribution.
+
+//! How long each part of processing a minidump took.
+//!
+//! Most of the time goes to fetching symbols and walking stacks, but a
+//! pathological minidump (thousands of threads, a corrupt stack that sends
+//! the stack scanner everywhere) or a slow symbol server can make any part
+//! of it drag on. These are recorded in [`ProcessState::timings`] when
+//! [`ProcessorOptions::record_timings`] is set.
+//!
+//! [`ProcessState::timings`]: crate::ProcessState::timings
+//! [`ProcessorOptions::record_timings`]: crate::ProcessorOptions::record_timings
+
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use serde_json::json;
+
+/// How long each part of processing a minidump took.
+///
+/// Threads are walked concurrently, so their times (and the time spent
+/// getting symbols, which happens while walking them) overlap and can add
+/// up to more than [`total`][Self::total].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ProcessTimings {
+    /// Everything, from reading the first stream to finishing the last
+    /// analysis.
+    pub total: Duration,
+    /// Reading the minidump's streams, before any stacks were walked.
+    pub read_streams: Duration,
+    /// Fetching every module's symbols up front, if
+    /// [`prefetch_symbols`][crate::ProcessorOptions::prefetch_symbols] was
+    /// set.
+    pub prefetch_symbols: Duration,
+    /// Walking each thread's stack (including getting symbols for its
+    /// frames), in the same order as
+    /// [`ProcessState::threads`][crate::ProcessState::threads].
+    pub threads: Vec<Duration>,
+    /// Getting symbols for each module while walking stacks (fetching them,
+    /// and looking frames up in them), by code file.
+    pub symbols: BTreeMap<String, Duration>,
+    /// Each [`AnalysisPass`][crate::AnalysisPass], by name, in the order
+    /// they ran.
+    pub analysis_passes: Vec<(String, Duration)>,
+}
+
+impl ProcessTimings {
+    pub(crate) fn to_json(&self) -> serde_json::Value {
+        let ms = |time: &Duration| time.as_millis() as u64;
+        json!({
+            "total_ms": ms(&self.total),
+            "read_streams_ms": ms(&self.read_streams),
+            "prefetch_symbols_ms": ms(&self.prefetch_symbols),
+            "threads_ms": self.threads.iter().map(ms).collect::<Vec<_>>(),
+            "symbols_ms": self
+                .symbols
+                .iter()
+                .map(|(module, time)| (module.clone(), json!(ms(time))))
+                .collect::<serde_json::Map<_, _>>(),
+            "analysis_passes": self.analysis_passes.iter().map(|(name, time)| json!({
+                "name": name,
+                "ms": ms(time),
+            })).collect::<Vec<_>>(),
+        })
+    }
+}
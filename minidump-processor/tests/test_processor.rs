@@ -1,14 +1,30 @@
 // Copyright 2015 Ted Mielczarek. See the COPYRIGHT
 // file at the top-level directory of this distribution.
 
+use futures_util::StreamExt;
+use minidump::format::{MinidumpType, PlatformId, ProcessorArchitecture, MINIDUMP_STREAM_TYPE};
 use minidump::system_info::{Cpu, Os};
 use minidump::{
-    Error, Minidump, MinidumpContext, MinidumpContextValidity, MinidumpRawContext, Module,
+    Error, Minidump, MinidumpContext, MinidumpContextValidity, MinidumpException,
+    MinidumpMemoryList, MinidumpModuleList, MinidumpRawContext, MinidumpSystemInfo,
+    MinidumpThreadList, Module, UnifiedMemoryInfoList, UnifiedMemoryList,
 };
+use minidump_processor::signature::SignatureOptions;
 use minidump_processor::{
-    simple_symbol_supplier, CallStackInfo, FrameTrust, LinuxStandardBase, ProcessState, Symbolizer,
+    simple_symbol_supplier, string_symbol_supplier, AnalysisContext, AnalysisPass, Assertion,
+    AssertionKind, AuxiliaryData, CallStack, CallStackInfo, CancellationToken, CrashAddressKind,
+    CrashHandleReason, CrashMemorySource, Degradation, DepPolicy, ExploitabilityRating,
+    FrameIterator, FrameTrust, HumanOptions, IntegrityLevel, JsonOptions, JsonStreamWriter,
+    LinuxProcStatus, LinuxStandardBase, PointerTarget, ProcessError, ProcessSecurity, ProcessState,
+    ProcessorOptions, ProgressEvent, SourceContext, SourcePathMapping, SourceRoot, StackFrame,
+    StackOverflow, SuspiciousModule, SuspiciousModuleReason, SymbolConfig, Symbolizer,
+    ThreadFilter, ThreadNamePattern, WalkControl,
 };
+use std::collections::{HashMap, HashSet};
+use std::convert::TryInto;
 use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
 use synth_minidump::*;
 use test_assembler::*;
@@ -115,128 +131,2848 @@ async fn test_processor() {
     assert_eq!(state.threads[1].frames.len(), 0);
 }
 
+#[tokio::test]
+async fn test_crash_memory() {
+    let dump = read_test_minidump().unwrap();
+    let provider = Symbolizer::new(simple_symbol_supplier(vec![]));
+    let state = minidump_processor::process_minidump(&dump, &provider)
+        .await
+        .unwrap();
+    assert_eq!(state.crash_memory, None);
+
+    let mut options = ProcessorOptions::default();
+    options.crash_memory_size = 32;
+    let state = minidump_processor::process_minidump_with_options(&dump, &provider, options)
+        .await
+        .unwrap();
+    let memory = state.crash_memory.as_ref().unwrap();
+    assert_eq!(
+        memory
+            .iter()
+            .map(|memory| (memory.source, memory.address, memory.base_address))
+            .collect::<Vec<_>>(),
+        vec![
+            (CrashMemorySource::CrashAddress, 0x45, 0x25),
+            (
+                CrashMemorySource::InstructionPointer,
+                0x0040429e,
+                0x0040428e
+            ),
+            (CrashMemorySource::StackPointer, 0x0012fe84, 0x0012fe74),
+        ]
+    );
+    // Only the stack is in the minidump, and it has no memory info.
+    assert_eq!(memory[0].bytes, vec![None; 32]);
+    assert_eq!(memory[1].bytes, vec![None; 32]);
+    assert_eq!(
+        memory[2].hex(),
+        "4e09917c5ad9000008b32200b4b622004500000070ff120000424000b8278700"
+    );
+    assert!(memory.iter().all(|memory| memory.access.is_none()));
+
+    let json = state.to_json_with_options(&JsonOptions::extended());
+    assert_eq!(json["sensitive"]["memory"][2]["source"], "stack_pointer");
+    assert_eq!(json["sensitive"]["memory"][0]["bytes"], "??".repeat(32));
+}
+
+#[tokio::test]
+async fn test_thread_filter() {
+    let dump = read_test_minidump().unwrap();
+    let provider = Symbolizer::new(simple_symbol_supplier(vec![]));
+    let state = minidump_processor::process_minidump(&dump, &provider)
+        .await
+        .unwrap();
+    let ids = state
+        .threads
+        .iter()
+        .map(|thread| thread.thread_id)
+        .collect::<Vec<_>>();
+    assert_eq!(state.requesting_thread, Some(0));
+    assert!(state.threads.iter().all(|thread| !thread.frames.is_empty()));
+
+    let options = ProcessorOptions::default()
+        .with_max_frames(2)
+        .with_scan(false)
+        .with_requesting_thread_only();
+    let state = minidump_processor::process_minidump_with_options(&dump, &provider, options)
+        .await
+        .unwrap();
+    assert!(state.threads[0].frames.len() <= 2);
+    assert_eq!(state.threads[1].info, CallStackInfo::ThreadSkipped);
+    assert!(state.threads[1].frames.is_empty());
+    assert_eq!(state.threads[1].thread_id, ids[1]);
+
+    // The requesting thread is walked even if it's excluded.
+    let options = ProcessorOptions::default().with_excluded_threads(ids.clone());
+    let state = minidump_processor::process_minidump_with_options(&dump, &provider, options)
+        .await
+        .unwrap();
+    assert!(!state.threads[0].frames.is_empty());
+    assert_eq!(state.threads[1].info, CallStackInfo::ThreadSkipped);
+
+    let options = ProcessorOptions::default().with_only_threads(vec![ids[1]]);
+    let state = minidump_processor::process_minidump_with_options(&dump, &provider, options)
+        .await
+        .unwrap();
+    assert!(state.threads.iter().all(|thread| !thread.frames.is_empty()));
+}
+
+#[tokio::test]
+async fn test_deadline() {
+    let dump = read_test_minidump().unwrap();
+    let provider = Symbolizer::new(simple_symbol_supplier(vec![]));
+    let state = minidump_processor::process_minidump(&dump, &provider)
+        .await
+        .unwrap();
+    assert!(!state.timed_out);
+    assert_eq!(
+        state.to_json_with_options(&JsonOptions::extended())["timed_out"],
+        false
+    );
+
+    // Already cancelled, so only the context frames are there.
+    let token = CancellationToken::new();
+    token.cancel();
+    let options = ProcessorOptions::default().with_cancellation(token);
+    let state = minidump_processor::process_minidump_with_options(&dump, &provider, options)
+        .await
+        .unwrap();
+    assert!(state.timed_out);
+    for thread in &state.threads {
+        assert_eq!(thread.info, CallStackInfo::TimedOut);
+        assert_eq!(thread.frames.len(), 1);
+        assert_eq!(thread.frames[0].trust, FrameTrust::Context);
+    }
+    assert_eq!(
+        state.to_json_with_options(&JsonOptions::extended())["timed_out"],
+        true
+    );
+
+    let options = ProcessorOptions::default().with_deadline(Instant::now());
+    let state = minidump_processor::process_minidump_with_options(&dump, &provider, options)
+        .await
+        .unwrap();
+    assert!(state.timed_out);
+    assert_eq!(state.threads[0].frames.len(), 1);
+}
+
+#[tokio::test]
+async fn test_assertion() {
+    let path = locate_testdata().join("invalid-parameter.dmp");
+    let dump = Minidump::read_path(&path).unwrap();
+    let provider = Symbolizer::new(simple_symbol_supplier(vec![]));
+    let state = minidump_processor::process_minidump(&dump, &provider)
+        .await
+        .unwrap();
+    let assertion = state.assertion.as_ref().unwrap();
+    assert_eq!(
+        *assertion,
+        Assertion {
+            kind: AssertionKind::InvalidParameter,
+            expression: Some(String::from("format != nullptr")),
+            function: Some(String::from("common_vfprintf")),
+            file: Some(String::from(
+                r"minkernel\crts\ucrt\src\appcrt\stdio\output.cpp"
+            )),
+            line: Some(32),
+        }
+    );
+    let description = "Invalid parameter passed to library function format != nullptr \
+        in function common_vfprintf, in file minkernel\\crts\\ucrt\\src\\appcrt\\stdio\\output.cpp \
+        at line 32";
+    assert_eq!(assertion.to_string(), description);
+
+    let json = state.to_json_with_options(&JsonOptions::extended());
+    assert_eq!(json["crash_info"]["assertion"], description);
+    assert_eq!(
+        json["crash_info"]["assertion_info"]["kind"],
+        "invalid_parameter"
+    );
+    assert_eq!(json["crash_info"]["assertion_info"]["line"], 32);
+
+    let mut human = Vec::new();
+    state.print(&mut human).unwrap();
+    let human = String::from_utf8(human).unwrap();
+    assert!(human.contains(&format!("Assertion: {}", description)));
+}
+
+#[tokio::test]
+async fn test_frame_observer() {
+    let dump = read_test_minidump().unwrap();
+    let seen = Arc::new(Mutex::new(vec![]));
+    let observer_seen = seen.clone();
+    let mut options = ProcessorOptions::default();
+    options.frame_observer = Some(Arc::new(move |thread_id: u32, frame: &StackFrame| {
+        let mut seen = observer_seen.lock().unwrap();
+        seen.push((thread_id, frame.trust));
+        if seen.len() == 2 {
+            WalkControl::Stop
+        } else {
+            WalkControl::Continue
+        }
+    }));
+    let state = minidump_processor::process_minidump_with_options(
+        &dump,
+        &Symbolizer::new(simple_symbol_supplier(vec![])),
+        options,
+    )
+    .await
+    .unwrap();
+
+    // The walk stopped at the second frame, which is still kept.
+    assert_eq!(state.threads[0].info, CallStackInfo::WalkStopped);
+    assert_eq!(state.threads[0].frames.len(), 2);
+    assert_eq!(
+        *seen.lock().unwrap(),
+        vec![
+            (3060, FrameTrust::Context),
+            (3060, FrameTrust::FramePointer)
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_progress_observer() {
+    let dump = read_test_minidump().unwrap();
+    let events = Arc::new(Mutex::new(vec![]));
+    let observer_events = events.clone();
+    let options = ProcessorOptions::default().with_progress_observer(Arc::new(
+        move |event: &ProgressEvent| {
+            observer_events.lock().unwrap().push(event.clone());
+        },
+    ));
+    let provider = Symbolizer::new(simple_symbol_supplier(vec![testdata_symbol_path()]));
+    minidump_processor::process_minidump_with_options(&dump, &provider, options)
+        .await
+        .unwrap();
+
+    let events = events.lock().unwrap();
+    assert!(matches!(
+        events[0],
+        ProgressEvent::StreamsRead {
+            thread_count: 2,
+            ..
+        }
+    ));
+    let finished = events
+        .iter()
+        .filter_map(|event| match *event {
+            ProgressEvent::ThreadFinished {
+                index,
+                thread_count,
+                ..
+            } => Some((index, thread_count)),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(finished, vec![(0, 2), (1, 2)]);
+    let started = events
+        .iter()
+        .filter(|event| matches!(event, ProgressEvent::ThreadStarted { .. }))
+        .count();
+    assert_eq!(started, 2);
+
+    // Each module is only reported once.
+    let fetched = events
+        .iter()
+        .filter_map(|event| match event {
+            ProgressEvent::SymbolsFetched { module, found } => Some((module.clone(), *found)),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    let unique = fetched
+        .iter()
+        .map(|(module, _)| module)
+        .collect::<HashSet<_>>();
+    assert_eq!(unique.len(), fetched.len());
+    assert!(fetched
+        .iter()
+        .any(|(module, found)| module.ends_with("test_app.exe") && *found));
+}
+
+#[tokio::test]
+async fn test_analysis_pass() {
+    struct SystemInfoSize;
+    impl AnalysisPass for SystemInfoSize {
+        fn name(&self) -> &str {
+            "system_info_size"
+        }
+        fn analyze(&self, context: &AnalysisContext<'_>, state: &mut ProcessState) {
+            let size = context
+                .raw_stream(MINIDUMP_STREAM_TYPE::SystemInfoStream as u32)
+                .map(|stream| stream.len());
+            state.system_info.cpu_info = size.map(|size| format!("{} bytes", size));
+        }
+    }
+
+    let dump = read_test_minidump().unwrap();
+    let options = ProcessorOptions::default().with_analysis_pass(Arc::new(SystemInfoSize));
+    let provider = Symbolizer::new(simple_symbol_supplier(vec![testdata_symbol_path()]));
+    let state = minidump_processor::process_minidump_with_options(&dump, &provider, options)
+        .await
+        .unwrap();
+    let size = dump
+        .get_raw_stream(MINIDUMP_STREAM_TYPE::SystemInfoStream as u32)
+        .unwrap()
+        .len();
+    assert_eq!(state.system_info.cpu_info, Some(format!("{} bytes", size)));
+}
+
+#[tokio::test]
+async fn test_auxiliary_data() {
+    let dump = read_test_minidump().unwrap();
+    let provider = Symbolizer::new(simple_symbol_supplier(vec![testdata_symbol_path()]));
+    let first = AuxiliaryData::default()
+        .with_thread_name(3060, "main")
+        .with_module_cert("test_app.exe", "Test Cert")
+        .with_annotation("channel", "beta");
+    let second = AuxiliaryData::default().with_annotation("channel", "release");
+    let options = ProcessorOptions::default()
+        .with_auxiliary_data(Arc::new(first))
+        .with_auxiliary_data(Arc::new(second));
+    let state = minidump_processor::process_minidump_with_options(&dump, &provider, options)
+        .await
+        .unwrap();
+
+    assert_eq!(state.threads[0].thread_name.as_deref(), Some("main"));
+    assert_eq!(
+        state.cert_info.get("test_app.exe").map(String::as_str),
+        Some("Test Cert")
+    );
+    // Later providers win.
+    assert_eq!(
+        state.annotations.get("channel").map(String::as_str),
+        Some("release")
+    );
+    assert_eq!(
+        state.to_json_with_options(&JsonOptions::extended())["annotations"]["channel"],
+        "release"
+    );
+}
+
+#[tokio::test]
+async fn test_stack_memory_limits() {
+    let dump = read_test_minidump().unwrap();
+    let provider = Symbolizer::new(simple_symbol_supplier(vec![testdata_symbol_path()]));
+    let (dump, provider) = (&dump, &provider);
+    let process =
+        move |options| minidump_processor::process_minidump_with_options(dump, provider, options);
+    let full = process(ProcessorOptions::default()).await.unwrap();
+    assert!(full
+        .threads
+        .iter()
+        .all(|stack| stack.info == CallStackInfo::Ok));
+
+    // Limits the stacks fit in change nothing.
+    let options = ProcessorOptions::default()
+        .with_max_stack_size(u64::MAX)
+        .with_max_total_stack_size(u64::MAX);
+    let state = process(options).await.unwrap();
+    assert!(state
+        .threads
+        .iter()
+        .all(|stack| stack.info == CallStackInfo::Ok));
+    assert_eq!(state.threads[0].frames.len(), full.threads[0].frames.len());
+
+    // A few bytes from the stack pointer up isn't enough to find every frame.
+    let options = ProcessorOptions::default().with_max_stack_size(16);
+    let state = process(options).await.unwrap();
+    assert!(state
+        .threads
+        .iter()
+        .all(|stack| stack.info == CallStackInfo::StackMemoryLimited));
+    assert!(state.threads[0].frames.len() < full.threads[0].frames.len());
+
+    // With no stack memory at all, each thread only has its context frame.
+    let options = ProcessorOptions::default().with_max_total_stack_size(0);
+    let state = process(options).await.unwrap();
+    for stack in &state.threads {
+        assert_eq!(stack.info, CallStackInfo::StackMemoryLimited);
+        assert_eq!(stack.frames[0].trust, FrameTrust::Context);
+    }
+    assert!(state.threads[0].frames.len() < full.threads[0].frames.len());
+}
+
+#[tokio::test]
+async fn test_timings() {
+    let dump = read_test_minidump().unwrap();
+    let provider = Symbolizer::new(simple_symbol_supplier(vec![testdata_symbol_path()]));
+    let state = minidump_processor::process_minidump(&dump, &provider)
+        .await
+        .unwrap();
+    assert_eq!(state.timings, None);
+    assert_eq!(
+        state.to_json_with_options(&JsonOptions::extended())["timings"],
+        serde_json::Value::Null
+    );
+
+    let options = ProcessorOptions::default().with_timings(true);
+    let state = minidump_processor::process_minidump_with_options(&dump, &provider, options)
+        .await
+        .unwrap();
+    let timings = state.timings.as_ref().unwrap();
+    assert_eq!(timings.threads.len(), 2);
+    assert!(timings.read_streams <= timings.total);
+    assert!(timings.threads.iter().all(|&time| time <= timings.total));
+    assert!(timings
+        .symbols
+        .keys()
+        .any(|module| module.ends_with("test_app.exe")));
+    let passes = timings
+        .analysis_passes
+        .iter()
+        .map(|(name, _)| name.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(passes.first(), Some(&"crash_memory"));
+    assert_eq!(passes.last(), Some(&"suspicious_modules"));
+    assert_eq!(
+        state.to_json_with_options(&JsonOptions::extended())["timings"]["threads_ms"]
+            .as_array()
+            .unwrap()
+            .len(),
+        2
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_batch_processor() {
+    let provider = Symbolizer::new(simple_symbol_supplier(vec![testdata_symbol_path()]));
+    let batch = BatchProcessor::new(provider).with_concurrency(2);
+    let path = locate_testdata().join("test.dmp");
+    let missing = locate_testdata().join("missing.dmp");
+    let mut results = batch
+        .process_paths(vec![path.clone(), missing.clone(), path.clone()])
+        .collect::<Vec<_>>()
+        .await;
+    results.sort_by(|(a, _), (b, _)| a.cmp(b));
+    assert_eq!(results.len(), 3);
+    assert_eq!(results[0].0, missing);
+    assert!(matches!(
+        results[0].1,
+        Err(ProcessError::MinidumpReadError(Error::FileNotFound))
+    ));
+    for (_, result) in &results[1..] {
+        let state = result.as_ref().unwrap();
+        assert_eq!(state.threads.len(), 2);
+        assert_eq!(
+            state.threads[0].frames[0].function_name.as_deref(),
+            Some("`anonymous namespace'::CrashFunction")
+        );
+    }
+    // Both minidumps shared the one symbolizer.
+    assert!(batch
+        .symbol_provider()
+        .stats()
+        .values()
+        .any(|stats| stats.loaded_symbols));
+
+    let bytes = std::fs::read(&path).unwrap();
+    let results = batch
+        .process_bytes(vec![(1, bytes.clone()), (2, b"not a minidump".to_vec())])
+        .collect::<HashMap<_, _>>()
+        .await;
+    assert_eq!(results[&1].as_ref().unwrap().threads.len(), 2);
+    assert!(results[&2].is_err());
+}
+
+#[tokio::test]
+async fn test_process_path_and_bytes() {
+    let path = locate_testdata().join("test.dmp");
+    let symbols = SymbolConfig::default().with_symbol_path(testdata_symbol_path());
+    let from_path = minidump_processor::process_path(&path, &symbols)
+        .await
+        .unwrap();
+    let bytes = std::fs::read(&path).unwrap();
+    let from_bytes = minidump_processor::process_bytes(bytes, &symbols)
+        .await
+        .unwrap();
+
+    let dump = read_test_minidump().unwrap();
+    let provider = Symbolizer::new(simple_symbol_supplier(vec![testdata_symbol_path()]));
+    let state = minidump_processor::process_minidump(&dump, &provider)
+        .await
+        .unwrap();
+    let function_names = |state: &ProcessState| {
+        state.threads[0]
+            .frames
+            .iter()
+            .map(|frame| frame.function_name.clone())
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(function_names(&from_path), function_names(&state));
+    assert_eq!(function_names(&from_bytes), function_names(&state));
+    assert!(function_names(&state)[0].is_some());
+
+    assert!(matches!(
+        minidump_processor::process_bytes(&b"not a minidump"[..], &symbols).await,
+        Err(ProcessError::MinidumpReadError(_))
+    ));
+}
+
+/// Check that `value` (at `path`) only has the fields `schema` describes,
+/// with the types it says.
+fn check_schema(value: &serde_json::Value, schema: &serde_json::Value, path: &str) {
+    let defs = &minidump_processor::json_schema()["$defs"];
+    let schema = match schema["$ref"].as_str() {
+        Some(reference) => &defs[reference.strip_prefix("#/$defs/").unwrap()],
+        None => schema,
+    };
+    let type_name = match value {
+        serde_json::Value::Null => "null",
+        serde_json::Value::Bool(_) => "boolean",
+        serde_json::Value::Number(_) => "integer",
+        serde_json::Value::String(_) => "string",
+        serde_json::Value::Array(_) => "array",
+        serde_json::Value::Object(_) => "object",
+    };
+    let types = match &schema["type"] {
+        serde_json::Value::String(ty) => vec![ty.as_str()],
+        types => types
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|ty| ty.as_str().unwrap())
+            .collect(),
+    };
+    assert!(types.contains(&type_name), "{} is {}", path, type_name);
+    if schema["pattern"].is_string() {
+        if let Some(hex) = value.as_str() {
+            assert!(hex.starts_with("0x"), "{} isn't hex: {}", path, hex);
+        }
+    }
+    match value {
+        serde_json::Value::Array(items) => {
+            for (i, item) in items.iter().enumerate() {
+                check_schema(item, &schema["items"], &format!("{}[{}]", path, i));
+            }
+        }
+        serde_json::Value::Object(fields) => {
+            for (key, field) in fields {
+                let field_schema = schema["properties"]
+                    .get(key)
+                    .or_else(|| schema.get("additionalProperties"))
+                    .unwrap_or_else(|| panic!("{}.{} isn't in the schema", path, key));
+                check_schema(field, field_schema, &format!("{}.{}", path, key));
+            }
+        }
+        _ => {}
+    }
+}
+
+#[tokio::test]
+async fn test_json_schema() {
+    let schema = minidump_processor::json_schema();
+    assert_eq!(schema["version"], minidump_processor::JSON_SCHEMA_VERSION);
+
+    let dump = read_test_minidump().unwrap();
+    let provider = Symbolizer::new(simple_symbol_supplier(vec![testdata_symbol_path()]));
+    let mut options = ProcessorOptions::default()
+        .with_exploitability(true)
+        .with_bit_flips(true)
+        .with_crash_memory(32)
+        .with_suspicious_modules(true)
+        .with_timings(true);
+    options.unwind_diagnostics = true;
+    let state = minidump_processor::process_minidump_with_options(&dump, &provider, options)
+        .await
+        .unwrap();
+    check_schema(
+        &state.to_json_with_options(&JsonOptions::extended()),
+        &schema,
+        "report",
+    );
+}
+
+#[tokio::test]
+async fn test_socorro_json() {
+    let dump = read_test_minidump().unwrap();
+    let provider = Symbolizer::new(simple_symbol_supplier(vec![testdata_symbol_path()]));
+    let state = minidump_processor::process_minidump(&dump, &provider)
+        .await
+        .unwrap();
+    let options = SignatureOptions::default();
+    let processed = state.to_socorro_json(&options);
+    let json_dump = state.to_json();
+
+    assert_eq!(processed["json_dump"], json_dump);
+    assert_eq!(processed["signature"], state.signature(&options));
+    assert_eq!(processed["mdsw_status_string"], "OK");
+    assert_eq!(processed["success"], true);
+    assert_eq!(processed["crashing_thread"], 0);
+    assert_eq!(processed["reason"], json_dump["crash_info"]["type"]);
+    assert_eq!(processed["address"], json_dump["crash_info"]["address"]);
+    assert_eq!(processed["os_name"], "Windows NT");
+    assert_eq!(processed["cpu_arch"], "x86");
+
+    // The signature is the start of the crashing thread's frames.
+    let proto_signature = processed["proto_signature"].as_str().unwrap();
+    let signature = state.signature(&options);
+    assert!(proto_signature.starts_with(signature.split(" | ").next().unwrap()));
+
+    // Every frame of the crashing thread is in a module.
+    let modules_in_stack = processed["modules_in_stack"].as_str().unwrap();
+    let frame_count = json_dump["crashing_thread"]["frames"]
+        .as_array()
+        .unwrap()
+        .len();
+    assert_eq!(modules_in_stack.split(';').count(), frame_count);
+    assert!(modules_in_stack.starts_with("test_app.pdb/"));
+}
+
+#[tokio::test]
+async fn test_walk_stack_from_context() {
+    let dump = read_test_minidump().unwrap();
+    let provider = Symbolizer::new(simple_symbol_supplier(vec![testdata_symbol_path()]));
+    let state = minidump_processor::process_minidump(&dump, &provider)
+        .await
+        .unwrap();
+
+    // Walk the crashing thread again, as if we'd only been handed its context
+    // and stack.
+    let system_info = dump.get_stream::<MinidumpSystemInfo>().unwrap();
+    let memory_list = dump.get_stream::<MinidumpMemoryList>().unwrap();
+    let thread_list = dump.get_stream::<MinidumpThreadList>().unwrap();
+    let modules = dump.get_stream::<MinidumpModuleList>().unwrap();
+    let exception = dump.get_stream::<MinidumpException>().unwrap();
+    let thread = &thread_list.threads[0];
+    let context = exception.context(&system_info, None).unwrap();
+    let stack = thread.stack_memory(&memory_list).unwrap();
+
+    let call_stack = minidump_processor::walk_stack_from_context(
+        &context,
+        stack.base_address,
+        stack.bytes,
+        &modules,
+        &provider,
+        &ProcessorOptions::default(),
+    )
+    .await;
+
+    assert_eq!(call_stack.info, CallStackInfo::Ok);
+    let expected = &state.threads[0].frames;
+    assert!(expected.len() > 1);
+    assert_eq!(
+        call_stack
+            .frames
+            .iter()
+            .map(|frame| (frame.instruction, frame.function_name.clone()))
+            .collect::<Vec<_>>(),
+        expected
+            .iter()
+            .map(|frame| (frame.instruction, frame.function_name.clone()))
+            .collect::<Vec<_>>(),
+    );
+}
+
+#[tokio::test]
+async fn test_frame_iterator() {
+    let dump = read_test_minidump().unwrap();
+    let provider = Symbolizer::new(simple_symbol_supplier(vec![testdata_symbol_path()]));
+    let state = minidump_processor::process_minidump(&dump, &provider)
+        .await
+        .unwrap();
+
+    let system_info = dump.get_stream::<MinidumpSystemInfo>().unwrap();
+    let memory_list = dump.get_stream::<MinidumpMemoryList>().unwrap();
+    let thread_list = dump.get_stream::<MinidumpThreadList>().unwrap();
+    let modules = dump.get_stream::<MinidumpModuleList>().unwrap();
+    let exception = dump.get_stream::<MinidumpException>().unwrap();
+    let thread = &thread_list.threads[0];
+    let context = exception.context(&system_info, None).unwrap();
+    let stack = thread.stack_memory(&memory_list).unwrap();
+    let memory_list = UnifiedMemoryList::Memory(memory_list);
+    let memory_info = UnifiedMemoryInfoList::default();
+    let options = ProcessorOptions::default();
+    let new_iterator = || {
+        FrameIterator::new(
+            Some(&*context),
+            Some(&*stack),
+            &memory_list,
+            &memory_info,
+            &modules,
+            &provider,
+            &options,
+        )
+    };
+
+    // The frames come one at a time, the same as walking it all at once.
+    let mut frames = new_iterator();
+    let mut walked = vec![];
+    while let Some(frame) = frames.next().await {
+        walked.push((frame.instruction, frame.function_name.clone()));
+    }
+    let expected = &state.threads[0].frames;
+    assert!(expected.len() > 1);
+    assert_eq!(
+        walked,
+        expected
+            .iter()
+            .map(|frame| (frame.instruction, frame.function_name.clone()))
+            .collect::<Vec<_>>(),
+    );
+    let call_stack = frames.into_call_stack();
+    assert_eq!(call_stack.info, CallStackInfo::Ok);
+    assert_eq!(call_stack.frames.len(), expected.len());
+
+    // Stopping early keeps what was walked.
+    let mut frames = new_iterator();
+    let first = frames.next().await.unwrap();
+    assert_eq!(first.trust, FrameTrust::Context);
+    let call_stack = frames.into_call_stack();
+    assert_eq!(call_stack.info, CallStackInfo::WalkStopped);
+    assert_eq!(call_stack.frames.len(), 1);
+}
+
 #[tokio::test]
 async fn test_processor_symbols() {
     let dump = read_test_minidump().unwrap();
-    let path = testdata_symbol_path();
-    println!("symbol path: {:?}", path);
+    let path = testdata_symbol_path();
+    println!("symbol path: {:?}", path);
+    let state = minidump_processor::process_minidump(
+        &dump,
+        &Symbolizer::new(simple_symbol_supplier(vec![path])),
+    )
+    .await
+    .unwrap();
+    let f0 = &state.threads[0].frames[0];
+    assert_eq!(
+        f0.function_name.as_deref(),
+        Some("`anonymous namespace'::CrashFunction")
+    );
+}
+
+#[tokio::test]
+async fn test_crash_hash() {
+    let dump = read_test_minidump().unwrap();
+    let state = minidump_processor::process_minidump(
+        &dump,
+        &Symbolizer::new(simple_symbol_supplier(vec![])),
+    )
+    .await
+    .unwrap();
+    // test_app.exe@0x429e, test_app.exe@0x41ff, test_app.exe@0x53eb and
+    // kernel32.dll@0x16fd6. This must never change.
+    let options = SignatureOptions::default();
+    assert_eq!(
+        state.crash_hash(&options).as_deref(),
+        Some("535afcc4d02394b6")
+    );
+
+    let state = read_synth_dump(minimal_minidump()).await;
+    assert_eq!(state.crash_hash(&options), None);
+}
+
+#[tokio::test]
+async fn test_process_uptime() {
+    let dump = read_test_minidump().unwrap();
+    let mut state = minidump_processor::process_minidump(
+        &dump,
+        &Symbolizer::new(simple_symbol_supplier(vec![])),
+    )
+    .await
+    .unwrap();
+    // test.dmp was written in the same second the process started.
+    assert_eq!(state.process_uptime(), Some(Duration::from_secs(0)));
+
+    state.process_create_time = Some(state.time - Duration::from_secs(90));
+    assert_eq!(state.process_uptime(), Some(Duration::from_secs(90)));
+    state.process_create_time = Some(state.time + Duration::from_secs(90));
+    assert_eq!(state.process_uptime(), Some(Duration::from_secs(0)));
+
+    let state = read_synth_dump(minimal_minidump()).await;
+    assert_eq!(state.process_uptime(), None);
+}
+
+#[tokio::test]
+async fn test_process_diff() {
+    let dump = read_test_minidump().unwrap();
+    let state = minidump_processor::process_minidump(
+        &dump,
+        &Symbolizer::new(simple_symbol_supplier(vec![])),
+    )
+    .await
+    .unwrap();
+    let diff = state.diff(&state);
+    assert!(diff.is_empty());
+    assert_eq!(diff.crashing_stack_similarity, Some(1.0));
+
+    // With symbols, the crashing stack's frames get function names.
+    let symbolized = minidump_processor::process_minidump(
+        &dump,
+        &Symbolizer::new(simple_symbol_supplier(vec![testdata_symbol_path()])),
+    )
+    .await
+    .unwrap();
+    let diff = state.diff(&symbolized);
+    assert!(!diff.is_empty());
+    assert!(diff.added_modules.is_empty());
+    assert!(diff.removed_modules.is_empty());
+    assert!(diff.changed_modules.is_empty());
+    assert_eq!(diff.old_thread_count, diff.new_thread_count);
+    assert_eq!(diff.old_crash_reason, diff.new_crash_reason);
+    let similarity = diff.crashing_stack_similarity.unwrap();
+    assert!((0.0..1.0).contains(&similarity));
+
+    // Modules that only one of the states has.
+    let with_modules = |modules: &[(&str, u64)]| {
+        let mut dump = minimal_minidump();
+        for &(name, base) in modules {
+            let name = DumpString::new(name, Endian::Little);
+            dump = dump
+                .add_module(synth_minidump::Module::new(
+                    Endian::Little,
+                    base,
+                    0x10000,
+                    &name,
+                    0,
+                    0,
+                    None,
+                ))
+                .add(name);
+        }
+        dump
+    };
+    let old_dump = with_modules(&[("app.exe", 0x0040_0000)]);
+    let new_dump = with_modules(&[("app.exe", 0x0040_0000), ("lib.dll", 0x1000_0000)]);
+    let old_state = read_synth_dump(old_dump).await;
+    let new_state = read_synth_dump(new_dump).await;
+    let diff = old_state.diff(&new_state);
+    assert_eq!(diff.added_modules, ["lib.dll"]);
+    assert!(diff.removed_modules.is_empty());
+    assert_eq!(diff.crashing_stack_similarity, None);
+    assert_eq!(new_state.diff(&old_state).removed_modules, ["lib.dll"]);
+}
+
+#[tokio::test]
+async fn test_to_json() {
+    let dump = read_test_minidump().unwrap();
+    let state = minidump_processor::process_minidump(
+        &dump,
+        &Symbolizer::new(simple_symbol_supplier(vec![testdata_symbol_path()])),
+    )
+    .await
+    .unwrap();
+    let json = state.to_json();
+
+    // print_json writes the same thing.
+    let mut printed = vec![];
+    state.print_json(&mut printed, false).unwrap();
+    assert_eq!(
+        serde_json::from_slice::<serde_json::Value>(&printed).unwrap(),
+        json
+    );
+
+    assert_eq!(json["status"], "OK");
+    assert_eq!(json["system_info"]["os"], "Windows NT");
+    assert_eq!(json["system_info"]["cpu_arch"], "x86");
+    assert_eq!(json["crash_info"]["address"], "0x00000045");
+    assert_eq!(json["crash_info"]["crashing_thread"], 0);
+    assert_eq!(json["thread_count"], 2);
+    let f0 = &json["threads"][0]["frames"][0];
+    assert_eq!(f0["trust"], "context");
+    assert_eq!(f0["module"], "test_app.exe");
+    assert_eq!(f0["function"], "`anonymous namespace'::CrashFunction");
+    assert_eq!(json["crashing_thread"]["threads_index"], 0);
+    assert_eq!(
+        json["crashing_thread"]["frames"][0]["registers"]["eip"],
+        "0x0040429e"
+    );
+    assert_eq!(json["modules"][0]["filename"], "test_app.exe");
+
+    // The keys added after the stable schema are only there when asked for.
+    assert_eq!(json.get("signature"), None);
+    assert_eq!(json["threads"][0].get("registers"), None);
+    let options = JsonOptions::extended();
+    let json = state.to_json_with_options(&options);
+    let mut printed = vec![];
+    state
+        .print_json_with_options(&mut printed, false, &options)
+        .unwrap();
+    assert_eq!(
+        serde_json::from_slice::<serde_json::Value>(&printed).unwrap(),
+        json
+    );
+    assert_eq!(json["signature"], "`anonymous namespace'::CrashFunction");
+    assert_eq!(json["threads"][0]["registers"]["eip"], "0x0040429e");
+}
+
+#[tokio::test]
+async fn test_exploitability() {
+    let dump = read_test_minidump().unwrap();
+    let provider = Symbolizer::new(simple_symbol_supplier(vec![testdata_symbol_path()]));
+    let state = minidump_processor::process_minidump(&dump, &provider)
+        .await
+        .unwrap();
+    assert_eq!(state.exploitability, None);
+
+    let mut options = ProcessorOptions::default();
+    options.analyze_exploitability = true;
+    let state = minidump_processor::process_minidump_with_options(&dump, &provider, options)
+        .await
+        .unwrap();
+    // A write to 0x45 is a null pointer dereference.
+    let exploitability = state.exploitability.as_ref().unwrap();
+    assert_eq!(exploitability.rating, ExploitabilityRating::Low);
+    assert_eq!(
+        exploitability.evidence,
+        vec![(
+            ExploitabilityRating::Low,
+            String::from("near-null pointer access at 0x45")
+        )]
+    );
+    assert_eq!(state.to_json()["sensitive"]["exploitability"], "low");
+}
+
+#[tokio::test]
+async fn test_bit_flips() {
+    let dump = read_test_minidump().unwrap();
+    let provider = Symbolizer::new(simple_symbol_supplier(vec![testdata_symbol_path()]));
+    let state = minidump_processor::process_minidump(&dump, &provider)
+        .await
+        .unwrap();
+    assert_eq!(state.possible_bit_flips, None);
+
+    let mut options = ProcessorOptions::default();
+    options.detect_bit_flips = true;
+    let state = minidump_processor::process_minidump_with_options(&dump, &provider, options)
+        .await
+        .unwrap();
+    // 0x45 is a null pointer, and the crashing instruction is in test_app.exe.
+    assert_eq!(state.possible_bit_flips, Some(vec![]));
+}
+
+#[tokio::test]
+async fn test_suspicious_modules() {
+    let endian = Endian::Little;
+    let names = [
+        DumpString::new(r"C:\Program Files\App\app.exe", endian),
+        DumpString::new(r"C:\Users\me\AppData\Local\Temp\inject.dll", endian),
+    ];
+    let mut dump = minimal_minidump();
+    for (name, &base) in names.iter().zip(&[0x0040_0000, 0x1000_0000]) {
+        dump = dump.add_module(synth_minidump::Module::new(
+            endian, base, 0x10000, name, 0, 0, None,
+        ));
+    }
+    for name in names {
+        dump = dump.add(name);
+    }
+    // MEM_COMMIT, PAGE_EXECUTE_READ and MEM_IMAGE. The first region is
+    // app.exe's, the other two are one image missing from the module list.
+    for &(base, size) in &[
+        (0x0040_0000, 0x1000),
+        (0x2000_0000, 0x1000),
+        (0x2000_1000, 0x2000),
+    ] {
+        dump = dump.add_memory_info(synth_minidump::MemoryInfo::new(
+            endian,
+            base,
+            base,
+            0x20,
+            size,
+            0x1000,
+            0x20,
+            0x0100_0000,
+        ));
+    }
+    let dump = Minidump::read(dump.finish().unwrap()).unwrap();
+    let provider = Symbolizer::new(simple_symbol_supplier(vec![]));
+
+    let state = minidump_processor::process_minidump(&dump, &provider)
+        .await
+        .unwrap();
+    assert_eq!(state.suspicious_modules, None);
+
+    let mut options = ProcessorOptions::default();
+    options.detect_suspicious_modules = true;
+    let state = minidump_processor::process_minidump_with_options(&dump, &provider, options)
+        .await
+        .unwrap();
+    assert_eq!(
+        state.suspicious_modules,
+        Some(vec![
+            SuspiciousModule {
+                code_file: Some(String::from(r"C:\Users\me\AppData\Local\Temp\inject.dll")),
+                base_address: 0x1000_0000,
+                size: 0x10000,
+                reason: SuspiciousModuleReason::UnusualPath,
+            },
+            SuspiciousModule {
+                code_file: None,
+                base_address: 0x2000_0000,
+                size: 0x3000,
+                reason: SuspiciousModuleReason::Unlisted,
+            },
+        ])
+    );
+}
+
+#[tokio::test]
+async fn test_json_stream_writer() {
+    let dump = read_test_minidump().unwrap();
+    let provider = Symbolizer::new(simple_symbol_supplier(vec![testdata_symbol_path()]));
+    let expected = minidump_processor::process_minidump(&dump, &provider)
+        .await
+        .unwrap()
+        .to_json();
+
+    let provider = Symbolizer::new(simple_symbol_supplier(vec![testdata_symbol_path()]));
+    let writer = Arc::new(JsonStreamWriter::new(vec![]));
+    let mut options = ProcessorOptions::default();
+    options.thread_observer = Some(writer.clone());
+    options.discard_frames = true;
+    let state = minidump_processor::process_minidump_with_options(&dump, &provider, options)
+        .await
+        .unwrap();
+    writer.finish(&state).unwrap();
+    let output = Arc::try_unwrap(writer).ok().unwrap().into_inner();
+    assert_eq!(
+        serde_json::from_slice::<serde_json::Value>(&output).unwrap(),
+        expected
+    );
+    // The requesting thread keeps its frames for the crashing thread.
+    assert_eq!(state.threads[0].frames.len(), 4);
+
+    // Threads are observed in order.
+    let seen = Arc::new(Mutex::new(vec![]));
+    let seen_threads = seen.clone();
+    let mut options = ProcessorOptions::default();
+    options.thread_observer = Some(Arc::new(
+        move |_: &minidump_processor::SystemInfo, index: usize, stack: &CallStack| {
+            seen_threads
+                .lock()
+                .unwrap()
+                .push((index, stack.frames.len()));
+        },
+    ));
+    options.thread_concurrency = 2;
+    minidump_processor::process_minidump_with_options(&dump, &provider, options)
+        .await
+        .unwrap();
+    assert_eq!(*seen.lock().unwrap(), vec![(0, 4), (1, 0)]);
+}
+
+#[tokio::test]
+async fn test_print_human() {
+    let dump = read_test_minidump().unwrap();
+    let state = minidump_processor::process_minidump(
+        &dump,
+        &Symbolizer::new(simple_symbol_supplier(vec![testdata_symbol_path()])),
+    )
+    .await
+    .unwrap();
+    let print_human = |options: &HumanOptions| {
+        let mut output = vec![];
+        state.print_human(&mut output, options).unwrap();
+        String::from_utf8(output).unwrap()
+    };
+
+    // print and print_brief are just presets.
+    let mut full = vec![];
+    state.print(&mut full).unwrap();
+    assert_eq!(print_human(&HumanOptions::default()).as_bytes(), &full[..]);
+    let mut brief = vec![];
+    state.print_brief(&mut brief).unwrap();
+    assert_eq!(print_human(&HumanOptions::brief()).as_bytes(), &brief[..]);
+
+    // The signature and crash details are left out unless asked for.
+    assert!(!print_human(&HumanOptions::default()).contains("Signature:"));
+    assert!(!print_human(&HumanOptions::default()).contains("Crash address kind:"));
+
+    let mut options = HumanOptions::brief();
+    options.registers = false;
+    options.modules = true;
+    options.signature = true;
+    options.crash_details = true;
+    let output = print_human(&options);
+    assert!(output.contains("Crash reason:  EXCEPTION_ACCESS_VIOLATION_WRITE"));
+    assert!(output.contains("Signature: `anonymous namespace'::CrashFunction\n"));
+    assert!(output.contains("Crash address kind: near-null pointer (from eax)\n"));
+    assert!(output.contains("Registers point to:\n"));
+    assert!(output.contains(
+        " 0  test_app.exe!`anonymous namespace'::CrashFunction [test_app.cc : 58 + 0x3]\n    Found by:"
+    ));
+    assert!(!output.contains("eip = "));
+    assert!(output.contains("0x00400000 - 0x0042cfff  test_app.exe  ???  (main)"));
+    assert!(!output.contains("Unknown streams"));
+
+    options.signature = false;
+    options.crash_details = false;
+    let output = print_human(&options);
+    assert!(!output.contains("Signature:"));
+    assert!(!output.contains("Registers point to:"));
+}
+
+#[tokio::test]
+async fn test_machine_readable() {
+    let dump = read_test_minidump().unwrap();
+    let state = minidump_processor::process_minidump(
+        &dump,
+        &Symbolizer::new(simple_symbol_supplier(vec![testdata_symbol_path()])),
+    )
+    .await
+    .unwrap();
+    let mut output = vec![];
+    state.print_machine_readable(&mut output).unwrap();
+    let output = String::from_utf8(output).unwrap();
+    let mut lines = output.lines();
+
+    assert_eq!(lines.next(), Some("OS|Windows NT|5.1.2600 Service Pack 2"));
+    assert_eq!(
+        lines.next(),
+        Some("CPU|x86|GenuineIntel family 6 model 13 stepping 8|1")
+    );
+    assert_eq!(
+        lines.next(),
+        Some("Crash|EXCEPTION_ACCESS_VIOLATION_WRITE|0x45|0")
+    );
+    let modules: Vec<_> = lines.by_ref().take_while(|line| !line.is_empty()).collect();
+    assert_eq!(modules.len(), state.modules.iter().count());
+    assert_eq!(
+        modules[0],
+        "Module|test_app.exe||test_app.pdb|5A9832E5287241C1838ED98914E9B7FF1|0x00400000|0x0042cfff|1"
+    );
+
+    let frames: Vec<_> = lines.collect();
+    assert_eq!(
+        frames[0],
+        "0|0|test_app.exe|`anonymous namespace'::CrashFunction|c:\\test_app.cc|58|0x3"
+    );
+    assert!(frames.iter().all(|line| line.split('|').count() == 7));
+    assert!(frames.iter().any(|line| line.starts_with("1|0|")));
+}
+
+#[cfg(feature = "serde")]
+#[tokio::test]
+async fn test_serialize_process_state() {
+    let dump = read_test_minidump().unwrap();
+    let state = minidump_processor::process_minidump(
+        &dump,
+        &Symbolizer::new(simple_symbol_supplier(vec![testdata_symbol_path()])),
+    )
+    .await
+    .unwrap();
+    let json = serde_json::to_value(&state).unwrap();
+
+    assert_eq!(json["system_info"]["os"], "windows");
+    assert_eq!(json["system_info"]["cpu"], "x86");
+    assert_eq!(json["crash_address"], 0x45);
+    assert_eq!(json["threads"].as_array().unwrap().len(), 2);
+    assert_eq!(json["modules"][0]["code_file"], "c:\\test_app.exe");
+
+    let f0 = &json["threads"][0]["frames"][0];
+    assert_eq!(f0["function_name"], "`anonymous namespace'::CrashFunction");
+    assert_eq!(f0["module"]["code_file"], "c:\\test_app.exe");
+    assert_eq!(f0["trust"], "Context");
+    assert_eq!(f0["context"]["eip"], "0x0040429e");
+
+    // The types that are entirely our own can be read back.
+    let trust: FrameTrust = serde_json::from_value(f0["trust"].clone()).unwrap();
+    assert_eq!(trust, FrameTrust::Context);
+}
+
+#[cfg(feature = "protobuf")]
+#[tokio::test]
+async fn test_protobuf() {
+    use minidump_processor::protobuf::ProcessedCrash;
+    use prost::Message;
+
+    let dump = read_test_minidump().unwrap();
+    let state = minidump_processor::process_minidump(
+        &dump,
+        &Symbolizer::new(simple_symbol_supplier(vec![testdata_symbol_path()])),
+    )
+    .await
+    .unwrap();
+    let crash = ProcessedCrash::decode(&*state.encode_protobuf()).unwrap();
+    assert_eq!(crash, state.to_protobuf());
+
+    let crash_info = crash.crash_info.unwrap();
+    assert_eq!(crash_info.address, Some(0x45));
+    assert_eq!(crash_info.crashing_thread, Some(0));
+    assert_eq!(crash.main_module, Some(0));
+    assert_eq!(crash.modules[0].filename, "test_app.exe");
+    assert_eq!(crash.threads.len(), 2);
+
+    let f0 = &crash.threads[0].frames[0];
+    assert_eq!(
+        f0.function.as_deref(),
+        Some("`anonymous namespace'::CrashFunction")
+    );
+    assert_eq!(f0.module, Some(0));
+    assert_eq!(f0.trust, "context");
+}
+
+#[cfg(feature = "symcache")]
+#[tokio::test]
+async fn test_symcache_symbol_provider() {
+    use minidump_processor::SymCacheSymbolProvider;
+    use symbolic::minidump::cfi::CfiCache;
+    use symbolic::common::ByteView;
+    use symbolic::debuginfo::Object;
+    use symbolic::symcache::SymCacheWriter;
+
+    // Make the caches from the Breakpad symbols, in the same layout.
+    let module_dir = Path::new("test_app.pdb").join("5A9832E5287241C1838ED98914E9B7FF1");
+    let sym = ByteView::open(
+        testdata_symbol_path()
+            .join(&module_dir)
+            .join("test_app.sym"),
+    )
+    .unwrap();
+    let object = Object::parse(&sym).unwrap();
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::create_dir_all(dir.path().join(&module_dir)).unwrap();
+    let symcache =
+        std::fs::File::create(dir.path().join(&module_dir).join("test_app.symcache")).unwrap();
+    SymCacheWriter::write_object(&object, symcache).unwrap();
+    let cficache = CfiCache::from_object(&object).unwrap();
+    std::fs::write(
+        dir.path().join(&module_dir).join("test_app.cficache"),
+        cficache.as_slice(),
+    )
+    .unwrap();
+
+    let dump = read_test_minidump().unwrap();
+    let provider = SymCacheSymbolProvider::new(vec![dir.path().to_owned()]);
+    let state = minidump_processor::process_minidump(&dump, &provider)
+        .await
+        .unwrap();
+
+    let f0 = &state.threads[0].frames[0];
+    assert_eq!(
+        f0.function_name.as_deref(),
+        Some("`anonymous namespace'::CrashFunction")
+    );
+    assert_eq!(f0.source_file_name.as_deref(), Some("c:\\test_app.cc"));
+    assert_eq!(f0.source_line, Some(58));
+    assert!(state.symbol_stats["test_app.exe"].loaded_symbols);
+
+    // The caches give the same stack as the symbols they were made from.
+    let breakpad = minidump_processor::process_minidump(
+        &dump,
+        &Symbolizer::new(simple_symbol_supplier(vec![testdata_symbol_path()])),
+    )
+    .await
+    .unwrap();
+    let functions = |state: &ProcessState| {
+        state.threads[0]
+            .frames
+            .iter()
+            .map(|frame| frame.function_name.clone())
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(functions(&state), functions(&breakpad));
+}
+
+fn minimal_minidump() -> SynthMinidump {
+    let context = synth_minidump::x86_context(Endian::Little, 0xabcd1234, 0x1010);
+    let stack = Memory::with_section(
+        Section::with_endian(Endian::Little).append_repeated(0, 0x1000),
+        0x1000,
+    );
+    let thread = Thread::new(Endian::Little, 0x1234, &stack, &context);
+    let system_info = SystemInfo::new(Endian::Little);
+    SynthMinidump::with_endian(Endian::Little)
+        .add_thread(thread)
+        .add_system_info(system_info)
+        .add(context)
+        .add_memory(stack)
+}
+
+async fn read_synth_dump(dump: SynthMinidump) -> ProcessState {
+    let dump = Minidump::read(dump.finish().unwrap()).unwrap();
+    minidump_processor::process_minidump(&dump, &Symbolizer::new(simple_symbol_supplier(vec![])))
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_crashpad_annotations() {
+    let name = DumpString::new("c:\\test_app.exe", Endian::Little);
+    let module = synth_minidump::Module::new(Endian::Little, 0x400000, 0x10000, &name, 0, 0, None);
+    let crashpad_info = CrashpadInfo::new(Endian::Little)
+        .add_simple_annotation("channel", "beta")
+        .add_module(
+            ModuleCrashpadInfo::new(0, Endian::Little)
+                .add_list_annotation("sandboxed")
+                .add_simple_annotation("feature", "on")
+                .add_annotation_object("build", AnnotationValue::String("1234".to_owned()))
+                .add_annotation_object("custom", AnnotationValue::Custom(0x8001, vec![42])),
+        )
+        .add_module(ModuleCrashpadInfo::new(7, Endian::Little).add_list_annotation("orphan"));
+    let dump = minimal_minidump()
+        .add_module(module)
+        .add(name)
+        .add_crashpad_info(crashpad_info);
+    let state = read_synth_dump(dump).await;
+
+    let crashpad = state.crashpad_annotations.as_ref().unwrap();
+    assert_eq!(crashpad.simple_annotations["channel"], "beta");
+    assert_eq!(crashpad.modules.len(), 2);
+    let module = &crashpad.modules[0];
+    assert_eq!(module.module_index, 0);
+    assert_eq!(module.module.as_deref(), Some("c:\\test_app.exe"));
+    assert_eq!(module.list_annotations, vec!["sandboxed"]);
+    assert_eq!(module.simple_annotations["feature"], "on");
+    // Only string annotation objects are kept.
+    assert_eq!(module.annotation_objects.len(), 1);
+    assert_eq!(module.annotation_objects["build"], "1234");
+    // There's no module 7 to attribute these to.
+    assert_eq!(crashpad.modules[1].module, None);
+
+    let json = state.to_json_with_options(&JsonOptions::extended());
+    let annotations = &json["crashpad_annotations"];
+    assert_eq!(annotations["simple_annotations"]["channel"], "beta");
+    assert_eq!(annotations["modules"][0]["module"], "test_app.exe");
+    assert_eq!(
+        annotations["modules"][0]["annotation_objects"]["build"],
+        "1234"
+    );
+    assert_eq!(annotations["modules"][1]["module"], serde_json::Value::Null);
+
+    let mut human = Vec::new();
+    state.print(&mut human).unwrap();
+    let human = String::from_utf8(human).unwrap();
+    assert!(
+        human.contains("Crashpad annotations:\n  channel: beta\n  test_app.exe:\n    sandboxed\n")
+    );
+
+    // Without a Crashpad info stream, there's nothing.
+    let state = read_synth_dump(minimal_minidump()).await;
+    assert_eq!(state.crashpad_annotations, None);
+    assert_eq!(
+        state.to_json_with_options(&JsonOptions::extended())["crashpad_annotations"],
+        serde_json::Value::Null
+    );
+}
+
+#[tokio::test]
+async fn test_synthesized_thread() {
+    // A dump from a writer that left out the thread list.
+    let context = synth_minidump::x86_context(Endian::Little, 0xabcd1234, 0x1010);
+    let stack = Memory::with_section(
+        Section::with_endian(Endian::Little).append_repeated(0, 0x1000),
+        0x1000,
+    );
+    let exception = access_violation(0x8).set_context(&context);
+    let dump = SynthMinidump::with_endian(Endian::Little)
+        .add_system_info(SystemInfo::new(Endian::Little))
+        .add_exception(exception)
+        .add(context)
+        .add_memory(stack);
+    let state = read_synth_dump(dump).await;
+
+    assert_eq!(state.degradations, vec![Degradation::SynthesizedThread]);
+    assert_eq!(state.threads.len(), 1);
+    assert_eq!(state.requesting_thread, Some(0));
+    let thread = &state.threads[0];
+    assert_eq!(thread.thread_id, 0x1234);
+    assert_eq!(thread.frames[0].instruction, 0xabcd1234);
+
+    // Without an exception stream either, there's nothing to process.
+    let dump =
+        SynthMinidump::with_endian(Endian::Little).add_system_info(SystemInfo::new(Endian::Little));
+    let dump = Minidump::read(dump.finish().unwrap()).unwrap();
+    let provider = Symbolizer::new(simple_symbol_supplier(vec![]));
+    let result = minidump_processor::process_minidump(&dump, &provider).await;
+    assert!(matches!(result, Err(ProcessError::MissingThreadList)));
+}
+
+#[tokio::test]
+async fn test_never_fail() {
+    // A dump from a writer that left out the system info and thread list,
+    // with only an exception stream to go on.
+    let context = synth_minidump::x86_context(Endian::Little, 0xabcd1234, 0x1010);
+    let stack = Memory::with_section(
+        Section::with_endian(Endian::Little).append_repeated(0, 0x1000),
+        0x1000,
+    );
+    let mut exception = Exception::new(Endian::Little).set_context(&context);
+    exception.thread_id = 0x1234;
+    let dump = SynthMinidump::with_endian(Endian::Little)
+        .add_exception(exception)
+        .add(context)
+        .add_memory(stack);
+    let dump = Minidump::read(dump.finish().unwrap()).unwrap();
+    let provider = Symbolizer::new(simple_symbol_supplier(vec![]));
+
+    let result = minidump_processor::process_minidump(&dump, &provider).await;
+    assert!(matches!(result, Err(ProcessError::MissingSystemInfo)));
+
+    let options = ProcessorOptions::default().with_never_fail(true);
+    let state = minidump_processor::process_minidump_with_options(&dump, &provider, options)
+        .await
+        .unwrap();
+    assert_eq!(
+        state.degradations,
+        vec![
+            Degradation::MissingSystemInfo,
+            Degradation::InferredCpu,
+            Degradation::SynthesizedThread,
+        ]
+    );
+    assert_eq!(state.system_info.cpu, Cpu::X86);
+    assert_eq!(state.system_info.os, Os::Unknown(0));
+    assert_eq!(state.threads.len(), 1);
+    assert_eq!(state.requesting_thread, Some(0));
+    let thread = &state.threads[0];
+    assert_eq!(thread.thread_id, 0x1234);
+    assert_eq!(thread.frames[0].instruction, 0xabcd1234);
+    assert_eq!(
+        state.to_json_with_options(&JsonOptions::extended())["degradations"],
+        serde_json::json!(["missing_system_info", "inferred_cpu", "synthesized_thread"])
+    );
+
+    // Without even an exception stream, there's nothing to process, but
+    // that's still not an error.
+    let dump = SynthMinidump::with_endian(Endian::Little);
+    let dump = Minidump::read(dump.finish().unwrap()).unwrap();
+    let options = ProcessorOptions::default().with_never_fail(true);
+    let state = minidump_processor::process_minidump_with_options(&dump, &provider, options)
+        .await
+        .unwrap();
+    assert_eq!(
+        state.degradations,
+        vec![
+            Degradation::MissingSystemInfo,
+            Degradation::MissingThreadList
+        ]
+    );
+    assert!(state.threads.is_empty());
+    assert_eq!(state.requesting_thread, None);
+
+    // A complete dump has nothing to make do without.
+    let state = read_synth_dump(minimal_minidump()).await;
+    assert!(state.degradations.is_empty());
+}
+
+#[tokio::test]
+async fn test_dump_type() {
+    let state = read_synth_dump(minimal_minidump()).await;
+    assert!(state.dump_type.is_empty());
+
+    let flags = MinidumpType::MiniDumpWithFullMemory | MinidumpType::MiniDumpWithHandleData;
+    let state = read_synth_dump(minimal_minidump().flags(flags.bits())).await;
+    assert_eq!(state.dump_type, flags);
+
+    let mut output = Vec::new();
+    state.print(&mut output).unwrap();
+    let output = String::from_utf8(output).unwrap();
+    assert!(output.contains("Dump type: MiniDumpWithFullMemory | MiniDumpWithHandleData\n"));
+}
+
+#[tokio::test]
+async fn test_linux_cpu_info() {
+    // Whitespace intentionally wonky to test robustness
+
+    let input = b"
+microcode : 0x1e34a6789
+";
+
+    let dump = minimal_minidump().set_linux_cpu_info(input);
+    let state = read_synth_dump(dump).await;
+
+    assert_eq!(state.system_info.cpu_microcode_version, Some(0x1e34a6789));
+
+    // Two hyper-threaded cores in one package.
+    let input = b"
+processor	: 0
+vendor_id	: GenuineIntel
+cpu family	: 6
+model name	: Intel(R) Core(TM) i7-8700K CPU @ 3.70GHz
+microcode	: 0xde
+physical id	: 0
+core id		: 0
+flags		: fpu sse4_2  avx2
+processor	: 1
+vendor_id	: GenuineIntel
+physical id	: 0
+core id		: 0
+flags		: fpu
+processor	: 2
+physical id	: 0
+core id		: 1
+processor	: 3
+physical id	: 0
+core id		: 1
+";
+
+    let dump = minimal_minidump().set_linux_cpu_info(input);
+    let state = read_synth_dump(dump).await;
+    let system_info = &state.system_info;
+
+    assert_eq!(system_info.cpu_microcode_version, Some(0xde));
+    assert_eq!(system_info.cpu_vendor.as_deref(), Some("GenuineIntel"));
+    assert_eq!(
+        system_info.cpu_model_name.as_deref(),
+        Some("Intel(R) Core(TM) i7-8700K CPU @ 3.70GHz")
+    );
+    assert_eq!(system_info.cpu_features, vec!["fpu", "sse4_2", "avx2"]);
+    assert_eq!(system_info.cpu_physical_core_count, Some(2));
+    assert_eq!(system_info.cpu_package_count, Some(1));
+    // From the system info, not the cpuinfo.
+    assert_eq!(system_info.cpu_family, Some(6));
+    assert_eq!(system_info.cpu_model, Some(0));
+}
+
+#[tokio::test]
+async fn test_linux_lsb_release() {
+    // Whitespace intentionally wonky to test robustness
+    {
+        let input = br#"
+DISTRIB_ID="hello"
+"DISTRIB_RELEASE"  =there
+"DISTRIB_CODENAME" =   "very long string"
+DISTRIB_DESCRIPTION= wow long string!!!
+"#;
+        let dump = minimal_minidump().set_linux_lsb_release(input);
+        let state = read_synth_dump(dump).await;
+
+        let LinuxStandardBase {
+            id,
+            release,
+            codename,
+            description,
+        } = state.linux_standard_base.unwrap();
+
+        assert_eq!(id, "hello");
+        assert_eq!(release, "there");
+        assert_eq!(codename, "very long string");
+        assert_eq!(description, "wow long string!!!");
+    }
+
+    {
+        let input = br#"
+ID="hello"
+"VERSION_ID"  =there
+"VERSION_CODENAME" =   "very long string"
+PRETTY_NAME= wow long string!!!
+"#;
+        let dump = minimal_minidump().set_linux_lsb_release(input);
+        let state = read_synth_dump(dump).await;
+
+        let LinuxStandardBase {
+            id,
+            release,
+            codename,
+            description,
+        } = state.linux_standard_base.unwrap();
+
+        assert_eq!(id, "hello");
+        assert_eq!(release, "there");
+        assert_eq!(codename, "very long string");
+        assert_eq!(description, "wow long string!!!");
+    }
+}
+
+#[tokio::test]
+async fn test_linux_environ() {
+    // Whitespace intentionally wonky to test robustness
+    let input = br#"
+LANG=en_US.UTF-8
+HOME=/home/someone
+ "container" = "podman"
+AWS_SECRET_ACCESS_KEY=hunter2
+LANG=C
+MY_APP_CHANNEL=beta
+"#;
+
+    let dump = minimal_minidump().set_linux_environ(input);
+    let state = read_synth_dump(dump).await;
+    let environment = state.environment.unwrap();
+    assert_eq!(
+        environment.into_iter().collect::<Vec<_>>(),
+        vec![
+            (String::from("LANG"), String::from("en_US.UTF-8")),
+            (String::from("container"), String::from("podman")),
+        ]
+    );
+
+    // Other variables can be allowed too.
+    let dump = minimal_minidump().set_linux_environ(input);
+    let dump = Minidump::read(dump.finish().unwrap()).unwrap();
+    let options = ProcessorOptions::default().with_environment_variable("MY_APP_CHANNEL");
+    let provider = Symbolizer::new(simple_symbol_supplier(vec![]));
+    let state = minidump_processor::process_minidump_with_options(&dump, &provider, options)
+        .await
+        .unwrap();
+    let environment = state.environment.unwrap();
+    assert_eq!(environment.len(), 3);
+    assert_eq!(environment["MY_APP_CHANNEL"], "beta");
+
+    // Nothing is kept without the stream.
+    let state = read_synth_dump(minimal_minidump()).await;
+    assert_eq!(state.environment, None);
+}
+
+#[tokio::test]
+async fn test_linux_proc_status() {
+    // Whitespace intentionally wonky to test robustness
+
+    let input = b"
+Name:\tfirefox
+State:\tS (sleeping)
+Threads:   87
+VmPeak:\t 4194304 kB
+VmSize:\t2097152 kB
+VmRSS:\t  garbage
+Seccomp:\t2
+";
+
+    let dump = minimal_minidump().set_linux_proc_status(input);
+    let state = read_synth_dump(dump).await;
+    let status = state.linux_proc_status.as_ref().unwrap();
+
+    assert_eq!(
+        *status,
+        LinuxProcStatus {
+            state: Some(String::from("S (sleeping)")),
+            threads: Some(87),
+            vm_peak: Some(4 * 1024 * 1024 * 1024),
+            vm_size: Some(2 * 1024 * 1024 * 1024),
+            vm_rss: None,
+            seccomp: Some(2),
+        }
+    );
+    assert_eq!(status.seccomp_name(), Some("filter"));
+
+    let mut output = Vec::new();
+    state.print(&mut output).unwrap();
+    let output = String::from_utf8(output).unwrap();
+    assert!(output.contains("  Threads: 87\n"));
+    assert!(output.contains("  VmPeak: 4194304 kB\n"));
+}
+
+#[tokio::test]
+async fn test_process_security() {
+    let mut misc = MiscStream::new(Endian::Little);
+    misc.process_integrity_level = Some(0x1000);
+    misc.protected_process = Some(0);
+    misc.process_execute_flags = Some(0x1 | 0x8);
+    let state = read_synth_dump(minimal_minidump().add_stream(misc)).await;
+    let security = state.process_security.unwrap();
+    assert_eq!(
+        security,
+        ProcessSecurity {
+            integrity_level: Some(IntegrityLevel::Low),
+            protected_process: Some(false),
+            dep: Some(DepPolicy {
+                enabled: true,
+                permanent: true,
+                atl_thunk_emulation: true,
+            }),
+        }
+    );
+    assert_eq!(
+        security.to_string(),
+        "low integrity, not protected, DEP (permanent)"
+    );
+
+    let json = state.to_json_with_options(&JsonOptions::extended());
+    assert_eq!(json["process_security"]["integrity_level"], "low");
+    assert_eq!(json["process_security"]["sandboxed"], true);
+    assert_eq!(json["process_security"]["elevated"], false);
+
+    // Older misc info doesn't have any of it.
+    let mut misc = MiscStream::new(Endian::Little);
+    misc.process_id = Some(1234);
+    let state = read_synth_dump(minimal_minidump().add_stream(misc)).await;
+    assert_eq!(state.process_security, None);
+}
+
+#[tokio::test]
+async fn test_crash_address_access() {
+    let input = b"
+00005000-00006000 r-xp 00000000 fd:05 1196511 /usr/lib64/libtdb1.so
+00007000-00008000 rw-p 00000000 00:00 0
+";
+
+    let mut exception = Exception::new(Endian::Little);
+    exception.thread_id = 0x1234;
+    exception.exception_record.exception_address = 0x7010;
+    let dump = minimal_minidump()
+        .set_linux_maps(input)
+        .add_exception(exception);
+    let state = read_synth_dump(dump).await;
+
+    assert_eq!(state.crash_address, Some(0x7010));
+    let access = state.crash_address_access.unwrap();
+    assert!(access.mapped);
+    assert!(access.readable);
+    assert!(access.writable);
+    assert!(!access.executable);
+    assert_eq!(access.to_string(), "rw-");
+
+    // Reserved memory isn't mapped, whatever its protection says.
+    let reserved = synth_minidump::MemoryInfo::new(
+        Endian::Little,
+        0x7000,
+        0x7000,
+        0x4, // PAGE_READWRITE
+        0x1000,
+        0x2000, // MEM_RESERVE
+        0x4,
+        0x20000, // MEM_PRIVATE
+    );
+    let mut exception = Exception::new(Endian::Little);
+    exception.thread_id = 0x1234;
+    exception.exception_record.exception_address = 0x7010;
+    let dump = minimal_minidump()
+        .add_memory_info(reserved)
+        .add_exception(exception);
+    let state = read_synth_dump(dump).await;
+    let access = state.crash_address_access.unwrap();
+    assert!(!access.mapped);
+    assert!(!access.readable);
+    assert_eq!(access.to_string(), "unmapped");
+
+    // Without any memory info there's nothing to report.
+    let mut exception = Exception::new(Endian::Little);
+    exception.thread_id = 0x1234;
+    exception.exception_record.exception_address = 0x7010;
+    let dump = minimal_minidump().add_exception(exception);
+    let state = read_synth_dump(dump).await;
+    assert_eq!(state.crash_address_access, None);
+}
+
+fn x86_minidump(platform_id: PlatformId, teb: u64) -> SynthMinidump {
+    let endian = Endian::Little;
+    let context = synth_minidump::x86_context(endian, 0xabcd1234, 0x1010);
+    let stack = Memory::with_section(
+        Section::with_endian(endian).append_repeated(0, 0x1000),
+        0x1000,
+    );
+    let mut system_info = SystemInfo::new(endian);
+    system_info.platform_id = platform_id as u32;
+    SynthMinidump::with_endian(endian)
+        .add_thread(Thread::with_teb(endian, 0x1234, teb, &stack, &context))
+        .add_system_info(system_info)
+        .add(context)
+        .add_memory(stack)
+}
+
+fn access_violation(address: u64) -> Exception {
+    let mut exception = Exception::new(Endian::Little);
+    exception.thread_id = 0x1234;
+    exception.exception_record.exception_code = 0xc0000005; // EXCEPTION_ACCESS_VIOLATION
+    exception.exception_record.number_parameters = 2;
+    exception.exception_record.exception_information[0] = 1; // write
+    exception.exception_record.exception_information[1] = address;
+    exception
+}
+
+#[tokio::test]
+async fn test_stack_overflow() {
+    // A segfault just below the stack mapping on Linux.
+    let maps = b"
+00001000-00002000 rw-p 00000000 00:00 0 [stack]
+";
+    let mut exception = Exception::new(Endian::Little);
+    exception.thread_id = 0x1234;
+    exception.exception_record.exception_code = 11; // SIGSEGV
+    exception.exception_record.exception_address = 0xff8;
+    let dump = x86_minidump(PlatformId::Linux, 0)
+        .set_linux_maps(maps)
+        .add_exception(exception);
+    let state = read_synth_dump(dump).await;
+    assert_eq!(state.stack_overflow, Some(StackOverflow::GuardPage));
+    assert_eq!(
+        state.to_json_with_options(&JsonOptions::extended())["crash_info"]["stack_overflow"],
+        "guard_page"
+    );
+
+    // An access violation just below the stack limit in the TEB on Windows.
+    let teb = 0x7ffd_e000;
+    let teb_memory = || {
+        Memory::with_section(
+            Section::with_endian(Endian::Little)
+                .D32(0) // ExceptionList
+                .D32(0x2000) // StackBase
+                .D32(0x1000), // StackLimit
+            teb,
+        )
+    };
+    let dump = x86_minidump(PlatformId::VER_PLATFORM_WIN32_NT, teb)
+        .add_memory(teb_memory())
+        .add_exception(access_violation(0xffc));
+    let state = read_synth_dump(dump).await;
+    assert_eq!(state.crash_address, Some(0xffc));
+    assert_eq!(state.stack_overflow, Some(StackOverflow::GuardPage));
+
+    // Crashes elsewhere aren't stack overflows.
+    let dump = x86_minidump(PlatformId::VER_PLATFORM_WIN32_NT, teb)
+        .add_memory(teb_memory())
+        .add_exception(access_violation(0x7010));
+    let state = read_synth_dump(dump).await;
+    assert_eq!(state.stack_overflow, None);
+
+    // Windows reports them itself, at least at first.
+    let mut exception = Exception::new(Endian::Little);
+    exception.thread_id = 0x1234;
+    exception.exception_record.exception_code = 0xc00000fd; // EXCEPTION_STACK_OVERFLOW
+    let dump = x86_minidump(PlatformId::VER_PLATFORM_WIN32_NT, 0).add_exception(exception);
+    let state = read_synth_dump(dump).await;
+    assert_eq!(state.stack_overflow, Some(StackOverflow::Reported));
+}
+
+#[cfg(feature = "disasm")]
+#[tokio::test]
+async fn test_disassembly() {
+    // Some nops, then `mov dword [eax + 0x10], 0x0` at the crash, with eax
+    // being 0.
+    let code = Memory::with_section(
+        Section::with_endian(Endian::Little)
+            .append_repeated(0x90, 0x10)
+            .append_bytes(&[0xc7, 0x40, 0x10, 0x00, 0x00, 0x00, 0x00])
+            .append_repeated(0x90, 0x10),
+        0x7000,
+    );
+    let endian = Endian::Little;
+    let context = synth_minidump::x86_context(endian, 0x7010, 0x1010);
+    let stack = Memory::with_section(
+        Section::with_endian(endian).append_repeated(0, 0x1000),
+        0x1000,
+    );
+    let dump = SynthMinidump::with_endian(endian)
+        .add_thread(Thread::new(endian, 0x1234, &stack, &context))
+        .add_system_info(SystemInfo::new(endian))
+        .add(context)
+        .add_memory(stack)
+        .add_memory(code)
+        .add_exception(access_violation(0x10));
+    let dump = Minidump::read(dump.finish().unwrap()).unwrap();
+    let provider = Symbolizer::new(simple_symbol_supplier(vec![]));
+    let state = minidump_processor::process_minidump(&dump, &provider)
+        .await
+        .unwrap();
+    // It's off by default.
+    assert_eq!(state.disassembly, None);
+
+    let options = ProcessorOptions::default().with_disassembly(true);
+    let state =
+        minidump_processor::process_minidump_with_options(&dump, &provider, options.clone())
+            .await
+            .unwrap();
+
+    let disassembly = state.disassembly.as_ref().unwrap();
+    assert_eq!(disassembly.crashing_instruction, 5);
+    assert_eq!(
+        disassembly
+            .instructions
+            .iter()
+            .map(|instruction| instruction.address)
+            .collect::<Vec<_>>(),
+        vec![
+            0x700b, 0x700c, 0x700d, 0x700e, 0x700f, 0x7010, 0x7017, 0x7018, 0x7019, 0x701a, 0x701b
+        ]
+    );
+    let crashing = &disassembly.instructions[5];
+    assert_eq!(
+        crashing.bytes,
+        vec![0xc7, 0x40, 0x10, 0x00, 0x00, 0x00, 0x00]
+    );
+    assert_eq!(disassembly.instructions[0].text, "nop");
+    let operand = disassembly.memory_operand.as_ref().unwrap();
+    assert_eq!(operand.text, "[eax + 0x10]");
+    assert_eq!(operand.address, Some(0x10));
+
+    let json = state.to_json_with_options(&JsonOptions::extended());
+    assert_eq!(json["crash_info"]["disassembly"]["crashing_instruction"], 5);
+    assert_eq!(
+        json["crash_info"]["disassembly"]["memory_operand"]["address"],
+        "0x00000010"
+    );
+
+    // Without the code's memory there's nothing to disassemble.
+    let dump =
+        x86_minidump(PlatformId::VER_PLATFORM_WIN32_NT, 0).add_exception(access_violation(0x10));
+    let dump = Minidump::read(dump.finish().unwrap()).unwrap();
+    let state = minidump_processor::process_minidump_with_options(&dump, &provider, options)
+        .await
+        .unwrap();
+    assert_eq!(state.disassembly, None);
+}
+
+#[tokio::test]
+async fn test_crash_address_class() {
+    // test.dmp writes to 0x45, which was in eax.
+    let dump = read_test_minidump().unwrap();
+    let state = minidump_processor::process_minidump(
+        &dump,
+        &Symbolizer::new(simple_symbol_supplier(vec![])),
+    )
+    .await
+    .unwrap();
+    let class = state.crash_address_class.as_ref().unwrap();
+    assert_eq!(class.kind, CrashAddressKind::NearNull);
+    assert_eq!(class.register.as_deref(), Some("eax"));
+    assert_eq!(
+        state.to_json_with_options(&JsonOptions::extended())["crash_info"]["address_class"],
+        serde_json::json!({ "kind": "near_null", "register": "eax" })
+    );
+
+    // Addresses that aren't near null aren't classified.
+    let state = read_synth_dump(
+        x86_minidump(PlatformId::VER_PLATFORM_WIN32_NT, 0)
+            .add_exception(access_violation(0x7f00_1234)),
+    )
+    .await;
+    assert_eq!(state.crash_address_class, None);
+}
+
+#[tokio::test]
+async fn test_thread_registers() {
+    let dump = read_test_minidump().unwrap();
+    let state = minidump_processor::process_minidump(
+        &dump,
+        &Symbolizer::new(simple_symbol_supplier(vec![])),
+    )
+    .await
+    .unwrap();
+    let context = state.threads[0].context.as_ref().unwrap();
+    assert_eq!(context.get_register("eax"), Some(0x45));
+    assert_eq!(
+        state.to_json_with_options(&JsonOptions::extended())["crashing_thread"]["registers"]["eax"],
+        "0x00000045"
+    );
+
+    // Only the requesting thread's registers are kept unless asked for.
+    let endian = Endian::Little;
+    let mut dump = SynthMinidump::with_endian(endian).add_system_info(SystemInfo::new(endian));
+    for &(id, stack_start) in &[(1, 0x1000), (2, 0x3000)] {
+        let context = synth_minidump::x86_context(endian, 0xabcd1234, stack_start + 0x10);
+        let stack = Memory::with_section(
+            Section::with_endian(endian).append_repeated(0, 0x100),
+            u64::from(stack_start),
+        );
+        dump = dump
+            .add_thread(Thread::new(endian, id, &stack, &context))
+            .add(context)
+            .add_memory(stack);
+    }
+    let mut exception = Exception::new(endian);
+    exception.thread_id = 1;
+    let dump = Minidump::read(dump.add_exception(exception).finish().unwrap()).unwrap();
+    let provider = Symbolizer::new(simple_symbol_supplier(vec![]));
+
+    let state = minidump_processor::process_minidump(&dump, &provider)
+        .await
+        .unwrap();
+    assert!(state.threads[0].context.is_some());
+    assert!(state.threads[1].context.is_none());
+    assert_eq!(
+        state.to_json_with_options(&JsonOptions::extended())["threads"][1]["registers"],
+        serde_json::Value::Null
+    );
+
+    let mut options = ProcessorOptions::default();
+    options.all_thread_registers = true;
+    let state = minidump_processor::process_minidump_with_options(&dump, &provider, options)
+        .await
+        .unwrap();
+    let context = state.threads[1].context.as_ref().unwrap();
+    assert_eq!(context.get_stack_pointer(), 0x3010);
+    assert_eq!(
+        state.to_json_with_options(&JsonOptions::extended())["threads"][1]["registers"]["esp"],
+        "0x00003010"
+    );
+}
+
+#[tokio::test]
+async fn test_register_targets() {
+    let dump = read_test_minidump().unwrap();
     let state = minidump_processor::process_minidump(
         &dump,
-        &Symbolizer::new(simple_symbol_supplier(vec![path])),
+        &Symbolizer::new(simple_symbol_supplier(vec![])),
     )
     .await
     .unwrap();
-    let f0 = &state.threads[0].frames[0];
+    let targets = state
+        .register_targets
+        .iter()
+        .map(|target| {
+            (
+                target.register.as_str(),
+                target.value,
+                target.target.clone(),
+            )
+        })
+        .collect::<Vec<_>>();
+    let module = |name: &str, offset| PointerTarget::Module {
+        name: String::from(name),
+        offset,
+    };
+    let stack = PointerTarget::Stack { thread: 0 };
     assert_eq!(
-        f0.function_name.as_deref(),
-        Some("`anonymous namespace'::CrashFunction")
+        targets,
+        vec![
+            ("eip", 0x40429e, module("test_app.exe", 0x429e)),
+            ("esp", 0x12fe84, stack.clone()),
+            ("ebp", 0x12fe88, stack.clone()),
+            ("ebx", 0x7c80abc1, module("kernel32.dll", 0xabc1)),
+            ("ecx", 0x12fe94, stack),
+            ("edx", 0x42bc58, module("test_app.exe", 0x2bc58)),
+        ]
+    );
+    assert_eq!(
+        state.to_json_with_options(&JsonOptions::extended())["crash_info"]["register_targets"][3],
+        serde_json::json!({
+            "register": "ebx",
+            "value": "0x7c80abc1",
+            "target": "module",
+            "module": "kernel32.dll",
+            "module_offset": "0x0000abc1",
+            "thread": null,
+        })
     );
+
+    // Without memory info, pointers outside of modules and stacks are left out.
+    let dump =
+        x86_minidump(PlatformId::VER_PLATFORM_WIN32_NT, 0).add_exception(access_violation(0));
+    let state = read_synth_dump(dump).await;
+    let targets = state
+        .register_targets
+        .iter()
+        .map(|target| (target.register.as_str(), target.target.clone()))
+        .collect::<Vec<_>>();
+    assert_eq!(targets, vec![("esp", PointerTarget::Stack { thread: 0 })]);
+
+    // With it, they're told apart by what they're mapped to.
+    for (maps, expected) in &[
+        (
+            &b"00001000-00002000 rw-p 00000000 00:00 0 [stack]\n"[..],
+            PointerTarget::Unmapped,
+        ),
+        (
+            &b"abcd0000-abce0000 rw-p 00000000 00:00 0 [heap]\n"[..],
+            PointerTarget::Heap,
+        ),
+        (
+            &b"abcd0000-abce0000 r--p 00000000 fd:05 1196511 /usr/share/fonts/a.ttf\n"[..],
+            PointerTarget::Mapped,
+        ),
+    ] {
+        let dump = x86_minidump(PlatformId::VER_PLATFORM_WIN32_NT, 0)
+            .set_linux_maps(maps)
+            .add_exception(access_violation(0));
+        let state = read_synth_dump(dump).await;
+        let eip = state
+            .register_targets
+            .iter()
+            .find(|target| target.register == "eip")
+            .unwrap();
+        assert_eq!(&eip.target, expected);
+    }
 }
 
-fn minimal_minidump() -> SynthMinidump {
-    let context = synth_minidump::x86_context(Endian::Little, 0xabcd1234, 0x1010);
+#[tokio::test]
+async fn test_named_thread_filter() {
+    let endian = Endian::Little;
+    let names = [
+        DumpString::new("Compositor", endian),
+        DumpString::new("Worker", endian),
+    ];
+    let mut dump = SynthMinidump::with_endian(endian).add_system_info(SystemInfo::new(endian));
+    for &(id, stack_start) in &[(1, 0x10000), (2, 0x20000), (3, 0x30000)] {
+        let context = synth_minidump::x86_context(endian, 0xabcd_0000, stack_start + 0x10);
+        let stack = Memory::with_section(
+            Section::with_endian(endian).append_repeated(0, 0x100),
+            u64::from(stack_start),
+        );
+        dump = dump
+            .add_thread(Thread::new(endian, id, &stack, &context))
+            .add(context)
+            .add_memory(stack);
+    }
+    dump = dump
+        .add_thread_name(ThreadName::new(endian, 2, Some(&names[0])))
+        .add_thread_name(ThreadName::new(endian, 3, Some(&names[1])));
+    for name in names {
+        dump = dump.add(name);
+    }
+    let mut exception = Exception::new(endian);
+    exception.thread_id = 1;
+    let dump = Minidump::read(dump.add_exception(exception).finish().unwrap()).unwrap();
+
+    let options = ProcessorOptions::default()
+        .with_requesting_and_named_threads(vec![String::from("Compositor")]);
+    let state = minidump_processor::process_minidump_with_options(
+        &dump,
+        &Symbolizer::new(simple_symbol_supplier(vec![])),
+        options,
+    )
+    .await
+    .unwrap();
+    assert_eq!(state.requesting_thread, Some(0));
+    assert!(!state.threads[0].frames.is_empty());
+    assert!(!state.threads[1].frames.is_empty());
+    assert_eq!(state.threads[1].thread_name.as_deref(), Some("Compositor"));
+    // Skipped threads keep their names.
+    assert_eq!(state.threads[2].info, CallStackInfo::ThreadSkipped);
+    assert!(state.threads[2].frames.is_empty());
+    assert_eq!(state.threads[2].thread_name.as_deref(), Some("Worker"));
+
+    let provider = Symbolizer::new(simple_symbol_supplier(vec![]));
+    let (dump, provider) = (&dump, &provider);
+    let walked = move |filter| {
+        let options = ProcessorOptions::default().with_thread_filter(filter);
+        async move {
+            let state = minidump_processor::process_minidump_with_options(dump, provider, options)
+                .await
+                .unwrap();
+            state
+                .threads
+                .iter()
+                .filter(|thread| thread.info != CallStackInfo::ThreadSkipped)
+                .map(|thread| thread.thread_id)
+                .collect::<Vec<_>>()
+        }
+    };
+    let select = |ids: &[u32], names: &[&str], skip_names: Option<&str>| ThreadFilter::Select {
+        ids: ids.iter().copied().collect(),
+        names: names.iter().map(|&name| String::from(name)).collect(),
+        skip_names: skip_names.map(|pattern| ThreadNamePattern::new(pattern).unwrap()),
+    };
+    assert_eq!(walked(select(&[], &[], None)).await, vec![1, 2, 3]);
+    assert_eq!(walked(select(&[3], &[], None)).await, vec![1, 3]);
+    assert_eq!(
+        walked(select(&[3], &["Compositor"], None)).await,
+        vec![1, 2, 3]
+    );
+    assert_eq!(walked(select(&[], &[], Some("^Comp"))).await, vec![1, 3]);
+    // Skipping wins, and the requesting thread is always walked.
+    assert_eq!(walked(select(&[1, 3], &[], Some("."))).await, vec![1]);
+}
+
+#[tokio::test]
+async fn test_thread_info() {
+    let endian = Endian::Little;
+    let names = [
+        DumpString::new("app.exe", endian),
+        DumpString::new("lib.dll", endian),
+    ];
+    let bases = [0x0040_0000, 0x1000_0000];
+    let mut dump = SynthMinidump::with_endian(endian).add_system_info(SystemInfo::new(endian));
+    for &(id, stack_start) in &[(1, 0x10000), (2, 0x20000), (3, 0x30000)] {
+        let context = synth_minidump::x86_context(endian, 0xabcd_0000, stack_start + 0x10);
+        let stack = Memory::with_section(
+            Section::with_endian(endian).append_repeated(0, 0x100),
+            u64::from(stack_start),
+        );
+        dump = dump
+            .add_thread(Thread::new(endian, id, &stack, &context))
+            .add(context)
+            .add_memory(stack);
+    }
+    // Thread 1 started in a function with symbols, thread 2 in a module
+    // without any, and thread 3 has no info.
+    dump = dump
+        .add_thread_info(ThreadInfo::new(endian, 1, 15_000_000, 2_500, 0x0040_1010))
+        .add_thread_info(ThreadInfo::new(endian, 2, 0, 0, 0x1000_2000));
+    for (name, &base) in names.iter().zip(bases.iter()) {
+        dump = dump.add_module(synth_minidump::Module::new(
+            endian, base, 0x10000, name, 0, 0, None,
+        ));
+    }
+    for name in names {
+        dump = dump.add(name);
+    }
+    let dump = Minidump::read(dump.finish().unwrap()).unwrap();
+
+    let mut symbols = std::collections::HashMap::new();
+    symbols.insert(
+        String::from("app.exe"),
+        String::from("MODULE windows x86 abcd1234 app.pdb\nFUNC 1000 30 0 worker_main\n"),
+    );
+    let state = minidump_processor::process_minidump(
+        &dump,
+        &Symbolizer::new(string_symbol_supplier(symbols)),
+    )
+    .await
+    .unwrap();
+
+    let thread = &state.threads[0];
+    assert_eq!(thread.kernel_time, Some(Duration::from_millis(1500)));
+    assert_eq!(thread.user_time, Some(Duration::from_micros(250)));
+    assert_eq!(thread.start_address, Some(0x0040_1010));
+    assert_eq!(thread.thread_name.as_deref(), Some("app.exe!worker_main"));
+    let json = state.to_json_with_options(&JsonOptions::extended());
+    assert_eq!(json["threads"][0]["kernel_time_ms"], 1500);
+    assert_eq!(json["threads"][0]["start_address"], "0x00401010");
+
+    let thread = &state.threads[1];
+    assert_eq!(thread.thread_name.as_deref(), Some("lib.dll+0x2000"));
+
+    let thread = &state.threads[2];
+    assert_eq!(thread.kernel_time, None);
+    assert_eq!(thread.start_address, None);
+    assert_eq!(thread.thread_name, None);
+}
+
+#[tokio::test]
+async fn test_guess_requesting_thread() {
+    // A hang dump with no exception stream: two threads waiting in ntdll,
+    // and one busy in the app.
+    let endian = Endian::Little;
+    let names = [
+        DumpString::new("app.exe", endian),
+        DumpString::new("ntdll.dll", endian),
+    ];
+    let bases = [0x0040_0000, 0x7700_0000];
+    let mut dump = SynthMinidump::with_endian(endian).add_system_info(SystemInfo::new(endian));
+    let threads = [
+        (1, 0x10000, 0x7700_1010),
+        (2, 0x20000, 0x0040_1010),
+        (3, 0x30000, 0x7700_2010),
+    ];
+    for &(id, stack_start, eip) in &threads {
+        let context = synth_minidump::x86_context(endian, eip, stack_start + 0x10);
+        let stack = Memory::with_section(
+            Section::with_endian(endian).append_repeated(0, 0x100),
+            u64::from(stack_start),
+        );
+        dump = dump
+            .add_thread(Thread::new(endian, id, &stack, &context))
+            .add(context)
+            .add_memory(stack);
+    }
+    for (name, &base) in names.iter().zip(bases.iter()) {
+        dump = dump.add_module(synth_minidump::Module::new(
+            endian, base, 0x10000, name, 0, 0, None,
+        ));
+    }
+    for name in names {
+        dump = dump.add(name);
+    }
+    let dump = Minidump::read(dump.finish().unwrap()).unwrap();
+
+    let mut symbols = std::collections::HashMap::new();
+    symbols.insert(
+        String::from("app.exe"),
+        String::from("MODULE windows x86 abcd1234 app.pdb\nFUNC 1000 30 0 busy_loop\n"),
+    );
+    symbols.insert(
+        String::from("ntdll.dll"),
+        String::from(
+            "MODULE windows x86 abcd1234 ntdll.pdb
+FUNC 1000 30 0 NtWaitForSingleObject
+FUNC 2000 30 0 ZwRemoveIoCompletion
+",
+        ),
+    );
+    let symbolizer = Symbolizer::new(string_symbol_supplier(symbols));
+
+    let state = minidump_processor::process_minidump(&dump, &symbolizer)
+        .await
+        .unwrap();
+    assert_eq!(state.requesting_thread, None);
+    assert!(!state.requesting_thread_guessed);
+
+    let options = ProcessorOptions::default().with_requesting_thread_guess(true);
+    let state = minidump_processor::process_minidump_with_options(&dump, &symbolizer, options)
+        .await
+        .unwrap();
+    assert_eq!(state.requesting_thread, Some(1));
+    assert!(state.requesting_thread_guessed);
+    let json = state.to_json_with_options(&JsonOptions::extended());
+    assert_eq!(json["crash_info"]["crashing_thread"], 1);
+    assert_eq!(json["crash_info"]["crashing_thread_guessed"], true);
+}
+
+#[tokio::test]
+async fn test_last_error() {
+    // The last error is 13 pointers into the TEB.
+    let teb = Memory::with_section(
+        Section::with_endian(Endian::Little)
+            .append_repeated(0, 13 * 4)
+            .D32(2) // ERROR_FILE_NOT_FOUND
+            .append_repeated(0, 0x100),
+        0x7ffd_e000,
+    );
+    let dump = x86_minidump(PlatformId::Win32NT, 0x7ffd_e000).add_memory(teb);
+    let state = read_synth_dump(dump).await;
+
+    let mut output = vec![];
+    state.print(&mut output).unwrap();
+    let output = String::from_utf8(output).unwrap();
+    assert!(output.contains(
+        "Last error: ERROR_FILE_NOT_FOUND - The system cannot find the file specified.\n"
+    ));
+
+    let json = state.to_json_with_options(&JsonOptions::extended());
+    assert_eq!(
+        json["threads"][0]["last_error_value"],
+        "ERROR_FILE_NOT_FOUND"
+    );
+    assert_eq!(
+        json["threads"][0]["last_error_description"],
+        "The system cannot find the file specified."
+    );
+}
+
+#[tokio::test]
+async fn test_handle_summary() {
+    let endian = Endian::Little;
+    // The name of the log file is on the stack, just past the stack pointer.
+    let log_name = "C:\\logs\\app.log"
+        .encode_utf16()
+        .flat_map(|unit| unit.to_le_bytes())
+        .collect::<Vec<_>>();
+    let context = synth_minidump::x86_context(endian, 0xabcd1234, 0x10010);
     let stack = Memory::with_section(
-        Section::with_endian(Endian::Little).append_repeated(0, 0x1000),
-        0x1000,
+        Section::with_endian(endian)
+            .append_repeated(0, 0x80)
+            .append_bytes(&log_name)
+            .append_repeated(0, 0x1000 - 0x80 - log_name.len()),
+        0x10000,
     );
-    let thread = Thread::new(Endian::Little, 0x1234, &stack, &context);
-    let system_info = SystemInfo::new(Endian::Little);
-    SynthMinidump::with_endian(Endian::Little)
-        .add_thread(thread)
-        .add_system_info(system_info)
+    let file = DumpString::new("File", endian);
+    let event = DumpString::new("Event", endian);
+    let log_path = DumpString::new("\\Device\\HarddiskVolume2\\logs\\app.log", endian);
+    let other_path = DumpString::new("\\Device\\HarddiskVolume2\\other.txt", endian);
+    let dump = SynthMinidump::with_endian(endian)
+        .add_thread(Thread::new(endian, 0x1234, &stack, &context))
+        .add_system_info(SystemInfo::new(endian))
+        .add_exception(access_violation(0x45))
         .add(context)
         .add_memory(stack)
+        .add_handle(HandleDescriptor::new(
+            endian,
+            0x200,
+            Some(&file),
+            Some(&log_path),
+        ))
+        .add_handle(HandleDescriptor::new(
+            endian,
+            0x204,
+            Some(&file),
+            Some(&other_path),
+        ))
+        // The stack pointer happens to hold this one's value.
+        .add_handle(HandleDescriptor::new(endian, 0x10010, Some(&event), None))
+        .add_handle(HandleDescriptor::new(endian, 0x208, None, None))
+        .add(file)
+        .add(event)
+        .add(log_path)
+        .add(other_path);
+    let state = read_synth_dump(dump).await;
+
+    let handles = state.handles.as_ref().unwrap();
+    let counts = handles
+        .counts
+        .iter()
+        .map(|(type_name, &count)| (type_name.as_str(), count))
+        .collect::<Vec<_>>();
+    assert_eq!(counts, [("<unknown>", 1), ("Event", 1), ("File", 2)]);
+    assert!(handles.suspicious_types.is_empty());
+
+    let crash_handles = &handles.crash_handles;
+    assert_eq!(crash_handles.len(), 2);
+    assert_eq!(crash_handles[0].handle, 0x200);
+    assert_eq!(
+        crash_handles[0].reason,
+        CrashHandleReason::NameInMemory(0x10080 + "C:\\logs\\".len() as u64 * 2)
+    );
+    assert_eq!(crash_handles[1].handle, 0x10010);
+    assert_eq!(crash_handles[1].type_name.as_deref(), Some("Event"));
+    assert_eq!(
+        crash_handles[1].reason,
+        CrashHandleReason::Register(String::from("esp"))
+    );
+
+    let json = state.to_json_with_options(&JsonOptions::extended());
+    assert_eq!(json["handles"]["counts"]["File"], 2);
+    assert_eq!(
+        json["handles"]["crash_handles"][0]["reason"],
+        "name_in_memory"
+    );
+    assert_eq!(json["handles"]["crash_handles"][1]["register"], "esp");
+
+    // Without the stream, there's no summary.
+    let state = read_synth_dump(x86_minidump(PlatformId::Win32NT, 0)).await;
+    assert_eq!(state.handles, None);
 }
 
-async fn read_synth_dump(dump: SynthMinidump) -> ProcessState {
-    let dump = Minidump::read(dump.finish().unwrap()).unwrap();
-    minidump_processor::process_minidump(&dump, &Symbolizer::new(simple_symbol_supplier(vec![])))
-        .await
-        .unwrap()
+#[tokio::test]
+async fn test_teb_stack_bounds() {
+    // A return address just past the end of the stack, which the memory
+    // recorded for it goes on past.
+    let endian = Endian::Little;
+    let teb = 0x7ffd_e000;
+    let dump = |stack_base: u32| {
+        let name = DumpString::new("C:\\app\\app.exe", endian);
+        let module = synth_minidump::Module::new(endian, 0x0040_0000, 0x10000, &name, 0, 0, None);
+        let context = synth_minidump::x86_context(endian, 0x0040_1000, 0x1000);
+        let stack = Memory::with_section(
+            Section::with_endian(endian)
+                .append_repeated(0, 0x20)
+                .D32(0x0040_2000) // return address into app.exe
+                .append_repeated(0, 0x1c),
+            0x1000,
+        );
+        let teb_memory = Memory::with_section(
+            Section::with_endian(endian)
+                .D32(0) // ExceptionList
+                .D32(stack_base) // StackBase
+                .D32(0x1000), // StackLimit
+            teb,
+        );
+        let mut system_info = SystemInfo::new(endian);
+        system_info.platform_id = PlatformId::VER_PLATFORM_WIN32_NT as u32;
+        SynthMinidump::with_endian(endian)
+            .add_thread(Thread::with_teb(endian, 0x1234, teb, &stack, &context))
+            .add_system_info(system_info)
+            .add(context)
+            .add_memory(stack)
+            .add_memory(teb_memory)
+            .add_module(module)
+            .add(name)
+    };
+
+    // Within the bounds, the scan finds it.
+    let state = read_synth_dump(dump(0x2000)).await;
+    let frames = &state.threads[0].frames;
+    assert_eq!(frames.len(), 2);
+    assert_eq!(frames[1].trust, FrameTrust::Scan);
+    assert_eq!(frames[1].resume_address, 0x0040_2000);
+
+    // Past the base, it's some other allocation's.
+    let state = read_synth_dump(dump(0x1020)).await;
+    assert_eq!(state.threads[0].frames.len(), 1);
 }
 
 #[tokio::test]
-async fn test_linux_cpu_info() {
-    // Whitespace intentionally wonky to test robustness
+async fn test_wow64_stack() {
+    // A 32-bit thread on 64-bit Windows that's in the middle of a system call:
+    // its context is in wow64cpu.dll, and its 32-bit context is found through
+    // its 64-bit TEB.
+    let endian = Endian::Little;
+    let teb = 0x7ffd_e000;
+    let cpu_area = 0x0030_0000;
+    let stack64_start = 0x0010_0000;
+    let stack32_start = 0x0020_0000;
 
-    let input = b"
-microcode : 0x1e34a6789
-";
+    let names = [
+        DumpString::new("C:\\Windows\\System32\\wow64cpu.dll", endian),
+        DumpString::new("C:\\Windows\\System32\\wow64.dll", endian),
+        DumpString::new("C:\\app\\app.exe", endian),
+    ];
+    let bases = [0x7700_0000, 0x7710_0000, 0x0040_0000];
+    let modules: Vec<_> = names
+        .iter()
+        .zip(bases.iter())
+        .map(|(name, &base)| synth_minidump::Module::new(endian, base, 0x10000, name, 0, 0, None))
+        .collect();
 
-    let dump = minimal_minidump().set_linux_cpu_info(input);
+    let context = synth_minidump::amd64_context(endian, 0x7700_1000, stack64_start);
+    let stack64 = Memory::with_section(
+        Section::with_endian(endian)
+            .append_repeated(0, 16)
+            .D64(0x7710_2000) // return address into wow64.dll
+            .append_repeated(0, 16),
+        stack64_start,
+    );
+    let teb_memory = Memory::with_section(
+        Section::with_endian(endian)
+            .append_repeated(0, 0x1488)
+            .D64(cpu_area), // TlsSlots[WOW64_TLS_CPURESERVED]
+        teb,
+    );
+    let cpu_area_memory = Memory::with_section(
+        Section::with_endian(endian)
+            .D16(0) // flags
+            .D16(0x14c) // machine
+            .append_section(synth_minidump::x86_context(
+                endian,
+                0x0040_1000,
+                stack32_start,
+            )),
+        cpu_area,
+    );
+    let stack32 = Memory::with_section(
+        Section::with_endian(endian)
+            .append_repeated(0, 8)
+            .D32(0x0040_2000) // return address into app.exe
+            .append_repeated(0, 8),
+        u64::from(stack32_start),
+    );
+
+    let mut system_info = SystemInfo::new(endian)
+        .set_processor_architecture(ProcessorArchitecture::PROCESSOR_ARCHITECTURE_AMD64 as u16);
+    system_info.platform_id = PlatformId::VER_PLATFORM_WIN32_NT as u32;
+    let thread = Thread::with_teb(endian, 0x1234, teb, &stack64, &context);
+    let mut dump = SynthMinidump::with_endian(endian)
+        .add_thread(thread)
+        .add_system_info(system_info)
+        .add(context)
+        .add_memory(stack64)
+        .add_memory(teb_memory)
+        .add_memory(cpu_area_memory)
+        .add_memory(stack32);
+    for module in modules {
+        dump = dump.add_module(module);
+    }
+    for name in names {
+        dump = dump.add(name);
+    }
     let state = read_synth_dump(dump).await;
 
-    assert_eq!(state.system_info.cpu_microcode_version, Some(0x1e34a6789));
+    let frames = &state.threads[0].frames;
+    let modules: Vec<_> = frames
+        .iter()
+        .map(|frame| frame.module.as_ref().unwrap().code_file().into_owned())
+        .collect();
+    // The frames in wow64.dll are replaced by the 32-bit frames.
+    assert_eq!(
+        modules,
+        [
+            "C:\\Windows\\System32\\wow64cpu.dll",
+            "C:\\app\\app.exe",
+            "C:\\app\\app.exe",
+        ]
+    );
+    assert!(matches!(
+        frames[0].context.raw,
+        MinidumpRawContext::Amd64(_)
+    ));
+    assert!(matches!(frames[1].context.raw, MinidumpRawContext::X86(_)));
+    assert_eq!(frames[1].trust, FrameTrust::Context);
+    assert_eq!(frames[1].instruction, 0x0040_1000);
+    assert_eq!(frames[2].trust, FrameTrust::Scan);
 }
 
 #[tokio::test]
-async fn test_linux_lsb_release() {
-    // Whitespace intentionally wonky to test robustness
-    {
-        let input = br#"
-DISTRIB_ID="hello"
-"DISTRIB_RELEASE"  =there
-"DISTRIB_CODENAME" =   "very long string"
-DISTRIB_DESCRIPTION= wow long string!!!
-"#;
-        let dump = minimal_minidump().set_linux_lsb_release(input);
-        let state = read_synth_dump(dump).await;
+async fn test_thread_order() {
+    let mut dump =
+        SynthMinidump::with_endian(Endian::Little).add_system_info(SystemInfo::new(Endian::Little));
+    for i in 0..10u32 {
+        let stack_start = 0x10000 * (i + 1);
+        let context =
+            synth_minidump::x86_context(Endian::Little, 0xabcd0000 + i, stack_start + 0x10);
+        let stack = Memory::with_section(
+            Section::with_endian(Endian::Little).append_repeated(0, 0x100),
+            stack_start.into(),
+        );
+        let thread = Thread::new(Endian::Little, 0x100 + i, &stack, &context);
+        dump = dump.add_thread(thread).add(context).add_memory(stack);
+    }
+    let dump = Minidump::read(dump.finish().unwrap()).unwrap();
+    let symbolizer = Symbolizer::new(simple_symbol_supplier(vec![]));
 
-        let LinuxStandardBase {
-            id,
-            release,
-            codename,
-            description,
-        } = state.linux_standard_base.unwrap();
+    for concurrency in [0, 1, 3, 64] {
+        let mut options = ProcessorOptions::default();
+        options.thread_concurrency = concurrency;
+        let state = minidump_processor::process_minidump_with_options(&dump, &symbolizer, options)
+            .await
+            .unwrap();
 
-        assert_eq!(id, "hello");
-        assert_eq!(release, "there");
-        assert_eq!(codename, "very long string");
-        assert_eq!(description, "wow long string!!!");
+        // Threads are reported in the order of the thread list, however many
+        // are walked at once.
+        assert_eq!(state.threads.len(), 10);
+        for (i, thread) in state.threads.iter().enumerate() {
+            assert_eq!(thread.thread_id, 0x100 + i as u32);
+            assert_eq!(thread.frames[0].instruction, 0xabcd0000 + i as u64);
+        }
     }
+}
 
-    {
-        let input = br#"
-ID="hello"
-"VERSION_ID"  =there
-"VERSION_CODENAME" =   "very long string"
-PRETTY_NAME= wow long string!!!
-"#;
-        let dump = minimal_minidump().set_linux_lsb_release(input);
-        let state = read_synth_dump(dump).await;
+#[tokio::test]
+async fn test_prefetch_symbols() {
+    let endian = Endian::Little;
+    let names = [
+        DumpString::new("app.exe", endian),
+        DumpString::new("lib.dll", endian),
+    ];
+    let bases = [0x0040_0000, 0x1000_0000];
+    // The only thread is in neither module.
+    let context = synth_minidump::x86_context(endian, 0xabcd_0000, 0x10010);
+    let stack = Memory::with_section(
+        Section::with_endian(endian).append_repeated(0, 0x100),
+        0x10000,
+    );
+    let thread = Thread::new(endian, 0x100, &stack, &context);
+    let mut dump = SynthMinidump::with_endian(endian)
+        .add_system_info(SystemInfo::new(endian))
+        .add_thread(thread)
+        .add(context)
+        .add_memory(stack);
+    for (name, &base) in names.iter().zip(bases.iter()) {
+        dump = dump.add_module(synth_minidump::Module::new(
+            endian, base, 0x10000, name, 0, 0, None,
+        ));
+    }
+    for name in names {
+        dump = dump.add(name);
+    }
+    let dump = Minidump::read(dump.finish().unwrap()).unwrap();
 
-        let LinuxStandardBase {
-            id,
-            release,
-            codename,
-            description,
-        } = state.linux_standard_base.unwrap();
+    let mut symbols = std::collections::HashMap::new();
+    symbols.insert(
+        String::from("app.exe"),
+        String::from("MODULE windows x86 abcd1234 app.pdb\nFUNC 1000 30 0 main\n"),
+    );
+    for prefetch in [false, true] {
+        let options = ProcessorOptions::default().with_prefetch_symbols(prefetch);
+        let state = minidump_processor::process_minidump_with_options(
+            &dump,
+            &Symbolizer::new(string_symbol_supplier(symbols.clone())),
+            options,
+        )
+        .await
+        .unwrap();
 
-        assert_eq!(id, "hello");
-        assert_eq!(release, "there");
-        assert_eq!(codename, "very long string");
-        assert_eq!(description, "wow long string!!!");
+        // Without prefetching, symbols are only looked for when a stack needs
+        // them.
+        if !prefetch {
+            assert!(state.symbol_stats.is_empty());
+            continue;
+        }
+        assert_eq!(state.symbol_stats.len(), 2);
+        assert!(state.symbol_stats["app.exe"].loaded_symbols);
+        assert!(!state.symbol_stats["lib.dll"].loaded_symbols);
     }
 }
 
 #[tokio::test]
-async fn test_linux_environ() {
-    // Whitespace intentionally wonky to test robustness
+async fn test_truncated_stream() {
+    let input = b"ID=ubuntu\nVERSION_ID=\"20.04\"\n";
+    let mut bytes = minimal_minidump()
+        .set_linux_lsb_release(input)
+        .finish()
+        .unwrap();
 
-    // TODO: add tests for values we care about
-    let input = b"";
+    // Make the lsb-release stream claim to run past the end of the file.
+    let count = u32::from_le_bytes(bytes[8..12].try_into().unwrap()) as usize;
+    let directory = u32::from_le_bytes(bytes[12..16].try_into().unwrap()) as usize;
+    let entry = (0..count)
+        .map(|i| directory + i * 12)
+        .find(|&entry| {
+            u32::from_le_bytes(bytes[entry..entry + 4].try_into().unwrap())
+                == MINIDUMP_STREAM_TYPE::LinuxLsbRelease as u32
+        })
+        .unwrap();
+    let size = input.len() as u32 + bytes.len() as u32;
+    bytes[entry + 4..entry + 8].copy_from_slice(&size.to_le_bytes());
 
-    let dump = minimal_minidump().set_linux_environ(input);
-    let _state = read_synth_dump(dump).await;
+    let dump = Minidump::read(bytes).unwrap();
+    let state = minidump_processor::process_minidump(
+        &dump,
+        &Symbolizer::new(simple_symbol_supplier(vec![])),
+    )
+    .await
+    .unwrap();
+
+    assert!(state.is_truncated());
+    assert_eq!(state.missing_directory_entries, 0);
+    assert_eq!(state.truncated_streams.len(), 1);
+    let stream = &state.truncated_streams[0];
+    assert_eq!(
+        stream.stream_type,
+        MINIDUMP_STREAM_TYPE::LinuxLsbRelease as u32
+    );
+    assert_eq!(stream.location.data_size, size);
+    assert!(stream.available_size < size);
+    assert!(state.linux_standard_base.is_none());
+    // Everything else should still be processed.
+    assert_eq!(state.threads.len(), 1);
+}
+
+/// A microdump of an arm process that crashed at 0xb6f0f110, in libfoo.so.
+fn microdump_text() -> String {
+    let mut context = vec![0u8; 4 + 16 * 4 + 4 + 8 + 32 * 8 + 8 * 4];
+    context[0..4].copy_from_slice(&0x40000003u32.to_le_bytes());
+    context[4 + 13 * 4..4 + 14 * 4].copy_from_slice(&0xa48bd000u32.to_le_bytes());
+    context[4 + 15 * 4..4 + 16 * 4].copy_from_slice(&0xb6f0f110u32.to_le_bytes());
+    let context: String = context.iter().map(|b| format!("{:02x}", b)).collect();
+
+    format!(
+        "W/google-breakpad(26491): -----BEGIN BREAKPAD MICRODUMP-----
+W/google-breakpad(26491): O A arm 04 armv7l 3.4.0-perf-g4d6e88e #1 SMP PREEMPT
+W/google-breakpad(26491): R b SIGSEGV 0x00000010
+W/google-breakpad(26491): S 0 A48BD000 A48BD000 00000008
+W/google-breakpad(26491): S A48BD000 0000000000000000
+W/google-breakpad(26491): C {}
+W/google-breakpad(26491): M B6F0F000 00000000 00013000 DE8A6AA5D0A8E4F95A5F5DB6A2E20E1A0 libfoo.so
+W/google-breakpad(26491): -----END BREAKPAD MICRODUMP-----
+",
+        context
+    )
 }
 
 #[tokio::test]
-async fn test_linux_proc_status() {
-    // Whitespace intentionally wonky to test robustness
+async fn test_microdump() {
+    let dump = Minidump::from_microdump(&microdump_text()).unwrap();
+    let mut symbols = std::collections::HashMap::new();
+    symbols.insert(
+        String::from("libfoo.so"),
+        String::from(
+            "MODULE Linux arm DE8A6AA5D0A8E4F95A5F5DB6A2E20E1A0 libfoo.so\nFUNC 100 20 0 crash_here\n",
+        ),
+    );
+    let state = minidump_processor::process_minidump(
+        &dump,
+        &Symbolizer::new(string_symbol_supplier(symbols)),
+    )
+    .await
+    .unwrap();
 
-    // TODO: add tests for values we care about
-    let input = b"";
+    assert_eq!(state.system_info.os, Os::Android);
+    assert_eq!(state.system_info.cpu, Cpu::Arm);
+    assert_eq!(state.crash_address, Some(0x10));
+    assert_eq!(state.requesting_thread, Some(0));
 
-    let dump = minimal_minidump().set_linux_proc_status(input);
-    let _state = read_synth_dump(dump).await;
+    let frame = &state.threads[0].frames[0];
+    assert_eq!(frame.trust, FrameTrust::Context);
+    assert_eq!(frame.module.as_ref().unwrap().code_file(), "libfoo.so");
+    assert_eq!(frame.function_name.as_deref(), Some("crash_here"));
+
+    // The frame shares its module with the module list instead of copying it.
+    let module = state
+        .modules
+        .shared_module_at_address(frame.instruction)
+        .unwrap();
+    assert!(Arc::ptr_eq(frame.module.as_ref().unwrap(), module));
+}
+
+#[tokio::test]
+async fn test_source_paths() {
+    let dump = Minidump::from_microdump(&microdump_text()).unwrap();
+    let mut symbols = std::collections::HashMap::new();
+    symbols.insert(
+        String::from("libfoo.so"),
+        String::from(
+            "MODULE Linux arm DE8A6AA5D0A8E4F95A5F5DB6A2E20E1A0 libfoo.so
+INFO SOURCE_LINK /builds/* https://example.com/abc123/*
+FILE 1 /builds/libfoo/foo.c
+FUNC 100 20 0 crash_here
+100 20 42 1
+",
+        ),
+    );
+    let mut options = ProcessorOptions::default();
+    options.source_path_mappings = vec![
+        SourcePathMapping {
+            from: String::from("/elsewhere/"),
+            to: String::from("wrong/"),
+        },
+        SourcePathMapping {
+            from: String::from("/builds/"),
+            to: String::from("src/"),
+        },
+    ];
+    let state = minidump_processor::process_minidump_with_options(
+        &dump,
+        &Symbolizer::new(string_symbol_supplier(symbols)),
+        options,
+    )
+    .await
+    .unwrap();
+
+    // The path is rewritten, but the URL comes from the original path.
+    let frame = &state.threads[0].frames[0];
+    assert_eq!(frame.source_file_name.as_deref(), Some("src/libfoo/foo.c"));
+    assert_eq!(frame.source_line, Some(42));
+    assert_eq!(
+        frame.source_url.as_deref(),
+        Some("https://example.com/abc123/libfoo/foo.c")
+    );
+}
+
+#[tokio::test]
+async fn test_source_context() {
+    let dir = tempfile::tempdir().unwrap();
+    let source = (1..=50)
+        .map(|i| format!("line {}\n", i))
+        .collect::<String>();
+    std::fs::create_dir_all(dir.path().join("builds/libfoo")).unwrap();
+    std::fs::write(dir.path().join("builds/libfoo/foo.c"), source).unwrap();
+
+    let dump = Minidump::from_microdump(&microdump_text()).unwrap();
+    let mut symbols = std::collections::HashMap::new();
+    symbols.insert(
+        String::from("libfoo.so"),
+        String::from(
+            "MODULE Linux arm DE8A6AA5D0A8E4F95A5F5DB6A2E20E1A0 libfoo.so
+FILE 1 /builds/libfoo/foo.c
+FUNC 100 20 0 crash_here
+100 20 42 1
+",
+        ),
+    );
+    let options = ProcessorOptions::default()
+        .with_source_root(SourceRoot::Path(dir.path().join("missing")))
+        .with_source_root(SourceRoot::Path(dir.path().to_owned()))
+        .with_source_context_lines(2);
+    let state = minidump_processor::process_minidump_with_options(
+        &dump,
+        &Symbolizer::new(string_symbol_supplier(symbols)),
+        options,
+    )
+    .await
+    .unwrap();
+
+    let frame = &state.threads[0].frames[0];
+    assert_eq!(
+        frame.source_context,
+        Some(SourceContext {
+            start_line: 40,
+            lines: (40..=44).map(|i| format!("line {}", i)).collect(),
+        })
+    );
+
+    let json = state.to_json_with_options(&JsonOptions::extended());
+    let context = &json["threads"][0]["frames"][0]["source_context"];
+    assert_eq!(context["start_line"], 40);
+    assert_eq!(context["lines"][2], "line 42");
+
+    let mut human = Vec::new();
+    state.print(&mut human).unwrap();
+    let human = String::from_utf8(human).unwrap();
+    assert!(human.contains("    > 42: line 42\n"));
+}
+
+#[tokio::test]
+async fn test_other_source_lines() {
+    let dump = Minidump::from_microdump(&microdump_text()).unwrap();
+    let mut symbols = std::collections::HashMap::new();
+    symbols.insert(
+        String::from("libfoo.so"),
+        String::from(
+            "MODULE Linux arm DE8A6AA5D0A8E4F95A5F5DB6A2E20E1A0 libfoo.so
+FILE 1 /builds/libfoo/foo.c
+FILE 2 /builds/libfoo/foo.h
+FUNC 100 20 0 crash_here
+100 20 42 1
+100 20 98 1
+100 20 12 2
+",
+        ),
+    );
+    let state = minidump_processor::process_minidump(
+        &dump,
+        &Symbolizer::new(string_symbol_supplier(symbols)),
+    )
+    .await
+    .unwrap();
+
+    let frame = &state.threads[0].frames[0];
+    assert_eq!(frame.source_line, Some(42));
+    assert_eq!(
+        frame.other_source_lines,
+        vec![
+            (String::from("/builds/libfoo/foo.c"), 98),
+            (String::from("/builds/libfoo/foo.h"), 12),
+        ]
+    );
+
+    let json = state.to_json_with_options(&JsonOptions::extended());
+    let lines = &json["threads"][0]["frames"][0]["other_lines"];
+    assert_eq!(lines[0]["line"], 98);
+    assert_eq!(lines[1]["file"], "/builds/libfoo/foo.h");
+
+    let mut human = Vec::new();
+    state.print(&mut human).unwrap();
+    let human = String::from_utf8(human).unwrap();
+    assert!(human.contains("!crash_here [foo.c : 42 (also 98, foo.h:12) + "));
 }
@@ -0,0 +1,168 @@
+// Copyright 2015 Ted Mielczarek. See the COPYRIGHT
+// file at the top-level directory of this distribution.
+
+//! The `diff` subcommand: processing two minidumps and saying how their
+//! crashes differ.
+//!
+//! This is for checking that a fix changed the crash (or didn't), and for
+//! comparing what two versions of the processor make of the same minidump.
+
+use std::fmt;
+use std::path::Path;
+
+use clap::{App, Arg, ArgMatches, SubCommand};
+use minidump::*;
+use minidump_processor::{MultiSymbolProvider, ProcessState, ProcessorOptions, SignatureOptions};
+use serde_json::json;
+
+use crate::{subcommand_symbols, ErrorFormat, Failure};
+
+pub fn subcommand() -> App<'static, 'static> {
+    SubCommand::with_name("diff")
+        .about("Process two minidumps and compare their crash signatures, modules and crashing stacks.")
+        .arg(
+            Arg::with_name("json")
+                .long("json")
+                .help("Emit the comparison as JSON.")
+        )
+        .arg(
+            Arg::with_name("symbols-url")
+                .long("symbols-url")
+                .multiple(true)
+                .takes_value(true)
+                .number_of_values(1)
+                .help("base URL from which URLs to symbol files can be constructed (see the main --help)")
+        )
+        .arg(
+            Arg::with_name("old")
+                .required(true)
+                .takes_value(true)
+                .help("Path to the minidump file to compare from.")
+        )
+        .arg(
+            Arg::with_name("new")
+                .required(true)
+                .takes_value(true)
+                .help("Path to the minidump file to compare to.")
+        )
+        .arg(
+            Arg::with_name("symbols-path")
+                .multiple(true)
+                .takes_value(true)
+                .help("Path to a symbol file.")
+        )
+}
+
+/// Process the two minidumps `matches` names and print how they differ.
+pub async fn diff(matches: &ArgMatches<'_>) {
+    let provider = subcommand_symbols(matches);
+    let old = process(Path::new(matches.value_of_os("old").unwrap()), &provider).await;
+    let new = process(Path::new(matches.value_of_os("new").unwrap()), &provider).await;
+
+    let diff = old.diff(&new);
+    let options = SignatureOptions::default();
+    let old_signature = old.signature(&options);
+    let new_signature = new.signature(&options);
+    let crash_reason = |state: &ProcessState| {
+        state
+            .crash_reason
+            .map_or_else(|| String::from("none"), |reason| reason.to_string())
+    };
+
+    if matches.is_present("json") {
+        let changed_modules = diff
+            .changed_modules
+            .iter()
+            .map(|change| {
+                json!({
+                    "code_file": change.code_file,
+                    "old_version": change.old_version,
+                    "new_version": change.new_version,
+                })
+            })
+            .collect::<Vec<_>>();
+        let output = json!({
+            "signature": {
+                "old": old_signature,
+                "new": new_signature,
+                "match": old_signature == new_signature,
+            },
+            "crash_reason": {
+                "old": old.crash_reason.map(|reason| reason.to_string()),
+                "new": new.crash_reason.map(|reason| reason.to_string()),
+            },
+            "crashing_stack_similarity": diff.crashing_stack_similarity,
+            "thread_count": {
+                "old": diff.old_thread_count,
+                "new": diff.new_thread_count,
+            },
+            "added_modules": diff.added_modules,
+            "removed_modules": diff.removed_modules,
+            "changed_modules": changed_modules,
+        });
+        println!("{}", output);
+        return;
+    }
+
+    if old_signature == new_signature {
+        println!("Signatures match: {}", old_signature);
+    } else {
+        println!("Signatures differ:");
+        println!("  old: {}", old_signature);
+        println!("  new: {}", new_signature);
+    }
+    print_change("Crash reason", crash_reason(&old), crash_reason(&new));
+    match diff.crashing_stack_similarity {
+        Some(similarity) => println!("Crashing stack similarity: {:.0}%", similarity * 100.0),
+        None => println!("Crashing stack similarity: unknown (no crashing thread)"),
+    }
+    print_change("Threads", diff.old_thread_count, diff.new_thread_count);
+    if !diff.added_modules.is_empty() {
+        println!("Added modules: {}", diff.added_modules.join(", "));
+    }
+    if !diff.removed_modules.is_empty() {
+        println!("Removed modules: {}", diff.removed_modules.join(", "));
+    }
+    if !diff.changed_modules.is_empty() {
+        println!("Changed modules:");
+        for change in &diff.changed_modules {
+            let version = |version: &Option<String>| version.as_deref().unwrap_or("unknown");
+            println!(
+                "  {}: {} -> {}",
+                change.code_file,
+                version(&change.old_version),
+                version(&change.new_version)
+            );
+        }
+    }
+}
+
+/// Print `old`, or how it changed to `new`.
+fn print_change<T: PartialEq + fmt::Display>(label: &str, old: T, new: T) {
+    if old == new {
+        println!("{}: {}", label, old);
+    } else {
+        println!("{}: {} -> {}", label, old, new);
+    }
+}
+
+/// Read and process the minidump at `path`, or exit if that fails.
+async fn process(path: &Path, provider: &MultiSymbolProvider) -> ProcessState {
+    let dump = match Minidump::read_path(path) {
+        Ok(dump) => dump,
+        Err(err) => ErrorFormat::Text.fail(
+            Failure::UnreadableDump,
+            Some(path),
+            &format!("{:?} - Error reading dump: {}", err, err),
+        ),
+    };
+    let options = ProcessorOptions::default();
+    match minidump_processor::process_minidump_with_options(&dump, provider, options).await {
+        Ok(state) => state,
+        Err(err) => ErrorFormat::Text.fail(
+            Failure::of(&err),
+            Some(path),
+            &format!("{:?} - Error processing dump: {}", err, err),
+        ),
+    }
+}
@@ -0,0 +1,538 @@
+// Copyright 2015 Ted Mielczarek. See the COPYRIGHT
+// file at the top-level directory of this distribution.
+
+//! The `explore` subcommand: a terminal UI for looking around a minidump.
+//!
+//! Everything shown comes from processing the minidump the same way as the
+//! main command does, plus the minidump's own memory for the hexdump.
+
+use std::borrow::Cow;
+use std::io;
+use std::path::Path;
+
+use clap::{App, Arg, ArgMatches, SubCommand};
+use crossterm::cursor::Show;
+use crossterm::event::{self, Event, KeyCode};
+use crossterm::execute;
+use crossterm::terminal::{
+    disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen,
+};
+use minidump::*;
+use minidump_processor::{FrameTrust, ProcessState, ProcessorOptions, StackFrame};
+use tui::backend::{Backend, CrosstermBackend};
+use tui::layout::{Constraint, Direction, Layout, Rect};
+use tui::style::{Color, Modifier, Style};
+use tui::text::{Span, Spans};
+use tui::widgets::{Block, Borders, List, ListItem, ListState, Paragraph};
+use tui::{Frame, Terminal};
+
+use crate::{basename, subcommand_symbols, ErrorFormat, Failure};
+
+/// How many bytes each row of the memory pane shows.
+const MEMORY_ROW: u64 = 16;
+
+pub fn subcommand() -> App<'static, 'static> {
+    SubCommand::with_name("explore")
+        .about("Look around a minidump's threads, stacks, registers, memory and modules in a terminal UI.")
+        .arg(
+            Arg::with_name("symbols-url")
+                .long("symbols-url")
+                .multiple(true)
+                .takes_value(true)
+                .number_of_values(1)
+                .help("base URL from which URLs to symbol files can be constructed (see the main --help)")
+        )
+        .arg(
+            Arg::with_name("minidump")
+                .required(true)
+                .takes_value(true)
+                .help("Path to the minidump file to explore.")
+        )
+        .arg(
+            Arg::with_name("symbols-path")
+                .multiple(true)
+                .takes_value(true)
+                .help("Path to a symbol file.")
+        )
+}
+
+/// Process the minidump `matches` names and explore the result until the
+/// user quits.
+pub async fn explore(matches: &ArgMatches<'_>) {
+    let minidump_path = Path::new(matches.value_of_os("minidump").unwrap());
+    let provider = subcommand_symbols(matches);
+
+    let dump = match Minidump::read_path(minidump_path) {
+        Ok(dump) => dump,
+        Err(err) => ErrorFormat::Text.fail(
+            Failure::UnreadableDump,
+            Some(minidump_path),
+            &format!("{:?} - Error reading dump: {}", err, err),
+        ),
+    };
+    let options = ProcessorOptions::default();
+    let state =
+        match minidump_processor::process_minidump_with_options(&dump, &provider, options).await {
+            Ok(state) => state,
+            Err(err) => ErrorFormat::Text.fail(
+                Failure::of(&err),
+                Some(minidump_path),
+                &format!("{:?} - Error processing dump: {}", err, err),
+            ),
+        };
+    let memory = UnifiedMemoryList::new(
+        dump.get_stream::<MinidumpMemoryList>().ok(),
+        dump.get_stream::<MinidumpMemory64List>().ok(),
+    )
+    .unwrap_or_default();
+
+    if let Err(err) = run(Explorer::new(&state, &memory)) {
+        ErrorFormat::Text.fail(
+            Failure::Other,
+            None,
+            &format!("{:?} - Error running the explorer: {}", err, err),
+        );
+    }
+}
+
+/// Take over the terminal and handle keys until the user quits.
+fn run(mut explorer: Explorer) -> io::Result<()> {
+    enable_raw_mode()?;
+    let _restore = RestoreTerminal;
+    let mut stdout = io::stdout();
+    execute!(stdout, EnterAlternateScreen)?;
+    let mut terminal = Terminal::new(CrosstermBackend::new(stdout))?;
+    terminal.hide_cursor()?;
+
+    loop {
+        terminal.draw(|f| explorer.draw(f))?;
+        if let Event::Key(key) = event::read()? {
+            match key.code {
+                KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+                KeyCode::Tab => explorer.next_pane(),
+                KeyCode::BackTab => explorer.prev_pane(),
+                KeyCode::Char('m') => explorer.toggle_modules(),
+                KeyCode::Up | KeyCode::Char('k') => explorer.move_by(-1),
+                KeyCode::Down | KeyCode::Char('j') => explorer.move_by(1),
+                KeyCode::PageUp => explorer.move_by(-16),
+                KeyCode::PageDown => explorer.move_by(16),
+                KeyCode::Home => explorer.move_by(isize::MIN),
+                KeyCode::End => explorer.move_by(isize::MAX),
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Puts the terminal back the way it was, however the explorer exits.
+struct RestoreTerminal;
+
+impl Drop for RestoreTerminal {
+    fn drop(&mut self) {
+        let _ = execute!(io::stdout(), LeaveAlternateScreen, Show);
+        let _ = disable_raw_mode();
+    }
+}
+
+/// The pane that the movement keys move around in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Pane {
+    Threads,
+    Frames,
+    Memory,
+    Modules,
+}
+
+struct Explorer<'a> {
+    state: &'a ProcessState,
+    memory: &'a UnifiedMemoryList<'a>,
+    pane: Pane,
+    threads: ListState,
+    frames: ListState,
+    modules: ListState,
+    /// How many rows the memory pane has been scrolled from the selected
+    /// frame's stack pointer.
+    memory_scroll: i64,
+}
+
+impl<'a> Explorer<'a> {
+    fn new(state: &'a ProcessState, memory: &'a UnifiedMemoryList<'a>) -> Self {
+        let mut explorer = Explorer {
+            state,
+            memory,
+            pane: Pane::Threads,
+            threads: ListState::default(),
+            frames: ListState::default(),
+            modules: ListState::default(),
+            memory_scroll: 0,
+        };
+        // Start out looking at the crash.
+        if !state.threads.is_empty() {
+            explorer
+                .threads
+                .select(Some(state.requesting_thread.unwrap_or(0)));
+        }
+        explorer.reset_frames();
+        if state.modules.iter().next().is_some() {
+            explorer.modules.select(Some(0));
+        }
+        explorer
+    }
+
+    fn selected_frame(&self) -> Option<&'a StackFrame> {
+        let thread = &self.state.threads[self.threads.selected()?];
+        thread.frames.get(self.frames.selected()?)
+    }
+
+    /// Select the first frame of the newly selected thread.
+    fn reset_frames(&mut self) {
+        let has_frames = self
+            .threads
+            .selected()
+            .map_or(false, |i| !self.state.threads[i].frames.is_empty());
+        self.frames.select(if has_frames { Some(0) } else { None });
+        self.memory_scroll = 0;
+    }
+
+    fn next_pane(&mut self) {
+        self.pane = match self.pane {
+            Pane::Threads => Pane::Frames,
+            Pane::Frames => Pane::Memory,
+            Pane::Memory => Pane::Threads,
+            Pane::Modules => Pane::Modules,
+        };
+    }
+
+    fn prev_pane(&mut self) {
+        self.pane = match self.pane {
+            Pane::Threads => Pane::Memory,
+            Pane::Frames => Pane::Threads,
+            Pane::Memory => Pane::Frames,
+            Pane::Modules => Pane::Modules,
+        };
+    }
+
+    fn toggle_modules(&mut self) {
+        self.pane = match self.pane {
+            Pane::Modules => Pane::Threads,
+            _ => Pane::Modules,
+        };
+    }
+
+    fn move_by(&mut self, delta: isize) {
+        let state = self.state;
+        match self.pane {
+            Pane::Threads => {
+                if move_selection(&mut self.threads, state.threads.len(), delta) {
+                    self.reset_frames();
+                }
+            }
+            Pane::Frames => {
+                let frames = self
+                    .threads
+                    .selected()
+                    .map_or(0, |i| state.threads[i].frames.len());
+                if move_selection(&mut self.frames, frames, delta) {
+                    self.memory_scroll = 0;
+                }
+            }
+            Pane::Memory => {
+                self.memory_scroll = self.memory_scroll.saturating_add(delta as i64);
+            }
+            Pane::Modules => {
+                move_selection(&mut self.modules, state.modules.iter().count(), delta);
+            }
+        }
+    }
+
+    fn draw<B: Backend>(&mut self, f: &mut Frame<B>) {
+        let rows = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(1),
+                Constraint::Min(8),
+                Constraint::Length(14),
+                Constraint::Length(1),
+            ])
+            .split(f.size());
+
+        f.render_widget(Paragraph::new(self.summary()), rows[0]);
+        f.render_widget(
+            Paragraph::new(Span::styled(
+                "Tab: next pane  m: modules  Up/Down/PgUp/PgDn/Home/End: move  q: quit",
+                Style::default().fg(Color::DarkGray),
+            )),
+            rows[3],
+        );
+
+        if self.pane == Pane::Modules {
+            let area = rows[1].union(rows[2]);
+            self.draw_modules(f, area);
+            return;
+        }
+
+        let columns = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([
+                Constraint::Percentage(25),
+                Constraint::Percentage(50),
+                Constraint::Percentage(25),
+            ])
+            .split(rows[1]);
+        self.draw_threads(f, columns[0]);
+        self.draw_frames(f, columns[1]);
+        self.draw_registers(f, columns[2]);
+        self.draw_memory(f, rows[2]);
+    }
+
+    fn block(&self, title: &'static str, pane: Pane) -> Block<'static> {
+        let border = if self.pane == pane {
+            Style::default().fg(Color::Yellow)
+        } else {
+            Style::default()
+        };
+        Block::default()
+            .borders(Borders::ALL)
+            .border_style(border)
+            .title(title)
+    }
+
+    /// The crash at a glance.
+    fn summary(&self) -> Spans<'static> {
+        let state = self.state;
+        let mut summary = vec![Span::styled(
+            format!("{} {}", state.system_info.os, state.system_info.cpu),
+            Style::default().add_modifier(Modifier::BOLD),
+        )];
+        if let Some(reason) = state.crash_reason {
+            summary.push(Span::raw(format!("  {}", reason)));
+        }
+        if let Some(address) = state.crash_address {
+            summary.push(Span::raw(format!(" at {:#x}", address)));
+        }
+        Spans::from(summary)
+    }
+
+    fn draw_threads<B: Backend>(&mut self, f: &mut Frame<B>, area: Rect) {
+        let state = self.state;
+        let items = state
+            .threads
+            .iter()
+            .enumerate()
+            .map(|(i, thread)| {
+                let mut text = format!("{:>3} {:#x}", i, thread.thread_id);
+                if let Some(ref name) = thread.thread_name {
+                    text.push(' ');
+                    text.push_str(name);
+                }
+                if state.requesting_thread == Some(i) {
+                    ListItem::new(text).style(Style::default().fg(Color::Red))
+                } else {
+                    ListItem::new(text)
+                }
+            })
+            .collect::<Vec<_>>();
+        let list = List::new(items)
+            .block(self.block("Threads", Pane::Threads))
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+        f.render_stateful_widget(list, area, &mut self.threads);
+    }
+
+    fn draw_frames<B: Backend>(&mut self, f: &mut Frame<B>, area: Rect) {
+        let state = self.state;
+        let frames: &[StackFrame] = match self.threads.selected() {
+            Some(i) => &state.threads[i].frames[..],
+            None => &[],
+        };
+        let items = frames
+            .iter()
+            .enumerate()
+            .map(|(i, frame)| {
+                ListItem::new(Spans::from(vec![
+                    Span::raw(format!("{:>3} ", i)),
+                    Span::styled(describe_frame(frame), trust_style(frame.trust)),
+                ]))
+            })
+            .collect::<Vec<_>>();
+        let list = List::new(items)
+            .block(self.block("Frames", Pane::Frames))
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+        f.render_stateful_widget(list, area, &mut self.frames);
+    }
+
+    fn draw_registers<B: Backend>(&mut self, f: &mut Frame<B>, area: Rect) {
+        let mut lines = Vec::new();
+        if let Some(frame) = self.selected_frame() {
+            lines.push(Spans::from(Span::styled(
+                format!("found by {}", frame.trust.description()),
+                trust_style(frame.trust),
+            )));
+            let context = &frame.context;
+            for reg in context.general_purpose_registers() {
+                let valid = match context.valid {
+                    MinidumpContextValidity::All => true,
+                    MinidumpContextValidity::Some(ref which) => which.contains(reg),
+                };
+                if valid {
+                    lines.push(Spans::from(format!(
+                        "{:>5} = {}",
+                        reg,
+                        context.format_register(reg)
+                    )));
+                }
+            }
+        }
+        let paragraph = Paragraph::new(lines).block(self.block("Registers", Pane::Frames));
+        f.render_widget(paragraph, area);
+    }
+
+    /// A hexdump of the stack, from the selected frame's stack pointer.
+    fn draw_memory<B: Backend>(&mut self, f: &mut Frame<B>, area: Rect) {
+        let mut lines = Vec::new();
+        if let Some(frame) = self.selected_frame() {
+            let stack_pointer = frame.context.get_stack_pointer();
+            let rows = area.height.saturating_sub(2) as i64;
+            let first =
+                (stack_pointer & !(MEMORY_ROW - 1)) as i64 + self.memory_scroll * MEMORY_ROW as i64;
+            for row in 0..rows {
+                let address = (first + row * MEMORY_ROW as i64) as u64;
+                lines.push(self.memory_row(address, stack_pointer));
+            }
+        }
+        let paragraph = Paragraph::new(lines).block(self.block("Memory", Pane::Memory));
+        f.render_widget(paragraph, area);
+    }
+
+    fn memory_row(&self, address: u64, stack_pointer: u64) -> Spans<'static> {
+        let bytes = (address..address.saturating_add(MEMORY_ROW))
+            .map(|address| {
+                let region = self.memory.memory_at_address(address)?;
+                region
+                    .bytes()
+                    .get((address - region.base_address()) as usize)
+                    .copied()
+            })
+            .collect::<Vec<_>>();
+        let mut hex = String::new();
+        let mut ascii = String::new();
+        for byte in &bytes {
+            match byte {
+                Some(byte) => {
+                    hex.push_str(&format!("{:02x} ", byte));
+                    ascii.push(if byte.is_ascii_graphic() {
+                        *byte as char
+                    } else {
+                        '.'
+                    });
+                }
+                None => {
+                    hex.push_str("?? ");
+                    ascii.push(' ');
+                }
+            }
+        }
+        let address_style = if (address..address + MEMORY_ROW).contains(&stack_pointer) {
+            Style::default().fg(Color::Yellow)
+        } else {
+            Style::default().fg(Color::DarkGray)
+        };
+        Spans::from(vec![
+            Span::styled(format!("{:016x}  ", address), address_style),
+            Span::raw(hex),
+            Span::raw(" "),
+            Span::raw(ascii),
+        ])
+    }
+
+    fn draw_modules<B: Backend>(&mut self, f: &mut Frame<B>, area: Rect) {
+        let state = self.state;
+        let items = state
+            .modules
+            .iter()
+            .map(|module| {
+                let code_file = module.code_file();
+                let name = basename(&code_file);
+                let (status, color) = match state.symbol_stats.get(name) {
+                    Some(stats) if stats.loaded_symbols => ("symbols", Color::Green),
+                    Some(stats) if stats.corrupt_symbols => ("corrupt symbols", Color::Red),
+                    Some(_) => ("missing symbols", Color::Yellow),
+                    None => ("", Color::Reset),
+                };
+                let version = module.version().unwrap_or(Cow::Borrowed(""));
+                ListItem::new(Spans::from(vec![
+                    Span::raw(format!(
+                        "{:#018x} - {:#018x}  {:<32} {:<16} ",
+                        module.base_address(),
+                        module.base_address() + module.size() - 1,
+                        name,
+                        version,
+                    )),
+                    Span::styled(status, Style::default().fg(color)),
+                ]))
+            })
+            .collect::<Vec<_>>();
+        let list = List::new(items)
+            .block(self.block("Modules", Pane::Modules))
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+        f.render_stateful_widget(list, area, &mut self.modules);
+    }
+}
+
+/// Move the selection in a list of `len` items by `delta`, staying in the
+/// list. Returns whether the selection changed.
+fn move_selection(list: &mut ListState, len: usize, delta: isize) -> bool {
+    if len == 0 {
+        return false;
+    }
+    let old = list.selected().unwrap_or(0);
+    let new = if delta < 0 {
+        old.saturating_sub(delta.unsigned_abs())
+    } else {
+        old.saturating_add(delta as usize).min(len - 1)
+    };
+    list.select(Some(new));
+    new != old
+}
+
+/// How much to trust a frame, at a glance: the less it's down to guesswork,
+/// the greener it is.
+fn trust_style(trust: FrameTrust) -> Style {
+    let color = match trust {
+        FrameTrust::Context | FrameTrust::PreWalked | FrameTrust::Inlined => Color::Green,
+        FrameTrust::CallFrameInfo => Color::Cyan,
+        FrameTrust::CfiScan | FrameTrust::FramePointer => Color::Yellow,
+        FrameTrust::Scan => Color::Red,
+        FrameTrust::None => Color::DarkGray,
+    };
+    Style::default().fg(color)
+}
+
+/// Where `frame` is, as `module!function [file : line]` (or as little of
+/// that as there are symbols for).
+fn describe_frame(frame: &StackFrame) -> String {
+    let module = match frame.module {
+        Some(ref module) => module,
+        None => return format!("{:#x}", frame.instruction),
+    };
+    let name = String::from(basename(&module.code_file()));
+    match (&frame.function_name, frame.function_base) {
+        (Some(function), Some(function_base)) => {
+            match (&frame.source_file_name, frame.source_line) {
+                (Some(file), Some(line)) => {
+                    format!("{}!{} [{} : {}]", name, function, basename(file), line)
+                }
+                _ => format!(
+                    "{}!{} + {:#x}",
+                    name,
+                    function,
+                    frame.instruction - function_base
+                ),
+            }
+        }
+        _ => format!(
+            "{} + {:#x}",
+            name,
+            frame.instruction - module.base_address()
+        ),
+    }
+}
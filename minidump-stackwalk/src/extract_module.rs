@@ -0,0 +1,165 @@
+// Copyright 2015 Ted Mielczarek. See the COPYRIGHT
+// file at the top-level directory of this distribution.
+
+//! The `extract-module` subcommand: listing a minidump's modules, and writing
+//! one of them out of the minidump's memory to a file.
+//!
+//! This only gets much out of full-memory dumps, since other minidumps rarely
+//! have more of a module than the pages around a crash.
+
+use std::fs;
+use std::path::Path;
+
+use clap::{App, Arg, ArgMatches, SubCommand};
+use minidump::*;
+
+use crate::{basename, parse_number, ErrorFormat, Failure};
+
+pub fn subcommand() -> App<'static, 'static> {
+    SubCommand::with_name("extract-module")
+        .about("List a minidump's modules, or write one of them out of the minidump's memory to a file.")
+        .arg(
+            Arg::with_name("module")
+                .long("module")
+                .takes_value(true)
+                .requires("output")
+                .long_help("The module to extract, by file name or by an address in it (0x...).
+
+Without this, the modules are listed with how much of each the minidump has.\n\n\n")
+        )
+        .arg(
+            Arg::with_name("output")
+                .long("output")
+                .takes_value(true)
+                .requires("module")
+                .help("Where to write the module to.")
+        )
+        .arg(
+            Arg::with_name("raw")
+                .long("raw")
+                .requires("module")
+                .long_help("Write the module's memory exactly as it was mapped.
+
+By default the module is rebuilt into a PE image that tools can load (with its headers fixed \
+up to match its mapped sections), which only works for Windows modules.\n\n\n")
+        )
+        .arg(
+            Arg::with_name("minidump")
+                .required(true)
+                .takes_value(true)
+                .help("Path to the minidump file to extract the module from.")
+        )
+}
+
+/// List the modules, or extract the one `matches` asks for.
+pub fn extract_module(matches: &ArgMatches<'_>) {
+    let minidump_path = Path::new(matches.value_of_os("minidump").unwrap());
+    let dump = match Minidump::read_path(minidump_path) {
+        Ok(dump) => dump,
+        Err(err) => ErrorFormat::Text.fail(
+            Failure::UnreadableDump,
+            Some(minidump_path),
+            &format!("{:?} - Error reading dump: {}", err, err),
+        ),
+    };
+    let modules = dump.get_stream::<MinidumpModuleList>().unwrap_or_default();
+    let memory = UnifiedMemoryList::new(
+        dump.get_stream::<MinidumpMemoryList>().ok(),
+        dump.get_stream::<MinidumpMemory64List>().ok(),
+    )
+    .unwrap_or_default();
+
+    let wanted = match matches.value_of("module") {
+        Some(wanted) => wanted,
+        None => {
+            for module in modules.by_addr() {
+                println!(
+                    "{:#018x} - {:#018x}  {}  ({} of {} bytes in memory)",
+                    module.base_address(),
+                    module.base_address() + module.size() - 1,
+                    basename(&module.code_file()),
+                    bytes_in_memory(module, &memory),
+                    module.size(),
+                );
+            }
+            return;
+        }
+    };
+
+    let module = match find_module(&modules, wanted) {
+        Some(module) => module,
+        None => ErrorFormat::Text.fail(
+            Failure::Other,
+            Some(minidump_path),
+            &format!("No module {} in the minidump", wanted),
+        ),
+    };
+    let code_file = module.code_file();
+    let name = basename(&code_file);
+    let image = if matches.is_present("raw") {
+        module.mapped_image_from_memory(&memory)
+    } else {
+        module.image_from_memory(&memory)
+    };
+    let image = match image {
+        Ok(image) => image,
+        Err(Error::DataError) => ErrorFormat::Text.fail(
+            Failure::Other,
+            Some(minidump_path),
+            &format!(
+                "{} doesn't have valid PE headers in memory (--raw can still write it out)",
+                name
+            ),
+        ),
+        Err(_) => ErrorFormat::Text.fail(
+            Failure::Other,
+            Some(minidump_path),
+            &format!("{} isn't in the minidump's memory", name),
+        ),
+    };
+
+    let output_path = Path::new(matches.value_of_os("output").unwrap());
+    if let Err(err) = fs::write(output_path, &image) {
+        ErrorFormat::Text.fail(
+            Failure::Other,
+            None,
+            &format!(
+                "{:?} - Error writing {}: {}",
+                err,
+                output_path.display(),
+                err
+            ),
+        );
+    }
+    println!(
+        "Wrote {} ({} bytes) to {}",
+        name,
+        image.len(),
+        output_path.display()
+    );
+}
+
+/// The module `wanted` names, either by (case-insensitive) file name or by
+/// an address in it.
+fn find_module<'a>(modules: &'a MinidumpModuleList, wanted: &str) -> Option<&'a MinidumpModule> {
+    if wanted.starts_with("0x") {
+        return modules.module_at_address(parse_number(wanted)?);
+    }
+    modules
+        .iter()
+        .find(|module| basename(&module.code_file()).eq_ignore_ascii_case(wanted))
+}
+
+/// How many of `module`'s bytes the minidump has.
+fn bytes_in_memory(module: &MinidumpModule, memory: &UnifiedMemoryList) -> u64 {
+    let start = module.base_address();
+    let end = start.saturating_add(module.size());
+    memory
+        .by_addr()
+        .map(|region| {
+            let region_start = region.base_address().max(start);
+            let region_end = region.base_address().saturating_add(region.size()).min(end);
+            region_end.saturating_sub(region_start)
+        })
+        .sum()
+}
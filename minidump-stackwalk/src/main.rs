@@ -2,34 +2,53 @@
 // file at the top-level directory of this distribution.
 
 use std::boxed::Box;
+use std::collections::BTreeSet;
 use std::env;
 use std::fs::File;
-use std::io::Write;
+use std::io::{self, Write};
 use std::ops::Deref;
 use std::panic;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
 
 use minidump::*;
 use minidump_processor::{
-    http_symbol_supplier, simple_symbol_supplier, MultiSymbolProvider, ProcessorOptions, Symbolizer,
+    http_symbol_supplier, json_schema, simple_symbol_supplier, BatchProcessor, FetchEvent,
+    HttpSymbolSupplier, HumanOptions, JsonOptions, LinkRegisterMode, MultiSymbolProvider,
+    ProcessError, ProcessState, ProcessorOptions, SourcePathMapping, SourceRoot, Symbolizer,
+    ThreadFilter, ThreadNamePattern,
 };
 
-use clap::{crate_version, App, AppSettings, Arg, ArgGroup};
+use clap::{crate_version, App, AppSettings, Arg, ArgGroup, ArgMatches};
+use futures_util::StreamExt;
 use log::error;
+use serde_json::json;
 use simplelog::{
-    ColorChoice, ConfigBuilder, Level, LevelFilter, TermLogger, TerminalMode, WriteLogger,
+    ColorChoice, CombinedLogger, ConfigBuilder, Level, LevelFilter, SharedLogger, TermLogger,
+    TerminalMode, WriteLogger,
 };
 
+mod diff;
+#[cfg(feature = "explore")]
+mod explore;
+mod extract_module;
+mod memory;
+mod prefetch;
+
 fn make_app() -> App<'static, 'static> {
-    App::new("minidump-stackwalk")
+    let app = App::new("minidump-stackwalk")
         .version(crate_version!())
         .about("Analyzes minidumps and produces a report (either human-readable or JSON).")
         .setting(AppSettings::NextLineHelp)
         .setting(AppSettings::ColoredHelp)
         .setting(AppSettings::DeriveDisplayOrder)
-        .usage("minidump-stackwalk [FLAGS] [OPTIONS] <minidump> [--] [symbols-path]...")
+        .setting(AppSettings::SubcommandsNegateReqs)
+        .setting(AppSettings::ArgsNegateSubcommands)
+        .setting(AppSettings::DisableHelpSubcommand)
+        .setting(AppSettings::VersionlessSubcommands)
+        .usage("minidump-stackwalk [FLAGS] [OPTIONS] <minidump>... [-- <symbols-path>...]")
         .arg(
             Arg::with_name("json")
                 .long("json")
@@ -46,6 +65,14 @@ https://github.com/luser/rust-minidump/blob/master/minidump-processor/json-schem
 The human-readable report does not have a specified format, and may not have as \
 many details as the JSON format. It is intended for quickly inspecting \
 a crash or debugging rust-minidump itself.\n\n\n")
+        )
+        .arg(
+            Arg::with_name("print-schema")
+                .long("print-schema")
+                .long_help("Print the JSON Schema of the --json report, instead of processing a minidump.
+
+It's the schema documented at the link in --json's help, in a form tools can validate the report against or \
+generate code from. Its `version` is the version of minidump-stackwalk.\n\n\n")
         )
         .arg(
             Arg::with_name("cyborg")
@@ -56,6 +83,14 @@ a crash or debugging rust-minidump itself.\n\n\n")
 Because this creates two output streams, you must specify a path to write the --json
 output to. The --human output will be the 'primary' output and default to stdout, which
 can be configured with --output-file as normal.\n\n\n")
+        )
+        .arg(
+            Arg::with_name("socorro")
+                .long("socorro")
+                .long_help("Emit the processed crash JSON of Mozilla's Socorro crash reporting server.
+
+This is the --json report as `json_dump`, with the crash signature and the fields Socorro copies out of the \
+report next to it.\n\n\n")
         )
         .arg(
             Arg::with_name("help-markdown")
@@ -64,7 +99,7 @@ can be configured with --output-file as normal.\n\n\n")
                 .hidden(true)
         )
         .group(ArgGroup::with_name("output-format")
-            .args(&["json", "human", "cyborg"])
+            .args(&["json", "human", "cyborg", "socorro"])
         )
         .arg(
             Arg::with_name("output-file")
@@ -72,12 +107,31 @@ can be configured with --output-file as normal.\n\n\n")
                 .takes_value(true)
                 .help("Where to write the output to (if unspecified, stdout is used)")
         )
+        .arg(
+            Arg::with_name("output-dir")
+                .long("output-dir")
+                .takes_value(true)
+                .conflicts_with("output-file")
+                .help("Where to write each minidump's report to, named after it (required for more than one minidump)")
+        )
         .arg(
             Arg::with_name("log-file")
                 .long("log-file")
                 .takes_value(true)
                 .help("Where to write logs to (if unspecified, stderr is used)")
         )
+        .arg(
+            Arg::with_name("error-format")
+                .long("error-format")
+                .possible_values(&["text", "json"])
+                .default_value("text")
+                .takes_value(true)
+                .long_help("How to report why the minidump couldn't be (fully) processed.
+
+With `json`, each failure is written to stderr as a JSON object on a line of its own, with
+the kind of failure (see Exit Codes below), the exit code, and a message, e.g.
+{\"error\":\"unreadable_dump\",\"exit_code\":2,\"message\":\"...\",\"minidump\":\"foo.dmp\"}\n\n\n")
+        )
         .arg(
             Arg::with_name("verbose")
                 .long("verbose")
@@ -89,18 +143,119 @@ can be configured with --output-file as normal.\n\n\n")
 The unwinder has been heavily instrumented with `trace` logging, so if you want to debug why \
 an unwind happened the way it did, --verbose=trace is very useful (all unwinder logging will \
 be prefixed with `unwind:`).\n\n\n")
+        )
+        .arg(
+            Arg::with_name("verbose-unwind")
+                .long("verbose-unwind")
+                .takes_value(true)
+                .long_help("Also write the unwinder's `trace` logging to this file.
+
+That's everything --verbose=trace would log about walking the stacks, and nothing else.
+The rest of the logging still goes where --log-file says, at the --verbose level.\n\n\n")
         )
         .arg(
             Arg::with_name("pretty")
                 .long("pretty")
                 .help("Pretty-print --json output.")
         )
+        .arg(
+            Arg::with_name("extended-json")
+                .long("extended-json")
+                .help("Include the fields added to --json output since its schema was made stable.")
+        )
         .arg(
             Arg::with_name("brief")
                 .long("brief")
                 .help("Provide a briefer --human report.
 
 Only provides the top-level summary and a backtrace of the crashing thread.")
+        )
+        .arg(
+            Arg::with_name("unwind-diagnostics")
+                .long("unwind-diagnostics")
+                .help("Report how the caller of each frame was looked for.
+
+Lists the ways the stackwalker tried to find each frame's caller, why the ones that didn't \
+work failed, and which stack reads were out of bounds. Useful for figuring out why a stack \
+came out wrong.")
+        )
+        .arg(
+            Arg::with_name("symbolicate-unloaded")
+                .long("symbolicate-unloaded")
+                .help("Look up symbols for frames in modules that were unloaded before the crash.
+
+They're found by the module's code file and code identifier, and the frames are marked as unloaded.")
+        )
+        .arg(
+            Arg::with_name("symbolicate-exports")
+                .long("symbolicate-exports")
+                .help("Name the functions of modules without symbols after the nearest function they export.
+
+This needs the module's image in the minidump, as full-memory minidumps have.")
+        )
+        .arg(
+            Arg::with_name("prefetch-symbols")
+                .long("prefetch-symbols")
+                .help("Fetch the symbols of every module before walking any stacks.
+
+Symbols are fetched several at a time instead of one after another as the stacks need them, which is faster \
+with a symbol server, though it also fetches symbols for modules that aren't on any stack.")
+        )
+        .arg(
+            Arg::with_name("crashing-thread-only")
+                .long("crashing-thread-only")
+                .help("Only walk the stack of the crashing thread.
+
+The other threads are still listed, but without frames, which makes processing much faster.")
+        )
+        .arg(
+            Arg::with_name("guess-crashing-thread")
+                .long("guess-crashing-thread")
+                .help("Guess the crashing thread of minidumps that don't say which thread requested them.
+
+This is for hang dumps and the like. Threads that aren't waiting on anything are preferred, and then \
+the main thread. The guess is marked as one in the report.")
+        )
+        .arg(
+            Arg::with_name("no-scan")
+                .long("no-scan")
+                .help("Don't scan the stack for return addresses.
+
+Scanning finds callers when CFI and frame pointers don't, but it also finds bogus frames. \
+Without it stacks are shorter, but what's there is more likely to be right.")
+        )
+        .arg(
+            Arg::with_name("resume-after-gaps")
+                .long("resume-after-gaps")
+                .help("Keep walking stacks past stretches where no caller could be found.
+
+When part of a stack is missing or trashed, look further up it for a return address to carry on from, \
+and mark what was skipped. The frames after a gap are only as good as scanned ones.")
+        )
+        .arg(
+            Arg::with_name("exploitability")
+                .long("exploitability")
+                .help("Guess how exploitable the crash is.
+
+This is only a coarse heuristic, based on the kind of crash and the addresses involved.")
+        )
+        .arg(
+            Arg::with_name("disassemble")
+                .long("disassemble")
+                .help("Disassemble the instructions around the crashing instruction.
+
+Only x86 and x86-64 are supported, and only if minidump-stackwalk was built with the `disasm` feature.")
+        )
+        .arg(
+            Arg::with_name("signature")
+                .long("signature")
+                .help("Include the crash signature in the --human report.")
+        )
+        .arg(
+            Arg::with_name("crash-details")
+                .long("crash-details")
+                .help("Include what kind of address the crash was at, and what the crashing thread's registers \
+point to, in the --human report.")
         )
         .arg(
             Arg::with_name("raw-json")
@@ -112,6 +267,120 @@ This is a gross hack for some legacy side-channel information that mozilla uses.
 hopefully be phased out and deprecated in favour of just using custom streams in the \
 minidump itself.\n\n\n")
         )
+        .arg(
+            Arg::with_name("arm64-link-register")
+                .long("arm64-link-register")
+                .possible_values(&["heuristic", "always", "never"])
+                .default_value("heuristic")
+                .takes_value(true)
+                .long_help("Whether the link register of an arm64 crashing frame holds its return address.
+
+Leaf functions keep their return address in the link register, while other functions save it \
+in their frame record and leave the link register stale. Without CFI for the crashing frame, \
+`heuristic` uses the link register unless the symbols say it points back into the crashing \
+frame's own function.\n\n\n")
+        )
+        .arg(
+            Arg::with_name("arm64-pac-mask")
+                .long("arm64-pac-mask")
+                .takes_value(true)
+                .validator(|v| {
+                    parse_number(&v)
+                        .map(|_| ())
+                        .ok_or_else(|| String::from("expected a number like 0xffff800000000000"))
+                })
+                .long_help("The bits of arm64 pointers that hold pointer authentication signatures, like 0xffff800000000000.
+
+Saved return addresses and frame pointers of arm64e processes are stripped of these bits.\n\n\n")
+        )
+        .arg(
+            Arg::with_name("max-frames")
+                .long("max-frames")
+                .takes_value(true)
+                .validator(|v| v.parse::<usize>().map(|_| ()).map_err(|e| e.to_string()))
+                .long_help("The most frames to unwind for a single thread (defaults to 1024).
+
+Corrupt stacks can make the stackwalker produce endless junk frames, so threads are cut off \
+at this many frames. Inlined frames don't count towards the limit.\n\n\n")
+        )
+        .arg(
+            Arg::with_name("scan-depth")
+                .long("scan-depth")
+                .takes_value(true)
+                .conflicts_with("no-scan")
+                .validator(|v| v.parse::<usize>().map(|_| ()).map_err(|e| e.to_string()))
+                .long_help("How many pointer-sized values a stack scan looks through (defaults to 40).
+
+Deeper scans find callers past big stack frames, but take longer and find more bogus frames.\n\n\n")
+        )
+        .arg(
+            Arg::with_name("thread")
+                .long("thread")
+                .multiple(true)
+                .takes_value(true)
+                .number_of_values(1)
+                .conflicts_with("crashing-thread-only")
+                .long_help("Only walk the stacks of this thread (and the crashing thread), given by id or name.
+
+Ids can be decimal or hex (0x...). This can be given multiple times.\n\n\n")
+        )
+        .arg(
+            Arg::with_name("skip-threads")
+                .long("skip-threads")
+                .takes_value(true)
+                .conflicts_with("crashing-thread-only")
+                .validator(|v| ThreadNamePattern::new(&v).map(|_| ()).map_err(|e| e.to_string()))
+                .long_help("Don't walk the stacks of threads with names matching this regex.
+
+The crashing thread is always walked. Threads that aren't walked are left out of --human output.\n\n\n")
+        )
+        .arg(
+            Arg::with_name("environment-var")
+                .long("environment-var")
+                .multiple(true)
+                .takes_value(true)
+                .number_of_values(1)
+                .long_help("Also include this environment variable of the process in the output, if the minidump has it.
+
+Only a few variables that describe the locale and any container the process ran in are included by default, since \
+the rest may be private. This can be given multiple times.\n\n\n")
+        )
+        .arg(
+            Arg::with_name("source-path-map")
+                .long("source-path-map")
+                .multiple(true)
+                .takes_value(true)
+                .number_of_values(1)
+                .validator(|v| {
+                    v.split_once('=')
+                        .map(|_| ())
+                        .ok_or_else(|| String::from("expected FROM=TO"))
+                })
+                .long_help("Rewrite source file paths that start with FROM to start with TO instead, given as FROM=TO.
+
+Each path is rewritten by the first mapping it matches. This can be given multiple times.\n\n\n")
+        )
+        .arg(
+            Arg::with_name("source-root")
+                .long("source-root")
+                .multiple(true)
+                .takes_value(true)
+                .number_of_values(1)
+                .long_help("A directory or http(s) URL to read source files from, to show the lines around each frame's source line.
+
+Source paths (after any --source-path-map) are looked for relative to each root in turn, with any \
+drive letter or leading separator dropped. This can be given multiple times.
+
+URLs are only read if minidump-stackwalk was built with the `http-sources` feature.\n\n\n")
+        )
+        .arg(
+            Arg::with_name("source-context-lines")
+                .long("source-context-lines")
+                .takes_value(true)
+                .requires("source-root")
+                .validator(|v| v.parse::<u32>().map(|_| ()).map_err(|e| e.to_string()))
+                .long_help("How many lines of source to show before and after each frame's source line (defaults to 5).\n\n\n")
+        )
         .arg(
             Arg::with_name("symbols-url")
                 .long("symbols-url")
@@ -173,15 +442,17 @@ This is necessary to enforce forward progress on misbehaving http responses.\n\n
         )
         .arg(
             Arg::with_name("minidump")
-                .required(true)
+                .required_unless("print-schema")
+                .multiple(true)
                 .takes_value(true)
-                .help("Path to the minidump file to analyze.")
+                .help("Paths to the minidumps to analyze (with --output-dir, any number of them, directories or globs).")
         )
         .arg(
             Arg::with_name("symbols-path")
                 .multiple(true)
+                .last(true)
                 .takes_value(true)
-                .long_help("Path to a symbol file.
+                .long_help("Path to a symbol file, after a `--`.
 
 If multiple symbols-path values are provided, all symbol files will be merged \
 into minidump-stackwalk's symbol database.\n\n\n")   
@@ -217,7 +488,26 @@ native debuginfo formats. We recommend using a version of dump_syms to generate
     * symbol file docs: https://chromium.googlesource.com/breakpad/breakpad/+/master/docs/symbol_files.md
     * mozilla's dump_syms (co-developed with this program): https://github.com/mozilla/dump_syms
 
-")
+
+
+Exit Codes:
+
+  The report is still written for 4 and 5, but it may be missing symbols (or more).
+
+    * 0: The minidump was processed.
+    * 1: Anything else went wrong, e.g. the arguments were bad.
+    * 2: The minidump couldn't be read at all (`unreadable_dump`).
+    * 3: The minidump is missing a stream it can't be processed without (`missing_streams`).
+    * 4: A symbol server was down, timed out, or sent garbage (`symbol_fetch_failed`).
+    * 5: Processing gave up on part of the minidump (`partial`).
+
+");
+    #[cfg(feature = "explore")]
+    let app = app.subcommand(explore::subcommand());
+    app.subcommand(memory::subcommand())
+        .subcommand(extract_module::subcommand())
+        .subcommand(prefetch::subcommand())
+        .subcommand(diff::subcommand())
 }
 
 #[cfg_attr(test, allow(dead_code))]
@@ -233,6 +523,52 @@ async fn main() {
         return;
     }
 
+    if matches.is_present("print-schema") {
+        println!("{}", serde_json::to_string_pretty(&json_schema()).unwrap());
+        return;
+    }
+
+    // The subcommands are their own thing, with their own args. All they log
+    // is what made them fail.
+    if matches.subcommand_name().is_some() {
+        let _ = TermLogger::init(
+            LevelFilter::Error,
+            ConfigBuilder::new()
+                .set_location_level(LevelFilter::Off)
+                .set_time_level(LevelFilter::Off)
+                .set_thread_level(LevelFilter::Off)
+                .set_target_level(LevelFilter::Off)
+                .add_filter_ignore_str("tracing::span")
+                .build(),
+            TerminalMode::Stderr,
+            ColorChoice::Auto,
+        );
+    }
+    match matches.subcommand() {
+        #[cfg(feature = "explore")]
+        ("explore", Some(explore_matches)) => {
+            explore::explore(explore_matches).await;
+            return;
+        }
+        ("memory", Some(memory_matches)) => {
+            memory::memory(memory_matches);
+            return;
+        }
+        ("extract-module", Some(extract_matches)) => {
+            extract_module::extract_module(extract_matches);
+            return;
+        }
+        ("prefetch-symbols", Some(prefetch_matches)) => {
+            prefetch::prefetch_symbols(prefetch_matches).await;
+            return;
+        }
+        ("diff", Some(diff_matches)) => {
+            diff::diff(diff_matches).await;
+            return;
+        }
+        _ => {}
+    }
+
     let output_file = matches
         .value_of_os("output-file")
         .map(|os_str| Path::new(os_str).to_owned());
@@ -241,6 +577,11 @@ async fn main() {
         .value_of_os("log-file")
         .map(|os_str| Path::new(os_str).to_owned());
 
+    let error_format = match matches.value_of("error-format").unwrap() {
+        "json" => ErrorFormat::Json,
+        _ => ErrorFormat::Text,
+    };
+
     let verbosity = match matches.value_of("verbose").unwrap() {
         "off" => LevelFilter::Off,
         "warn" => LevelFilter::Warn,
@@ -250,34 +591,56 @@ async fn main() {
         _ => LevelFilter::Error,
     };
 
-    // Init the logger (and make trace logging less noisy)
-    if let Some(log_path) = log_file {
+    // Init the logger (and make trace logging less noisy). The processor's
+    // traces come through `tracing`, which also logs every time a span is
+    // entered or exited. Those aren't much use without a `tracing`
+    // subscriber, so leave them out.
+    let logger: Box<dyn SharedLogger> = if let Some(log_path) = log_file {
         let log_file = File::create(log_path).unwrap();
-        let _ = WriteLogger::init(
+        WriteLogger::new(
             verbosity,
             ConfigBuilder::new()
                 .set_location_level(LevelFilter::Off)
                 .set_time_level(LevelFilter::Off)
                 .set_thread_level(LevelFilter::Off)
                 .set_target_level(LevelFilter::Off)
+                .add_filter_ignore_str("tracing::span")
                 .build(),
             log_file,
         )
-        .unwrap();
     } else {
-        let _ = TermLogger::init(
+        TermLogger::new(
             verbosity,
             ConfigBuilder::new()
                 .set_location_level(LevelFilter::Off)
                 .set_time_level(LevelFilter::Off)
                 .set_thread_level(LevelFilter::Off)
                 .set_target_level(LevelFilter::Off)
+                .add_filter_ignore_str("tracing::span")
                 .set_level_color(Level::Trace, None)
                 .build(),
             TerminalMode::Stderr,
             ColorChoice::Auto,
-        );
+        )
+    };
+    let mut loggers = vec![logger];
+    // The unwinder's traces can go to a file of their own, whatever the
+    // verbosity of the rest.
+    if let Some(unwind_path) = matches.value_of_os("verbose-unwind") {
+        let unwind_file = File::create(unwind_path).unwrap();
+        loggers.push(WriteLogger::new(
+            LevelFilter::Trace,
+            ConfigBuilder::new()
+                .set_location_level(LevelFilter::Off)
+                .set_time_level(LevelFilter::Off)
+                .set_thread_level(LevelFilter::Off)
+                .set_target_level(LevelFilter::Off)
+                .add_filter_allow_str("minidump_processor::stackwalker")
+                .build(),
+            unwind_file,
+        ));
     }
+    let _ = CombinedLogger::init(loggers);
 
     // Set a panic hook to redirect to the logger
     panic::set_hook(Box::new(|panic_info| {
@@ -304,7 +667,78 @@ async fn main() {
 
     let mut options = ProcessorOptions::default();
 
-    options.evil_json = matches.value_of_os("raw-json").map(Path::new);
+    options.link_register_mode = match matches.value_of("arm64-link-register").unwrap() {
+        "always" => LinkRegisterMode::Always,
+        "never" => LinkRegisterMode::Never,
+        _ => LinkRegisterMode::Heuristic,
+    };
+    if let Some(mask) = matches.value_of("arm64-pac-mask") {
+        options.arm64_pac_mask = parse_number(mask);
+    }
+    if let Some(max_frames) = matches.value_of("max-frames") {
+        options.max_frames = max_frames.parse().unwrap();
+    }
+    options.allow_scan = !matches.is_present("no-scan");
+    options.resume_after_gaps = matches.is_present("resume-after-gaps");
+    options.guess_requesting_thread = matches.is_present("guess-crashing-thread");
+    if let Some(scan_depth) = matches.value_of("scan-depth") {
+        options.scan_depth = scan_depth.parse().unwrap();
+    }
+    options.analyze_exploitability = matches.is_present("exploitability");
+    options.disassemble = matches.is_present("disassemble");
+    options.unwind_diagnostics = matches.is_present("unwind-diagnostics");
+    options.symbolicate_unloaded_modules = matches.is_present("symbolicate-unloaded");
+    options.symbolicate_from_exports = matches.is_present("symbolicate-exports");
+    options.prefetch_symbols = matches.is_present("prefetch-symbols");
+    if matches.is_present("crashing-thread-only") {
+        options.thread_filter = ThreadFilter::RequestingThread;
+    }
+    let skip_threads = matches.value_of("skip-threads");
+    if matches.is_present("thread") || skip_threads.is_some() {
+        let mut ids = BTreeSet::new();
+        let mut names = BTreeSet::new();
+        for thread in matches.values_of("thread").into_iter().flatten() {
+            match parse_thread_id(thread) {
+                Some(id) => ids.insert(id),
+                None => names.insert(String::from(thread)),
+            };
+        }
+        options.thread_filter = ThreadFilter::Select {
+            ids,
+            names,
+            skip_names: skip_threads.map(|pattern| ThreadNamePattern::new(pattern).unwrap()),
+        };
+    }
+    if let Some(mappings) = matches.values_of("source-path-map") {
+        options.source_path_mappings = mappings
+            .map(|mapping| {
+                let (from, to) = mapping.split_once('=').unwrap();
+                SourcePathMapping {
+                    from: String::from(from),
+                    to: String::from(to),
+                }
+            })
+            .collect();
+    }
+    if let Some(names) = matches.values_of("environment-var") {
+        options
+            .environment_allowlist
+            .extend(names.map(String::from));
+    }
+    if let Some(roots) = matches.values_of("source-root") {
+        options.source_roots = roots
+            .map(|root| {
+                if root.starts_with("http://") || root.starts_with("https://") {
+                    SourceRoot::Url(String::from(root))
+                } else {
+                    SourceRoot::Path(PathBuf::from(root))
+                }
+            })
+            .collect();
+    }
+    if let Some(lines) = matches.value_of("source-context-lines") {
+        options.source_context_lines = lines.parse().unwrap();
+    }
 
     let temp_dir = std::env::temp_dir();
 
@@ -339,13 +773,18 @@ async fn main() {
         .map(Duration::from_secs)
         .unwrap();
 
-    let minidump_path = matches.value_of_os("minidump").map(Path::new).unwrap();
+    let minidump_args = matches
+        .values_of_os("minidump")
+        .unwrap()
+        .map(Path::new)
+        .collect::<Vec<_>>();
 
     // Determine the kind of output we're producing -- json, human, or cyborg (both).
     // Although we have a --human argument it's mostly just there to make the documentation
     // more clear. human output is enabled by default, and --json disables it.
     // Mutual exclusion is enforced by an ArgGroup.
-    let mut json = matches.is_present("json");
+    let socorro = matches.is_present("socorro");
+    let mut json = matches.is_present("json") || socorro;
     let mut human = !json;
     let cyborg = matches.value_of_os("cyborg").map(Path::new);
 
@@ -357,46 +796,168 @@ async fn main() {
     // Now check if arguments that tweak the output are valid. We can't use
     // Arg::requires because clap doesn't understand --json being implicitly enabled.
     let pretty = matches.is_present("pretty");
+    let extended_json = matches.is_present("extended-json");
     let brief = matches.is_present("brief");
+    let signature = matches.is_present("signature");
+    let crash_details = matches.is_present("crash-details");
 
     if pretty && !json {
-        error!("Humans must be hideous! (The --pretty and --human flags cannot both be set)");
-        std::process::exit(1);
+        error_format.fail(
+            Failure::Other,
+            None,
+            "Humans must be hideous! (The --pretty and --human flags cannot both be set)",
+        );
+    }
+
+    if extended_json && !json {
+        error_format.fail(
+            Failure::Other,
+            None,
+            "The --extended-json flag is only valid for --json output (or --cyborg)",
+        );
     }
 
     if brief && !human {
-        error!("Robots cannot be brief! (The --brief flag is only valid for --human output (or --cyborg)");
-        std::process::exit(1);
+        error_format.fail(
+            Failure::Other,
+            None,
+            "Robots cannot be brief! (The --brief flag is only valid for --human output (or --cyborg)",
+        );
     }
 
+    let report = Report {
+        human,
+        json,
+        socorro,
+        brief,
+        signature,
+        crash_details,
+        pretty,
+        extended_json,
+    };
+
     // Ok now let's do the thing!!!!
 
-    match Minidump::read_path(minidump_path) {
-        Ok(dump) => {
-            let mut provider = MultiSymbolProvider::new();
-
-            if !symbols_urls.is_empty() {
-                provider.add(Box::new(Symbolizer::new(http_symbol_supplier(
-                    symbols_paths,
-                    symbols_urls,
-                    symbols_cache,
-                    symbols_tmp,
-                    timeout,
-                ))));
-            } else if !symbols_paths.is_empty() {
-                provider.add(Box::new(Symbolizer::new(simple_symbol_supplier(
-                    symbols_paths,
-                ))));
+    let mut provider = MultiSymbolProvider::new();
+
+    // Symbol servers not having some symbols is business as usual, but one
+    // being down (or sending garbage) gets its own exit code, so keep track.
+    let fetch_failures = Arc::new(Mutex::new(Vec::new()));
+    if !symbols_urls.is_empty() {
+        let failures = fetch_failures.clone();
+        let supplier = HttpSymbolSupplier::new(
+            symbols_urls,
+            symbols_cache,
+            symbols_tmp,
+            symbols_paths,
+            timeout,
+        )
+        .with_fetch_callback(move |event| {
+            if let FetchEvent::Failed {
+                url,
+                error,
+                missing: false,
+            } = event
+            {
+                failures.lock().unwrap().push(format!("{}: {}", url, error));
             }
+        });
+        provider.add(Box::new(Symbolizer::new(supplier)));
+    } else if !symbols_paths.is_empty() {
+        provider.add(Box::new(Symbolizer::new(simple_symbol_supplier(
+            symbols_paths,
+        ))));
+    }
+
+    let output_dir = matches.value_of_os("output-dir").map(Path::new);
+
+    // More than one minidump (or a directory or glob of them) is processed
+    // as a batch, sharing the symbols, with a report for each in the output
+    // dir.
+    if minidump_args.len() > 1 || minidump_args.iter().any(|path| is_batch_arg(path)) {
+        let output_dir = match output_dir {
+            Some(output_dir) => output_dir,
+            None => error_format.fail(
+                Failure::Other,
+                None,
+                "A batch of minidumps needs an --output-dir to write their reports to",
+            ),
+        };
+        if matches.is_present("raw-json") || cyborg.is_some() {
+            error_format.fail(
+                Failure::Other,
+                None,
+                "--raw-json and --cyborg only work with a single minidump, not a batch",
+            );
+        }
+        let mut paths = Vec::new();
+        for &arg in &minidump_args {
+            match batch_paths(arg) {
+                Ok(arg_paths) => paths.extend(arg_paths),
+                Err(message) => error_format.fail(Failure::Other, Some(arg), &message),
+            }
+        }
+        paths.sort();
+        paths.dedup();
+        std::fs::create_dir_all(output_dir).unwrap();
+
+        let batch = BatchProcessor::new(provider).with_options(options);
+        let mut results = batch.process_paths(paths);
+        let mut failures = BTreeSet::new();
+        while let Some((path, result)) = results.next().await {
+            match result {
+                Ok(state) => {
+                    let mut output = File::create(report.path_in(output_dir, &path)).unwrap();
+                    report.write(&state, &mut output, None).unwrap();
+                    if let Some(message) = partial_processing(&state) {
+                        error_format.report(Failure::Partial, Some(&path), &message);
+                        failures.insert(Failure::Partial);
+                    }
+                }
+                Err(err) => {
+                    let failure = Failure::of(&err);
+                    error_format.report(
+                        failure,
+                        Some(&path),
+                        &format!("{:?} - Error processing dump: {}", err, err),
+                    );
+                    failures.insert(failure);
+                }
+            }
+        }
+        // The symbols are shared, so their failures can't be pinned on any
+        // one minidump.
+        if let Some(message) = symbol_fetch_failures(&fetch_failures) {
+            error_format.report(Failure::SymbolFetch, None, &message);
+            failures.insert(Failure::SymbolFetch);
+        }
+        // If the minidumps all failed the same way, that's the exit code,
+        // otherwise it's too mixed a bag to say.
+        match failures.len() {
+            0 => return,
+            1 => std::process::exit(failures.iter().next().unwrap().exit_code()),
+            _ => std::process::exit(Failure::Other.exit_code()),
+        }
+    }
 
+    let minidump_path = minidump_args[0];
+
+    // Unlike a batch's, the options for a single minidump can borrow the
+    // evil JSON's path.
+    let mut options = options;
+    options.evil_json = matches.value_of_os("raw-json").map(Path::new);
+    let output_file = output_file.or_else(|| Some(report.path_in(output_dir?, minidump_path)));
+
+    match Minidump::read_path(minidump_path) {
+        Ok(dump) => {
             match minidump_processor::process_minidump_with_options(&dump, &provider, options).await
             {
                 Ok(state) => {
                     let mut stdout;
                     let mut output_f;
-                    let cyborg_output_f = cyborg.map(|path| File::create(path).unwrap());
+                    let mut cyborg_output_f = cyborg.map(|path| File::create(path).unwrap());
 
-                    let mut output: &mut dyn Write = if let Some(output_path) = output_file {
+                    let output: &mut dyn Write = if let Some(output_path) = output_file {
                         output_f = File::create(output_path).unwrap();
                         &mut output_f
                     } else {
@@ -404,37 +965,314 @@ async fn main() {
                         &mut stdout
                     };
 
-                    // Print the human output if requested (always uses the "real" output).
-                    if human {
-                        if brief {
-                            state.print_brief(&mut output).unwrap();
-                        } else {
-                            state.print(&mut output).unwrap();
-                        }
+                    report
+                        .write(
+                            &state,
+                            output,
+                            cyborg_output_f.as_mut().map(|f| f as &mut dyn Write),
+                        )
+                        .unwrap();
+
+                    // The report's out, but it may not be the whole story.
+                    if let Some(message) = symbol_fetch_failures(&fetch_failures) {
+                        error_format.fail(Failure::SymbolFetch, Some(minidump_path), &message);
                     }
-
-                    // Print the json output if requested (using "cyborg" output if available).
-                    if json {
-                        if let Some(mut cyborg_output_f) = cyborg_output_f {
-                            state.print_json(&mut cyborg_output_f, pretty).unwrap();
-                        } else {
-                            state.print_json(&mut output, pretty).unwrap();
-                        }
+                    if let Some(message) = partial_processing(&state) {
+                        error_format.fail(Failure::Partial, Some(minidump_path), &message);
                     }
                 }
                 Err(err) => {
-                    error!("{:?} - Error processing dump: {}", err, err);
-                    std::process::exit(1);
+                    error_format.fail(
+                        Failure::of(&err),
+                        Some(minidump_path),
+                        &format!("{:?} - Error processing dump: {}", err, err),
+                    );
                 }
             }
         }
         Err(err) => {
-            error!("{:?} - Error reading dump: {}", err, err);
-            std::process::exit(1);
+            error_format.fail(
+                Failure::UnreadableDump,
+                Some(minidump_path),
+                &format!("{:?} - Error reading dump: {}", err, err),
+            );
         }
     }
 }
 
+/// Why minidump-stackwalk didn't just succeed, each with its own exit code
+/// so scripts can tell a bad minidump from a symbol server that's down.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Failure {
+    /// Bad arguments, or anything else that isn't one of the below.
+    Other,
+    /// The minidump couldn't be read at all.
+    UnreadableDump,
+    /// The minidump is missing a stream it can't be processed without.
+    MissingStreams,
+    /// A symbol server failed some other way than not having the symbols.
+    SymbolFetch,
+    /// Processing finished, but gave up on part of the minidump.
+    Partial,
+}
+
+impl Failure {
+    /// What a failure to process the minidump comes down to.
+    fn of(err: &ProcessError) -> Failure {
+        match err {
+            ProcessError::MinidumpReadError(_) => Failure::UnreadableDump,
+            ProcessError::MissingSystemInfo | ProcessError::MissingThreadList => {
+                Failure::MissingStreams
+            }
+            ProcessError::UnknownError => Failure::Other,
+        }
+    }
+
+    fn exit_code(self) -> i32 {
+        match self {
+            Failure::Other => 1,
+            Failure::UnreadableDump => 2,
+            Failure::MissingStreams => 3,
+            Failure::SymbolFetch => 4,
+            Failure::Partial => 5,
+        }
+    }
+
+    /// The name of the failure in --error-format=json output.
+    fn name(self) -> &'static str {
+        match self {
+            Failure::Other => "other",
+            Failure::UnreadableDump => "unreadable_dump",
+            Failure::MissingStreams => "missing_streams",
+            Failure::SymbolFetch => "symbol_fetch_failed",
+            Failure::Partial => "partial",
+        }
+    }
+}
+
+/// How to report failures, from --error-format.
+#[derive(Debug, Clone, Copy)]
+enum ErrorFormat {
+    /// Log them, like everything else.
+    Text,
+    /// Write each one to stderr as a line of JSON.
+    Json,
+}
+
+impl ErrorFormat {
+    /// Report `failure`, with `message` saying what went wrong, and the
+    /// minidump it went wrong for if it's down to one.
+    fn report(self, failure: Failure, minidump: Option<&Path>, message: &str) {
+        match self {
+            ErrorFormat::Text => match minidump {
+                Some(path) => error!("{}: {}", path.display(), message),
+                None => error!("{}", message),
+            },
+            ErrorFormat::Json => {
+                let mut error = json!({
+                    "error": failure.name(),
+                    "exit_code": failure.exit_code(),
+                    "message": message,
+                });
+                if let Some(path) = minidump {
+                    error["minidump"] = json!(path.to_string_lossy());
+                }
+                eprintln!("{}", error);
+            }
+        }
+    }
+
+    /// Report `failure` and exit with its exit code.
+    fn fail(self, failure: Failure, minidump: Option<&Path>, message: &str) -> ! {
+        self.report(failure, minidump, message);
+        std::process::exit(failure.exit_code());
+    }
+}
+
+/// What the symbol servers did wrong, if anything.
+fn symbol_fetch_failures(failures: &Mutex<Vec<String>>) -> Option<String> {
+    let failures = failures.lock().unwrap();
+    if failures.is_empty() {
+        return None;
+    }
+    Some(format!(
+        "Failed to fetch symbols, so the report may be missing some - {}",
+        failures.join(", ")
+    ))
+}
+
+/// What part of the minidump processing gave up on, if any.
+fn partial_processing(state: &ProcessState) -> Option<String> {
+    if state.timed_out {
+        Some(String::from("Processing timed out before it was done"))
+    } else if !state.degradations.is_empty() {
+        Some(format!(
+            "Processing made do without parts of the minidump - {:?}",
+            state.degradations
+        ))
+    } else {
+        None
+    }
+}
+
+/// The kinds of output to write for each minidump.
+struct Report {
+    human: bool,
+    json: bool,
+    socorro: bool,
+    brief: bool,
+    signature: bool,
+    crash_details: bool,
+    pretty: bool,
+    extended_json: bool,
+}
+
+impl Report {
+    /// Write the report for `state` to `output`, with the JSON going to
+    /// `cyborg_output` instead if there is one.
+    fn write(
+        &self,
+        state: &ProcessState,
+        mut output: &mut dyn Write,
+        cyborg_output: Option<&mut dyn Write>,
+    ) -> io::Result<()> {
+        // Print the human output if requested (always uses the "real" output).
+        if self.human {
+            let mut options = if self.brief {
+                HumanOptions::brief()
+            } else {
+                HumanOptions::default()
+            };
+            options.signature = self.signature;
+            options.crash_details = self.crash_details;
+            state.print_human(&mut output, &options)?;
+        }
+
+        // Print the json output if requested (using "cyborg" output if available).
+        if self.json {
+            match cyborg_output {
+                Some(cyborg_output) => self.write_json(state, cyborg_output)?,
+                None => self.write_json(state, output)?,
+            }
+        }
+        Ok(())
+    }
+
+    /// Write the JSON report for `state` to `output`.
+    fn write_json(
+        &self,
+        state: &ProcessState,
+        mut output: &mut dyn Write,
+    ) -> Result<(), serde_json::Error> {
+        if self.socorro {
+            state.print_socorro_json(&mut output, self.pretty)
+        } else {
+            let mut options = JsonOptions::default();
+            options.extended = self.extended_json;
+            state.print_json_with_options(&mut output, self.pretty, &options)
+        }
+    }
+
+    /// Where in `output_dir` the report for the minidump at `minidump_path`
+    /// goes.
+    fn path_in(&self, output_dir: &Path, minidump_path: &Path) -> PathBuf {
+        let mut name = minidump_path
+            .file_stem()
+            .unwrap_or_else(|| minidump_path.as_os_str())
+            .to_os_string();
+        name.push(if self.human { ".txt" } else { ".json" });
+        output_dir.join(name)
+    }
+}
+
+/// Whether the minidump argument `path` names more than one minidump: a
+/// directory, or a glob that isn't the name of an actual file.
+fn is_batch_arg(path: &Path) -> bool {
+    path.is_dir() || (!path.exists() && matches!(path.to_str(), Some(path) if is_glob(path)))
+}
+
+fn is_glob(path: &str) -> bool {
+    path.contains(&['*', '?', '['][..])
+}
+
+/// The minidumps the minidump argument `path` names: the files in it if it's
+/// a directory, the files matching it if it's a glob, or else just itself.
+fn batch_paths(path: &Path) -> Result<Vec<PathBuf>, String> {
+    if path.is_dir() {
+        let entries = std::fs::read_dir(path)
+            .map_err(|err| format!("{:?} - Error reading directory: {}", err, err))?;
+        Ok(entries
+            .filter_map(|entry| Some(entry.ok()?.path()))
+            .filter(|path| path.is_file())
+            .collect())
+    } else if is_batch_arg(path) {
+        let pattern = path.to_str().unwrap();
+        let matches =
+            glob::glob(pattern).map_err(|err| format!("{:?} - Bad glob: {}", err, err))?;
+        Ok(matches
+            .filter_map(Result::ok)
+            .filter(|path| path.is_file())
+            .collect())
+    } else {
+        Ok(vec![path.to_owned()])
+    }
+}
+
+/// A thread id given to --thread, in decimal or hex, or `None` if it's a
+/// thread name.
+fn parse_thread_id(thread: &str) -> Option<u32> {
+    match thread.strip_prefix("0x") {
+        Some(hex) => u32::from_str_radix(hex, 16).ok(),
+        None => thread.parse().ok(),
+    }
+}
+
+/// The symbols for a subcommand's `symbols-path`s and `--symbols-url`s,
+/// cached in the temp dir like the main command's by default.
+fn subcommand_symbols(matches: &ArgMatches<'_>) -> MultiSymbolProvider {
+    let symbols_paths = matches
+        .values_of_os("symbols-path")
+        .map(|v| v.map(|os_str| Path::new(os_str).to_owned()).collect())
+        .unwrap_or_else(Vec::new);
+    let symbols_urls = matches
+        .values_of("symbols-url")
+        .map(|v| v.map(String::from).collect::<Vec<_>>())
+        .unwrap_or_else(Vec::new);
+
+    let mut provider = MultiSymbolProvider::new();
+    if !symbols_urls.is_empty() {
+        let temp_dir = env::temp_dir();
+        provider.add(Box::new(Symbolizer::new(http_symbol_supplier(
+            symbols_paths,
+            symbols_urls,
+            temp_dir.join("rust-minidump-cache"),
+            temp_dir,
+            Duration::from_secs(1000),
+        ))));
+    } else if !symbols_paths.is_empty() {
+        provider.add(Box::new(Symbolizer::new(simple_symbol_supplier(
+            symbols_paths,
+        ))));
+    }
+    provider
+}
+
+/// An address or length, in decimal or hex (0x...).
+fn parse_number(number: &str) -> Option<u64> {
+    match number.strip_prefix("0x") {
+        Some(hex) => u64::from_str_radix(hex, 16).ok(),
+        None => number.parse().ok(),
+    }
+}
+
+/// The file name at the end of a module's path.
+fn basename(path: &str) -> &str {
+    match path.rfind(|c| c == '/' || c == '\\') {
+        None => path,
+        Some(index) => &path[(index + 1)..],
+    }
+}
+
 fn print_help_markdown() {
     let mut help_buf = Vec::new();
 
@@ -452,10 +1290,12 @@ fn print_help_markdown() {
     println!("Version: `{}`", lines.next().unwrap());
     println!();
 
+    let mut in_subcommands = false;
     for line in lines {
         // Use a trailing colon to indicate a heading
         if let Some(heading) = line.strip_suffix(':') {
             if !line.starts_with(' ') {
+                in_subcommands = heading == "SUBCOMMANDS";
                 // SCREAMING headers are Main headings
                 if heading.to_ascii_uppercase() == heading {
                     println!("# {}", heading);
@@ -474,6 +1314,12 @@ fn print_help_markdown() {
             continue;
         }
 
+        // subcommand names are subheadings (their help is indented further)
+        if in_subcommands && line.starts_with("    ") && !line.starts_with("     ") {
+            println!("### `{}`", line.trim());
+            continue;
+        }
+
         // The rest is indented, get rid of that
         let line = line.trim();
 
@@ -0,0 +1,199 @@
+// Copyright 2015 Ted Mielczarek. See the COPYRIGHT
+// file at the top-level directory of this distribution.
+
+//! The `memory` subcommand: an annotated hexdump of the minidump's memory.
+//!
+//! Every aligned pointer-sized value in the dumped bytes that points into a
+//! module or a thread's stack gets a note saying so, which makes it easier to
+//! pick return addresses and saved frame pointers out of a stack by hand.
+
+use std::path::Path;
+
+use clap::{App, Arg, ArgGroup, ArgMatches, SubCommand};
+use minidump::*;
+
+use crate::{basename, parse_number, parse_thread_id, ErrorFormat, Failure};
+
+/// How many bytes each row of the hexdump shows.
+const ROW: u64 = 16;
+
+/// How many bytes to dump from --address if --length isn't given.
+const DEFAULT_LENGTH: u64 = 256;
+
+pub fn subcommand() -> App<'static, 'static> {
+    SubCommand::with_name("memory")
+        .about("Print a hexdump of a minidump's memory, noting the pointers into modules and stacks.")
+        .arg(
+            Arg::with_name("address")
+                .long("address")
+                .takes_value(true)
+                .validator(|v| parse_number(&v).map(|_| ()).ok_or_else(|| String::from("expected a number")))
+                .help("Where to start the hexdump, in decimal or hex (0x...).")
+        )
+        .arg(
+            Arg::with_name("thread-stack")
+                .long("thread-stack")
+                .takes_value(true)
+                .validator(|v| parse_thread_id(&v).map(|_| ()).ok_or_else(|| String::from("expected a thread id")))
+                .help("Dump this thread's stack from its stack pointer instead, given by id in decimal or hex (0x...).")
+        )
+        .arg(
+            Arg::with_name("length")
+                .long("length")
+                .takes_value(true)
+                .validator(|v| parse_number(&v).map(|_| ()).ok_or_else(|| String::from("expected a number")))
+                .help("How many bytes to dump (defaults to 256, or the rest of the stack with --thread-stack).")
+        )
+        .group(
+            ArgGroup::with_name("start")
+                .args(&["address", "thread-stack"])
+                .required(true)
+        )
+        .arg(
+            Arg::with_name("minidump")
+                .required(true)
+                .takes_value(true)
+                .help("Path to the minidump file to dump memory from.")
+        )
+}
+
+/// Print the hexdump of the memory `matches` asks for.
+pub fn memory(matches: &ArgMatches<'_>) {
+    let minidump_path = Path::new(matches.value_of_os("minidump").unwrap());
+
+    let dump = match Minidump::read_path(minidump_path) {
+        Ok(dump) => dump,
+        Err(err) => ErrorFormat::Text.fail(
+            Failure::UnreadableDump,
+            Some(minidump_path),
+            &format!("{:?} - Error reading dump: {}", err, err),
+        ),
+    };
+    let memory = UnifiedMemoryList::new(
+        dump.get_stream::<MinidumpMemoryList>().ok(),
+        dump.get_stream::<MinidumpMemory64List>().ok(),
+    )
+    .unwrap_or_default();
+    let modules = dump.get_stream::<MinidumpModuleList>().unwrap_or_default();
+    let threads = dump.get_stream::<MinidumpThreadList>().ok();
+    let system_info = dump.get_stream::<MinidumpSystemInfo>().ok();
+    let pointer_width = system_info
+        .as_ref()
+        .and_then(|info| info.cpu.pointer_width())
+        .unwrap_or(8);
+
+    let length = matches.value_of("length").and_then(parse_number);
+    let (start, end) = if let Some(address) = matches.value_of("address") {
+        let start = parse_number(address).unwrap();
+        (
+            start,
+            start.saturating_add(length.unwrap_or(DEFAULT_LENGTH)),
+        )
+    } else {
+        let id = parse_thread_id(matches.value_of("thread-stack").unwrap()).unwrap();
+        let thread = match threads.as_ref().and_then(|threads| threads.get_thread(id)) {
+            Some(thread) => thread,
+            None => ErrorFormat::Text.fail(
+                Failure::Other,
+                Some(minidump_path),
+                &format!("No thread {:#x} in the minidump", id),
+            ),
+        };
+        let stack_start = thread.raw.stack.start_of_memory_range;
+        let stack_end = stack_start + thread.raw.stack.memory.data_size as u64;
+        let misc = dump.get_stream::<MinidumpMiscInfo>().ok();
+        let stack_pointer = system_info
+            .as_ref()
+            .and_then(|info| thread.context(info, misc.as_ref()))
+            .map(|context| context.get_stack_pointer())
+            .filter(|sp| (stack_start..stack_end).contains(sp))
+            .unwrap_or(stack_start);
+        let end = match length {
+            Some(length) => stack_pointer.saturating_add(length).min(stack_end),
+            None => stack_end,
+        };
+        (stack_pointer, end)
+    };
+
+    if !(start..end).any(|address| memory.memory_at_address(address).is_some()) {
+        ErrorFormat::Text.fail(
+            Failure::Other,
+            Some(minidump_path),
+            &format!("No memory from {:#x} to {:#x} in the minidump", start, end),
+        );
+    }
+
+    let owner = |pointer: u64| {
+        if let Some(module) = modules.module_at_address(pointer) {
+            return Some(format!(
+                "{} + {:#x}",
+                basename(&module.code_file()),
+                pointer - module.base_address()
+            ));
+        }
+        let thread = threads.as_ref()?.threads.iter().find(|thread| {
+            let stack = &thread.raw.stack;
+            let stack_start = stack.start_of_memory_range;
+            (stack_start..stack_start + stack.memory.data_size as u64).contains(&pointer)
+        })?;
+        Some(format!("stack of thread {:#x}", thread.raw.thread_id))
+    };
+
+    let mut row = start;
+    while row < end {
+        let row_end = row.saturating_add(ROW).min(end);
+        println!("{}", hexdump_row(&memory, row, row_end));
+        for address in (row..row_end).filter(|address| address % pointer_width == 0) {
+            if address + pointer_width > end {
+                break;
+            }
+            let pointer = match read_pointer(&memory, address, pointer_width) {
+                Some(pointer) if pointer != 0 => pointer,
+                _ => continue,
+            };
+            if let Some(owner) = owner(pointer) {
+                println!("    {:#x}: {:#x} -> {}", address, pointer, owner);
+            }
+        }
+        row = row_end;
+    }
+}
+
+/// The pointer at `address`, if the minidump has all of it.
+fn read_pointer(memory: &UnifiedMemoryList, address: u64, pointer_width: u64) -> Option<u64> {
+    let region = memory.memory_at_address(address)?;
+    match pointer_width {
+        4 => region.get_memory_at_address::<u32>(address).map(u64::from),
+        _ => region.get_memory_at_address::<u64>(address),
+    }
+}
+
+/// The bytes from `start` to `end` (at most one row), with the bytes the
+/// minidump doesn't have shown as `??`.
+fn hexdump_row(memory: &UnifiedMemoryList, start: u64, end: u64) -> String {
+    let mut hex = String::new();
+    let mut ascii = String::new();
+    for address in start..end {
+        let byte = memory.memory_at_address(address).and_then(|region| {
+            region
+                .bytes()
+                .get((address - region.base_address()) as usize)
+                .copied()
+        });
+        match byte {
+            Some(byte) => {
+                hex.push_str(&format!("{:02x} ", byte));
+                ascii.push(if byte.is_ascii_graphic() {
+                    byte as char
+                } else {
+                    '.'
+                });
+            }
+            None => {
+                hex.push_str("?? ");
+                ascii.push(' ');
+            }
+        }
+    }
+    format!("{:016x}  {:<48} {}", start, hex, ascii)
+}
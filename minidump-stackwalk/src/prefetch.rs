@@ -0,0 +1,175 @@
+// Copyright 2015 Ted Mielczarek. See the COPYRIGHT
+// file at the top-level directory of this distribution.
+
+//! The `prefetch-symbols` subcommand: downloading the symbols some minidumps
+//! will need into the cache, without processing them.
+//!
+//! This is for warming a cache that the machines processing the minidumps
+//! share, so they don't all wait on the same downloads.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::env;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use clap::{App, Arg, ArgMatches, SubCommand};
+use minidump::*;
+use minidump_processor::{
+    FetchEvent, HttpSymbolSupplier, Symbolizer, DEFAULT_PREFETCH_CONCURRENCY,
+};
+
+use crate::{ErrorFormat, Failure};
+
+pub fn subcommand() -> App<'static, 'static> {
+    SubCommand::with_name("prefetch-symbols")
+        .about("Download the symbols some minidumps need into the symbols-cache, without processing them.")
+        .arg(
+            Arg::with_name("symbols-url")
+                .long("symbols-url")
+                .required(true)
+                .multiple(true)
+                .takes_value(true)
+                .number_of_values(1)
+                .help("base URL from which URLs to symbol files can be constructed (see the main --help)")
+        )
+        .arg(
+            Arg::with_name("symbols-cache")
+                .long("symbols-cache")
+                .takes_value(true)
+                .help("The directory to download the symbols into (see the main --help)")
+        )
+        .arg(
+            Arg::with_name("symbols-tmp")
+                .long("symbols-tmp")
+                .takes_value(true)
+                .help("A directory to use as temp space for downloading symbols (see the main --help)")
+        )
+        .arg(
+            Arg::with_name("symbol-download-timeout-secs")
+                .long("symbol-download-timeout-secs")
+                .default_value("1000")
+                .takes_value(true)
+                .validator(|v| v.parse::<u64>().map(|_| ()).map_err(|e| e.to_string()))
+                .help("The maximum amount of time (in seconds) a symbol file download is allowed to take.")
+        )
+        .arg(
+            Arg::with_name("concurrency")
+                .long("concurrency")
+                .takes_value(true)
+                .validator(|v| v.parse::<usize>().map(|_| ()).map_err(|e| e.to_string()))
+                .help("How many symbol files to download at once (defaults to 16).")
+        )
+        .arg(
+            Arg::with_name("minidump")
+                .required(true)
+                .multiple(true)
+                .takes_value(true)
+                .help("Paths to the minidump files to get the symbols of.")
+        )
+}
+
+/// Download the symbols of every module in the minidumps `matches` names,
+/// and list how it went for each.
+pub async fn prefetch_symbols(matches: &ArgMatches<'_>) {
+    let temp_dir = env::temp_dir();
+    let symbols_cache = matches
+        .value_of_os("symbols-cache")
+        .map(|os_str| Path::new(os_str).to_owned())
+        .unwrap_or_else(|| temp_dir.join("rust-minidump-cache"));
+    let symbols_tmp = matches
+        .value_of_os("symbols-tmp")
+        .map(|os_str| Path::new(os_str).to_owned())
+        .unwrap_or(temp_dir);
+    let symbols_urls = matches
+        .values_of("symbols-url")
+        .unwrap()
+        .map(String::from)
+        .collect::<Vec<_>>();
+    let timeout = matches
+        .value_of("symbol-download-timeout-secs")
+        .and_then(|x| x.parse().ok())
+        .map(Duration::from_secs)
+        .unwrap();
+    let concurrency = matches
+        .value_of("concurrency")
+        .and_then(|x| x.parse().ok())
+        .unwrap_or(DEFAULT_PREFETCH_CONCURRENCY);
+
+    let mut failures = BTreeSet::new();
+    let mut module_lists = Vec::new();
+    for path in matches.values_of_os("minidump").unwrap().map(Path::new) {
+        match Minidump::read_path(path) {
+            Ok(dump) => module_lists.extend(dump.get_stream::<MinidumpModuleList>().ok()),
+            Err(err) => {
+                ErrorFormat::Text.report(
+                    Failure::UnreadableDump,
+                    Some(path),
+                    &format!("{:?} - Error reading dump: {}", err, err),
+                );
+                failures.insert(Failure::UnreadableDump);
+            }
+        }
+    }
+
+    let fetch_failures = Arc::new(Mutex::new(Vec::new()));
+    let supplier_failures = fetch_failures.clone();
+    let supplier = HttpSymbolSupplier::new(
+        symbols_urls,
+        symbols_cache,
+        symbols_tmp,
+        Vec::new(),
+        timeout,
+    )
+    .with_fetch_callback(move |event| {
+        if let FetchEvent::Failed {
+            url,
+            error,
+            missing: false,
+        } = event
+        {
+            supplier_failures
+                .lock()
+                .unwrap()
+                .push(format!("{}: {}", url, error));
+        }
+    });
+    // Nothing's going to use the symbols, so don't hold on to them.
+    let symbolizer = Symbolizer::new(supplier).with_memory_budget(0);
+    let modules = module_lists
+        .iter()
+        .flat_map(|modules| modules.iter())
+        .map(|module| module as &(dyn Module + Sync));
+    symbolizer.prefetch(modules, concurrency).await;
+
+    let stats = symbolizer.stats().into_iter().collect::<BTreeMap<_, _>>();
+    for (name, stats) in stats {
+        let status = if stats.corrupt_symbols {
+            "corrupt symbols"
+        } else if stats.cache_hit {
+            "cached"
+        } else if stats.loaded_symbols {
+            "downloaded"
+        } else {
+            "missing symbols"
+        };
+        println!("{}  {}", name, status);
+    }
+
+    let fetch_failures = fetch_failures.lock().unwrap();
+    if !fetch_failures.is_empty() {
+        ErrorFormat::Text.report(
+            Failure::SymbolFetch,
+            None,
+            &format!("Failed to fetch symbols - {}", fetch_failures.join(", ")),
+        );
+        failures.insert(Failure::SymbolFetch);
+    }
+    // Like a batch, if everything failed the same way that's the exit code,
+    // otherwise it's too mixed a bag to say.
+    match failures.len() {
+        0 => {}
+        1 => std::process::exit(failures.iter().next().unwrap().exit_code()),
+        _ => std::process::exit(Failure::Other.exit_code()),
+    }
+}
@@ -76,6 +76,58 @@ fn test_json_pretty() {
     assert_eq!(stderr, "");
 }
 
+#[test]
+fn test_json_extended() {
+    let bin = env!("CARGO_BIN_EXE_minidump-stackwalk");
+    let output = Command::new(bin)
+        .arg("--json")
+        .arg("--extended-json")
+        .arg("../testdata/test.dmp")
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .output()
+        .unwrap();
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+
+    assert!(output.status.success());
+    let json: serde_json::Value = serde_json::from_str(&stdout).unwrap();
+    assert_eq!(json["signature"], "test_app.exe@0x429e");
+    assert_eq!(json["crash_info"]["address_class"]["kind"], "near_null");
+    assert_eq!(json["threads"][0]["registers"]["eip"], "0x0040429e");
+    assert_eq!(stderr, "");
+}
+
+#[test]
+fn test_socorro() {
+    let bin = env!("CARGO_BIN_EXE_minidump-stackwalk");
+    let output = Command::new(bin)
+        .arg("--socorro")
+        .arg("../testdata/test.dmp")
+        .arg("--")
+        .arg("../testdata/symbols/")
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .output()
+        .unwrap();
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+
+    assert!(output.status.success());
+    assert_eq!(stderr, "");
+
+    let processed: serde_json::Value = serde_json::from_str(&stdout).unwrap();
+    assert_eq!(processed["success"], true);
+    assert_eq!(
+        processed["signature"],
+        "`anonymous namespace'::CrashFunction"
+    );
+    assert_eq!(processed["json_dump"]["status"], "OK");
+    assert_eq!(processed["reason"], "EXCEPTION_ACCESS_VIOLATION_WRITE");
+}
+
 #[test]
 fn test_json_symbols() {
     // For a while this didn't parse right
@@ -84,6 +136,7 @@ fn test_json_symbols() {
         .arg("--json")
         .arg("--pretty")
         .arg("../testdata/test.dmp")
+        .arg("--")
         .arg("../testdata/symbols/")
         .stdout(Stdio::piped())
         .stderr(Stdio::piped())
@@ -108,6 +161,7 @@ fn test_evil_json() {
         .arg("--raw-json")
         .arg("../testdata/evil.json")
         .arg("../testdata/test.dmp")
+        .arg("--")
         .arg("../testdata/symbols/")
         .stdout(Stdio::piped())
         .stderr(Stdio::piped())
@@ -148,6 +202,7 @@ fn test_human_symbols() {
     let output = Command::new(bin)
         .arg("--human")
         .arg("../testdata/test.dmp")
+        .arg("--")
         .arg("../testdata/symbols/")
         .stdout(Stdio::piped())
         .stderr(Stdio::piped())
@@ -170,6 +225,7 @@ fn test_human_symbols_garbo_url() {
         .arg("--human")
         .arg(r#"--symbols-url="garbage.realwebsite""#)
         .arg("../testdata/test.dmp")
+        .arg("--")
         .arg("../testdata/symbols/")
         .stdout(Stdio::piped())
         .stderr(Stdio::piped())
@@ -204,6 +260,31 @@ fn test_human_brief() {
     assert_eq!(stderr, "");
 }
 
+#[test]
+fn test_human_signature() {
+    let bin = env!("CARGO_BIN_EXE_minidump-stackwalk");
+    let output = Command::new(bin)
+        .arg("--human")
+        .arg("--brief")
+        .arg("--signature")
+        .arg("--crash-details")
+        .arg("../testdata/test.dmp")
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .output()
+        .unwrap();
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+
+    assert!(output.status.success());
+    assert!(stdout.starts_with("Operating system:"));
+    assert!(stdout.contains("\nSignature: test_app.exe@0x429e\n"));
+    assert!(stdout.contains("\nCrash address kind: near-null pointer (from eax)\n"));
+    assert!(stdout.contains("\nRegisters point to:\n  eip = 0x40429e -> test_app.exe + 0x429e\n"));
+    assert_eq!(stderr, "");
+}
+
 #[test]
 fn test_default() {
     // Should be the same as --human
@@ -318,6 +399,122 @@ fn test_output_files() {
     assert_eq!(stderr, "");
 }
 
+#[test]
+fn test_output_dir() {
+    let dump_dir = test_output("mdsw-test-output-dir-dumps");
+    let out_dir = test_output("mdsw-test-output-dir-out");
+    let _ = std::fs::create_dir(&dump_dir);
+    std::fs::copy("../testdata/test.dmp", dump_dir.join("test.dmp")).unwrap();
+    std::fs::write(dump_dir.join("garbage.dmp"), b"not a minidump").unwrap();
+
+    let bin = env!("CARGO_BIN_EXE_minidump-stackwalk");
+    let output = Command::new(bin)
+        .arg("--json")
+        .arg("--output-dir")
+        .arg(&out_dir)
+        .arg(&dump_dir)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .output()
+        .unwrap();
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+
+    // The bad minidump fails, but doesn't stop the good one being processed.
+    assert!(!output.status.success());
+    assert_eq!(stdout, "");
+    assert!(stderr.contains("garbage.dmp"));
+    assert!(!out_dir.join("garbage.json").exists());
+    let json = std::fs::read_to_string(out_dir.join("test.json")).unwrap();
+    insta::assert_snapshot!("json", json);
+}
+
+#[test]
+fn test_output_dir_paths_and_globs() {
+    let dump_dir = test_output("mdsw-test-output-dir-glob-dumps");
+    let out_dir = test_output("mdsw-test-output-dir-glob-out");
+    let _ = std::fs::create_dir(&dump_dir);
+    std::fs::copy("../testdata/test.dmp", dump_dir.join("a.dmp")).unwrap();
+    std::fs::copy("../testdata/test.dmp", dump_dir.join("b.dmp")).unwrap();
+    std::fs::write(dump_dir.join("notes.txt"), b"not a minidump").unwrap();
+
+    let bin = env!("CARGO_BIN_EXE_minidump-stackwalk");
+    let output = Command::new(bin)
+        .arg("--json")
+        .arg("--output-dir")
+        .arg(&out_dir)
+        .arg(dump_dir.join("*.dmp"))
+        .arg("../testdata/test.dmp")
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .output()
+        .unwrap();
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+
+    assert!(output.status.success());
+    assert_eq!(stdout, "");
+    assert_eq!(stderr, "");
+    for name in ["a.json", "b.json", "test.json"] {
+        assert!(out_dir.join(name).exists());
+    }
+    assert!(!out_dir.join("notes.json").exists());
+}
+
+#[test]
+fn test_dir_without_output_dir() {
+    let bin = env!("CARGO_BIN_EXE_minidump-stackwalk");
+    let output = Command::new(bin)
+        .arg("--json")
+        .arg("../testdata")
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .output()
+        .unwrap();
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+
+    assert!(!output.status.success());
+    assert_eq!(stdout, "");
+    assert!(!stderr.is_empty());
+}
+
+#[test]
+fn test_verbose_unwind() {
+    let unwind_path = test_output("mdsw-test-verbose-unwind.txt");
+    let bin = env!("CARGO_BIN_EXE_minidump-stackwalk");
+    let output = Command::new(bin)
+        .arg("--human")
+        .arg("--verbose-unwind")
+        .arg(&unwind_path)
+        .arg("../testdata/test.dmp")
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .output()
+        .unwrap();
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+
+    let unwind_file = File::open(unwind_path).unwrap();
+    let mut unwind_bytes = vec![];
+    BufReader::new(unwind_file)
+        .read_to_end(&mut unwind_bytes)
+        .unwrap();
+    let unwind = String::from_utf8(unwind_bytes).unwrap();
+
+    assert!(output.status.success());
+    // The normal output is untouched, and only the unwinder's traces are in
+    // the file.
+    insta::assert_snapshot!("human", stdout);
+    assert_eq!(stderr, "");
+    assert!(unwind.contains("unwind: starting stack unwind"));
+    assert!(!unwind.contains("Minidump contains multiple streams"));
+}
+
 #[test]
 fn test_version() {
     let bin = env!("CARGO_BIN_EXE_minidump-stackwalk");
@@ -344,6 +541,29 @@ fn test_version() {
 }
 
 #[test]
+fn test_print_schema() {
+    let bin = env!("CARGO_BIN_EXE_minidump-stackwalk");
+    let output = Command::new(bin)
+        .arg("--print-schema")
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .output()
+        .unwrap();
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+
+    assert!(output.status.success());
+    assert_eq!(stderr, "");
+
+    let schema: serde_json::Value = serde_json::from_str(&stdout).unwrap();
+    assert_eq!(schema["version"], env!("CARGO_PKG_VERSION"));
+    assert!(schema["properties"]["threads"].is_object());
+}
+
+// The snapshots are of the default build, without the `explore` subcommand.
+#[test]
+#[cfg(not(feature = "explore"))]
 fn test_long_help() {
     let bin = env!("CARGO_BIN_EXE_minidump-stackwalk");
     let output = Command::new(bin)
@@ -361,7 +581,9 @@ fn test_long_help() {
     assert_eq!(stderr, "");
 }
 
+// The snapshots are of the default build, without the `explore` subcommand.
 #[test]
+#[cfg(not(feature = "explore"))]
 fn test_short_help() {
     let bin = env!("CARGO_BIN_EXE_minidump-stackwalk");
     let output = Command::new(bin)
@@ -379,7 +601,9 @@ fn test_short_help() {
     assert_eq!(stderr, "");
 }
 
+// The snapshots are of the default build, without the `explore` subcommand.
 #[test]
+#[cfg(not(feature = "explore"))]
 fn test_markdown_help() {
     let bin = env!("CARGO_BIN_EXE_minidump-stackwalk");
     let output = Command::new(bin)
@@ -515,6 +739,389 @@ fn test_brief_robots() {
     assert!(!stderr.is_empty());
 }
 
+#[test]
+fn test_bad_skip_threads() {
+    let bin = env!("CARGO_BIN_EXE_minidump-stackwalk");
+    let output = Command::new(bin)
+        .arg("--skip-threads")
+        .arg("(unclosed")
+        .arg("../testdata/test.dmp")
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .output()
+        .unwrap();
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+
+    assert!(!output.status.success());
+    assert_eq!(stdout, "");
+    assert!(!stderr.is_empty());
+}
+
+#[test]
+fn test_thread_filter_crashing_only_conflict() {
+    let bin = env!("CARGO_BIN_EXE_minidump-stackwalk");
+    let output = Command::new(bin)
+        .arg("--thread")
+        .arg("0x1234")
+        .arg("--crashing-thread-only")
+        .arg("../testdata/test.dmp")
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .output()
+        .unwrap();
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+
+    assert!(!output.status.success());
+    assert_eq!(stdout, "");
+    assert!(!stderr.is_empty());
+}
+
+#[test]
+fn test_unreadable_dump_exit_code() {
+    let bin = env!("CARGO_BIN_EXE_minidump-stackwalk");
+    let output = Command::new(bin)
+        .arg("--error-format=json")
+        .arg("Cargo.toml")
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .output()
+        .unwrap();
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+
+    assert_eq!(output.status.code(), Some(2));
+    assert_eq!(stdout, "");
+    let error: serde_json::Value = serde_json::from_str(stderr.trim()).unwrap();
+    assert_eq!(error["error"], "unreadable_dump");
+    assert_eq!(error["exit_code"], 2);
+    assert_eq!(error["minidump"], "Cargo.toml");
+    assert!(error["message"].is_string());
+}
+
+#[test]
+fn test_missing_streams_exit_code() {
+    let synth_path = test_output("mdsw-test-missing-streams.dmp");
+    let context = synth_minidump::x86_context(Endian::Little, 0xf00800, 0x1010);
+    let stack = Memory::with_section(
+        Section::with_endian(Endian::Little).append_repeated(0, 0x1000),
+        0x1000,
+    );
+    let thread = Thread::new(Endian::Little, 0x1234, &stack, &context);
+    // No system info, so there's no telling what the threads' contexts are.
+    let minidump = SynthMinidump::with_endian(Endian::Little)
+        .add_thread(thread)
+        .add(context)
+        .add_memory(stack)
+        .finish()
+        .unwrap();
+    File::create(&synth_path)
+        .unwrap()
+        .write_all(&minidump)
+        .unwrap();
+
+    let bin = env!("CARGO_BIN_EXE_minidump-stackwalk");
+    let output = Command::new(bin)
+        .arg(&synth_path)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .output()
+        .unwrap();
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+
+    assert_eq!(output.status.code(), Some(3));
+    assert_eq!(stdout, "");
+    assert!(stderr.contains("MissingSystemInfo"));
+}
+
+#[test]
+#[cfg(feature = "explore")]
+fn test_explore_unreadable_dump() {
+    // The minidump is read before the explorer takes over the terminal, so
+    // this fails like the main command does.
+    let bin = env!("CARGO_BIN_EXE_minidump-stackwalk");
+    let output = Command::new(bin)
+        .arg("explore")
+        .arg("Cargo.toml")
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .output()
+        .unwrap();
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+
+    assert_eq!(output.status.code(), Some(2));
+    assert_eq!(stdout, "");
+    assert!(stderr.contains("Error reading dump"));
+}
+
+#[test]
+fn test_memory_thread_stack() {
+    let synth_path = test_output("mdsw-test-memory.dmp");
+    let context = synth_minidump::x86_context(Endian::Little, 0xf00800, 0x1010);
+    // A return address into test.dll, a saved frame pointer, and some text.
+    let stack = Memory::with_section(
+        Section::with_endian(Endian::Little)
+            .append_repeated(0, 0x10)
+            .D32(0xf00800)
+            .D32(0x1040)
+            .append_bytes(b"ABCD")
+            .append_repeated(0, 0x1000 - 0x1c),
+        0x1000,
+    );
+    let thread = Thread::new(Endian::Little, 0x1234, &stack, &context);
+    let module_name = DumpString::new(r"C:\test\test.dll", Endian::Little);
+    let module =
+        synth_minidump::Module::new(Endian::Little, 0xf00000, 0x1000, &module_name, 0, 0, None);
+    let minidump = SynthMinidump::with_endian(Endian::Little)
+        .add_thread(thread)
+        .add_module(module)
+        .add_system_info(SystemInfo::new(Endian::Little))
+        .add(context)
+        .add(module_name)
+        .add_memory(stack)
+        .finish()
+        .unwrap();
+    File::create(&synth_path)
+        .unwrap()
+        .write_all(&minidump)
+        .unwrap();
+
+    let bin = env!("CARGO_BIN_EXE_minidump-stackwalk");
+    let output = Command::new(bin)
+        .arg("memory")
+        .arg("--thread-stack=0x1234")
+        .arg("--length=32")
+        .arg(&synth_path)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .output()
+        .unwrap();
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+
+    assert!(output.status.success());
+    assert_eq!(
+        stdout,
+        "\
+0000000000001010  00 08 f0 00 40 10 00 00 41 42 43 44 00 00 00 00  ....@...ABCD....
+    0x1010: 0xf00800 -> test.dll + 0x800
+    0x1014: 0x1040 -> stack of thread 0x1234
+0000000000001020  00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00  ................
+"
+    );
+    assert_eq!(stderr, "");
+}
+
+#[test]
+fn test_extract_module() {
+    let synth_path = test_output("mdsw-test-extract-module.dmp");
+    let out_path = test_output("mdsw-test-extract-module.bin");
+    // Only the first page of the module made it into the minidump.
+    let contents = (0..0x1000).map(|i| i as u8).collect::<Vec<u8>>();
+    let module_memory = Memory::with_section(
+        Section::with_endian(Endian::Little).append_bytes(&contents),
+        0x200000,
+    );
+    let module_name = DumpString::new(r"C:\test\inject.dll", Endian::Little);
+    let module =
+        synth_minidump::Module::new(Endian::Little, 0x200000, 0x2000, &module_name, 0, 0, None);
+    let minidump = minimal_minidump()
+        .add_module(module)
+        .add(module_name)
+        .add_memory(module_memory)
+        .finish()
+        .unwrap();
+    File::create(&synth_path)
+        .unwrap()
+        .write_all(&minidump)
+        .unwrap();
+
+    let bin = env!("CARGO_BIN_EXE_minidump-stackwalk");
+    let output = Command::new(bin)
+        .arg("extract-module")
+        .arg(&synth_path)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .output()
+        .unwrap();
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+
+    assert!(output.status.success());
+    assert_eq!(
+        stdout,
+        "0x0000000000200000 - 0x0000000000201fff  inject.dll  (4096 of 8192 bytes in memory)\n"
+    );
+    assert_eq!(stderr, "");
+
+    // It's not a PE image, so it can only be written out as it was mapped.
+    let output = Command::new(bin)
+        .arg("extract-module")
+        .arg("--module=INJECT.DLL")
+        .arg("--output")
+        .arg(&out_path)
+        .arg(&synth_path)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .output()
+        .unwrap();
+
+    let stderr = String::from_utf8(output.stderr).unwrap();
+
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stderr.contains("inject.dll doesn't have valid PE headers"));
+
+    let output = Command::new(bin)
+        .arg("extract-module")
+        .arg("--module=0x201234")
+        .arg("--raw")
+        .arg("--output")
+        .arg(&out_path)
+        .arg(&synth_path)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+    let extracted = std::fs::read(&out_path).unwrap();
+    assert_eq!(extracted.len(), 0x2000);
+    assert_eq!(&extracted[..0x1000], &contents[..]);
+    assert!(extracted[0x1000..].iter().all(|&b| b == 0));
+}
+
+#[test]
+fn test_prefetch_symbols_server_down() {
+    let synth_path = test_output("mdsw-test-prefetch.dmp");
+    let cache_path = test_output("mdsw-test-prefetch-cache");
+    let module_name = DumpString::new(r"C:\test\test.dll", Endian::Little);
+    let cv_record = Section::with_endian(Endian::Little)
+        .D32(0x53445352) // "RSDS", a PDB 7.0 record
+        .D32(0xabcd1234)
+        .D16(0xf00d)
+        .D16(0xbeef)
+        .append_bytes(b"\x01\x02\x03\x04\x05\x06\x07\x08")
+        .D32(1)
+        .append_bytes(b"c:\\test\\test.pdb\0");
+    let module =
+        synth_minidump::Module::new(Endian::Little, 0x200000, 0x2000, &module_name, 0, 0, None)
+            .cv_record(&cv_record);
+    let minidump = minimal_minidump()
+        .add_module(module)
+        .add(module_name)
+        .add(cv_record)
+        .finish()
+        .unwrap();
+    File::create(&synth_path)
+        .unwrap()
+        .write_all(&minidump)
+        .unwrap();
+
+    // Nothing listens on port 1, so every download fails.
+    let bin = env!("CARGO_BIN_EXE_minidump-stackwalk");
+    let output = Command::new(bin)
+        .arg("prefetch-symbols")
+        .arg("--symbols-url=http://127.0.0.1:1/")
+        .arg("--symbols-cache")
+        .arg(&cache_path)
+        .arg(&synth_path)
+        .arg("Cargo.toml")
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .output()
+        .unwrap();
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+
+    // An unreadable minidump and a symbol server that's down are too mixed
+    // a bag for anything but 1.
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(stdout, "test.dll  missing symbols\n");
+    assert!(stderr.contains("Error reading dump"));
+    assert!(stderr.contains("Failed to fetch symbols - http://127.0.0.1:1/"));
+}
+
+#[test]
+fn test_diff() {
+    // The same process, but with a different module loaded.
+    let dump_with_module = |file_name: &str, module_name: &str| {
+        let synth_path = test_output(file_name);
+        let module_name = DumpString::new(module_name, Endian::Little);
+        let module =
+            synth_minidump::Module::new(Endian::Little, 0x200000, 0x2000, &module_name, 0, 0, None);
+        let minidump = minimal_minidump()
+            .add_module(module)
+            .add(module_name)
+            .finish()
+            .unwrap();
+        File::create(&synth_path)
+            .unwrap()
+            .write_all(&minidump)
+            .unwrap();
+        synth_path
+    };
+    let old_path = dump_with_module("mdsw-test-diff-old.dmp", "a.dll");
+    let new_path = dump_with_module("mdsw-test-diff-new.dmp", "b.dll");
+
+    let bin = env!("CARGO_BIN_EXE_minidump-stackwalk");
+    let output = Command::new(bin)
+        .arg("diff")
+        .arg(&old_path)
+        .arg(&new_path)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .output()
+        .unwrap();
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+
+    assert!(output.status.success());
+    assert_eq!(
+        stdout,
+        "\
+Signatures match: EMPTY: no crashing thread identified
+Crash reason: none
+Crashing stack similarity: unknown (no crashing thread)
+Threads: 1
+Added modules: b.dll
+Removed modules: a.dll
+"
+    );
+    assert_eq!(stderr, "");
+
+    let output = Command::new(bin)
+        .arg("diff")
+        .arg("--json")
+        .arg(&old_path)
+        .arg(&new_path)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .output()
+        .unwrap();
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+
+    assert!(output.status.success());
+    let diff: serde_json::Value = serde_json::from_str(stdout.trim()).unwrap();
+    assert_eq!(diff["signature"]["match"], true);
+    assert_eq!(diff["crashing_stack_similarity"], serde_json::Value::Null);
+    assert_eq!(diff["added_modules"], serde_json::json!(["b.dll"]));
+    assert_eq!(diff["removed_modules"], serde_json::json!(["a.dll"]));
+    assert_eq!(diff["changed_modules"], serde_json::json!([]));
+}
+
 fn minimal_minidump() -> SynthMinidump {
     let context = synth_minidump::x86_context(Endian::Little, 0xf00800, 0x1010);
     let stack = Memory::with_section(
@@ -531,9 +1138,13 @@ fn minimal_minidump() -> SynthMinidump {
 }
 
 fn unloaded_minidump() -> PathBuf {
+    unloaded_minidump_named("unloaded-minidump.dmp")
+}
+
+fn unloaded_minidump_named(file_name: &str) -> PathBuf {
     // Testing how we handle a stack frame having no module mapping, but many
     // "hits" with unloaded modules.
-    let synth_path = test_output("unloaded-minidump.dmp");
+    let synth_path = test_output(file_name);
 
     let mod1_name = DumpString::new("many.dll", Endian::Little);
     let mod2_name = DumpString::new("solo.dll", Endian::Little);
@@ -680,3 +1291,41 @@ fn test_unloaded_human() {
     insta::assert_snapshot!("human-unloaded", stdout);
     assert_eq!(stderr, "");
 }
+
+#[test]
+fn test_unloaded_symbols() {
+    let synth_path = unloaded_minidump_named("unloaded-symbols-minidump.dmp");
+
+    // Unloaded modules only have a code file and id to find symbols by.
+    let symbols_path = test_output("unloaded-symbols");
+    let sym_dir = symbols_path.join("solo.dll").join("B1054D2A2000");
+    std::fs::create_dir_all(&sym_dir).unwrap();
+    std::fs::write(
+        sym_dir.join("solo.sym"),
+        "MODULE windows x86 B1054D2A2000 solo.pdb
+FILE 1 c:\\solo\\solo.cpp
+FUNC 5d0 20 0 solo_function
+5d0 20 12 1
+",
+    )
+    .unwrap();
+
+    let bin = env!("CARGO_BIN_EXE_minidump-stackwalk");
+    let output = Command::new(bin)
+        .arg("--human")
+        .arg("--symbolicate-unloaded")
+        .arg(synth_path)
+        .arg("--")
+        .arg(&symbols_path)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .output()
+        .unwrap();
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+
+    assert!(output.status.success());
+    assert!(stdout.contains(" 0  (unloaded) solo.dll!solo_function [solo.cpp : 12 + 0x10]\n"));
+    assert_eq!(stderr, "");
+}
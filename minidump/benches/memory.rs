@@ -0,0 +1,56 @@
+//! Benchmarks for reading values out of a region of memory, the way stack
+//! scanning does: every pointer-sized word of a stack, one after another.
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use minidump::MinidumpMemory;
+use scroll::LE;
+
+const STACK_SIZE: u64 = 1024 * 1024;
+const BASE_ADDRESS: u64 = 0x7fff_0000_0000;
+
+fn memory(c: &mut Criterion) {
+    let bytes: Vec<u8> = (0..STACK_SIZE).map(|i| i as u8).collect();
+    let memory = MinidumpMemory {
+        desc: Default::default(),
+        base_address: BASE_ADDRESS,
+        size: STACK_SIZE,
+        bytes: &bytes,
+    };
+    // Step by 4 so half of the 8-byte reads are unaligned, as with a 32-bit
+    // stack, and run a word past the end so the last reads fail.
+    let addresses: Vec<u64> = (0..STACK_SIZE / 4 + 1)
+        .map(|i| BASE_ADDRESS + i * 4)
+        .collect();
+
+    c.bench_function("get_memory_at_address::<u32>", |b| {
+        b.iter(|| {
+            for &address in &addresses {
+                black_box(memory.get_memory_at_address::<u32>(address));
+            }
+        })
+    });
+    c.bench_function("get_u32_at_address", |b| {
+        b.iter(|| {
+            for &address in &addresses {
+                black_box(memory.get_u32_at_address(address, LE));
+            }
+        })
+    });
+    c.bench_function("get_memory_at_address::<u64>", |b| {
+        b.iter(|| {
+            for &address in &addresses {
+                black_box(memory.get_memory_at_address::<u64>(address));
+            }
+        })
+    });
+    c.bench_function("get_u64_at_address", |b| {
+        b.iter(|| {
+            for &address in &addresses {
+                black_box(memory.get_u64_at_address(address, LE));
+            }
+        })
+    });
+}
+
+criterion_group!(benches, memory);
+criterion_main!(benches);
@@ -3,10 +3,10 @@ use libfuzzer_sys::fuzz_target;
 
 use minidump::{
     MinidumpAssertion, MinidumpBreakpadInfo, MinidumpCrashpadInfo, MinidumpException,
-    MinidumpLinuxCpuInfo, MinidumpLinuxEnviron, MinidumpLinuxLsbRelease, MinidumpLinuxMaps,
-    MinidumpLinuxProcStatus, MinidumpMacCrashInfo, MinidumpMemoryInfoList, MinidumpMemoryList,
-    MinidumpMiscInfo, MinidumpModuleList, MinidumpSystemInfo, MinidumpThreadList,
-    MinidumpThreadNames, MinidumpUnloadedModuleList,
+    MinidumpHandleDataStream, MinidumpLinuxCpuInfo, MinidumpLinuxEnviron, MinidumpLinuxLsbRelease,
+    MinidumpLinuxMaps, MinidumpLinuxProcStatus, MinidumpMacCrashInfo, MinidumpMemoryInfoList,
+    MinidumpMemoryList, MinidumpMiscInfo, MinidumpModuleList, MinidumpSystemInfo,
+    MinidumpThreadInfoList, MinidumpThreadList, MinidumpThreadNames, MinidumpUnloadedModuleList,
 };
 
 fuzz_target!(|data: &[u8]| {
@@ -15,6 +15,7 @@ fuzz_target!(|data: &[u8]| {
         let _ = dump.get_stream::<MinidumpBreakpadInfo>();
         let _ = dump.get_stream::<MinidumpCrashpadInfo>();
         let _ = dump.get_stream::<MinidumpException>();
+        let _ = dump.get_stream::<MinidumpHandleDataStream>();
         let _ = dump.get_stream::<MinidumpLinuxCpuInfo>();
         let _ = dump.get_stream::<MinidumpLinuxEnviron>();
         let _ = dump.get_stream::<MinidumpLinuxLsbRelease>();
@@ -26,6 +27,7 @@ fuzz_target!(|data: &[u8]| {
         let _ = dump.get_stream::<MinidumpMiscInfo>();
         let _ = dump.get_stream::<MinidumpModuleList>();
         let _ = dump.get_stream::<MinidumpSystemInfo>();
+        let _ = dump.get_stream::<MinidumpThreadInfoList>();
         let _ = dump.get_stream::<MinidumpThreadNames>();
         let _ = dump.get_stream::<MinidumpThreadList>();
         let _ = dump.get_stream::<MinidumpUnloadedModuleList>();
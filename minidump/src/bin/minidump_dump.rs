@@ -26,6 +26,28 @@ fn print_raw_stream<T: Write>(name: &str, contents: &[u8], out: &mut T) -> io::R
     write!(out, "{}\n\n", s)
 }
 
+fn print_hexdump<T: Write>(name: &str, contents: &[u8], out: &mut T) -> io::Result<()> {
+    writeln!(out, "Stream {} ({} bytes):", name, contents.len())?;
+    for (i, line) in contents.chunks(16).enumerate() {
+        write!(out, "  {:08x} ", i * 16)?;
+        for byte in line {
+            write!(out, " {:02x}", byte)?;
+        }
+        // Line the text up with the lines before it.
+        write!(out, "{:width$}  ", "", width = (16 - line.len()) * 3)?;
+        for &byte in line {
+            let c = if byte.is_ascii_graphic() || byte == b' ' {
+                byte as char
+            } else {
+                '.'
+            };
+            write!(out, "{}", c)?;
+        }
+        writeln!(out)?;
+    }
+    writeln!(out)
+}
+
 fn print_minidump_dump(path: &Path) {
     match Minidump::read_path(path) {
         Ok(dump) => {
@@ -56,6 +78,9 @@ fn print_minidump_dump(path: &Path) {
             if let Some(memory_list) = memory_list {
                 memory_list.print(stdout).unwrap();
             }
+            if let Ok(memory64_list) = dump.get_stream::<MinidumpMemory64List<'_>>() {
+                memory64_list.print(stdout).unwrap();
+            }
             if let Ok(memory_info_list) = dump.get_stream::<MinidumpMemoryInfoList<'_>>() {
                 memory_info_list.print(stdout).unwrap();
             }
@@ -79,6 +104,12 @@ fn print_minidump_dump(path: &Path) {
             if let Ok(thread_names) = dump.get_stream::<MinidumpThreadNames>() {
                 thread_names.print(stdout).unwrap();
             }
+            if let Ok(thread_infos) = dump.get_stream::<MinidumpThreadInfoList>() {
+                thread_infos.print(stdout).unwrap();
+            }
+            if let Ok(handles) = dump.get_stream::<MinidumpHandleDataStream>() {
+                handles.print(stdout).unwrap();
+            }
             match dump.get_stream::<MinidumpCrashpadInfo>() {
                 Ok(crashpad_info) => crashpad_info.print(stdout).unwrap(),
                 Err(Error::StreamNotFound) => (),
@@ -96,6 +127,30 @@ fn print_minidump_dump(path: &Path) {
                     print_raw_stream(name, contents, stdout).unwrap();
                 }
             }
+            // Everything else is hexdumped, as there's nothing more to say
+            // about it. (The command line was already printed above.)
+            let mut unimplemented = dump
+                .unimplemented_streams()
+                .filter(|stream| {
+                    stream.stream_type
+                        != minidump_common::format::MINIDUMP_STREAM_TYPE::LinuxCmdLine
+                })
+                .collect::<Vec<_>>();
+            unimplemented.sort_by_key(|stream| u32::from(stream.stream_type));
+            for stream in unimplemented {
+                if let Ok(contents) = dump.get_raw_stream(stream.stream_type) {
+                    let name = format!("{:?}", stream.stream_type);
+                    print_hexdump(&name, contents, stdout).unwrap();
+                }
+            }
+            let mut unknown = dump.unknown_streams().collect::<Vec<_>>();
+            unknown.sort_by_key(|stream| stream.stream_type);
+            for stream in unknown {
+                if let Ok(contents) = dump.get_raw_stream(stream.stream_type) {
+                    let name = format!("{:#x} ({})", stream.stream_type, stream.vendor);
+                    print_hexdump(&name, contents, stdout).unwrap();
+                }
+            }
         }
         Err(err) => {
             let mut stderr = std::io::stderr();
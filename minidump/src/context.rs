@@ -30,6 +30,8 @@ pub enum MinidumpRawContext {
     Arm64(md::CONTEXT_ARM64),
     OldArm64(md::CONTEXT_ARM64_OLD),
     Mips(md::CONTEXT_MIPS),
+    Mips64(md::CONTEXT_MIPS64),
+    Riscv64(md::CONTEXT_RISCV64),
 }
 
 /// Generic over the specifics of a CPU context.
@@ -70,6 +72,9 @@ pub trait CpuContext {
     fn set_register(&mut self, reg: &str, val: Self::Register) -> Option<()>;
 
     /// Gets a static version of the given register name, if possible.
+    ///
+    /// For registers with several names, this may be a different name for
+    /// the same register.
     fn memoize_register(&self, reg: &str) -> Option<&'static str>;
 
     /// Return a String containing the value of `reg` formatted to its natural width.
@@ -208,6 +213,7 @@ impl CpuContext for md::CONTEXT_ARM {
         if let MinidumpContextValidity::Some(ref which) = valid {
             match reg {
                 "r11" | "fp" => which.contains("r11") || which.contains("fp"),
+                "r12" | "ip" => which.contains("r12") || which.contains("ip"),
                 "r13" | "sp" => which.contains("r13") || which.contains("sp"),
                 "r14" | "lr" => which.contains("r14") || which.contains("lr"),
                 "r15" | "pc" => which.contains("r15") || which.contains("pc"),
@@ -239,6 +245,7 @@ impl CpuContext for md::CONTEXT_ARM {
             "pc" => self.iregs[md::ArmRegisterNumbers::ProgramCounter as usize],
             "lr" => self.iregs[md::ArmRegisterNumbers::LinkRegister as usize],
             "fp" => self.iregs[md::ArmRegisterNumbers::FramePointer as usize],
+            "ip" => self.iregs[12],
             "sp" => self.iregs[md::ArmRegisterNumbers::StackPointer as usize],
             _ => unreachable!("Invalid arm register! {}", reg),
         }
@@ -265,6 +272,7 @@ impl CpuContext for md::CONTEXT_ARM {
             "pc" => self.iregs[md::ArmRegisterNumbers::ProgramCounter as usize] = val,
             "lr" => self.iregs[md::ArmRegisterNumbers::LinkRegister as usize] = val,
             "fp" => self.iregs[md::ArmRegisterNumbers::FramePointer as usize] = val,
+            "ip" => self.iregs[12] = val,
             "sp" => self.iregs[md::ArmRegisterNumbers::StackPointer as usize] = val,
             _ => return None,
         }
@@ -272,6 +280,18 @@ impl CpuContext for md::CONTEXT_ARM {
     }
 
     fn memoize_register(&self, reg: &str) -> Option<&'static str> {
+        // Registers go by several names depending on who's asking (symbol
+        // files from different toolchains don't agree), so settle on the
+        // names breakpad uses. That way validity sets never end up with the
+        // same register under two names.
+        let reg = match reg {
+            "fp" => "r11",
+            "ip" => "r12",
+            "r13" => "sp",
+            "r14" => "lr",
+            "r15" => "pc",
+            _ => reg,
+        };
         let idx = ARM_REGS.iter().position(|val| *val == reg)?;
         Some(ARM_REGS[idx])
     }
@@ -509,6 +529,313 @@ impl CpuContext for md::CONTEXT_ARM64 {
     }
 }
 
+impl CpuContext for md::CONTEXT_MIPS {
+    // Only 32-bit MIPS contexts are supported, even though the raw
+    // context stores 64-bit registers.
+    type Register = u32;
+
+    fn get_register_always(&self, reg: &str) -> u32 {
+        match reg {
+            "pc" => self.epc as u32,
+            _ => {
+                let idx = MIPS_REGS[..32]
+                    .iter()
+                    .position(|val| *val == reg)
+                    .unwrap_or_else(|| unreachable!("Invalid mips register! {}", reg));
+                self.iregs[idx] as u32
+            }
+        }
+    }
+
+    fn set_register(&mut self, reg: &str, val: Self::Register) -> Option<()> {
+        match reg {
+            "pc" => self.epc = val as u64,
+            _ => {
+                let idx = MIPS_REGS[..32].iter().position(|val| *val == reg)?;
+                self.iregs[idx] = val as u64;
+            }
+        }
+        Some(())
+    }
+
+    fn memoize_register(&self, reg: &str) -> Option<&'static str> {
+        let idx = MIPS_REGS.iter().position(|val| *val == reg)?;
+        Some(MIPS_REGS[idx])
+    }
+
+    fn stack_pointer_register_name(&self) -> &'static str {
+        "sp"
+    }
+
+    fn instruction_pointer_register_name(&self) -> &'static str {
+        "pc"
+    }
+}
+
+impl CpuContext for md::CONTEXT_MIPS64 {
+    type Register = u64;
+
+    fn get_register_always(&self, reg: &str) -> u64 {
+        match reg {
+            "pc" => self.epc,
+            _ => {
+                let idx = MIPS_REGS[..32]
+                    .iter()
+                    .position(|val| *val == reg)
+                    .unwrap_or_else(|| unreachable!("Invalid mips64 register! {}", reg));
+                self.iregs[idx]
+            }
+        }
+    }
+
+    fn set_register(&mut self, reg: &str, val: Self::Register) -> Option<()> {
+        match reg {
+            "pc" => self.epc = val,
+            _ => {
+                let idx = MIPS_REGS[..32].iter().position(|val| *val == reg)?;
+                self.iregs[idx] = val;
+            }
+        }
+        Some(())
+    }
+
+    fn memoize_register(&self, reg: &str) -> Option<&'static str> {
+        let idx = MIPS_REGS.iter().position(|val| *val == reg)?;
+        Some(MIPS_REGS[idx])
+    }
+
+    fn stack_pointer_register_name(&self) -> &'static str {
+        "sp"
+    }
+
+    fn instruction_pointer_register_name(&self) -> &'static str {
+        "pc"
+    }
+}
+
+impl CpuContext for md::CONTEXT_PPC {
+    type Register = u32;
+
+    fn get_register_always(&self, reg: &str) -> u32 {
+        match reg {
+            "srr0" => self.srr0,
+            "lr" => self.lr,
+            _ => {
+                let idx = PPC_REGS[..32]
+                    .iter()
+                    .position(|val| *val == reg)
+                    .unwrap_or_else(|| unreachable!("Invalid ppc register! {}", reg));
+                self.gpr[idx]
+            }
+        }
+    }
+
+    fn set_register(&mut self, reg: &str, val: Self::Register) -> Option<()> {
+        match reg {
+            "srr0" => self.srr0 = val,
+            "lr" => self.lr = val,
+            _ => {
+                let idx = PPC_REGS[..32].iter().position(|val| *val == reg)?;
+                self.gpr[idx] = val;
+            }
+        }
+        Some(())
+    }
+
+    fn memoize_register(&self, reg: &str) -> Option<&'static str> {
+        let idx = PPC_REGS.iter().position(|val| *val == reg)?;
+        Some(PPC_REGS[idx])
+    }
+
+    fn stack_pointer_register_name(&self) -> &'static str {
+        "r1"
+    }
+
+    fn instruction_pointer_register_name(&self) -> &'static str {
+        "srr0"
+    }
+}
+
+impl CpuContext for md::CONTEXT_PPC64 {
+    type Register = u64;
+
+    fn get_register_always(&self, reg: &str) -> u64 {
+        match reg {
+            "srr0" => self.srr0,
+            "lr" => self.lr,
+            _ => {
+                let idx = PPC_REGS[..32]
+                    .iter()
+                    .position(|val| *val == reg)
+                    .unwrap_or_else(|| unreachable!("Invalid ppc64 register! {}", reg));
+                self.gpr[idx]
+            }
+        }
+    }
+
+    fn set_register(&mut self, reg: &str, val: Self::Register) -> Option<()> {
+        match reg {
+            "srr0" => self.srr0 = val,
+            "lr" => self.lr = val,
+            _ => {
+                let idx = PPC_REGS[..32].iter().position(|val| *val == reg)?;
+                self.gpr[idx] = val;
+            }
+        }
+        Some(())
+    }
+
+    fn memoize_register(&self, reg: &str) -> Option<&'static str> {
+        let idx = PPC_REGS.iter().position(|val| *val == reg)?;
+        Some(PPC_REGS[idx])
+    }
+
+    fn stack_pointer_register_name(&self) -> &'static str {
+        "r1"
+    }
+
+    fn instruction_pointer_register_name(&self) -> &'static str {
+        "srr0"
+    }
+}
+
+impl CpuContext for md::CONTEXT_SPARC {
+    type Register = u64;
+
+    fn get_register_always(&self, reg: &str) -> u64 {
+        match reg {
+            "pc" => self.pc,
+            "npc" => self.npc,
+            _ => {
+                let idx = SPARC_REGS[..32]
+                    .iter()
+                    .position(|val| *val == reg)
+                    .unwrap_or_else(|| unreachable!("Invalid sparc register! {}", reg));
+                self.g_r[idx]
+            }
+        }
+    }
+
+    fn set_register(&mut self, reg: &str, val: Self::Register) -> Option<()> {
+        match reg {
+            "pc" => self.pc = val,
+            "npc" => self.npc = val,
+            _ => {
+                let idx = SPARC_REGS[..32].iter().position(|val| *val == reg)?;
+                self.g_r[idx] = val;
+            }
+        }
+        Some(())
+    }
+
+    fn memoize_register(&self, reg: &str) -> Option<&'static str> {
+        let idx = SPARC_REGS.iter().position(|val| *val == reg)?;
+        Some(SPARC_REGS[idx])
+    }
+
+    fn stack_pointer_register_name(&self) -> &'static str {
+        md::SparcRegisterNumbers::StackPointer.name()
+    }
+
+    fn instruction_pointer_register_name(&self) -> &'static str {
+        "pc"
+    }
+}
+
+impl CpuContext for md::CONTEXT_RISCV64 {
+    type Register = u64;
+
+    fn get_register_always(&self, reg: &str) -> u64 {
+        match reg {
+            "pc" => self.pc,
+            "ra" => self.ra,
+            "sp" => self.sp,
+            "gp" => self.gp,
+            "tp" => self.tp,
+            "t0" => self.t0,
+            "t1" => self.t1,
+            "t2" => self.t2,
+            "s0" => self.s0,
+            "s1" => self.s1,
+            "a0" => self.a0,
+            "a1" => self.a1,
+            "a2" => self.a2,
+            "a3" => self.a3,
+            "a4" => self.a4,
+            "a5" => self.a5,
+            "a6" => self.a6,
+            "a7" => self.a7,
+            "s2" => self.s2,
+            "s3" => self.s3,
+            "s4" => self.s4,
+            "s5" => self.s5,
+            "s6" => self.s6,
+            "s7" => self.s7,
+            "s8" => self.s8,
+            "s9" => self.s9,
+            "s10" => self.s10,
+            "s11" => self.s11,
+            "t3" => self.t3,
+            "t4" => self.t4,
+            "t5" => self.t5,
+            "t6" => self.t6,
+            _ => unreachable!("Invalid riscv64 register! {}", reg),
+        }
+    }
+
+    fn set_register(&mut self, reg: &str, val: Self::Register) -> Option<()> {
+        match reg {
+            "pc" => self.pc = val,
+            "ra" => self.ra = val,
+            "sp" => self.sp = val,
+            "gp" => self.gp = val,
+            "tp" => self.tp = val,
+            "t0" => self.t0 = val,
+            "t1" => self.t1 = val,
+            "t2" => self.t2 = val,
+            "s0" => self.s0 = val,
+            "s1" => self.s1 = val,
+            "a0" => self.a0 = val,
+            "a1" => self.a1 = val,
+            "a2" => self.a2 = val,
+            "a3" => self.a3 = val,
+            "a4" => self.a4 = val,
+            "a5" => self.a5 = val,
+            "a6" => self.a6 = val,
+            "a7" => self.a7 = val,
+            "s2" => self.s2 = val,
+            "s3" => self.s3 = val,
+            "s4" => self.s4 = val,
+            "s5" => self.s5 = val,
+            "s6" => self.s6 = val,
+            "s7" => self.s7 = val,
+            "s8" => self.s8 = val,
+            "s9" => self.s9 = val,
+            "s10" => self.s10 = val,
+            "s11" => self.s11 = val,
+            "t3" => self.t3 = val,
+            "t4" => self.t4 = val,
+            "t5" => self.t5 = val,
+            "t6" => self.t6 = val,
+            _ => return None,
+        }
+        Some(())
+    }
+
+    fn memoize_register(&self, reg: &str) -> Option<&'static str> {
+        let idx = RISCV64_REGS.iter().position(|val| *val == reg)?;
+        Some(RISCV64_REGS[idx])
+    }
+
+    fn stack_pointer_register_name(&self) -> &'static str {
+        "sp"
+    }
+
+    fn instruction_pointer_register_name(&self) -> &'static str {
+        "pc"
+    }
+}
+
 /// Information about which registers are valid in a `MinidumpContext`.
 #[derive(Clone, Debug, PartialEq)]
 pub enum MinidumpContextValidity {
@@ -570,9 +897,122 @@ static ARM64_REGS: [&str; 35] = [
     "x15", "x16", "x17", "x18", "x19", "x20", "x21", "x22", "x23", "x24", "x25", "x26", "x27",
     "x28", "x29", "x30", "pc", "lr", "fp", "sp",
 ];
+
+/// General-purpose registers for RISC-V 64, in the same order as `CONTEXT_RISCV64`.
+static RISCV64_REGS: [&str; 32] = [
+    "pc", "ra", "sp", "gp", "tp", "t0", "t1", "t2", "s0", "s1", "a0", "a1", "a2", "a3", "a4", "a5",
+    "a6", "a7", "s2", "s3", "s4", "s5", "s6", "s7", "s8", "s9", "s10", "s11", "t3", "t4", "t5",
+    "t6",
+];
+
+/// General-purpose registers for SPARC.
+///
+/// The first 32 entries are in the same order as `CONTEXT_SPARC::g_r`: the
+/// globals followed by the current register window.
+static SPARC_REGS: [&str; 34] = [
+    "g0", "g1", "g2", "g3", "g4", "g5", "g6", "g7", "o0", "o1", "o2", "o3", "o4", "o5", "o6", "o7",
+    "l0", "l1", "l2", "l3", "l4", "l5", "l6", "l7", "i0", "i1", "i2", "i3", "i4", "i5", "i6", "i7",
+    "pc", "npc",
+];
+
+/// General-purpose registers for PPC and PPC64.
+///
+/// The first 32 entries are in the same order as `CONTEXT_PPC::gpr`.
+static PPC_REGS: [&str; 34] = [
+    "r0", "r1", "r2", "r3", "r4", "r5", "r6", "r7", "r8", "r9", "r10", "r11", "r12", "r13", "r14",
+    "r15", "r16", "r17", "r18", "r19", "r20", "r21", "r22", "r23", "r24", "r25", "r26", "r27",
+    "r28", "r29", "r30", "r31", "srr0", "lr",
+];
+
+/// General-purpose registers for MIPS and MIPS64.
+///
+/// The first 32 entries are in the same order as `CONTEXT_MIPS::iregs`.
+static MIPS_REGS: [&str; 33] = [
+    "zero", "at", "v0", "v1", "a0", "a1", "a2", "a3", "t0", "t1", "t2", "t3", "t4", "t5", "t6",
+    "t7", "s0", "s1", "s2", "s3", "s4", "s5", "s6", "s7", "t8", "t9", "k0", "k1", "gp", "sp", "fp",
+    "ra", "pc",
+];
+/// x86 registers covered by [`md::CONTEXT_CONTROL`].
+static X86_CONTROL_REGS: [&str; 4] = ["eip", "esp", "ebp", "efl"];
+
+/// x86-64 registers covered by [`md::CONTEXT_CONTROL`].
+static X86_64_CONTROL_REGS: [&str; 2] = ["rip", "rsp"];
+
+/// Work out which registers of an x86 or x86-64 context are valid from its `context_flags`.
+///
+/// Writers that don't set either [`md::CONTEXT_CONTROL`] or [`md::CONTEXT_INTEGER`] are
+/// assumed to have filled in everything, as they always have been.
+fn x86_validity(
+    context_flags: u32,
+    all_regs: &[&'static str],
+    control_regs: &[&'static str],
+) -> MinidumpContextValidity {
+    let control = context_flags & md::CONTEXT_CONTROL != 0;
+    let integer = context_flags & md::CONTEXT_INTEGER != 0;
+    if control == integer {
+        return MinidumpContextValidity::All;
+    }
+    let valid = all_regs
+        .iter()
+        .filter(|reg| control_regs.contains(reg) == control)
+        .copied()
+        .collect();
+    MinidumpContextValidity::Some(valid)
+}
+
 //======================================================
 // Implementations
 
+/// Print a `CONTEXT_MIPS` or `CONTEXT_MIPS64`, which have the same layout.
+macro_rules! print_mips_context {
+    ($f:expr, $name:expr, $raw:expr) => {{
+        let (f, raw) = ($f, $raw);
+        write!(
+            f,
+            r#"{}
+  context_flags       = {:#x}
+"#,
+            $name, raw.context_flags
+        )?;
+
+        for (i, reg) in raw.iregs.iter().enumerate() {
+            writeln!(f, "  iregs[{:2}]           = {:#x}", i, reg)?;
+        }
+        write!(
+            f,
+            r#"  mdhi                = {:#x}
+  mdlo                = {:#x}
+"#,
+            raw.mdhi, raw.mdlo
+        )?;
+        for i in 0..3 {
+            writeln!(f, "  hi[{}]               = {:#x}", i, raw.hi[i])?;
+            writeln!(f, "  lo[{}]               = {:#x}", i, raw.lo[i])?;
+        }
+        write!(
+            f,
+            r#"  dsp_control         = {:#x}
+  epc                 = {:#x}
+  badvaddr            = {:#x}
+  status              = {:#x}
+  cause               = {:#x}
+  float_save.fpcsr    = {:#x}
+  float_save.fir      = {:#x}
+"#,
+            raw.dsp_control,
+            raw.epc,
+            raw.badvaddr,
+            raw.status,
+            raw.cause,
+            raw.float_save.fpcsr,
+            raw.float_save.fir
+        )?;
+        for (i, reg) in raw.float_save.regs.iter().enumerate() {
+            writeln!(f, "  float_save.regs[{:2}] = {:#x}", i, reg)?;
+        }
+    }};
+}
+
 impl MinidumpContext {
     /// Return a MinidumpContext given a `MinidumpRawContext`.
     pub fn from_raw(raw: MinidumpRawContext) -> MinidumpContext {
@@ -612,25 +1052,55 @@ impl MinidumpContext {
                         // FIXME: uses MISC_INFO_5 to parse out extra sections here
                         warn!("Cpu context has extra XSTATE that is being ignored");
                     }
-                    Ok(MinidumpContext::from_raw(MinidumpRawContext::X86(ctx)))
+                    Ok(MinidumpContext {
+                        valid: x86_validity(ctx.context_flags, &X86_REGS, &X86_CONTROL_REGS),
+                        ..MinidumpContext::from_raw(MinidumpRawContext::X86(ctx))
+                    })
                 } else {
                     Err(ContextError::ReadFailure)
                 }
             }
             Some(PROCESSOR_ARCHITECTURE_AMD64) => {
-                let ctx: md::CONTEXT_AMD64 = bytes
-                    .gread_with(&mut offset, endian)
-                    .or(Err(ContextError::ReadFailure))?;
-
-                let flags = ContextFlagsCpu::from_flags(ctx.context_flags as u32);
-                if flags == ContextFlagsCpu::CONTEXT_AMD64 {
-                    if ctx.context_flags & md::CONTEXT_HAS_XSTATE != 0 {
-                        // FIXME: uses MISC_INFO_5 to parse out extra sections here
-                        warn!("Cpu context has extra XSTATE that is being ignored");
+                let ctx: Option<md::CONTEXT_AMD64> = bytes.gread_with(&mut offset, endian).ok();
+                match ctx {
+                    Some(ctx)
+                        if ContextFlagsCpu::from_flags(ctx.context_flags as u32)
+                            == ContextFlagsCpu::CONTEXT_AMD64 =>
+                    {
+                        if ctx.context_flags & md::CONTEXT_HAS_XSTATE != 0 {
+                            // FIXME: uses MISC_INFO_5 to parse out extra sections here
+                            warn!("Cpu context has extra XSTATE that is being ignored");
+                        }
+                        Ok(MinidumpContext {
+                            valid: x86_validity(
+                                ctx.context_flags,
+                                &X86_64_REGS,
+                                &X86_64_CONTROL_REGS,
+                            ),
+                            ..MinidumpContext::from_raw(MinidumpRawContext::Amd64(ctx))
+                        })
+                    }
+                    _ => {
+                        // 32-bit processes running under WOW64 on 64-bit Windows
+                        // can have 32-bit contexts, which we can tell apart by
+                        // their flags.
+                        let ctx: md::CONTEXT_X86 = bytes
+                            .pread_with(0, endian)
+                            .or(Err(ContextError::ReadFailure))?;
+                        let flags = ContextFlagsCpu::from_flags(ctx.context_flags);
+                        if flags == ContextFlagsCpu::CONTEXT_X86 {
+                            Ok(MinidumpContext {
+                                valid: x86_validity(
+                                    ctx.context_flags,
+                                    &X86_REGS,
+                                    &X86_CONTROL_REGS,
+                                ),
+                                ..MinidumpContext::from_raw(MinidumpRawContext::X86(ctx))
+                            })
+                        } else {
+                            Err(ContextError::ReadFailure)
+                        }
                     }
-                    Ok(MinidumpContext::from_raw(MinidumpRawContext::Amd64(ctx)))
-                } else {
-                    Err(ContextError::ReadFailure)
                 }
             }
             Some(PROCESSOR_ARCHITECTURE_PPC) => {
@@ -705,14 +1175,35 @@ impl MinidumpContext {
                     Err(ContextError::ReadFailure)
                 }
             }
-            Some(PROCESSOR_ARCHITECTURE_MIPS) => {
-                let ctx: md::CONTEXT_MIPS = bytes
-                    .gread_with(&mut offset, endian)
+            Some(PROCESSOR_ARCHITECTURE_MIPS) | Some(PROCESSOR_ARCHITECTURE_MIPS64) => {
+                // Both contexts have the same layout, only the flags tell them apart.
+                let context_flags: u32 = bytes
+                    .pread_with(offset, endian)
                     .or(Err(ContextError::ReadFailure))?;
 
-                let flags = ContextFlagsCpu::from_flags(ctx.context_flags as u32);
+                let flags = ContextFlagsCpu::from_flags(context_flags);
                 if flags == ContextFlagsCpu::CONTEXT_MIPS {
+                    let ctx: md::CONTEXT_MIPS = bytes
+                        .gread_with(&mut offset, endian)
+                        .or(Err(ContextError::ReadFailure))?;
                     Ok(MinidumpContext::from_raw(MinidumpRawContext::Mips(ctx)))
+                } else if flags == ContextFlagsCpu::CONTEXT_MIPS64 {
+                    let ctx: md::CONTEXT_MIPS64 = bytes
+                        .gread_with(&mut offset, endian)
+                        .or(Err(ContextError::ReadFailure))?;
+                    Ok(MinidumpContext::from_raw(MinidumpRawContext::Mips64(ctx)))
+                } else {
+                    Err(ContextError::ReadFailure)
+                }
+            }
+            Some(PROCESSOR_ARCHITECTURE_RISCV64) => {
+                let ctx: md::CONTEXT_RISCV64 = bytes
+                    .gread_with(&mut offset, endian)
+                    .or(Err(ContextError::ReadFailure))?;
+
+                let flags = ContextFlagsCpu::from_flags(ctx.context_flags);
+                if flags == ContextFlagsCpu::CONTEXT_RISCV64 {
+                    Ok(MinidumpContext::from_raw(MinidumpRawContext::Riscv64(ctx)))
                 } else {
                     Err(ContextError::ReadFailure)
                 }
@@ -733,7 +1224,9 @@ impl MinidumpContext {
             MinidumpRawContext::Ppc64(ref ctx) => ctx.srr0,
             MinidumpRawContext::Sparc(ref ctx) => ctx.pc,
             MinidumpRawContext::X86(ref ctx) => ctx.eip as u64,
-            MinidumpRawContext::Mips(ref ctx) => ctx.epc,
+            MinidumpRawContext::Mips(ref ctx) => ctx.epc as u32 as u64,
+            MinidumpRawContext::Mips64(ref ctx) => ctx.epc,
+            MinidumpRawContext::Riscv64(ref ctx) => ctx.pc,
         }
     }
 
@@ -760,8 +1253,12 @@ impl MinidumpContext {
             }
             MinidumpRawContext::X86(ref ctx) => ctx.esp as u64,
             MinidumpRawContext::Mips(ref ctx) => {
+                ctx.iregs[md::MipsRegisterNumbers::StackPointer as usize] as u32 as u64
+            }
+            MinidumpRawContext::Mips64(ref ctx) => {
                 ctx.iregs[md::MipsRegisterNumbers::StackPointer as usize]
             }
+            MinidumpRawContext::Riscv64(ref ctx) => ctx.sp,
         }
     }
 
@@ -771,11 +1268,33 @@ impl MinidumpContext {
             MinidumpRawContext::Arm(ref ctx) => ctx.format_register(reg),
             MinidumpRawContext::Arm64(ref ctx) => ctx.format_register(reg),
             MinidumpRawContext::OldArm64(ref ctx) => ctx.format_register(reg),
-            MinidumpRawContext::Ppc(_) => unimplemented!(),
-            MinidumpRawContext::Ppc64(_) => unimplemented!(),
-            MinidumpRawContext::Sparc(_) => unimplemented!(),
+            MinidumpRawContext::Ppc(ref ctx) => ctx.format_register(reg),
+            MinidumpRawContext::Ppc64(ref ctx) => ctx.format_register(reg),
+            MinidumpRawContext::Sparc(ref ctx) => ctx.format_register(reg),
             MinidumpRawContext::X86(ref ctx) => ctx.format_register(reg),
-            MinidumpRawContext::Mips(_) => unimplemented!(),
+            MinidumpRawContext::Mips(ref ctx) => ctx.format_register(reg),
+            MinidumpRawContext::Mips64(ref ctx) => ctx.format_register(reg),
+            MinidumpRawContext::Riscv64(ref ctx) => ctx.format_register(reg),
+        }
+    }
+
+    /// Get the value of the register named `reg`, if it's valid.
+    ///
+    /// Returns `None` for registers this context's CPU doesn't have.
+    pub fn get_register(&self, reg: &str) -> Option<u64> {
+        let valid = &self.valid;
+        match self.raw {
+            MinidumpRawContext::Amd64(ref ctx) => ctx.get_register(reg, valid),
+            MinidumpRawContext::Arm(ref ctx) => ctx.get_register(reg, valid).map(u64::from),
+            MinidumpRawContext::Arm64(ref ctx) => ctx.get_register(reg, valid),
+            MinidumpRawContext::OldArm64(ref ctx) => ctx.get_register(reg, valid),
+            MinidumpRawContext::Ppc(ref ctx) => ctx.get_register(reg, valid).map(u64::from),
+            MinidumpRawContext::Ppc64(ref ctx) => ctx.get_register(reg, valid),
+            MinidumpRawContext::Sparc(ref ctx) => ctx.get_register(reg, valid),
+            MinidumpRawContext::X86(ref ctx) => ctx.get_register(reg, valid).map(u64::from),
+            MinidumpRawContext::Mips(ref ctx) => ctx.get_register(reg, valid).map(u64::from),
+            MinidumpRawContext::Mips64(ref ctx) => ctx.get_register(reg, valid),
+            MinidumpRawContext::Riscv64(ref ctx) => ctx.get_register(reg, valid),
         }
     }
 
@@ -785,11 +1304,13 @@ impl MinidumpContext {
             MinidumpRawContext::Arm(_) => &ARM_REGS[..],
             MinidumpRawContext::Arm64(_) => &ARM64_REGS[..],
             MinidumpRawContext::OldArm64(_) => &ARM64_REGS[..],
-            MinidumpRawContext::Ppc(_) => unimplemented!(),
-            MinidumpRawContext::Ppc64(_) => unimplemented!(),
-            MinidumpRawContext::Sparc(_) => unimplemented!(),
+            MinidumpRawContext::Ppc(_) => &PPC_REGS[..],
+            MinidumpRawContext::Ppc64(_) => &PPC_REGS[..],
+            MinidumpRawContext::Sparc(_) => &SPARC_REGS[..],
             MinidumpRawContext::X86(_) => &X86_REGS[..],
-            MinidumpRawContext::Mips(_) => unimplemented!(),
+            MinidumpRawContext::Mips(_) => &MIPS_REGS[..],
+            MinidumpRawContext::Mips64(_) => &MIPS_REGS[..],
+            MinidumpRawContext::Riscv64(_) => &RISCV64_REGS[..],
         }
     }
 
@@ -877,11 +1398,90 @@ impl MinidumpContext {
                 write_bytes(f, &raw.extended_registers)?;
                 write!(f, "\n\n")?;
             }
-            MinidumpRawContext::Ppc(_) => {
-                unimplemented!();
+            MinidumpRawContext::Ppc(ref raw) => {
+                write!(
+                    f,
+                    r#"CONTEXT_PPC
+  context_flags            = {:#x}
+  srr0                     = {:#x}
+  srr1                     = {:#x}
+"#,
+                    raw.context_flags, raw.srr0, raw.srr1
+                )?;
+                for (i, reg) in raw.gpr.iter().enumerate() {
+                    writeln!(f, "  gpr[{:2}]                  = {:#x}", i, reg)?;
+                }
+                write!(
+                    f,
+                    r#"  cr                       = {:#x}
+  xer                      = {:#x}
+  lr                       = {:#x}
+  ctr                      = {:#x}
+  mq                       = {:#x}
+  vrsave                   = {:#x}
+"#,
+                    raw.cr, raw.xer, raw.lr, raw.ctr, raw.mq, raw.vrsave
+                )?;
+                for (i, reg) in raw.float_save.fpregs.iter().enumerate() {
+                    writeln!(f, "  float_save.fpregs[{:2}]    = {:#x}", i, reg)?;
+                }
+                writeln!(
+                    f,
+                    "  float_save.fpscr         = {:#x}",
+                    raw.float_save.fpscr
+                )?;
+                for (i, reg) in raw.vector_save.save_vr.iter().enumerate() {
+                    writeln!(f, "  vector_save.save_vr[{:2}]  = {:#x}", i, reg)?;
+                }
+                write!(
+                    f,
+                    r#"  vector_save.save_vscr    = {:#x}
+  vector_save.save_vrvalid = {:#x}
+"#,
+                    raw.vector_save.save_vscr, raw.vector_save.save_vrvalid
+                )?;
             }
-            MinidumpRawContext::Ppc64(_) => {
-                unimplemented!();
+            MinidumpRawContext::Ppc64(ref raw) => {
+                write!(
+                    f,
+                    r#"CONTEXT_PPC64
+  context_flags            = {:#x}
+  srr0                     = {:#x}
+  srr1                     = {:#x}
+"#,
+                    raw.context_flags, raw.srr0, raw.srr1
+                )?;
+                for (i, reg) in raw.gpr.iter().enumerate() {
+                    writeln!(f, "  gpr[{:2}]                  = {:#x}", i, reg)?;
+                }
+                write!(
+                    f,
+                    r#"  cr                       = {:#x}
+  xer                      = {:#x}
+  lr                       = {:#x}
+  ctr                      = {:#x}
+  vrsave                   = {:#x}
+"#,
+                    raw.cr, raw.xer, raw.lr, raw.ctr, raw.vrsave
+                )?;
+                for (i, reg) in raw.float_save.fpregs.iter().enumerate() {
+                    writeln!(f, "  float_save.fpregs[{:2}]    = {:#x}", i, reg)?;
+                }
+                writeln!(
+                    f,
+                    "  float_save.fpscr         = {:#x}",
+                    raw.float_save.fpscr
+                )?;
+                for (i, reg) in raw.vector_save.save_vr.iter().enumerate() {
+                    writeln!(f, "  vector_save.save_vr[{:2}]  = {:#x}", i, reg)?;
+                }
+                write!(
+                    f,
+                    r#"  vector_save.save_vscr    = {:#x}
+  vector_save.save_vrvalid = {:#x}
+"#,
+                    raw.vector_save.save_vscr, raw.vector_save.save_vrvalid
+                )?;
             }
             MinidumpRawContext::Amd64(ref raw) => {
                 write!(
@@ -967,8 +1567,38 @@ impl MinidumpContext {
                     raw.rip,
                 )?;
             }
-            MinidumpRawContext::Sparc(_) => {
-                unimplemented!();
+            MinidumpRawContext::Sparc(ref raw) => {
+                write!(
+                    f,
+                    r#"CONTEXT_SPARC
+  context_flags       = {:#x}
+"#,
+                    raw.context_flags
+                )?;
+                for (i, reg) in raw.g_r.iter().enumerate() {
+                    writeln!(f, "  g_r[{:2}]             = {:#x}", i, reg)?;
+                }
+                write!(
+                    f,
+                    r#"  ccr                 = {:#x}
+  pc                  = {:#x}
+  npc                 = {:#x}
+  y                   = {:#x}
+  asi                 = {:#x}
+  fprs                = {:#x}
+"#,
+                    raw.ccr, raw.pc, raw.npc, raw.y, raw.asi, raw.fprs
+                )?;
+                for (i, reg) in raw.float_save.regs.iter().enumerate() {
+                    writeln!(f, "  float_save.regs[{:2}] = {:#x}", i, reg)?;
+                }
+                write!(
+                    f,
+                    r#"  float_save.filler   = {:#x}
+  float_save.fsr      = {:#x}
+"#,
+                    raw.float_save.filler, raw.float_save.fsr
+                )?;
             }
             MinidumpRawContext::Arm(ref raw) => {
                 write!(
@@ -1045,8 +1675,86 @@ impl MinidumpContext {
                     writeln!(f, "  float_save.regs[{:2}] = {:#x}", i, reg)?;
                 }
             }
-            MinidumpRawContext::Mips(_) => {
-                unimplemented!();
+            MinidumpRawContext::Mips(ref raw) => print_mips_context!(f, "CONTEXT_MIPS", raw),
+            MinidumpRawContext::Mips64(ref raw) => print_mips_context!(f, "CONTEXT_MIPS64", raw),
+            MinidumpRawContext::Riscv64(ref raw) => {
+                write!(
+                    f,
+                    r#"CONTEXT_RISCV64
+  context_flags = {:#x}
+  version       = {:#x}
+  pc            = {:#x}
+  ra            = {:#x}
+  sp            = {:#x}
+  gp            = {:#x}
+  tp            = {:#x}
+  t0            = {:#x}
+  t1            = {:#x}
+  t2            = {:#x}
+  s0            = {:#x}
+  s1            = {:#x}
+  a0            = {:#x}
+  a1            = {:#x}
+  a2            = {:#x}
+  a3            = {:#x}
+  a4            = {:#x}
+  a5            = {:#x}
+  a6            = {:#x}
+  a7            = {:#x}
+  s2            = {:#x}
+  s3            = {:#x}
+  s4            = {:#x}
+  s5            = {:#x}
+  s6            = {:#x}
+  s7            = {:#x}
+  s8            = {:#x}
+  s9            = {:#x}
+  s10           = {:#x}
+  s11           = {:#x}
+  t3            = {:#x}
+  t4            = {:#x}
+  t5            = {:#x}
+  t6            = {:#x}
+"#,
+                    raw.context_flags,
+                    raw.version,
+                    raw.pc,
+                    raw.ra,
+                    raw.sp,
+                    raw.gp,
+                    raw.tp,
+                    raw.t0,
+                    raw.t1,
+                    raw.t2,
+                    raw.s0,
+                    raw.s1,
+                    raw.a0,
+                    raw.a1,
+                    raw.a2,
+                    raw.a3,
+                    raw.a4,
+                    raw.a5,
+                    raw.a6,
+                    raw.a7,
+                    raw.s2,
+                    raw.s3,
+                    raw.s4,
+                    raw.s5,
+                    raw.s6,
+                    raw.s7,
+                    raw.s8,
+                    raw.s9,
+                    raw.s10,
+                    raw.s11,
+                    raw.t3,
+                    raw.t4,
+                    raw.t5,
+                    raw.t6,
+                )?;
+                for (i, reg) in raw.float_save.regs.iter().enumerate() {
+                    writeln!(f, "  float_save.regs[{:2}] = {:#x}", i, reg)?;
+                }
+                writeln!(f, "  float_save.fpcsr    = {:#x}", raw.float_save.fpcsr)?;
             }
         }
         Ok(())
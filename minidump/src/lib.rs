@@ -71,6 +71,7 @@
 //! * [`MinidumpBreakpadInfo`][]
 //! * [`MinidumpCrashpadInfo`][]
 //! * [`MinidumpException`][]
+//! * [`MinidumpHandleDataStream`][]
 //! * [`MinidumpLinuxCpuInfo`][]
 //! * [`MinidumpLinuxEnviron`][]
 //! * [`MinidumpLinuxLsbRelease`][]
@@ -78,10 +79,12 @@
 //! * [`MinidumpLinuxProcStatus`][]
 //! * [`MinidumpMacCrashInfo`][]
 //! * [`MinidumpMemoryList`][]
+//! * [`MinidumpMemory64List`][]
 //! * [`MinidumpMemoryInfoList`][]
 //! * [`MinidumpMiscInfo`][]
 //! * [`MinidumpModuleList`][]
 //! * [`MinidumpSystemInfo`][]
+//! * [`MinidumpThreadInfoList`][]
 //! * [`MinidumpThreadList`][]
 //! * [`MinidumpThreadNames`][]
 //! * [`MinidumpUnloadedModuleList`][]
@@ -350,6 +353,7 @@
 //! Known members of this family:
 //!
 //! * [`MinidumpMemoryInfoList`][] (entries are [`MINIDUMP_MEMORY_INFO`][format::MINIDUMP_MEMORY_INFO])
+//! * [`MinidumpThreadInfoList`][] (entries are [`MINIDUMP_THREAD_INFO`][format::MINIDUMP_THREAD_INFO])
 //! * [`MinidumpUnloadedModuleList`][] (entries are [`MINIDUMP_UNLOADED_MODULE`][format::MINIDUMP_UNLOADED_MODULE])
 //!
 //!
@@ -402,5 +406,6 @@ pub use minidump_common::traits::Module;
 pub use crate::iostuff::Readable;
 pub use crate::minidump::*;
 
+pub mod microdump;
 pub mod strings;
 pub mod system_info;
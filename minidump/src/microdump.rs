@@ -0,0 +1,757 @@
+// Copyright 2015 Ted Mielczarek. See the COPYRIGHT
+// file at the top-level directory of this distribution.
+
+//! Support for Breakpad "microdumps".
+//!
+//! Chrome and WebView on Android can't always upload a full minidump, so instead they
+//! write a tiny text summary of the crash to logcat: the OS and CPU, the crashing
+//! thread's context and stack, and the list of mapped modules. This module parses
+//! that text format into a [`Microdump`], which can then be converted into an
+//! in-memory [`Minidump`] so the usual processing and symbolication machinery can
+//! handle it.
+//!
+//! A microdump (with its logcat prefixes) looks like this:
+//!
+//! ```text
+//! W/google-breakpad(26491): -----BEGIN BREAKPAD MICRODUMP-----
+//! W/google-breakpad(26491): V WebView:42.0.2311.136
+//! W/google-breakpad(26491): O A arm 04 armv7l 3.4.0-perf-g4d6e88e #1 SMP PREEMPT
+//! W/google-breakpad(26491): S 0 A48BD840 A48BD000 00002000
+//! W/google-breakpad(26491): S A48BD000 0000000000000000...
+//! W/google-breakpad(26491): C 0300080000000000...
+//! W/google-breakpad(26491): M B6F0F000 00000000 00013000 DE8A6AA5D0A8E4F95A5F5DB6A2E20E1A0 libfoo.so
+//! W/google-breakpad(26491): -----END BREAKPAD MICRODUMP-----
+//! ```
+
+use std::convert::TryFrom;
+
+use scroll::ctx::SizeWith;
+use scroll::LE;
+
+use crate::system_info::Os;
+use crate::{Error, Minidump};
+use minidump_common::format as md;
+use minidump_common::format::ProcessorArchitecture::*;
+
+const MICRODUMP_BEGIN: &str = "-----BEGIN BREAKPAD MICRODUMP-----";
+const MICRODUMP_END: &str = "-----END BREAKPAD MICRODUMP-----";
+/// The logcat tag every line of a microdump is logged with.
+const LOGCAT_TAG: &str = "google-breakpad";
+/// The most stack we'll read, including the gaps between chunks (which take up
+/// memory once they're filled in). Breakpad only dumps the crashing thread's
+/// stack, which is much smaller than this.
+const MAX_STACK_SIZE: usize = 8 * 1024 * 1024;
+
+/// The contents of a Breakpad microdump.
+///
+/// Use [`Microdump::parse`] to read one from text, and [`Microdump::to_minidump`]
+/// (or [`Minidump::from_microdump`]) to process it like any other minidump.
+#[derive(Debug, Clone)]
+pub struct Microdump {
+    /// The product name and version (`V` record), e.g. `WebView:42.0.2311.136`.
+    pub product: Option<String>,
+    /// The operating system the crash happened on (`O` record).
+    pub os: Os,
+    /// The CPU architecture, as named by Breakpad: `arm`, `arm64`, `x86`, `mips` or `mips64`.
+    pub arch: String,
+    /// The number of CPUs in the system.
+    pub cpu_count: u8,
+    /// The hardware architecture reported by the kernel, e.g. `armv7l`.
+    pub hw_arch: String,
+    /// The kernel version string.
+    pub os_version: String,
+    /// A description of the GPU (`G` record), if present.
+    pub gpu: Option<String>,
+    /// Why the process crashed (`R` record), if present.
+    pub crash_reason: Option<MicrodumpCrashReason>,
+    /// The raw CPU context of the crashing thread, in the same layout as in a minidump.
+    pub context: Vec<u8>,
+    /// The address of the first byte of `stack`.
+    pub stack_base: u64,
+    /// The contents of the crashing thread's stack.
+    pub stack: Vec<u8>,
+    /// The mapped modules (`M` records).
+    pub modules: Vec<MicrodumpModule>,
+}
+
+/// The crash reason from a microdump's `R` record.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MicrodumpCrashReason {
+    /// The signal number.
+    pub signal: u32,
+    /// The name of the signal, e.g. `SIGSEGV`.
+    pub name: String,
+    /// The faulting address.
+    pub address: u64,
+}
+
+/// A module from a microdump's `M` record.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MicrodumpModule {
+    /// The address the module was mapped at.
+    pub base_address: u64,
+    /// The offset into the file of the mapping.
+    pub offset: u64,
+    /// The size of the mapping.
+    pub size: u64,
+    /// The Breakpad debug identifier of the module, e.g. `DE8A6AA5D0A8E4F95A5F5DB6A2E20E1A0`.
+    pub debug_id: String,
+    /// The path of the module's file.
+    pub filename: String,
+}
+
+/// Errors encountered while reading a `Microdump`.
+#[derive(Debug, thiserror::Error, PartialEq)]
+pub enum MicrodumpError {
+    #[error("No microdump found")]
+    MissingMicrodump,
+    #[error("Microdump has no OS information")]
+    MissingOs,
+    #[error("Microdump has no CPU context")]
+    MissingContext,
+    #[error("Unsupported microdump architecture: {0}")]
+    UnsupportedArchitecture(String),
+    #[error("Microdump CPU context size mismatch: {actual} bytes is not valid for {arch}")]
+    ContextSizeMismatch { arch: String, actual: usize },
+    #[error("Microdump stack data is not contiguous")]
+    NonContiguousStack,
+    #[error("Microdump stack is larger than {} bytes", MAX_STACK_SIZE)]
+    StackTooLarge,
+    #[error("Malformed microdump line: {0}")]
+    MalformedLine(String),
+    #[error("Error reading converted microdump: {0}")]
+    MinidumpError(#[from] Error),
+}
+
+/// Parse a hex number, as printed by Breakpad (with or without a `0x` prefix).
+fn parse_hex<T>(s: &str) -> Option<T>
+where
+    T: TryFrom<u64>,
+{
+    let s = s.trim_start_matches("0x");
+    u64::from_str_radix(s, 16)
+        .ok()
+        .and_then(|v| T::try_from(v).ok())
+}
+
+/// Parse a string of hex-encoded bytes.
+fn parse_hex_bytes(s: &str) -> Option<Vec<u8>> {
+    s.as_bytes()
+        .chunks(2)
+        .map(|pair| match pair {
+            [hi, lo] => Some((hex_digit(*hi)? << 4) | hex_digit(*lo)?),
+            _ => None,
+        })
+        .collect()
+}
+
+fn hex_digit(c: u8) -> Option<u8> {
+    (c as char).to_digit(16).map(|d| d as u8)
+}
+
+impl Microdump {
+    /// Parse a microdump from `text`.
+    ///
+    /// `text` may be a full logcat capture: everything before the start of the
+    /// microdump is ignored, and if the microdump lines have logcat prefixes, only
+    /// lines logged with Breakpad's tag are considered. Only the first microdump in
+    /// `text` is read.
+    pub fn parse(text: &str) -> Result<Microdump, MicrodumpError> {
+        let mut lines = text.lines();
+        // Find the start of the microdump, and check whether it's been logged
+        // through logcat (in which case other processes' logging may be interleaved).
+        let logcat = loop {
+            let line = lines.next().ok_or(MicrodumpError::MissingMicrodump)?;
+            if line.contains(MICRODUMP_BEGIN) {
+                break line.contains(LOGCAT_TAG);
+            }
+        };
+
+        let mut product = None;
+        let mut os_info = None;
+        let mut gpu = None;
+        let mut crash_reason = None;
+        let mut context = None;
+        let mut stack_base = 0;
+        let mut stack = Vec::new();
+        let mut modules = Vec::new();
+
+        for line in lines {
+            if line.contains(MICRODUMP_END) {
+                break;
+            }
+            let payload = if logcat {
+                if !line.contains(LOGCAT_TAG) {
+                    continue;
+                }
+                match line.split_once(": ") {
+                    Some((_, payload)) => payload,
+                    None => continue,
+                }
+            } else {
+                line
+            };
+            let payload = payload.trim();
+            let malformed = || MicrodumpError::MalformedLine(payload.to_owned());
+            let (key, rest) = payload.split_once(' ').unwrap_or((payload, ""));
+
+            match key {
+                "V" => product = Some(rest.to_owned()),
+                "O" => {
+                    // O <os> <arch> <cpu count> <hw arch> <os version...>
+                    let mut tokens = rest.splitn(5, ' ');
+                    let os = match tokens.next().ok_or_else(malformed)? {
+                        "L" => Os::Linux,
+                        "A" => Os::Android,
+                        _ => Os::Unknown(0),
+                    };
+                    let arch = tokens.next().ok_or_else(malformed)?.to_owned();
+                    let cpu_count = tokens.next().and_then(parse_hex).ok_or_else(malformed)?;
+                    let hw_arch = tokens.next().unwrap_or("").to_owned();
+                    let os_version = tokens.next().unwrap_or("").to_owned();
+                    os_info = Some((os, arch, cpu_count, hw_arch, os_version));
+                }
+                "G" => gpu = Some(rest.to_owned()),
+                "R" => {
+                    // R <signal> <signal name> <address>
+                    let mut tokens = rest.split_whitespace();
+                    let signal = tokens.next().and_then(parse_hex).ok_or_else(malformed)?;
+                    let name = tokens.next().ok_or_else(malformed)?.to_owned();
+                    let address = tokens.next().and_then(parse_hex).ok_or_else(malformed)?;
+                    crash_reason = Some(MicrodumpCrashReason {
+                        signal,
+                        name,
+                        address,
+                    });
+                }
+                "S" => {
+                    let mut tokens = rest.split_whitespace();
+                    let start = tokens.next().ok_or_else(malformed)?;
+                    if start == "0" {
+                        // The first line (S 0 <sp> <stack start> <stack size>) is a header
+                        // describing the stack. We just use the chunks that follow it.
+                        continue;
+                    }
+                    let start: u64 = parse_hex(start).ok_or_else(malformed)?;
+                    let chunk = tokens
+                        .next()
+                        .and_then(parse_hex_bytes)
+                        .ok_or_else(malformed)?;
+                    if stack.is_empty() {
+                        stack_base = start;
+                    } else {
+                        // Chunks must be in order, but we can fill any gaps with zeros.
+                        let end = stack_base.saturating_add(stack.len() as u64);
+                        if start < end {
+                            return Err(MicrodumpError::NonContiguousStack);
+                        }
+                        let gap = usize::try_from(start - end)
+                            .ok()
+                            .filter(|gap| *gap <= MAX_STACK_SIZE - stack.len())
+                            .ok_or(MicrodumpError::StackTooLarge)?;
+                        stack.resize(stack.len() + gap, 0);
+                    }
+                    if chunk.len() > MAX_STACK_SIZE - stack.len() {
+                        return Err(MicrodumpError::StackTooLarge);
+                    }
+                    stack.extend_from_slice(&chunk);
+                }
+                "C" => context = Some(parse_hex_bytes(rest).ok_or_else(malformed)?),
+                "M" => {
+                    // M <start> <offset> <size> <debug id> <filename>
+                    let mut tokens = rest.splitn(5, ' ');
+                    let base_address = tokens.next().and_then(parse_hex).ok_or_else(malformed)?;
+                    let offset = tokens.next().and_then(parse_hex).ok_or_else(malformed)?;
+                    let size = tokens.next().and_then(parse_hex).ok_or_else(malformed)?;
+                    let debug_id = tokens.next().ok_or_else(malformed)?.to_owned();
+                    let filename = tokens.next().unwrap_or("").to_owned();
+                    modules.push(MicrodumpModule {
+                        base_address,
+                        offset,
+                        size,
+                        debug_id,
+                        filename,
+                    });
+                }
+                // Other records (heap info, process type...) aren't interesting to us.
+                _ => {}
+            }
+        }
+
+        let (os, arch, cpu_count, hw_arch, os_version) =
+            os_info.ok_or(MicrodumpError::MissingOs)?;
+        let context = context.ok_or(MicrodumpError::MissingContext)?;
+        let microdump = Microdump {
+            product,
+            os,
+            arch,
+            cpu_count,
+            hw_arch,
+            os_version,
+            gpu,
+            crash_reason,
+            context,
+            stack_base,
+            stack,
+            modules,
+        };
+        // Make sure we'll be able to make sense of the context.
+        microdump.processor_architecture()?;
+        Ok(microdump)
+    }
+
+    /// Get the minidump `processor_architecture` matching this microdump's
+    /// architecture and context.
+    fn processor_architecture(&self) -> Result<md::ProcessorArchitecture, MicrodumpError> {
+        let actual = self.context.len();
+        let (arch, expected) = match &self.arch[..] {
+            "arm" => (PROCESSOR_ARCHITECTURE_ARM, md::CONTEXT_ARM::size_with(&LE)),
+            // Older Breakpad versions wrote the old arm64 context layout.
+            "arm64" if actual == md::CONTEXT_ARM64_OLD::size_with(&LE) => {
+                (PROCESSOR_ARCHITECTURE_ARM64_OLD, actual)
+            }
+            "arm64" => (
+                PROCESSOR_ARCHITECTURE_ARM64,
+                md::CONTEXT_ARM64::size_with(&LE),
+            ),
+            "x86" => (
+                PROCESSOR_ARCHITECTURE_INTEL,
+                md::CONTEXT_X86::size_with(&LE),
+            ),
+            "mips" => (
+                PROCESSOR_ARCHITECTURE_MIPS,
+                md::CONTEXT_MIPS::size_with(&LE),
+            ),
+            "mips64" => (
+                PROCESSOR_ARCHITECTURE_MIPS64,
+                md::CONTEXT_MIPS64::size_with(&LE),
+            ),
+            _ => return Err(MicrodumpError::UnsupportedArchitecture(self.arch.clone())),
+        };
+        if actual != expected {
+            return Err(MicrodumpError::ContextSizeMismatch {
+                arch: self.arch.clone(),
+                actual,
+            });
+        }
+        Ok(arch)
+    }
+
+    /// Convert this microdump into the bytes of an equivalent minidump.
+    ///
+    /// The minidump will contain a system info stream, a thread list with the
+    /// crashing thread (with thread id 0), a memory list with its stack, a module
+    /// list, and an exception stream if the microdump has a crash reason.
+    pub fn to_minidump_bytes(&self) -> Result<Vec<u8>, MicrodumpError> {
+        let processor_architecture = self.processor_architecture()?;
+        let mut dump = DumpWriter::default();
+
+        let stream_count = 4 + self.crash_reason.is_some() as u32;
+        let header_size = md::MINIDUMP_HEADER::size_with(&LE) as u32;
+        let directory_size = stream_count * md::MINIDUMP_DIRECTORY::size_with(&LE) as u32;
+        dump.buf.resize((header_size + directory_size) as usize, 0);
+
+        // First write out all the data that the streams refer to.
+        let context = dump.bytes(&self.context);
+        let stack = dump.bytes(&self.stack);
+        let csd_version = dump.string(&self.os_version);
+        let modules = self
+            .modules
+            .iter()
+            .map(|module| (dump.string(&module.filename), dump.codeview(module)))
+            .collect::<Vec<_>>();
+
+        // Then the streams themselves.
+        let mut directory = Vec::new();
+
+        let start = dump.pos();
+        // The kernel version looks like "3.4.0-perf-g4d6e88e #1 SMP PREEMPT", so
+        // try to pull the major.minor.build numbers from the start of it.
+        let mut version = self
+            .os_version
+            .split(|c: char| !c.is_ascii_digit())
+            .take(3)
+            .map(|n| n.parse::<u32>().unwrap_or(0));
+        let platform_id = match self.os {
+            Os::Linux => md::PlatformId::Linux as u32,
+            Os::Android => md::PlatformId::Android as u32,
+            _ => 0,
+        };
+        dump.u16(processor_architecture as u16);
+        dump.u16(0); // processor_level
+        dump.u16(0); // processor_revision
+        dump.u8(self.cpu_count);
+        dump.u8(0); // product_type
+        dump.u32(version.next().unwrap_or(0));
+        dump.u32(version.next().unwrap_or(0));
+        dump.u32(version.next().unwrap_or(0));
+        dump.u32(platform_id);
+        dump.u32(csd_version);
+        dump.u16(0); // suite_mask
+        dump.u16(0); // reserved2
+        dump.zeros(md::CPU_INFORMATION::size_with(&LE));
+        directory.push(dump.stream(md::MINIDUMP_STREAM_TYPE::SystemInfoStream, start));
+
+        let start = dump.pos();
+        dump.u32(1);
+        dump.u32(0); // thread_id
+        dump.u32(0); // suspend_count
+        dump.u32(0); // priority_class
+        dump.u32(0); // priority
+        dump.u64(0); // teb
+        dump.u64(self.stack_base);
+        dump.location(stack);
+        dump.location(context);
+        directory.push(dump.stream(md::MINIDUMP_STREAM_TYPE::ThreadListStream, start));
+
+        let start = dump.pos();
+        dump.u32(1);
+        dump.u64(self.stack_base);
+        dump.location(stack);
+        directory.push(dump.stream(md::MINIDUMP_STREAM_TYPE::MemoryListStream, start));
+
+        let start = dump.pos();
+        dump.u32(self.modules.len() as u32);
+        for (module, (name, codeview)) in self.modules.iter().zip(modules) {
+            dump.u64(module.base_address);
+            dump.u32(module.size as u32);
+            dump.u32(0); // checksum
+            dump.u32(0); // time_date_stamp
+            dump.u32(name);
+            dump.zeros(md::VS_FIXEDFILEINFO::size_with(&LE));
+            dump.location(codeview);
+            dump.location((0, 0)); // misc_record
+            dump.u64(0); // reserved0
+            dump.u64(0); // reserved1
+        }
+        directory.push(dump.stream(md::MINIDUMP_STREAM_TYPE::ModuleListStream, start));
+
+        if let Some(ref crash_reason) = self.crash_reason {
+            let start = dump.pos();
+            dump.u32(0); // thread_id
+            dump.u32(0); // __align
+            dump.u32(crash_reason.signal);
+            dump.u32(0); // exception_flags
+            dump.u64(0); // exception_record
+            dump.u64(crash_reason.address);
+            dump.u32(0); // number_parameters
+            dump.u32(0); // __align
+            dump.zeros(15 * 8); // exception_information
+            dump.location(context);
+            directory.push(dump.stream(md::MINIDUMP_STREAM_TYPE::ExceptionStream, start));
+        }
+
+        // Finally, go back and fill in the header and stream directory.
+        let mut buf = dump.buf;
+        let mut header = Vec::with_capacity((header_size + directory_size) as usize);
+        header.extend_from_slice(&md::MINIDUMP_SIGNATURE.to_le_bytes());
+        header.extend_from_slice(&md::MINIDUMP_VERSION.to_le_bytes());
+        header.extend_from_slice(&stream_count.to_le_bytes());
+        header.extend_from_slice(&header_size.to_le_bytes());
+        header.extend_from_slice(&0u32.to_le_bytes()); // checksum
+        header.extend_from_slice(&0u32.to_le_bytes()); // time_date_stamp
+        header.extend_from_slice(&0u64.to_le_bytes()); // flags
+        for (stream_type, (size, rva)) in directory {
+            header.extend_from_slice(&(stream_type as u32).to_le_bytes());
+            header.extend_from_slice(&size.to_le_bytes());
+            header.extend_from_slice(&rva.to_le_bytes());
+        }
+        buf[..header.len()].copy_from_slice(&header);
+        Ok(buf)
+    }
+
+    /// Convert this microdump into an equivalent in-memory `Minidump`.
+    ///
+    /// See [`Microdump::to_minidump_bytes`] for what the minidump contains.
+    pub fn to_minidump<'a>(&self) -> Result<Minidump<'a, Vec<u8>>, MicrodumpError> {
+        Ok(Minidump::read(self.to_minidump_bytes()?)?)
+    }
+}
+
+impl<'a> Minidump<'a, Vec<u8>> {
+    /// Read a `Minidump` from the text of a Breakpad microdump.
+    ///
+    /// See [the `microdump` module](crate::microdump) for details.
+    pub fn from_microdump(text: &str) -> Result<Minidump<'a, Vec<u8>>, MicrodumpError> {
+        Microdump::parse(text)?.to_minidump()
+    }
+}
+
+/// A minimal little-endian minidump writer, just enough to describe a microdump.
+#[derive(Default)]
+struct DumpWriter {
+    buf: Vec<u8>,
+}
+
+/// The `(data_size, rva)` of something written with a `DumpWriter`.
+type Location = (u32, u32);
+
+impl DumpWriter {
+    fn pos(&self) -> u32 {
+        self.buf.len() as u32
+    }
+
+    fn u8(&mut self, val: u8) {
+        self.buf.push(val);
+    }
+
+    fn u16(&mut self, val: u16) {
+        self.buf.extend_from_slice(&val.to_le_bytes());
+    }
+
+    fn u32(&mut self, val: u32) {
+        self.buf.extend_from_slice(&val.to_le_bytes());
+    }
+
+    fn u64(&mut self, val: u64) {
+        self.buf.extend_from_slice(&val.to_le_bytes());
+    }
+
+    fn zeros(&mut self, len: usize) {
+        self.buf.resize(self.buf.len() + len, 0);
+    }
+
+    fn location(&mut self, (size, rva): Location) {
+        self.u32(size);
+        self.u32(rva);
+    }
+
+    /// Write the `(data_size, rva)` of a stream that started at `start` for the directory.
+    fn stream(
+        &mut self,
+        stream_type: md::MINIDUMP_STREAM_TYPE,
+        start: u32,
+    ) -> (md::MINIDUMP_STREAM_TYPE, Location) {
+        (stream_type, (self.pos() - start, start))
+    }
+
+    fn bytes(&mut self, bytes: &[u8]) -> Location {
+        let rva = self.pos();
+        self.buf.extend_from_slice(bytes);
+        (bytes.len() as u32, rva)
+    }
+
+    /// Write a `MINIDUMP_STRING` and return its rva.
+    fn string(&mut self, s: &str) -> u32 {
+        let rva = self.pos();
+        let units = s.encode_utf16().collect::<Vec<_>>();
+        self.u32(units.len() as u32 * 2);
+        for unit in units {
+            self.u16(unit);
+        }
+        self.u16(0);
+        rva
+    }
+
+    /// Write a PDB 7.0 CodeView record for `module`.
+    ///
+    /// Linux minidumps have historically been written this way, with the build id
+    /// standing in for the GUID, and that's what Breakpad's debug identifiers
+    /// are derived from.
+    fn codeview(&mut self, module: &MicrodumpModule) -> Location {
+        let id = &module.debug_id;
+        let guid = id.get(..32).and_then(parse_hex_bytes);
+        let age = id.get(32..).and_then(parse_hex::<u32>);
+        let (guid, age) = match (guid, age) {
+            (Some(guid), Some(age)) => (guid, age),
+            _ => return (0, 0),
+        };
+        let rva = self.pos();
+        self.u32(md::CvSignature::Pdb70 as u32);
+        // The GUID's first three fields are integers, but they're printed in big-endian order.
+        self.u32(u32::from_be_bytes([guid[0], guid[1], guid[2], guid[3]]));
+        self.u16(u16::from_be_bytes([guid[4], guid[5]]));
+        self.u16(u16::from_be_bytes([guid[6], guid[7]]));
+        self.buf.extend_from_slice(&guid[8..]);
+        self.u32(age);
+        self.buf.extend_from_slice(module.filename.as_bytes());
+        self.u8(0);
+        (self.pos() - rva, rva)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::*;
+
+    fn hex(bytes: &[u8]) -> String {
+        bytes.iter().map(|b| format!("{:02X}", b)).collect()
+    }
+
+    fn arm_context_hex(pc: u32, sp: u32) -> String {
+        let mut context = vec![0u8; md::CONTEXT_ARM::size_with(&LE)];
+        let flags = md::ContextFlagsCpu::CONTEXT_ARM.bits() | 0x3;
+        context[0..4].copy_from_slice(&flags.to_le_bytes());
+        // iregs[13] is sp, iregs[15] is pc.
+        context[4 + 13 * 4..4 + 14 * 4].copy_from_slice(&sp.to_le_bytes());
+        context[4 + 15 * 4..4 + 16 * 4].copy_from_slice(&pc.to_le_bytes());
+        hex(&context)
+    }
+
+    fn microdump_text() -> String {
+        let lines = [
+            MICRODUMP_BEGIN.to_owned(),
+            "V WebView:42.0.2311.136".to_owned(),
+            "O A arm 04 armv7l 3.4.0-perf-g4d6e88e #1 SMP PREEMPT Mon Mar 30 19:09:30 2015"
+                .to_owned(),
+            "R b SIGSEGV 0x00000000".to_owned(),
+            "G OpenGL ES 3.0|Qualcomm|Adreno (TM) 330".to_owned(),
+            "S 0 A48BD840 A48BD000 00000010".to_owned(),
+            "S A48BD000 0102030405060708".to_owned(),
+            // Leave a gap which should be filled with zeros.
+            "S A48BD00C 090A0B0C".to_owned(),
+            format!("C {}", arm_context_hex(0xb6f0f100, 0xa48bd000)),
+            "M B6F0F000 00000000 00013000 DE8A6AA5D0A8E4F95A5F5DB6A2E20E1A0 /system/lib/libfoo.so"
+                .to_owned(),
+            "M B6F30000 00001000 00002000 00000000000000000000000000000000 libbar.so".to_owned(),
+            MICRODUMP_END.to_owned(),
+        ];
+        lines
+            .iter()
+            .map(|line| format!("W/google-breakpad(26491): {}\n", line))
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_microdump() {
+        // Other logging should be ignored, even in the middle of the microdump.
+        let mut text = String::from("I/chromium(26491): hello\n");
+        for line in microdump_text().lines() {
+            text.push_str(line);
+            text.push('\n');
+            text.push_str("I/chromium(26491): S 0 interleaved\n");
+        }
+        let microdump = Microdump::parse(&text).unwrap();
+
+        assert_eq!(microdump.product.as_deref(), Some("WebView:42.0.2311.136"));
+        assert_eq!(microdump.os, Os::Android);
+        assert_eq!(microdump.arch, "arm");
+        assert_eq!(microdump.cpu_count, 4);
+        assert_eq!(microdump.hw_arch, "armv7l");
+        assert_eq!(
+            microdump.os_version,
+            "3.4.0-perf-g4d6e88e #1 SMP PREEMPT Mon Mar 30 19:09:30 2015"
+        );
+        assert_eq!(
+            microdump.gpu.as_deref(),
+            Some("OpenGL ES 3.0|Qualcomm|Adreno (TM) 330")
+        );
+        assert_eq!(
+            microdump.crash_reason,
+            Some(MicrodumpCrashReason {
+                signal: 11,
+                name: "SIGSEGV".to_owned(),
+                address: 0,
+            })
+        );
+        assert_eq!(microdump.stack_base, 0xa48bd000);
+        assert_eq!(
+            microdump.stack,
+            [1, 2, 3, 4, 5, 6, 7, 8, 0, 0, 0, 0, 9, 10, 11, 12]
+        );
+        assert_eq!(microdump.modules.len(), 2);
+        assert_eq!(
+            microdump.modules[0],
+            MicrodumpModule {
+                base_address: 0xb6f0f000,
+                offset: 0,
+                size: 0x13000,
+                debug_id: "DE8A6AA5D0A8E4F95A5F5DB6A2E20E1A0".to_owned(),
+                filename: "/system/lib/libfoo.so".to_owned(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_microdump_to_minidump() {
+        let dump = Minidump::from_microdump(&microdump_text()).unwrap();
+
+        let system_info = dump.get_stream::<MinidumpSystemInfo>().unwrap();
+        assert_eq!(system_info.os, Os::Android);
+        assert_eq!(system_info.cpu, system_info::Cpu::Arm);
+        assert_eq!(system_info.raw.number_of_processors, 4);
+        assert_eq!(
+            (
+                system_info.raw.major_version,
+                system_info.raw.minor_version,
+                system_info.raw.build_number
+            ),
+            (3, 4, 0)
+        );
+        assert_eq!(
+            system_info.csd_version().as_deref(),
+            Some("3.4.0-perf-g4d6e88e #1 SMP PREEMPT Mon Mar 30 19:09:30 2015")
+        );
+
+        let threads = dump.get_stream::<MinidumpThreadList>().unwrap();
+        assert_eq!(threads.threads.len(), 1);
+        let thread = &threads.threads[0];
+        let context = thread.context(&system_info, None).unwrap();
+        assert_eq!(context.get_instruction_pointer(), 0xb6f0f100);
+        assert_eq!(context.get_stack_pointer(), 0xa48bd000);
+        let memory = dump.get_stream::<MinidumpMemoryList>().unwrap();
+        let stack = thread.stack_memory(&memory).unwrap();
+        assert_eq!(stack.base_address, 0xa48bd000);
+        assert_eq!(stack.size, 16);
+
+        let exception = dump.get_stream::<MinidumpException>().unwrap();
+        assert_eq!(exception.raw.exception_record.exception_code, 11);
+        assert_eq!(
+            exception.get_crash_address(system_info.os, system_info.cpu),
+            0
+        );
+
+        let modules = dump.get_stream::<MinidumpModuleList>().unwrap();
+        let libfoo = modules.module_at_address(0xb6f0f100).unwrap();
+        assert_eq!(libfoo.code_file(), "/system/lib/libfoo.so");
+        assert_eq!(libfoo.debug_file().unwrap(), "/system/lib/libfoo.so");
+        assert_eq!(
+            libfoo.debug_identifier().unwrap(),
+            "DE8A6AA5D0A8E4F95A5F5DB6A2E20E1A0"
+        );
+        assert_eq!(libfoo.size(), 0x13000);
+        let libbar = modules.module_at_address(0xb6f30000).unwrap();
+        assert_eq!(libbar.code_file(), "libbar.so");
+    }
+
+    #[test]
+    fn test_microdump_errors() {
+        assert_eq!(
+            Microdump::parse("nothing to see here").unwrap_err(),
+            MicrodumpError::MissingMicrodump
+        );
+
+        let text = microdump_text();
+        let no_context = text
+            .lines()
+            .filter(|line| !line.contains(": C "))
+            .collect::<Vec<_>>()
+            .join("\n");
+        assert_eq!(
+            Microdump::parse(&no_context).unwrap_err(),
+            MicrodumpError::MissingContext
+        );
+
+        let wrong_arch = text.replace("O A arm ", "O A arm64 ");
+        assert_eq!(
+            Microdump::parse(&wrong_arch).unwrap_err(),
+            MicrodumpError::ContextSizeMismatch {
+                arch: "arm64".to_owned(),
+                actual: md::CONTEXT_ARM::size_with(&LE),
+            }
+        );
+
+        let backwards_stack = text.replace("S A48BD00C", "S A48BD004");
+        assert_eq!(
+            Microdump::parse(&backwards_stack).unwrap_err(),
+            MicrodumpError::NonContiguousStack
+        );
+        // A huge gap between chunks shouldn't be filled in.
+        let huge_gap = text.replace("S A48BD00C", "S FFFFFF00");
+        assert_eq!(
+            Microdump::parse(&huge_gap).unwrap_err(),
+            MicrodumpError::StackTooLarge
+        );
+    }
+}
@@ -1,16 +1,15 @@
 // Copyright 2015 Ted Mielczarek. See the COPYRIGHT
 // file at the top-level directory of this distribution.
 
-use encoding::all::UTF_16LE;
-use encoding::{DecoderTrap, Encoding};
 use log::warn;
 use memmap2::Mmap;
 use num_traits::FromPrimitive;
 use scroll::ctx::{SizeWith, TryFromCtx};
-use scroll::{self, Pread, BE, LE};
+use scroll::{self, Pread, Pwrite, BE, LE};
 use std::borrow::Cow;
 use std::collections::BTreeMap;
 use std::collections::HashMap;
+use std::convert::TryInto;
 use std::fmt;
 use std::fs::File;
 use std::io;
@@ -21,6 +20,7 @@ use std::mem;
 use std::ops::Deref;
 use std::path::Path;
 use std::str;
+use std::sync::Arc;
 use std::time::{Duration, SystemTime};
 
 pub use crate::context::*;
@@ -63,6 +63,8 @@ where
     streams: HashMap<u32, (u32, md::MINIDUMP_DIRECTORY)>,
     /// The endianness of this minidump file.
     pub endian: scroll::Endian,
+    /// How many stream directory entries were cut off by the end of the file.
+    missing_directory_entries: u32,
     _phantom: PhantomData<&'a [u8]>,
 }
 
@@ -123,6 +125,8 @@ pub trait MinidumpStream<'a>: Sized {
 /// Currently an enum because there is no situation where you can have both,
 /// but this may change if the format evolves. Prefer using this type's methods
 /// over pattern matching.
+///
+/// Like the lists it wraps, clones share the regions, so cloning is cheap.
 #[derive(Debug, Clone)]
 pub enum UnifiedMemoryInfoList<'a> {
     Maps(MinidumpLinuxMaps<'a>),
@@ -144,10 +148,10 @@ pub enum UnifiedMemoryInfo<'a> {
 #[derive(Debug, Clone)]
 pub struct MinidumpLinuxMaps<'a> {
     /// The memory regions, in the order they were stored in the minidump.
-    regions: Vec<MinidumpLinuxMapInfo<'a>>,
+    regions: Arc<[MinidumpLinuxMapInfo<'a>]>,
     /// Map from address range to index in regions. Use
     /// [`MinidumpLinuxMaps::memory_info_at_address`].
-    regions_by_addr: RangeMap<u64, usize>,
+    regions_by_addr: Arc<RangeMap<u64, usize>>,
 }
 
 /// A memory mapping entry for the process we are analyzing.
@@ -155,7 +159,7 @@ pub struct MinidumpLinuxMaps<'a> {
 pub struct MinidumpLinuxMapInfo<'a> {
     /// The first address this metadata applies to
     pub base_address: u64,
-    /// The last address this metadata applies to
+    /// The end of the range this metadata applies to (exclusive)
     pub final_address: u64,
 
     /// The kind of mapping
@@ -204,10 +208,10 @@ pub enum MinidumpLinuxMapKind<'a> {
 #[derive(Debug, Clone)]
 pub struct MinidumpMemoryInfoList<'a> {
     /// The memory regions, in the order they were stored in the minidump.
-    regions: Vec<MinidumpMemoryInfo<'a>>,
+    regions: Arc<[MinidumpMemoryInfo<'a>]>,
     /// Map from address range to index in regions. Use
     /// [`MinidumpMemoryInfoList::memory_info_at_address`].
-    regions_by_addr: RangeMap<u64, usize>,
+    regions_by_addr: Arc<RangeMap<u64, usize>>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -226,6 +230,30 @@ pub struct MinidumpMemoryInfo<'a> {
     _phantom: PhantomData<&'a u8>,
 }
 
+/// A four-part version number, as stored in a `VS_FIXEDFILEINFO`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct MinidumpModuleVersion {
+    pub major: u16,
+    pub minor: u16,
+    pub build: u16,
+    pub revision: u16,
+}
+
+/// Version information for a module, parsed from its `VS_FIXEDFILEINFO`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MinidumpModuleVersionInfo {
+    /// The version of the module's file.
+    pub file_version: MinidumpModuleVersion,
+    /// The version of the product the module is distributed with.
+    pub product_version: MinidumpModuleVersion,
+    /// Attributes of the file, with any bits not in `file_flags_mask` cleared.
+    pub file_flags: md::FileFlags,
+    /// The operating system the file was designed for.
+    pub file_os: u32,
+    /// The general type of the file (application, dll, driver...).
+    pub file_type: u32,
+}
+
 /// CodeView data describes how to locate debug symbols
 #[derive(Debug, Clone)]
 pub enum CodeView {
@@ -245,7 +273,11 @@ pub struct MinidumpModule {
     /// The `MINIDUMP_MODULE` direct from the minidump file.
     pub raw: md::MINIDUMP_MODULE,
     /// The module name. This is stored separately in the minidump.
+    ///
+    /// This is lossily decoded from `raw_name`.
     pub name: String,
+    /// The module name as raw UTF-16 code units, exactly as stored in the minidump.
+    pub raw_name: MinidumpString,
     /// A `CodeView` record, if one is present.
     pub codeview_info: Option<CodeView>,
     /// A misc debug record, if one is present.
@@ -253,18 +285,64 @@ pub struct MinidumpModule {
 }
 
 /// A list of `MinidumpModule`s contained in a `Minidump`.
+///
+/// Clones share the modules, so cloning is cheap, and a list can be handed to
+/// as many concurrent analyses as need it.
 #[derive(Debug, Clone)]
 pub struct MinidumpModuleList {
     /// The modules, in the order they were stored in the minidump.
-    modules: Vec<MinidumpModule>,
+    ///
+    /// These are shared so the stack frames in each module can point at it
+    /// without a copy of their own.
+    modules: Arc<[Arc<MinidumpModule>]>,
     /// Map from address range to index in modules. Use `MinidumpModuleList::module_at_address`.
-    modules_by_addr: RangeMap<u64, usize>,
+    modules_by_addr: Arc<RangeMap<u64, usize>>,
 }
 
 /// A mapping of thread ids to their names.
 #[derive(Debug, Clone, Default)]
 pub struct MinidumpThreadNames {
-    names: HashMap<u32, String>,
+    names: HashMap<u32, MinidumpString>,
+}
+
+/// Extra information about the threads of the process, like how long they ran for.
+#[derive(Debug, Clone, Default)]
+pub struct MinidumpThreadInfoList {
+    /// The thread infos, in the order they were stored in the minidump.
+    thread_infos: Vec<MinidumpThreadInfo>,
+    /// Map from thread id to index in `thread_infos`. Use
+    /// [`MinidumpThreadInfoList::get_thread_info`].
+    thread_ids: HashMap<u32, usize>,
+}
+
+/// Extra information about a thread, from a [`MinidumpThreadInfoList`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MinidumpThreadInfo {
+    /// The `MINIDUMP_THREAD_INFO` direct from the minidump file.
+    pub raw: md::MINIDUMP_THREAD_INFO,
+    /// What went wrong while writing the thread to the minidump, if anything.
+    pub dump_flags: md::ThreadInfoFlags,
+}
+
+/// The handles the process had open (files, events, registry keys, and so on).
+#[derive(Debug, Clone, Default)]
+pub struct MinidumpHandleDataStream {
+    /// The handles, in the order they were stored in the minidump.
+    pub handles: Vec<MinidumpHandleDescriptor>,
+}
+
+/// A handle the process had open, from a [`MinidumpHandleDataStream`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MinidumpHandleDescriptor {
+    /// The `MINIDUMP_HANDLE_DESCRIPTOR` direct from the minidump file.
+    ///
+    /// For minidumps with `MINIDUMP_HANDLE_DESCRIPTOR_2`s, this is the part
+    /// they have in common.
+    pub raw: md::MINIDUMP_HANDLE_DESCRIPTOR,
+    /// The name of the type of the object, like "File" or "Event", if known.
+    pub type_name: Option<String>,
+    /// The name of the object, like the path of a file, if it has one.
+    pub object_name: Option<String>,
 }
 
 /// An executable or shared library that was once loaded into the process, but was unloaded
@@ -274,17 +352,21 @@ pub struct MinidumpUnloadedModule {
     /// The `MINIDUMP_UNLOADED_MODULE` direct from the minidump file.
     pub raw: md::MINIDUMP_UNLOADED_MODULE,
     /// The module name. This is stored separately in the minidump.
+    ///
+    /// This is lossily decoded from `raw_name`.
     pub name: String,
+    /// The module name as raw UTF-16 code units, exactly as stored in the minidump.
+    pub raw_name: MinidumpString,
 }
 
 /// A list of `MinidumpUnloadedModule`s contained in a `Minidump`.
 #[derive(Debug, Clone)]
 pub struct MinidumpUnloadedModuleList {
     /// The modules, in the order they were stored in the minidump.
-    modules: Vec<MinidumpUnloadedModule>,
+    modules: Arc<[MinidumpUnloadedModule]>,
     /// Map from address range to index in modules.
     /// Use `MinidumpUnloadedModuleList::modules_at_address`.
-    modules_by_addr: Vec<(Range<u64>, usize)>,
+    modules_by_addr: Arc<[(Range<u64>, usize)]>,
 }
 
 /// The state of a thread from the process when the minidump was written.
@@ -328,10 +410,13 @@ pub struct MinidumpSystemInfo {
 }
 
 /// A region of memory from the process that wrote the minidump.
+///
+/// This is generic over the kind of descriptor the region was read from.
+/// You will usually want [`MinidumpMemory`] or [`MinidumpMemory64`].
 #[derive(Clone, Debug)]
-pub struct MinidumpMemory<'a> {
-    /// The raw `MINIDUMP_MEMORY_DESCRIPTOR` from the minidump.
-    pub desc: md::MINIDUMP_MEMORY_DESCRIPTOR,
+pub struct MinidumpMemoryBase<'a, Descriptor> {
+    /// The raw descriptor (e.g. `MINIDUMP_MEMORY_DESCRIPTOR`) from the minidump.
+    pub desc: Descriptor,
     /// The starting address of this range of memory.
     pub base_address: u64,
     /// The length of this range of memory.
@@ -340,6 +425,12 @@ pub struct MinidumpMemory<'a> {
     pub bytes: &'a [u8],
 }
 
+/// A region of memory from a [`MinidumpMemoryList`].
+pub type MinidumpMemory<'a> = MinidumpMemoryBase<'a, md::MINIDUMP_MEMORY_DESCRIPTOR>;
+
+/// A region of memory from a [`MinidumpMemory64List`] (full-memory minidumps).
+pub type MinidumpMemory64<'a> = MinidumpMemoryBase<'a, md::MINIDUMP_MEMORY_DESCRIPTOR64>;
+
 #[allow(clippy::large_enum_variant)]
 #[derive(Debug)]
 pub enum RawMacCrashInfo {
@@ -430,6 +521,7 @@ pub enum CrashReason {
     MacBadInstructionArm(md::ExceptionCodeMacBadInstructionArmType),
     MacBadInstructionPpc(md::ExceptionCodeMacBadInstructionPpcType),
     MacBadInstructionX86(md::ExceptionCodeMacBadInstructionX86Type),
+    MacArithmeticArm(md::ExceptionCodeMacArithmeticArmType),
     MacArithmeticPpc(md::ExceptionCodeMacArithmeticPpcType),
     MacArithmeticX86(md::ExceptionCodeMacArithmeticX86Type),
     MacSoftware(md::ExceptionCodeMacSoftwareType),
@@ -494,12 +586,52 @@ pub struct MinidumpException<'a> {
 }
 
 /// A list of memory regions included in a minidump.
-#[derive(Debug)]
-pub struct MinidumpMemoryList<'a> {
+///
+/// This is generic over the kind of descriptor the regions were read from.
+/// You will usually want [`MinidumpMemoryList`] or [`MinidumpMemory64List`].
+///
+/// Some minidump writers emit overlapping or duplicate memory descriptors. When
+/// looking up an address, **earlier descriptors take precedence**: a region only
+/// serves the addresses that aren't covered by any region that comes before it in
+/// the minidump. A region that is entirely covered by earlier regions is never
+/// returned by [`memory_at_address`][MinidumpMemoryListBase::memory_at_address],
+/// but is still available through [`iter`][MinidumpMemoryListBase::iter].
+///
+/// Clones share the regions, so cloning is cheap.
+#[derive(Debug, Clone)]
+pub struct MinidumpMemoryListBase<'a, Descriptor> {
     /// The memory regions, in the order they were stored in the minidump.
-    regions: Vec<MinidumpMemory<'a>>,
-    /// Map from address range to index in regions. Use `MinidumpMemoryList::memory_at_address`.
-    regions_by_addr: RangeMap<u64, usize>,
+    regions: Arc<[MinidumpMemoryBase<'a, Descriptor>]>,
+    /// Map from address range to index in regions. Use `MinidumpMemoryListBase::memory_at_address`.
+    regions_by_addr: Arc<RangeMap<u64, usize>>,
+}
+
+/// A list of memory regions included in a minidump (the `MemoryListStream`).
+///
+/// This is usually the stacks of the threads and a bit of memory around
+/// interesting addresses.
+pub type MinidumpMemoryList<'a> = MinidumpMemoryListBase<'a, md::MINIDUMP_MEMORY_DESCRIPTOR>;
+
+/// A list of memory regions included in a full-memory minidump (the `Memory64ListStream`).
+pub type MinidumpMemory64List<'a> = MinidumpMemoryListBase<'a, md::MINIDUMP_MEMORY_DESCRIPTOR64>;
+
+/// Provides a unified interface for getting the memory contents of the process at the
+/// time of the crash.
+///
+/// Currently this is one of [`MinidumpMemoryList`], found in most minidumps, or
+/// [`MinidumpMemory64List`], found in full-memory minidumps. Prefer using this type's
+/// methods over pattern matching.
+#[derive(Debug, Clone)]
+pub enum UnifiedMemoryList<'a> {
+    Memory(MinidumpMemoryList<'a>),
+    Memory64(MinidumpMemory64List<'a>),
+}
+
+/// A region of memory from a [`UnifiedMemoryList`].
+#[derive(Debug, Clone, Copy)]
+pub enum UnifiedMemory<'a, 'mdmp> {
+    Memory(&'a MinidumpMemory<'mdmp>),
+    Memory64(&'a MinidumpMemory64<'mdmp>),
 }
 
 /// Information about an assertion that caused a crash.
@@ -597,16 +729,27 @@ fn location_slice<'a>(
 
 /// Read a u32 length-prefixed UTF-16 string from `bytes` at `offset`.
 fn read_string_utf16(offset: &mut usize, bytes: &[u8], endian: scroll::Endian) -> Option<String> {
+    read_minidump_string(offset, bytes, endian)?.to_string_checked()
+}
+
+/// Read a `MINIDUMP_STRING` without decoding it, preserving invalid UTF-16.
+fn read_minidump_string(
+    offset: &mut usize,
+    bytes: &[u8],
+    endian: scroll::Endian,
+) -> Option<MinidumpString> {
     let u: u32 = bytes.gread_with(offset, endian).ok()?;
     let size = u as usize;
     if size % 2 != 0 || (*offset + size) > bytes.len() {
         return None;
     }
-    let s = UTF_16LE
-        .decode(&bytes[*offset..*offset + size], DecoderTrap::Strict)
-        .ok()?;
+    // Like the rest of the minidump's strings, these are always little-endian.
+    let units = bytes[*offset..*offset + size]
+        .chunks_exact(2)
+        .map(|c| u16::from_le_bytes([c[0], c[1]]))
+        .collect();
     *offset += size;
-    Some(s)
+    Some(MinidumpString::from_utf16(units))
 }
 
 #[inline]
@@ -712,6 +855,7 @@ impl MinidumpModule {
                 ..md::MINIDUMP_MODULE::default()
             },
             name: String::from(name),
+            raw_name: MinidumpString::from(name),
             codeview_info: None,
             misc_info: None,
         }
@@ -725,8 +869,9 @@ impl MinidumpModule {
         endian: scroll::Endian,
     ) -> Result<MinidumpModule, Error> {
         let mut offset = raw.module_name_rva as usize;
-        let name =
-            read_string_utf16(&mut offset, bytes, endian).ok_or(Error::CodeViewReadFailure)?;
+        let raw_name =
+            read_minidump_string(&mut offset, bytes, endian).ok_or(Error::CodeViewReadFailure)?;
+        let name = raw_name.to_string_lossy();
         let codeview_info = if raw.cv_record.data_size == 0 {
             None
         } else {
@@ -735,6 +880,7 @@ impl MinidumpModule {
         Ok(MinidumpModule {
             raw,
             name,
+            raw_name,
             codeview_info,
             misc_info: None,
         })
@@ -895,6 +1041,178 @@ impl MinidumpModule {
             self.base_address().checked_add(self.size())? - 1,
         ))
     }
+
+    /// The version information of this module, if the minidump contains a valid `VS_FIXEDFILEINFO`.
+    pub fn version_info(&self) -> Option<MinidumpModuleVersionInfo> {
+        let info = &self.raw.version_info;
+        if info.signature != md::VS_FFI_SIGNATURE || info.struct_version != md::VS_FFI_STRUCVERSION
+        {
+            return None;
+        }
+        Some(MinidumpModuleVersionInfo {
+            file_version: MinidumpModuleVersion::from_parts(
+                info.file_version_hi,
+                info.file_version_lo,
+            ),
+            product_version: MinidumpModuleVersion::from_parts(
+                info.product_version_hi,
+                info.product_version_lo,
+            ),
+            file_flags: md::FileFlags::from_bits_truncate(info.file_flags & info.file_flags_mask),
+            file_os: info.file_os,
+            file_type: info.file_type,
+        })
+    }
+
+    /// Reconstruct the on-disk PE image of this module from `memory`.
+    ///
+    /// This is mostly useful with full-memory dumps, where the module's pages
+    /// are all present in the memory list. It can be used to recover modules
+    /// that were injected or unpacked at runtime and don't exist on disk.
+    ///
+    /// The loader has already applied relocations to the mapped image, so the
+    /// returned image is "realigned": every section's raw data is placed at its
+    /// virtual address, and the PE header is fixed up to match (`ImageBase` is
+    /// set to the address the module was loaded at, `FileAlignment` is set to
+    /// `SectionAlignment`, and each section's `PointerToRawData`/`SizeOfRawData`
+    /// point at its in-memory contents). Pages of the module that aren't
+    /// present in `memory` are zero-filled.
+    ///
+    /// Returns [`Error::MemoryReadFailure`] if the start of the module isn't in
+    /// `memory`, and [`Error::DataError`] if it doesn't contain valid PE headers.
+    pub fn image_from_memory(&self, memory: &UnifiedMemoryList) -> Result<Vec<u8>, Error> {
+        let base = self.base_address();
+        if memory.memory_at_address(base).is_none() {
+            return Err(Error::MemoryReadFailure);
+        }
+        let mut image = self.mapped_image_from_memory(memory)?;
+        fix_pe_headers(&mut image, base).ok_or(Error::DataError)?;
+        Ok(image)
+    }
+
+    /// Copy this module's pages out of `memory`, exactly as they were mapped.
+    ///
+    /// Unlike [`MinidumpModule::image_from_memory`], this doesn't touch the
+    /// contents, so it works for any kind of module. Pages of the module that
+    /// aren't present in `memory` are zero-filled.
+    ///
+    /// Returns [`Error::MemoryReadFailure`] if none of the module is in `memory`,
+    /// and [`Error::DataError`] if the module is implausibly large (over 1GiB).
+    pub fn mapped_image_from_memory(&self, memory: &UnifiedMemoryList) -> Result<Vec<u8>, Error> {
+        let base = self.base_address();
+        let range = self.memory_range().ok_or(Error::MemoryReadFailure)?;
+        // The size comes straight from the dump, so don't trust it with an
+        // allocation of any size.
+        if self.size() > MAX_MAPPED_IMAGE_SIZE {
+            return Err(Error::DataError);
+        }
+
+        // Copy the mapped image out of every region that overlaps it.
+        let mut image = vec![0u8; self.size() as usize];
+        let mut found = false;
+        for region in memory.by_addr() {
+            let region_range = match region.memory_range() {
+                Some(r) => r,
+                None => continue,
+            };
+            let start = region_range.start.max(range.start);
+            let end = region_range.end.min(range.end);
+            if start > end {
+                continue;
+            }
+            // A region's bytes can be shorter than its size says (if it was
+            // put together by hand), so only copy the bytes it has.
+            let bytes = region.bytes();
+            let src = (start - region.base_address()) as usize;
+            if src >= bytes.len() {
+                continue;
+            }
+            let dst = (start - base) as usize;
+            let len = ((end - start + 1) as usize).min(bytes.len() - src);
+            image[dst..dst + len].copy_from_slice(&bytes[src..src + len]);
+            found = true;
+        }
+
+        if !found {
+            return Err(Error::MemoryReadFailure);
+        }
+        Ok(image)
+    }
+}
+
+/// The largest module [`MinidumpModule::mapped_image_from_memory`] will copy
+/// out. Real modules are a few hundred megabytes at most.
+const MAX_MAPPED_IMAGE_SIZE: u64 = 1024 * 1024 * 1024;
+
+// Offsets into the PE headers, as documented in the PE format specification.
+const IMAGE_DOS_SIGNATURE: u16 = 0x5a4d; // "MZ"
+const IMAGE_NT_SIGNATURE: u32 = 0x0000_4550; // "PE\0\0"
+const IMAGE_NT_OPTIONAL_HDR32_MAGIC: u16 = 0x10b;
+const IMAGE_NT_OPTIONAL_HDR64_MAGIC: u16 = 0x20b;
+const DOS_HEADER_LFANEW_OFFSET: usize = 0x3c;
+const FILE_HEADER_SIZE: usize = 20;
+const SECTION_HEADER_SIZE: usize = 40;
+
+/// Rewrite the PE headers of a mapped `image` loaded at `base` so that it can
+/// be used as an on-disk image with sections laid out at their virtual addresses.
+fn fix_pe_headers(image: &mut [u8], base: u64) -> Option<()> {
+    if image.pread_with::<u16>(0, LE).ok()? != IMAGE_DOS_SIGNATURE {
+        return None;
+    }
+    let nt_offset = image.pread_with::<u32>(DOS_HEADER_LFANEW_OFFSET, LE).ok()? as usize;
+    if image.pread_with::<u32>(nt_offset, LE).ok()? != IMAGE_NT_SIGNATURE {
+        return None;
+    }
+
+    let file_header = nt_offset.checked_add(4)?;
+    let number_of_sections = image.pread_with::<u16>(file_header + 2, LE).ok()?;
+    let size_of_optional_header = image.pread_with::<u16>(file_header + 16, LE).ok()?;
+
+    let optional_header = file_header.checked_add(FILE_HEADER_SIZE)?;
+    match image.pread_with::<u16>(optional_header, LE).ok()? {
+        IMAGE_NT_OPTIONAL_HDR32_MAGIC => {
+            use std::convert::TryFrom;
+            let base = u32::try_from(base).ok()?;
+            image
+                .pwrite_with::<u32>(base, optional_header + 28, LE)
+                .ok()?;
+        }
+        IMAGE_NT_OPTIONAL_HDR64_MAGIC => {
+            image
+                .pwrite_with::<u64>(base, optional_header + 24, LE)
+                .ok()?;
+        }
+        _ => return None,
+    }
+    let section_alignment = image.pread_with::<u32>(optional_header + 32, LE).ok()?;
+    image
+        .pwrite_with::<u32>(section_alignment, optional_header + 36, LE)
+        .ok()?;
+
+    let image_size = image.len() as u64;
+    let mut section = optional_header.checked_add(size_of_optional_header as usize)?;
+    for _ in 0..number_of_sections {
+        let virtual_size = image.pread_with::<u32>(section + 8, LE).ok()?;
+        let virtual_address = image.pread_with::<u32>(section + 12, LE).ok()?;
+        let raw_size = image.pread_with::<u32>(section + 16, LE).ok()?;
+        if virtual_address as u64 > image_size {
+            return None;
+        }
+        // Some linkers leave VirtualSize zeroed, in which case the raw size is
+        // the best we have.
+        let size = if virtual_size != 0 {
+            virtual_size
+        } else {
+            raw_size
+        };
+        let size = (size as u64).min(image_size - virtual_address as u64) as u32;
+        image.pwrite_with::<u32>(size, section + 16, LE).ok()?;
+        image
+            .pwrite_with::<u32>(virtual_address, section + 20, LE)
+            .ok()?;
+        section += SECTION_HEADER_SIZE;
+    }
+    Some(())
 }
 
 impl Module for MinidumpModule {
@@ -962,23 +1280,33 @@ impl Module for MinidumpModule {
         }
     }
     fn version(&self) -> Option<Cow<'_, str>> {
-        if self.raw.version_info.signature == md::VS_FFI_SIGNATURE
-            && self.raw.version_info.struct_version == md::VS_FFI_STRUCVERSION
-        {
-            let ver = format!(
-                "{}.{}.{}.{}",
-                self.raw.version_info.file_version_hi >> 16,
-                self.raw.version_info.file_version_hi & 0xffff,
-                self.raw.version_info.file_version_lo >> 16,
-                self.raw.version_info.file_version_lo & 0xffff
-            );
-            Some(Cow::Owned(ver))
-        } else {
-            None
+        self.version_info()
+            .map(|info| Cow::Owned(info.file_version.to_string()))
+    }
+}
+
+impl MinidumpModuleVersion {
+    /// Split the high and low dwords of a `VS_FIXEDFILEINFO` version into its parts.
+    pub fn from_parts(hi: u32, lo: u32) -> MinidumpModuleVersion {
+        MinidumpModuleVersion {
+            major: (hi >> 16) as u16,
+            minor: (hi & 0xffff) as u16,
+            build: (lo >> 16) as u16,
+            revision: (lo & 0xffff) as u16,
         }
     }
 }
 
+impl fmt::Display for MinidumpModuleVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}.{}.{}.{}",
+            self.major, self.minor, self.build, self.revision
+        )
+    }
+}
+
 impl MinidumpUnloadedModule {
     /// Create a `MinidumpUnloadedModule` with some basic info.
     ///
@@ -991,6 +1319,7 @@ impl MinidumpUnloadedModule {
                 ..md::MINIDUMP_UNLOADED_MODULE::default()
             },
             name: String::from(name),
+            raw_name: MinidumpString::from(name),
         }
     }
 
@@ -1002,8 +1331,13 @@ impl MinidumpUnloadedModule {
         endian: scroll::Endian,
     ) -> Result<MinidumpUnloadedModule, Error> {
         let mut offset = raw.module_name_rva as usize;
-        let name = read_string_utf16(&mut offset, bytes, endian).ok_or(Error::DataError)?;
-        Ok(MinidumpUnloadedModule { raw, name })
+        let raw_name = read_minidump_string(&mut offset, bytes, endian).ok_or(Error::DataError)?;
+        let name = raw_name.to_string_lossy();
+        Ok(MinidumpUnloadedModule {
+            raw,
+            name,
+            raw_name,
+        })
     }
 
     /// Write a human-readable description of this `MinidumpModule` to `f`.
@@ -1219,7 +1553,7 @@ impl<'a> MinidumpStream<'a> for MinidumpThreadNames {
         for raw_name in raw_names {
             let mut offset = raw_name.thread_name_rva as usize;
             // Better to just drop unreadable names individually than the whole stream.
-            if let Some(name) = read_string_utf16(&mut offset, all, endian) {
+            if let Some(name) = read_minidump_string(&mut offset, all, endian) {
                 names.insert(raw_name.thread_id, name);
             } else {
                 warn!(
@@ -1233,10 +1567,16 @@ impl<'a> MinidumpStream<'a> for MinidumpThreadNames {
 }
 
 impl MinidumpThreadNames {
+    /// Get the name of the thread with `thread_id`, lossily decoded.
     pub fn get_name(&self, thread_id: u32) -> Option<Cow<str>> {
         self.names
             .get(&thread_id)
-            .map(|name| Cow::Borrowed(&**name))
+            .map(|name| Cow::Owned(name.to_string_lossy()))
+    }
+
+    /// Get the name of the thread with `thread_id` as raw UTF-16 code units.
+    pub fn get_raw_name(&self, thread_id: u32) -> Option<&MinidumpString> {
+        self.names.get(&thread_id)
     }
 
     /// Write a human-readable description of this `MinidumpThreadNames` to `f`.
@@ -1257,7 +1597,9 @@ MINIDUMP_THREAD_NAME
   thread_id = {:#x}
   name      = \"{}\"
 ",
-                i, thread_id, name
+                i,
+                thread_id,
+                name.display()
             )?;
         }
 
@@ -1265,12 +1607,224 @@ MINIDUMP_THREAD_NAME
     }
 }
 
+impl<'a> MinidumpStream<'a> for MinidumpThreadInfoList {
+    const STREAM_TYPE: MINIDUMP_STREAM_TYPE = MINIDUMP_STREAM_TYPE::ThreadInfoListStream;
+
+    fn read(bytes: &'a [u8], _all: &'a [u8], endian: scroll::Endian) -> Result<Self, Error> {
+        let mut offset = 0;
+        let raw_infos: Vec<md::MINIDUMP_THREAD_INFO> =
+            read_ex_stream_list(&mut offset, bytes, endian)?;
+        let thread_infos = raw_infos
+            .into_iter()
+            .map(|raw| MinidumpThreadInfo {
+                dump_flags: md::ThreadInfoFlags::from_bits_truncate(raw.dump_flags),
+                raw,
+            })
+            .collect();
+        Ok(MinidumpThreadInfoList::from_thread_infos(thread_infos))
+    }
+}
+
+impl MinidumpThreadInfoList {
+    /// Return an empty `MinidumpThreadInfoList`.
+    pub fn new() -> MinidumpThreadInfoList {
+        MinidumpThreadInfoList::default()
+    }
+
+    /// Create a `MinidumpThreadInfoList` from a list of `MinidumpThreadInfo`s.
+    pub fn from_thread_infos(thread_infos: Vec<MinidumpThreadInfo>) -> MinidumpThreadInfoList {
+        let thread_ids = thread_infos
+            .iter()
+            .enumerate()
+            .map(|(i, info)| (info.raw.thread_id, i))
+            .collect();
+        MinidumpThreadInfoList {
+            thread_infos,
+            thread_ids,
+        }
+    }
+
+    /// Get the info for the thread with `thread_id`, if there is any.
+    pub fn get_thread_info(&self, thread_id: u32) -> Option<&MinidumpThreadInfo> {
+        self.thread_ids
+            .get(&thread_id)
+            .map(|&index| &self.thread_infos[index])
+    }
+
+    /// Iterate over the thread infos in the order contained in the minidump.
+    pub fn iter(&self) -> impl Iterator<Item = &MinidumpThreadInfo> {
+        self.thread_infos.iter()
+    }
+
+    /// Write a human-readable description of this `MinidumpThreadInfoList` to `f`.
+    pub fn print<T: Write>(&self, f: &mut T) -> io::Result<()> {
+        write!(
+            f,
+            "MinidumpThreadInfoList
+  thread_count = {}
+
+",
+            self.thread_infos.len()
+        )?;
+        for (i, info) in self.thread_infos.iter().enumerate() {
+            writeln!(f, "thread_info[{}]", i)?;
+            info.print(f)?;
+        }
+        Ok(())
+    }
+}
+
+impl MinidumpThreadInfo {
+    /// When the thread was created, if known.
+    pub fn create_time(&self) -> Option<SystemTime> {
+        systemtime_from_filetime(self.raw.create_time)
+    }
+
+    /// When the thread exited, if it had.
+    pub fn exit_time(&self) -> Option<SystemTime> {
+        systemtime_from_filetime(self.raw.exit_time)
+    }
+
+    /// How long the thread spent running in kernel mode.
+    pub fn kernel_time(&self) -> Duration {
+        duration_from_filetime(self.raw.kernel_time)
+    }
+
+    /// How long the thread spent running in user mode.
+    pub fn user_time(&self) -> Duration {
+        duration_from_filetime(self.raw.user_time)
+    }
+
+    /// Write a human-readable description.
+    pub fn print<T: Write>(&self, f: &mut T) -> io::Result<()> {
+        write!(
+            f,
+            "MINIDUMP_THREAD_INFO
+  thread_id     = {:#x}
+  dump_flags    = {:#x}
+  dump_error    = {:#x}
+  exit_status   = {:#x}
+  create_time   = {:#x}
+  exit_time     = {:#x}
+  kernel_time   = {:#x}
+  user_time     = {:#x}
+  start_address = {:#x}
+  affinity      = {:#x}
+",
+            self.raw.thread_id,
+            self.dump_flags,
+            self.raw.dump_error,
+            self.raw.exit_status,
+            self.raw.create_time,
+            self.raw.exit_time,
+            self.raw.kernel_time,
+            self.raw.user_time,
+            self.raw.start_address,
+            self.raw.affinity,
+        )?;
+        writeln!(f)
+    }
+}
+
+impl<'a> MinidumpStream<'a> for MinidumpHandleDataStream {
+    const STREAM_TYPE: MINIDUMP_STREAM_TYPE = MINIDUMP_STREAM_TYPE::HandleDataStream;
+
+    fn read(bytes: &'a [u8], all: &'a [u8], endian: scroll::Endian) -> Result<Self, Error> {
+        let header: md::MINIDUMP_HANDLE_DATA_STREAM = bytes
+            .pread_with(0, endian)
+            .or(Err(Error::StreamReadFailure))?;
+        // Newer minidumps have `MINIDUMP_HANDLE_DESCRIPTOR_2`s, which are
+        // `MINIDUMP_HANDLE_DESCRIPTOR`s with extra fields at the end.
+        let size_of_descriptor = header.size_of_descriptor as usize;
+        if size_of_descriptor < md::MINIDUMP_HANDLE_DESCRIPTOR::size_with(&endian) {
+            return Err(Error::StreamReadFailure);
+        }
+        let (number_of_descriptors, _) = ensure_count_in_bound(
+            bytes,
+            header.number_of_descriptors as usize,
+            size_of_descriptor,
+            header.size_of_header as usize,
+        )?;
+
+        let read_name = |rva: md::RVA| {
+            if rva == 0 {
+                return None;
+            }
+            let mut offset = rva as usize;
+            read_minidump_string(&mut offset, all, endian).map(|name| name.to_string_lossy())
+        };
+        let mut handles = Vec::with_capacity(number_of_descriptors);
+        for i in 0..number_of_descriptors {
+            let offset = header.size_of_header as usize + i * size_of_descriptor;
+            let raw: md::MINIDUMP_HANDLE_DESCRIPTOR = bytes
+                .pread_with(offset, endian)
+                .or(Err(Error::StreamReadFailure))?;
+            handles.push(MinidumpHandleDescriptor {
+                type_name: read_name(raw.type_name_rva),
+                object_name: read_name(raw.object_name_rva),
+                raw,
+            });
+        }
+        Ok(MinidumpHandleDataStream { handles })
+    }
+}
+
+impl MinidumpHandleDataStream {
+    /// Iterate over the handles in the order contained in the minidump.
+    pub fn iter(&self) -> impl Iterator<Item = &MinidumpHandleDescriptor> {
+        self.handles.iter()
+    }
+
+    /// Write a human-readable description of this `MinidumpHandleDataStream` to `f`.
+    pub fn print<T: Write>(&self, f: &mut T) -> io::Result<()> {
+        write!(
+            f,
+            "MinidumpHandleDataStream
+  handle_count = {}
+
+",
+            self.handles.len()
+        )?;
+        for (i, handle) in self.handles.iter().enumerate() {
+            writeln!(f, "handle[{}]", i)?;
+            handle.print(f)?;
+        }
+        Ok(())
+    }
+}
+
+impl MinidumpHandleDescriptor {
+    /// Write a human-readable description.
+    pub fn print<T: Write>(&self, f: &mut T) -> io::Result<()> {
+        write!(
+            f,
+            "MINIDUMP_HANDLE_DESCRIPTOR
+  handle         = {:#x}
+  type_name      = \"{}\"
+  object_name    = \"{}\"
+  attributes     = {:#x}
+  granted_access = {:#x}
+  handle_count   = {}
+  pointer_count  = {}
+",
+            self.raw.handle,
+            self.type_name.as_deref().unwrap_or(""),
+            self.object_name.as_deref().unwrap_or(""),
+            self.raw.attributes,
+            self.raw.granted_access,
+            self.raw.handle_count,
+            self.raw.pointer_count,
+        )?;
+        writeln!(f)
+    }
+}
+
 impl MinidumpModuleList {
     /// Return an empty `MinidumpModuleList`.
     pub fn new() -> MinidumpModuleList {
         MinidumpModuleList {
-            modules: vec![],
-            modules_by_addr: RangeMap::new(),
+            modules: Arc::new([]),
+            modules_by_addr: Arc::new(RangeMap::new()),
         }
     }
     /// Create a `MinidumpModuleList` from a list of `MinidumpModule`s.
@@ -1281,8 +1835,8 @@ impl MinidumpModuleList {
             .map(|(i, module)| (module.memory_range(), i))
             .into_rangemap_safe();
         MinidumpModuleList {
-            modules,
-            modules_by_addr,
+            modules: modules.into_iter().map(Arc::new).collect(),
+            modules_by_addr: Arc::new(modules_by_addr),
         }
     }
 
@@ -1290,15 +1844,19 @@ impl MinidumpModuleList {
     pub fn main_module(&self) -> Option<&MinidumpModule> {
         // The main code module is the first one present in a minidump file's
         // MINIDUMP_MODULEList.
-        if !self.modules.is_empty() {
-            Some(&self.modules[0])
-        } else {
-            None
-        }
+        self.modules.first().map(|module| &**module)
     }
 
     /// Return a `MinidumpModule` whose address range covers `address`.
     pub fn module_at_address(&self, address: u64) -> Option<&MinidumpModule> {
+        self.shared_module_at_address(address)
+            .map(|module| &**module)
+    }
+
+    /// Like [`module_at_address`][Self::module_at_address], but returns the
+    /// module as it's shared by the list, for holding on to it without
+    /// copying it.
+    pub fn shared_module_at_address(&self, address: u64) -> Option<&Arc<MinidumpModule>> {
         self.modules_by_addr
             .get(address)
             .map(|&index| &self.modules[index])
@@ -1306,14 +1864,14 @@ impl MinidumpModuleList {
 
     /// Iterate over the modules in arbitrary order.
     pub fn iter(&self) -> impl Iterator<Item = &MinidumpModule> {
-        self.modules.iter()
+        self.modules.iter().map(|module| &**module)
     }
 
     /// Iterate over the modules in order by memory address.
     pub fn by_addr(&self) -> impl DoubleEndedIterator<Item = &MinidumpModule> {
         self.modules_by_addr
             .ranges_values()
-            .map(move |&(_, index)| &self.modules[index])
+            .map(move |&(_, index)| &*self.modules[index])
     }
 
     /// Write a human-readable description of this `MinidumpModuleList` to `f`.
@@ -1372,8 +1930,8 @@ impl MinidumpUnloadedModuleList {
     /// Return an empty `MinidumpModuleList`.
     pub fn new() -> MinidumpUnloadedModuleList {
         MinidumpUnloadedModuleList {
-            modules: vec![],
-            modules_by_addr: vec![],
+            modules: Arc::new([]),
+            modules_by_addr: Arc::new([]),
         }
     }
     /// Create a `MinidumpModuleList` from a list of `MinidumpModule`s.
@@ -1385,8 +1943,8 @@ impl MinidumpUnloadedModuleList {
         modules_by_addr.sort_by_key(|(range, _idx)| *range);
 
         MinidumpUnloadedModuleList {
-            modules,
-            modules_by_addr,
+            modules: modules.into(),
+            modules_by_addr: modules_by_addr.into(),
         }
     }
 
@@ -1472,6 +2030,7 @@ impl<'a> MinidumpStream<'a> for MinidumpUnloadedModuleList {
 }
 
 impl<'a> MinidumpMemory<'a> {
+    /// Read a `MinidumpMemory` described by `desc` from `data`.
     pub fn read(
         desc: &md::MINIDUMP_MEMORY_DESCRIPTOR,
         data: &'a [u8],
@@ -1492,26 +2051,6 @@ impl<'a> MinidumpMemory<'a> {
         })
     }
 
-    /// Get `mem::size_of::<T>()` bytes of memory at `addr` from this region.
-    ///
-    /// Return `None` if the requested address range falls out of the bounds
-    /// of this memory region.
-    pub fn get_memory_at_address<T>(&self, addr: u64) -> Option<T>
-    where
-        T: TryFromCtx<'a, scroll::Endian, [u8], Error = scroll::Error>,
-        T: SizeWith<scroll::Endian>,
-    {
-        let end = self.base_address.checked_add(self.size)?;
-
-        let in_range = |a: u64| a >= self.base_address && a < end;
-        let size = <T>::size_with(&LE);
-        if !in_range(addr) || !in_range(addr + size as u64 - 1) {
-            return None;
-        }
-        let start = (addr - self.base_address) as usize;
-        self.bytes.pread_with::<T>(start, LE).ok()
-    }
-
     /// Write a human-readable description of this `MinidumpMemory` to `f`.
     ///
     /// This is very verbose, it is the format used by `minidump_dump`.
@@ -1529,55 +2068,141 @@ Memory
         self.print_contents(f)?;
         writeln!(f)
     }
+}
 
-    /// Write the contents of this `MinidumpMemory` to `f` as a hex string.
-    pub fn print_contents<T: Write>(&self, f: &mut T) -> io::Result<()> {
-        write!(f, "0x")?;
-        for byte in self.bytes.iter() {
-            write!(f, "{:02x}", byte)?;
-        }
-        writeln!(f)?;
-        Ok(())
-    }
-
-    pub fn memory_range(&self) -> Option<Range<u64>> {
-        if self.size == 0 {
-            return None;
-        }
-        Some(Range::new(
-            self.base_address,
-            self.base_address.checked_add(self.size)? - 1,
-        ))
+impl<'a> MinidumpMemory64<'a> {
+    /// Write a human-readable description of this `MinidumpMemory64` to `f`.
+    ///
+    /// This is very verbose, it is the format used by `minidump_dump`.
+    pub fn print<T: Write>(&self, f: &mut T) -> io::Result<()> {
+        write!(
+            f,
+            "MINIDUMP_MEMORY_DESCRIPTOR64
+  start_of_memory_range = {:#x}
+  data_size             = {:#x}
+Memory
+",
+            self.desc.start_of_memory_range, self.desc.data_size,
+        )?;
+        self.print_contents(f)?;
+        writeln!(f)
     }
 }
 
-impl<'mdmp> MinidumpMemoryList<'mdmp> {
-    /// Return an empty `MinidumpMemoryList`.
-    pub fn new() -> MinidumpMemoryList<'mdmp> {
-        MinidumpMemoryList {
-            regions: vec![],
-            regions_by_addr: RangeMap::new(),
+impl<'a, Descriptor> MinidumpMemoryBase<'a, Descriptor> {
+    /// Get `mem::size_of::<T>()` bytes of memory at `addr` from this region.
+    ///
+    /// Return `None` if the requested address range falls out of the bounds
+    /// of this memory region.
+    pub fn get_memory_at_address<T>(&self, addr: u64) -> Option<T>
+    where
+        T: TryFromCtx<'a, scroll::Endian, [u8], Error = scroll::Error>,
+        T: SizeWith<scroll::Endian>,
+    {
+        let bytes = self.bytes_at_address(addr, <T>::size_with(&LE))?;
+        T::try_from_ctx(bytes, LE).ok().map(|(value, _)| value)
+    }
+
+    /// Get the `u32` at `addr` from this region, in the given byte order.
+    ///
+    /// This is equivalent to `get_memory_at_address::<u32>` (for little-endian),
+    /// but skips the generic parsing, as stack scanning does a lot of these.
+    pub fn get_u32_at_address(&self, addr: u64, endian: scroll::Endian) -> Option<u32> {
+        let bytes = self.bytes_at_address(addr, 4)?.try_into().ok()?;
+        Some(if endian.is_little() {
+            u32::from_le_bytes(bytes)
+        } else {
+            u32::from_be_bytes(bytes)
+        })
+    }
+
+    /// Get the `u64` at `addr` from this region, in the given byte order.
+    ///
+    /// See [`get_u32_at_address`][Self::get_u32_at_address].
+    pub fn get_u64_at_address(&self, addr: u64, endian: scroll::Endian) -> Option<u64> {
+        let bytes = self.bytes_at_address(addr, 8)?.try_into().ok()?;
+        Some(if endian.is_little() {
+            u64::from_le_bytes(bytes)
+        } else {
+            u64::from_be_bytes(bytes)
+        })
+    }
+
+    /// The `size` bytes at `addr`, if they're all in this region.
+    fn bytes_at_address(&self, addr: u64, size: usize) -> Option<&'a [u8]> {
+        let start = addr.checked_sub(self.base_address)?;
+        let end = start.checked_add(size as u64)?;
+        if end > self.size {
+            return None;
         }
+        self.bytes.get(start as usize..end as usize)
     }
 
-    /// Create a `MinidumpMemoryList` from a list of `MinidumpMemory`s.
-    pub fn from_regions(regions: Vec<MinidumpMemory<'mdmp>>) -> MinidumpMemoryList<'mdmp> {
-        let regions_by_addr = regions
-            .iter()
-            .enumerate()
-            .map(|(i, region)| (region.memory_range(), i))
-            .into_rangemap_safe();
-        MinidumpMemoryList {
-            regions,
-            regions_by_addr,
+    /// Write the contents of this memory region to `f` as a hex string.
+    pub fn print_contents<T: Write>(&self, f: &mut T) -> io::Result<()> {
+        write!(f, "0x")?;
+        for byte in self.bytes.iter() {
+            write!(f, "{:02x}", byte)?;
         }
+        writeln!(f)?;
+        Ok(())
     }
 
-    /// Return a `MinidumpMemory` containing memory at `address`, if one exists.
-    pub fn memory_at_address(&self, address: u64) -> Option<&MinidumpMemory<'mdmp>> {
-        self.regions_by_addr
-            .get(address)
-            .map(|&index| &self.regions[index])
+    pub fn memory_range(&self) -> Option<Range<u64>> {
+        if self.size == 0 {
+            return None;
+        }
+        Some(Range::new(
+            self.base_address,
+            self.base_address.checked_add(self.size)? - 1,
+        ))
+    }
+}
+
+impl<'mdmp, Descriptor> MinidumpMemoryListBase<'mdmp, Descriptor> {
+    /// Return an empty `MinidumpMemoryListBase`.
+    pub fn new() -> MinidumpMemoryListBase<'mdmp, Descriptor> {
+        MinidumpMemoryListBase {
+            regions: Arc::new([]),
+            regions_by_addr: Arc::new(RangeMap::new()),
+        }
+    }
+
+    /// Create a `MinidumpMemoryListBase` from a list of `MinidumpMemoryBase`s.
+    ///
+    /// Overlapping regions are resolved as described on [`MinidumpMemoryListBase`].
+    pub fn from_regions(
+        regions: Vec<MinidumpMemoryBase<'mdmp, Descriptor>>,
+    ) -> MinidumpMemoryListBase<'mdmp, Descriptor> {
+        let regions_by_addr = resolve_overlapping_ranges(
+            regions
+                .iter()
+                .enumerate()
+                .filter_map(|(i, region)| Some((region.memory_range()?, i))),
+        );
+        MinidumpMemoryListBase {
+            regions: regions.into(),
+            regions_by_addr: Arc::new(regions_by_addr),
+        }
+    }
+
+    /// Return a `MinidumpMemoryBase` containing memory at `address`, if one exists.
+    ///
+    /// If several regions contain `address`, the one that comes first in the minidump is returned.
+    pub fn memory_at_address(
+        &self,
+        address: u64,
+    ) -> Option<&MinidumpMemoryBase<'mdmp, Descriptor>> {
+        self.region_index_at_address(address)
+            .map(|index| &self.regions[index])
+    }
+
+    /// Return the index of the descriptor that serves `address`, if one exists.
+    ///
+    /// This is the index of the region in the minidump (and in [`iter`][Self::iter]),
+    /// which is useful for figuring out which of several overlapping descriptors was used.
+    pub fn region_index_at_address(&self, address: u64) -> Option<usize> {
+        self.regions_by_addr.get(address).copied()
     }
 
     /// Iterate over the memory regions in the order contained in the minidump.
@@ -1586,17 +2211,76 @@ impl<'mdmp> MinidumpMemoryList<'mdmp> {
     /// That is the lifetime of the item is bound to the lifetime of the iterator itself
     /// (`'slf`), while the slice inside [MinidumpMemory] pointing at the memory itself has
     /// the lifetime of the [Minidump] struct ('mdmp).
-    pub fn iter<'slf>(&'slf self) -> impl Iterator<Item = &'slf MinidumpMemory<'mdmp>> {
+    pub fn iter<'slf>(
+        &'slf self,
+    ) -> impl Iterator<Item = &'slf MinidumpMemoryBase<'mdmp, Descriptor>> {
         self.regions.iter()
     }
 
     /// Iterate over the memory regions in order by memory address.
-    pub fn by_addr<'slf>(&'slf self) -> impl Iterator<Item = &'slf MinidumpMemory<'mdmp>> {
-        self.regions_by_addr
+    ///
+    /// Regions which are entirely covered by earlier regions are skipped.
+    pub fn by_addr<'slf>(
+        &'slf self,
+    ) -> impl Iterator<Item = &'slf MinidumpMemoryBase<'mdmp, Descriptor>> {
+        let mut indices = self
+            .regions_by_addr
             .ranges_values()
-            .map(move |&(_, index)| &self.regions[index])
+            .map(|&(_, index)| index)
+            .collect::<Vec<_>>();
+        indices.sort_by_key(|&index| (self.regions[index].base_address, index));
+        indices.dedup();
+        indices.into_iter().map(move |index| &self.regions[index])
     }
+}
 
+/// Build an address map from possibly-overlapping `ranges`, giving earlier ranges precedence.
+///
+/// Each range only maps the addresses that aren't already covered by a range before it.
+fn resolve_overlapping_ranges(
+    ranges: impl Iterator<Item = (Range<u64>, usize)>,
+) -> RangeMap<u64, usize> {
+    // Start address -> (end address, value) of the non-overlapping pieces so far.
+    let mut covered = BTreeMap::<u64, (u64, usize)>::new();
+    for (range, val) in ranges {
+        let mut gaps = vec![];
+        let mut next = Some(range.start);
+        // A piece starting before this range may still cover the start of it.
+        if let Some((_, &(end, _))) = covered.range(..range.start).next_back() {
+            if end >= range.start {
+                next = end.checked_add(1);
+            }
+        }
+        if let Some(start) = next.filter(|&start| start <= range.end) {
+            for (&piece_start, &(piece_end, _)) in covered.range(start..=range.end) {
+                let cur = match next {
+                    Some(cur) => cur,
+                    None => break,
+                };
+                if piece_start > cur {
+                    gaps.push((cur, piece_start - 1));
+                }
+                next = piece_end.checked_add(1);
+            }
+        }
+        if let Some(cur) = next {
+            if cur <= range.end {
+                gaps.push((cur, range.end));
+            }
+        }
+        for (start, end) in gaps {
+            covered.insert(start, (end, val));
+        }
+    }
+    RangeMap::from_sorted_vec(
+        covered
+            .into_iter()
+            .map(|(start, (end, val))| (Range::new(start, end), val))
+            .collect(),
+    )
+}
+
+impl<'mdmp> MinidumpMemoryList<'mdmp> {
     /// Write a human-readable description of this `MinidumpMemoryList` to `f`.
     ///
     /// This is very verbose, it is the format used by `minidump_dump`.
@@ -1617,7 +2301,28 @@ impl<'mdmp> MinidumpMemoryList<'mdmp> {
     }
 }
 
-impl<'a> Default for MinidumpMemoryList<'a> {
+impl<'mdmp> MinidumpMemory64List<'mdmp> {
+    /// Write a human-readable description of this `MinidumpMemory64List` to `f`.
+    ///
+    /// This is very verbose, it is the format used by `minidump_dump`.
+    pub fn print<T: Write>(&self, f: &mut T) -> io::Result<()> {
+        write!(
+            f,
+            "MinidumpMemory64List
+  region_count = {}
+
+",
+            self.regions.len()
+        )?;
+        for (i, region) in self.regions.iter().enumerate() {
+            writeln!(f, "region[{}]", i)?;
+            region.print(f)?;
+        }
+        Ok(())
+    }
+}
+
+impl<'a, Descriptor> Default for MinidumpMemoryListBase<'a, Descriptor> {
     fn default() -> Self {
         Self::new()
     }
@@ -1648,6 +2353,279 @@ impl<'a> MinidumpStream<'a> for MinidumpMemoryList<'a> {
     }
 }
 
+impl<'a> MinidumpStream<'a> for MinidumpMemory64List<'a> {
+    const STREAM_TYPE: MINIDUMP_STREAM_TYPE = MINIDUMP_STREAM_TYPE::Memory64ListStream;
+
+    fn read(
+        bytes: &'a [u8],
+        all: &'a [u8],
+        endian: scroll::Endian,
+    ) -> Result<MinidumpMemory64List<'a>, Error> {
+        use std::convert::TryFrom;
+        let mut offset = 0;
+        let header: md::MINIDUMP_MEMORY64_LIST = bytes
+            .gread_with(&mut offset, endian)
+            .or(Err(Error::StreamReadFailure))?;
+        let size_of_entry = <md::MINIDUMP_MEMORY_DESCRIPTOR64>::size_with(&endian);
+        let expected_size = usize::try_from(header.number_of_memory_ranges)
+            .ok()
+            .and_then(|n| n.checked_mul(size_of_entry))
+            .and_then(|v| v.checked_add(offset))
+            .ok_or(Error::StreamReadFailure)?;
+        if bytes.len() < expected_size {
+            return Err(Error::StreamSizeMismatch {
+                expected: expected_size,
+                actual: bytes.len(),
+            });
+        }
+
+        // The contents of every region are stored back-to-back, starting at base_rva.
+        let mut rva = header.base_rva;
+        let mut regions = Vec::with_capacity(header.number_of_memory_ranges as usize);
+        for _ in 0..header.number_of_memory_ranges {
+            let desc: md::MINIDUMP_MEMORY_DESCRIPTOR64 = bytes
+                .gread_with(&mut offset, endian)
+                .or(Err(Error::StreamReadFailure))?;
+            let start = rva;
+            rva = rva
+                .checked_add(desc.data_size)
+                .ok_or(Error::StreamReadFailure)?;
+            let contents = usize::try_from(start)
+                .ok()
+                .zip(usize::try_from(rva).ok())
+                .and_then(|(start, end)| all.get(start..end));
+            if let Some(contents) = contents {
+                regions.push(MinidumpMemory64 {
+                    desc,
+                    base_address: desc.start_of_memory_range,
+                    size: desc.data_size,
+                    bytes: contents,
+                });
+            } else {
+                // Just skip over truncated entries and try to limp along.
+                continue;
+            }
+        }
+        Ok(MinidumpMemory64List::from_regions(regions))
+    }
+}
+
+impl<'a> Default for UnifiedMemoryList<'a> {
+    fn default() -> Self {
+        Self::Memory(MinidumpMemoryList::default())
+    }
+}
+
+impl<'mdmp> UnifiedMemoryList<'mdmp> {
+    /// Take two potential memory list sources and create an interface that unifies them.
+    ///
+    /// Full-memory minidumps will contain a [`MinidumpMemory64List`], which is
+    /// preferred if both are provided because it should contain everything.
+    pub fn new(
+        memory: Option<MinidumpMemoryList<'mdmp>>,
+        memory64: Option<MinidumpMemory64List<'mdmp>>,
+    ) -> Option<Self> {
+        match (memory, memory64) {
+            (_, Some(memory64)) => Some(Self::Memory64(memory64)),
+            (Some(memory), None) => Some(Self::Memory(memory)),
+            (None, None) => None,
+        }
+    }
+
+    /// Return a region containing memory at `address`, if one exists.
+    ///
+    /// If several regions contain `address`, the one that comes first in the minidump
+    /// is returned (see [`MinidumpMemoryListBase`] for details).
+    pub fn memory_at_address(&self, address: u64) -> Option<UnifiedMemory<'_, 'mdmp>> {
+        match self {
+            Self::Memory(memory) => memory.memory_at_address(address).map(UnifiedMemory::Memory),
+            Self::Memory64(memory64) => memory64
+                .memory_at_address(address)
+                .map(UnifiedMemory::Memory64),
+        }
+    }
+
+    /// Return the index of the descriptor that serves `address`, if one exists.
+    ///
+    /// See [`MinidumpMemoryListBase::region_index_at_address`].
+    pub fn region_index_at_address(&self, address: u64) -> Option<usize> {
+        match self {
+            Self::Memory(memory) => memory.region_index_at_address(address),
+            Self::Memory64(memory64) => memory64.region_index_at_address(address),
+        }
+    }
+
+    /// Iterate over the memory regions in the order contained in the minidump.
+    pub fn iter<'slf>(&'slf self) -> impl Iterator<Item = UnifiedMemory<'slf, 'mdmp>> {
+        let memory = self
+            .memory()
+            .into_iter()
+            .flat_map(|memory| memory.iter().map(UnifiedMemory::Memory));
+        let memory64 = self
+            .memory64()
+            .into_iter()
+            .flat_map(|memory64| memory64.iter().map(UnifiedMemory::Memory64));
+
+        memory.chain(memory64)
+    }
+
+    /// Iterate over the memory regions in order by memory address.
+    pub fn by_addr<'slf>(&'slf self) -> impl Iterator<Item = UnifiedMemory<'slf, 'mdmp>> {
+        let memory = self
+            .memory()
+            .into_iter()
+            .flat_map(|memory| memory.by_addr().map(UnifiedMemory::Memory));
+        let memory64 = self
+            .memory64()
+            .into_iter()
+            .flat_map(|memory64| memory64.by_addr().map(UnifiedMemory::Memory64));
+
+        memory.chain(memory64)
+    }
+
+    /// Find every address where `pattern` appears in the dump's memory, in address order.
+    ///
+    /// If `mask` is provided, only the bits set in the mask are compared, so e.g.
+    /// a mask byte of `0x00` is a wildcard. Matches that span two separate
+    /// regions are not found, even if the regions are adjacent. An empty
+    /// pattern never matches.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `mask` is not the same length as `pattern`.
+    pub fn find<'slf>(
+        &'slf self,
+        pattern: &'slf [u8],
+        mask: Option<&'slf [u8]>,
+    ) -> impl Iterator<Item = u64> + 'slf {
+        if let Some(mask) = mask {
+            assert_eq!(
+                mask.len(),
+                pattern.len(),
+                "mask must be the same length as pattern"
+            );
+        }
+        let matches = move |window: &[u8]| match mask {
+            Some(mask) => window
+                .iter()
+                .zip(pattern)
+                .zip(mask)
+                .all(|((byte, pat), mask)| byte & mask == pat & mask),
+            None => window == pattern,
+        };
+        self.by_addr()
+            .filter(move |_| !pattern.is_empty())
+            .flat_map(move |region| {
+                let base = region.base_address();
+                region
+                    .bytes()
+                    .windows(pattern.len().max(1))
+                    .enumerate()
+                    .filter(move |(_, window)| matches(window))
+                    .map(move |(i, _)| base + i as u64)
+            })
+    }
+
+    /// Write a human-readable description of this `UnifiedMemoryList` to `f`.
+    ///
+    /// This is very verbose, it is the format used by `minidump_dump`.
+    pub fn print<T: Write>(&self, f: &mut T) -> io::Result<()> {
+        match self {
+            Self::Memory(memory) => memory.print(f),
+            Self::Memory64(memory64) => memory64.print(f),
+        }
+    }
+
+    /// Get the [`MinidumpMemoryList`] contained inside, if it exists.
+    pub fn memory(&self) -> Option<&MinidumpMemoryList<'mdmp>> {
+        match self {
+            Self::Memory(memory) => Some(memory),
+            Self::Memory64(_) => None,
+        }
+    }
+
+    /// Get the [`MinidumpMemory64List`] contained inside, if it exists.
+    pub fn memory64(&self) -> Option<&MinidumpMemory64List<'mdmp>> {
+        match self {
+            Self::Memory(_) => None,
+            Self::Memory64(memory64) => Some(memory64),
+        }
+    }
+}
+
+impl<'a, 'mdmp> UnifiedMemory<'a, 'mdmp> {
+    /// The starting address of this range of memory.
+    pub fn base_address(&self) -> u64 {
+        match self {
+            Self::Memory(memory) => memory.base_address,
+            Self::Memory64(memory64) => memory64.base_address,
+        }
+    }
+
+    /// The length of this range of memory.
+    pub fn size(&self) -> u64 {
+        match self {
+            Self::Memory(memory) => memory.size,
+            Self::Memory64(memory64) => memory64.size,
+        }
+    }
+
+    /// The contents of the memory.
+    pub fn bytes(&self) -> &'mdmp [u8] {
+        match self {
+            Self::Memory(memory) => memory.bytes,
+            Self::Memory64(memory64) => memory64.bytes,
+        }
+    }
+
+    /// The range of memory this region covers.
+    pub fn memory_range(&self) -> Option<Range<u64>> {
+        match self {
+            Self::Memory(memory) => memory.memory_range(),
+            Self::Memory64(memory64) => memory64.memory_range(),
+        }
+    }
+
+    /// Get `mem::size_of::<T>()` bytes of memory at `addr` from this region.
+    ///
+    /// Return `None` if the requested address range falls out of the bounds
+    /// of this memory region.
+    pub fn get_memory_at_address<T>(&self, addr: u64) -> Option<T>
+    where
+        T: TryFromCtx<'mdmp, scroll::Endian, [u8], Error = scroll::Error>,
+        T: SizeWith<scroll::Endian>,
+    {
+        match self {
+            Self::Memory(memory) => memory.get_memory_at_address(addr),
+            Self::Memory64(memory64) => memory64.get_memory_at_address(addr),
+        }
+    }
+
+    /// Get the `u32` at `addr` from this region, in the given byte order.
+    pub fn get_u32_at_address(&self, addr: u64, endian: scroll::Endian) -> Option<u32> {
+        match self {
+            Self::Memory(memory) => memory.get_u32_at_address(addr, endian),
+            Self::Memory64(memory64) => memory64.get_u32_at_address(addr, endian),
+        }
+    }
+
+    /// Get the `u64` at `addr` from this region, in the given byte order.
+    pub fn get_u64_at_address(&self, addr: u64, endian: scroll::Endian) -> Option<u64> {
+        match self {
+            Self::Memory(memory) => memory.get_u64_at_address(addr, endian),
+            Self::Memory64(memory64) => memory64.get_u64_at_address(addr, endian),
+        }
+    }
+
+    /// Write a human-readable description of this region to `f`.
+    pub fn print<T: Write>(&self, f: &mut T) -> io::Result<()> {
+        match self {
+            Self::Memory(memory) => memory.print(f),
+            Self::Memory64(memory64) => memory64.print(f),
+        }
+    }
+}
+
 impl<'a> MinidumpStream<'a> for MinidumpMemoryInfoList<'a> {
     const STREAM_TYPE: MINIDUMP_STREAM_TYPE = MINIDUMP_STREAM_TYPE::MemoryInfoListStream;
 
@@ -1686,8 +2664,8 @@ impl<'mdmp> MinidumpMemoryInfoList<'mdmp> {
     /// Return an empty `MinidumpMemoryList`.
     pub fn new() -> MinidumpMemoryInfoList<'mdmp> {
         MinidumpMemoryInfoList {
-            regions: vec![],
-            regions_by_addr: RangeMap::new(),
+            regions: Arc::new([]),
+            regions_by_addr: Arc::new(RangeMap::new()),
         }
     }
 
@@ -1699,8 +2677,8 @@ impl<'mdmp> MinidumpMemoryInfoList<'mdmp> {
             .map(|(i, region)| (region.memory_range(), i))
             .into_rangemap_safe();
         MinidumpMemoryInfoList {
-            regions,
-            regions_by_addr,
+            regions: regions.into(),
+            regions_by_addr: Arc::new(regions_by_addr),
         }
     }
 
@@ -1790,6 +2768,46 @@ impl<'a> MinidumpMemoryInfo<'a> {
                 | md::MemoryProtection::PAGE_EXECUTE_WRITECOPY,
         )
     }
+
+    /// Whether this memory range was readable.
+    ///
+    /// Guard pages are not considered readable, since touching them faults.
+    pub fn is_readable(&self) -> bool {
+        !self.protection.contains(md::MemoryProtection::PAGE_GUARD)
+            && self.protection.intersects(
+                md::MemoryProtection::PAGE_READONLY
+                    | md::MemoryProtection::PAGE_READWRITE
+                    | md::MemoryProtection::PAGE_WRITECOPY
+                    | md::MemoryProtection::PAGE_EXECUTE_READ
+                    | md::MemoryProtection::PAGE_EXECUTE_READWRITE
+                    | md::MemoryProtection::PAGE_EXECUTE_WRITECOPY,
+            )
+    }
+
+    /// Whether this memory range was writable.
+    ///
+    /// Guard pages are not considered writable, since touching them faults.
+    pub fn is_writable(&self) -> bool {
+        !self.protection.contains(md::MemoryProtection::PAGE_GUARD)
+            && self.protection.intersects(
+                md::MemoryProtection::PAGE_READWRITE
+                    | md::MemoryProtection::PAGE_WRITECOPY
+                    | md::MemoryProtection::PAGE_EXECUTE_READWRITE
+                    | md::MemoryProtection::PAGE_EXECUTE_WRITECOPY,
+            )
+    }
+
+    /// Whether this memory range wasn't backed by a file, like memory
+    /// allocated with `VirtualAlloc`.
+    pub fn is_anonymous(&self) -> bool {
+        self.ty.contains(md::MemoryType::MEM_PRIVATE)
+    }
+
+    /// Whether this memory range was committed, rather than just reserved or
+    /// free (in which case its protection doesn't mean anything).
+    pub fn is_committed(&self) -> bool {
+        self.state.contains(md::MemoryState::MEM_COMMIT)
+    }
 }
 
 impl<'a> MinidumpStream<'a> for MinidumpLinuxMaps<'a> {
@@ -1820,8 +2838,8 @@ impl<'mdmp> MinidumpLinuxMaps<'mdmp> {
     /// Return an empty `MinidumpMemoryList`.
     pub fn new() -> Self {
         Self {
-            regions: vec![],
-            regions_by_addr: RangeMap::new(),
+            regions: Arc::new([]),
+            regions_by_addr: Arc::new(RangeMap::new()),
         }
     }
 
@@ -1833,8 +2851,8 @@ impl<'mdmp> MinidumpLinuxMaps<'mdmp> {
             .map(|(i, region)| (region.memory_range(), i))
             .into_rangemap_safe();
         Self {
-            regions,
-            regions_by_addr,
+            regions: regions.into(),
+            regions_by_addr: Arc::new(regions_by_addr),
         }
     }
 
@@ -2072,17 +3090,33 @@ impl<'a> MinidumpLinuxMapInfo<'a> {
     }
 
     pub fn memory_range(&self) -> Option<Range<u64>> {
-        // final address is inclusive afaik
-        if self.base_address > self.final_address {
+        // The end of a range in /proc/self/maps is exclusive, so it's also the
+        // start of the next mapping when two are adjacent.
+        if self.base_address >= self.final_address {
             return None;
         }
-        Some(Range::new(self.base_address, self.final_address))
+        Some(Range::new(self.base_address, self.final_address - 1))
     }
 
     /// Whether this memory range was executable.
     pub fn is_executable(&self) -> bool {
         self.is_exec
     }
+
+    /// Whether this memory range was readable.
+    pub fn is_readable(&self) -> bool {
+        self.is_read
+    }
+
+    /// Whether this memory range was writable.
+    pub fn is_writable(&self) -> bool {
+        self.is_write
+    }
+
+    /// Whether this memory range wasn't backed by a file.
+    pub fn is_anonymous(&self) -> bool {
+        self.kind == MinidumpLinuxMapKind::AnonymousMap
+    }
 }
 
 impl<'a> Default for UnifiedMemoryInfoList<'a> {
@@ -2125,6 +3159,49 @@ impl<'a> UnifiedMemoryInfoList<'a> {
         }
     }
 
+    /// Return the region containing `address`, if one exists.
+    ///
+    /// This is the same as [`UnifiedMemoryInfoList::memory_info_at_address`].
+    pub fn region_containing(&self, address: u64) -> Option<UnifiedMemoryInfo<'_>> {
+        self.memory_info_at_address(address)
+    }
+
+    /// Whether `address` was in executable memory.
+    ///
+    /// Returns `false` if `address` isn't in any known region.
+    pub fn is_executable(&self, address: u64) -> bool {
+        matches!(self.region_containing(address), Some(info) if info.is_executable())
+    }
+
+    /// Whether `address` was in readable memory.
+    ///
+    /// Returns `false` if `address` isn't in any known region.
+    pub fn is_readable(&self, address: u64) -> bool {
+        matches!(self.region_containing(address), Some(info) if info.is_readable())
+    }
+
+    /// Whether `address` was in writable memory.
+    ///
+    /// Returns `false` if `address` isn't in any known region.
+    pub fn is_writable(&self, address: u64) -> bool {
+        matches!(self.region_containing(address), Some(info) if info.is_writable())
+    }
+
+    /// Whether `address` was in memory that wasn't backed by a file.
+    ///
+    /// Returns `false` if `address` isn't in any known region.
+    pub fn is_anonymous(&self, address: u64) -> bool {
+        matches!(self.region_containing(address), Some(info) if info.is_anonymous())
+    }
+
+    /// Whether `address` was in memory that was actually mapped, see
+    /// [`UnifiedMemoryInfo::is_committed`].
+    ///
+    /// Returns `false` if `address` isn't in any known region.
+    pub fn is_committed(&self, address: u64) -> bool {
+        matches!(self.region_containing(address), Some(info) if info.is_committed())
+    }
+
     /// Iterate over the memory regions in the order contained in the minidump.
     pub fn iter(&self) -> impl Iterator<Item = UnifiedMemoryInfo> {
         // Use `flat_map` and `chain` to create a unified stream of the two types
@@ -2211,6 +3288,39 @@ impl<'a> UnifiedMemoryInfo<'a> {
             Self::Map(map) => map.is_executable(),
         }
     }
+
+    /// Whether this memory range was readable.
+    pub fn is_readable(&self) -> bool {
+        match self {
+            Self::Info(info) => info.is_readable(),
+            Self::Map(map) => map.is_readable(),
+        }
+    }
+
+    /// Whether this memory range was writable.
+    pub fn is_writable(&self) -> bool {
+        match self {
+            Self::Info(info) => info.is_writable(),
+            Self::Map(map) => map.is_writable(),
+        }
+    }
+
+    /// Whether this memory range wasn't backed by a file.
+    pub fn is_anonymous(&self) -> bool {
+        match self {
+            Self::Info(info) => info.is_anonymous(),
+            Self::Map(map) => map.is_anonymous(),
+        }
+    }
+
+    /// Whether this memory range was actually mapped: committed, rather than
+    /// reserved or free. Linux only lists mapped memory, so that's all of it.
+    pub fn is_committed(&self) -> bool {
+        match self {
+            Self::Info(info) => info.is_committed(),
+            Self::Map(_) => true,
+        }
+    }
 }
 
 impl<'a> MinidumpThread<'a> {
@@ -2318,6 +3428,92 @@ impl<'a> MinidumpThread<'a> {
 
         Some(CrashReason::from_windows_error(val))
     }
+
+    /// Gets the 32-bit context of a thread in a 32-bit process running under
+    /// WOW64 on 64-bit Windows.
+    ///
+    /// The context recorded for such a thread is the 64-bit state of the WOW64
+    /// layer. The thread's 32-bit registers were saved when it last switched
+    /// over to 64-bit code, in a CPU area the `WOW64_TLS_CPURESERVED` slot of
+    /// its 64-bit TEB points to.
+    ///
+    /// Returns `None` if this doesn't look like a WOW64 thread, or if the
+    /// memory that the context is in wasn't captured.
+    pub fn wow64_context(&self, cpu: Cpu, memory: &UnifiedMemoryList) -> Option<MinidumpContext> {
+        if cpu != Cpu::X86_64 {
+            return None;
+        }
+        let slot = self.raw.teb.checked_add(WOW64_TLS_CPURESERVED_OFFSET)?;
+        let cpu_area: u64 = memory
+            .memory_at_address(slot)?
+            .get_memory_at_address(slot)?;
+        // The CPU area starts with 16-bit flags and machine type fields.
+        let addr = cpu_area.checked_add(4)?;
+        let ctx: md::CONTEXT_X86 = memory
+            .memory_at_address(addr)?
+            .get_memory_at_address(addr)?;
+        if md::ContextFlagsCpu::from_flags(ctx.context_flags) != md::ContextFlagsCpu::CONTEXT_X86 {
+            return None;
+        }
+        Some(MinidumpContext::from_raw(MinidumpRawContext::X86(ctx)))
+    }
+
+    /// Gets the bounds of the thread's stack from its TEB, as its `StackLimit`
+    /// (the lowest committed address) and its `StackBase` (one past the
+    /// highest address).
+    ///
+    /// The memory recorded for a thread's stack doesn't always stop at the
+    /// stack: full-memory minidumps only record whole regions, which can go
+    /// on into whatever was allocated next to it.
+    ///
+    /// Returns `None` if the TEB's memory wasn't captured, or the bounds in
+    /// it don't make sense.
+    pub fn stack_bounds(&self, cpu: Cpu, memory: &UnifiedMemoryList) -> Option<(u64, u64)> {
+        teb_stack_bounds(self.raw.teb, cpu.pointer_width()?, memory)
+    }
+
+    /// Gets the bounds of the 32-bit stack of a thread in a 32-bit process
+    /// running under WOW64 on 64-bit Windows, like
+    /// [`MinidumpThread::stack_bounds`], from the thread's 32-bit TEB.
+    pub fn wow64_stack_bounds(&self, cpu: Cpu, memory: &UnifiedMemoryList) -> Option<(u64, u64)> {
+        if cpu != Cpu::X86_64 {
+            return None;
+        }
+        let teb = self.raw.teb.checked_add(WOW64_TEB32_OFFSET)?;
+        teb_stack_bounds(teb, 4, memory)
+    }
+}
+
+/// The offset of `TlsSlots[WOW64_TLS_CPURESERVED]` in a 64-bit TEB.
+const WOW64_TLS_CPURESERVED_OFFSET: u64 = 0x1480 + 8;
+
+/// The offset of a WOW64 thread's 32-bit TEB from its 64-bit one.
+const WOW64_TEB32_OFFSET: u64 = 0x2000;
+
+/// The `StackLimit` and `StackBase` of the TEB at `teb`.
+fn teb_stack_bounds(
+    teb: u64,
+    pointer_width: u64,
+    memory: &UnifiedMemoryList,
+) -> Option<(u64, u64)> {
+    if teb == 0 {
+        return None;
+    }
+    let read_pointer = |index: u64| -> Option<u64> {
+        let addr = teb.checked_add(pointer_width.checked_mul(index)?)?;
+        let memory = memory.memory_at_address(addr)?;
+        match pointer_width {
+            4 => memory.get_memory_at_address::<u32>(addr).map(u64::from),
+            _ => memory.get_memory_at_address(addr),
+        }
+    };
+    // The TEB starts with an NT_TIB: ExceptionList, StackBase, StackLimit.
+    let base = read_pointer(1)?;
+    let limit = read_pointer(2)?;
+    if limit == 0 || limit >= base {
+        return None;
+    }
+    Some((limit, base))
 }
 
 impl<'a> MinidumpStream<'a> for MinidumpThreadList<'a> {
@@ -2600,6 +3796,66 @@ impl MinidumpSystemInfo {
     pub fn cpu_info(&self) -> Option<Cow<str>> {
         self.cpu_info.as_deref().map(Cow::Borrowed)
     }
+
+    /// A stand-in for the system info of a minidump that doesn't have any,
+    /// with nothing known about the system.
+    pub fn unknown() -> MinidumpSystemInfo {
+        let raw = md::MINIDUMP_SYSTEM_INFO {
+            processor_architecture: md::ProcessorArchitecture::PROCESSOR_ARCHITECTURE_UNKNOWN
+                as u16,
+            processor_level: 0,
+            processor_revision: 0,
+            number_of_processors: 0,
+            product_type: 0,
+            major_version: 0,
+            minor_version: 0,
+            build_number: 0,
+            platform_id: 0,
+            csd_version_rva: 0,
+            suite_mask: 0,
+            reserved2: 0,
+            cpu: md::CPU_INFORMATION { data: [0; 24] },
+        };
+        MinidumpSystemInfo {
+            os: Os::from_platform_id(raw.platform_id),
+            cpu: Cpu::from_processor_architecture(raw.processor_architecture),
+            raw,
+            csd_version: None,
+            cpu_info: None,
+        }
+    }
+
+    /// A stand-in for missing system info, with the CPU inferred from the
+    /// `context_flags` of `context`, which say what kind of CPU context it
+    /// is.
+    fn infer_from_context(context: &[u8], endian: scroll::Endian) -> Option<MinidumpSystemInfo> {
+        use md::ProcessorArchitecture::*;
+
+        // Every kind of context has its own flag, so at most one of these
+        // parses. x86 goes before amd64, since the latter also accepts the
+        // 32-bit contexts of WOW64 processes.
+        let architectures = [
+            PROCESSOR_ARCHITECTURE_INTEL,
+            PROCESSOR_ARCHITECTURE_AMD64,
+            PROCESSOR_ARCHITECTURE_ARM,
+            PROCESSOR_ARCHITECTURE_ARM64,
+            PROCESSOR_ARCHITECTURE_ARM64_OLD,
+            PROCESSOR_ARCHITECTURE_PPC,
+            PROCESSOR_ARCHITECTURE_PPC64,
+            PROCESSOR_ARCHITECTURE_SPARC,
+            PROCESSOR_ARCHITECTURE_MIPS,
+            PROCESSOR_ARCHITECTURE_MIPS64,
+            PROCESSOR_ARCHITECTURE_RISCV64,
+        ];
+        architectures.iter().find_map(|&arch| {
+            let mut system_info = MinidumpSystemInfo::unknown();
+            system_info.raw.processor_architecture = arch as u16;
+            system_info.cpu = Cpu::from_processor_architecture(arch as u16);
+            MinidumpContext::read(context, endian, &system_info, None)
+                .ok()
+                .map(|_| system_info)
+        })
+    }
 }
 
 // Generates an accessor for a MISC_INFO field with two possible syntaxes:
@@ -3079,6 +4335,25 @@ fn systemtime_from_timestamp(timestamp: u64) -> Option<SystemTime> {
     SystemTime::UNIX_EPOCH.checked_add(Duration::from_secs(timestamp))
 }
 
+/// The number of seconds between the `FILETIME` epoch (1601) and the Unix epoch (1970).
+const FILETIME_UNIX_EPOCH_SECS: u64 = 11_644_473_600;
+
+/// A `FILETIME` (100-nanosecond intervals since 1601) as a `SystemTime`, or
+/// `None` if it's 0 (which means unknown) or before 1970.
+fn systemtime_from_filetime(filetime: u64) -> Option<SystemTime> {
+    if filetime == 0 {
+        return None;
+    }
+    let since_unix_epoch = duration_from_filetime(filetime)
+        .checked_sub(Duration::from_secs(FILETIME_UNIX_EPOCH_SECS))?;
+    SystemTime::UNIX_EPOCH.checked_add(since_unix_epoch)
+}
+
+/// A duration in 100-nanosecond intervals, like the times of a `FILETIME`.
+fn duration_from_filetime(filetime: u64) -> Duration {
+    Duration::new(filetime / 10_000_000, (filetime % 10_000_000) as u32 * 100)
+}
+
 impl MinidumpMiscInfo {
     pub fn process_create_time(&self) -> Option<SystemTime> {
         self.raw
@@ -3447,6 +4722,13 @@ impl CrashReason {
                 }
             },
             ExceptionCodeMac::EXC_ARITHMETIC => match cpu {
+                Cpu::Arm64 => {
+                    if let Some(ty) =
+                        md::ExceptionCodeMacArithmeticArmType::from_u32(exception_flags)
+                    {
+                        reason = CrashReason::MacArithmeticArm(ty);
+                    }
+                }
                 Cpu::Ppc => {
                     if let Some(ty) =
                         md::ExceptionCodeMacArithmeticPpcType::from_u32(exception_flags)
@@ -3582,7 +4864,7 @@ impl fmt::Display for CrashReason {
             if let Some(nt_status) = nt_status {
                 write!(f, "{:?}", nt_status)
             } else {
-                write!(f, "0x{:08}", raw_nt_status)
+                write!(f, "0x{:08x}", raw_nt_status)
             }
         }
 
@@ -3591,7 +4873,7 @@ impl fmt::Display for CrashReason {
             if let Some(fast_fail) = fast_fail {
                 write!(f, "{:?}", fast_fail)
             } else {
-                write!(f, "0x{:08}", raw_fast_fail)
+                write!(f, "0x{:08x}", raw_fast_fail)
             }
         }
 
@@ -3617,7 +4899,7 @@ impl fmt::Display for CrashReason {
                             cpu_flavor, interval, cpu_limit, cpu_consumed
                         )
                     } else {
-                        write!(f, "0x{:016} / 0x{:016}", code, subcode)
+                        write!(f, "0x{:016x} / 0x{:016x}", code, subcode)
                     }
                 }
                 md::ExceptionCodeMacResourceType::RESOURCE_TYPE_WAKEUPS => {
@@ -3633,7 +4915,7 @@ impl fmt::Display for CrashReason {
                             wakeups_flavor, interval, wakeups_permitted, wakeups_observed
                         )
                     } else {
-                        write!(f, "0x{:016} / 0x{:016}", code, subcode)
+                        write!(f, "0x{:016x} / 0x{:016x}", code, subcode)
                     }
                 }
                 md::ExceptionCodeMacResourceType::RESOURCE_TYPE_MEMORY => {
@@ -3647,7 +4929,7 @@ impl fmt::Display for CrashReason {
                             memory_flavor, hwm_limit
                         )
                     } else {
-                        write!(f, "0x{:016} / 0x{:016}", code, subcode)
+                        write!(f, "0x{:016x} / 0x{:016x}", code, subcode)
                     }
                 }
                 md::ExceptionCodeMacResourceType::RESOURCE_TYPE_IO => {
@@ -3662,7 +4944,7 @@ impl fmt::Display for CrashReason {
                             io_flavor, interval, io_limit, io_observed
                         )
                     } else {
-                        write!(f, "0x{:016} / 0x{:016}", code, subcode)
+                        write!(f, "0x{:016x} / 0x{:016x}", code, subcode)
                     }
                 }
                 md::ExceptionCodeMacResourceType::RESOURCE_TYPE_THREADS => {
@@ -3676,7 +4958,7 @@ impl fmt::Display for CrashReason {
                             threads_flavor, hwm_limit
                         )
                     } else {
-                        write!(f, "0x{:016} / 0x{:016}", code, subcode)
+                        write!(f, "0x{:016x} / 0x{:016x}", code, subcode)
                     }
                 }
             }
@@ -3705,7 +4987,7 @@ impl fmt::Display for CrashReason {
                             mach_port_flavor, port_name, subcode,
                         )
                     } else {
-                        write!(f, " / 0x{:016} / 0x{:016}", code, subcode)
+                        write!(f, " / 0x{:016x} / 0x{:016x}", code, subcode)
                     }
                 }
                 md::ExceptionCodeMacGuardType::GUARD_TYPE_FD => {
@@ -3717,7 +4999,7 @@ impl fmt::Display for CrashReason {
                             fd_flavor, fd, subcode,
                         )
                     } else {
-                        write!(f, " / 0x{:016} / 0x{:016}", code, subcode)
+                        write!(f, " / 0x{:016x} / 0x{:016x}", code, subcode)
                     }
                 }
                 md::ExceptionCodeMacGuardType::GUARD_TYPE_USER => {
@@ -3737,7 +5019,7 @@ impl fmt::Display for CrashReason {
                             vn_flavor, pid, subcode,
                         )
                     } else {
-                        write!(f, " / 0x{:016} / 0x{:016}", code, subcode)
+                        write!(f, " / 0x{:016x} / 0x{:016x}", code, subcode)
                     }
                 }
                 md::ExceptionCodeMacGuardType::GUARD_TYPE_VIRT_MEMORY => {
@@ -3746,18 +5028,44 @@ impl fmt::Display for CrashReason {
                     {
                         write!(f, " / {:?} offset: {}", virt_memory_flavor, subcode)
                     } else {
-                        write!(f, " / 0x{:016} / 0x{:016}", code, subcode)
+                        write!(f, " / 0x{:016x} / 0x{:016x}", code, subcode)
                     }
                 }
             }
         }
 
+        fn write_exc_crash(f: &mut fmt::Formatter<'_>, code: u32) -> fmt::Result {
+            // The kernel packs the original exception and the signal that
+            // ended the process into the code.
+            let exception = (code >> 20) & 0xf;
+            let signal = (code >> 24) & 0xff;
+            write!(f, "EXC_CRASH / ")?;
+            if let Some(exception) = md::ExceptionCodeMac::from_u32(exception) {
+                write!(f, "{:?} / ", exception)?;
+            }
+            write!(f, "signal {}", signal)
+        }
+
+        fn write_windows_unknown(f: &mut fmt::Formatter<'_>, code: u32) -> fmt::Result {
+            write!(f, "unknown 0x{:08x}", code)?;
+            // HRESULTs and NTSTATUSes wrapping a win32 error (FACILITY_WIN32
+            // and FACILITY_NTWIN32) keep the error in their low 16 bits.
+            let facility = (code >> 16) & 0xfff;
+            if code & 0x8000_0000 != 0 && facility == 7 {
+                if let Some(winerror) = md::WinErrorWindows::from_u32(code & 0xffff) {
+                    write!(f, " / {:?}", winerror)?;
+                }
+            }
+            Ok(())
+        }
+
         fn write_signal(
             f: &mut fmt::Formatter<'_>,
             ex: ExceptionCodeLinux,
             flags: u32,
         ) -> fmt::Result {
-            if let Some(si_code) = md::ExceptionCodeLinuxSicode::from_u32(flags) {
+            // si_codes for signals sent from userspace are negative
+            if let Some(si_code) = md::ExceptionCodeLinuxSicode::from_i32(flags as i32) {
                 if si_code == md::ExceptionCodeLinuxSicode::SI_USER {
                     write!(f, "{:?}", ex)
                 } else {
@@ -3776,6 +5084,7 @@ impl fmt::Display for CrashReason {
 
             // These codes get special messages
             MacGeneral(md::ExceptionCodeMac::SIMULATED, _) => write!(f, "Simulated Exception"),
+            MacGeneral(md::ExceptionCodeMac::EXC_CRASH, flags) => write_exc_crash(f, flags),
 
             // Thse codes just repeat their names
             MacGeneral(ex, flags) => write!(f, "{:?} / 0x{:08x}", ex, flags),
@@ -3786,6 +5095,7 @@ impl fmt::Display for CrashReason {
             MacBadInstructionArm(ex) => write!(f, "EXC_BAD_INSTRUCTION / {:?}", ex),
             MacBadInstructionPpc(ex) => write!(f, "EXC_BAD_INSTRUCTION / {:?}", ex),
             MacBadInstructionX86(ex) => write!(f, "EXC_BAD_INSTRUCTION / {:?}", ex),
+            MacArithmeticArm(ex) => write!(f, "EXC_ARITHMETIC / {:?}", ex),
             MacArithmeticPpc(ex) => write!(f, "EXC_ARITHMETIC / {:?}", ex),
             MacArithmeticX86(ex) => write!(f, "EXC_ARITHMETIC / {:?}", ex),
             MacSoftware(ex) => write!(f, "EXC_SOFTWARE / {:?}", ex),
@@ -3827,9 +5137,9 @@ impl fmt::Display for CrashReason {
                 write!(f, "EXCEPTION_STACK_BUFFER_OVERRUN / ")?;
                 write_fast_fail(f, fast_fail)
             }
-            WindowsUnknown(code) => write!(f, "unknown 0x{:08}", code),
+            WindowsUnknown(code) => write_windows_unknown(f, code),
 
-            Unknown(code, flags) => write!(f, "unknown 0x{:08} / 0x{:08}", code, flags),
+            Unknown(code, flags) => write!(f, "unknown 0x{:08x} / 0x{:08x}", code, flags),
         }
     }
 }
@@ -3873,6 +5183,40 @@ impl<'a> MinidumpException<'a> {
             .map(Cow::Owned)
     }
 
+    /// A stand-in for the system info of a minidump that doesn't have a
+    /// system info stream.
+    ///
+    /// The CPU is inferred from the flags of this exception's context, which
+    /// say what kind of context it is, and nothing else about the system is
+    /// known. Returns `None` if there's no context, or it isn't any kind of
+    /// context we know.
+    pub fn infer_system_info(&self) -> Option<MinidumpSystemInfo> {
+        MinidumpSystemInfo::infer_from_context(self.context?, self.endian)
+    }
+
+    /// A stand-in for the thread that encountered this exception, for a
+    /// minidump that doesn't have a thread list stream.
+    ///
+    /// The thread has this exception's thread id and context, and `stack` as
+    /// its stack memory (which a caller can find by looking up the context's
+    /// stack pointer in the memory list).
+    pub fn synthesize_thread(&self, stack: Option<MinidumpMemory<'a>>) -> MinidumpThread<'a> {
+        MinidumpThread {
+            raw: md::MINIDUMP_THREAD {
+                thread_id: self.thread_id,
+                suspend_count: 0,
+                priority_class: 0,
+                priority: 0,
+                teb: 0,
+                stack: stack.as_ref().map(|stack| stack.desc).unwrap_or_default(),
+                thread_context: self.raw.thread_context,
+            },
+            context: self.context,
+            stack,
+            endian: self.endian,
+        }
+    }
+
     /// Get the address that "caused" the crash.
     ///
     /// The meaning of this value depends on the kind of crash this was.
@@ -4006,12 +5350,13 @@ impl<'a> MinidumpStream<'a> for MinidumpAssertion {
 }
 
 fn utf16_to_string(data: &[u16]) -> Option<String> {
-    use std::slice;
+    utf16_to_minidump_string(data).to_string_checked()
+}
 
+/// Get the NUL-terminated prefix of `data` without decoding it.
+fn utf16_to_minidump_string(data: &[u16]) -> MinidumpString {
     let len = data.iter().take_while(|c| **c != 0).count();
-    let s16 = &data[..len];
-    let bytes = unsafe { slice::from_raw_parts(s16.as_ptr() as *const u8, s16.len() * 2) };
-    UTF_16LE.decode(bytes, DecoderTrap::Strict).ok()
+    MinidumpString::from_utf16(data[..len].to_vec())
 }
 
 impl MinidumpAssertion {
@@ -4027,6 +5372,20 @@ impl MinidumpAssertion {
     pub fn file(&self) -> Option<String> {
         utf16_to_string(&self.raw.file)
     }
+    /// Get the assertion expression as raw UTF-16 code units.
+    ///
+    /// Unlike [`MinidumpAssertion::expression`], this never fails for invalid UTF-16.
+    pub fn raw_expression(&self) -> MinidumpString {
+        utf16_to_minidump_string(&self.raw.expression)
+    }
+    /// Get the function name where the assertion happened as raw UTF-16 code units.
+    pub fn raw_function(&self) -> MinidumpString {
+        utf16_to_minidump_string(&self.raw.function)
+    }
+    /// Get the source file name where the assertion happened as raw UTF-16 code units.
+    pub fn raw_file(&self) -> MinidumpString {
+        utf16_to_minidump_string(&self.raw.file)
+    }
 
     /// Write a human-readable description of this `MinidumpAssertion` to `f`.
     ///
@@ -4353,6 +5712,19 @@ pub struct MinidumpUnknownStream {
     pub vendor: &'static str,
 }
 
+/// A stream in the minidump whose data extends past the end of the file.
+///
+/// This usually means the minidump was cut off while it was being written
+/// (e.g. the disk filled up or the writer was killed).
+#[derive(Debug)]
+pub struct MinidumpTruncatedStream {
+    pub stream_type: u32,
+    pub location: md::MINIDUMP_LOCATION_DESCRIPTOR,
+    /// How many bytes of the stream are actually present in the file.
+    pub available_size: u32,
+    pub vendor: &'static str,
+}
+
 /// A stream in the minidump that this implementation is aware of but doesn't
 /// yet support.
 #[derive(Debug)]
@@ -4393,18 +5765,34 @@ where
         if (header.version & 0x0000ffff) != md::MINIDUMP_VERSION {
             return Err(Error::VersionMismatch);
         }
+        if header.flags & !md::MinidumpType::all().bits() != 0 {
+            warn!(
+                "Minidump header has unknown flags: {:#x}",
+                header.flags & !md::MinidumpType::all().bits()
+            );
+        }
 
         offset = header.stream_directory_rva as usize;
 
-        let (count, _) = ensure_count_in_bound(
-            &data,
-            header.stream_count as usize,
-            <md::MINIDUMP_DIRECTORY>::size_with(&endian),
-            offset,
-        )?;
+        // If the minidump was cut off while it was being written, the directory itself
+        // may be incomplete. Read whatever entries are present and remember how many
+        // are missing, instead of refusing to read the minidump at all.
+        let available_entries =
+            data.len().saturating_sub(offset) / <md::MINIDUMP_DIRECTORY>::size_with(&endian);
+        let count = (header.stream_count as usize).min(available_entries);
+        if count == 0 && header.stream_count != 0 {
+            return Err(Error::MissingDirectory);
+        }
+        let missing_directory_entries = header.stream_count - count as u32;
+        if missing_directory_entries != 0 {
+            warn!(
+                "Minidump is truncated: {} of {} stream directory entries are missing",
+                missing_directory_entries, header.stream_count
+            );
+        }
 
         let mut streams = HashMap::with_capacity(count);
-        for i in 0..header.stream_count {
+        for i in 0..count as u32 {
             let dir: md::MINIDUMP_DIRECTORY = data
                 .gread_with(&mut offset, endian)
                 .or(Err(Error::MissingDirectory))?;
@@ -4431,13 +5819,21 @@ where
                 }
             }
         }
-        Ok(Minidump {
+        let dump = Minidump {
             data,
             header,
             streams,
             endian,
+            missing_directory_entries,
             _phantom: PhantomData,
-        })
+        };
+        for stream in dump.truncated_streams() {
+            warn!(
+                "Minidump is truncated: stream {:#x} has {} of {} bytes",
+                stream.stream_type, stream.available_size, stream.location.data_size
+            );
+        }
+        Ok(dump)
     }
 
     /// Read and parse the specified [`MinidumpStream`][] `S` from the Minidump, if it exists.
@@ -4500,6 +5896,7 @@ where
     /// * [`MinidumpBreakpadInfo`][]
     /// * [`MinidumpCrashpadInfo`][]
     /// * [`MinidumpException`][]
+    /// * [`MinidumpHandleDataStream`][]
     /// * [`MinidumpLinuxCpuInfo`][]
     /// * [`MinidumpLinuxEnviron`][]
     /// * [`MinidumpLinuxLsbRelease`][]
@@ -4507,10 +5904,12 @@ where
     /// * [`MinidumpLinuxProcStatus`][]
     /// * [`MinidumpMacCrashInfo`][]
     /// * [`MinidumpMemoryList`][]
+    /// * [`MinidumpMemory64List`][]
     /// * [`MinidumpMemoryInfoList`][]
     /// * [`MinidumpMiscInfo`][]
     /// * [`MinidumpModuleList`][]
     /// * [`MinidumpSystemInfo`][]
+    /// * [`MinidumpThreadInfoList`][]
     /// * [`MinidumpThreadList`][]
     /// * [`MinidumpThreadNames`][]
     /// * [`MinidumpUnloadedModuleList`][]
@@ -4544,19 +5943,71 @@ where
         match self.streams.get(&stream_type.into()) {
             None => Err(Error::StreamNotFound),
             Some(&(_, ref dir)) => {
+                let available = self.available_size(&dir.location);
+                if available < dir.location.data_size {
+                    return Err(Error::StreamSizeMismatch {
+                        expected: dir.location.data_size as usize,
+                        actual: available as usize,
+                    });
+                }
                 let bytes = self.data.deref();
                 location_slice(bytes, &dir.location)
             }
         }
     }
 
+    /// How many bytes of the data at `location` are actually present in the file.
+    fn available_size(&self, location: &md::MINIDUMP_LOCATION_DESCRIPTOR) -> u32 {
+        let len = self.data.deref().len();
+        let available = len.saturating_sub(location.rva as usize);
+        available.min(location.data_size as usize) as u32
+    }
+
+    /// Whether this minidump appears to have been cut off while it was being written.
+    ///
+    /// This is the case if any stream directory entries or stream data extend past
+    /// the end of the file. See [`Minidump::missing_directory_entries`] and
+    /// [`Minidump::truncated_streams`] for details.
+    pub fn is_truncated(&self) -> bool {
+        self.missing_directory_entries != 0 || self.truncated_streams().next().is_some()
+    }
+
+    /// How many entries of the stream directory were cut off by the end of the file.
+    ///
+    /// The streams those entries describe can't be found at all.
+    pub fn missing_directory_entries(&self) -> u32 {
+        self.missing_directory_entries
+    }
+
+    /// A listing of all the streams in the Minidump whose data extends past the end of the file.
+    ///
+    /// Trying to read these streams with [`Minidump::get_stream`] or
+    /// [`Minidump::get_raw_stream`] will fail with [`Error::StreamSizeMismatch`].
+    ///
+    /// If there are multiple copies of the same stream (which should not happen for
+    /// well-formed Minidumps), then only one of them will be yielded, arbitrarily.
+    pub fn truncated_streams(&self) -> impl Iterator<Item = MinidumpTruncatedStream> + '_ {
+        self.streams.iter().filter_map(move |(_, (_, stream))| {
+            let available_size = self.available_size(&stream.location);
+            if available_size < stream.location.data_size {
+                return Some(MinidumpTruncatedStream {
+                    stream_type: stream.stream_type,
+                    location: stream.location,
+                    available_size,
+                    vendor: stream_vendor(stream.stream_type),
+                });
+            }
+            None
+        })
+    }
+
     /// A listing of all the streams in the Minidump that this library is *aware* of,
     /// but has no further analysis for.
     ///
     /// If there are multiple copies of the same stream type (which should not happen for
     /// well-formed Minidumps), then only one of them will be yielded, arbitrarily.
     pub fn unimplemented_streams(&self) -> impl Iterator<Item = MinidumpUnimplementedStream> + '_ {
-        static UNIMPLEMENTED_STREAMS: [MINIDUMP_STREAM_TYPE; 33] = [
+        static UNIMPLEMENTED_STREAMS: [MINIDUMP_STREAM_TYPE; 30] = [
             // Presumably will never have an implementation:
             MINIDUMP_STREAM_TYPE::UnusedStream,
             MINIDUMP_STREAM_TYPE::ReservedStream0,
@@ -4564,12 +6015,9 @@ where
             MINIDUMP_STREAM_TYPE::LastReservedStream,
             // Presumably should be implemented:
             MINIDUMP_STREAM_TYPE::ThreadExListStream,
-            MINIDUMP_STREAM_TYPE::Memory64ListStream,
             MINIDUMP_STREAM_TYPE::CommentStreamA,
             MINIDUMP_STREAM_TYPE::CommentStreamW,
-            MINIDUMP_STREAM_TYPE::HandleDataStream,
             MINIDUMP_STREAM_TYPE::FunctionTable,
-            MINIDUMP_STREAM_TYPE::ThreadInfoListStream,
             MINIDUMP_STREAM_TYPE::HandleOperationListStream,
             MINIDUMP_STREAM_TYPE::TokenStream,
             MINIDUMP_STREAM_TYPE::JavaScriptDataStream,
@@ -4653,7 +6101,7 @@ where
   stream_directory_rva = {:#x}
   checksum             = {:#x}
   time_date_stamp      = {:#x} {}
-  flags                = {:#x}
+  flags                = {:#x} {:?}
 
 "#,
             self.header.signature,
@@ -4664,6 +6112,7 @@ where
             self.header.time_date_stamp,
             format_time_t(self.header.time_date_stamp),
             self.header.flags,
+            self.header.dump_type(),
         )?;
         let mut streams = self.streams.iter().collect::<Vec<_>>();
         streams.sort_by(|&(&_, &(a, _)), &(&_, &(b, _))| a.cmp(&b));
@@ -4687,13 +6136,29 @@ MDRawDirectory
         writeln!(f, "Streams:")?;
         streams.sort_by(|&(&a, &(_, _)), &(&b, &(_, _))| a.cmp(&b));
         for (_, &(i, ref stream)) in streams {
-            writeln!(
+            write!(
                 f,
                 "  stream type {:#x} ({}) at index {}",
                 stream.stream_type,
                 get_stream_name(stream.stream_type),
                 i
             )?;
+            let available = self.available_size(&stream.location);
+            if available < stream.location.data_size {
+                write!(
+                    f,
+                    " (truncated: {} of {} bytes)",
+                    available, stream.location.data_size
+                )?;
+            }
+            writeln!(f)?;
+        }
+        if self.missing_directory_entries != 0 {
+            writeln!(
+                f,
+                "  ({} stream directory entries missing, minidump is truncated)",
+                self.missing_directory_entries
+            )?;
         }
         writeln!(f)?;
         Ok(())
@@ -4719,11 +6184,11 @@ mod test {
     use minidump_common::format::ProcessorArchitecture;
     use std::mem;
     use synth_minidump::{
-        self, AnnotationValue, CrashpadInfo, DumpString, Exception, Memory,
+        self, AnnotationValue, CrashpadInfo, DumpString, Exception, HandleDescriptor, Memory,
         MemoryInfo as SynthMemoryInfo, MiscFieldsBuildString, MiscFieldsPowerInfo,
         MiscFieldsProcessTimes, MiscFieldsTimeZone, MiscInfo5Fields, MiscStream,
         Module as SynthModule, ModuleCrashpadInfo, SimpleStream, SynthMinidump, SystemInfo, Thread,
-        ThreadName, UnloadedModule as SynthUnloadedModule, STOCK_VERSION_INFO,
+        ThreadInfo, ThreadName, UnloadedModule as SynthUnloadedModule, STOCK_VERSION_INFO,
     };
     use test_assembler::*;
 
@@ -4771,6 +6236,21 @@ mod test {
         );
     }
 
+    #[test]
+    fn test_dump_type() {
+        let dump = read_synth_dump(SynthMinidump::with_endian(Endian::Little)).unwrap();
+        assert_eq!(dump.header.dump_type(), md::MinidumpType::empty());
+
+        let flags =
+            md::MinidumpType::MiniDumpWithFullMemory | md::MinidumpType::MiniDumpWithHandleData;
+        let dump =
+            SynthMinidump::with_endian(Endian::Little).flags(flags.bits() | 0x1000_0000_0000);
+        let dump = read_synth_dump(dump).unwrap();
+        // Unknown bits are ignored.
+        assert_eq!(dump.header.dump_type(), flags);
+        assert_eq!(dump.header.implementation_version(), 0);
+    }
+
     #[test]
     fn test_thread_names() {
         let good_thread_id = 17;
@@ -4798,6 +6278,136 @@ mod test {
         assert_eq!(thread_names.get_name(corrupt_thread_id), None);
     }
 
+    #[test]
+    fn test_thread_info_list() {
+        let dump = SynthMinidump::with_endian(Endian::Little)
+            .add_thread_info(ThreadInfo::new(
+                Endian::Little,
+                17,
+                15_000_000,
+                2_500,
+                0x7ff7_1234_5678,
+            ))
+            .add_thread_info(ThreadInfo::new(Endian::Little, 18, 0, 0, 0));
+
+        let dump = read_synth_dump(dump).unwrap();
+        let thread_infos = dump.get_stream::<MinidumpThreadInfoList>().unwrap();
+        assert_eq!(thread_infos.iter().count(), 2);
+        let info = thread_infos.get_thread_info(17).unwrap();
+        assert_eq!(info.raw.start_address, 0x7ff7_1234_5678);
+        assert_eq!(info.kernel_time(), Duration::from_millis(1500));
+        assert_eq!(info.user_time(), Duration::from_micros(250));
+        assert_eq!(info.create_time(), None);
+        assert!(info.dump_flags.is_empty());
+        assert!(thread_infos.get_thread_info(19).is_none());
+        assert!(dump.unimplemented_streams().next().is_none());
+    }
+
+    #[test]
+    fn test_handle_data() {
+        let file_type = DumpString::new("File", Endian::Little);
+        let path = DumpString::new("C:\\Users\\me\\log.txt", Endian::Little);
+        let dump = SynthMinidump::with_endian(Endian::Little)
+            .add_handle(HandleDescriptor::new(
+                Endian::Little,
+                0x1a4,
+                Some(&file_type),
+                Some(&path),
+            ))
+            .add_handle(HandleDescriptor::new(Endian::Little, 0x1a8, None, None))
+            .add(file_type)
+            .add(path);
+
+        let dump = read_synth_dump(dump).unwrap();
+        let handles = dump.get_stream::<MinidumpHandleDataStream>().unwrap();
+        let handles = handles.iter().collect::<Vec<_>>();
+        assert_eq!(handles.len(), 2);
+        assert_eq!(handles[0].raw.handle, 0x1a4);
+        assert_eq!(handles[0].type_name.as_deref(), Some("File"));
+        assert_eq!(
+            handles[0].object_name.as_deref(),
+            Some("C:\\Users\\me\\log.txt")
+        );
+        assert_eq!(handles[0].raw.handle_count, 1);
+        assert_eq!(handles[1].raw.handle, 0x1a8);
+        assert_eq!(handles[1].type_name, None);
+        assert_eq!(handles[1].object_name, None);
+    }
+
+    #[test]
+    fn test_truncated_dump() {
+        const STREAM_TYPE1: u32 = 0x11223344;
+        const STREAM_TYPE2: u32 = 0x55667788;
+        // SynthMinidump puts the stream directory at the end of the file, but we want to
+        // truncate it like a real minidump writer would, so lay this one out by hand.
+        let header_size = md::MINIDUMP_HEADER::size_with(&LE) as u32;
+        let entry_size = md::MINIDUMP_DIRECTORY::size_with(&LE) as u32;
+        let data_rva = header_size + entry_size * 2;
+        let dump = Section::with_endian(Endian::Little)
+            .D32(md::MINIDUMP_SIGNATURE)
+            .D32(md::MINIDUMP_VERSION)
+            .D32(2) // stream_count
+            .D32(header_size) // stream_directory_rva
+            .D32(0) // checksum
+            .D32(0) // time_date_stamp
+            .D64(0) // flags
+            .D32(STREAM_TYPE1)
+            .D32(4)
+            .D32(data_rva)
+            .D32(STREAM_TYPE2)
+            .D32(4)
+            .D32(data_rva + 4)
+            .D32(0x55667788)
+            .D32(0x99aabbcc);
+        let mut bytes = dump.get_contents().unwrap();
+        let full_len = bytes.len();
+
+        let dump = Minidump::read(&bytes[..]).unwrap();
+        assert!(!dump.is_truncated());
+        assert_eq!(dump.missing_directory_entries(), 0);
+        assert_eq!(dump.truncated_streams().count(), 0);
+
+        // Cut off the end of the last stream.
+        bytes.truncate(full_len - 1);
+        let dump = Minidump::read(&bytes[..]).unwrap();
+        assert!(dump.is_truncated());
+        assert_eq!(dump.missing_directory_entries(), 0);
+        let truncated = dump.truncated_streams().collect::<Vec<_>>();
+        assert_eq!(truncated.len(), 1);
+        assert_eq!(truncated[0].stream_type, STREAM_TYPE2);
+        assert_eq!(truncated[0].location.data_size, 4);
+        assert_eq!(truncated[0].available_size, 3);
+        assert_eq!(
+            dump.get_raw_stream(STREAM_TYPE1).unwrap(),
+            &[0x88, 0x77, 0x66, 0x55]
+        );
+        assert_eq!(
+            dump.get_raw_stream(STREAM_TYPE2),
+            Err(Error::StreamSizeMismatch {
+                expected: 4,
+                actual: 3
+            })
+        );
+
+        // Cut off the second directory entry.
+        bytes.truncate((header_size + entry_size) as usize);
+        let dump = Minidump::read(&bytes[..]).unwrap();
+        assert!(dump.is_truncated());
+        assert_eq!(dump.missing_directory_entries(), 1);
+        assert_eq!(dump.truncated_streams().count(), 1);
+        assert_eq!(
+            dump.get_raw_stream(STREAM_TYPE2),
+            Err(Error::StreamNotFound)
+        );
+
+        // With no directory at all there's nothing we can do.
+        bytes.truncate(header_size as usize);
+        assert_eq!(
+            Minidump::read(&bytes[..]).unwrap_err(),
+            Error::MissingDirectory
+        );
+    }
+
     #[test]
     fn test_module_list() {
         let name = DumpString::new("single module", Endian::Little);
@@ -4838,6 +6448,71 @@ mod test {
             modules[0].debug_identifier().unwrap(),
             "ABCD1234F00DBEEF01020304050607081"
         );
+        assert_eq!(modules[0].version().unwrap(), "4369.4369.8738.8738");
+        let version_info = modules[0].version_info().unwrap();
+        assert_eq!(
+            version_info.file_version,
+            MinidumpModuleVersion {
+                major: 0x1111,
+                minor: 0x1111,
+                build: 0x2222,
+                revision: 0x2222,
+            }
+        );
+        assert_eq!(
+            version_info.product_version,
+            MinidumpModuleVersion::from_parts(0x33333333, 0x44444444)
+        );
+        assert_eq!(version_info.file_flags, md::FileFlags::VS_FF_DEBUG);
+        assert_eq!(version_info.file_os, 0x40004);
+        assert_eq!(version_info.file_type, 1);
+
+        // Clones share the modules rather than copying them.
+        let clone = module_list.clone();
+        assert!(Arc::ptr_eq(
+            module_list
+                .shared_module_at_address(0xa90206ca83eb2852)
+                .unwrap(),
+            clone.shared_module_at_address(0xa90206ca83eb2852).unwrap()
+        ));
+    }
+
+    #[test]
+    fn test_unpaired_surrogate_names() {
+        // "bad\u{d800}.dll", which has no valid UTF-8 representation.
+        let units: Vec<u16> = "bad"
+            .encode_utf16()
+            .chain(iter::once(0xd800))
+            .chain(".dll".encode_utf16())
+            .collect();
+        let module_name = DumpString::from_utf16(&units, Endian::Little);
+        let thread_name = DumpString::from_utf16(&units, Endian::Little);
+        let module = SynthModule::new(
+            Endian::Little,
+            0x10000,
+            0x1000,
+            &module_name,
+            0xb1054d2a,
+            0x34571371,
+            Some(&STOCK_VERSION_INFO),
+        );
+        let dump = SynthMinidump::with_endian(Endian::Little)
+            .add_module(module)
+            .add_thread_name(ThreadName::new(Endian::Little, 1, Some(&thread_name)))
+            .add(module_name)
+            .add(thread_name);
+        let dump = read_synth_dump(dump).unwrap();
+
+        let module_list = dump.get_stream::<MinidumpModuleList>().unwrap();
+        let module = module_list.iter().next().unwrap();
+        assert_eq!(module.raw_name.as_utf16(), &units[..]);
+        assert_eq!(module.name, "bad\u{fffd}.dll");
+        assert_eq!(module.raw_name.display().to_string(), module.name);
+        assert_eq!(module.raw_name.to_string_checked(), None);
+
+        let thread_names = dump.get_stream::<MinidumpThreadNames>().unwrap();
+        assert_eq!(thread_names.get_raw_name(1).unwrap().as_utf16(), &units[..]);
+        assert_eq!(&*thread_names.get_name(1).unwrap(), "bad\u{fffd}.dll");
     }
 
     #[test]
@@ -4941,6 +6616,10 @@ mod test {
         assert_eq!(infos[0].state, info1_state);
         assert_eq!(infos[0].ty, info1_ty);
         assert!(infos[0].is_executable());
+        assert!(infos[0].is_readable());
+        assert!(!infos[0].is_writable());
+        assert!(!infos[0].is_anonymous());
+        assert!(!infos[0].is_committed());
 
         assert_eq!(infos[1].raw.base_address, 0xd70206ca83eb2852);
         assert_eq!(infos[1].raw.allocation_base, 0xb802064383eb2752);
@@ -4958,6 +6637,10 @@ mod test {
         assert_eq!(infos[1].state, info2_state);
         assert_eq!(infos[1].ty, info2_ty);
         assert!(!infos[1].is_executable());
+        assert!(infos[1].is_readable());
+        assert!(!infos[1].is_writable());
+        assert!(infos[1].is_anonymous());
+        assert!(infos[1].is_committed());
     }
 
     #[test]
@@ -5010,6 +6693,7 @@ c70206ca83eb2852-de0206ca83eb2852  -w-s  10bac9000 fd:05 1196511 /usr/lib64/libt
         assert!(maps[0].is_private);
         assert!(!maps[0].is_shared);
         assert!(maps[0].is_executable());
+        assert!(!maps[0].is_anonymous());
 
         assert_eq!(maps[1].base_address, 0xc70206ca83eb2852);
         assert_eq!(maps[1].final_address, 0xde0206ca83eb2852);
@@ -5030,6 +6714,23 @@ c70206ca83eb2852-de0206ca83eb2852  -w-s  10bac9000 fd:05 1196511 /usr/lib64/libt
 
         assert!(matches!(unified_infos.next(), Some(UnifiedMemoryInfo::Map(m)) if m == maps[0]));
         assert!(matches!(unified_infos.next(), Some(UnifiedMemoryInfo::Map(m)) if m == maps[1]));
+
+        assert!(
+            matches!(unified_info.region_containing(0xa90206ca83eb3000), Some(UnifiedMemoryInfo::Map(m)) if m == maps[0])
+        );
+        assert!(unified_info.is_readable(0xa90206ca83eb3000));
+        assert!(!unified_info.is_writable(0xa90206ca83eb3000));
+        assert!(unified_info.is_executable(0xa90206ca83eb3000));
+
+        assert!(!unified_info.is_readable(0xc70206ca83eb3000));
+        assert!(unified_info.is_writable(0xc70206ca83eb3000));
+        assert!(!unified_info.is_executable(0xc70206ca83eb3000));
+
+        // Addresses outside of any region have no permissions.
+        assert!(unified_info.region_containing(0x1000).is_none());
+        assert!(!unified_info.is_readable(0x1000));
+        assert!(!unified_info.is_writable(0x1000));
+        assert!(!unified_info.is_executable(0x1000));
     }
 
     #[test]
@@ -5050,7 +6751,7 @@ c70206ca83eb2852-de0206ca83eb2852  -w-s  10bac9000 fd:05 1196511 /usr/lib64/libt
 
             assert_eq!(map.base_address, 0x10a00);
             assert_eq!(map.final_address, 0x10b00);
-            assert_eq!(map.memory_range(), Some(Range::new(0x10a00, 0x10b00)));
+            assert_eq!(map.memory_range(), Some(Range::new(0x10a00, 0x10aff)));
             assert_eq!(
                 map.kind,
                 File(Cow::Borrowed(LinuxOsStr::from_bytes(
@@ -5075,7 +6776,7 @@ c70206ca83eb2852-de0206ca83eb2852  -w-s  10bac9000 fd:05 1196511 /usr/lib64/libt
             assert_eq!(map.final_address, 0xffffffffff601000);
             assert_eq!(
                 map.memory_range(),
-                Some(Range::new(0xffffffffff600000, 0xffffffffff601000))
+                Some(Range::new(0xffffffffff600000, 0xffffffffff600fff))
             );
             assert_eq!(
                 map.kind,
@@ -5099,7 +6800,7 @@ c70206ca83eb2852-de0206ca83eb2852  -w-s  10bac9000 fd:05 1196511 /usr/lib64/libt
 
             assert_eq!(map.base_address, 0x10a00);
             assert_eq!(map.final_address, 0x10b00);
-            assert_eq!(map.memory_range(), Some(Range::new(0x10a00, 0x10b00)));
+            assert_eq!(map.memory_range(), Some(Range::new(0x10a00, 0x10aff)));
             assert_eq!(map.kind, MainThreadStack);
 
             assert!(!map.is_read);
@@ -5117,7 +6818,7 @@ c70206ca83eb2852-de0206ca83eb2852  -w-s  10bac9000 fd:05 1196511 /usr/lib64/libt
 
             assert_eq!(map.base_address, 0x10a00);
             assert_eq!(map.final_address, 0x10b00);
-            assert_eq!(map.memory_range(), Some(Range::new(0x10a00, 0x10b00)));
+            assert_eq!(map.memory_range(), Some(Range::new(0x10a00, 0x10aff)));
             assert_eq!(map.kind, Stack(1234567));
 
             assert!(!map.is_read);
@@ -5135,7 +6836,7 @@ c70206ca83eb2852-de0206ca83eb2852  -w-s  10bac9000 fd:05 1196511 /usr/lib64/libt
 
             assert_eq!(map.base_address, 0x10a00);
             assert_eq!(map.final_address, 0x10b00);
-            assert_eq!(map.memory_range(), Some(Range::new(0x10a00, 0x10b00)));
+            assert_eq!(map.memory_range(), Some(Range::new(0x10a00, 0x10aff)));
             assert_eq!(map.kind, Heap);
 
             assert!(!map.is_read);
@@ -5153,7 +6854,7 @@ c70206ca83eb2852-de0206ca83eb2852  -w-s  10bac9000 fd:05 1196511 /usr/lib64/libt
 
             assert_eq!(map.base_address, 0x10a00);
             assert_eq!(map.final_address, 0x10b00);
-            assert_eq!(map.memory_range(), Some(Range::new(0x10a00, 0x10b00)));
+            assert_eq!(map.memory_range(), Some(Range::new(0x10a00, 0x10aff)));
             assert_eq!(map.kind, Vdso);
 
             assert!(map.is_read);
@@ -5171,7 +6872,7 @@ c70206ca83eb2852-de0206ca83eb2852  -w-s  10bac9000 fd:05 1196511 /usr/lib64/libt
 
             assert_eq!(map.base_address, 0x10a00);
             assert_eq!(map.final_address, 0x10b00);
-            assert_eq!(map.memory_range(), Some(Range::new(0x10a00, 0x10b00)));
+            assert_eq!(map.memory_range(), Some(Range::new(0x10a00, 0x10aff)));
             assert_eq!(
                 map.kind,
                 UnknownSpecial(Cow::Borrowed(LinuxOsStr::from_bytes(b"[asdfasd]")))
@@ -5192,8 +6893,9 @@ c70206ca83eb2852-de0206ca83eb2852  -w-s  10bac9000 fd:05 1196511 /usr/lib64/libt
 
             assert_eq!(map.base_address, 0x10a00);
             assert_eq!(map.final_address, 0x10b00);
-            assert_eq!(map.memory_range(), Some(Range::new(0x10a00, 0x10b00)));
+            assert_eq!(map.memory_range(), Some(Range::new(0x10a00, 0x10aff)));
             assert_eq!(map.kind, AnonymousMap);
+            assert!(map.is_anonymous());
 
             assert!(map.is_read);
             assert!(!map.is_write);
@@ -5210,7 +6912,7 @@ c70206ca83eb2852-de0206ca83eb2852  -w-s  10bac9000 fd:05 1196511 /usr/lib64/libt
 
             assert_eq!(map.base_address, 0x10a00);
             assert_eq!(map.final_address, 0x10b00);
-            assert_eq!(map.memory_range(), Some(Range::new(0x10a00, 0x10b00)));
+            assert_eq!(map.memory_range(), Some(Range::new(0x10a00, 0x10aff)));
             assert_eq!(map.kind, AnonymousMap);
 
             assert!(!map.is_read);
@@ -5232,13 +6934,13 @@ c70206ca83eb2852-de0206ca83eb2852  -w-s  10bac9000 fd:05 1196511 /usr/lib64/libt
         }
 
         {
-            // Equal ranges are valid
+            // Equal ranges parse, but are empty
             let map = parse(b"fffff-fffff");
             let map = map.unwrap();
 
             assert_eq!(map.base_address, 0xfffff);
             assert_eq!(map.final_address, 0xfffff);
-            assert_eq!(map.memory_range(), Some(Range::new(0xfffff, 0xfffff)));
+            assert_eq!(map.memory_range(), None);
         }
 
         {
@@ -5409,6 +7111,229 @@ c70206ca83eb2852-de0206ca83eb2852  -w-s  10bac9000 fd:05 1196511 /usr/lib64/libt
         assert_eq!(&regions[0].bytes, &CONTENTS);
     }
 
+    #[test]
+    fn test_memory_reads() {
+        let bytes = [1, 2, 3, 4, 5, 6, 7, 8, 9];
+        let memory = MinidumpMemory {
+            desc: Default::default(),
+            base_address: 0x1000,
+            size: bytes.len() as u64,
+            bytes: &bytes,
+        };
+        assert_eq!(memory.get_u32_at_address(0x1000, LE), Some(0x04030201));
+        assert_eq!(memory.get_u32_at_address(0x1000, BE), Some(0x01020304));
+        assert_eq!(
+            memory.get_u64_at_address(0x1001, LE),
+            memory.get_memory_at_address::<u64>(0x1001)
+        );
+        assert_eq!(
+            memory.get_u64_at_address(0x1001, LE),
+            Some(0x0908070605040302)
+        );
+        // Reads that run off either end of the region fail.
+        assert_eq!(memory.get_u64_at_address(0x1002, LE), None);
+        assert_eq!(memory.get_u32_at_address(0xfff, LE), None);
+        assert_eq!(memory.get_memory_at_address::<u32>(0x1006), None);
+        assert_eq!(memory.get_memory_at_address::<u8>(0x1008), Some(9));
+        assert_eq!(memory.get_u32_at_address(u64::MAX, LE), None);
+    }
+
+    #[test]
+    fn test_memory64_list() {
+        const CONTENTS1: &[u8] = b"memory_contents";
+        const CONTENTS2: &[u8] = b"more memory";
+        let memory1 = Memory::with_section(
+            Section::with_endian(Endian::Little).append_bytes(CONTENTS1),
+            0x309d68010bd21b2c,
+        );
+        let memory2 = Memory::with_section(
+            Section::with_endian(Endian::Little).append_bytes(CONTENTS2),
+            0x1000,
+        );
+        let dump = SynthMinidump::with_endian(Endian::Little)
+            .add_memory64(memory1)
+            .add_memory64(memory2);
+        let dump = read_synth_dump(dump).unwrap();
+        let memory_list = dump.get_stream::<MinidumpMemory64List<'_>>().unwrap();
+        let regions = memory_list.iter().collect::<Vec<_>>();
+        assert_eq!(regions.len(), 2);
+        assert_eq!(regions[0].base_address, 0x309d68010bd21b2c);
+        assert_eq!(regions[0].size, CONTENTS1.len() as u64);
+        assert_eq!(&regions[0].bytes, &CONTENTS1);
+        assert_eq!(regions[1].base_address, 0x1000);
+        assert_eq!(regions[1].size, CONTENTS2.len() as u64);
+        assert_eq!(&regions[1].bytes, &CONTENTS2);
+
+        let memory = memory_list.memory_at_address(0x1004).unwrap();
+        assert_eq!(memory.base_address, 0x1000);
+        assert_eq!(
+            memory.get_memory_at_address::<u32>(0x1000),
+            Some(u32::from_le_bytes(*b"more"))
+        );
+    }
+
+    #[test]
+    fn test_unified_memory_find() {
+        let memory1 = Memory::with_section(
+            Section::with_endian(Endian::Little)
+                .D32(0xdeadbeef)
+                .D32(0x12345678)
+                .D32(0xdeadbeef),
+            0x2000,
+        );
+        let memory2 = Memory::with_section(
+            Section::with_endian(Endian::Little)
+                .D32(0xdeadf00d)
+                .D32(0xdeadbeef),
+            0x1000,
+        );
+        let dump = SynthMinidump::with_endian(Endian::Little)
+            .add_memory64(memory1)
+            .add_memory64(memory2);
+        let dump = read_synth_dump(dump).unwrap();
+        let memory_list = UnifiedMemoryList::new(
+            dump.get_stream::<MinidumpMemoryList>().ok(),
+            dump.get_stream::<MinidumpMemory64List>().ok(),
+        )
+        .unwrap();
+        assert!(memory_list.memory64().is_some());
+
+        let pattern = 0xdeadbeefu32.to_le_bytes();
+        let found = memory_list.find(&pattern, None).collect::<Vec<_>>();
+        assert_eq!(found, vec![0x1004, 0x2000, 0x2008]);
+
+        // Only compare the top two bytes.
+        let mask = [0x00, 0x00, 0xff, 0xff];
+        let found = memory_list.find(&pattern, Some(&mask)).collect::<Vec<_>>();
+        assert_eq!(found, vec![0x1000, 0x1004, 0x2000, 0x2008]);
+
+        assert_eq!(memory_list.find(&[], None).count(), 0);
+        assert_eq!(memory_list.find(b"not present", None).count(), 0);
+    }
+
+    #[test]
+    fn test_module_image_from_memory() {
+        const BASE: u64 = 0x7ff6_0000_0000;
+        const CODE: &[u8] = b"\x55\x48\x89\xe5\x5d\xc3";
+
+        // A minimal mapped PE32+ image with a single .text section.
+        let mut pe = vec![0u8; 0x2000];
+        pe.pwrite_with::<u16>(0x5a4d, 0, LE).unwrap();
+        pe.pwrite_with::<u32>(0x40, 0x3c, LE).unwrap();
+        pe.pwrite_with::<u32>(0x4550, 0x40, LE).unwrap();
+        pe.pwrite_with::<u16>(1, 0x44 + 2, LE).unwrap(); // NumberOfSections
+        pe.pwrite_with::<u16>(0xf0, 0x44 + 16, LE).unwrap(); // SizeOfOptionalHeader
+        let opt = 0x58;
+        pe.pwrite_with::<u16>(0x20b, opt, LE).unwrap();
+        pe.pwrite_with::<u64>(0x1_4000_0000, opt + 24, LE).unwrap(); // ImageBase
+        pe.pwrite_with::<u32>(0x1000, opt + 32, LE).unwrap(); // SectionAlignment
+        pe.pwrite_with::<u32>(0x200, opt + 36, LE).unwrap(); // FileAlignment
+        let section = opt + 0xf0;
+        pe[section..section + 8].copy_from_slice(b".text\0\0\0");
+        pe.pwrite_with::<u32>(CODE.len() as u32, section + 8, LE)
+            .unwrap();
+        pe.pwrite_with::<u32>(0x1000, section + 12, LE).unwrap();
+        pe.pwrite_with::<u32>(0x200, section + 16, LE).unwrap();
+        pe.pwrite_with::<u32>(0x400, section + 20, LE).unwrap();
+        pe[0x1000..0x1000 + CODE.len()].copy_from_slice(CODE);
+
+        let name = DumpString::new("injected.dll", Endian::Little);
+        let module = SynthModule::new(Endian::Little, BASE, 0x3000, &name, 0, 0, None);
+        // The headers and the code live in separate regions, and the last page
+        // of the module is missing entirely.
+        let headers = Memory::with_section(
+            Section::with_endian(Endian::Little).append_bytes(&pe[..0x1000]),
+            BASE,
+        );
+        let code = Memory::with_section(
+            Section::with_endian(Endian::Little).append_bytes(&pe[0x1000..]),
+            BASE + 0x1000,
+        );
+        let dump = SynthMinidump::with_endian(Endian::Little)
+            .add_module(module)
+            .add(name)
+            .add_memory(headers)
+            .add_memory(code);
+        let dump = read_synth_dump(dump).unwrap();
+        let modules = dump.get_stream::<MinidumpModuleList>().unwrap();
+        let memory = UnifiedMemoryList::Memory(dump.get_stream::<MinidumpMemoryList>().unwrap());
+        let module = modules.iter().next().unwrap();
+
+        let image = module.image_from_memory(&memory).unwrap();
+        assert_eq!(image.len(), 0x3000);
+        assert_eq!(&image[0x1000..0x1000 + CODE.len()], CODE);
+        assert!(image[0x2000..].iter().all(|&b| b == 0));
+        assert_eq!(image.pread_with::<u64>(opt + 24, LE).unwrap(), BASE);
+        assert_eq!(image.pread_with::<u32>(opt + 36, LE).unwrap(), 0x1000);
+        assert_eq!(
+            image.pread_with::<u32>(section + 16, LE).unwrap(),
+            CODE.len() as u32
+        );
+        assert_eq!(image.pread_with::<u32>(section + 20, LE).unwrap(), 0x1000);
+
+        // The mapped image is left as it was.
+        let mapped = module.mapped_image_from_memory(&memory).unwrap();
+        assert_eq!(&mapped[..0x2000], &pe[..]);
+        assert!(mapped[0x2000..].iter().all(|&b| b == 0));
+
+        // Without the headers there's nothing to reconstruct.
+        let empty = UnifiedMemoryList::default();
+        assert_eq!(
+            module.image_from_memory(&empty),
+            Err(Error::MemoryReadFailure)
+        );
+        assert_eq!(
+            module.mapped_image_from_memory(&empty),
+            Err(Error::MemoryReadFailure)
+        );
+    }
+
+    #[test]
+    fn test_mapped_image_too_large() {
+        let name = DumpString::new("huge.dll", Endian::Little);
+        let module = SynthModule::new(Endian::Little, 0x10000000, 0xfffff000, &name, 0, 0, None);
+        let memory = Memory::with_section(
+            Section::with_endian(Endian::Little).append_bytes(b"MZ"),
+            0x10000000,
+        );
+        let dump = SynthMinidump::with_endian(Endian::Little)
+            .add_module(module)
+            .add(name)
+            .add_memory(memory);
+        let dump = read_synth_dump(dump).unwrap();
+        let modules = dump.get_stream::<MinidumpModuleList>().unwrap();
+        let memory = UnifiedMemoryList::Memory(dump.get_stream::<MinidumpMemoryList>().unwrap());
+        let module = modules.iter().next().unwrap();
+        assert_eq!(
+            module.mapped_image_from_memory(&memory),
+            Err(Error::DataError)
+        );
+    }
+
+    #[test]
+    fn test_mapped_image_short_region() {
+        let name = DumpString::new("short.dll", Endian::Little);
+        let module = SynthModule::new(Endian::Little, 0x10000000, 0x1000, &name, 0, 0, None);
+        let dump = SynthMinidump::with_endian(Endian::Little)
+            .add_module(module)
+            .add(name);
+        let dump = read_synth_dump(dump).unwrap();
+        let modules = dump.get_stream::<MinidumpModuleList>().unwrap();
+        let module = modules.iter().next().unwrap();
+        // A region that claims to cover the whole module, but has 4 bytes.
+        let region = MinidumpMemory {
+            desc: Default::default(),
+            base_address: 0x10000000,
+            size: 0x1000,
+            bytes: b"MZ\x90\x00",
+        };
+        let memory = UnifiedMemoryList::Memory(MinidumpMemoryList::from_regions(vec![region]));
+        let image = module.mapped_image_from_memory(&memory).unwrap();
+        assert_eq!(image.len(), 0x1000);
+        assert_eq!(&image[..4], b"MZ\x90\x00");
+        assert!(image[4..].iter().all(|&byte| byte == 0));
+    }
+
     #[test]
     fn test_memory_list_lifetimes() {
         // A memory list should not own any of the minidump data.
@@ -5487,16 +7412,32 @@ c70206ca83eb2852-de0206ca83eb2852  -w-s  10bac9000 fd:05 1196511 /usr/lib64/libt
         assert_eq!(regions[4].base_address, 0x2000);
         assert_eq!(regions[4].size, 0x1000);
 
-        // memory_at_address should discard overlapping regions.
-        assert_eq!(memory_list.by_addr().count(), 2);
+        // Earlier regions take precedence over later overlapping regions.
+        assert_eq!(memory_list.by_addr().count(), 3);
         let m1 = memory_list.memory_at_address(0x1a00).unwrap();
         assert_eq!(m1.base_address, 0x1000);
         assert_eq!(m1.size, 0x1000);
         assert_eq!(m1.bytes, &[0u8; 0x1000][..]);
-        let m2 = memory_list.memory_at_address(0x2a00).unwrap();
-        assert_eq!(m2.base_address, 0x2000);
-        assert_eq!(m2.size, 0x1000);
-        assert_eq!(m2.bytes, &[4u8; 0x1000][..]);
+        assert_eq!(memory_list.region_index_at_address(0x1000), Some(0));
+        assert_eq!(memory_list.region_index_at_address(0x1a00), Some(0));
+        // The only address of mem3 that isn't covered by mem1 or mem2.
+        let m3 = memory_list.memory_at_address(0x2000).unwrap();
+        assert_eq!(m3.base_address, 0x1001);
+        assert_eq!(m3.bytes, &[2u8; 0x1000][..]);
+        assert_eq!(memory_list.region_index_at_address(0x2000), Some(2));
+        let m5 = memory_list.memory_at_address(0x2a00).unwrap();
+        assert_eq!(m5.base_address, 0x2000);
+        assert_eq!(m5.size, 0x1000);
+        assert_eq!(m5.bytes, &[4u8; 0x1000][..]);
+        assert_eq!(memory_list.region_index_at_address(0x2001), Some(4));
+        assert_eq!(memory_list.region_index_at_address(0x3000), None);
+        assert_eq!(memory_list.region_index_at_address(0xfff), None);
+
+        let bases = memory_list
+            .by_addr()
+            .map(|region| region.base_address)
+            .collect::<Vec<_>>();
+        assert_eq!(bases, vec![0x1000, 0x1001, 0x2000]);
     }
 
     #[test]
@@ -5909,11 +7850,196 @@ c70206ca83eb2852-de0206ca83eb2852  -w-s  10bac9000 fd:05 1196511 /usr/lib64/libt
             }
             _ => panic!("Got unexpected raw context type!"),
         }
+        assert_eq!(context.valid, MinidumpContextValidity::All);
         let stack = thread.stack.take().expect("Should have stack memory");
         assert_eq!(stack.base_address, 0x1000000010000000);
         assert_eq!(stack.size, 0x1000);
     }
 
+    #[test]
+    fn test_thread_list_amd64_control_only() {
+        // A context with only CONTEXT_CONTROL set has nothing but the
+        // instruction and stack pointers.
+        let mut bytes =
+            synth_minidump::amd64_context(Endian::Little, 0x1234abcd1234abcd, 0x1000000010000000)
+                .get_contents()
+                .unwrap();
+        bytes[48..52].copy_from_slice(&0x100001u32.to_le_bytes());
+        let context = Section::with_endian(Endian::Little).append_bytes(&bytes);
+        let stack = Memory::with_section(
+            Section::with_endian(Endian::Little).append_repeated(0, 0x1000),
+            0x1000000010000000,
+        );
+        let arch = md::ProcessorArchitecture::PROCESSOR_ARCHITECTURE_AMD64 as u16;
+        let system_info = SystemInfo::new(Endian::Little).set_processor_architecture(arch);
+        let thread = Thread::new(Endian::Little, 0x1234, &stack, &context);
+        let dump = SynthMinidump::with_endian(Endian::Little)
+            .add_thread(thread)
+            .add(context)
+            .add_memory(stack)
+            .add_system_info(system_info);
+        let dump = read_synth_dump(dump).unwrap();
+        let thread_list = dump.get_stream::<MinidumpThreadList<'_>>().unwrap();
+        let system_info = dump.get_stream::<MinidumpSystemInfo>().unwrap();
+        let context = thread_list.threads[0]
+            .context(&system_info, None)
+            .expect("Should have a thread context");
+        assert_eq!(
+            context.valid,
+            MinidumpContextValidity::Some(["rip", "rsp"].iter().copied().collect())
+        );
+    }
+
+    #[test]
+    fn test_thread_list_amd64_with_x86_context() {
+        // A 32-bit thread of a WOW64 process can have an x86 context in an
+        // otherwise 64-bit dump.
+        let context = synth_minidump::x86_context(Endian::Little, 0xabcd1234, 0x1010);
+        let stack = Memory::with_section(
+            Section::with_endian(Endian::Little).append_repeated(0, 0x1000),
+            0x1000,
+        );
+        let arch = md::ProcessorArchitecture::PROCESSOR_ARCHITECTURE_AMD64 as u16;
+        let system_info = SystemInfo::new(Endian::Little).set_processor_architecture(arch);
+        let thread = Thread::new(Endian::Little, 0x1234, &stack, &context);
+        let dump = SynthMinidump::with_endian(Endian::Little)
+            .add_thread(thread)
+            .add(context)
+            .add_memory(stack)
+            .add_system_info(system_info);
+        let dump = read_synth_dump(dump).unwrap();
+        let thread_list = dump.get_stream::<MinidumpThreadList<'_>>().unwrap();
+        let system_info = dump.get_stream::<MinidumpSystemInfo>().unwrap();
+        let context = thread_list.threads[0]
+            .context(&system_info, None)
+            .expect("Should have a thread context");
+        match &context.raw {
+            MinidumpRawContext::X86(raw) => {
+                assert_eq!(raw.eip, 0xabcd1234);
+                assert_eq!(raw.esp, 0x1010);
+            }
+            _ => panic!("Got unexpected raw context type!"),
+        }
+    }
+
+    #[test]
+    fn test_thread_wow64_context() {
+        let teb = 0x7ffd_e000;
+        let cpu_area = 0x0030_0000;
+        let context = synth_minidump::x86_context(Endian::Little, 0x0040_1000, 0x0012_fe80)
+            .get_contents()
+            .unwrap();
+        let teb_bytes = Section::with_endian(Endian::Little)
+            .append_repeated(0, WOW64_TLS_CPURESERVED_OFFSET as usize)
+            .D64(cpu_area)
+            .get_contents()
+            .unwrap();
+        let cpu_area_bytes = Section::with_endian(Endian::Little)
+            .D16(0) // flags
+            .D16(0x14c) // machine
+            .append_bytes(&context)
+            .get_contents()
+            .unwrap();
+        let memory = UnifiedMemoryList::Memory(MinidumpMemoryList::from_regions(vec![
+            MinidumpMemory {
+                desc: Default::default(),
+                base_address: teb,
+                size: teb_bytes.len() as u64,
+                bytes: &teb_bytes,
+            },
+            MinidumpMemory {
+                desc: Default::default(),
+                base_address: cpu_area,
+                size: cpu_area_bytes.len() as u64,
+                bytes: &cpu_area_bytes,
+            },
+        ]));
+        let thread = MinidumpThread {
+            raw: md::MINIDUMP_THREAD {
+                thread_id: 0x1234,
+                suspend_count: 0,
+                priority_class: 0,
+                priority: 0,
+                teb,
+                stack: Default::default(),
+                thread_context: Default::default(),
+            },
+            context: None,
+            stack: None,
+            endian: LE,
+        };
+
+        let context = thread
+            .wow64_context(Cpu::X86_64, &memory)
+            .expect("Should have a wow64 context");
+        match &context.raw {
+            MinidumpRawContext::X86(raw) => {
+                assert_eq!(raw.eip, 0x0040_1000);
+                assert_eq!(raw.esp, 0x0012_fe80);
+            }
+            _ => panic!("Got unexpected raw context type!"),
+        }
+
+        // Only 64-bit processes have a 64-bit TEB to look in.
+        assert!(thread.wow64_context(Cpu::X86, &memory).is_none());
+        // No TEB, no context.
+        assert!(thread
+            .wow64_context(Cpu::X86_64, &UnifiedMemoryList::default())
+            .is_none());
+    }
+
+    #[test]
+    fn test_thread_stack_bounds() {
+        let teb = 0x7ffd_e000;
+        let teb_bytes = Section::with_endian(Endian::Little)
+            .D64(0) // ExceptionList
+            .D64(0x0013_0000) // StackBase
+            .D64(0x0012_c000) // StackLimit
+            .append_repeated(0, WOW64_TEB32_OFFSET as usize - 24)
+            .D32(0) // ExceptionList
+            .D32(0x0034_0000) // StackBase
+            .D32(0x0033_0000) // StackLimit
+            .get_contents()
+            .unwrap();
+        let memory =
+            UnifiedMemoryList::Memory(MinidumpMemoryList::from_regions(vec![MinidumpMemory {
+                desc: Default::default(),
+                base_address: teb,
+                size: teb_bytes.len() as u64,
+                bytes: &teb_bytes,
+            }]));
+        let thread = MinidumpThread {
+            raw: md::MINIDUMP_THREAD {
+                thread_id: 0x1234,
+                suspend_count: 0,
+                priority_class: 0,
+                priority: 0,
+                teb,
+                stack: Default::default(),
+                thread_context: Default::default(),
+            },
+            context: None,
+            stack: None,
+            endian: LE,
+        };
+
+        assert_eq!(
+            thread.stack_bounds(Cpu::X86_64, &memory),
+            Some((0x0012_c000, 0x0013_0000))
+        );
+        assert_eq!(
+            thread.wow64_stack_bounds(Cpu::X86_64, &memory),
+            Some((0x0033_0000, 0x0034_0000))
+        );
+        // Read with the wrong pointer size, the bounds don't make sense.
+        assert_eq!(thread.stack_bounds(Cpu::X86, &memory), None);
+        assert_eq!(thread.wow64_stack_bounds(Cpu::X86, &memory), None);
+        assert_eq!(
+            thread.stack_bounds(Cpu::X86_64, &UnifiedMemoryList::default()),
+            None
+        );
+    }
+
     #[test]
     fn test_crashpad_info_missing() {
         let dump = SynthMinidump::with_endian(Endian::Little);
@@ -6038,6 +8164,135 @@ c70206ca83eb2852-de0206ca83eb2852  -w-s  10bac9000 fd:05 1196511 /usr/lib64/libt
         );
     }
 
+    #[test]
+    fn test_exception_without_system_info_or_threads() {
+        let context =
+            synth_minidump::amd64_context(Endian::Little, 0x1234abcd1234abcd, 0x1000000010000000);
+        let stack = Memory::with_section(
+            Section::with_endian(Endian::Little).append_repeated(0, 0x1000),
+            0x1000000010000000,
+        );
+        let mut exception = Exception::new(Endian::Little).set_context(&context);
+        exception.thread_id = 0x1234;
+        let dump = SynthMinidump::with_endian(Endian::Little)
+            .add_exception(exception)
+            .add(context)
+            .add_memory(stack);
+        let dump = read_synth_dump(dump).unwrap();
+        let exception = dump.get_stream::<MinidumpException>().unwrap();
+        let memory_list = dump.get_stream::<MinidumpMemoryList>().unwrap();
+
+        // The context's flags say it's an amd64 context.
+        let system_info = exception.infer_system_info().unwrap();
+        assert_eq!(system_info.cpu, Cpu::X86_64);
+        assert_eq!(system_info.os, Os::Unknown(0));
+        let context = exception.context(&system_info, None).unwrap();
+        assert_eq!(context.get_stack_pointer(), 0x1000000010000000);
+
+        let stack = memory_list
+            .memory_at_address(context.get_stack_pointer())
+            .cloned();
+        let thread = exception.synthesize_thread(stack);
+        assert_eq!(thread.raw.thread_id, 0x1234);
+        assert_eq!(thread.raw.stack.start_of_memory_range, 0x1000000010000000);
+        assert_eq!(
+            thread.stack_memory(&memory_list).unwrap().base_address,
+            0x1000000010000000
+        );
+        let thread_context = thread.context(&system_info, None).unwrap();
+        assert_eq!(thread_context.get_instruction_pointer(), 0x1234abcd1234abcd);
+
+        // Without a context there's nothing to infer anything from.
+        let dump = SynthMinidump::with_endian(Endian::Little)
+            .add_exception(Exception::new(Endian::Little));
+        let dump = read_synth_dump(dump).unwrap();
+        let exception = dump.get_stream::<MinidumpException>().unwrap();
+        assert!(exception.infer_system_info().is_none());
+        assert_eq!(MinidumpSystemInfo::unknown().cpu, Cpu::Unknown(0xffff));
+    }
+
+    fn crash_reason(os: Os, cpu: Cpu, code: u32, flags: u32, info: &[u64]) -> String {
+        let mut exception_information = [0; 15];
+        exception_information[..info.len()].copy_from_slice(info);
+        let raw = md::MINIDUMP_EXCEPTION_STREAM {
+            thread_id: 0,
+            __align: 0,
+            exception_record: md::MINIDUMP_EXCEPTION {
+                exception_code: code,
+                exception_flags: flags,
+                exception_record: 0,
+                exception_address: 0,
+                number_parameters: info.len() as u32,
+                __align: 0,
+                exception_information,
+            },
+            thread_context: md::MINIDUMP_LOCATION_DESCRIPTOR::default(),
+        };
+        CrashReason::from_exception(&raw, os, cpu).to_string()
+    }
+
+    #[test]
+    fn test_crash_reason_names() {
+        // Linux signals are refined by their si_code
+        let linux = |code, flags| crash_reason(Os::Linux, Cpu::X86_64, code, flags, &[]);
+        assert_eq!(linux(11, 1), "SIGSEGV / SEGV_MAPERR");
+        assert_eq!(linux(11, 2), "SIGSEGV / SEGV_ACCERR");
+        assert_eq!(linux(11, 9), "SIGSEGV / SEGV_MTESERR");
+        assert_eq!(linux(11, 0x80), "SIGSEGV / SI_KERNEL");
+        assert_eq!(linux(11, 0), "SIGSEGV");
+        assert_eq!(linux(8, 14), "SIGFPE / FPE_FLTUNK");
+        assert_eq!(linux(6, 0xfffffffa), "SIGABRT / SI_TKILL");
+        assert_eq!(linux(6, 0x1234), "SIGABRT / 0x00001234");
+
+        // macOS exceptions are refined by their codes, which can be CPU-specific
+        let mac = |cpu, code, flags| crash_reason(Os::MacOs, cpu, code, flags, &[]);
+        assert_eq!(
+            mac(Cpu::X86_64, 1, 1),
+            "EXC_BAD_ACCESS / KERN_INVALID_ADDRESS"
+        );
+        assert_eq!(
+            mac(Cpu::Arm64, 1, 0x105),
+            "EXC_BAD_ACCESS / EXC_ARM_PAC_FAIL"
+        );
+        assert_eq!(mac(Cpu::Arm64, 1, 0x999), "EXC_BAD_ACCESS / 0x00000999");
+        assert_eq!(mac(Cpu::Arm64, 3, 2), "EXC_ARITHMETIC / EXC_ARM_FP_DZ");
+        assert_eq!(
+            mac(Cpu::X86_64, 5, 0x10003),
+            "EXC_SOFTWARE / EXC_SOFT_SIGNAL"
+        );
+        assert_eq!(
+            mac(Cpu::Arm64, 10, 0x0610_0000),
+            "EXC_CRASH / EXC_BAD_ACCESS / signal 6"
+        );
+
+        // Windows codes fall back to NTSTATUS values, and otherwise keep the raw code
+        let windows = |code, info: &[u64]| crash_reason(Os::Windows, Cpu::X86_64, code, 0, info);
+        assert_eq!(
+            windows(0xc0000005, &[1, 0]),
+            "EXCEPTION_ACCESS_VIOLATION_WRITE"
+        );
+        assert_eq!(windows(0xc0000374, &[]), "STATUS_HEAP_CORRUPTION");
+        assert_eq!(windows(0xe0434352, &[]), "CLR_EXCEPTION");
+        assert_eq!(
+            windows(0xc0000409, &[0x7]),
+            "EXCEPTION_STACK_BUFFER_OVERRUN / FAST_FAIL_FATAL_APP_EXIT"
+        );
+        assert_eq!(
+            windows(0xc0000409, &[0x1234]),
+            "EXCEPTION_STACK_BUFFER_OVERRUN / 0x00001234"
+        );
+        assert_eq!(windows(0xdeadbeef, &[]), "unknown 0xdeadbeef");
+        assert_eq!(
+            windows(0x80070005, &[]),
+            "unknown 0x80070005 / ERROR_ACCESS_DENIED"
+        );
+
+        assert_eq!(
+            crash_reason(Os::Solaris, Cpu::X86, 0xabcd, 0x12, &[]),
+            "unknown 0x0000abcd / 0x00000012"
+        );
+    }
+
     #[test]
     fn test_fuzzed_oom() {
         // https://github.com/luser/rust-minidump/issues/381
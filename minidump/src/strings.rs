@@ -1,4 +1,5 @@
 use std::borrow::{Borrow, Cow, ToOwned};
+use std::fmt;
 use std::ops::Deref;
 use std::str::Utf8Error;
 use std::vec::Vec;
@@ -11,6 +12,22 @@ pub struct LinuxOsStr([u8]);
 #[derive(Default, Debug, PartialOrd, Ord, Eq, PartialEq, Clone)]
 pub struct LinuxOsString(Vec<u8>);
 
+/// A UTF-16 string from a minidump (a `MINIDUMP_STRING`), as raw code units.
+///
+/// Windows strings aren't guaranteed to be valid UTF-16 (paths can contain
+/// unpaired surrogates), so converting them to a `String` can lose information.
+/// This type keeps the exact code units around so they can be round-tripped
+/// and compared byte-for-byte, and only converts when asked to.
+#[derive(Default, Debug, PartialOrd, Ord, Eq, PartialEq, Hash, Clone)]
+pub struct MinidumpString(Vec<u16>);
+
+/// A helper for printing a UTF-16 string that may not be valid.
+///
+/// Invalid code units are replaced with U+FFFD REPLACEMENT CHARACTER.
+/// This is returned by [`MinidumpString::display`].
+#[derive(Debug, Clone, Copy)]
+pub struct LossyDisplay<'a>(&'a [u16]);
+
 impl LinuxOsStr {
     pub fn new() -> &'static Self {
         Self::from_bytes(b"")
@@ -148,6 +165,63 @@ impl LinuxOsString {
     }
 }
 
+impl MinidumpString {
+    /// Create a new MinidumpString from UTF-16 code units.
+    pub fn from_utf16(units: Vec<u16>) -> Self {
+        Self(units)
+    }
+
+    pub fn new() -> Self {
+        Self(Vec::new())
+    }
+
+    /// The raw UTF-16 code units of the string, exactly as stored in the minidump.
+    pub fn as_utf16(&self) -> &[u16] {
+        &self.0
+    }
+
+    /// Tries to interpret the string as valid UTF-16.
+    ///
+    /// Returns `None` if the string contains unpaired surrogates.
+    pub fn to_string_checked(&self) -> Option<String> {
+        String::from_utf16(&self.0).ok()
+    }
+
+    /// Converts to a utf8 string lossily, replacing invalid code units with
+    /// U+FFFD REPLACEMENT CHARACTER.
+    pub fn to_string_lossy(&self) -> String {
+        String::from_utf16_lossy(&self.0)
+    }
+
+    /// Returns an object that implements `Display` by lossily decoding the string.
+    pub fn display(&self) -> LossyDisplay<'_> {
+        LossyDisplay(&self.0)
+    }
+}
+
+impl<'a> From<&'a str> for MinidumpString {
+    fn from(s: &'a str) -> Self {
+        Self(s.encode_utf16().collect())
+    }
+}
+
+impl<'a> LossyDisplay<'a> {
+    /// Wrap arbitrary UTF-16 code units for display.
+    pub fn new(units: &'a [u16]) -> Self {
+        Self(units)
+    }
+}
+
+impl<'a> fmt::Display for LossyDisplay<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use std::fmt::Write;
+        for c in std::char::decode_utf16(self.0.iter().cloned()) {
+            f.write_char(c.unwrap_or(std::char::REPLACEMENT_CHARACTER))?;
+        }
+        Ok(())
+    }
+}
+
 impl Borrow<LinuxOsStr> for LinuxOsString {
     fn borrow(&self) -> &LinuxOsStr {
         LinuxOsStr::from_bytes(&self.0)
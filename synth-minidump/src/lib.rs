@@ -39,8 +39,14 @@ pub struct SynthMinidump {
     thread_list: Option<ListStream<Thread>>,
     /// List of thread names in this minidump.
     thread_names_list: Option<ListStream<ThreadName>>,
+    /// List of extra info about threads in this minidump.
+    thread_info_list: Option<ExListStream<ThreadInfo>>,
+    /// List of handles in this minidump.
+    handle_data: Option<HandleDataStream>,
     /// List of memory regions in this minidump.
     memory_list: Option<ListStream<Section>>,
+    /// List of full-memory regions in this minidump.
+    memory64_list: Vec<Memory>,
     /// List of extra info about memory regions in this minidump.
     memory_info_list: Option<ExListStream<MemoryInfo>>,
     /// Crashpad extension containing annotations.
@@ -190,10 +196,17 @@ impl SynthMinidump {
                 md::MINIDUMP_STREAM_TYPE::ThreadNamesStream,
                 endian,
             )),
+            thread_info_list: Some(ExListStream::new(
+                md::MINIDUMP_STREAM_TYPE::ThreadInfoListStream,
+                mem::size_of::<md::MINIDUMP_THREAD_INFO>(),
+                endian,
+            )),
+            handle_data: Some(HandleDataStream::new(endian)),
             memory_list: Some(ListStream::new(
                 md::MINIDUMP_STREAM_TYPE::MemoryListStream,
                 endian,
             )),
+            memory64_list: vec![],
             memory_info_list: Some(ExListStream::new(
                 md::MINIDUMP_STREAM_TYPE::MemoryInfoListStream,
                 mem::size_of::<md::MINIDUMP_MEMORY_INFO>(),
@@ -257,6 +270,14 @@ impl SynthMinidump {
         self.add(memory)
     }
 
+    /// Add `memory` to the memory64 list stream (as found in full-memory dumps).
+    ///
+    /// The contents of these regions are written contiguously at the end of the dump.
+    pub fn add_memory64(mut self, memory: Memory) -> SynthMinidump {
+        self.memory64_list.push(memory);
+        self
+    }
+
     /// Add `info` to `self`, adding it to the memory info list stream as well.
     pub fn add_memory_info(mut self, info: MemoryInfo) -> SynthMinidump {
         self.memory_info_list = self
@@ -284,6 +305,21 @@ impl SynthMinidump {
         self
     }
 
+    /// Add `info` to `self`, adding it to the thread info list stream as well.
+    pub fn add_thread_info(mut self, info: ThreadInfo) -> SynthMinidump {
+        self.thread_info_list = self
+            .thread_info_list
+            .take()
+            .map(|info_list| info_list.add(info));
+        self
+    }
+
+    /// Add `handle` to `self`, adding it to the handle data stream as well.
+    pub fn add_handle(mut self, handle: HandleDescriptor) -> SynthMinidump {
+        self.handle_data = self.handle_data.take().map(|stream| stream.add(handle));
+        self
+    }
+
     /// Add crashpad module and annotation extension information.
     pub fn add_crashpad_info(mut self, crashpad_info: CrashpadInfo) -> Self {
         self.crashpad_info = Some(crashpad_info);
@@ -400,6 +436,15 @@ impl SynthMinidump {
         // Add thread names stream if any names were added.
         let thread_names = self.thread_names_list.take();
         self = self.finish_list(thread_names);
+        // Add thread info list stream if any thread infos were added.
+        let thread_infos = self.thread_info_list.take();
+        self = self.finish_ex_list(thread_infos);
+        // Add handle data stream if any handles were added.
+        if let Some(handle_data) = self.handle_data.take() {
+            if !handle_data.is_empty() {
+                self = self.add_stream(handle_data);
+            }
+        }
         // Add crashpad info stream if any.
         if let Some(crashpad_info) = self.crashpad_info.take() {
             self = self.add_stream(crashpad_info);
@@ -425,6 +470,26 @@ impl SynthMinidump {
         if let Some(stream) = self.linux_environ.take() {
             self = self.add_stream(stream);
         }
+        // Add memory64 list stream if any regions were added. The contents of
+        // all the regions must immediately follow each other, starting at base_rva.
+        let memories64 = mem::take(&mut self.memory64_list);
+        if !memories64.is_empty() {
+            let base_rva = Label::new();
+            let section = memories64.iter().fold(
+                Section::with_endian(self.section.endian)
+                    .D64(memories64.len() as u64)
+                    .D64(base_rva.clone()),
+                |section, memory| section.D64(memory.address).D64(memory.file_size()),
+            );
+            self = self.add_stream(SimpleStream {
+                stream_type: md::MINIDUMP_STREAM_TYPE::Memory64ListStream as u32,
+                section,
+            });
+            self.section = self.section.mark(&base_rva);
+            for memory in memories64 {
+                self = self.add(memory);
+            }
+        }
 
         let SynthMinidump {
             section,
@@ -769,6 +834,15 @@ impl DumpString {
             .append_bytes(&u16_s);
         DumpString { section }
     }
+
+    /// Create a new `DumpString` from raw UTF-16 code units, which need not be valid UTF-16.
+    pub fn from_utf16(units: &[u16], endian: Endian) -> DumpString {
+        let section = units.iter().fold(
+            Section::with_endian(endian).D32(units.len() as u32 * 2),
+            |section, &unit| section.append_bytes(&unit.to_le_bytes()),
+        );
+        DumpString { section }
+    }
 }
 
 impl From<DumpString> for Section {
@@ -934,6 +1008,14 @@ pub struct Thread {
 
 impl Thread {
     pub fn new<T>(endian: Endian, id: u32, stack: &Memory, context: &T) -> Thread
+    where
+        T: DumpSection,
+    {
+        Thread::with_teb(endian, id, 0, stack, context)
+    }
+
+    /// Like [`Thread::new`], but with `teb` as the address of the thread's TEB.
+    pub fn with_teb<T>(endian: Endian, id: u32, teb: u64, stack: &Memory, context: &T) -> Thread
     where
         T: DumpSection,
     {
@@ -942,7 +1024,7 @@ impl Thread {
             .D32(0) // suspend_count
             .D32(0) // priority_class
             .D32(0) // priority
-            .D64(0) // teb
+            .D64(teb)
             .cite_memory(stack)
             .cite_location(context);
         Thread { section }
@@ -983,6 +1065,134 @@ impl From<ThreadName> for Section {
     }
 }
 
+/// Extra information about a minidump thread.
+pub struct ThreadInfo {
+    section: Section,
+}
+
+impl ThreadInfo {
+    /// A `MINIDUMP_THREAD_INFO` for the thread with `id`, which ran for
+    /// `kernel_time` and `user_time` (in 100-nanosecond units) after starting
+    /// at `start_address`.
+    pub fn new(
+        endian: Endian,
+        id: u32,
+        kernel_time: u64,
+        user_time: u64,
+        start_address: u64,
+    ) -> ThreadInfo {
+        let section = Section::with_endian(endian)
+            .D32(id)
+            .D32(0) // dump_flags
+            .D32(0) // dump_error
+            .D32(0) // exit_status
+            .D64(0) // create_time
+            .D64(0) // exit_time
+            .D64(kernel_time)
+            .D64(user_time)
+            .D64(start_address)
+            .D64(0); // affinity
+        ThreadInfo { section }
+    }
+}
+
+impl_dumpsection!(ThreadInfo);
+
+impl From<ThreadInfo> for Section {
+    fn from(info: ThreadInfo) -> Self {
+        info.section
+    }
+}
+
+/// A handle the process had open.
+pub struct HandleDescriptor {
+    section: Section,
+}
+
+impl HandleDescriptor {
+    /// A `MINIDUMP_HANDLE_DESCRIPTOR` for `handle`, to an object of type
+    /// `type_name` named `object_name`.
+    pub fn new(
+        endian: Endian,
+        handle: u64,
+        type_name: Option<&DumpString>,
+        object_name: Option<&DumpString>,
+    ) -> HandleDescriptor {
+        let cite_name = |section: Section, name: Option<&DumpString>| match name {
+            Some(name) => section.D32(name.file_offset()),
+            None => section.D32(0),
+        };
+        let section = Section::with_endian(endian).D64(handle);
+        let section = cite_name(section, type_name);
+        let section = cite_name(section, object_name)
+            .D32(0) // attributes
+            .D32(0) // granted_access
+            .D32(1) // handle_count
+            .D32(1); // pointer_count
+        HandleDescriptor { section }
+    }
+}
+
+impl_dumpsection!(HandleDescriptor);
+
+impl From<HandleDescriptor> for Section {
+    fn from(handle: HandleDescriptor) -> Self {
+        handle.section
+    }
+}
+
+/// A `HandleDataStream`, with its own header rather than a list's.
+pub struct HandleDataStream {
+    /// The stream's contents.
+    section: Section,
+    /// The number of handles.
+    count: u32,
+    /// The number of handles, as a `Label`.
+    count_label: Label,
+}
+
+impl HandleDataStream {
+    pub fn new(endian: Endian) -> HandleDataStream {
+        let count_label = Label::new();
+        let section = Section::with_endian(endian)
+            .D32(mem::size_of::<md::MINIDUMP_HANDLE_DATA_STREAM>() as u32)
+            .D32(mem::size_of::<md::MINIDUMP_HANDLE_DESCRIPTOR>() as u32)
+            .D32(&count_label)
+            .D32(0); // reserved
+        HandleDataStream {
+            section,
+            count: 0,
+            count_label,
+        }
+    }
+
+    #[allow(clippy::should_implement_trait)]
+    pub fn add(mut self, handle: HandleDescriptor) -> HandleDataStream {
+        self.count += 1;
+        self.section = self.section.append_section(handle);
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+}
+
+impl_dumpsection!(HandleDataStream);
+
+impl From<HandleDataStream> for Section {
+    fn from(stream: HandleDataStream) -> Self {
+        stream.count_label.set_const(stream.count as u64);
+        stream.section
+    }
+}
+
+impl Stream for HandleDataStream {
+    fn stream_type(&self) -> u32 {
+        md::MINIDUMP_STREAM_TYPE::HandleDataStream.into()
+    }
+}
+
 /// A range of memory contents.
 pub struct Memory {
     section: Section,
@@ -1710,7 +1920,6 @@ impl From<SystemInfo> for Section {
         let section = info
             .section
             .D16(info.processor_architecture)
-            .D16(info.processor_architecture)
             .D16(info.processor_level)
             .D16(info.processor_revision)
             .D8(info.number_of_processors)
@@ -1753,6 +1962,8 @@ pub struct Exception {
     pub exception_record: ExceptionRecord,
     // TODO: implement this LOCATION_DESCRIPTOR properly
     pub thread_context: (u32, u32),
+    /// Where the context set by `set_context` is, which overrides `thread_context`.
+    context: Option<(Label, Label)>,
 }
 
 pub struct ExceptionRecord {
@@ -1779,8 +1990,15 @@ impl Exception {
                 exception_information: [0; 15],
             },
             thread_context: (0, 0),
+            context: None,
         }
     }
+
+    /// Make `context` the exception's context. It still has to be added to the dump.
+    pub fn set_context<T: DumpSection>(mut self, context: &T) -> Self {
+        self.context = Some((context.file_size(), context.file_offset()));
+        self
+    }
 }
 
 impl_dumpsection!(Exception);
@@ -1802,9 +2020,12 @@ impl From<Exception> for Section {
             section = section.D64(chunk);
         }
 
-        section = section
-            .D32(info.thread_context.0)
-            .D32(info.thread_context.1);
+        section = match info.context {
+            Some(context) => section.cite_location(&context),
+            None => section
+                .D32(info.thread_context.0)
+                .D32(info.thread_context.1),
+        };
 
         section
     }